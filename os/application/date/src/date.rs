@@ -8,7 +8,14 @@ use terminal::{print, println};
 use time::date;
 
 #[unsafe(no_mangle)]
-pub fn main() {
-    let date = date();
-    println!("{}", date.format("%Y-%m-%d %H:%M:%S"));
+pub fn main(_args: Args) -> i32 {
+    match date() {
+        Ok(date) => println!("{}", date.format("%Y-%m-%d %H:%M:%S")),
+        Err(errno) => {
+            println!("date: {}", errno);
+            return 1;
+        }
+    }
+
+    0
 }
\ No newline at end of file
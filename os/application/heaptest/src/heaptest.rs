@@ -0,0 +1,39 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+#[allow(unused_imports)]
+use runtime::*;
+use terminal::{print, println};
+
+/// Number of elements pushed, enough to let the heap grow several times beyond `heap::GROW_SIZE`
+const ELEMENTS: usize = 4 * heap::GROW_SIZE / size_of::<usize>();
+
+#[unsafe(no_mangle)]
+pub fn main(_args: Args) -> i32 {
+    // Grow a vector element by element (forces many reallocations)
+    let mut vec = Vec::new();
+    for i in 0..ELEMENTS {
+        vec.push(i);
+    }
+
+    let (size, _) = heap::stats();
+    assert!(size > heap::GROW_SIZE, "Heap did not grow (size: {} bytes)", size);
+    assert!(vec.iter().enumerate().all(|(i, &value)| i == value), "Vector content corrupted");
+    println!("Pushed {} elements, heap size: {} KiB", vec.len(), size / 1024);
+
+    // Free memory and allocate many small vectors, which must reuse the freed memory
+    drop(vec);
+    let mut vecs = Vec::new();
+    for i in 0..1024 {
+        vecs.push(Vec::<u8>::with_capacity(i + 1));
+    }
+
+    let (size_after, free) = heap::stats();
+    assert!(size_after == size, "Heap grew although enough memory was free ({} -> {} bytes)", size, size_after);
+    println!("Allocated {} small vectors, {} KiB free", vecs.len(), free / 1024);
+    println!("heaptest: all tests passed.");
+
+    0
+}
@@ -8,15 +8,16 @@ use runtime::*;
 use terminal::{print, println};
 
 #[unsafe(no_mangle)]
-pub fn main() {
+pub fn main(args: Args) -> i32 {
     let process = process::current().unwrap();
     let thread = thread::current().unwrap();
 
     println!("Hello from Thread [{}] in Process [{}]!\n", thread.id(), process.id());
 
     println!("Arguments:");
-    let args = env::args();
     for arg in args {
         println!("  {}", arg);
     }
+
+    0
 }
\ No newline at end of file
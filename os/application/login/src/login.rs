@@ -0,0 +1,76 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use concurrent::process;
+use syscall::return_vals::Errno;
+use terminal::read::{read, read_line};
+use terminal::{print, println};
+#[allow(unused_imports)]
+use runtime::*;
+
+/// Number of failed attempts, before login gives up
+const MAX_ATTEMPTS: usize = 3;
+
+/// Read a password without echo (and without adding it to the line history)
+fn read_password() -> String {
+    let mut password = String::new();
+    while let Some(c) = read() {
+        match c {
+            '\n' => break,
+            '\x08' | '\x7f' => { password.pop(); }
+            c => password.push(c),
+        }
+    }
+
+    println!("");
+    password
+}
+
+/// Start a shell for the user `uid` and wait for it to exit. Only root may switch to another user.
+fn start_shell(uid: u32) -> i32 {
+    if let Err(errno) = process::set_uid(uid) {
+        println!("login: {}", errno);
+        return 1;
+    }
+
+    match process::execute("shell", Vec::new()).and_then(|shell| shell.wait()) {
+        Ok(exit_code) => exit_code,
+        Err(errno) => {
+            println!("login: {}", errno);
+            1
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub fn main(mut args: Args) -> i32 {
+    // Optional argument: name of the user
+    let mut user = args.nth(1);
+
+    for _ in 0..MAX_ATTEMPTS {
+        let name = match user.take() {
+            Some(name) => name,
+            None => {
+                print!("login: ");
+                match read_line() {
+                    Some(name) => String::from(name.trim()),
+                    None => return 1,
+                }
+            }
+        };
+
+        print!("password: ");
+        let password = read_password();
+
+        match process::authenticate(&name, &password) {
+            Ok(uid) => return start_shell(uid),
+            Err(Errno::EACCES) => println!("Login incorrect"),
+            Err(errno) => println!("login: {}", errno),
+        }
+    }
+
+    1
+}
@@ -5,16 +5,20 @@ extern crate alloc;
 #[allow(unused_imports)]
 use runtime::*;
 use terminal::{print, println};
-use naming::mkentry;
+use naming::{sys_mkentry, EntryKind};
 
 #[unsafe(no_mangle)]
-pub fn main() {
-    let args = env::args();
+pub fn main(args: Args) -> i32 {
     for (i, arg) in args.enumerate() {
         println!("Arg[{}]: {}", i, arg);
     }
 
-    let res = mkentry("/home/schoettner", "test.txt", 1);
+    let res = sys_mkentry("/home/schoettner/test.txt", EntryKind::File);
 
     println!("app: mkentry {:?}", res);
+
+    match res {
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
 }
\ No newline at end of file
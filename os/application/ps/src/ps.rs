@@ -0,0 +1,33 @@
+#![no_std]
+
+extern crate alloc;
+
+use concurrent::process;
+use syscall::process::ProcessState;
+use terminal::{print, println};
+#[allow(unused_imports)]
+use runtime::*;
+
+#[unsafe(no_mangle)]
+pub fn main(_args: Args) -> i32 {
+    let infos = match process::list() {
+        Ok(infos) => infos,
+        Err(errno) => {
+            println!("ps: {}", errno);
+            return 1;
+        }
+    };
+
+    println!("{:>5} {:>5} {:<7} {:>10} NAME", "PID", "PPID", "STATE", "TIME");
+    for info in infos {
+        let state = match info.state {
+            ProcessState::Active => "active",
+            ProcessState::Exited => "exited",
+        };
+        // Run time in seconds with millisecond precision
+        println!("{:>5} {:>5} {:<7} {:>6}.{:03} {}", info.pid, info.parent_pid, state,
+                 info.run_time_ms / 1000, info.run_time_ms % 1000, info.name());
+    }
+
+    0
+}
@@ -2,47 +2,86 @@
 
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
-use concurrent::thread;
-use terminal::read::read;
+use core::str;
+use concurrent::process;
+use syscall::keyboard::{set_keyboard_layout, KeyboardLayout, LAYOUT_NAMES};
+use syscall::memory::{heap_allocations, heap_allocations_len};
+use syscall::return_vals::Errno;
+use terminal::read::read_line;
 use terminal::{print, println};
 #[allow(unused_imports)]
 use runtime::*;
 
 
-fn process_next_char(line: &mut String, ch: char) {
-    match ch {
-        '\n' => {
-            let split = line.split_whitespace().collect::<Vec<&str>>();
-            if !split.is_empty() {
-                match thread::start_application(split[0], split[1..].iter().map(|&s| s).collect()) {
-                    Some(app) => app.join(),
-                    None => println!("Command not found!"),
+fn process_line(line: &str) {
+    let split = line.split_whitespace().collect::<Vec<&str>>();
+    match split.first() {
+        Some(&"poweroff") => println!("poweroff: {}", process::poweroff()),
+        Some(&"reboot") => println!("reboot: {}", process::reboot()),
+        Some(&"leaks") => print_leaks(),
+        Some(&"layout") => set_layout(split.get(1).copied()),
+        Some(&name) => match process::execute(name, split[1..].iter().map(|&s| s).collect()) {
+            Ok(app) => {
+                let result = app.wait();
+                // Return to the primary screen, if the application exited on the alternate screen (no effect otherwise)
+                print!("\x1b[?1049l");
+                match result {
+                    Ok(0) => (),
+                    Ok(exit_code) => println!("{}: exited with status {}", name, exit_code),
+                    Err(errno) => println!("{}: {}", name, errno),
                 }
+            },
+            Err(Errno::ENOENT) => println!("Command not found!"),
+            Err(errno) => println!("{}: {}", name, errno),
+        },
+        None => (),
+    }
+}
+
+/// Description: Print the live kernel heap allocations grouped by call site
+fn print_leaks() {
+    loop {
+        // Some room for allocations made between both system calls
+        let length = match heap_allocations_len() {
+            Ok(length) => length + 1024,
+            Err(errno) => {
+                println!("leaks: {}", errno);
+                return;
             }
+        };
 
-            line.clear();
-            print!("> ");
-        },
-        '\x08' => { 
-            line.pop(); 
-        }, 
-        _ => {
-            line.push(ch);
+        let mut buffer = vec![0u8; length];
+        match heap_allocations(&mut buffer) {
+            Ok(length) => print!("{}", str::from_utf8(&buffer[..length]).unwrap_or("")),
+            Err(Errno::ENOMEM) => continue,
+            Err(errno) => println!("leaks: {}", errno),
+        }
+        return;
+    }
+}
+
+/// Description: Switch the keyboard layout to `name` (without a name, the available layouts are listed)
+fn set_layout(name: Option<&str>) {
+    match name.map(KeyboardLayout::from_name) {
+        Some(Some(layout)) => match set_keyboard_layout(layout) {
+            Ok(_) => (),
+            Err(errno) => println!("layout: {}", errno),
         },
+        Some(None) | None => {
+            let names = LAYOUT_NAMES.iter().map(|(name, _)| *name).collect::<Vec<&str>>();
+            println!("usage: layout <{}>", names.join("|"));
+        }
     }
 }
 
 #[unsafe(no_mangle)]
-pub fn main() {
-    let mut line = String::new();
-    print!("> ");
-
+pub fn main(_args: Args) -> i32 {
     loop {
-        match read() {
-            Some(ch) => process_next_char(&mut line, ch),
-            None => (),
+        print!("> ");
+        if let Some(line) = read_line() {
+            process_line(&line);
         }
     }
 }
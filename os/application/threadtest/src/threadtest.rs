@@ -0,0 +1,41 @@
+#![no_std]
+
+extern crate alloc;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use concurrent::{process, thread};
+#[allow(unused_imports)]
+use runtime::*;
+use terminal::{print, println};
+
+static CHILD_THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+static CHILD_PROCESS_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn child() {
+    // The second call returns the cached id, which must be the id of this thread (not the one cached by the main thread)
+    let id = thread::sys_thread_id();
+    assert_eq!(thread::sys_thread_id(), id, "Cached thread id differs");
+
+    CHILD_THREAD_ID.store(id, Ordering::Relaxed);
+    CHILD_PROCESS_ID.store(process::sys_process_id(), Ordering::Relaxed);
+}
+
+#[unsafe(no_mangle)]
+pub fn main(_args: Args) -> i32 {
+    let main_id = thread::sys_thread_id();
+    let process_id = process::sys_process_id();
+
+    let child_thread = thread::create(child).expect("Failed to create thread");
+    child_thread.join().expect("Failed to join thread");
+
+    let child_id = CHILD_THREAD_ID.load(Ordering::Relaxed);
+    assert_ne!(child_id, main_id, "Both threads see the same thread id");
+    assert_eq!(child_id, child_thread.id(), "Child thread sees a wrong thread id");
+    assert_eq!(thread::sys_thread_id(), main_id, "Cached id of the main thread has changed");
+    assert_eq!(CHILD_PROCESS_ID.load(Ordering::Relaxed), process_id, "Threads see different process ids");
+
+    println!("Main thread [{}], child thread [{}], process [{}]", main_id, child_id, process_id);
+    println!("threadtest: all tests passed.");
+
+    0
+}
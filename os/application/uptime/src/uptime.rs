@@ -8,7 +8,7 @@ use terminal::{print, println};
 use time::systime;
 
 #[unsafe(no_mangle)]
-pub fn main() {
+pub fn main(_args: Args) -> i32 {
     let systime = systime();
 
     if systime.num_seconds() < 60 {
@@ -19,4 +19,6 @@ pub fn main() {
         let seconds = systime.num_seconds() - (systime.num_minutes() * 60);
         println!("{}:{:0>2}:{:0>2}", systime.num_hours(), systime.num_minutes() % 60, seconds);
     }
+
+    0
 }
\ No newline at end of file
@@ -3,9 +3,11 @@
 [EXTERN ___KERNEL_DATA_START__]
 [EXTERN ___KERNEL_DATA_END__]
 [EXTERN start]
+[EXTERN __stack_chk_guard]
 
 ; Kernel constants
 STACK_SIZE equ 0x10000
+IA32_FS_BASE equ 0xc0000100
 
 ; Multiboot2 constants
 MULTIBOOT2_HEADER_MAGIC equ 0xe85250d6
@@ -153,6 +155,15 @@ clear_bss:
     xor rsi, rsi
     mov edi, eax
     mov esi, ebx
+
+    ; Let the FS base point 0x28 bytes below the guard value of the stack protector,
+    ; which is read from 'fs:0x28' by protected functions (see 'stack_guard.rs')
+    mov rax, __stack_chk_guard - 0x28
+    mov rdx, rax
+    shr rdx, 32
+    mov ecx, IA32_FS_BASE
+    wrmsr
+
     call start
 
 [SECTION .bss]
@@ -11,7 +11,10 @@
 use crate::interrupt::interrupt_dispatcher;
 use crate::naming::name_service;
 use crate::syscall::syscall_dispatcher;
+use crate::process::auth;
+use crate::process::scheduler::{DEFAULT_TIME_SLICE_MS, MAX_TIME_SLICE_MS};
 use crate::process::thread::Thread;
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::ToString;
 use alloc::sync::Arc;
@@ -19,10 +22,14 @@ use alloc::vec::Vec;
 use core::ffi::c_void;
 use core::mem::size_of;
 use core::ops::Deref;
+use core::hint::spin_loop;
 use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
 use chrono::DateTime;
-use log::{debug, error, info, LevelFilter};
-use multiboot2::{BootInformation, BootInformationHeader, EFIMemoryMapTag, MemoryAreaType, MemoryMapTag, TagHeader};
+use log::{debug, error, info, trace, warn, LevelFilter};
+use graphic::color::{ChannelMask, PixelFormat};
+use multiboot2::{BootInformation, BootInformationHeader, EFIMemoryMapTag, FramebufferTag, FramebufferType, MemoryAreaType, MemoryMapTag, TagHeader};
 use smoltcp::iface;
 use smoltcp::iface::Interface;
 use smoltcp::time::Instant;
@@ -32,9 +39,9 @@ use uefi::mem::memory_map::MemoryMap;
 use uefi::prelude::*;
 use uefi::table::boot::PAGE_SIZE;
 use uefi::table::Runtime;
-use uefi::table::runtime::Time;
 use uefi_raw::table::boot::MemoryType;
 use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
 use x86_64::instructions::segmentation::{Segment, CS, DS, ES, FS, GS, SS};
 use x86_64::instructions::tables::load_tss;
 use x86_64::{PhysAddr, VirtAddr};
@@ -44,13 +51,25 @@ use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
 use x86_64::PrivilegeLevel::Ring0;
 use x86_64::structures::paging::frame::PhysFrameRange;
 use x86_64::structures::paging::page::PageRange;
-use crate::{acpi_tables, allocator, apic, built_info, efi_system_table, gdt, init_acpi_tables, init_apic, init_efi_system_table, init_initrd, init_pci, init_serial_port, init_terminal, initrd, keyboard, logger, memory, network, process_manager, scheduler, serial_port, terminal, timer, tss};
+use crate::{allocator, apic, built_info, clock, efi_system_table, gdt, init_acpi_tables, init_apic, init_clock, init_efi_system_table, init_initrd, init_pci, init_serial_port, init_terminal, initrd, keyboard, logger, memory, network, process_manager, scheduler, serial_port, terminal, timer, tss};
+use crate::boot_state;
+#[cfg(feature = "kernel-tests")]
+use crate::kernel_tests;
+use crate::boot_timing::BootTimer;
+use crate::stack_guard;
+use crate::device::clock::ClockSource;
 use crate::device::pit::Timer;
 use crate::device::ps2::Keyboard;
 use crate::device::qemu_cfg;
-use crate::device::serial::SerialPort;
-use crate::memory::{MemorySpace, nvmem};
-use crate::memory::nvmem::Nfit;
+use crate::device::rng;
+use crate::device::lfb_terminal::{auto_font_scale, LfbBackend};
+use crate::device::serial::{ComPort, SerialPort};
+use crate::device::terminal::TerminalBackend;
+use crate::device::vga_text;
+use crate::device::vga_text::VgaTextBackend;
+use crate::memory::{ksm, MemorySpace, nvmem, swap};
+use crate::memory::r#virtual::AddressSpace;
+use crate::memory::regions::UsableRegions;
 use crate::network::rtl8139;
 
 // import labels from linker script 'link.ld'
@@ -61,6 +80,11 @@ unsafe extern "C" {
 
 const INIT_HEAP_PAGES: usize = 0x400;   // number of heap pages for booting the OS
 
+/// Boot waits while this is set (only with `waitdebug` on the kernel command line).
+/// A debugger continues the boot with `set var DEBUGGER_WAIT = 0`.
+#[unsafe(no_mangle)]
+static DEBUGGER_WAIT: AtomicBool = AtomicBool::new(true);
+
 /// Description: First rust function called from assembly code `boot.asm` \
 ///
 /// Parameters: \
@@ -68,6 +92,12 @@ const INIT_HEAP_PAGES: usize = 0x400;   // number of heap pages for booting the
 ///    `multiboot2_addr` address of multiboot2 info records
 #[unsafe(no_mangle)]
 pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInformationHeader) {
+    // Set a random guard value for the stack protector (first, since protected functions entered before would fail on return)
+    stack_guard::init(rng::random_u64());
+
+    // Measure the duration of the init phases (only raw TSC values until the clock is known)
+    let mut boot_timer = BootTimer::new();
+
     // Initialize logger
     log::set_logger(logger()).map(|()| log::set_max_level(LevelFilter::Debug)).expect("Failed to initialize logger!");
 
@@ -79,28 +109,70 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         panic!("Invalid Multiboot2 magic number!");
     }
 
-    // Search memory map, provided by bootloader of EFI, for usable memory and initialize physical memory management
-    let multiboot = multiboot2_search_memory_map(multiboot2_addr);
+    // Search memory map, provided by bootloader of EFI, for usable memory
+    let (multiboot, usable_regions) = multiboot2_search_memory_map(multiboot2_addr);
+    boot_timer.phase("Memory map");
+
+    // Give a debugger the chance to attach, if requested on the kernel command line
+    if has_cmdline_flag(&multiboot, "waitdebug") {
+        wait_for_debugger();
+        boot_timer.phase("Debugger wait");
+    }
 
     // Setup global descriptor table
     // Has to be done after EFI boot services have been exited, since they rely on their own GDT
     info!("Initializing GDT");
     init_gdt();
+    boot_timer.phase("GDT");
     
+    // Initialize physical memory management with all usable regions
+    usable_regions.iter().for_each(|region| unsafe { memory::physical::insert(region) });
+
     // The bootloader marks the kernel image region as available, so we need to reserve it manually
     unsafe { memory::physical::reserve(kernel_image_region()); }
 
-    // and initialize kernel heap, after which formatted strings may be used in logs and panics.
+    // and initialize kernel heap (in the largest region), after which formatted strings may be used in logs and panics.
+    // The heap is placed at a random position in the region, unless 'nokaslr' is given on the kernel command line (for debugging).
     info!("Initializing kernel heap");
-    let heap_region = memory::physical::alloc(INIT_HEAP_PAGES);
+    let randomize_heap = !has_cmdline_flag(&multiboot, "nokaslr");
+    let heap_region = match randomize_heap {
+        true => usable_regions.random_heap_region(INIT_HEAP_PAGES, kernel_image_region(), rng::random_u64()),
+        false => usable_regions.heap_region(INIT_HEAP_PAGES, kernel_image_region()),
+    }.expect("No usable memory region is large enough for the kernel heap!");
+    memory::physical::alloc_at(heap_region);
     unsafe { allocator().init(&heap_region); }
-    debug!("Kernel heap is initialized [0x{:x} - 0x{:x}]", heap_region.start.start_address().as_u64(), heap_region.end.start_address().as_u64());
-    debug!("Page frame allocator:\n{}", memory::physical::dump());
+    if randomize_heap {
+        info!("Kernel heap base is randomized (RDRAND available: {})", rng::has_rdrand());
+        trace!("Kernel heap is initialized [0x{:x} - 0x{:x}]", heap_region.start.start_address().as_u64(), heap_region.end.start_address().as_u64());
+    } else {
+        debug!("Kernel heap is initialized [0x{:x} - 0x{:x}]", heap_region.start.start_address().as_u64(), heap_region.end.start_address().as_u64());
+    }
+    if usable_regions.ignored() > 0 {
+        warn!("Memory map contains more than {} usable regions, ignoring [{}] of them", memory::regions::MAX_USABLE_REGIONS, usable_regions.ignored());
+    }
+    if !randomize_heap {
+        debug!("Page frame allocator:\n{}", memory::physical::dump());
+    }
+
+    // All available memory is known now, so the reference counters for shared mappings can be created
+    memory::physical::init_ref_counts();
+    boot_timer.phase("Heap");
 
     // Initialize virtual memory management
     info!("Initializing paging");
-    let kernel_process = process_manager().write().create_process();
+    memory::r#virtual::enable_no_execute();
+    memory::user::enable_protection();
+    let kernel_process = process_manager().write().create_process("kernel");
     kernel_process.address_space().load();
+    memory::r#virtual::enable_pcid();
+
+    // Map the identity mapping (including the kernel heap) with 2 MiB pages, where possible (opt-in)
+    if has_cmdline_flag(&multiboot, "hugepages") {
+        let pages = PageRange { start: Page::containing_address(VirtAddr::zero()), end: Page::containing_address(VirtAddr::new(memory::physical::phys_limit().start_address().as_u64())) };
+        let promoted = kernel_process.address_space().promote(pages);
+        info!("Kernel identity mapping uses [{}] huge pages", promoted);
+    }
+    boot_timer.phase("Paging");
 
     // Initialize serial port and enable serial logging
     init_serial_port();
@@ -108,17 +180,35 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         logger().register(serial);
     }
 
-    // Map the framebuffer, needed for text output of the terminal
-    let fb_info = multiboot.framebuffer_tag()
-        .expect("No framebuffer information provided by bootloader!")
-        .expect("Unknown framebuffer type!");
-    let fb_start_page = Page::from_start_address(VirtAddr::new(fb_info.address())).expect("Framebuffer address is not page aligned");
-    let fb_end_page = Page::from_start_address(VirtAddr::new(fb_info.address() + (fb_info.height() * fb_info.pitch()) as u64).align_up(PAGE_SIZE as u64)).unwrap();
-    kernel_process.address_space().map(PageRange { start: fb_start_page, end: fb_end_page }, MemorySpace::Kernel, PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE);
+    // Map the framebuffer, needed for text output of the terminal (falls back to the VGA text mode without linear framebuffer)
+    let framebuffer = multiboot.framebuffer_tag().and_then(|tag| tag.ok());
+    let backend: Box<dyn TerminalBackend> = match framebuffer {
+        Some(fb_info) if !matches!(fb_info.buffer_type(), Ok(FramebufferType::Text)) => {
+            map_framebuffer(&kernel_process.address_space(), fb_info.address(), (fb_info.height() * fb_info.pitch()) as u64);
+            let (width, height) = (fb_info.width(), fb_info.height());
+            Box::new(LfbBackend::new(fb_info.address() as *mut u8, fb_info.pitch(), width, height, fb_info.bpp(), framebuffer_pixel_format(fb_info), auto_font_scale(width, height)))
+        }
+        _ => {
+            // A text mode tag gives the size in characters, otherwise the standard mode is assumed
+            let (address, columns, rows) = match framebuffer {
+                Some(fb_info) => (fb_info.address(), fb_info.width() as u16, fb_info.height() as u16),
+                None => (vga_text::BUFFER_ADDRESS, vga_text::COLUMNS, vga_text::ROWS),
+            };
+
+            map_framebuffer(&kernel_process.address_space(), address, columns as u64 * rows as u64 * size_of::<u16>() as u64);
+            Box::new(unsafe { VgaTextBackend::new(address as *mut u16, columns, rows) }.with_hardware_cursor())
+        }
+    };
 
     // Initialize terminal and enable terminal logging
-    init_terminal(fb_info.address() as *mut u8, fb_info.pitch(), fb_info.width(), fb_info.height(), fb_info.bpp());
+    init_terminal(backend);
     logger().register(terminal());
+
+    // Blank the screen after 'blank=<seconds>' without keyboard input ('blank=0' disables the screen saver)
+    if let Some(seconds) = cmdline_value(&multiboot, "blank").and_then(|value| value.parse::<usize>().ok()) {
+        terminal().set_blank_timeout(seconds.saturating_mul(1000));
+    }
+    boot_timer.phase("Serial and terminal");
  
     // Dumping basic infos
     info!("Welcome to D3OS!");
@@ -149,19 +239,25 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         panic!("ACPI not available!");
     };
     init_acpi_tables(rsdp_addr);
+    boot_timer.phase("ACPI");
 
     // Initialize interrupts
     info!("Initializing IDT");
     interrupt_dispatcher::setup_idt();
     info!("Initializing system calls");
     syscall_dispatcher::init();
+    boot_timer.phase("IDT and syscalls");
     info!("Initializing APIC");
     init_apic();
+    boot_timer.phase("APIC");
 
     // Initialize timer
     info!("Initializing timer");
     let timer = timer();
     Timer::plugin(Arc::clone(&timer));
+    info!("Initializing monotonic clock");
+    init_clock();
+    boot_timer.phase("Timer and clock");
 
     // Enable interrupts
     info!("Enabling interrupts");
@@ -185,6 +281,7 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         let system_table = efi_system_table.read();
         info!("EFI runtime services available (Vendor: [{}], UEFI version: [{}])", system_table.firmware_vendor(), system_table.uefi_revision());
     }
+    boot_timer.phase("EFI runtime");
 
     // Initialize keyboard
     info!("Initializing PS/2 devices");
@@ -194,10 +291,12 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
     if let Some(serial) = serial_port() {
         SerialPort::plugin(serial);
     }
+    boot_timer.phase("PS/2 and serial");
 
     // Scan PCI bus
     info!("Scanning PCI bus");
     init_pci();
+    boot_timer.phase("PCI");
 
     // Initialize network stack
     network::init();
@@ -218,42 +317,30 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
 
         network::add_interface(interface);
     }
+    boot_timer.phase("Network");
 
     // Initialize non-volatile memory (creates identity mappings for any non-volatile memory regions)
     nvmem::init();
 
-    // As a demo for NVRAM support, we read the last boot time from NVRAM and write the current boot time to it
-    if let Ok(nfit) = acpi_tables().lock().find_table::<Nfit>() {
-        if let Some(range) = nfit.get_phys_addr_ranges().first() {
-            let date_ptr = range.as_phys_frame_range().start.start_address().as_u64() as *mut Time;
-
-            // Read last boot time from NVRAM
-            let date = unsafe { date_ptr.read() };
-            if date.is_valid().is_ok() {
-                info!("Last boot time: [{:0>4}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}]", date.year(), date.month(), date.day(), date.hour(), date.minute(), date.second());
-            }
-
-            // Get current time
-            if let Some(efi_system_table) = efi_system_table() {
-                let system_table = efi_system_table.read();
-                let runtime_services = unsafe { system_table.runtime_services() };
+    // Report last boot time and the reason of the last restart (stored in NVRAM)
+    boot_state::init();
 
-                // Write current boot time to NVRAM
-                if let Ok(time) = runtime_services.get_time() {
-                    unsafe { date_ptr.write(time) }
-                }
-            }
-        }
+    // Swap user heap pages to non-volatile memory under memory pressure (opt-in, since it reduces the NVRAM available to applications)
+    if has_cmdline_flag(&multiboot, "nvswap") {
+        swap::init_nvram();
     }
+    boot_timer.phase("NVRAM");
 
     // Init naming service
     name_service::init();
+    auth::init();
 
     // Load initial ramdisk
     let initrd_tag = multiboot.module_tags()
         .find(|module| module.cmdline().is_ok_and(|name| name == "initrd"))
         .expect("Initrd not found!");
     init_initrd(initrd_tag);
+    boot_timer.phase("Naming and initrd");
 
     // Create and register the cleanup thread in the scheduler
     // (If the last thread of a process terminates, it cannot delete its own address space)
@@ -264,11 +351,32 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         }
     }));
 
+    // Create and register the swapper thread (does nothing, unless a swap area has been configured)
+    scheduler().ready(Thread::new_kernel_thread(swap::run_swapper));
+
+    // Create and register the thread merging identical user pages (opt-in, since scanning costs CPU time)
+    if has_cmdline_flag(&multiboot, "ksm") {
+        scheduler().ready(Thread::new_kernel_thread(ksm::run_scanner));
+    }
+
     // Create and register the 'shell' thread (from app image in ramdisk) in the scheduler
-    scheduler().ready(Thread::load_application(initrd().entries()
-        .find(|entry| entry.filename().as_str().unwrap() == "shell")
-        .expect("Shell application not available!")
-        .data(), "shell", &Vec::new()));
+    #[cfg(not(feature = "kernel-tests"))]
+    start_shell();
+
+    // Run all kernel tests in their own thread, which starts the shell afterwards
+    #[cfg(feature = "kernel-tests")]
+    scheduler().ready(Thread::new_kernel_thread(|| {
+        kernel_tests::run();
+        start_shell();
+    }));
+    boot_timer.phase("Threads");
+
+    // Report the boot phases, before terminal logging is disabled
+    let tsc_frequency = match clock().source() {
+        ClockSource::Tsc { frequency, .. } => Some(frequency),
+        ClockSource::Timer => None,
+    };
+    boot_timer.report(tsc_frequency);
 
     // Disable terminal logging (remove terminal output stream)
     logger().remove(terminal().as_ref());
@@ -277,12 +385,72 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
     println!(include_str!("banner.txt"), version, git_ref.rsplit("/").next().unwrap_or(git_ref), git_commit, build_date,
              built_info::RUSTC_VERSION.split_once("(").unwrap_or((built_info::RUSTC_VERSION, "")).0.trim(), bootloader_name);
 
-    // Start APIC timer & scheduler
-    info!("Starting scheduler");
-    apic().start_timer(10);
+    // Start APIC timer & scheduler (the timer interrupt preempts the running thread after 'timeslice=<ms>')
+    let time_slice = cmdline_value(&multiboot, "timeslice").and_then(|value| value.parse::<usize>().ok())
+        .filter(|ms| (1..=MAX_TIME_SLICE_MS).contains(ms))
+        .unwrap_or(DEFAULT_TIME_SLICE_MS);
+    info!("Starting scheduler (time slice: [{} ms])", time_slice);
+    apic().start_timer(time_slice);
     scheduler().start();
 }
 
+/// Description: Create and register the 'shell' thread (from app image in ramdisk) in the scheduler
+fn start_shell() {
+    scheduler().ready(Thread::load_application(initrd().entries()
+        .find(|entry| entry.filename().as_str().unwrap() == "shell")
+        .expect("Shell application not available!")
+        .data(), "shell", &Vec::new(), &Vec::new()));
+}
+
+/// Description: Check if `flag` is given on the kernel command line (arguments are separated by whitespace)
+fn has_cmdline_flag(multiboot: &BootInformation, flag: &str) -> bool {
+    multiboot.command_line_tag().is_some_and(|tag| tag.cmdline().is_ok_and(|cmdline| cmdline.split_whitespace().any(|arg| arg == flag)))
+}
+
+/// Description: Value of the kernel command line argument `key=value` (`None`, if it is not given)
+fn cmdline_value<'a>(multiboot: &'a BootInformation, key: &str) -> Option<&'a str> {
+    let cmdline = multiboot.command_line_tag()?.cmdline().ok()?;
+    cmdline.split_whitespace().find_map(|arg| arg.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// Description: Map `size` bytes of the framebuffer at `address` uncached into `address_space`
+fn map_framebuffer(address_space: &AddressSpace, address: u64, size: u64) {
+    let start_page = Page::from_start_address(VirtAddr::new(address)).expect("Framebuffer address is not page aligned");
+    let end_page = Page::from_start_address(VirtAddr::new(address + size).align_up(PAGE_SIZE as u64)).unwrap();
+    address_space.map(PageRange { start: start_page, end: end_page }, MemorySpace::Kernel, PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE);
+}
+
+/// Description: Pixel format from the channel masks reported by the bootloader (RGB, if there are none)
+fn framebuffer_pixel_format(framebuffer: &FramebufferTag) -> PixelFormat {
+    match framebuffer.buffer_type() {
+        Ok(FramebufferType::RGB { red, green, blue }) => {
+            let format = PixelFormat::from_masks(framebuffer.bpp(), ChannelMask::new(red.position, red.size), ChannelMask::new(green.position, green.size), ChannelMask::new(blue.position, blue.size));
+            info!("Framebuffer pixel format: {:?}", format);
+            format
+        }
+        _ => PixelFormat::Rgb,
+    }
+}
+
+/// Description: Wait until a debugger clears `DEBUGGER_WAIT` or a key is pressed on the serial console (COM1)
+fn wait_for_debugger() {
+    info!("Waiting for debugger (continue with 'set var DEBUGGER_WAIT = 0' in gdb or press a key on COM1)");
+
+    let mut line_status = Port::<u8>::new(ComPort::Com1 as u16 + 5);
+    let mut data = Port::<u8>::new(ComPort::Com1 as u16);
+    while DEBUGGER_WAIT.load(Relaxed) {
+        // Bit 0 of the line status register signals received data (reads as 1 without a serial port, so the boot continues)
+        if unsafe { line_status.read() } & 0x01 != 0 {
+            unsafe { data.read(); }
+            break;
+        }
+
+        spin_loop();
+    }
+
+    info!("Continuing boot");
+}
+
 /// Description: Set up the GDT
 fn init_gdt() {
     let mut gdt = gdt().lock();
@@ -333,16 +501,17 @@ fn kernel_image_region() -> PhysFrameRange {
 }
 
 
-/// Description: Search memory map, provided by bootloader of EFI, for usable memory and initialize physical memory management \
+/// Description: Search memory map, provided by bootloader of EFI, for usable memory \
 ///
 /// Parameters: \
 ///    `multiboot2_addr` address of multiboot2 info records
 ///
-/// Return: `BootInformation`
-fn multiboot2_search_memory_map(multiboot2_addr: *const BootInformationHeader) -> BootInformation<'static> {
+/// Return: `BootInformation` and all usable memory regions
+fn multiboot2_search_memory_map(multiboot2_addr: *const BootInformationHeader) -> (BootInformation<'static>, UsableRegions) {
     let multiboot = unsafe { BootInformation::load(multiboot2_addr).expect("Failed to get Multiboot2 information") };
 
-    // Search memory map, provided by bootloader of EFI, for usable memory and initialize physical memory management
+    // Search memory map, provided by bootloader of EFI, for usable memory
+    let regions;
     if let Some(_) = multiboot.efi_bs_not_exited_tag() {
         // EFI boot services have not been exited, and we obtain access to the memory map and EFI runtime services by exiting them manually
         info!("EFI boot services have not been exited");
@@ -360,7 +529,7 @@ fn multiboot2_search_memory_map(multiboot2_addr: *const BootInformationHeader) -
         info!("Exiting EFI boot services to obtain runtime system table and memory map");
         unsafe {
             let (runtime_table, memory_map) = system_table.exit_boot_services(MemoryType::LOADER_DATA);
-            scan_efi_memory_map(&memory_map);
+            regions = scan_efi_memory_map(&memory_map);
             init_efi_system_table(runtime_table);
         }
     } else {
@@ -368,59 +537,55 @@ fn multiboot2_search_memory_map(multiboot2_addr: *const BootInformationHeader) -
         if let Some(memory_map) = multiboot.efi_memory_map_tag() {
             // EFI services have been exited, but the bootloader has provided us with the EFI memory map
             info!("Bootloader provides EFI memory map");
-            scan_efi_multiboot2_memory_map(memory_map);
+            regions = scan_efi_multiboot2_memory_map(memory_map);
         } else if let Some(memory_map) = multiboot.memory_map_tag() {
             // EFI services have been exited, but the bootloader has provided us with a Multiboot2 memory map
             info!("Bootloader provides Multiboot2 memory map");
-            scan_multiboot2_memory_map(memory_map);
+            regions = scan_multiboot2_memory_map(memory_map);
         } else {
             panic!("No memory information available!");
         }
     }
-    multiboot
+    (multiboot, regions)
 }
 
 /// Description: Searching available memory regions provided by multiboot2
 ///              Available only if efi boot services have been exited
 ///              and bootloader provides these memory maps.
-fn scan_multiboot2_memory_map(memory_map: &MemoryMapTag) {
+fn scan_multiboot2_memory_map(memory_map: &MemoryMapTag) -> UsableRegions {
     info!("Searching memory map for available regions");
+    let mut regions = UsableRegions::new();
     memory_map.memory_areas().iter()
         .filter(|area| area.typ() == MemoryAreaType::Available)
-        .for_each(|area| {
-            unsafe {
-                memory::physical::insert(PhysFrameRange {
-                    start: PhysFrame::from_start_address(PhysAddr::new(area.start_address()).align_up(PAGE_SIZE as u64)).unwrap(),
-                    end: PhysFrame::from_start_address(PhysAddr::new(area.end_address()).align_down(PAGE_SIZE as u64)).unwrap()
-                });
-            }
-        });
+        .for_each(|area| regions.add(area.start_address(), area.end_address()));
+
+    regions
 }
 
 
 /// Description: Memory map from efi. Only available if boot services have been exited.
 ///              Sometimes bootloaders do not provide multiboot2 memory maps if
 ///              efi information has been requested.
-fn scan_efi_multiboot2_memory_map(memory_map: &EFIMemoryMapTag) {
+fn scan_efi_multiboot2_memory_map(memory_map: &EFIMemoryMapTag) -> UsableRegions {
     info!("Searching memory map for available regions");
+    let mut regions = UsableRegions::new();
     memory_map.memory_areas()
         .filter(|area| area.ty.0 == MemoryType::CONVENTIONAL.0 || area.ty.0 == MemoryType::LOADER_CODE.0 || area.ty.0 == MemoryType::LOADER_DATA.0
             || area.ty.0 == MemoryType::BOOT_SERVICES_CODE.0 || area.ty.0 == MemoryType::BOOT_SERVICES_DATA.0) // .0 necessary because of different version dependencies to uefi-crate
-        .for_each(|area| {
-            let start = PhysFrame::from_start_address(PhysAddr::new(area.phys_start).align_up(PAGE_SIZE as u64)).unwrap();
-            unsafe { memory::physical::insert(PhysFrameRange { start, end: start + area.page_count }); }
-        });
+        .for_each(|area| regions.add(area.phys_start, area.phys_start + area.page_count * PAGE_SIZE as u64));
+
+    regions
 }
 
 
 /// Description: Memory map from efi. Only available if boot services have NOT been exited.
-fn scan_efi_memory_map(memory_map: &dyn MemoryMap) {
+fn scan_efi_memory_map(memory_map: &dyn MemoryMap) -> UsableRegions {
     info!("Searching memory map for available regions");
+    let mut regions = UsableRegions::new();
     memory_map.entries()
         .filter(|area| area.ty == MemoryType::CONVENTIONAL || area.ty == MemoryType::LOADER_CODE || area.ty == MemoryType::LOADER_DATA
             || area.ty == MemoryType::BOOT_SERVICES_CODE || area.ty == MemoryType::BOOT_SERVICES_DATA)
-        .for_each(|area| {
-            let start = PhysFrame::from_start_address(PhysAddr::new(area.phys_start).align_up(PAGE_SIZE as u64)).unwrap();
-            unsafe { memory::physical::insert(PhysFrameRange { start, end: start + area.page_count }); }
-        });
+        .for_each(|area| regions.add(area.phys_start, area.phys_start + area.page_count * PAGE_SIZE as u64));
+
+    regions
 }
@@ -0,0 +1,175 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: boot_state                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: State kept in NVRAM across reboots (root allocation of the      ║
+   ║         NVRAM allocator): the time of the last boot and the reason for  ║
+   ║         the last restart. During boot, the reason is set to 'Running'.  ║
+   ║         'reboot()', 'poweroff()' and the panic handler replace it with  ║
+   ║         their own reason. If 'Running' is found on the next boot, the   ║
+   ║         system has been reset without recording a reason (crash).       ║
+   ║         The state is checksummed, so an incomplete write (power loss)   ║
+   ║         is detected and reported as 'Unknown'.                          ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::alloc::{Allocator, Layout};
+use core::ptr;
+use core::slice;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use log::{info, warn};
+use uefi::table::runtime::Time;
+use crate::memory::nvmem;
+use crate::memory::nvram_alloc;
+use crate::{efi_system_table, init_last_boot_reason, nvram_allocator};
+
+/// "D3OSBOOT"
+const BOOT_STATE_MAGIC: u64 = 0x44334f53424f4f54;
+
+#[repr(u64)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BootReason {
+    Unknown = 0,   // no (valid) state in NVRAM: first boot, power loss or no NVRAM
+    Running = 1,   // set during boot, never reported as reason of a restart
+    Reboot = 2,    // reboot requested (e.g. by 'reboot' system call)
+    PowerOff = 3,  // power off requested
+    Panic = 4,     // kernel panic
+    Crash = 5,     // reset while running, without recording a reason (e.g. triple fault)
+}
+
+impl BootReason {
+    fn from_u64(value: u64) -> Option<Self> {
+        match value {
+            0 => Some(BootReason::Unknown),
+            1 => Some(BootReason::Running),
+            2 => Some(BootReason::Reboot),
+            3 => Some(BootReason::PowerOff),
+            4 => Some(BootReason::Panic),
+            5 => Some(BootReason::Crash),
+            _ => None,
+        }
+    }
+}
+
+#[repr(C)]
+pub struct BootState {
+    magic: u64,
+    checksum: u64,
+    reason: u64,
+    boot_time: Time,
+}
+
+/// Boot state in NVRAM (null, if there is no NVRAM)
+static BOOT_STATE: AtomicPtr<BootState> = AtomicPtr::new(ptr::null_mut());
+
+impl Default for BootState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BootState {
+    pub const fn new() -> Self {
+        Self { magic: BOOT_STATE_MAGIC, checksum: 0, reason: BootReason::Unknown as u64, boot_time: Time::invalid() }
+    }
+
+    /// Description: Check magic and checksum
+    pub fn is_valid(&self) -> bool {
+        self.magic == BOOT_STATE_MAGIC && self.checksum == self.checksum()
+    }
+
+    /// Description: Recorded reason or `None`, if the state is not valid
+    pub fn reason(&self) -> Option<BootReason> {
+        if self.is_valid() { BootReason::from_u64(self.reason) } else { None }
+    }
+
+    /// Description: Set the reason and update the checksum (the checksum is written last)
+    pub fn set_reason(&mut self, reason: BootReason) {
+        self.reason = reason as u64;
+        self.checksum = self.checksum();
+    }
+
+    fn set_boot_time(&mut self, time: Time) {
+        self.boot_time = time;
+        self.checksum = self.checksum();
+    }
+
+    /// Description: Checksum over all fields following the checksum
+    fn checksum(&self) -> u64 {
+        let time = unsafe { slice::from_raw_parts(ptr::from_ref(&self.boot_time) as *const u8, size_of::<Time>()) };
+        nvram_alloc::checksum(self.reason.to_le_bytes().iter().chain(time.iter()))
+    }
+}
+
+///
+/// Description:
+///    Read the boot state from NVRAM (allocated on first use), report the last boot
+///    time and the reason of the last restart, and mark the system as running.
+///    Must be called after 'nvmem::init()'. Without NVRAM, the reason is 'Unknown'.
+///
+pub fn init() {
+    if !nvram_allocator().is_initialized() {
+        init_last_boot_reason(BootReason::Unknown);
+        return;
+    }
+
+    // Defragment the non-volatile memory, while nobody references the boot state
+    match unsafe { nvmem::compact(Layout::new::<BootState>()) } {
+        Ok(compaction) if compaction.moved_bytes > 0 => info!("Compacted NVRAM (largest free space: [{}] bytes)", compaction.largest_free),
+        Ok(_) => {}
+        Err(_) => warn!("Failed to compact NVRAM (root does not match the boot state)"),
+    }
+
+    let state = match nvram_allocator().root() {
+        Some(root) => root.cast::<BootState>(),
+        None => {
+            let state = nvram_allocator().allocate(Layout::new::<BootState>()).expect("Failed to allocate boot state in NVRAM").cast::<BootState>();
+            unsafe { state.write(BootState::new()) };
+            nvram_allocator().set_root(Some(state.cast()));
+            state
+        }
+    };
+    let state = unsafe { state.as_ptr().as_mut().unwrap() };
+
+    let reason = match state.reason() {
+        Some(BootReason::Running) => BootReason::Crash,
+        Some(reason) => reason,
+        None => {
+            warn!("Boot state in NVRAM is invalid (incomplete write?)");
+            *state = BootState::new();
+            BootReason::Unknown
+        }
+    };
+    init_last_boot_reason(reason);
+
+    let date = state.boot_time;
+    if date.is_valid().is_ok() {
+        info!("Last boot time: [{:0>4}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}]", date.year(), date.month(), date.day(), date.hour(), date.minute(), date.second());
+    }
+    info!("Last restart reason: [{:?}]", reason);
+
+    // Write current boot time to NVRAM
+    if let Some(efi_system_table) = efi_system_table() {
+        let system_table = efi_system_table.read();
+        if let Ok(time) = unsafe { system_table.runtime_services() }.get_time() {
+            state.set_boot_time(time);
+        }
+    }
+
+    state.set_reason(BootReason::Running);
+    nvmem::flush();
+    BOOT_STATE.store(state, Release);
+}
+
+///
+/// Description:
+///    Record `reason` as reason of the next restart and write it back to NVRAM.
+///    Does not allocate or lock, so it may be called from the panic handler.
+///
+pub fn record(reason: BootReason) {
+    if let Some(state) = unsafe { BOOT_STATE.load(Acquire).as_mut() } {
+        state.set_reason(reason);
+        nvmem::flush();
+    }
+}
@@ -0,0 +1,53 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: boot_state_tests                                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test validation of the boot state (on a copy in normal memory). ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use core::ptr;
+use crate::boot_state::{BootReason, BootState};
+
+///
+/// Description: Run all boot state tests
+///
+pub fn run_tests() {
+    info!("boot_state: running tests");
+
+    test_round_trip();
+    test_incomplete_write();
+    test_bad_magic();
+
+    info!("boot_state: all tests passed.");
+}
+
+/// Description: A recorded reason is read back
+fn test_round_trip() {
+    let mut state = BootState::new();
+    state.set_reason(BootReason::Panic);
+
+    assert!(state.is_valid());
+    assert_eq!(state.reason(), Some(BootReason::Panic));
+}
+
+/// Description: A reason written without updating the checksum is detected
+fn test_incomplete_write() {
+    let mut state = BootState::new();
+    state.set_reason(BootReason::Reboot);
+
+    // Overwrite the reason only, as if power was lost before the checksum was written
+    unsafe { (ptr::from_mut(&mut state) as *mut u64).add(2).write(BootReason::PowerOff as u64) };
+    assert!(!state.is_valid());
+    assert_eq!(state.reason(), None);
+}
+
+/// Description: Memory without the magic number (e.g. from an older kernel) is not a boot state
+fn test_bad_magic() {
+    let mut state = BootState::new();
+    state.set_reason(BootReason::Reboot);
+
+    unsafe { (ptr::from_mut(&mut state) as *mut u64).write(0) };
+    assert_eq!(state.reason(), None);
+}
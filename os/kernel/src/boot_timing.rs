@@ -0,0 +1,87 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: boot_timing                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Measures the duration of the init phases in 'boot::start()'.    ║
+   ║         The heap and the monotonic clock are not available in the       ║
+   ║         earliest phases, so raw TSC values are kept in a fixed array    ║
+   ║         and converted, when the TSC frequency is known.                 ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::arch::x86_64::_rdtsc;
+use log::info;
+use crate::device::pit::tsc_to_nanos;
+
+/// Maximum number of recorded phases (further phases are ignored)
+pub const MAX_PHASES: usize = 24;
+
+pub struct BootTimer {
+    start: u64,
+    phases: [(&'static str, u64); MAX_PHASES], // name and TSC value at the end of the phase
+    count: usize,
+}
+
+impl Default for BootTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BootTimer {
+    /// Start measuring (the first phase starts now)
+    pub fn new() -> Self {
+        Self { start: unsafe { _rdtsc() }, phases: [("", 0); MAX_PHASES], count: 0 }
+    }
+
+    /// Mark the end of phase `name` (the next phase starts now)
+    pub fn phase(&mut self, name: &'static str) {
+        if self.count < MAX_PHASES {
+            self.phases[self.count] = (name, unsafe { _rdtsc() });
+            self.count += 1;
+        }
+    }
+
+    /// Duration of each recorded phase in TSC cycles
+    pub fn cycles(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        let mut last = self.start;
+        self.phases[..self.count].iter().map(move |(name, end)| {
+            let cycles = end.saturating_sub(last);
+            last = *end;
+            (*name, cycles)
+        })
+    }
+
+    /// Cycles from creation of the timer to the end of the last phase
+    pub fn total_cycles(&self) -> u64 {
+        match self.count {
+            0 => 0,
+            count => self.phases[count - 1].1.saturating_sub(self.start),
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Log the duration of all phases as a table. Without a known TSC frequency
+    ///    (the monotonic clock does not use the TSC), durations are given in cycles.
+    ///
+    /// Parameters: `tsc_frequency` TSC frequency in Hz
+    ///
+    pub fn report(&self, tsc_frequency: Option<u64>) {
+        let (unit, convert): (&str, &dyn Fn(u64) -> u64) = match tsc_frequency {
+            Some(frequency) => ("us", &move |cycles| tsc_to_nanos(cycles, frequency) / 1000),
+            None => ("kcycles", &|cycles| cycles / 1000),
+        };
+
+        info!("Boot time report:");
+        info!("+----------------------+--------------+");
+        info!("| {:<20} | {:>12} |", "Phase", unit);
+        info!("+----------------------+--------------+");
+        for (name, cycles) in self.cycles() {
+            info!("| {:<20} | {:>12} |", name, convert(cycles));
+        }
+        info!("+----------------------+--------------+");
+        info!("| {:<20} | {:>12} |", "Total", convert(self.total_cycles()));
+        info!("+----------------------+--------------+");
+    }
+}
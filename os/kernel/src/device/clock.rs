@@ -0,0 +1,70 @@
+use core::arch::x86_64::_rdtsc;
+use log::info;
+use raw_cpuid::CpuId;
+use crate::device::pit::tsc_to_nanos;
+use crate::timer;
+
+/// Time used to measure the TSC frequency against the PIT
+const CALIBRATION_TIME_MS: usize = 10;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClockSource {
+    /// Invariant TSC, running at a constant rate in all power states
+    Tsc { frequency: u64, base: u64 },
+    /// PIT based system time (fallback, if the TSC is not invariant)
+    Timer,
+}
+
+/// Monotonic clock, counting nanoseconds since its initialization.
+/// The time source is selected once at initialization, so reading the clock is cheap.
+pub struct MonotonicClock {
+    source: ClockSource,
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MonotonicClock {
+    /// Select the time source. Must be called after the PIT has been initialized,
+    /// since it is used to measure the TSC frequency.
+    pub fn new() -> Self {
+        let source = if has_invariant_tsc() {
+            let start = unsafe { _rdtsc() };
+            timer().wait(CALIBRATION_TIME_MS);
+            let end = unsafe { _rdtsc() };
+
+            let frequency = (end - start) * (1000 / CALIBRATION_TIME_MS as u64);
+            info!("Monotonic clock: Using invariant TSC ({} MHz)", frequency / 1000000);
+            ClockSource::Tsc { frequency, base: unsafe { _rdtsc() } }
+        } else {
+            info!("Monotonic clock: TSC is not invariant, using PIT");
+            ClockSource::Timer
+        };
+
+        Self { source }
+    }
+
+    pub fn source(&self) -> ClockSource {
+        self.source
+    }
+
+    /// Nanoseconds since the clock has been initialized (Timer: since boot)
+    pub fn now_ns(&self) -> u64 {
+        match self.source {
+            ClockSource::Tsc { frequency, base } => tsc_to_nanos(unsafe { _rdtsc() }.saturating_sub(base), frequency),
+            ClockSource::Timer => timer().systime_ns() as u64,
+        }
+    }
+}
+
+/// Check CPUID for an invariant TSC (CPUID.80000007H:EDX[8])
+fn has_invariant_tsc() -> bool {
+    let cpuid = CpuId::new();
+    let has_tsc = cpuid.get_feature_info().is_some_and(|features| features.has_tsc());
+    let invariant = cpuid.get_advanced_power_mgmt_info().is_some_and(|info| info.has_invariant_tsc());
+
+    has_tsc && invariant
+}
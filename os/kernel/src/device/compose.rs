@@ -0,0 +1,129 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: compose                                                         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Compose key sequences for typing characters, that are not on    ║
+   ║         the keyboard layout (e.g. accented letters on the US layout):   ║
+   ║         the compose key (menu key) followed by two characters is        ║
+   ║         replaced by the character of `DEFAULT_TABLE` (e.g. compose ' e  ║
+   ║         gives é, the order of the two characters does not matter).      ║
+   ║         Unmapped combinations and sequences interrupted by other keys   ║
+   ║         or the timeout emit the typed characters unchanged. Dead keys   ║
+   ║         are not supported.                                              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::VecDeque;
+use pc_keyboard::{DecodedKey, KeyCode};
+
+/// Key starting a compose sequence
+pub const COMPOSE_KEY: KeyCode = KeyCode::Apps;
+/// A sequence is given up, if no key is pressed for this time (the characters typed so far are emitted)
+pub const TIMEOUT_MS: usize = 5000;
+
+/// Two characters and the character they are composed to
+pub type ComposeEntry = (char, char, char);
+
+/// Latin-1 characters composed from an accent and a letter, or from two similar looking characters
+pub const DEFAULT_TABLE: [ComposeEntry; 80] = [
+    ('\'', 'a', 'á'), ('\'', 'e', 'é'), ('\'', 'i', 'í'), ('\'', 'o', 'ó'), ('\'', 'u', 'ú'), ('\'', 'y', 'ý'),
+    ('\'', 'A', 'Á'), ('\'', 'E', 'É'), ('\'', 'I', 'Í'), ('\'', 'O', 'Ó'), ('\'', 'U', 'Ú'), ('\'', 'Y', 'Ý'),
+    ('`', 'a', 'à'), ('`', 'e', 'è'), ('`', 'i', 'ì'), ('`', 'o', 'ò'), ('`', 'u', 'ù'), ('`', 'A', 'À'),
+    ('`', 'E', 'È'), ('`', 'I', 'Ì'), ('`', 'O', 'Ò'), ('`', 'U', 'Ù'),
+    ('^', 'a', 'â'), ('^', 'e', 'ê'), ('^', 'i', 'î'), ('^', 'o', 'ô'), ('^', 'u', 'û'), ('^', 'A', 'Â'),
+    ('^', 'E', 'Ê'), ('^', 'I', 'Î'), ('^', 'O', 'Ô'), ('^', 'U', 'Û'),
+    ('"', 'a', 'ä'), ('"', 'e', 'ë'), ('"', 'i', 'ï'), ('"', 'o', 'ö'), ('"', 'u', 'ü'), ('"', 'y', 'ÿ'),
+    ('"', 'A', 'Ä'), ('"', 'E', 'Ë'), ('"', 'I', 'Ï'), ('"', 'O', 'Ö'), ('"', 'U', 'Ü'),
+    ('~', 'a', 'ã'), ('~', 'n', 'ñ'), ('~', 'o', 'õ'), ('~', 'A', 'Ã'), ('~', 'N', 'Ñ'), ('~', 'O', 'Õ'),
+    (',', 'c', 'ç'), (',', 'C', 'Ç'),
+    ('o', 'a', 'å'), ('O', 'A', 'Å'), ('a', 'e', 'æ'), ('A', 'E', 'Æ'), ('o', '/', 'ø'), ('O', '/', 'Ø'),
+    ('s', 's', 'ß'), ('!', '!', '¡'), ('?', '?', '¿'), ('<', '<', '«'), ('>', '>', '»'), ('e', '=', '€'),
+    ('c', 'o', '©'), ('r', 'o', '®'), ('^', '1', '¹'), ('^', '2', '²'), ('^', '3', '³'), ('o', 'o', '°'),
+    ('+', '-', '±'), ('1', '2', '½'), ('1', '4', '¼'), ('x', 'x', '×'), ('-', ':', '÷'), ('l', '-', '£'),
+    ('y', '=', '¥'), ('c', '|', '¢'), ('s', 'o', '§'), ('p', '!', '¶'), ('.', '.', '·'),
+];
+
+/// Compose state of a keyboard. Decoded keys are passed to `feed()` and read back from `pop_key()`.
+pub struct Compose {
+    table: &'static [ComposeEntry],
+    state: State,
+    output: VecDeque<DecodedKey>, // keys, that have passed the compose state machine
+}
+
+enum State {
+    Idle,
+    Started(usize),     // compose key pressed at the given time
+    First(char, usize), // first character typed at the given time
+}
+
+impl Compose {
+    pub const fn new(table: &'static [ComposeEntry]) -> Self {
+        Self { table, state: State::Idle, output: VecDeque::new() }
+    }
+
+    ///
+    /// Description:
+    ///    Pass the key `key`, pressed at `now_ms`, through the compose state machine. Outside of a
+    ///    sequence, keys are passed unchanged. A key, that is not a character, aborts the sequence
+    ///    (the character typed so far is emitted before it), a second compose key cancels it.
+    ///
+    pub fn feed(&mut self, key: DecodedKey, now_ms: usize) {
+        self.check_timeout(now_ms);
+
+        self.state = match (&self.state, key) {
+            (State::Idle, DecodedKey::RawKey(COMPOSE_KEY)) => State::Started(now_ms),
+            (State::Idle, key) => {
+                self.output.push_back(key);
+                State::Idle
+            }
+            (State::Started(_), DecodedKey::Unicode(first)) => State::First(first, now_ms),
+            (State::First(first, _), DecodedKey::Unicode(second)) => {
+                match self.lookup(*first, second) {
+                    Some(composed) => self.output.push_back(DecodedKey::Unicode(composed)),
+                    None => self.output.extend([DecodedKey::Unicode(*first), DecodedKey::Unicode(second)]),
+                }
+                State::Idle
+            }
+            (_, DecodedKey::RawKey(COMPOSE_KEY)) => State::Idle,
+            (state, key) => {
+                if let State::First(first, _) = state {
+                    self.output.push_back(DecodedKey::Unicode(*first));
+                }
+                self.output.push_back(key);
+                State::Idle
+            }
+        };
+    }
+
+    /// Description: Give up a sequence, if no key has been pressed for `TIMEOUT_MS` at `now_ms` (the character typed so far is emitted)
+    pub fn check_timeout(&mut self, now_ms: usize) {
+        let since_ms = match self.state {
+            State::Idle => return,
+            State::Started(since_ms) | State::First(_, since_ms) => since_ms,
+        };
+
+        if now_ms.saturating_sub(since_ms) >= TIMEOUT_MS {
+            if let State::First(first, _) = self.state {
+                self.output.push_back(DecodedKey::Unicode(first));
+            }
+            self.state = State::Idle;
+        }
+    }
+
+    /// Description: A compose sequence has been started and is not complete yet
+    pub fn is_composing(&self) -> bool {
+        !matches!(self.state, State::Idle)
+    }
+
+    /// Description: Return the next key, that has passed the compose state machine (`None` if there is none)
+    pub fn pop_key(&mut self) -> Option<DecodedKey> {
+        self.output.pop_front()
+    }
+
+    /// Description: Character composed from `first` and `second` (in any order) or `None`, if there is no table entry
+    fn lookup(&self, first: char, second: char) -> Option<char> {
+        self.table.iter()
+            .find(|(a, b, _)| (*a == first && *b == second) || (*a == second && *b == first))
+            .map(|(_, _, composed)| *composed)
+    }
+}
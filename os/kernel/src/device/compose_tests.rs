@@ -0,0 +1,111 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: compose_tests                                                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test compose key sequences: composing characters (in both       ║
+   ║         orders), unmapped combinations, sequences aborted by other      ║
+   ║         keys, a second compose key or the timeout, and keys passed      ║
+   ║         unchanged outside of a sequence.                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec::Vec;
+use ::log::info;
+use pc_keyboard::{DecodedKey, KeyCode};
+
+use crate::device::compose::{Compose, COMPOSE_KEY, DEFAULT_TABLE, TIMEOUT_MS};
+
+const COMPOSE: DecodedKey = DecodedKey::RawKey(COMPOSE_KEY);
+
+///
+/// Description: Run all compose tests
+///
+pub fn run_tests() {
+    info!("compose: running tests");
+
+    test_compose();
+    test_unmapped();
+    test_aborted();
+    test_timeout();
+    test_table();
+
+    info!("compose: all tests passed.");
+}
+
+/// Description: Feed `keys` (one per millisecond) and return the keys passed on
+fn feed(compose: &mut Compose, keys: &[DecodedKey]) -> Vec<DecodedKey> {
+    let mut output = Vec::new();
+    for (time, key) in keys.iter().enumerate() {
+        compose.feed(*key, time);
+        while let Some(key) = compose.pop_key() {
+            output.push(key);
+        }
+    }
+
+    output
+}
+
+/// Description: Characters of `text` as typed keys
+fn typed(text: &str) -> Vec<DecodedKey> {
+    text.chars().map(DecodedKey::Unicode).collect()
+}
+
+/// Description: Compose, accent and letter give the accented letter (the order of accent and letter does not matter)
+fn test_compose() {
+    let mut compose = Compose::new(&DEFAULT_TABLE);
+
+    assert_eq!(feed(&mut compose, &[COMPOSE, DecodedKey::Unicode('\''), DecodedKey::Unicode('e')]), typed("é"));
+    assert_eq!(feed(&mut compose, &[COMPOSE, DecodedKey::Unicode('e'), DecodedKey::Unicode('\'')]), typed("é"));
+    assert_eq!(feed(&mut compose, &[COMPOSE, DecodedKey::Unicode('s'), DecodedKey::Unicode('s')]), typed("ß"));
+    assert!(!compose.is_composing());
+
+    // Outside of a sequence, keys are passed unchanged
+    assert_eq!(feed(&mut compose, &typed("'e")), typed("'e"));
+}
+
+/// Description: An unmapped combination emits both typed characters
+fn test_unmapped() {
+    let mut compose = Compose::new(&DEFAULT_TABLE);
+
+    assert_eq!(feed(&mut compose, &[COMPOSE, DecodedKey::Unicode('q'), DecodedKey::Unicode('z')]), typed("qz"));
+    assert!(!compose.is_composing());
+}
+
+/// Description: A key, that is not a character, aborts the sequence, a second compose key cancels it
+fn test_aborted() {
+    let mut compose = Compose::new(&DEFAULT_TABLE);
+    let left = DecodedKey::RawKey(KeyCode::ArrowLeft);
+
+    assert_eq!(feed(&mut compose, &[COMPOSE, left]), [left]);
+    assert_eq!(feed(&mut compose, &[COMPOSE, DecodedKey::Unicode('e'), left]), [DecodedKey::Unicode('e'), left]);
+    assert!(feed(&mut compose, &[COMPOSE, DecodedKey::Unicode('e'), COMPOSE]).is_empty());
+    assert_eq!(feed(&mut compose, &[COMPOSE, COMPOSE, DecodedKey::Unicode('e')]), typed("e"));
+}
+
+/// Description: Without a key for TIMEOUT_MS, the sequence is given up and the typed character is emitted
+fn test_timeout() {
+    let mut compose = Compose::new(&DEFAULT_TABLE);
+
+    compose.feed(COMPOSE, 0);
+    compose.feed(DecodedKey::Unicode('e'), 1);
+    compose.check_timeout(TIMEOUT_MS);
+    assert!(compose.is_composing());
+    compose.check_timeout(TIMEOUT_MS + 1);
+    assert!(!compose.is_composing());
+    assert_eq!(compose.pop_key(), Some(DecodedKey::Unicode('e')));
+
+    // The key after the timeout is not composed
+    compose.feed(COMPOSE, 0);
+    compose.feed(DecodedKey::Unicode('\''), TIMEOUT_MS);
+    assert_eq!(compose.pop_key(), Some(DecodedKey::Unicode('\'')));
+    assert!(!compose.is_composing());
+}
+
+/// Description: Each pair of characters is composed to a single character (in both orders)
+fn test_table() {
+    for (index, (first, second, _)) in DEFAULT_TABLE.iter().enumerate() {
+        let duplicate = DEFAULT_TABLE[index + 1..].iter()
+            .any(|(a, b, _)| (a == first && b == second) || (a == second && b == first));
+        assert!(!duplicate, "compose: duplicate entry for '{}' and '{}'", first, second);
+    }
+}
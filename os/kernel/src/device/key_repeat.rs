@@ -0,0 +1,96 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: key_repeat                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Software key repeat (typematic) of the terminal: after a key    ║
+   ║         has been held for the repeat delay, the key is repeated every   ║
+   ║         repeat interval until it is released. Only the most recently    ║
+   ║         pressed key repeats, modifier and lock keys are not repeated.   ║
+   ║         Repeats of the keyboard hardware are dropped, so the timing can ║
+   ║         be changed with `set_key_repeat()`.                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use pc_keyboard::{DecodedKey, KeyCode};
+use crate::device::compose::COMPOSE_KEY;
+
+/// Time a key has to be held until it is repeated, unless changed with `set_key_repeat()`
+pub const DEFAULT_DELAY_MS: usize = 500;
+/// Time between two repeats of a held key, unless changed with `set_key_repeat()`
+pub const DEFAULT_INTERVAL_MS: usize = 33;
+
+/// Key repeat state of a keyboard (see `press()`, `release()` and `poll()`)
+pub struct KeyRepeat {
+    delay_ms: usize,
+    interval_ms: usize,       // 0 = keys are not repeated
+    held: Option<HeldKey>,    // most recently pressed key, while it is held
+}
+
+struct HeldKey {
+    code: KeyCode,
+    key: Option<DecodedKey>,  // `None` for keys, that are not repeated (see `repeats()`, or repeating is disabled)
+    next_ms: usize,           // system time of the next repeat
+}
+
+impl KeyRepeat {
+    pub const fn new(delay_ms: usize, interval_ms: usize) -> Self {
+        Self { delay_ms, interval_ms, held: None }
+    }
+
+    /// Description: Repeat held keys after `delay_ms` every `interval_ms` (0 = never), starting with the next key press
+    pub fn set_timing(&mut self, delay_ms: usize, interval_ms: usize) {
+        self.delay_ms = delay_ms;
+        self.interval_ms = interval_ms;
+        self.held = None;
+    }
+
+    /// Description: `code` is the held key (a key down event for it is a repeat of the keyboard hardware)
+    pub fn is_held(&self, code: KeyCode) -> bool {
+        self.held.as_ref().is_some_and(|held| held.code == code)
+    }
+
+    /// Description: The key `code` decoded to `key` has been pressed at `now_ms` (it takes over repeating from a held key)
+    pub fn press(&mut self, code: KeyCode, key: DecodedKey, now_ms: usize) {
+        let key = Some(key).filter(|key| self.interval_ms > 0 && repeats(*key));
+        self.held = Some(HeldKey { code, key, next_ms: now_ms.saturating_add(self.delay_ms) });
+    }
+
+    /// Description: The key `code` has been released (other keys, which are still held, do not start repeating again)
+    pub fn release(&mut self, code: KeyCode) {
+        if self.is_held(code) {
+            self.held = None;
+        }
+    }
+
+    /// Description: Forget the held key (e.g. when the keyboard layout changes)
+    pub fn release_all(&mut self) {
+        self.held = None;
+    }
+
+    ///
+    /// Description:
+    ///    Return the held key, if it is due to be repeated at `now_ms` (`None` otherwise).
+    ///    If the key has not been polled for several intervals, it is repeated only once.
+    ///
+    pub fn poll(&mut self, now_ms: usize) -> Option<DecodedKey> {
+        let interval_ms = self.interval_ms;
+        let held = self.held.as_mut()?;
+        if held.key.is_none() || now_ms < held.next_ms {
+            return None;
+        }
+
+        held.next_ms += interval_ms;
+        if held.next_ms <= now_ms {
+            held.next_ms = now_ms + interval_ms;
+        }
+
+        held.key
+    }
+}
+
+/// Description: `key` is repeated, while it is held (modifier keys are not repeated, lock and compose keys would toggle again)
+fn repeats(key: DecodedKey) -> bool {
+    !matches!(key, DecodedKey::RawKey(KeyCode::LShift | KeyCode::RShift | KeyCode::LControl | KeyCode::RControl | KeyCode::LAlt
+        | KeyCode::RAltGr | KeyCode::LWin | KeyCode::RWin | KeyCode::CapsLock | KeyCode::NumpadLock | KeyCode::ScrollLock
+        | COMPOSE_KEY))
+}
@@ -0,0 +1,96 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: key_repeat_tests                                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the timing of the software key repeat: the delay before    ║
+   ║         the first repeat, the interval between repeats, releasing the   ║
+   ║         key and a second key taking over repeating from a held key.     ║
+   ║         Lock keys are held, but not repeated.                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use pc_keyboard::{DecodedKey, KeyCode};
+
+use crate::device::key_repeat::KeyRepeat;
+
+const DELAY_MS: usize = 500;
+const INTERVAL_MS: usize = 50;
+
+///
+/// Description: Run all key repeat tests
+///
+pub fn run_tests() {
+    info!("key_repeat: running tests");
+
+    test_delay_and_interval();
+    test_release();
+    test_second_key();
+    test_disabled();
+    test_lock_key();
+
+    info!("key_repeat: all tests passed.");
+}
+
+/// Description: The held key is repeated after the delay and then every interval (missed repeats are not caught up)
+fn test_delay_and_interval() {
+    let mut repeat = KeyRepeat::new(DELAY_MS, INTERVAL_MS);
+    repeat.press(KeyCode::A, DecodedKey::Unicode('a'), 1000);
+    assert!(repeat.is_held(KeyCode::A));
+
+    assert_eq!(repeat.poll(1000), None);
+    assert_eq!(repeat.poll(1000 + DELAY_MS - 1), None);
+    assert_eq!(repeat.poll(1000 + DELAY_MS), Some(DecodedKey::Unicode('a')));
+    assert_eq!(repeat.poll(1000 + DELAY_MS), None);
+    assert_eq!(repeat.poll(1000 + DELAY_MS + INTERVAL_MS), Some(DecodedKey::Unicode('a')));
+
+    assert_eq!(repeat.poll(1000 + DELAY_MS + 10 * INTERVAL_MS), Some(DecodedKey::Unicode('a')));
+    assert_eq!(repeat.poll(1000 + DELAY_MS + 10 * INTERVAL_MS + 1), None);
+}
+
+/// Description: Releasing the held key stops repeating, releasing another key does not
+fn test_release() {
+    let mut repeat = KeyRepeat::new(DELAY_MS, INTERVAL_MS);
+    repeat.press(KeyCode::A, DecodedKey::Unicode('a'), 0);
+
+    repeat.release(KeyCode::B);
+    assert_eq!(repeat.poll(DELAY_MS), Some(DecodedKey::Unicode('a')));
+    repeat.release(KeyCode::A);
+    assert!(!repeat.is_held(KeyCode::A));
+    assert_eq!(repeat.poll(DELAY_MS + INTERVAL_MS), None);
+}
+
+/// Description: A key pressed while another one is held takes over repeating (with a new delay)
+fn test_second_key() {
+    let mut repeat = KeyRepeat::new(DELAY_MS, INTERVAL_MS);
+    repeat.press(KeyCode::A, DecodedKey::Unicode('a'), 0);
+    assert_eq!(repeat.poll(DELAY_MS), Some(DecodedKey::Unicode('a')));
+
+    repeat.press(KeyCode::B, DecodedKey::Unicode('b'), DELAY_MS + 1);
+    assert!(!repeat.is_held(KeyCode::A));
+    assert_eq!(repeat.poll(DELAY_MS + INTERVAL_MS), None);
+    assert_eq!(repeat.poll(2 * DELAY_MS + 1), Some(DecodedKey::Unicode('b')));
+
+    // Releasing the first key does not stop the second one, releasing the second one does not restart the first one
+    repeat.release(KeyCode::A);
+    assert_eq!(repeat.poll(2 * DELAY_MS + 1 + INTERVAL_MS), Some(DecodedKey::Unicode('b')));
+    repeat.release(KeyCode::B);
+    assert_eq!(repeat.poll(3 * DELAY_MS), None);
+}
+
+/// Description: With an interval of 0, keys are not repeated (but repeats of the keyboard hardware are still recognized)
+fn test_disabled() {
+    let mut repeat = KeyRepeat::new(DELAY_MS, INTERVAL_MS);
+    repeat.set_timing(DELAY_MS, 0);
+    repeat.press(KeyCode::A, DecodedKey::Unicode('a'), 0);
+    assert!(repeat.is_held(KeyCode::A));
+    assert_eq!(repeat.poll(10 * DELAY_MS), None);
+}
+
+/// Description: A held lock key is not repeated, but its repeats by the keyboard hardware are recognized
+fn test_lock_key() {
+    let mut repeat = KeyRepeat::new(DELAY_MS, INTERVAL_MS);
+    repeat.press(KeyCode::CapsLock, DecodedKey::RawKey(KeyCode::CapsLock), 0);
+    assert!(repeat.is_held(KeyCode::CapsLock));
+    assert_eq!(repeat.poll(10 * DELAY_MS), None);
+}
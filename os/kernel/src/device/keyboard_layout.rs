@@ -0,0 +1,44 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: keyboard_layout                                                 ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Scancode translation tables of the keyboard layouts (see        ║
+   ║         `syscall::keyboard::KeyboardLayout`). Each layout is an entry   ║
+   ║         in `LAYOUTS`, so adding a layout means adding a table entry.    ║
+   ║         The tables themselves are provided by `pc_keyboard`. Dead keys  ║
+   ║         are not supported.                                              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use pc_keyboard::layouts::{AnyLayout, De105Key, Us104Key};
+use pc_keyboard::{DecodedKey, HandleControl, KeyCode, Keyboard, ScancodeSet1};
+use ps2::flags::KeyboardLedFlags;
+use syscall::keyboard::KeyboardLayout;
+
+/// Layout of the terminal after booting
+pub const DEFAULT_LAYOUT: KeyboardLayout = KeyboardLayout::De;
+
+/// Lock keys, that are on in a new decoder (`pc_keyboard` starts with Num Lock on)
+pub const INITIAL_LEDS: KeyboardLedFlags = KeyboardLedFlags::NUM_LOCK;
+
+/// Translation table of each layout
+const LAYOUTS: [(KeyboardLayout, fn() -> AnyLayout); 2] = [
+    (KeyboardLayout::Us, || AnyLayout::Us104Key(Us104Key)),
+    (KeyboardLayout::De, || AnyLayout::De105Key(De105Key)),
+];
+
+/// Description: Decoder translating scancodes (set 1, as sent by the PS/2 controller) into keys of `layout`
+pub fn decoder(layout: KeyboardLayout) -> Keyboard<AnyLayout, ScancodeSet1> {
+    let table = LAYOUTS.iter().find(|(entry, _)| *entry == layout).map(|(_, table)| table()).expect("Keyboard layout without translation table");
+    Keyboard::new(ScancodeSet1::new(), table, HandleControl::Ignore)
+}
+
+/// Description: LED of the lock key decoded to `key` (the decoder toggles Caps Lock and Num Lock) or `None` for other keys
+pub fn lock_led(key: DecodedKey) -> Option<KeyboardLedFlags> {
+    match key {
+        DecodedKey::RawKey(KeyCode::CapsLock) => Some(KeyboardLedFlags::CAPS_LOCK),
+        DecodedKey::RawKey(KeyCode::NumpadLock) => Some(KeyboardLedFlags::NUM_LOCK),
+        DecodedKey::RawKey(KeyCode::ScrollLock) => Some(KeyboardLedFlags::SCROLL_LOCK),
+        _ => None,
+    }
+}
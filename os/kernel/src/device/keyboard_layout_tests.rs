@@ -0,0 +1,90 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: keyboard_layout_tests                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the translation of a scancode sequence under the US and    ║
+   ║         the German layout (swapped `y` and `z`, umlauts and shifted     ║
+   ║         keys), as well as the lock keys (Caps Lock, Num Lock) and their ║
+   ║         LEDs.                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::String;
+use ::log::info;
+use pc_keyboard::{DecodedKey, KeyCode};
+use ps2::flags::KeyboardLedFlags;
+use syscall::keyboard::{KeyboardLayout, LAYOUT_NAMES};
+
+use crate::device::keyboard_layout::{decoder, lock_led, INITIAL_LEDS};
+
+/// Scancodes (set 1) of the keys labeled Y, Z, semicolon, apostrophe, left bracket and minus on a US keyboard
+/// (pressed and released), followed by the apostrophe key with shift pressed
+const SEQUENCE: [u8; 16] = [
+    0x15, 0x95, 0x2c, 0xac, 0x27, 0xa7, 0x28, 0xa8, 0x1a, 0x9a, 0x0c, 0x8c,
+    0x2a, 0x28, 0xa8, 0xaa,
+];
+
+///
+/// Description: Run all keyboard layout tests
+///
+pub fn run_tests() {
+    info!("keyboard_layout: running tests");
+
+    test_us();
+    test_de();
+    test_names();
+    test_lock_keys();
+
+    info!("keyboard_layout: all tests passed.");
+}
+
+/// Description: Translate `scancodes` with `layout` into the typed characters
+fn translate(layout: KeyboardLayout, scancodes: &[u8]) -> String {
+    let mut decoder = decoder(layout);
+    let mut text = String::new();
+
+    for scancode in scancodes {
+        if let Ok(Some(event)) = decoder.add_byte(*scancode)
+            && let Some(DecodedKey::Unicode(c)) = decoder.process_keyevent(event) {
+            text.push(c);
+        }
+    }
+
+    text
+}
+
+fn test_us() {
+    assert_eq!(translate(KeyboardLayout::Us, &SEQUENCE), "yz;'[-\"");
+}
+
+/// Description: QWERTZ swaps 'y' and 'z' and has the umlauts right of 'l' and 'p'
+fn test_de() {
+    assert_eq!(translate(KeyboardLayout::De, &SEQUENCE), "zyöäüßÄ");
+}
+
+/// Description: Every layout has a name and a translation table
+fn test_names() {
+    for (name, layout) in LAYOUT_NAMES {
+        assert_eq!(KeyboardLayout::from_name(name), Some(layout));
+        assert_eq!(layout.name(), name);
+        assert!(!translate(layout, &[0x1e, 0x9e]).is_empty()); // 'a' on all layouts
+    }
+
+    assert_eq!(KeyboardLayout::from_name("xx"), None);
+}
+
+/// Description: Caps Lock and Num Lock change the translation and toggle their LEDs
+fn test_lock_keys() {
+    // Caps Lock (0x3a) pressed twice around 'a' (0x1e)
+    assert_eq!(translate(KeyboardLayout::Us, &[0x3a, 0xba, 0x1e, 0x9e, 0x3a, 0xba, 0x1e, 0x9e]), "Aa");
+
+    // Numpad 7 (0x47) is a digit with Num Lock on (initial state) and Home after pressing Num Lock (0x45)
+    assert!(INITIAL_LEDS.contains(KeyboardLedFlags::NUM_LOCK));
+    assert_eq!(translate(KeyboardLayout::Us, &[0x47, 0xc7]), "7");
+    assert_eq!(translate(KeyboardLayout::Us, &[0x45, 0xc5, 0x47, 0xc7]), "");
+
+    assert_eq!(lock_led(DecodedKey::RawKey(KeyCode::CapsLock)), Some(KeyboardLedFlags::CAPS_LOCK));
+    assert_eq!(lock_led(DecodedKey::RawKey(KeyCode::NumpadLock)), Some(KeyboardLedFlags::NUM_LOCK));
+    assert_eq!(lock_led(DecodedKey::RawKey(KeyCode::ScrollLock)), Some(KeyboardLedFlags::SCROLL_LOCK));
+    assert_eq!(lock_led(DecodedKey::Unicode('a')), None);
+}
@@ -1,32 +1,57 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
 use alloc::format;
+use alloc::string::String;
 use alloc::sync::Arc;
-use crate::device::terminal::Terminal;
+use crate::device::line_editor::{EditResult, LineEditor};
+use crate::device::terminal::{Terminal, TerminalBackend};
 use graphic::ansi::COLOR_TABLE_256;
-use graphic::buffered_lfb::BufferedLFB;
-use graphic::color::{Color, INVISIBLE};
+use graphic::buffered_lfb::{BufferedLFB, Rect};
+use graphic::color::{Color, PixelFormat, INVISIBLE};
 use graphic::lfb::LFB;
 use graphic::{color, lfb};
 use stream::{InputStream, OutputStream};
 use alloc::vec::Vec;
 use anstyle_parse::{Params, ParamsIter, Parser, Perform, Utf8Parser};
 use core::cell::RefCell;
-use core::mem::size_of;
+use core::mem::{replace, size_of};
 use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
 use chrono::TimeDelta;
-use pc_keyboard::layouts::{AnyLayout, De105Key};
-use pc_keyboard::{DecodedKey, HandleControl, Keyboard, ScancodeSet1};
-use spin::Mutex;
-use crate::{built_info, efi_system_table, keyboard, process_manager, scheduler, speaker, timer};
+use pc_keyboard::layouts::AnyLayout;
+use pc_keyboard::{DecodedKey, KeyCode, KeyState, Keyboard, ScancodeSet1};
+use ps2::flags::KeyboardLedFlags;
+use spin::{Mutex, Once};
+use syscall::keyboard::KeyboardLayout;
+use crate::device::compose::{self, Compose};
+use crate::device::key_repeat::{self, KeyRepeat};
+use crate::device::keyboard_layout;
+use crate::device::serial::SerialPort;
+use crate::{built_info, clock, efi_system_table, keyboard, process_manager, scheduler, speaker, timer};
 
 const CURSOR: char = if let Some(cursor) = char::from_u32(0x2588) { cursor } else { '_' };
-const TAB_SPACES: u16 = 8;
+/// Tab stops are every `DEFAULT_TAB_WIDTH` columns, unless changed with `set_tab_width()`
+const DEFAULT_TAB_WIDTH: u16 = 8;
 const CURSOR_UPDATE_INTERVAL: usize = 250;
+/// The screen is blanked after this time without keyboard input, unless changed with `set_blank_timeout()`
+const DEFAULT_BLANK_TIMEOUT_MS: usize = 10 * 60 * 1000;
+pub(crate) const SCROLLBACK_ROWS: usize = 500;
+/// Minimum terminal size, the font scale is selected for (the largest scale, which still gives this size)
+const MIN_COLUMNS: u16 = 120;
+const MIN_ROWS: u16 = 30;
+/// Serial input bytes, that follow each other within this time, belong to the same burst (see `read_serial_burst()`)
+const PASTE_GAP_NS: u64 = 2_000_000;
+/// Bursts of at least this many bytes are pasted text (shorter ones are typed keys or escape sequences of special keys)
+pub(crate) const PASTE_MIN_BYTES: usize = 8;
 
 struct CursorState {
     pos: (u16, u16),
     saved_pos: (u16, u16),
+    visible: bool, // the blinking cursor is drawn (see 'hide_cursor()')
 }
 
+#[derive(Clone)]
 struct ColorState {
     fg_color: Color,
     bg_color: Color,
@@ -39,10 +64,37 @@ struct ColorState {
     dim: bool,
 }
 
+/// Backend drawing the glyphs of the terminal font to a linear framebuffer
+pub struct LfbBackend {
+    size: (u16, u16),
+    scale: u32,                        // font scale (each glyph pixel is drawn as `scale` x `scale` pixels)
+    char_size: (u32, u32),             // size of a character on the screen (in pixels, scaled)
+    lfb: BufferedLFB,                  // back buffer always contains the live screen
+}
+
 struct DisplayState {
     size: (u16, u16),
-    lfb: BufferedLFB,
+    backend: Box<dyn TerminalBackend>,
+    char_buffer: Vec<Character>,
+    history: VecDeque<Vec<Character>>, // rows scrolled off the screen, oldest first (at most SCROLLBACK_ROWS)
+    scroll_offset: usize,              // number of rows the view is scrolled back (0 = live screen)
+    tab_width: u16,                    // columns between two tab stops
+    blanked: bool,                     // the screen saver has cleared the screen (the back buffer keeps the live screen)
+    primary: Option<PrimaryScreen>,    // saved primary screen, while the alternate screen is shown
+}
+
+/// Primary screen, saved while a full screen program uses the alternate screen (`ESC[?1049h`)
+struct PrimaryScreen {
     char_buffer: Vec<Character>,
+    cursor_pos: (u16, u16),
+    saved_cursor_pos: (u16, u16),
+    color: ColorState,
+}
+
+struct ScreenSaverState {
+    timeout_ms: usize,                 // blank the screen after this time without input (0 = never)
+    idle_since_ms: usize,              // system time of the last keyboard input (or of setting the timeout)
+    received_bytes: usize,             // scancodes received by the keyboard at the last check
 }
 
 pub struct LFBTerminal {
@@ -51,6 +103,23 @@ pub struct LFBTerminal {
     color: Mutex<ColorState>,
     parser: Mutex<RefCell<Parser>>,
     decoder: Mutex<Keyboard<AnyLayout, ScancodeSet1>>,
+    layout: Mutex<KeyboardLayout>,     // layout of 'decoder' (only changed, while holding the lock of 'decoder')
+    input: Mutex<VecDeque<u8>>,        // decoded input bytes, not yet consumed by 'read_line()'
+    line_editor: Mutex<LineEditor>,    // keeps the history between calls of 'read_line()'
+    shift: AtomicBool,                 // shift key is pressed (for Shift+PageUp/PageDown)
+    key_repeat: Mutex<KeyRepeat>,      // held key (only locked, while holding the lock of 'decoder')
+    leds: Mutex<KeyboardLedFlags>,     // lock keys, that are on (only locked, while holding the lock of 'decoder')
+    compose: Mutex<Compose>,           // compose sequence and composed keys (only locked, while holding the lock of 'decoder')
+    serial: Once<Arc<SerialPort>>,     // second input source (see 'attach_serial()')
+    serial_escape: AtomicBool,         // inside an escape sequence received by the serial port (not echoed)
+    bracketed_paste: AtomicBool,       // pasted input is wrapped in 'ESC[200~' and 'ESC[201~' (set by 'ESC[?2004h')
+    screen_saver: Mutex<ScreenSaverState>, // locked before 'display'
+}
+
+/// Input of the terminal
+enum Input {
+    Key(DecodedKey), // key pressed on the PS/2 keyboard
+    Serial(u8),      // byte received by the serial port (already echoed to the serial port)
 }
 
 pub struct CursorThread {
@@ -67,7 +136,7 @@ struct Character {
 
 impl CursorState {
     pub const fn new() -> Self {
-        Self { pos: (0, 1), saved_pos: (0, 1) }
+        Self { pos: (0, 1), saved_pos: (0, 1), visible: true }
     }
 }
 
@@ -78,21 +147,144 @@ impl ColorState {
     }
 }
 
-impl DisplayState {
-    pub fn new(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8) -> Self {
-        let raw_lfb = LFB::new(buffer, pitch, width, height, bpp);
-        let mut lfb = BufferedLFB::new(raw_lfb);
-        let size = ((width / lfb::DEFAULT_CHAR_WIDTH) as u16, (height / lfb::DEFAULT_CHAR_HEIGHT) as u16);
+/// Description: Number of columns and rows of a terminal with `width` x `height` pixels and a font scaled by `scale`
+pub(crate) fn text_size(width: u32, height: u32, scale: u32) -> (u16, u16) {
+    ((width / (lfb::DEFAULT_CHAR_WIDTH * scale)) as u16, (height / (lfb::DEFAULT_CHAR_HEIGHT * scale)) as u16)
+}
+
+/// Description: Largest font scale, which still gives at least `MIN_COLUMNS` x `MIN_ROWS` characters (1 for small screens)
+pub(crate) fn auto_font_scale(width: u32, height: u32) -> u32 {
+    (1..=lfb::MAX_FONT_SCALE).rev()
+        .find(|&scale| {
+            let size = text_size(width, height, scale);
+            size.0 >= MIN_COLUMNS && size.1 >= MIN_ROWS
+        })
+        .unwrap_or(1)
+}
+
+impl LfbBackend {
+    /// Description: Create a backend, drawing each glyph pixel as `scale` x `scale` pixels (1 ..= `lfb::MAX_FONT_SCALE`)
+    pub fn new(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8, format: PixelFormat, scale: u32) -> Self {
+        assert!((1..=lfb::MAX_FONT_SCALE).contains(&scale), "LFBTerminal: Invalid font scale [{}]!", scale);
+
+        let mut lfb = LFB::new_double_buffered(buffer, pitch, width, height, bpp, format);
+        lfb.lfb().clear();
+        lfb.flush();
+
+        let size = text_size(width, height, scale);
+        let char_size = (lfb::DEFAULT_CHAR_WIDTH * scale, lfb::DEFAULT_CHAR_HEIGHT * scale);
+        Self { size, scale, char_size, lfb }
+    }
 
+    /// Description: Mark `count` rows, starting at `row`, as changed
+    fn mark_rows(&mut self, row: u16, count: u16) {
+        self.lfb.mark_dirty(row as u32 * self.char_size.1, count as u32 * self.char_size.1);
+    }
+
+    /// Description: Mark `size` cells, starting at `pos`, as changed (only these pixels are copied to the screen)
+    fn mark_cells(&mut self, pos: (u16, u16), size: (u16, u16)) {
+        let char_size = self.char_size;
+        self.lfb.mark_dirty_rect(Rect::new(pos.0 as u32 * char_size.0, pos.1 as u32 * char_size.1, size.0 as u32 * char_size.0, size.1 as u32 * char_size.1));
+    }
+}
+
+impl TerminalBackend for LfbBackend {
+    fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    fn draw_char(&mut self, pos: (u16, u16), c: char, fg_color: Color, bg_color: Color) -> u16 {
+        let width = self.lfb.lfb().draw_char_scaled(pos.0 as u32 * self.char_size.0, pos.1 as u32 * self.char_size.1, self.scale, self.scale, fg_color, bg_color, c);
+        let columns = width.div_ceil(lfb::DEFAULT_CHAR_WIDTH) as u16; // glyph width is not scaled
+        self.mark_cells(pos, (columns.max(1), 1));
+
+        columns
+    }
+
+    fn draw_char_direct(&mut self, pos: (u16, u16), c: char, fg_color: Color, bg_color: Color) {
+        let (scale, char_size) = (self.scale, self.char_size);
+        self.lfb.direct_lfb().draw_char_scaled(pos.0 as u32 * char_size.0, pos.1 as u32 * char_size.1, scale, scale, fg_color, bg_color, c);
+    }
+
+    fn fill(&mut self, pos: (u16, u16), size: (u16, u16), bg_color: Color) {
+        let char_size = self.char_size;
+        self.lfb.lfb().fill_rect(pos.0 as u32 * char_size.0, pos.1 as u32 * char_size.1, size.0 as u32 * char_size.0, size.1 as u32 * char_size.1, bg_color);
+        self.mark_cells(pos, size);
+    }
+
+    fn scroll_up(&mut self, bg_color: Color) {
+        let (size, char_size) = (self.size, self.char_size);
+        self.lfb.lfb().scroll_up(char_size.1);
+        self.lfb.lfb().fill_rect(0, (size.1 - 1) as u32 * char_size.1, size.0 as u32 * char_size.0, char_size.1, bg_color);
+        self.mark_rows(0, size.1);
+    }
+
+    fn draw_status_bar(&mut self, info: &str, date: Option<&str>) {
+        let (size, scale, char_size) = (self.size, self.scale, self.char_size);
+
+        // Draw background
+        for i in 0..size.0 as u32 * char_size.0 {
+            for j in 0..char_size.1 {
+                self.lfb.lfb().draw_pixel(i, j, color::HHU_GREEN);
+            }
+        }
+
+        self.lfb.lfb().draw_string_scaled(0, 0, scale, scale, color::HHU_BLUE, color::INVISIBLE, info);
+        if let Some(date) = date {
+            self.lfb.lfb().draw_string_scaled((size.0 as u32).saturating_sub(date.len() as u32) * char_size.0, 0, scale, scale, color::HHU_BLUE, color::INVISIBLE, date);
+        }
+
+        self.mark_rows(0, 1);
+    }
+
+    fn flush(&mut self) {
+        self.lfb.present();
+    }
+
+    fn restore(&mut self) {
+        self.lfb.flush();
+    }
+
+    fn blank(&mut self) {
+        self.lfb.direct_lfb().clear();
+    }
+
+    fn flushed_bytes(&self) -> usize {
+        self.lfb.flushed_bytes()
+    }
+
+    fn screenshot(&mut self) -> Vec<u8> {
+        self.lfb.lfb().to_ppm()
+    }
+}
+
+impl DisplayState {
+    pub fn new(backend: Box<dyn TerminalBackend>) -> Self {
+        let size = backend.size();
         let mut char_buffer = Vec::with_capacity(size.0 as usize * size.1 as usize * size_of::<Character>());
         for _ in 0..char_buffer.capacity() {
             char_buffer.push(Character { value: ' ', fg_color: color::WHITE, bg_color: color::BLACK });
         }
 
-        lfb.lfb().clear();
-        lfb.flush();
+        Self { size, backend, char_buffer, history: VecDeque::with_capacity(SCROLLBACK_ROWS), scroll_offset: 0, tab_width: DEFAULT_TAB_WIDTH, blanked: false, primary: None }
+    }
 
-        Self { size, lfb, char_buffer }
+    ///
+    /// Description:
+    ///    Copy changes of the live screen to the screen and move the hardware cursor (if any)
+    ///    to `cursor`, unless the view is scrolled back (the hardware cursor is disabled then).
+    ///
+    fn flush(&mut self, cursor: &CursorState) {
+        if self.blanked {
+            return; // changes are copied, when the screen is restored
+        }
+
+        if self.scroll_offset == 0 {
+            self.backend.flush();
+            self.backend.set_hardware_cursor(if cursor.visible { Some(cursor.pos) } else { None });
+        } else {
+            self.backend.set_hardware_cursor(None);
+        }
     }
 }
 
@@ -113,24 +305,17 @@ impl CursorThread {
 
             // CAUTION: This only works because LFBTerminal is the only implementation of Terminal
             let terminal = unsafe { (ptr::from_ref(self.terminal.as_ref()) as *const LFBTerminal).as_ref().unwrap() };
+            terminal.check_idle(timer().systime_ms(), keyboard().received_bytes());
 
-            let mut display = terminal.display.lock();
-            let cursor = terminal.cursor.lock();
-            let character = display.char_buffer[(cursor.pos.1 * display.size.0 + cursor.pos.0) as usize];
-
-            let draw_character = match self.visible {
-                true => match character.value {
-                    '\0' => ' ',
-                    value => value
-                }
-                false => CURSOR
-            };
-
-            display.lfb.direct_lfb().draw_char(cursor.pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, cursor.pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, character.fg_color, character.bg_color, draw_character);
+            // Alternate between the cursor and the character at its position
+            terminal.blink_cursor(!self.visible);
             self.visible = !self.visible;
 
+            let mut display = terminal.display.lock();
             if sleep_counter >= 1000 {
+                let cursor = terminal.cursor.lock();
                 LFBTerminal::draw_status_bar(&mut display);
+                display.flush(&cursor);
                 sleep_counter = 0;
             }
         }
@@ -148,6 +333,7 @@ impl OutputStream for LFBTerminal {
         // so it is safe to just construct a mutable reference here.
         unsafe { parser.borrow_mut().advance(ptr::from_ref(self).cast_mut().as_mut().unwrap(), b); }
         self.parser.lock().swap(&parser);
+        self.flush();
     }
 
     fn write_str(&self, string: &str) {
@@ -160,35 +346,26 @@ impl OutputStream for LFBTerminal {
         }
 
         self.parser.lock().swap(&parser);
+        self.flush();
     }
 }
 
 impl InputStream for LFBTerminal {
+    /// Description: Return the next input byte and echo it. Typed characters are returned as UTF-8 (one byte per call).
     fn read_byte(&self) -> i16 {
-        let keyboard = keyboard();
-        let read_byte;
-
-        loop {
-            let mut decoder = self.decoder.lock();
-            let scancode = keyboard.read_byte();
-            if scancode == -1 {
-                panic!("Keyboard stream closed!");
+        let read_byte = loop {
+            if let Some(byte) = self.input.lock().pop_front() {
+                break byte;
             }
 
-            if let Ok(Some(event)) = decoder.add_byte(scancode as u8) {
-                if let Some(key) = decoder.process_keyevent(event) {
-                    match key {
-                        DecodedKey::Unicode(c) => {
-                            read_byte = c;
-                            break;
-                        }
-                        _ => {}
-                    }
-                }
+            match self.read_any() {
+                Input::Key(DecodedKey::Unicode(c)) => self.input.lock().extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                Input::Serial(byte) => self.read_serial_burst(byte, &mut self.input.lock()),
+                _ => {}
             }
-        }
+        };
 
-        self.write_byte(read_byte as u8);
+        self.write_byte(read_byte);
         read_byte as i16
     }
 }
@@ -201,20 +378,599 @@ impl Terminal for LFBTerminal {
 
         LFBTerminal::clear_screen(&mut display, &mut color);
         LFBTerminal::position(&mut display, &mut cursor, &mut color, (0, 0));
+        display.flush(&cursor);
+    }
+
+    fn set_cursor(&self, x: u16, y: u16) {
+        let mut display = self.display.lock();
+        let mut cursor = self.cursor.lock();
+        let mut color = self.color.lock();
+
+        LFBTerminal::position_clamped(&mut display, &mut cursor, &mut color, (x, y));
+        display.flush(&cursor);
+    }
+
+    fn hide_cursor(&self) {
+        let mut display = self.display.lock();
+        let mut cursor = self.cursor.lock();
+
+        cursor.visible = false;
+        LFBTerminal::draw_cursor_cell(&mut display, &cursor, false); // remove a cursor drawn by the blinking thread
+        display.flush(&cursor);
+    }
+
+    fn show_cursor(&self) {
+        let mut display = self.display.lock();
+        let mut cursor = self.cursor.lock();
+
+        cursor.visible = true;
+        display.flush(&cursor);
+    }
+
+    fn set_tab_width(&self, width: u16) {
+        self.display.lock().tab_width = width.max(1);
+    }
+
+    fn set_keyboard_layout(&self, layout: KeyboardLayout) -> KeyboardLayout {
+        let mut decoder = self.decoder.lock();
+        *decoder = keyboard_layout::decoder(layout);
+        self.shift.store(false, Relaxed);
+        self.key_repeat.lock().release_all();
+
+        // The new decoder starts with the initial lock states
+        let mut leds = self.leds.lock();
+        if *leds != keyboard_layout::INITIAL_LEDS {
+            *leds = keyboard_layout::INITIAL_LEDS;
+            keyboard().set_leds(*leds);
+        }
+
+        replace(&mut *self.layout.lock(), layout)
+    }
+
+    fn set_key_repeat(&self, delay_ms: usize, interval_ms: usize) {
+        let _decoder = self.decoder.lock();
+        self.key_repeat.lock().set_timing(delay_ms, interval_ms);
+    }
+
+    fn set_blank_timeout(&self, timeout_ms: usize) {
+        let mut screen_saver = self.screen_saver.lock();
+        screen_saver.timeout_ms = timeout_ms;
+        screen_saver.idle_since_ms = timer().systime_ms();
+
+        if timeout_ms == 0 {
+            self.unblank();
+        }
+    }
+
+    fn read_line(&self) -> String {
+        let mut editor = self.line_editor.lock();
+        let mut start = self.cursor_index();
+
+        loop {
+            match editor.feed(self.read_input()) {
+                EditResult::None => {}
+                EditResult::Redraw => start = self.redraw_line(start, &editor),
+                EditResult::Done(line) => {
+                    self.set_cursor_index(start + line.chars().count());
+                    self.write_byte(b'\n');
+                    return line;
+                }
+            }
+        }
     }
 }
 
 impl LFBTerminal {
-    pub fn new(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8) -> Self {
+    /// Description: Create a terminal with a font scale selected for the resolution (see `auto_font_scale()`)
+    pub fn new(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8, format: PixelFormat) -> Self {
+        LFBTerminal::with_font_scale(buffer, pitch, width, height, bpp, format, auto_font_scale(width, height))
+    }
+
+    /// Description: Create a terminal, drawing each glyph pixel as `scale` x `scale` pixels (1 ..= `lfb::MAX_FONT_SCALE`)
+    pub fn with_font_scale(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8, format: PixelFormat, scale: u32) -> Self {
+        LFBTerminal::with_backend(Box::new(LfbBackend::new(buffer, pitch, width, height, bpp, format, scale)))
+    }
+
+    /// Description: Create a terminal drawing to `backend` (e.g. a `VgaTextBackend`, if there is no linear framebuffer)
+    pub fn with_backend(backend: Box<dyn TerminalBackend>) -> Self {
         Self {
-            display: Mutex::new(DisplayState::new(buffer, pitch, width, height, bpp)),
+            display: Mutex::new(DisplayState::new(backend)),
             cursor: Mutex::new(CursorState::new()),
             color: Mutex::new(ColorState::new()),
             parser: Mutex::new(RefCell::new(Parser::<Utf8Parser>::new())),
-            decoder: Mutex::new(Keyboard::new(ScancodeSet1::new(), AnyLayout::De105Key(De105Key), HandleControl::Ignore))
+            decoder: Mutex::new(keyboard_layout::decoder(keyboard_layout::DEFAULT_LAYOUT)),
+            layout: Mutex::new(keyboard_layout::DEFAULT_LAYOUT),
+            input: Mutex::new(VecDeque::new()),
+            line_editor: Mutex::new(LineEditor::new()),
+            shift: AtomicBool::new(false),
+            key_repeat: Mutex::new(KeyRepeat::new(key_repeat::DEFAULT_DELAY_MS, key_repeat::DEFAULT_INTERVAL_MS)),
+            leds: Mutex::new(keyboard_layout::INITIAL_LEDS),
+            compose: Mutex::new(Compose::new(&compose::DEFAULT_TABLE)),
+            serial: Once::new(),
+            serial_escape: AtomicBool::new(false),
+            bracketed_paste: AtomicBool::new(false),
+            screen_saver: Mutex::new(ScreenSaverState { timeout_ms: DEFAULT_BLANK_TIMEOUT_MS, idle_since_ms: 0, received_bytes: 0 }),
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Use `serial` as additional input source: bytes received by the serial port are read
+    ///    like typed keys (e.g. for a QEMU `-serial stdio` connection) and echoed to the port.
+    ///    Can only be called once.
+    ///
+    pub fn attach_serial(&self, serial: Arc<SerialPort>) {
+        self.serial.call_once(|| serial);
+    }
+
+    ///
+    /// Description:
+    ///    Block until a key has been pressed or a byte has been received by the serial port
+    ///    and return it. Both sources are polled, the CPU is given up while there is no input.
+    ///
+    fn read_any(&self) -> Input {
+        loop {
+            if let Some(key) = self.poll_key() {
+                return Input::Key(key);
+            }
+            if let Some(byte) = self.poll_serial() {
+                return Input::Serial(byte);
+            }
+
+            scheduler().switch_thread_no_interrupt();
         }
     }
 
+    ///
+    /// Description:
+    ///    Decode the scancodes received by the keyboard, until a key has been pressed. If there
+    ///    are no more scancodes, return the held key, if it is due to be repeated (`None` otherwise).
+    ///    Keys are passed through the compose state machine (see `compose`), so the keys of a
+    ///    compose sequence are not returned, but the composed character.
+    ///
+    fn poll_key(&self) -> Option<DecodedKey> {
+        let keyboard = keyboard();
+
+        loop {
+            let mut decoder = self.decoder.lock();
+            let mut key_repeat = self.key_repeat.lock();
+            let mut compose = self.compose.lock();
+            if let Some(key) = compose.pop_key() {
+                return self.typed(key); // e.g. the second character of an unmapped compose sequence
+            }
+
+            let scancode = match keyboard.try_read_byte() {
+                Some(scancode) => scancode,
+                None => {
+                    let now_ms = timer().systime_ms();
+                    match key_repeat.poll(now_ms) {
+                        Some(key) => compose.feed(key, now_ms),
+                        None => compose.check_timeout(now_ms),
+                    }
+
+                    return compose.pop_key().and_then(|key| self.typed(key));
+                }
+            };
+            self.unblank(); // do not wait for the next check of the screen saver
+
+            if let Ok(Some(event)) = decoder.add_byte(scancode) {
+                if event.state == KeyState::Up {
+                    key_repeat.release(event.code);
+                }
+
+                match (event.code, event.state) {
+                    // Repeats of the keyboard hardware are replaced by our own (see 'key_repeat')
+                    (code, KeyState::Down) if key_repeat.is_held(code) => continue,
+                    (KeyCode::LShift | KeyCode::RShift, state) => self.shift.store(state == KeyState::Down, Relaxed),
+                    (KeyCode::PageUp, KeyState::Down) if self.shift.load(Relaxed) => {
+                        self.scroll_view(self.page_rows() as isize);
+                        continue;
+                    }
+                    (KeyCode::PageDown, KeyState::Down) if self.shift.load(Relaxed) => {
+                        self.scroll_view(-(self.page_rows() as isize));
+                        continue;
+                    }
+                    _ => {}
+                }
+
+                let code = event.code;
+                if let Some(key) = decoder.process_keyevent(event) {
+                    let now_ms = timer().systime_ms();
+                    key_repeat.press(code, key, now_ms);
+                    if let Some(led) = keyboard_layout::lock_led(key) {
+                        let mut leds = self.leds.lock();
+                        leds.toggle(led);
+                        keyboard.set_leds(*leds);
+                    }
+
+                    compose.feed(key, now_ms);
+                    if let Some(key) = compose.pop_key() {
+                        return self.typed(key);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Description: Return `key` (typing returns to the live screen)
+    fn typed(&self, key: DecodedKey) -> Option<DecodedKey> {
+        if let DecodedKey::Unicode(_) = key {
+            self.scroll_view(isize::MIN);
+        }
+
+        Some(key)
+    }
+
+    ///
+    /// Description:
+    ///    Return the next byte received by the attached serial port (`None` if there is none)
+    ///    and echo it to the port. A serial terminal sends '\r' for the enter key, which is
+    ///    returned as '\n'. Escape sequences (e.g. cursor keys) are not echoed.
+    ///    Must not be called, while holding a lock of the terminal: the logger writes to the
+    ///    serial port and to the terminal.
+    ///
+    fn poll_serial(&self) -> Option<u8> {
+        let serial = self.serial.get()?;
+        let byte = match serial.try_read_byte()? {
+            b'\r' => b'\n',
+            byte => byte,
+        };
+
+        if self.serial_escape.load(Relaxed) {
+            // Sequence ends with a byte in 0x40..=0x7e (except '[', which starts a CSI sequence)
+            if (0x40..=0x7e).contains(&byte) && byte != b'[' {
+                self.serial_escape.store(false, Relaxed);
+            }
+            return Some(byte);
+        }
+
+        match byte {
+            0x1b => self.serial_escape.store(true, Relaxed),
+            0x08 | 0x7f => serial.write_str("\x08 \x08"),
+            b'\n' | b'\t' => serial.write_byte(byte),
+            byte if byte >= 0x20 => serial.write_byte(byte),
+            _ => {}
+        }
+
+        Some(byte)
+    }
+
+    /// Description: Return next input byte (UTF-8), special keys are translated to ANSI escape sequences
+    fn read_input(&self) -> u8 {
+        let mut input = self.input.lock();
+
+        loop {
+            if let Some(byte) = input.pop_front() {
+                return byte;
+            }
+
+            match self.read_any() {
+                Input::Key(DecodedKey::Unicode(c)) => input.extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                Input::Key(DecodedKey::RawKey(KeyCode::ArrowUp)) => input.extend(b"\x1b[A"),
+                Input::Key(DecodedKey::RawKey(KeyCode::ArrowDown)) => input.extend(b"\x1b[B"),
+                Input::Key(DecodedKey::RawKey(KeyCode::ArrowRight)) => input.extend(b"\x1b[C"),
+                Input::Key(DecodedKey::RawKey(KeyCode::ArrowLeft)) => input.extend(b"\x1b[D"),
+                Input::Key(DecodedKey::RawKey(KeyCode::Home)) => input.extend(b"\x1b[H"),
+                Input::Key(DecodedKey::RawKey(KeyCode::End)) => input.extend(b"\x1b[F"),
+                Input::Serial(byte) => self.read_serial_burst(byte, &mut input), // already UTF-8 or ANSI escape sequences
+                _ => {}
+            }
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Append `first` (received by the serial port) to `input`. In bracketed paste mode, the
+    ///    bytes following `first` without a gap of `PASTE_GAP_NS` are read as well. A serial
+    ///    terminal has no paste event, so a burst of at least `PASTE_MIN_BYTES` bytes is taken as
+    ///    pasted text and wrapped in 'ESC[200~' and 'ESC[201~' (nobody types that fast).
+    ///
+    fn read_serial_burst(&self, first: u8, input: &mut VecDeque<u8>) {
+        if !self.bracketed_paste.load(Relaxed) {
+            input.push_back(first);
+            return;
+        }
+
+        let mut burst = Vec::from([first]);
+        let mut last_ns = clock().now_ns();
+        loop {
+            match self.poll_serial() {
+                Some(byte) => {
+                    burst.push(byte);
+                    last_ns = clock().now_ns();
+                }
+                None if clock().now_ns() - last_ns < PASTE_GAP_NS => scheduler().switch_thread_no_interrupt(),
+                None => break,
+            }
+        }
+
+        // A burst starting with ESC is a special key (e.g. cursor keys), even if several keys are sent at once
+        if burst.len() >= PASTE_MIN_BYTES && first != 0x1b {
+            input.extend(b"\x1b[200~");
+            input.extend(burst);
+            input.extend(b"\x1b[201~");
+        } else {
+            input.extend(burst);
+        }
+    }
+
+    /// Description: Number of text rows on the screen (without the status bar)
+    fn page_rows(&self) -> usize {
+        self.display.lock().size.1 as usize - 1
+    }
+
+    ///
+    /// Description:
+    ///    Scroll the view `rows` rows back into the history (negative values scroll towards
+    ///    the live screen). Output, while the view is scrolled back, is only written to
+    ///    the back buffer and becomes visible, when the view returns to the live screen.
+    ///
+    pub(crate) fn scroll_view(&self, rows: isize) {
+        let mut display = self.display.lock();
+        if display.primary.is_some() {
+            return; // the history belongs to the primary screen
+        }
+
+        let offset = display.scroll_offset.saturating_add_signed(rows).min(display.history.len());
+        if offset == display.scroll_offset {
+            return;
+        }
+
+        display.scroll_offset = offset;
+        if !display.blanked {
+            LFBTerminal::redraw(&mut display);
+        }
+
+        let cursor = self.cursor.lock();
+        display.flush(&cursor);
+    }
+
+    /// Description: Draw the whole view: the live screen from the back buffer or the history at the current scroll offset
+    fn redraw(display: &mut DisplayState) {
+        if display.scroll_offset == 0 {
+            display.backend.restore(); // Restore the live screen from the back buffer
+        } else {
+            LFBTerminal::draw_history(display);
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Blank the screen, if there has been no keyboard input for the blank timeout, or restore it
+    ///    on input (called periodically by the `CursorThread`). Input is detected by a change of
+    ///    `received_bytes` (scancodes received by the keyboard), even if nobody reads it.
+    ///
+    pub(crate) fn check_idle(&self, now_ms: usize, received_bytes: usize) {
+        let mut screen_saver = self.screen_saver.lock();
+        if received_bytes != screen_saver.received_bytes {
+            screen_saver.received_bytes = received_bytes;
+            screen_saver.idle_since_ms = now_ms;
+            self.unblank();
+            return;
+        }
+
+        let timeout = screen_saver.timeout_ms;
+        if timeout > 0 && now_ms.saturating_sub(screen_saver.idle_since_ms) >= timeout {
+            let mut display = self.display.lock();
+            if !display.blanked {
+                display.blanked = true;
+                display.backend.blank();
+            }
+        }
+    }
+
+    /// Description: Restore a blanked screen (the output written meanwhile becomes visible)
+    fn unblank(&self) {
+        let mut display = self.display.lock();
+        if display.blanked {
+            display.blanked = false;
+            LFBTerminal::redraw(&mut display);
+
+            let cursor = self.cursor.lock();
+            display.flush(&cursor);
+        }
+    }
+
+    /// Description: The screen saver has blanked the screen
+    pub(crate) fn is_blanked(&self) -> bool {
+        self.display.lock().blanked
+    }
+
+    /// Description: Return the number of rows the view is scrolled back (0 = live screen)
+    pub(crate) fn scroll_offset(&self) -> usize {
+        self.display.lock().scroll_offset
+    }
+
+    /// Description: Draw all cells of the character buffer (except the status bar) to the back buffer
+    fn draw_cells(display: &mut DisplayState) {
+        let columns = display.size.0 as usize;
+        for y in 1..display.size.1 as usize {
+            for x in 0..columns {
+                let character = display.char_buffer[y * columns + x];
+                let value = match character.value {
+                    '\0' if character.bg_color == INVISIBLE => continue, // covered by a wide glyph
+                    '\0' => ' ',
+                    value => value,
+                };
+
+                display.backend.draw_char((x as u16, y as u16), value, character.fg_color, character.bg_color);
+            }
+        }
+    }
+
+    /// Description: Save the primary screen with cursor and colors, and show the cleared alternate screen (no effect, if it is already shown)
+    fn enter_alternate_screen(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
+        if display.primary.is_some() {
+            return;
+        }
+
+        LFBTerminal::draw_cursor_cell(display, cursor, false); // remove a cursor drawn by the blinking thread
+        display.primary = Some(PrimaryScreen { char_buffer: display.char_buffer.clone(), cursor_pos: cursor.pos, saved_cursor_pos: cursor.saved_pos, color: color.clone() });
+
+        LFBTerminal::clear_screen(display, color);
+        LFBTerminal::position(display, cursor, color, (0, 1));
+    }
+
+    /// Description: Restore the primary screen with cursor and colors (no effect, if the alternate screen is not shown)
+    fn leave_alternate_screen(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
+        let primary = match display.primary.take() {
+            Some(primary) => primary,
+            None => return,
+        };
+
+        LFBTerminal::draw_cursor_cell(display, cursor, false);
+        display.char_buffer = primary.char_buffer;
+        display.scroll_offset = 0;
+        *color = primary.color;
+        cursor.pos = primary.cursor_pos;
+        cursor.saved_pos = primary.saved_cursor_pos;
+
+        LFBTerminal::draw_cells(display);
+        LFBTerminal::draw_status_bar(display);
+    }
+
+    ///
+    /// Description:
+    ///    Set (`ESC[?{mode}h`) or reset (`ESC[?{mode}l`) DEC private modes. Supported are the
+    ///    alternate screen (47, 1047 and 1049) and bracketed paste (2004), others are ignored.
+    ///
+    fn handle_ansi_private_mode(&self, set: bool, params: &Params) {
+        for param in params.iter() {
+            match (param[0], set) {
+                (47 | 1047 | 1049, true) => LFBTerminal::enter_alternate_screen(&mut self.display.lock(), &mut self.cursor.lock(), &mut self.color.lock()),
+                (47 | 1047 | 1049, false) => LFBTerminal::leave_alternate_screen(&mut self.display.lock(), &mut self.cursor.lock(), &mut self.color.lock()),
+                (2004, set) => self.bracketed_paste.store(set, Relaxed),
+                _ => {}
+            }
+        }
+    }
+
+    /// Description: Draw the rows of history and live screen at the current scroll offset directly to the screen
+    fn draw_history(display: &mut DisplayState) {
+        let columns = display.size.0 as usize;
+        let top = display.history.len() - display.scroll_offset;
+
+        for y in 1..display.size.1 as usize {
+            let index = top + y - 1;
+            let row = match display.history.get(index) {
+                Some(row) => row.clone(),
+                None => {
+                    let live_row = index - display.history.len() + 1;
+                    display.char_buffer[live_row * columns..(live_row + 1) * columns].to_vec()
+                }
+            };
+
+            for (x, character) in row.iter().enumerate() {
+                let value = match character.value {
+                    '\0' if character.bg_color == INVISIBLE => continue, // covered by a wide glyph
+                    '\0' => ' ',
+                    value => value,
+                };
+
+                display.backend.draw_char_direct((x as u16, y as u16), value, character.fg_color, character.bg_color);
+            }
+        }
+    }
+
+    /// Description: Return current foreground and background color (as set by SGR escape sequences)
+    pub(crate) fn colors(&self) -> (Color, Color) {
+        let color = self.color.lock();
+        (color.fg_color, color.bg_color)
+    }
+
+    /// Description: Return a PPM image of the live screen (also while the view is scrolled back)
+    pub fn screenshot(&self) -> Vec<u8> {
+        self.display.lock().backend.screenshot()
+    }
+
+    ///
+    /// Description:
+    ///    Write a screenshot (see `screenshot()`) hex encoded to `stream` (e.g. the serial port),
+    ///    enclosed by marker lines. The lines between the markers give the PPM file with `xxd -r -p`.
+    ///
+    pub fn dump_screenshot(&self, stream: &dyn OutputStream) {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let ppm = self.screenshot();
+
+        stream.write_str("-----BEGIN SCREENSHOT-----\n");
+        for chunk in ppm.chunks(32) {
+            let mut line = String::with_capacity(chunk.len() * 2 + 1);
+            for byte in chunk {
+                line.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+                line.push(HEX_DIGITS[(byte & 0x0f) as usize] as char);
+            }
+
+            line.push('\n');
+            stream.write_str(&line);
+        }
+        stream.write_str("-----END SCREENSHOT-----\n");
+    }
+
+    /// Description: Return number of bytes copied from the back buffer to the screen so far
+    pub(crate) fn flushed_bytes(&self) -> usize {
+        self.display.lock().backend.flushed_bytes()
+    }
+
+    ///
+    /// Description:
+    ///    Draw the cursor (`on` = true) or the character at the cursor position directly to the
+    ///    screen (called periodically by the `CursorThread`). A hidden cursor is never drawn and
+    ///    nothing is drawn, while the view is scrolled back or the backend has a hardware cursor.
+    ///
+    pub(crate) fn blink_cursor(&self, on: bool) {
+        let mut display = self.display.lock();
+        let cursor = self.cursor.lock();
+        if display.scroll_offset == 0 && !display.backend.has_hardware_cursor() {
+            let on = on && cursor.visible;
+            LFBTerminal::draw_cursor_cell(&mut display, &cursor, on);
+        }
+    }
+
+    /// Description: Return cursor position as index into the character buffer (row * columns + column)
+    pub(crate) fn cursor_index(&self) -> usize {
+        let display = self.display.lock();
+        let cursor = self.cursor.lock();
+        cursor.pos.1 as usize * display.size.0 as usize + cursor.pos.0 as usize
+    }
+
+    /// Description: Set cursor position from an index into the character buffer
+    fn set_cursor_index(&self, index: usize) {
+        let mut display = self.display.lock();
+        let mut cursor = self.cursor.lock();
+        let mut color = self.color.lock();
+        let columns = display.size.0 as usize;
+
+        LFBTerminal::position(&mut display, &mut cursor, &mut color, ((index % columns) as u16, (index / columns) as u16));
+        display.flush(&cursor);
+    }
+
+    /// Description: Copy all changes since the last flush from the back buffer to the screen
+    fn flush(&self) {
+        let mut display = self.display.lock();
+        let cursor = self.cursor.lock();
+        display.flush(&cursor);
+    }
+
+    ///
+    /// Description:
+    ///    Redraw the line of `editor`, starting at cursor index `start`. The line
+    ///    may span several rows. If the screen scrolls while printing, the start
+    ///    moves up accordingly (so backspace can move back to a previous row).
+    ///
+    /// Return: new start index of the line
+    ///
+    fn redraw_line(&self, start: usize, editor: &LineEditor) -> usize {
+        let line = editor.line();
+
+        self.set_cursor_index(start);
+        self.write_str(&line);
+        self.write_str("\x1b[J"); // Clear remains of a previously longer line
+
+        let start = self.cursor_index().saturating_sub(line.chars().count());
+        self.set_cursor_index(start + editor.cursor());
+        start
+    }
+
     fn print_char(&self, c: char) {
         let mut display = self.display.lock();
         let mut cursor = self.cursor.lock();
@@ -225,11 +981,12 @@ impl LFBTerminal {
 
             cursor.pos.0 = 0;
             cursor.pos.1 += 1;
+        } else if c == '\t' {
+            LFBTerminal::handle_tab(&mut display, &mut cursor, &mut color);
         } else {
-            let char_width = LFBTerminal::print_char_at(&mut display, &mut color, c, cursor.pos);
-            if char_width > 0 {
+            let char_columns = LFBTerminal::print_char_at(&mut display, &mut color, c, cursor.pos);
+            if char_columns > 0 {
                 let index = (cursor.pos.1 * display.size.0 + cursor.pos.0) as usize;
-                let char_columns = (char_width / lfb::DEFAULT_CHAR_WIDTH + (if char_width % lfb::DEFAULT_CHAR_WIDTH == 0 { 0 } else { 1 })) as u16;
 
                 // Set character in character buffer
                 display.char_buffer[index] = Character { value: c, fg_color: color.fg_color, bg_color: color.bg_color };
@@ -261,54 +1018,74 @@ impl LFBTerminal {
             LFBTerminal::scroll_up(&mut display, &mut color);
             cursor.pos.0 = 0;
             cursor.pos.1 = display.size.1 - 1;
-            let pos = (0, display.size.1);
+        }
+    }
 
-            LFBTerminal::print_char_at(&mut display, &mut color, '_', pos);
+    /// Description: Draw the cursor glyph (`cursor_glyph` = true) or the character at the cursor position directly to the screen
+    fn draw_cursor_cell(display: &mut DisplayState, cursor: &CursorState, cursor_glyph: bool) {
+        if display.blanked {
+            return;
         }
+
+        let character = display.char_buffer[(cursor.pos.1 * display.size.0 + cursor.pos.0) as usize];
+        let draw_character = match (cursor_glyph, character.value) {
+            (true, _) => CURSOR,
+            (false, '\0') => ' ',
+            (false, value) => value,
+        };
+
+        display.backend.draw_char_direct(cursor.pos, draw_character, character.fg_color, character.bg_color);
     }
 
-    fn print_char_at(display: &mut DisplayState, color: &mut ColorState, c: char, pos: (u16, u16)) -> u32 {
-        display.lfb.lfb().draw_char(pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, color.fg_color, color.bg_color, c);
-        display.lfb.direct_lfb().draw_char(pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, color.fg_color, color.bg_color, c)
+    /// Description: Draw `c` at `pos` in the current colors and return the number of columns it covers
+    fn print_char_at(display: &mut DisplayState, color: &mut ColorState, c: char, pos: (u16, u16)) -> u16 {
+        display.backend.draw_char(pos, c, color.fg_color, color.bg_color)
     }
 
     fn draw_status_bar(display: &mut DisplayState) {
-        // Draw background
-        for i in 0..display.size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH {
-            for j in 0..lfb::DEFAULT_CHAR_HEIGHT {
-                display.lfb.lfb().draw_pixel(i, j, color::HHU_GREEN);
-            }
-        }
-
         // Collect system information
         let uptime = TimeDelta::try_milliseconds(timer().systime_ms() as i64).expect("Failed to create TimeDelta struct from systime");
         let active_process_ids = process_manager().read().active_process_ids();
         let active_thread_ids = scheduler().active_thread_ids();
 
-        // Draw info string
+        // Format info string
         let info_string = format!("D³OS v{} ({}) | Uptime: {:0>2}:{:0>2}:{:0>2} | Processes: {} | Threads: {}",
                                   built_info::PKG_VERSION, built_info::PROFILE,
                                   uptime.num_hours(), uptime.num_minutes() % 60, uptime.num_seconds() - (uptime.num_minutes() * 60),
                                   active_process_ids.len(),
                                   active_thread_ids.len());
 
-        display.lfb.lfb().draw_string(0, 0, color::HHU_BLUE, color::INVISIBLE, info_string.as_str());
-
-        // Draw date
-        if let Some(efi_system_table) = efi_system_table() {
+        // Format date
+        let date_str = efi_system_table().and_then(|efi_system_table| {
             let system_table = efi_system_table.read();
             let runtime_services = unsafe { system_table.runtime_services() };
 
-            if let Ok(date) = runtime_services.get_time() {
-                let date_str = format!("{}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}", date.year(), date.month(), date.day(), date.hour(), date.minute(), date.second());
-                display.lfb.lfb().draw_string((display.size.0 as u32 - date_str.len() as u32) * lfb::DEFAULT_CHAR_WIDTH, 0, color::HHU_BLUE, color::INVISIBLE, &date_str);
-            }
-        }
+            runtime_services.get_time().ok()
+                .map(|date| format!("{}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}", date.year(), date.month(), date.day(), date.hour(), date.minute(), date.second()))
+        });
 
-        display.lfb.flush_lines(0, lfb::DEFAULT_CHAR_HEIGHT);
+        display.backend.draw_status_bar(info_string.as_str(), date_str.as_deref());
     }
 
     fn scroll_up(display: &mut DisplayState, color: &mut ColorState) {
+        // Keep the topmost text row (row 0 is covered by the status bar) in the history (the alternate screen has no history)
+        let columns = display.size.0 as usize;
+        if display.primary.is_none() {
+            let row = if display.history.len() == SCROLLBACK_ROWS {
+                let mut row = display.history.pop_front().unwrap(); // reuse memory of the oldest row
+                row.copy_from_slice(&display.char_buffer[columns..2 * columns]);
+                row
+            } else {
+                display.char_buffer[columns..2 * columns].to_vec()
+            };
+            display.history.push_back(row);
+        }
+
+        // Keep the view stable, while it is scrolled back
+        if display.scroll_offset > 0 {
+            display.scroll_offset = (display.scroll_offset + 1).min(display.history.len());
+        }
+
         unsafe {
             let char_ptr = display.char_buffer.as_ptr() as *mut u8;
             char_ptr.copy_from(char_ptr.offset(display.size.0 as isize * size_of::<Character>() as isize),
@@ -322,12 +1099,8 @@ impl LFBTerminal {
             item.bg_color = color.bg_color;
         });
 
-        let size = display.size;
-        display.lfb.lfb().scroll_up(lfb::DEFAULT_CHAR_HEIGHT);
-        display.lfb.lfb().fill_rect(0, (size.1 - 1) as u32 * lfb::DEFAULT_CHAR_HEIGHT, size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
-
+        display.backend.scroll_up(color.bg_color);
         LFBTerminal::draw_status_bar(display);
-        display.lfb.flush();
     }
 
     fn position(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState, pos: (u16, u16)) {
@@ -343,24 +1116,48 @@ impl LFBTerminal {
         }
     }
 
+    /// Description: Move the cursor to `pos`, clamped to the grid (a cursor drawn at the old position is removed)
+    fn position_clamped(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState, pos: (u16, u16)) {
+        if display.scroll_offset == 0 {
+            LFBTerminal::draw_cursor_cell(display, cursor, false);
+        }
+
+        let pos = (pos.0.min(display.size.0 - 1), pos.1.min(display.size.1 - 1));
+        LFBTerminal::position(display, cursor, color, pos);
+    }
+
     fn handle_bell() {
         let speaker = speaker();
         speaker.play(440, 250);
         speaker.play(880, 250);
     }
 
+    /// Description: Advance the cursor to the next tab stop, overwriting the skipped cells with spaces (wraps at the end of the row)
     fn handle_tab(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
-        if cursor.pos.0 + TAB_SPACES >= display.size.0 {
-            LFBTerminal::position(display, cursor, color, (0, cursor.pos.1 + 1));
+        let row = cursor.pos.1;
+        let next_stop = (cursor.pos.0 / display.tab_width + 1).saturating_mul(display.tab_width);
+        let end = next_stop.min(display.size.0);
+
+        for column in cursor.pos.0..end {
+            LFBTerminal::print_char_at(display, color, ' ', (column, row));
+            let index = (row * display.size.0 + column) as usize;
+            display.char_buffer[index] = Character { value: ' ', fg_color: color.fg_color, bg_color: color.bg_color };
+        }
+
+        if next_stop >= display.size.0 {
+            LFBTerminal::position(display, cursor, color, (0, row + 1));
         } else {
-            LFBTerminal::position(display, cursor, color, (((cursor.pos.0 + TAB_SPACES) / TAB_SPACES) * TAB_SPACES, cursor.pos.1));
+            cursor.pos.0 = next_stop;
         }
     }
 
     fn clear_screen(display: &mut DisplayState, color: &mut ColorState) {
+        // Return to the live screen (the whole screen is marked dirty below)
+        display.scroll_offset = 0;
+
         // Clear screen
         let size = display.size;
-        display.lfb.lfb().fill_rect(0, 0, size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, size.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        display.backend.fill((0, 0), size, color.bg_color);
 
         // Clear character buffer
         display.char_buffer.iter_mut().for_each(|item| {
@@ -370,7 +1167,6 @@ impl LFBTerminal {
         });
 
         LFBTerminal::draw_status_bar(display);
-        display.lfb.flush();
     }
 
     fn clear_screen_to_cursor(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
@@ -378,10 +1174,10 @@ impl LFBTerminal {
         let size = display.size;
 
         // Clear from start of line to cursor
-        display.lfb.lfb().fill_rect(0, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        display.backend.fill((0, pos.1), (pos.0, 1), color.bg_color);
 
         // Clear from start of screen to line before cursor
-        display.lfb.lfb().fill_rect(0, 0, size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        display.backend.fill((0, 0), (size.0, pos.1), color.bg_color);
 
         // Clear character buffer from beginning of screen to cursor
         display.char_buffer.iter_mut().enumerate()
@@ -393,7 +1189,6 @@ impl LFBTerminal {
             });
 
         LFBTerminal::draw_status_bar(display);
-        display.lfb.flush();
     }
 
     fn clear_screen_from_cursor(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
@@ -401,10 +1196,10 @@ impl LFBTerminal {
         let size = display.size;
 
         // Clear from cursor to end of line
-        display.lfb.lfb().fill_rect(pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, (size.0 - pos.0) as u32 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        display.backend.fill(pos, (size.0 - pos.0, 1), color.bg_color);
 
         // Clear from next line to end of screen
-        display.lfb.lfb().fill_rect(0, (pos.1 + 1) as u32 * lfb::DEFAULT_CHAR_HEIGHT, size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, (size.1 - pos.1 - 1) as u32 * lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        display.backend.fill((0, pos.1 + 1), (size.0, size.1 - pos.1 - 1), color.bg_color);
 
         // Clear character buffer from cursor to end of screen
         display.char_buffer.iter_mut().skip((pos.1 * size.0 + pos.0) as usize)
@@ -415,20 +1210,19 @@ impl LFBTerminal {
             });
 
         LFBTerminal::draw_status_bar(display);
-        display.lfb.flush();
     }
 
     fn clear_line(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
         let pos = cursor.pos;
         let size = display.size;
 
-        // Clear line in lfb
-        display.lfb.lfb().fill_rect(0, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        // Clear line on screen
+        display.backend.fill((0, pos.1), (size.0, 1), color.bg_color);
         // Clear line in character buffer
         display.char_buffer.iter_mut().skip((pos.1 * size.0) as usize).enumerate()
             .filter(|item| item.0 < size.0 as usize)
             .for_each(|item| {
-                item.1.value = 'a';
+                item.1.value = '\0';
                 item.1.fg_color = color.fg_color;
                 item.1.bg_color = color.bg_color;
             });
@@ -436,15 +1230,14 @@ impl LFBTerminal {
         if pos.1 == 0 {
             LFBTerminal::draw_status_bar(display);
         }
-        display.lfb.flush();
     }
 
     fn clear_line_to_cursor(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
         let pos = cursor.pos;
         let size = display.size;
 
-        // Clear line in lfb
-        display.lfb.lfb().fill_rect(0, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        // Clear line on screen
+        display.backend.fill((0, pos.1), (pos.0, 1), color.bg_color);
 
         // Clear line in character buffer
         display.char_buffer.iter_mut().skip((pos.1 * size.0) as usize).enumerate()
@@ -458,15 +1251,14 @@ impl LFBTerminal {
         if pos.1 == 0 {
             LFBTerminal::draw_status_bar(display);
         }
-        display.lfb.flush();
     }
 
     fn clear_line_from_cursor(display: &mut DisplayState, cursor: &mut CursorState, color: &mut ColorState) {
         let pos = cursor.pos;
         let size = display.size;
 
-        // Clear line in lfb
-        display.lfb.lfb().fill_rect(pos.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, pos.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, (size.0 - pos.0) as u32 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, color.bg_color);
+        // Clear line on screen
+        display.backend.fill(pos, (size.0 - pos.0, 1), color.bg_color);
 
         // Clear line in character buffer
         display.char_buffer.iter_mut().skip((pos.1 * size.0 + pos.0) as usize).enumerate()
@@ -480,7 +1272,6 @@ impl LFBTerminal {
         if pos.1 == 0 {
             LFBTerminal::draw_status_bar(display);
         }
-        display.lfb.flush();
     }
 
     fn handle_ansi_color(color: &mut ColorState, params: &Params) {
@@ -641,18 +1432,11 @@ impl LFBTerminal {
                 }
             }
             0x48 | 0x66 => {
-                // Set cursor position
-                let param1 = iter.next();
-                let param2 = iter.next();
-
-                if param1.is_some() && param2.is_some() {
-                    let column = param1.unwrap()[0];
-                    let row = param2.unwrap()[0];
-
-                    LFBTerminal::position(display, cursor, color, (if column > display.size.0 { display.size.0 - 1 } else { column }, if row > display.size.1 { display.size.1 - 1 } else { row }));
-                } else {
-                    LFBTerminal::position(display, cursor, color, (0, 0));
-                }
+                // Set cursor position ("\x1b[{row};{col}H", counted from 1, missing parameters are 1).
+                // Row 1 is the first row below the status bar.
+                let row = iter.next().map_or(1, |param| param[0]).max(1);
+                let column = iter.next().map_or(1, |param| param[0]).max(1);
+                LFBTerminal::position_clamped(display, cursor, color, (column - 1, row));
             }
             0x73 => {
                 // Save cursor position
@@ -734,6 +1518,17 @@ fn parse_complex_color(iter: &mut ParamsIter) -> Option<Color> {
     };
 }
 
+///
+/// Escape sequences are parsed by a state machine ('anstyle_parse::Parser'), whose state is
+/// kept in 'LFBTerminal' between calls of 'write_byte()'/'write_str()'. Thus, a sequence may be
+/// split across several writes (e.g. "\x1b[3" followed by "1m").
+/// Colors are set via SGR sequences ("\x1b[...m"): 30-37/40-47 select one of the colors from
+/// the 'color' module, 90-97/100-107 their bright variants and 0 resets to white on black.
+/// Erase sequences paint with the current background color: "\x1b[2J" clears the screen and
+/// moves the cursor home (so "\x1b[2J\x1b[H" is a portable clear), "\x1b[K" clears from the
+/// cursor to the end of the line.
+/// Unrecognized or malformed sequences are swallowed and not printed.
+///
 impl Perform for LFBTerminal {
     fn print(&mut self, c: char) {
         self.print_char(c);
@@ -742,7 +1537,7 @@ impl Perform for LFBTerminal {
     fn execute(&mut self, byte: u8) {
         match byte {
             0x07 => LFBTerminal::handle_bell(),
-            0x09 => LFBTerminal::handle_tab(&mut self.display.lock(), &mut self.cursor.lock(), &mut self.color.lock()),
+            0x09 => self.print_char('\t'),
             0x0a => self.print_char('\n'),
             _ => {}
         }
@@ -756,8 +1551,9 @@ impl Perform for LFBTerminal {
 
     fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
 
-    fn csi_dispatch(&mut self, params: &Params, _intermediates: &[u8], _ignore: bool, action: u8) {
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: u8) {
         match action {
+            0x68 | 0x6c if intermediates == b"?" => self.handle_ansi_private_mode(action == 0x68, params),
             0x41..=0x48 | 0x66 | 0x6e | 0x73 | 0x75 => LFBTerminal::handle_ansi_cursor_sequence(&mut self.display.lock(), &mut self.cursor.lock(), &mut self.color.lock(), action, params),
             0x4a | 0x4b => LFBTerminal::handle_ansi_erase_sequence(&mut self.display.lock(), &mut self.cursor.lock(), &mut self.color.lock(), action, params),
             0x6d => LFBTerminal::handle_ansi_color(&mut self.color.lock(), params),
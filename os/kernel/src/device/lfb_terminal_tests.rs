@@ -0,0 +1,666 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: lfb_terminal_tests                                              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test ANSI color escape sequences, double buffering, the         ║
+   ║         scroll-back history, font scaling and serial input of the LFB   ║
+   ║         terminal. The tests use an off-screen terminal, backed by a     ║
+   ║         buffer on the heap. Serial input is simulated by passing bytes  ║
+   ║         to a detached handle of COM1 (echoed bytes are sent to COM1).   ║
+   ║         Also tests positioning and hiding the cursor, tab stops and     ║
+   ║         erase sequences, as well as rendering text into an off-screen   ║
+   ║         framebuffer, taking screenshots, the screen saver and the       ║
+   ║         alternate screen and bracketed paste.                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use ::log::info;
+use graphic::{color, lfb};
+use graphic::color::PixelFormat;
+use graphic::offscreen_lfb::OffscreenLFB;
+use stream::{InputStream, OutputStream};
+
+use crate::{clock, timer};
+use crate::device::serial::{ComPort, SerialPort};
+use crate::device::terminal::Terminal;
+use crate::device::lfb_terminal::{auto_font_scale, text_size, LFBTerminal, PASTE_MIN_BYTES, SCROLLBACK_ROWS};
+
+const COLUMNS: u32 = 16;
+const ROWS: u32 = 4;
+const BPP: u8 = 32;
+
+///
+/// Description:
+///    Run all terminal tests (requires the process manager and scheduler for the status bar)
+///
+pub fn run_tests() {
+    info!("lfb_terminal: running tests");
+
+    test_colors();
+    test_bright_colors();
+    test_split_sequence();
+    test_malformed_sequence();
+    test_flush_per_write();
+    test_scrollback();
+    test_scrollback_bounded();
+    test_font_scale_size();
+    test_auto_font_scale();
+    test_scaled_scroll();
+    test_serial_input();
+    test_bracketed_paste();
+    test_set_cursor();
+    test_ansi_cursor_position();
+    test_hide_cursor();
+    test_tab_stops();
+    test_clear_screen();
+    test_clear_to_end_of_line();
+    test_clear_resets_view();
+    test_offscreen_text();
+    test_blank_screen();
+    test_blank_disabled();
+    test_alternate_screen();
+    benchmark_scroll();
+
+    info!("lfb_terminal: all tests passed.");
+}
+
+/// Description: Create a terminal drawing into `buffer`
+fn create_terminal(buffer: &mut [u8]) -> LFBTerminal {
+    create_terminal_sized(buffer, COLUMNS, ROWS)
+}
+
+/// Description: Create a terminal with `columns` x `rows` characters drawing into `buffer`
+fn create_terminal_sized(buffer: &mut [u8], columns: u32, rows: u32) -> LFBTerminal {
+    let width = columns * lfb::DEFAULT_CHAR_WIDTH;
+    let height = rows * lfb::DEFAULT_CHAR_HEIGHT;
+    let pitch = width * (BPP as u32 / 8);
+    assert!(buffer.len() >= (pitch * height) as usize);
+
+    LFBTerminal::new(buffer.as_mut_ptr(), pitch, width, height, BPP, PixelFormat::Rgb)
+}
+
+fn buffer() -> vec::Vec<u8> {
+    buffer_sized(COLUMNS, ROWS)
+}
+
+fn buffer_sized(columns: u32, rows: u32) -> vec::Vec<u8> {
+    vec![0; (columns * lfb::DEFAULT_CHAR_WIDTH * rows * lfb::DEFAULT_CHAR_HEIGHT * BPP as u32 / 8) as usize]
+}
+
+/// Description: Size of one text row in the framebuffer (in bytes)
+fn row_bytes(columns: u32) -> usize {
+    (columns * lfb::DEFAULT_CHAR_WIDTH * BPP as u32 / 8 * lfb::DEFAULT_CHAR_HEIGHT) as usize
+}
+
+/// Description: Foreground/background codes select colors from the 'color' module, 0 resets
+fn test_colors() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+
+    terminal.write_str("\x1b[31m");
+    assert_eq!(terminal.colors(), (color::RED, color::BLACK));
+    terminal.write_str("\x1b[44m");
+    assert_eq!(terminal.colors(), (color::RED, color::BLUE));
+    terminal.write_str("\x1b[32;47m");
+    assert_eq!(terminal.colors(), (color::GREEN, color::WHITE));
+    terminal.write_str("\x1b[0m");
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+}
+
+/// Description: Codes 90-97 and 100-107 select the bright variants
+fn test_bright_colors() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.write_str("\x1b[96;101m");
+    assert_eq!(terminal.colors(), (color::CYAN.bright(), color::RED.bright()));
+    terminal.write_str("\x1b[0m");
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+}
+
+/// Description: A sequence split across several writes must be recognized
+fn test_split_sequence() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let start = terminal.cursor_index();
+
+    terminal.write_str("\x1b[3");
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+    terminal.write_str("1m");
+    assert_eq!(terminal.colors(), (color::RED, color::BLACK));
+    terminal.write_byte(0x1b);
+    terminal.write_byte(b'[');
+    terminal.write_byte(b'0');
+    terminal.write_byte(b'm');
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+
+    // Nothing has been printed
+    assert_eq!(terminal.cursor_index(), start);
+}
+
+/// Description: Unknown sequences are swallowed, without changing colors; following text is printed
+fn test_malformed_sequence() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let start = terminal.cursor_index();
+
+    terminal.write_str("\x1b[31x");
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+    assert_eq!(terminal.cursor_index(), start);
+
+    terminal.write_str("\x1b[38;9m"); // invalid extended color mode
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+
+    terminal.write_str("ok");
+    assert_eq!(terminal.cursor_index(), start + 2);
+}
+
+/// Description: Text is drawn to the back buffer and copied to the screen once per write
+fn test_flush_per_write() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let row = terminal.cursor_index() / COLUMNS as usize;
+    let flushed = terminal.flushed_bytes();
+
+    // Only the cells of the three characters have changed
+    terminal.write_str("abc");
+    assert_eq!(terminal.flushed_bytes() - flushed, row_bytes(3));
+
+    let offset = row * row_bytes(COLUMNS);
+    assert!(buffer[offset..offset + row_bytes(COLUMNS)].iter().any(|byte| *byte != 0));
+
+    // Scrolling several times within one write copies the screen only once
+    let flushed = terminal.flushed_bytes();
+    let mut lines = String::new();
+    for _ in 0..ROWS * 2 {
+        lines.push_str("line\n");
+    }
+    terminal.write_str(&lines);
+    assert_eq!(terminal.flushed_bytes() - flushed, row_bytes(COLUMNS) * ROWS as usize);
+}
+
+/// Description: Scrolling back and forth restores the live screen; output while scrolled back does not change the view
+fn test_scrollback() {
+    let mut screen = buffer();
+    let terminal = create_terminal(&mut screen);
+    for i in 0..ROWS * 3 {
+        terminal.write_str(&format!("line {}\n", i));
+    }
+
+    let live = screen.clone();
+    terminal.scroll_view(ROWS as isize - 1);
+    assert_eq!(terminal.scroll_offset(), ROWS as usize - 1);
+    assert_ne!(screen, live);
+    terminal.scroll_view(-(ROWS as isize - 1));
+    assert_eq!(terminal.scroll_offset(), 0);
+    assert_eq!(screen, live);
+
+    terminal.scroll_view(1);
+    let view = screen.clone();
+    terminal.write_str("more\n");
+    assert_eq!(screen, view);
+    assert_eq!(terminal.scroll_offset(), 2);
+
+    // Back at the bottom, the screen shows the same text as a terminal, that has never been scrolled back
+    terminal.scroll_view(isize::MIN);
+    let mut reference_buffer = buffer();
+    let reference = create_terminal(&mut reference_buffer);
+    for i in 0..ROWS * 3 {
+        reference.write_str(&format!("line {}\n", i));
+    }
+    reference.write_str("more\n");
+
+    // Row 0 (status bar) contains the uptime and is skipped
+    assert_eq!(screen[row_bytes(COLUMNS)..], reference_buffer[row_bytes(COLUMNS)..]);
+}
+
+/// Description: The history keeps at most SCROLLBACK_ROWS rows
+fn test_scrollback_bounded() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    for _ in 0..SCROLLBACK_ROWS + ROWS as usize * 2 {
+        terminal.write_str("x\n");
+    }
+
+    terminal.scroll_view(isize::MAX);
+    assert_eq!(terminal.scroll_offset(), SCROLLBACK_ROWS);
+    terminal.scroll_view(isize::MIN);
+    assert_eq!(terminal.scroll_offset(), 0);
+}
+
+/// Description: Columns and rows are computed from the scaled character size
+fn test_font_scale_size() {
+    assert_eq!(text_size(3840, 2160, 2), (240, 67));
+    assert_eq!(text_size(3840, 2160, 1), (480, 135));
+    assert_eq!(text_size(3840, 2160, 4), (120, 33));
+}
+
+/// Description: The largest scale still giving a usable terminal is selected; small screens are not scaled
+fn test_auto_font_scale() {
+    assert_eq!(auto_font_scale(3840, 2160), 4);
+    assert_eq!(auto_font_scale(2560, 1440), 2);
+    assert_eq!(auto_font_scale(1920, 1080), 2);
+    assert_eq!(auto_font_scale(1280, 720), 1);
+    assert_eq!(auto_font_scale(COLUMNS * lfb::DEFAULT_CHAR_WIDTH, ROWS * lfb::DEFAULT_CHAR_HEIGHT), 1);
+}
+
+/// Description: With a scaled font, printing and scrolling move whole scaled rows
+fn test_scaled_scroll() {
+    const SCALE: u32 = 2;
+
+    let width = COLUMNS * lfb::DEFAULT_CHAR_WIDTH * SCALE;
+    let height = ROWS * lfb::DEFAULT_CHAR_HEIGHT * SCALE;
+    let pitch = width * (BPP as u32 / 8);
+    let scaled_row_bytes = row_bytes(COLUMNS) * (SCALE * SCALE) as usize;
+
+    let mut buffer = vec![0; (pitch * height) as usize];
+    let terminal = LFBTerminal::with_font_scale(buffer.as_mut_ptr(), pitch, width, height, BPP, PixelFormat::Rgb, SCALE);
+    let row = terminal.cursor_index() / COLUMNS as usize;
+
+    // Only the scaled cells of the three characters have changed
+    let flushed = terminal.flushed_bytes();
+    terminal.write_str("abc");
+    assert_eq!(terminal.flushed_bytes() - flushed, row_bytes(3) * (SCALE * SCALE) as usize);
+    let offset = row * scaled_row_bytes;
+    assert!(buffer[offset..offset + scaled_row_bytes].iter().any(|byte| *byte != 0));
+
+    // Scrolling within one write copies the whole (scaled) screen once and keeps the character grid
+    let flushed = terminal.flushed_bytes();
+    let mut lines = String::new();
+    for _ in 0..ROWS * 2 {
+        lines.push_str("line\n");
+    }
+    terminal.write_str(&lines);
+    assert_eq!(terminal.flushed_bytes() - flushed, buffer.len());
+    assert_eq!(buffer.len(), scaled_row_bytes * ROWS as usize);
+    assert_eq!(terminal.cursor_index() / COLUMNS as usize, ROWS as usize - 1);
+}
+
+/// Description: Bytes received by the serial port are read like typed keys; escape sequences and backspace are passed to the line editor
+fn test_serial_input() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let serial = Arc::new(SerialPort::new_detached(ComPort::Com1, 64));
+    terminal.attach_serial(Arc::clone(&serial));
+    let receive = |bytes: &[u8]| bytes.iter().for_each(|byte| serial.receive(*byte));
+
+    receive(b"x");
+    assert_eq!(terminal.read_byte(), b'x' as i16);
+
+    // Enter key of a serial terminal sends '\r'
+    receive(b"ls\r");
+    assert_eq!(terminal.read_line(), "ls");
+
+    receive(b"ax\x7fb\r");
+    assert_eq!(terminal.read_line(), "ab");
+
+    // Cursor left in the middle of the line
+    receive(b"ac\x1b[Db\r");
+    assert_eq!(terminal.read_line(), "abc");
+
+    // Logging (to the serial port and the terminal) while echoing serial input must not deadlock
+    receive(b"log\r");
+    info!("lfb_terminal: serial input pending");
+    assert_eq!(terminal.read_line(), "log");
+    assert_eq!(serial.try_read_byte(), None);
+}
+
+/// Description: In bracketed paste mode ("\x1b[?2004h"), a burst of serial input is wrapped in "\x1b[200~" and "\x1b[201~"
+fn test_bracketed_paste() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let serial = Arc::new(SerialPort::new_detached(ComPort::Com1, 64));
+    terminal.attach_serial(Arc::clone(&serial));
+    let receive = |bytes: &[u8]| bytes.iter().for_each(|byte| serial.receive(*byte));
+    let read = |count: usize| (0..count).map(|_| terminal.read_byte() as u8).collect::<vec::Vec<u8>>();
+
+    let pasted = b"if x {\r    y\r}";
+    assert!(pasted.len() >= PASTE_MIN_BYTES);
+    receive(pasted);
+    assert_eq!(read(pasted.len()), b"if x {\n    y\n}");
+
+    terminal.write_str("\x1b[?2004h");
+    receive(pasted);
+    assert_eq!(read(pasted.len() + 12), b"\x1b[200~if x {\n    y\n}\x1b[201~");
+
+    // Typed keys and escape sequences of special keys are not pasted
+    receive(b"a");
+    assert_eq!(read(1), b"a");
+    receive(b"\x1b[A\x1b[A\x1b[A");
+    assert_eq!(read(9), b"\x1b[A\x1b[A\x1b[A");
+
+    terminal.write_str("\x1b[?2004l");
+    receive(pasted);
+    assert_eq!(read(pasted.len()), b"if x {\n    y\n}");
+    terminal.write_str("\n");
+}
+
+/// Description: Pixels of the character cell in `column` and `row` on the screen
+fn cell_pixels(buffer: &[u8], column: u32, row: u32) -> vec::Vec<u8> {
+    let pitch = (COLUMNS * lfb::DEFAULT_CHAR_WIDTH * BPP as u32 / 8) as usize;
+    let cell_bytes = (lfb::DEFAULT_CHAR_WIDTH * BPP as u32 / 8) as usize;
+
+    (0..lfb::DEFAULT_CHAR_HEIGHT as usize).flat_map(|line| {
+        let start = (row * lfb::DEFAULT_CHAR_HEIGHT) as usize * pitch + line * pitch + column as usize * cell_bytes;
+        buffer[start..start + cell_bytes].iter().copied()
+    }).collect()
+}
+
+/// Description: The cursor can be moved to any cell of the grid, positions outside are clamped
+fn test_set_cursor() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.set_cursor(3, 2);
+    assert_eq!(terminal.cursor_index(), 2 * COLUMNS as usize + 3);
+    terminal.write_str("x");
+    assert_eq!(terminal.cursor_index(), 2 * COLUMNS as usize + 4);
+
+    terminal.set_cursor(u16::MAX, u16::MAX);
+    assert_eq!(terminal.cursor_index(), (ROWS * COLUMNS) as usize - 1);
+
+    // Row 0 is the status bar
+    terminal.set_cursor(5, 0);
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize + 5);
+}
+
+/// Description: "\x1b[H" moves the cursor home, "\x1b[{row};{col}H" to a cell (counted from 1, below the status bar)
+fn test_ansi_cursor_position() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.write_str("\x1b[2;5H");
+    assert_eq!(terminal.cursor_index(), 2 * COLUMNS as usize + 4);
+    terminal.write_str("\x1b[H");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize);
+    terminal.write_str("\x1b[3H");
+    assert_eq!(terminal.cursor_index(), 3 * COLUMNS as usize);
+    terminal.write_str("\x1b[99;99H");
+    assert_eq!(terminal.cursor_index(), (ROWS * COLUMNS) as usize - 1);
+    terminal.write_str("\x1b[0;0f");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize);
+}
+
+/// Description: A hidden cursor is not drawn at the write position, a visible one is
+fn test_hide_cursor() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let blank = cell_pixels(&buffer, COLUMNS - 1, ROWS - 1);
+
+    terminal.hide_cursor();
+    terminal.write_str("ab");
+    let (column, row) = (terminal.cursor_index() as u32 % COLUMNS, terminal.cursor_index() as u32 / COLUMNS);
+    terminal.blink_cursor(true);
+    assert_eq!(cell_pixels(&buffer, column, row), blank);
+
+    terminal.show_cursor();
+    terminal.blink_cursor(true);
+    assert_ne!(cell_pixels(&buffer, column, row), blank);
+    terminal.blink_cursor(false);
+    assert_eq!(cell_pixels(&buffer, column, row), blank);
+
+    // Hiding removes a cursor, which is currently drawn
+    terminal.blink_cursor(true);
+    terminal.hide_cursor();
+    assert_eq!(cell_pixels(&buffer, column, row), blank);
+}
+
+/// Description: All pixels of the character cell in `column` and `row` have the color `expected`
+fn cell_has_color(buffer: &[u8], column: u32, row: u32, expected: color::Color) -> bool {
+    let pixel = PixelFormat::Rgb.pack(expected, BPP).to_le_bytes();
+    cell_pixels(buffer, column, row).chunks_exact(pixel.len()).all(|bytes| bytes == pixel)
+}
+
+/// Description: "\x1b[2J" paints every cell below the status bar with the current background color and moves the cursor home
+fn test_clear_screen() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.write_str("some text\nmore text");
+    terminal.write_str("\x1b[44m\x1b[2J");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize);
+    for row in 1..ROWS {
+        for column in 0..COLUMNS {
+            assert!(cell_has_color(&buffer, column, row, color::BLUE));
+        }
+    }
+
+    // Also with an explicit cursor home, as used for a portable clear
+    terminal.write_str("\x1b[0mtext\x1b[2J\x1b[H");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize);
+    assert!(cell_has_color(&buffer, 0, 1, color::BLACK));
+}
+
+/// Description: "\x1b[K" clears from the cursor to the end of the line with the current background color
+fn test_clear_to_end_of_line() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.write_str("abcdefgh\x1b[1;3H\x1b[42m\x1b[K");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize + 2);
+    assert!(!cell_has_color(&buffer, 0, 1, color::GREEN));
+    assert!(!cell_has_color(&buffer, 1, 1, color::GREEN));
+    for column in 2..COLUMNS {
+        assert!(cell_has_color(&buffer, column, 1, color::GREEN));
+    }
+}
+
+/// Description: Clearing the screen returns a scrolled back view to the live screen
+fn test_clear_resets_view() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    for _ in 0..ROWS * 2 {
+        terminal.write_str("line\n");
+    }
+
+    terminal.scroll_view(2);
+    assert_eq!(terminal.scroll_offset(), 2);
+    terminal.write_str("\x1b[2J");
+    assert_eq!(terminal.scroll_offset(), 0);
+    for column in 0..COLUMNS {
+        assert!(cell_has_color(&buffer, column, ROWS - 1, color::BLACK));
+    }
+}
+
+/// Description: A tab advances to the next multiple of the tab width, erasing the skipped cells, and wraps at the end of the row
+fn test_tab_stops() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    let blank = cell_pixels(&buffer, COLUMNS - 1, ROWS - 1);
+
+    terminal.write_str("abc\t");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize + 8);
+    terminal.write_str("x\t");
+    assert_eq!(terminal.cursor_index(), 2 * COLUMNS as usize); // the next tab stop is the end of the row
+
+    // Stale glyphs are overwritten with spaces
+    terminal.hide_cursor();
+    terminal.set_cursor(0, 2);
+    terminal.write_str("abcdefghij");
+    terminal.set_cursor(3, 2);
+    terminal.write_str("\t");
+    assert_eq!(terminal.cursor_index(), 2 * COLUMNS as usize + 8);
+    assert!((3..8).all(|column| cell_pixels(&buffer, column, 2) == blank));
+    assert_ne!(cell_pixels(&buffer, 8, 2), blank);
+
+    terminal.set_tab_width(4);
+    terminal.set_cursor(1, 3);
+    terminal.write_str("\t");
+    assert_eq!(terminal.cursor_index(), 3 * COLUMNS as usize + 4);
+    terminal.write_str("\t");
+    assert_eq!(terminal.cursor_index(), 3 * COLUMNS as usize + 8);
+}
+
+/// Description: "OK" written to an off-screen terminal shows the glyphs of the font, also in the screenshot
+fn test_offscreen_text() {
+    let (width, height) = (COLUMNS * lfb::DEFAULT_CHAR_WIDTH, ROWS * lfb::DEFAULT_CHAR_HEIGHT);
+    let screen = OffscreenLFB::new(width, height, BPP, PixelFormat::Rgb);
+    let terminal = LFBTerminal::with_font_scale(screen.lfb().buffer(), screen.lfb().pitch(), width, height, BPP, PixelFormat::Rgb, 1);
+    terminal.write_str("OK");
+
+    // The first text row (below the status bar) matches the glyphs drawn directly
+    let reference = OffscreenLFB::new(2 * lfb::DEFAULT_CHAR_WIDTH, lfb::DEFAULT_CHAR_HEIGHT, BPP, PixelFormat::Rgb);
+    reference.lfb().draw_string(0, 0, color::WHITE, color::BLACK, "OK");
+    let top = lfb::DEFAULT_CHAR_HEIGHT;
+    for y in 0..lfb::DEFAULT_CHAR_HEIGHT {
+        for x in 0..2 * lfb::DEFAULT_CHAR_WIDTH {
+            assert_eq!(screen.lfb().read_pixel(x, top + y), reference.lfb().read_pixel(x, y));
+        }
+    }
+
+    // Both glyphs set pixels in the foreground color
+    let glyph_pixels = |column: u32| (0..lfb::DEFAULT_CHAR_HEIGHT)
+        .flat_map(|y| (0..lfb::DEFAULT_CHAR_WIDTH).map(move |x| (column * lfb::DEFAULT_CHAR_WIDTH + x, top + y)))
+        .filter(|&(x, y)| screen.lfb().read_pixel(x, y) == color::WHITE)
+        .count();
+    assert!(glyph_pixels(0) > 0 && glyph_pixels(1) > 0);
+    assert_eq!(glyph_pixels(2), 0);
+
+    // The screenshot contains the same pixels
+    let ppm = terminal.screenshot();
+    let header = format!("P6\n{} {}\n255\n", width, height);
+    assert!(ppm.starts_with(header.as_bytes()));
+    assert_eq!(ppm.len(), header.len() + (width * height * 3) as usize);
+    for y in top..top + lfb::DEFAULT_CHAR_HEIGHT {
+        for x in 0..width {
+            let pixel = screen.lfb().read_pixel(x, y);
+            let offset = header.len() + ((y * width + x) * 3) as usize;
+            assert_eq!(ppm[offset..offset + 3], [pixel.red, pixel.green, pixel.blue]);
+        }
+    }
+}
+
+/// Description: The screen is blanked after the timeout without input and restored with the output written meanwhile
+fn test_blank_screen() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    terminal.write_str("text");
+    let live = buffer.clone();
+
+    terminal.set_blank_timeout(1000);
+    let start = timer().systime_ms();
+    terminal.check_idle(start + 999, 0);
+    assert!(!terminal.is_blanked());
+    terminal.check_idle(start + 1000, 0);
+    assert!(terminal.is_blanked());
+    assert!(buffer.iter().all(|byte| *byte == 0));
+
+    // The back buffer keeps the live screen, so nothing is lost
+    terminal.write_str("more");
+    assert!(buffer.iter().all(|byte| *byte == 0));
+
+    // A scancode restores the screen and restarts the timeout
+    terminal.check_idle(start + 1500, 1);
+    assert!(!terminal.is_blanked());
+    let row = terminal.cursor_index() / COLUMNS as usize;
+    let offset = row * row_bytes(COLUMNS);
+    assert_eq!(buffer[..offset], live[..offset]);
+    assert_ne!(buffer[offset..offset + row_bytes(COLUMNS)], live[offset..offset + row_bytes(COLUMNS)]);
+
+    terminal.check_idle(start + 2499, 1);
+    assert!(!terminal.is_blanked());
+    terminal.check_idle(start + 2500, 1);
+    assert!(terminal.is_blanked());
+
+    // Disabling the screen saver restores the screen
+    terminal.set_blank_timeout(0);
+    assert!(!terminal.is_blanked());
+    assert!(buffer.iter().any(|byte| *byte != 0));
+}
+
+/// Description: With a timeout of 0, the screen is never blanked
+fn test_blank_disabled() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.set_blank_timeout(0);
+    terminal.check_idle(usize::MAX, 0);
+    assert!(!terminal.is_blanked());
+}
+
+/// Description: "\x1b[?1049h" shows an empty alternate screen, "\x1b[?1049l" restores screen, cursor, colors and history
+fn test_alternate_screen() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+    for i in 0..ROWS * 2 {
+        terminal.write_str(&format!("line {}\n", i));
+    }
+    terminal.write_str("primary");
+
+    let history = {
+        terminal.scroll_view(isize::MAX);
+        let rows = terminal.scroll_offset();
+        terminal.scroll_view(isize::MIN);
+        rows
+    };
+    let primary = buffer.clone();
+    let cursor = terminal.cursor_index();
+
+    terminal.write_str("\x1b[?1049h");
+    assert_eq!(terminal.cursor_index(), COLUMNS as usize);
+    assert_ne!(buffer[row_bytes(COLUMNS)..], primary[row_bytes(COLUMNS)..]);
+
+    // Output on the alternate screen neither changes the primary screen nor its history
+    terminal.write_str("\x1b[32m");
+    for i in 0..ROWS * 2 {
+        terminal.write_str(&format!("alternate {}\n", i));
+    }
+    terminal.scroll_view(1);
+    assert_eq!(terminal.scroll_offset(), 0);
+
+    // Row 0 (status bar) contains the uptime and is skipped
+    terminal.write_str("\x1b[?1049l");
+    assert_eq!(buffer[row_bytes(COLUMNS)..], primary[row_bytes(COLUMNS)..]);
+    assert_eq!(terminal.cursor_index(), cursor);
+    assert_eq!(terminal.colors(), (color::WHITE, color::BLACK));
+    terminal.scroll_view(isize::MAX);
+    assert_eq!(terminal.scroll_offset(), history);
+    terminal.scroll_view(isize::MIN);
+
+    // Leaving the alternate screen again (e.g. by the shell after a program exited) has no effect
+    terminal.write_str("\x1b[?1049l");
+    assert_eq!(buffer[row_bytes(COLUMNS)..], primary[row_bytes(COLUMNS)..]);
+    assert_eq!(terminal.cursor_index(), cursor);
+}
+
+/// Description: Compare scrolling a full screen in a single write with one write per line
+fn benchmark_scroll() {
+    const BENCH_COLUMNS: u32 = 80;
+    const BENCH_ROWS: u32 = 25;
+
+    let mut line = String::new();
+    for _ in 0..BENCH_COLUMNS - 1 {
+        line.push('x');
+    }
+    line.push('\n');
+
+    let mut buffer = buffer_sized(BENCH_COLUMNS, BENCH_ROWS);
+    let terminal = create_terminal_sized(&mut buffer, BENCH_COLUMNS, BENCH_ROWS);
+    let flushed = terminal.flushed_bytes();
+    let start = clock().now_ns();
+    for _ in 0..BENCH_ROWS {
+        terminal.write_str(&line);
+    }
+    let per_line_ns = clock().now_ns() - start;
+    let per_line_bytes = terminal.flushed_bytes() - flushed;
+
+    let mut screen = String::new();
+    for _ in 0..BENCH_ROWS {
+        screen.push_str(&line);
+    }
+    let flushed = terminal.flushed_bytes();
+    let start = clock().now_ns();
+    terminal.write_str(&screen);
+    let single_ns = clock().now_ns() - start;
+    let single_bytes = terminal.flushed_bytes() - flushed;
+
+    assert_eq!(single_bytes, row_bytes(BENCH_COLUMNS) * BENCH_ROWS as usize);
+    assert!(single_bytes < per_line_bytes);
+    info!("lfb_terminal: full screen scroll: {} bytes in {} us (one write per line: {} bytes in {} us)",
+        single_bytes, single_ns / 1000, per_line_bytes, per_line_ns / 1000);
+}
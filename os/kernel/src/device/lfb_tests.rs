@@ -0,0 +1,323 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: lfb_tests                                                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test packing colors with the pixel formats of the LFB (RGB, BGR ║
+   ║         and channel bitmasks), addressing pixels in framebuffers with   ║
+   ║         padded scanlines, as well as drawing characters and scrolling   ║
+   ║         with 32, 24, 16 and 15 bits per pixel. Pixels are drawn into a  ║
+   ║         buffer on the heap and the resulting byte order is checked.     ║
+   ║         Also tests encoding an off-screen framebuffer as PPM image, box ║
+   ║         drawing and block glyphs, the replacement glyph and the damage  ║
+   ║         tracking of the double buffered LFB.                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::vec;
+use ::log::info;
+use graphic::buffered_lfb::{BufferedLFB, Rect};
+use graphic::color::{ChannelMask, Color, PixelFormat};
+use graphic::lfb::{bytes_per_pixel, has_glyph, DEFAULT_CHAR_HEIGHT, DEFAULT_CHAR_WIDTH, LFB, REPLACEMENT_CHAR};
+use graphic::offscreen_lfb::OffscreenLFB;
+
+const WIDTH: u32 = 4;
+const HEIGHT: u32 = 2;
+const COLOR: Color = Color { red: 0x12, green: 0x34, blue: 0x56, alpha: 0xff };
+
+///
+/// Description:
+///    Run all LFB pixel format tests
+///
+pub fn run_tests() {
+    info!("lfb: running tests");
+
+    test_rgb_bgr_32();
+    test_rgb_bgr_24();
+    test_bitmask();
+    test_from_masks();
+    test_padded_pitch();
+    for bpp in [32, 24, 16, 15] {
+        test_char_and_scroll(bpp);
+    }
+    test_ppm();
+    test_box_drawing();
+    test_block_elements();
+    test_replacement_glyph();
+    test_damage_tracking();
+    test_damage_bounding_box();
+
+    info!("lfb: all tests passed.");
+}
+
+/// Description: Draw `COLOR` at pixel (1, 1) of a `bpp` framebuffer with `format` and return the bytes of this pixel
+fn draw(format: PixelFormat, bpp: u8) -> vec::Vec<u8> {
+    draw_padded(format, bpp, 0)
+}
+
+/// Description: Like `draw()`, but each scanline is followed by `padding` unused bytes
+fn draw_padded(format: PixelFormat, bpp: u8, padding: u32) -> vec::Vec<u8> {
+    let bytes_per_pixel = bytes_per_pixel(bpp);
+    let pitch = WIDTH * bytes_per_pixel + padding;
+    let mut buffer = vec![0u8; (pitch * HEIGHT) as usize];
+
+    let lfb = LFB::new(buffer.as_mut_ptr(), pitch, WIDTH, HEIGHT, bpp, format);
+    lfb.draw_pixel(1, 1, COLOR);
+    if bpp == 32 {
+        assert_eq!(lfb.read_pixel(1, 1), COLOR);
+    }
+
+    let offset = (pitch + bytes_per_pixel) as usize;
+    assert_eq!(lfb.pixel_offset(1, 1), offset);
+    assert!(buffer[..offset].iter().chain(&buffer[offset + bytes_per_pixel as usize..]).all(|byte| *byte == 0));
+    buffer[offset..offset + bytes_per_pixel as usize].to_vec()
+}
+
+/// Description: Little endian memory order: blue first for RGB, red first for BGR (alpha is the highest byte)
+fn test_rgb_bgr_32() {
+    assert_eq!(draw(PixelFormat::Rgb, 32), [0x56, 0x34, 0x12, 0xff]);
+    assert_eq!(draw(PixelFormat::Bgr, 32), [0x12, 0x34, 0x56, 0xff]);
+}
+
+fn test_rgb_bgr_24() {
+    assert_eq!(draw(PixelFormat::Rgb, 24), [0x56, 0x34, 0x12]);
+    assert_eq!(draw(PixelFormat::Bgr, 24), [0x12, 0x34, 0x56]);
+}
+
+/// Description: Channels at arbitrary positions (here red in the lowest bits, blue in the highest color bits)
+fn test_bitmask() {
+    let format = PixelFormat::Bitmask { red: ChannelMask::new(0, 8), green: ChannelMask::new(8, 8), blue: ChannelMask::new(16, 8) };
+    assert_eq!(draw(format, 32), draw(PixelFormat::Bgr, 32));
+    assert_eq!(format.unpack(format.pack(COLOR, 32), 32), COLOR);
+
+    // Channels with less than 8 bits keep the highest bits
+    let format = PixelFormat::Bitmask { red: ChannelMask::new(0, 5), green: ChannelMask::new(5, 6), blue: ChannelMask::new(11, 5) };
+    let pixel = format.pack(COLOR, 16);
+    assert_eq!(pixel, (0x12 >> 3) | ((0x34 >> 2) << 5) | ((0x56 >> 3) << 11));
+    assert_eq!(format.unpack(pixel, 16), Color { red: 0x10, green: 0x34, blue: 0x50, alpha: 0 });
+}
+
+/// Description: Masks of the common layouts are detected as RGB or BGR
+fn test_from_masks() {
+    let (high, middle, low) = (ChannelMask::new(16, 8), ChannelMask::new(8, 8), ChannelMask::new(0, 8));
+    assert_eq!(PixelFormat::from_masks(32, high, middle, low), PixelFormat::Rgb);
+    assert_eq!(PixelFormat::from_masks(32, low, middle, high), PixelFormat::Bgr);
+    assert_eq!(PixelFormat::from_masks(16, ChannelMask::new(11, 5), ChannelMask::new(5, 6), ChannelMask::new(0, 5)), PixelFormat::Rgb);
+    assert_eq!(PixelFormat::from_masks(15, ChannelMask::new(0, 5), ChannelMask::new(5, 5), ChannelMask::new(10, 5)), PixelFormat::Bgr);
+
+    let (red, green, blue) = (ChannelMask::new(24, 8), ChannelMask::new(16, 8), ChannelMask::new(8, 8));
+    assert_eq!(PixelFormat::from_masks(32, red, green, blue), PixelFormat::Bitmask { red, green, blue });
+}
+
+/// Description: Rows start every `pitch` bytes, even if the pitch is not a multiple of the pixel size (15 bit pixels use 2 bytes)
+fn test_padded_pitch() {
+    assert_eq!(bytes_per_pixel(15), 2);
+    assert_eq!(bytes_per_pixel(16), 2);
+    assert_eq!(bytes_per_pixel(24), 3);
+    assert_eq!(bytes_per_pixel(32), 4);
+
+    for padding in [1, 3, 6] {
+        assert_eq!(draw_padded(PixelFormat::Rgb, 32, padding), [0x56, 0x34, 0x12, 0xff]);
+        assert_eq!(draw_padded(PixelFormat::Rgb, 24, padding), [0x56, 0x34, 0x12]);
+        assert_eq!(draw_padded(PixelFormat::Rgb, 16, padding), COLOR.rgb_16().to_le_bytes());
+        assert_eq!(draw_padded(PixelFormat::Rgb, 15, padding), COLOR.rgb_15().to_le_bytes());
+    }
+
+    // The last pixel is read without exceeding the framebuffer
+    let pitch = WIDTH * 3 + 1;
+    let mut buffer = vec![0u8; (pitch * (HEIGHT - 1) + WIDTH * 3) as usize];
+    let lfb = LFB::new(buffer.as_mut_ptr(), pitch, WIDTH, HEIGHT, 24, PixelFormat::Rgb);
+    lfb.draw_pixel(WIDTH - 1, HEIGHT - 1, COLOR);
+    assert_eq!(lfb.read_pixel(WIDTH - 1, HEIGHT - 1), Color { alpha: 0, ..COLOR });
+    assert_eq!(buffer[buffer.len() - 3..], [0x56, 0x34, 0x12]);
+}
+
+/// Description: A character is drawn with the (packed) foreground and background colors and scrolling moves whole rows
+fn test_char_and_scroll(bpp: u8) {
+    let format = PixelFormat::Rgb;
+    let (fg_color, bg_color) = (COLOR, Color { red: 0xa0, green: 0xb0, blue: 0xc0, alpha: 0xff });
+    let (width, height) = (DEFAULT_CHAR_WIDTH, 2 * DEFAULT_CHAR_HEIGHT);
+    let pitch = width * bytes_per_pixel(bpp) + 2;
+    let mut buffer = vec![0u8; (pitch * height) as usize];
+    let lfb = LFB::new(buffer.as_mut_ptr(), pitch, width, height, bpp, format);
+
+    // Colors lose their lowest bits with less than 8 bits per channel (and the alpha channel without 32 bits)
+    let stored = |color: Color| format.unpack(format.pack(color, bpp), bpp);
+    assert_eq!(lfb.draw_char(0, DEFAULT_CHAR_HEIGHT, fg_color, bg_color, 'A'), DEFAULT_CHAR_WIDTH);
+    let mut fg_pixels = 0;
+    for y in DEFAULT_CHAR_HEIGHT..height {
+        for x in 0..width {
+            let pixel = lfb.read_pixel(x, y);
+            assert!(pixel == stored(fg_color) || pixel == stored(bg_color), "{} bpp: pixel ({}, {}) = {:?}", bpp, x, y, pixel);
+            fg_pixels += (pixel == stored(fg_color)) as usize;
+        }
+    }
+    assert!(fg_pixels > 0);
+
+    // The upper half is empty, the padding bytes are not written
+    let half = (pitch * DEFAULT_CHAR_HEIGHT) as usize;
+    assert!(buffer[..half].iter().all(|byte| *byte == 0));
+    assert!((DEFAULT_CHAR_HEIGHT..height).all(|y| buffer[((y + 1) * pitch) as usize - 2..((y + 1) * pitch) as usize] == [0, 0]));
+
+    let lower = buffer[half..].to_vec();
+    lfb.scroll_up(DEFAULT_CHAR_HEIGHT);
+    assert_eq!(buffer[..half], lower[..]);
+    assert!(buffer[half..].iter().all(|byte| *byte == 0));
+
+    lfb.scroll_up(height + 1);
+    assert!(buffer.iter().all(|byte| *byte == 0));
+}
+
+/// Description: PPM images start with the size and contain the RGB bytes of all pixels, row by row
+fn test_ppm() {
+    let screen = OffscreenLFB::new(WIDTH, HEIGHT, 16, PixelFormat::Bgr);
+    assert_eq!(screen.bytes().len(), (WIDTH * HEIGHT * 2) as usize);
+    screen.lfb().draw_pixel(1, 0, Color { red: 0xf8, green: 0x80, blue: 0x08, alpha: 0xff });
+
+    let ppm = screen.lfb().to_ppm();
+    let header = format!("P6\n{} {}\n255\n", WIDTH, HEIGHT);
+    assert!(ppm.starts_with(header.as_bytes()));
+    assert_eq!(ppm.len(), header.len() + (WIDTH * HEIGHT * 3) as usize);
+    assert_eq!(ppm[header.len()..header.len() + 6], [0, 0, 0, 0xf8, 0x80, 0x08]);
+    assert!(ppm[header.len() + 6..].iter().all(|byte| *byte == 0));
+}
+
+const FG_COLOR: Color = Color { red: 0xff, green: 0xff, blue: 0xff, alpha: 0xff };
+const BG_COLOR: Color = Color { red: 0, green: 0, blue: 0, alpha: 0xff };
+
+/// Description: Draw `lines` (one character per cell) into an off-screen framebuffer with `FG_COLOR` on `BG_COLOR`
+fn draw_lines(lines: &[&str]) -> OffscreenLFB {
+    let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u32;
+    let screen = OffscreenLFB::new(columns * DEFAULT_CHAR_WIDTH, lines.len() as u32 * DEFAULT_CHAR_HEIGHT, 32, PixelFormat::Rgb);
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            assert_eq!(screen.lfb().draw_char(column as u32 * DEFAULT_CHAR_WIDTH, row as u32 * DEFAULT_CHAR_HEIGHT, FG_COLOR, BG_COLOR, c), DEFAULT_CHAR_WIDTH);
+        }
+    }
+
+    screen
+}
+
+/// Description: Box drawing and block element glyphs exist and are one cell wide
+fn test_box_drawing() {
+    assert!(('\u{2500}'..='\u{259f}').all(has_glyph));
+
+    let screen = draw_lines(&["┌─┐", "│ │", "└─┘"]);
+    let lfb = screen.lfb();
+    let set = |x: u32, y: u32| lfb.read_pixel(x, y) == FG_COLOR;
+
+    // Row of the horizontal line in '─' and column of the vertical line in '│'
+    let line_y = (0..DEFAULT_CHAR_HEIGHT).find(|&y| (DEFAULT_CHAR_WIDTH..2 * DEFAULT_CHAR_WIDTH).all(|x| set(x, y))).expect("No horizontal line in '─'");
+    let line_x = (0..DEFAULT_CHAR_WIDTH).find(|&x| (DEFAULT_CHAR_HEIGHT..2 * DEFAULT_CHAR_HEIGHT).all(|y| set(x, y))).expect("No vertical line in '│'");
+
+    // The lines of the corners meet the lines of the neighbouring cells without gaps
+    let right_x = 2 * DEFAULT_CHAR_WIDTH + line_x;
+    let bottom_y = 2 * DEFAULT_CHAR_HEIGHT + line_y;
+    for y in [line_y, bottom_y] {
+        assert!((line_x..=right_x).all(|x| set(x, y)));
+    }
+    for x in [line_x, right_x] {
+        assert!((line_y..=bottom_y).all(|y| set(x, y)));
+    }
+
+    // Nothing is drawn outside of the box
+    assert!((0..line_x).all(|x| (0..3 * DEFAULT_CHAR_HEIGHT).all(|y| !set(x, y))));
+    assert!((0..line_y).all(|y| (0..3 * DEFAULT_CHAR_WIDTH).all(|x| !set(x, y))));
+}
+
+/// Description: '█' fills the whole cell, '▀' and '▄' the upper and lower half (e.g. for progress bars)
+fn test_block_elements() {
+    let screen = draw_lines(&["█▀▄"]);
+    let lfb = screen.lfb();
+    let half = DEFAULT_CHAR_HEIGHT / 2;
+
+    for x in 0..DEFAULT_CHAR_WIDTH {
+        for y in 0..DEFAULT_CHAR_HEIGHT {
+            assert_eq!(lfb.read_pixel(x, y), FG_COLOR);
+            assert_eq!(lfb.read_pixel(DEFAULT_CHAR_WIDTH + x, y), if y < half { FG_COLOR } else { BG_COLOR });
+            assert_eq!(lfb.read_pixel(2 * DEFAULT_CHAR_WIDTH + x, y), if y < half { BG_COLOR } else { FG_COLOR });
+        }
+    }
+}
+
+/// Description: Characters without glyph are drawn as `REPLACEMENT_CHAR` instead of leaving the cell blank
+fn test_replacement_glyph() {
+    let missing = '\u{10ffff}';
+    assert!(!has_glyph(missing));
+    assert!(has_glyph(REPLACEMENT_CHAR));
+
+    let screen = draw_lines(&[&format!("{}{}", missing, REPLACEMENT_CHAR)]);
+    let lfb = screen.lfb();
+    let mut fg_pixels = 0;
+    for x in 0..DEFAULT_CHAR_WIDTH {
+        for y in 0..DEFAULT_CHAR_HEIGHT {
+            assert_eq!(lfb.read_pixel(x, y), lfb.read_pixel(DEFAULT_CHAR_WIDTH + x, y));
+            fg_pixels += (lfb.read_pixel(x, y) == FG_COLOR) as usize;
+        }
+    }
+    assert!(fg_pixels > 0);
+}
+
+/// Description: Create a double buffered LFB with a cleared back buffer, presenting to the off-screen framebuffer `screen`
+fn buffered(screen: &OffscreenLFB) -> BufferedLFB {
+    let lfb = screen.lfb();
+    let mut buffered = BufferedLFB::new(LFB::new(lfb.buffer(), lfb.pitch(), lfb.width(), lfb.height(), lfb.bpp(), lfb.pixel_format()));
+    buffered.lfb().clear();
+
+    buffered
+}
+
+/// Description: Adjacent dirty rectangles are coalesced and only dirty regions are copied by `present()`
+fn test_damage_tracking() {
+    let screen = OffscreenLFB::new(64, 32, 32, PixelFormat::Rgb);
+    let mut buffered = buffered(&screen);
+
+    buffered.lfb().draw_string(0, 0, FG_COLOR, BG_COLOR, "ab");
+    buffered.mark_dirty_rect(Rect::new(0, 0, DEFAULT_CHAR_WIDTH, DEFAULT_CHAR_HEIGHT));
+    buffered.mark_dirty_rect(Rect::new(DEFAULT_CHAR_WIDTH, 0, DEFAULT_CHAR_WIDTH, DEFAULT_CHAR_HEIGHT));
+    assert_eq!(buffered.dirty_rects(), [Rect::new(0, 0, 2 * DEFAULT_CHAR_WIDTH, DEFAULT_CHAR_HEIGHT)]);
+    assert_eq!(buffered.damage_stats().coalesced, 1);
+
+    // A distant region stays separate, regions outside of the screen are clipped
+    buffered.lfb().draw_char(40, 16, FG_COLOR, BG_COLOR, 'c');
+    buffered.mark_dirty_rect(Rect::new(40, 16, DEFAULT_CHAR_WIDTH, DEFAULT_CHAR_HEIGHT));
+    buffered.mark_dirty_rect(Rect::new(48, 24, 30, 30));
+    buffered.mark_dirty_rect(Rect::new(64, 0, 10, 10));
+    assert_eq!(buffered.dirty_rects().len(), 2);
+    assert!(buffered.dirty_rects().contains(&Rect::new(40, 16, 24, 16)));
+
+    // Drawn, but not marked dirty: not copied
+    buffered.lfb().fill_rect(30, 0, 2, 2, FG_COLOR);
+
+    buffered.present();
+    let stats = buffered.damage_stats();
+    assert_eq!((stats.presents, stats.rects), (1, 2));
+    assert_eq!(stats.flushed_bytes, ((2 * DEFAULT_CHAR_WIDTH + 24) * DEFAULT_CHAR_HEIGHT * 4) as usize);
+    assert!(buffered.dirty_rects().is_empty());
+
+    for (x, y) in [(0, 0), (15, 15), (40, 16), (63, 31)] {
+        assert_eq!(screen.lfb().read_pixel(x, y), buffered.lfb().read_pixel(x, y));
+    }
+    assert_eq!(screen.lfb().read_pixel(30, 0), Color { red: 0, green: 0, blue: 0, alpha: 0 });
+
+    // Nothing dirty: nothing copied
+    buffered.present();
+    assert_eq!(buffered.damage_stats().presents, 1);
+}
+
+/// Description: Too many separate regions are merged into their bounding box, which covers all of them
+fn test_damage_bounding_box() {
+    let screen = OffscreenLFB::new(64, 32, 32, PixelFormat::Rgb);
+    let mut buffered = buffered(&screen);
+
+    for i in 0..17 {
+        buffered.mark_dirty_rect(Rect::new(2 * i, i, 1, 1));
+    }
+    assert_eq!(buffered.dirty_rects(), [Rect::new(0, 0, 33, 17)]);
+
+    // Whole lines are copied with a single copy
+    buffered.mark_dirty(20, 2);
+    buffered.present();
+    assert_eq!(buffered.damage_stats().rects, 2);
+    assert_eq!(buffered.damage_stats().flushed_bytes, (33 * 17 + 64 * 2) * 4);
+}
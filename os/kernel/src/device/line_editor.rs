@@ -0,0 +1,236 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: line_editor                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Input buffer for reading a line with editing and history.       ║
+   ║         Consumes raw input bytes (UTF-8 and ANSI escape sequences):     ║
+   ║           0x08, 0x7f     delete character left of cursor                ║
+   ║           ESC[D, ESC[C   move cursor left/right                         ║
+   ║           ESC[H, ESC[F   move cursor to start/end of line               ║
+   ║           ESC[3~         delete character at cursor                     ║
+   ║           ESC[A, ESC[B   previous/next line from history                ║
+   ║           '\n', '\r'     finish line                                    ║
+   ║         The editor does not draw anything, it only tells the caller     ║
+   ║         when the line must be redrawn (see 'Terminal::read_line').      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use stream::utf8::Utf8Decoder;
+
+/// Number of lines kept in the history
+pub const HISTORY_SIZE: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum EditResult {
+    None,          // nothing visible has changed
+    Redraw,        // line or cursor has changed and must be redrawn
+    Done(String),  // line has been finished (it has been added to the history)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EscapeState {
+    Normal,
+    Escape,     // ESC received
+    Csi(u16),   // ESC[ received, with numeric parameter
+}
+
+pub struct LineEditor {
+    line: Vec<char>,
+    cursor: usize,
+    history: VecDeque<String>,
+    history_index: Option<usize>, // index into history while browsing (0 = most recent)
+    edited_line: Vec<char>,        // line being edited before browsing the history
+    escape: EscapeState,
+    utf8: Utf8Decoder,             // collects multibyte UTF-8 characters
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineEditor {
+    pub const fn new() -> Self {
+        Self {
+            line: Vec::new(),
+            cursor: 0,
+            history: VecDeque::new(),
+            history_index: None,
+            edited_line: Vec::new(),
+            escape: EscapeState::Normal,
+            utf8: Utf8Decoder::new(),
+        }
+    }
+
+    /// Description: Return the current (unfinished) line
+    pub fn line(&self) -> String {
+        self.line.iter().collect()
+    }
+
+    /// Description: Return the cursor position (in characters) within the current line
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Description: Return the history, most recent line first
+    pub fn history(&self) -> impl Iterator<Item = &String> {
+        self.history.iter()
+    }
+
+    ///
+    /// Description: Process one input byte.
+    ///
+    /// Parameters: `byte` next input byte
+    ///
+    pub fn feed(&mut self, byte: u8) -> EditResult {
+        match self.escape {
+            EscapeState::Normal => {}
+            EscapeState::Escape => {
+                self.escape = if byte == b'[' { EscapeState::Csi(0) } else { EscapeState::Normal };
+                return EditResult::None;
+            }
+            EscapeState::Csi(param) => {
+                if byte.is_ascii_digit() {
+                    self.escape = EscapeState::Csi(param.saturating_mul(10).saturating_add((byte - b'0') as u16));
+                    return EditResult::None;
+                }
+
+                self.escape = EscapeState::Normal;
+                return self.handle_csi(byte, param);
+            }
+        }
+
+        // Collect multibyte UTF-8 characters (invalid sequences are inserted as U+FFFD)
+        if self.utf8.is_pending() || byte >= 0x80 {
+            let mut result = EditResult::None;
+            for c in self.utf8.feed(byte) {
+                let next = match c.is_ascii() {
+                    true => self.handle_ascii(c as u8), // interrupted a sequence
+                    false => self.insert(c),
+                };
+                if next != EditResult::None {
+                    result = next;
+                }
+            }
+
+            return result;
+        }
+
+        self.handle_ascii(byte)
+    }
+
+    fn handle_ascii(&mut self, byte: u8) -> EditResult {
+        match byte {
+            0x1b => {
+                self.escape = EscapeState::Escape;
+                EditResult::None
+            }
+            b'\n' | b'\r' => self.finish(),
+            0x08 | 0x7f => self.backspace(),
+            b'\t' => self.insert(' '),
+            byte if byte < 0x20 => EditResult::None, // ignore other control characters
+            byte => self.insert(byte as char),
+        }
+    }
+
+    fn handle_csi(&mut self, action: u8, param: u16) -> EditResult {
+        match (action, param) {
+            (b'D', _) if self.cursor > 0 => {
+                self.cursor -= 1;
+                EditResult::Redraw
+            }
+            (b'C', _) if self.cursor < self.line.len() => {
+                self.cursor += 1;
+                EditResult::Redraw
+            }
+            (b'H', _) => {
+                self.cursor = 0;
+                EditResult::Redraw
+            }
+            (b'F', _) => {
+                self.cursor = self.line.len();
+                EditResult::Redraw
+            }
+            (b'~', 3) if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+                EditResult::Redraw
+            }
+            (b'A', _) => self.history_previous(),
+            (b'B', _) => self.history_next(),
+            _ => EditResult::None,
+        }
+    }
+
+    fn insert(&mut self, c: char) -> EditResult {
+        self.line.insert(self.cursor, c);
+        self.cursor += 1;
+        EditResult::Redraw
+    }
+
+    fn backspace(&mut self) -> EditResult {
+        if self.cursor == 0 {
+            return EditResult::None;
+        }
+
+        self.cursor -= 1;
+        self.line.remove(self.cursor);
+        EditResult::Redraw
+    }
+
+    fn finish(&mut self) -> EditResult {
+        let line: String = self.line.iter().collect();
+        if !line.trim().is_empty() && self.history.front() != Some(&line) {
+            if self.history.len() == HISTORY_SIZE {
+                self.history.pop_back();
+            }
+            self.history.push_front(line.clone());
+        }
+
+        self.line.clear();
+        self.edited_line.clear();
+        self.cursor = 0;
+        self.history_index = None;
+
+        EditResult::Done(line)
+    }
+
+    fn history_previous(&mut self) -> EditResult {
+        let index = match self.history_index {
+            None if !self.history.is_empty() => {
+                self.edited_line = self.line.clone();
+                0
+            }
+            Some(index) if index + 1 < self.history.len() => index + 1,
+            _ => return EditResult::None,
+        };
+
+        self.history_index = Some(index);
+        self.set_line(self.history[index].chars().collect());
+        EditResult::Redraw
+    }
+
+    fn history_next(&mut self) -> EditResult {
+        match self.history_index {
+            None => return EditResult::None,
+            Some(0) => {
+                self.history_index = None;
+                self.set_line(self.edited_line.clone());
+            }
+            Some(index) => {
+                self.history_index = Some(index - 1);
+                self.set_line(self.history[index - 1].chars().collect());
+            }
+        }
+
+        EditResult::Redraw
+    }
+
+    fn set_line(&mut self, line: Vec<char>) {
+        self.line = line;
+        self.cursor = self.line.len();
+    }
+}
@@ -0,0 +1,112 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: line_editor_tests                                               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test line editing and history of the terminal line editor.      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::string::String;
+use ::log::info;
+
+use crate::device::line_editor::{EditResult, LineEditor, HISTORY_SIZE};
+
+///
+/// Description:
+///    Run all line editor tests
+///
+pub fn run_tests() {
+    info!("line_editor: running tests");
+
+    test_backspace();
+    test_cursor_movement();
+    test_utf8();
+    test_history();
+    test_history_limit();
+
+    info!("line_editor: all tests passed.");
+}
+
+/// Description: Feed all bytes of `input` and return the result of the last byte
+fn feed(editor: &mut LineEditor, input: &[u8]) -> EditResult {
+    let mut result = EditResult::None;
+    for byte in input {
+        result = editor.feed(*byte);
+    }
+
+    result
+}
+
+fn done(line: &str) -> EditResult {
+    EditResult::Done(String::from(line))
+}
+
+/// Description: Backspace removes the character left of the cursor and is ignored on an empty line
+fn test_backspace() {
+    let mut editor = LineEditor::new();
+    assert_eq!(feed(&mut editor, b"\x08"), EditResult::None);
+    assert_eq!(feed(&mut editor, b"lsx\x08\n"), done("ls"));
+    assert_eq!(feed(&mut editor, b"ab\x7f\x7fcd\n"), done("cd"));
+}
+
+/// Description: Cursor keys move within the line, characters are inserted at the cursor
+fn test_cursor_movement() {
+    let mut editor = LineEditor::new();
+    assert_eq!(feed(&mut editor, b"helo\x1b[D"), EditResult::Redraw);
+    assert_eq!(editor.cursor(), 3);
+    assert_eq!(feed(&mut editor, b"l\n"), done("hello"));
+
+    assert_eq!(feed(&mut editor, b"world\x1b[H"), EditResult::Redraw);
+    assert_eq!(editor.cursor(), 0);
+    assert_eq!(feed(&mut editor, b"\x1b[3~\x1b[C\x1b[C\x08"), EditResult::Redraw);
+    assert_eq!(editor.line(), "old");
+    assert_eq!(feed(&mut editor, b"\x1b[F!\n"), done("old!"));
+
+    // Moving beyond the line boundaries has no effect
+    assert_eq!(feed(&mut editor, b"\x1b[D"), EditResult::None);
+    assert_eq!(feed(&mut editor, b"a\x1b[C"), EditResult::None);
+}
+
+/// Description: Multibyte UTF-8 characters count as one character
+fn test_utf8() {
+    let mut editor = LineEditor::new();
+    assert_eq!(feed(&mut editor, "grüß".as_bytes()), EditResult::Redraw);
+    assert_eq!(editor.cursor(), 4);
+    assert_eq!(feed(&mut editor, b"\x08\x08\n"), done("gr"));
+}
+
+/// Description: Up/down browse the history and restore the line being edited
+fn test_history() {
+    let mut editor = LineEditor::new();
+    feed(&mut editor, b"first\n");
+    feed(&mut editor, b"second\n");
+    feed(&mut editor, b"second\n"); // consecutive duplicates are stored once
+    feed(&mut editor, b"   \n");    // empty lines are not stored
+    assert_eq!(editor.history().count(), 2);
+
+    feed(&mut editor, b"draft");
+    assert_eq!(feed(&mut editor, b"\x1b[A"), EditResult::Redraw);
+    assert_eq!(editor.line(), "second");
+    assert_eq!(feed(&mut editor, b"\x1b[A"), EditResult::Redraw);
+    assert_eq!(editor.line(), "first");
+    assert_eq!(feed(&mut editor, b"\x1b[A"), EditResult::None);
+    assert_eq!(feed(&mut editor, b"\x1b[B\x1b[B"), EditResult::Redraw);
+    assert_eq!(editor.line(), "draft");
+    assert_eq!(feed(&mut editor, b"\x1b[B"), EditResult::None);
+
+    assert_eq!(feed(&mut editor, b"\x1b[A\x1b[A!\n"), done("first!"));
+    assert_eq!(editor.history().next().map(|line| line.as_str()), Some("first!"));
+}
+
+/// Description: The history keeps only the last HISTORY_SIZE lines
+fn test_history_limit() {
+    let mut editor = LineEditor::new();
+    for i in 0..HISTORY_SIZE + 4 {
+        feed(&mut editor, format!("cmd{}\n", i).as_bytes());
+    }
+
+    assert_eq!(editor.history().count(), HISTORY_SIZE);
+    assert_eq!(editor.history().next().map(|line| line.as_str()), Some(format!("cmd{}", HISTORY_SIZE + 3).as_str()));
+    assert_eq!(editor.history().last().map(|line| line.as_str()), Some("cmd4"));
+}
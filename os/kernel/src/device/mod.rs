@@ -1,11 +1,27 @@
 pub mod apic;
+pub mod clock;
+pub mod compose;
 pub mod pit;
+pub mod keyboard_layout;
+pub mod key_repeat;
 pub mod ps2;
 pub mod qemu_cfg;
+pub mod rng;
 pub mod speaker;
 #[macro_use]
 pub mod terminal;
 pub mod lfb_terminal;
+pub mod line_editor;
 pub mod serial;
 pub mod pci;
 pub mod rtl8139;
+pub mod vga_text;
+pub mod compose_tests;
+pub mod pit_tests;
+pub mod keyboard_layout_tests;
+pub mod key_repeat_tests;
+pub mod lfb_tests;
+pub mod lfb_terminal_tests;
+pub mod line_editor_tests;
+pub mod utf8_tests;
+pub mod vga_text_tests;
@@ -2,13 +2,69 @@ use alloc::boxed::Box;
 use alloc::sync::Arc;
 use crate::interrupt::interrupt_dispatcher::InterruptVector;
 use crate::interrupt::interrupt_handler::InterruptHandler;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicU64, Ordering};
 use spin::Mutex;
 use x86_64::instructions::port::{Port, PortWriteOnly};
-use crate::{apic, interrupt_dispatcher};
+use crate::{apic, interrupt_dispatcher, scheduler};
 
 pub const BASE_FREQUENCY: usize = 1193182;
-const NANOSECONDS_PER_TICK: usize = 1000000000 / BASE_FREQUENCY;
+const NANOSECONDS_PER_SECOND: u64 = 1000000000;
+
+///
+/// Description:
+///    Compute `value * numerator / denominator`, rounded to the nearest integer.
+///    A 128-bit intermediate is used, so the multiplication cannot overflow.
+///    Results not fitting into an u64 saturate at `u64::MAX`.
+///
+const fn scale(value: u64, numerator: u64, denominator: u64) -> u64 {
+    if denominator == 0 {
+        return 0;
+    }
+
+    let result = (value as u128 * numerator as u128 + denominator as u128 / 2) / denominator as u128;
+    if result > u64::MAX as u128 { u64::MAX } else { result as u64 }
+}
+
+/// Description: Convert PIT ticks to nanoseconds (rounded to nearest, saturating)
+pub const fn ticks_to_nanos(ticks: u64) -> u64 {
+    scale(ticks, NANOSECONDS_PER_SECOND, BASE_FREQUENCY as u64)
+}
+
+/// Description: Convert nanoseconds to PIT ticks (rounded to nearest)
+pub const fn nanos_to_ticks(nanos: u64) -> u64 {
+    scale(nanos, BASE_FREQUENCY as u64, NANOSECONDS_PER_SECOND)
+}
+
+///
+/// Description:
+///    Number of PIT ticks, a thread sleeping for `ms` milliseconds has to wait. The system time only
+///    advances once per interrupt (by `interval_ticks`), so the sleep time is rounded up to whole
+///    interrupt intervals. One more interval is added for the interval already in progress, so the
+///    thread sleeps at least `ms` milliseconds.
+///
+/// Parameters: \
+///    `ms` sleep time in milliseconds \
+///    `interval_ticks` PIT ticks per timer interrupt (see `Timer::interrupt_rate()`)
+///
+pub const fn sleep_ticks(ms: usize, interval_ticks: u64) -> u64 {
+    let ticks = nanos_to_ticks((ms as u64).saturating_mul(1000000));
+    if interval_ticks == 0 {
+        return ticks;
+    }
+
+    ticks.div_ceil(interval_ticks).saturating_add(1).saturating_mul(interval_ticks)
+}
+
+///
+/// Description: Convert TSC cycles to nanoseconds (rounded to nearest, saturating)
+///
+/// Parameters: \
+///    `cycles` number of TSC cycles \
+///    `tsc_frequency` TSC frequency in Hz (0 yields 0, since the frequency is unknown)
+///
+pub const fn tsc_to_nanos(cycles: u64, tsc_frequency: u64) -> u64 {
+    scale(cycles, NANOSECONDS_PER_SECOND, tsc_frequency)
+}
 
 #[derive(Copy, Clone)]
 #[allow(dead_code)]
@@ -52,8 +108,8 @@ enum Channel  {
 
 pub struct Timer {
     registers: Mutex<Registers>,
-    interval_ns: usize,
-    systime_ns: AtomicUsize,
+    interval_ticks: u64,
+    systime_ticks: AtomicU64,
 }
 
 struct Registers {
@@ -93,6 +149,7 @@ impl Command {
 impl InterruptHandler for TimerInterruptHandler {
     fn trigger(&self) {
         self.timer.inc_systime();
+        scheduler().wake_up_sleeping();
     }
 }
 
@@ -115,8 +172,8 @@ impl Timer {
     pub fn new() -> Self {
         let mut timer = Self {
             registers: Mutex::new(Registers::new()),
-            interval_ns: 0,
-            systime_ns: AtomicUsize::new(0)
+            interval_ticks: 0,
+            systime_ticks: AtomicU64::new(0)
         };
 
         timer.interrupt_rate(1);
@@ -124,12 +181,12 @@ impl Timer {
     }
 
     fn interrupt_rate(&mut self, interval_ms: usize) {
-        let mut divisor = (interval_ms * 1000000) / NANOSECONDS_PER_TICK;
-        if divisor > u16::MAX as usize {
-            divisor = u16::MAX as usize;
+        let mut divisor = nanos_to_ticks((interval_ms as u64).saturating_mul(1000000));
+        if divisor > u16::MAX as u64 {
+            divisor = u16::MAX as u64;
         }
 
-        self.interval_ns = NANOSECONDS_PER_TICK * divisor;
+        self.interval_ticks = divisor;
 
         let command = Command::new(OperatingMode::RateGenerator, AccessMode::LowByteHighByte);
         let mut registers = self.registers.lock();
@@ -160,31 +217,45 @@ impl Timer {
         apic().allow(InterruptVector::Pit);
     }
 
+    /// Description: System time in PIT ticks (advanced by the interval of the timer with every interrupt)
+    pub fn systime_ticks(&self) -> u64 {
+        self.systime_ticks.load(Ordering::Relaxed)
+    }
+
+    /// Description: Number of PIT ticks, a thread sleeping for `ms` milliseconds has to wait (see `sleep_ticks()`)
+    pub fn sleep_ticks(&self, ms: usize) -> u64 {
+        sleep_ticks(ms, self.interval_ticks)
+    }
+
     pub fn systime_ms(&self) -> usize {
-        self.systime_ns.load(Ordering::Relaxed) / 1000000
+        self.systime_ns() / 1000000
+    }
+
+    pub fn systime_ns(&self) -> usize {
+        ticks_to_nanos(self.systime_ticks.load(Ordering::Relaxed)) as usize
     }
 
     pub fn wait(&self, wait_time_ms: usize) {
-        let wait_time_ns = wait_time_ms * 1000000;
-        let mut elapsed_time_ns = 0;
+        let wait_time_ticks = nanos_to_ticks((wait_time_ms as u64).saturating_mul(1000000));
+        let mut elapsed_ticks = 0u64;
         let mut last_timer_value = self.read_timer();
 
-        while elapsed_time_ns < wait_time_ns {
+        while elapsed_ticks < wait_time_ticks {
             let timer_value = self.read_timer();
             let ticks = if last_timer_value >= timer_value {
                 // Timer did not wrap around
                 last_timer_value - timer_value
             } else {
                 // Timer wrapped around
-                (self.interval_ns / NANOSECONDS_PER_TICK) as u16 - timer_value + last_timer_value
+                self.interval_ticks as u16 - timer_value + last_timer_value
             };
 
-            elapsed_time_ns += ticks as usize * NANOSECONDS_PER_TICK;
+            elapsed_ticks += ticks as u64;
             last_timer_value = timer_value;
         }
     }
 
     fn inc_systime(&self) {
-        self.systime_ns.fetch_add(self.interval_ns, Ordering::Relaxed);
+        self.systime_ticks.fetch_add(self.interval_ticks, Ordering::Relaxed);
     }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pit_tests                                                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the overflow-safe time conversions of the timer module.    ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+
+use crate::device::pit::{nanos_to_ticks, sleep_ticks, ticks_to_nanos, tsc_to_nanos, BASE_FREQUENCY};
+
+const NANOS_PER_TICK: u64 = 839; // 838.095... rounded up
+
+///
+/// Description:
+///    Run all conversion tests
+///
+pub fn run_tests() {
+    info!("pit: running tests");
+
+    test_exact_values();
+    test_round_trip();
+    test_large_values();
+    test_tsc();
+    test_sleep_ticks();
+
+    info!("pit: all tests passed.");
+}
+
+/// Description: One second worth of ticks is exactly one second
+fn test_exact_values() {
+    assert_eq!(ticks_to_nanos(0), 0);
+    assert_eq!(ticks_to_nanos(BASE_FREQUENCY as u64), 1_000_000_000);
+    assert_eq!(nanos_to_ticks(1_000_000_000), BASE_FREQUENCY as u64);
+    assert_eq!(ticks_to_nanos(1), 838); // 838.095... rounded to nearest
+}
+
+/// Description: Converting back and forth loses at most one tick worth of nanoseconds
+fn test_round_trip() {
+    for ticks in [1, 2, 1000, 65535, 1 << 40, u64::MAX / 1_000_000_000] {
+        assert_eq!(nanos_to_ticks(ticks_to_nanos(ticks)), ticks, "round trip of {} ticks", ticks);
+    }
+
+    for nanos in [1_000, 1_000_000, 123_456_789_000, u64::MAX / 2, u64::MAX] {
+        let back = ticks_to_nanos(nanos_to_ticks(nanos));
+        assert!(back.abs_diff(nanos) <= NANOS_PER_TICK, "round trip of {} ns -> {} ns", nanos, back);
+    }
+}
+
+/// Description: Values near u64::MAX must not overflow (results saturate)
+fn test_large_values() {
+    // 10 years of uptime, where 'ticks * 1_000_000_000' overflows an u64
+    let ticks = 10 * 365 * 24 * 3600 * BASE_FREQUENCY as u64;
+    assert!(ticks.checked_mul(1_000_000_000).is_none());
+    assert_eq!(ticks_to_nanos(ticks), 10 * 365 * 24 * 3600 * 1_000_000_000);
+
+    assert_eq!(ticks_to_nanos(u64::MAX), u64::MAX);
+    assert_eq!(nanos_to_ticks(u64::MAX), 22_010_322_987_356_910); // (2^64 - 1) * 1193182 / 10^9, rounded
+    assert!(nanos_to_ticks(u64::MAX - 1) <= nanos_to_ticks(u64::MAX));
+}
+
+/// Description: TSC conversion uses the given frequency, an unknown frequency yields 0
+fn test_tsc() {
+    assert_eq!(tsc_to_nanos(3_000_000_000, 3_000_000_000), 1_000_000_000);
+    assert_eq!(tsc_to_nanos(3, 3_000_000_000), 1);
+    assert_eq!(tsc_to_nanos(u64::MAX, 1_000_000_000), u64::MAX);
+    assert_eq!(tsc_to_nanos(u64::MAX, 4_000_000_000), u64::MAX / 4 + 1); // rounded to nearest
+    assert_eq!(tsc_to_nanos(1000, 0), 0);
+}
+
+/// Description: Sleep times are rounded up to whole interrupt intervals, plus the interval in progress
+fn test_sleep_ticks() {
+    let interval = nanos_to_ticks(1_000_000); // 1 ms per interrupt
+    assert_eq!(sleep_ticks(1, interval), 2 * interval);
+    assert_eq!(sleep_ticks(2, interval), 3 * interval);
+
+    // 10 ms per interrupt -> 1 ms and 10 ms both need a full interval
+    let interval = nanos_to_ticks(10_000_000);
+    assert_eq!(sleep_ticks(1, interval), 2 * interval);
+    assert_eq!(sleep_ticks(10, interval), 2 * interval);
+    assert_eq!(sleep_ticks(11, interval), 3 * interval);
+    assert!(ticks_to_nanos(sleep_ticks(25, interval) - interval) >= 25_000_000);
+
+    assert!(sleep_ticks(usize::MAX, interval) >= nanos_to_ticks(u64::MAX));
+    assert_eq!(sleep_ticks(5, 0), nanos_to_ticks(5_000_000));
+}
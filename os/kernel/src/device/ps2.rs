@@ -1,5 +1,7 @@
 use alloc::boxed::Box;
 use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU8, AtomicUsize};
+use core::sync::atomic::Ordering::Relaxed;
 use crate::interrupt::interrupt_dispatcher::InterruptVector;
 use crate::interrupt::interrupt_handler::InterruptHandler;
 use stream::InputStream;
@@ -10,10 +12,23 @@ use ps2::{Controller, KeyboardType};
 use ps2::error::{ControllerError, KeyboardError};
 use spin::Mutex;
 use spin::once::Once;
+use x86_64::instructions::interrupts;
+use crate::device::keyboard_layout;
 use crate::{apic, interrupt_dispatcher};
 
 const KEYBOARD_BUFFER_CAPACITY: usize = 128;
 
+/// Keyboard command setting the LEDs (followed by a data byte with the `KeyboardLedFlags`)
+const SET_LEDS_COMMAND: u8 = 0xed;
+/// Responses of the keyboard to each byte of a command
+const ACKNOWLEDGE: u8 = 0xfa;
+const RESEND: u8 = 0xfe;
+
+/// Progress of the 'set LEDs' command (see `Keyboard::set_leds()`)
+const LEDS_IDLE: u8 = 0;
+const LEDS_COMMAND_SENT: u8 = 1; // waiting for the acknowledgement of SET_LEDS_COMMAND
+const LEDS_DATA_SENT: u8 = 2;    // waiting for the acknowledgement of the LED byte
+
 pub struct PS2 {
     controller: Arc<Mutex<Controller>>,
     keyboard: Once<Arc<Keyboard>>,
@@ -22,6 +37,10 @@ pub struct PS2 {
 pub struct Keyboard {
     controller: Arc<Mutex<Controller>>,
     buffer: (mpmc::bounded::scq::Receiver<u8>, mpmc::bounded::scq::Sender<u8>),
+    received_bytes: AtomicUsize, // scancodes received so far (to detect input, even if nobody reads it)
+    leds: AtomicU8,              // LEDs to be shown (see 'set_leds()')
+    sent_leds: AtomicU8,         // LEDs sent with the last 'set LEDs' command
+    led_command: AtomicU8,       // progress of the 'set LEDs' command (LEDS_IDLE, LEDS_COMMAND_SENT or LEDS_DATA_SENT)
 }
 
 struct KeyboardInterruptHandler {
@@ -30,13 +49,79 @@ struct KeyboardInterruptHandler {
 
 impl Keyboard {
     fn new(controller: Arc<Mutex<Controller>>, buffer_cap: usize) -> Self {
-        Self { controller, buffer: mpmc::bounded::scq::queue(buffer_cap) }
+        Self { controller, buffer: mpmc::bounded::scq::queue(buffer_cap), received_bytes: AtomicUsize::new(0),
+            leds: AtomicU8::new(keyboard_layout::INITIAL_LEDS.bits()), sent_leds: AtomicU8::new(keyboard_layout::INITIAL_LEDS.bits()), led_command: AtomicU8::new(LEDS_IDLE) }
     }
 
     pub fn plugin(keyboard: Arc<Keyboard>) {
         interrupt_dispatcher().assign(InterruptVector::Keyboard, Box::new(KeyboardInterruptHandler::new(Arc::clone(&keyboard))));
         apic().allow(InterruptVector::Keyboard);
     }
+
+    /// Description: Return the next scancode, or `None` if there is none (never blocks)
+    pub fn try_read_byte(&self) -> Option<u8> {
+        self.buffer.0.try_dequeue().ok()
+    }
+
+    /// Description: Number of scancodes received so far (changes with every key press or release)
+    pub fn received_bytes(&self) -> usize {
+        self.received_bytes.load(Relaxed)
+    }
+
+    ///
+    /// Description:
+    ///    Switch the keyboard LEDs to `leds` (never blocks). Only the command byte is sent here,
+    ///    the LED byte is sent by the interrupt handler, when the keyboard has acknowledged the
+    ///    command (see `handle_led_response()`). If a command is still in progress, `leds` are
+    ///    sent, when it has been completed.
+    ///
+    pub fn set_leds(&self, leds: KeyboardLedFlags) {
+        // The interrupt handler must not find the controller locked
+        interrupts::without_interrupts(|| {
+            self.leds.store(leds.bits(), Relaxed);
+            if self.led_command.load(Relaxed) == LEDS_IDLE {
+                self.send_led_byte(&mut self.controller.lock(), LEDS_COMMAND_SENT, SET_LEDS_COMMAND);
+            }
+        });
+    }
+
+    /// Description: Send `byte` of the 'set LEDs' command and continue with `stage` (the command is given up, if sending fails)
+    fn send_led_byte(&self, controller: &mut Controller, stage: u8, byte: u8) {
+        match controller.write_data(byte) {
+            Ok(()) => self.led_command.store(stage, Relaxed),
+            Err(_) => self.led_command.store(LEDS_IDLE, Relaxed),
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Continue the 'set LEDs' command with the response `data` of the keyboard (called by
+    ///    the interrupt handler). The keyboard acknowledges each byte or requests to resend it.
+    ///
+    /// Return: `true`, if `data` has been a response to the command (and is not a scancode)
+    ///
+    fn handle_led_response(&self, controller: &mut Controller, data: u8) -> bool {
+        match (self.led_command.load(Relaxed), data) {
+            (LEDS_COMMAND_SENT, ACKNOWLEDGE) => {
+                let leds = self.leds.load(Relaxed);
+                self.sent_leds.store(leds, Relaxed);
+                self.send_led_byte(controller, LEDS_DATA_SENT, leds);
+            }
+            (LEDS_COMMAND_SENT, RESEND) => self.send_led_byte(controller, LEDS_COMMAND_SENT, SET_LEDS_COMMAND),
+            (LEDS_DATA_SENT, ACKNOWLEDGE) => {
+                // The LEDs may have been changed, while the command has been in progress
+                if self.leds.load(Relaxed) != self.sent_leds.load(Relaxed) {
+                    self.send_led_byte(controller, LEDS_COMMAND_SENT, SET_LEDS_COMMAND);
+                } else {
+                    self.led_command.store(LEDS_IDLE, Relaxed);
+                }
+            }
+            (LEDS_DATA_SENT, RESEND) => self.send_led_byte(controller, LEDS_DATA_SENT, self.sent_leds.load(Relaxed)),
+            _ => return false,
+        }
+
+        true
+    }
 }
 
 impl InputStream for Keyboard {
@@ -61,6 +146,11 @@ impl InterruptHandler for KeyboardInterruptHandler {
     fn trigger(&self) {
         if let Some(mut controller) = self.keyboard.controller.try_lock() {
             if let Ok(data) = controller.read_data() {
+                if self.keyboard.handle_led_response(&mut controller, data) {
+                    return;
+                }
+
+                self.keyboard.received_bytes.fetch_add(1, Relaxed);
                 while self.keyboard.buffer.1.try_enqueue(data).is_err() {
                     if self.keyboard.buffer.0.try_dequeue().is_err() {
                         panic!("Keyboard: Failed to store received byte in buffer!");
@@ -149,7 +239,7 @@ impl PS2 {
         controller.keyboard().set_defaults()?;
         controller.keyboard().set_scancode_set(1)?;
         controller.keyboard().set_typematic_rate_and_delay(0)?;
-        controller.keyboard().set_leds(KeyboardLedFlags::empty())?;
+        controller.keyboard().set_leds(keyboard_layout::INITIAL_LEDS)?;
         controller.keyboard().enable_scanning()?;
 
         self.keyboard.call_once(|| {
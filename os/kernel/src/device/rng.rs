@@ -0,0 +1,40 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: rng                                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Random numbers from the hardware random number generator of the ║
+   ║         CPU (RDRAND). If it is not available (or fails), the time stamp ║
+   ║         counter is scrambled instead, which is not secure, but still    ║
+   ║         differs across boots. Works without heap (usable during boot).  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::arch::x86_64::_rdtsc;
+use x86_64::instructions::random::RdRand;
+
+/// Description: Check if the CPU has a hardware random number generator
+pub fn has_rdrand() -> bool {
+    RdRand::new().is_some()
+}
+
+/// Description: Random 64-bit number (from RDRAND, if available)
+pub fn random_u64() -> u64 {
+    // RDRAND may fail temporarily, if the entropy is exhausted (Intel recommends 10 retries)
+    if let Some(rdrand) = RdRand::new() {
+        for _ in 0..10 {
+            if let Some(random) = rdrand.get_u64() {
+                return random;
+            }
+        }
+    }
+
+    mix(unsafe { _rdtsc() })
+}
+
+/// Description: Scramble all bits of `value` (finalizer of SplitMix64)
+const fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
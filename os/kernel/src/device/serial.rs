@@ -3,7 +3,6 @@ use crate::device::serial::ComPort::{Com1, Com2, Com3, Com4};
 use crate::interrupt::interrupt_dispatcher::InterruptVector;
 use crate::interrupt::interrupt_handler::InterruptHandler;
 use stream::{InputStream, OutputStream};
-use alloc::string::String;
 use alloc::sync::Arc;
 use core::ptr;
 use bitflags::bitflags;
@@ -249,7 +248,12 @@ pub fn check_port(port: ComPort) -> bool {
 
 impl OutputStream for SerialPort {
     fn write_byte(&self, b: u8) {
-        self.write_str(&String::from(char::from(b)));
+        // Write the raw byte (converting it to a char would encode bytes >= 0x80 as two bytes)
+        if b == b'\n' {
+            self.transceiver.write(b'\r');
+        }
+
+        self.transceiver.write(b);
     }
 
     fn write_str(&self, string: &str) {
@@ -291,14 +295,8 @@ impl InterruptHandler for SerialInterruptHandler {
         }
 
         let transceiver = &self.serial_port.transceiver;
-        if let Some(buffer) = &self.serial_port.buffer {
-            while let Some(data) = transceiver.read() {
-                while buffer.1.try_enqueue(data).is_err() {
-                    if buffer.0.try_dequeue().is_err() {
-                        panic!("Serial: Failed to store received byte in buffer!");
-                    }
-                }
-            }
+        while let Some(data) = transceiver.read() {
+            self.serial_port.receive(data);
         }
     }
 }
@@ -330,6 +328,45 @@ impl SerialPort {
         }
     }
 
+    ///
+    /// Description:
+    ///    Create a second handle for a port, which has already been configured by `new()`. The port
+    ///    is not touched (its interrupts stay enabled) and received bytes only reach this handle,
+    ///    if they are passed to `receive()` (e.g. by tests simulating received data).
+    ///
+    pub(crate) fn new_detached(port: ComPort, buffer_cap: usize) -> Self {
+        Self {
+            port,
+            transceiver: Transceiver::new(port),
+            interrupt_status: Mutex::new(PortReadOnly::new(port as u16 + 2)),
+            buffer: Some(mpmc::bounded::scq::queue(buffer_cap))
+        }
+    }
+
+    /// Description: Return the next received byte, or `None` if there is none (never blocks)
+    pub fn try_read_byte(&self) -> Option<u8> {
+        match &self.buffer {
+            Some(buffer) => buffer.0.try_dequeue().ok(),
+            None => None,
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Store a received byte for `read_byte()` (called by the interrupt handler). If the buffer
+    ///    is full, the oldest byte is dropped. Does not lock any register, so the logger can
+    ///    write to the port at the same time.
+    ///
+    pub(crate) fn receive(&self, byte: u8) {
+        if let Some(buffer) = &self.buffer {
+            while buffer.1.try_enqueue(byte).is_err() {
+                if buffer.0.try_dequeue().is_err() {
+                    panic!("Serial: Failed to store received byte in buffer!");
+                }
+            }
+        }
+    }
+
     pub fn plugin(serial_port: Arc<SerialPort>) {
         let vector = match serial_port.port {
             Com1 | Com3 => InterruptVector::Com1,
@@ -1,11 +1,87 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use graphic::color::Color;
 use stream::{InputStream, OutputStream};
 use core::fmt::Write;
 use core::ops::Deref;
 use core::{fmt, ptr};
+use syscall::keyboard::KeyboardLayout;
 use crate::terminal;
 
 pub trait Terminal: OutputStream + InputStream {
     fn clear(&self);
+
+    /// Move the cursor to column `x` and row `y` of the character grid (clamped to the grid, row 0 is the status bar)
+    fn set_cursor(&self, x: u16, y: u16);
+
+    /// Do not draw the blinking cursor (e.g. for full screen programs, which position text arbitrarily)
+    fn hide_cursor(&self);
+
+    fn show_cursor(&self);
+
+    /// Set the distance of tab stops in columns (default 8, at least 1)
+    fn set_tab_width(&self, width: u16);
+
+    /// Translate the scancodes of the keyboard with `layout` (pressed modifier keys are forgotten, lock keys are reset) and return the previous layout
+    fn set_keyboard_layout(&self, layout: KeyboardLayout) -> KeyboardLayout;
+
+    /// Repeat a held key after `delay_ms` every `interval_ms` (0 = keys are not repeated)
+    fn set_key_repeat(&self, delay_ms: usize, interval_ms: usize);
+
+    /// Read a line with editing (backspace, cursor keys) and history (up/down keys), without the newline
+    fn read_line(&self) -> String;
+
+    /// Blank the screen after `timeout_ms` without keyboard input (0 = never). The next input restores the screen.
+    fn set_blank_timeout(&self, timeout_ms: usize);
+}
+
+///
+/// Screen of a terminal (linear framebuffer or VGA text mode). The terminal keeps the text,
+/// cursor and escape sequence state and calls the backend to draw it. All positions and sizes
+/// are given in character cells (column, row). Drawing goes to a back buffer (the live screen),
+/// which is copied to the screen by `flush()`, except for the `*_direct()` functions.
+///
+pub trait TerminalBackend {
+    /// Number of columns and rows
+    fn size(&self) -> (u16, u16);
+
+    /// Draw `c` at `pos` and return the number of columns it covers (0, if there is no glyph for `c`)
+    fn draw_char(&mut self, pos: (u16, u16), c: char, fg_color: Color, bg_color: Color) -> u16;
+
+    /// Draw `c` at `pos` directly to the screen, bypassing the back buffer (cursor and history)
+    fn draw_char_direct(&mut self, pos: (u16, u16), c: char, fg_color: Color, bg_color: Color);
+
+    /// Fill `size` cells, starting at `pos`, with `bg_color`
+    fn fill(&mut self, pos: (u16, u16), size: (u16, u16), bg_color: Color);
+
+    /// Move all rows up by one row and fill the last row with `bg_color`
+    fn scroll_up(&mut self, bg_color: Color);
+
+    /// Draw the status bar in row 0 with `info` left aligned and `date` (if any) right aligned
+    fn draw_status_bar(&mut self, info: &str, date: Option<&str>);
+
+    /// Copy rows changed since the last flush from the back buffer to the screen
+    fn flush(&mut self);
+
+    /// Copy the whole back buffer to the screen (e.g. after drawing the history directly)
+    fn restore(&mut self);
+
+    /// Clear the screen, but not the back buffer (the screen saver brings it back with `restore()`)
+    fn blank(&mut self);
+
+    /// Number of bytes copied to the screen so far
+    fn flushed_bytes(&self) -> usize;
+
+    /// PPM image of the live screen (see `LFB::to_ppm()`)
+    fn screenshot(&mut self) -> Vec<u8>;
+
+    /// The backend has a hardware cursor (the terminal does not draw a blinking cursor)
+    fn has_hardware_cursor(&self) -> bool {
+        false
+    }
+
+    /// Move the hardware cursor to `pos` or disable it (`None`)
+    fn set_hardware_cursor(&mut self, _pos: Option<(u16, u16)>) {}
 }
 
 // Implementation of the 'core::fmt::Write' trait for our Terminal
@@ -39,5 +115,8 @@ pub fn print(args: fmt::Arguments) {
     // Writing to LFBTerminal does not need a mutable reference,
     // so it is safe to construct a mutable reference here and use it for writing.
     let terminal_mut = unsafe { ptr::from_ref(terminal.as_ref()).cast_mut().as_mut().unwrap() };
-    terminal_mut.write_fmt(args).unwrap();
+    if terminal_mut.write_fmt(args).is_err() {
+        // A formatting trait implementation has failed (e.g. out of memory) -> mark the incomplete output with glyphs directly
+        OutputStream::write_str(terminal.as_ref(), "<formatting failed>\n");
+    }
 }
@@ -0,0 +1,97 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: utf8_tests                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the streaming UTF-8 decoder used for terminal input.       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::char::REPLACEMENT_CHARACTER;
+use ::log::info;
+use stream::utf8::Utf8Decoder;
+
+use crate::device::line_editor::{EditResult, LineEditor};
+
+///
+/// Description:
+///    Run all UTF-8 decoder tests
+///
+pub fn run_tests() {
+    info!("utf8: running tests");
+
+    test_byte_by_byte();
+    test_all_lengths();
+    test_invalid();
+    test_interrupted();
+    test_line_editor();
+
+    info!("utf8: all tests passed.");
+}
+
+/// Description: Feed all `bytes` one at a time and collect the decoded characters
+fn decode(decoder: &mut Utf8Decoder, bytes: &[u8]) -> String {
+    bytes.iter().flat_map(|byte| decoder.feed(*byte)).collect()
+}
+
+/// Description: A character is only returned, once all its bytes have been fed
+fn test_byte_by_byte() {
+    let mut decoder = Utf8Decoder::new();
+    let chars = "héllo".bytes().map(|byte| decoder.feed(byte).collect::<Vec<char>>()).collect::<Vec<Vec<char>>>();
+
+    assert_eq!(chars, [vec!['h'], vec![], vec!['é'], vec!['l'], vec!['l'], vec!['o']]);
+    assert!(!decoder.is_pending());
+    assert_eq!(decoder.finish(), None);
+}
+
+/// Description: Sequences of one to four bytes are decoded
+fn test_all_lengths() {
+    let mut decoder = Utf8Decoder::new();
+    let text = "a\u{7f}ß€\u{ffff}😀\u{10ffff}";
+    assert_eq!(decode(&mut decoder, text.as_bytes()), text);
+}
+
+/// Description: Bytes, which cannot start a character, and malformed sequences are replaced by U+FFFD
+fn test_invalid() {
+    let mut decoder = Utf8Decoder::new();
+
+    // Continuation byte without start byte, overlong encodings, invalid start bytes
+    assert_eq!(decode(&mut decoder, b"a\x80b"), "a\u{fffd}b");
+    assert_eq!(decode(&mut decoder, b"\xc0\xaf"), "\u{fffd}\u{fffd}");
+    assert_eq!(decode(&mut decoder, b"\xf5\xff"), "\u{fffd}\u{fffd}");
+
+    // Surrogates and code points above U+10FFFF
+    assert_eq!(decode(&mut decoder, b"\xed\xa0\x80"), "\u{fffd}\u{fffd}\u{fffd}");
+    assert_eq!(decode(&mut decoder, b"\xf4\x90\x80\x80"), "\u{fffd}\u{fffd}\u{fffd}\u{fffd}");
+    assert!(!decoder.is_pending());
+}
+
+/// Description: A byte interrupting a sequence yields U+FFFD followed by the character the byte starts
+fn test_interrupted() {
+    let mut decoder = Utf8Decoder::new();
+    assert_eq!(decoder.feed(0xe2).count(), 0);
+    assert_eq!(decoder.feed(0x82).count(), 0);
+    assert!(decoder.is_pending());
+    assert_eq!(decoder.feed(b'x').collect::<String>(), "\u{fffd}x");
+
+    // The interrupting byte starts a new sequence
+    assert_eq!(decode(&mut decoder, b"\xc3\xc3\xa9"), "\u{fffd}é");
+
+    // Incomplete sequence at the end of input
+    assert_eq!(decoder.feed(0xf0).count(), 0);
+    assert_eq!(decoder.finish(), Some(REPLACEMENT_CHARACTER));
+    assert!(!decoder.is_pending());
+}
+
+/// Description: The line editor inserts invalid sequences as U+FFFD and still handles an interrupting control byte
+fn test_line_editor() {
+    let mut editor = LineEditor::new();
+    let mut result = EditResult::None;
+    for byte in b"h\xc3\xa9llo \xe2\x82\n" {
+        result = editor.feed(*byte);
+    }
+
+    assert_eq!(result, EditResult::Done(String::from("héllo \u{fffd}")));
+}
@@ -0,0 +1,302 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: vga_text                                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Terminal backend for the VGA text mode (80x25 characters at     ║
+   ║         0xb8000), used if the bootloader provides no linear             ║
+   ║         framebuffer. Each cell is a CP437 character and an attribute    ║
+   ║         byte (foreground in bits 0-3, background in bits 4-6). Colors   ║
+   ║         are mapped to the nearest of the 16 VGA colors. The hardware    ║
+   ║         cursor is positioned via the CRTC registers (ports              ║
+   ║         0x3d4/0x3d5).                                                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::ptr;
+use graphic::color::{Color, PixelFormat};
+use graphic::lfb;
+use graphic::offscreen_lfb::OffscreenLFB;
+use x86_64::instructions::port::Port;
+use crate::device::terminal::TerminalBackend;
+
+/// Physical address of the text buffer
+pub const BUFFER_ADDRESS: u64 = 0xb8000;
+/// Size of the standard text mode
+pub const COLUMNS: u16 = 80;
+pub const ROWS: u16 = 25;
+
+const CRTC_INDEX_PORT: u16 = 0x3d4;
+const CRTC_DATA_PORT: u16 = 0x3d5;
+const CRTC_CURSOR_START: u8 = 0x0a;
+const CRTC_CURSOR_LOCATION_HIGH: u8 = 0x0e;
+const CRTC_CURSOR_LOCATION_LOW: u8 = 0x0f;
+const CURSOR_DISABLE: u8 = 1 << 5; // bit 5 of the cursor start register
+
+const STATUS_BAR_ATTRIBUTE: u8 = 0x21; // blue on green
+const REPLACEMENT_CHARACTER: u8 = b'?';
+
+/// Default palette of the 16 VGA colors (index = color code)
+const PALETTE: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), (0x00, 0x00, 0xaa), (0x00, 0xaa, 0x00), (0x00, 0xaa, 0xaa),
+    (0xaa, 0x00, 0x00), (0xaa, 0x00, 0xaa), (0xaa, 0x55, 0x00), (0xaa, 0xaa, 0xaa),
+    (0x55, 0x55, 0x55), (0x55, 0x55, 0xff), (0x55, 0xff, 0x55), (0x55, 0xff, 0xff),
+    (0xff, 0x55, 0x55), (0xff, 0x55, 0xff), (0xff, 0xff, 0x55), (0xff, 0xff, 0xff),
+];
+
+/// Characters outside of ASCII, that exist in code page 437
+const CP437: [(char, u8); 65] = [
+    ('Ç', 0x80), ('ü', 0x81), ('é', 0x82), ('â', 0x83), ('ä', 0x84), ('à', 0x85), ('å', 0x86), ('ç', 0x87),
+    ('ê', 0x88), ('ë', 0x89), ('è', 0x8a), ('ï', 0x8b), ('î', 0x8c), ('ì', 0x8d), ('Ä', 0x8e), ('Å', 0x8f),
+    ('É', 0x90), ('æ', 0x91), ('Æ', 0x92), ('ô', 0x93), ('ö', 0x94), ('ò', 0x95), ('û', 0x96), ('ù', 0x97),
+    ('ÿ', 0x98), ('Ö', 0x99), ('Ü', 0x9a), ('¢', 0x9b), ('£', 0x9c), ('¥', 0x9d), ('á', 0xa0), ('í', 0xa1),
+    ('ó', 0xa2), ('ú', 0xa3), ('ñ', 0xa4), ('Ñ', 0xa5), ('░', 0xb0), ('▒', 0xb1), ('▓', 0xb2), ('█', 0xdb),
+    ('ß', 0xe1), ('°', 0xf8), ('²', 0xfd), ('³', b'3'), // there is no superscript three (used in the status bar)
+    ('─', 0xc4), ('│', 0xb3), ('┌', 0xda), ('┐', 0xbf), ('└', 0xc0), ('┘', 0xd9), ('├', 0xc3), ('┤', 0xb4),
+    ('┬', 0xc2), ('┴', 0xc1), ('┼', 0xc5), ('═', 0xcd), ('║', 0xba), ('╔', 0xc9), ('╗', 0xbb), ('╚', 0xc8),
+    ('╝', 0xbc), ('▀', 0xdf), ('▄', 0xdc), ('▌', 0xdd), ('▐', 0xde),
+];
+
+/// Text buffer of the VGA text mode with a back buffer on the heap (see `BufferedLFB`)
+pub struct VgaTextBackend {
+    buffer: *mut u16,
+    size: (u16, u16),
+    cells: Vec<u16>,           // back buffer always contains the live screen
+    dirty: Option<(u16, u16)>, // first row and row after last row, that have not been flushed yet
+    flushed_bytes: usize,
+    crtc: Option<(Port<u8>, Port<u8>)>, // index and data port (None = no hardware cursor)
+}
+
+unsafe impl Send for VgaTextBackend {}
+
+/// Description: Code (0-15) of the VGA color nearest to `color` (only the first `count` colors are considered)
+pub fn vga_color(color: Color, count: usize) -> u8 {
+    let distance = |(red, green, blue): (u8, u8, u8)| {
+        let delta = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        delta(red, color.red) + delta(green, color.green) + delta(blue, color.blue)
+    };
+
+    (0..count.min(PALETTE.len())).min_by_key(|&code| distance(PALETTE[code])).unwrap_or(0) as u8
+}
+
+/// Description: Attribute byte for `fg_color` on `bg_color` (bit 7 is blinking, so the background uses only 8 colors)
+pub fn vga_attribute(fg_color: Color, bg_color: Color) -> u8 {
+    vga_color(bg_color, 8) << 4 | vga_color(fg_color, 16)
+}
+
+/// Description: Color of the VGA color `code` (0-15)
+pub fn palette_color(code: u8) -> Color {
+    let (red, green, blue) = PALETTE[code as usize & 0x0f];
+    Color { red, green, blue, alpha: 255 }
+}
+
+/// Description: Character for the code page 437 character `code` ('?' for codes without mapping)
+pub fn from_cp437(code: u8) -> char {
+    match code {
+        b' '..=b'~' => code as char,
+        _ => CP437.iter().find(|(_, value)| *value == code).map_or(REPLACEMENT_CHARACTER as char, |(c, _)| *c),
+    }
+}
+
+/// Description: Code page 437 character for `c` ('?' if there is none)
+pub fn cp437(c: char) -> u8 {
+    match c {
+        '\0' => b' ',
+        ' '..='~' => c as u8,
+        _ => CP437.iter().find(|(value, _)| *value == c).map_or(REPLACEMENT_CHARACTER, |(_, code)| *code),
+    }
+}
+
+const fn cell(character: u8, attribute: u8) -> u16 {
+    (attribute as u16) << 8 | character as u16
+}
+
+impl VgaTextBackend {
+    ///
+    /// Description:
+    ///    Create a backend for the text buffer at `buffer` with `columns` x `rows` cells.
+    ///    The screen is cleared. Call `with_hardware_cursor()` to use the cursor of the VGA.
+    ///
+    /// # Safety
+    /// `buffer` must be valid for `columns` * `rows` cells
+    ///
+    pub unsafe fn new(buffer: *mut u16, columns: u16, rows: u16) -> Self {
+        let mut backend = Self { buffer, size: (columns, rows), cells: vec![cell(b' ', 0x07); columns as usize * rows as usize],
+            dirty: None, flushed_bytes: 0, crtc: None };
+        backend.restore();
+
+        backend
+    }
+
+    /// Description: Position the cursor of the VGA (CRTC registers) instead of drawing a blinking cursor
+    pub fn with_hardware_cursor(mut self) -> Self {
+        self.crtc = Some((Port::new(CRTC_INDEX_PORT), Port::new(CRTC_DATA_PORT)));
+        self
+    }
+
+    /// Description: Return the back buffer cell (character and attribute) at `pos`
+    pub fn cell(&self, pos: (u16, u16)) -> u16 {
+        self.cells[self.index(pos)]
+    }
+
+    fn index(&self, pos: (u16, u16)) -> usize {
+        pos.1 as usize * self.size.0 as usize + pos.0 as usize
+    }
+
+    /// Description: Remember `count` rows of the back buffer, starting at `row`, that have been drawn to
+    fn mark_dirty(&mut self, row: u16, count: u16) {
+        let end = (row + count).min(self.size.1);
+        if row >= end {
+            return;
+        }
+
+        self.dirty = Some(match self.dirty {
+            Some((start, dirty_end)) => (start.min(row), dirty_end.max(end)),
+            None => (row, end),
+        });
+    }
+
+    /// Description: Copy `count` rows, starting at `row`, from the back buffer to the text buffer
+    fn flush_rows(&mut self, row: u16, count: u16) {
+        let start = self.index((0, row));
+        let length = count as usize * self.size.0 as usize;
+
+        unsafe { ptr::copy_nonoverlapping(self.cells.as_ptr().add(start), self.buffer.add(start), length); }
+        self.flushed_bytes += length * size_of::<u16>();
+    }
+
+    /// Description: Write a CRTC register
+    fn write_crtc(&mut self, register: u8, value: u8) {
+        if let Some((index, data)) = self.crtc.as_mut() {
+            unsafe {
+                index.write(register);
+                data.write(value);
+            }
+        }
+    }
+
+    /// Description: Read a CRTC register (0 without hardware cursor)
+    fn read_crtc(&mut self, register: u8) -> u8 {
+        match self.crtc.as_mut() {
+            Some((index, data)) => unsafe {
+                index.write(register);
+                data.read()
+            },
+            None => 0,
+        }
+    }
+}
+
+impl TerminalBackend for VgaTextBackend {
+    fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    fn draw_char(&mut self, pos: (u16, u16), c: char, fg_color: Color, bg_color: Color) -> u16 {
+        let index = self.index(pos);
+        self.cells[index] = cell(cp437(c), vga_attribute(fg_color, bg_color));
+        self.mark_dirty(pos.1, 1);
+
+        1
+    }
+
+    fn draw_char_direct(&mut self, pos: (u16, u16), c: char, fg_color: Color, bg_color: Color) {
+        let index = self.index(pos);
+        unsafe { self.buffer.add(index).write_volatile(cell(cp437(c), vga_attribute(fg_color, bg_color))); }
+    }
+
+    fn fill(&mut self, pos: (u16, u16), size: (u16, u16), bg_color: Color) {
+        let blank = cell(b' ', vga_attribute(bg_color, bg_color));
+        for row in pos.1..pos.1 + size.1 {
+            let start = self.index((pos.0, row));
+            self.cells[start..start + size.0 as usize].fill(blank);
+        }
+
+        self.mark_dirty(pos.1, size.1);
+    }
+
+    fn scroll_up(&mut self, bg_color: Color) {
+        let columns = self.size.0 as usize;
+        self.cells.copy_within(columns.., 0);
+
+        let last_row = self.cells.len() - columns;
+        self.cells[last_row..].fill(cell(b' ', vga_attribute(bg_color, bg_color)));
+        self.mark_dirty(0, self.size.1);
+    }
+
+    fn draw_status_bar(&mut self, info: &str, date: Option<&str>) {
+        let columns = self.size.0 as usize;
+        let mut row = vec![cell(b' ', STATUS_BAR_ATTRIBUTE); columns];
+
+        for (target, c) in row.iter_mut().zip(info.chars()) {
+            *target = cell(cp437(c), STATUS_BAR_ATTRIBUTE);
+        }
+
+        if let Some(date) = date {
+            let start = columns.saturating_sub(date.chars().count());
+            for (target, c) in row[start..].iter_mut().zip(date.chars()) {
+                *target = cell(cp437(c), STATUS_BAR_ATTRIBUTE);
+            }
+        }
+
+        self.cells[..columns].copy_from_slice(&row);
+        self.mark_dirty(0, 1);
+    }
+
+    fn flush(&mut self) {
+        if let Some((start, end)) = self.dirty.take() {
+            self.flush_rows(start, end - start);
+        }
+    }
+
+    fn restore(&mut self) {
+        self.dirty = None;
+        self.flush_rows(0, self.size.1);
+    }
+
+    fn blank(&mut self) {
+        for index in 0..self.cells.len() {
+            unsafe { self.buffer.add(index).write_volatile(cell(b' ', 0x00)); }
+        }
+
+        self.set_hardware_cursor(None);
+    }
+
+    fn flushed_bytes(&self) -> usize {
+        self.flushed_bytes
+    }
+
+    /// Render the back buffer with the glyphs of the LFB font (8x16 pixels per cell, 32 bits per pixel)
+    fn screenshot(&mut self) -> Vec<u8> {
+        let screen = OffscreenLFB::new(self.size.0 as u32 * lfb::DEFAULT_CHAR_WIDTH, self.size.1 as u32 * lfb::DEFAULT_CHAR_HEIGHT, 32, PixelFormat::Rgb);
+        for row in 0..self.size.1 {
+            for column in 0..self.size.0 {
+                let cell = self.cell((column, row));
+                let attribute = (cell >> 8) as u8;
+                screen.lfb().draw_char(column as u32 * lfb::DEFAULT_CHAR_WIDTH, row as u32 * lfb::DEFAULT_CHAR_HEIGHT,
+                    palette_color(attribute & 0x0f), palette_color((attribute >> 4) & 0x07), from_cp437(cell as u8));
+            }
+        }
+
+        screen.lfb().to_ppm()
+    }
+
+    fn has_hardware_cursor(&self) -> bool {
+        self.crtc.is_some()
+    }
+
+    fn set_hardware_cursor(&mut self, pos: Option<(u16, u16)>) {
+        let cursor_start = self.read_crtc(CRTC_CURSOR_START);
+        match pos {
+            Some(pos) => {
+                let location = self.index(pos) as u16;
+                self.write_crtc(CRTC_CURSOR_LOCATION_HIGH, (location >> 8) as u8);
+                self.write_crtc(CRTC_CURSOR_LOCATION_LOW, location as u8);
+                self.write_crtc(CRTC_CURSOR_START, cursor_start & !CURSOR_DISABLE);
+            }
+            None => self.write_crtc(CRTC_CURSOR_START, cursor_start | CURSOR_DISABLE),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: vga_text_tests                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the VGA text mode backend of the terminal: character and   ║
+   ║         color mapping, text output, scrolling, flushing and rendering   ║
+   ║         screenshots. The tests use a text buffer on the heap and no     ║
+   ║         hardware cursor.                                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use ::log::info;
+use graphic::{color, lfb};
+use stream::OutputStream;
+
+use crate::device::lfb_terminal::LFBTerminal;
+use crate::device::terminal::TerminalBackend;
+use crate::device::vga_text::{cp437, from_cp437, vga_attribute, vga_color, VgaTextBackend, COLUMNS, ROWS};
+
+///
+/// Description:
+///    Run all VGA text mode tests (requires the process manager and scheduler for the status bar)
+///
+pub fn run_tests() {
+    info!("vga_text: running tests");
+
+    test_cp437();
+    test_colors();
+    test_print();
+    test_scroll();
+    test_flush_rows();
+    test_screenshot();
+
+    info!("vga_text: all tests passed.");
+}
+
+fn buffer() -> Vec<u16> {
+    vec![0; COLUMNS as usize * ROWS as usize]
+}
+
+/// Description: Create a terminal writing to the text buffer `buffer`
+fn create_terminal(buffer: &mut [u16]) -> LFBTerminal {
+    assert!(buffer.len() >= COLUMNS as usize * ROWS as usize);
+    LFBTerminal::with_backend(Box::new(unsafe { VgaTextBackend::new(buffer.as_mut_ptr(), COLUMNS, ROWS) }))
+}
+
+/// Description: Character of the cell at `column` and `row`
+fn character(buffer: &[u16], column: u16, row: u16) -> u8 {
+    buffer[row as usize * COLUMNS as usize + column as usize] as u8
+}
+
+/// Description: Attribute byte of the cell at `column` and `row`
+fn attribute(buffer: &[u16], column: u16, row: u16) -> u8 {
+    (buffer[row as usize * COLUMNS as usize + column as usize] >> 8) as u8
+}
+
+/// Description: ASCII is kept, umlauts are mapped to code page 437 and unknown characters to '?'
+fn test_cp437() {
+    assert_eq!(cp437('A'), b'A');
+    assert_eq!(cp437('~'), b'~');
+    assert_eq!(cp437('ä'), 0x84);
+    assert_eq!(cp437('Ü'), 0x9a);
+    assert_eq!(cp437('ß'), 0xe1);
+    assert_eq!(cp437('█'), 0xdb);
+    assert_eq!(cp437('┌'), 0xda);
+    assert_eq!(cp437('═'), 0xcd);
+    assert_eq!(from_cp437(0xc4), '─');
+    assert_eq!(cp437('\0'), b' ');
+    assert_eq!(cp437('€'), b'?');
+}
+
+/// Description: Colors of the 'color' module map to their VGA codes, the background uses only 8 colors
+fn test_colors() {
+    assert_eq!(vga_color(color::BLACK, 16), 0);
+    assert_eq!(vga_color(color::BLUE, 16), 1);
+    assert_eq!(vga_color(color::RED, 16), 4);
+    assert_eq!(vga_color(color::BROWN, 16), 6);
+    assert_eq!(vga_color(color::WHITE, 16), 7);
+    assert_eq!(vga_color(color::RED.bright(), 16), 12);
+    assert!(vga_color(color::RED.bright(), 8) < 8);
+
+    assert_eq!(vga_attribute(color::WHITE, color::BLACK), 0x07);
+    assert_eq!(vga_attribute(color::CYAN.bright(), color::BLUE), 0x1b);
+}
+
+/// Description: Text and SGR colors end up in the text buffer, row 0 is the status bar
+fn test_print() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    terminal.write_str("Hi\x1b[31;44mä\x1b[0m\n");
+    assert_eq!(character(&buffer, 0, 1), b'H');
+    assert_eq!(character(&buffer, 1, 1), b'i');
+    assert_eq!(attribute(&buffer, 0, 1), 0x07);
+    assert_eq!(character(&buffer, 2, 1), 0x84);
+    assert_eq!(attribute(&buffer, 2, 1), 0x14);
+    assert_eq!(terminal.cursor_index(), 2 * COLUMNS as usize);
+}
+
+/// Description: Writing past the last row scrolls the text buffer up by one row per line and redraws the status bar
+fn test_scroll() {
+    let mut buffer = buffer();
+    let terminal = create_terminal(&mut buffer);
+
+    for i in 0..ROWS as u8 + 5 {
+        terminal.write_byte(b'a' + i);
+        terminal.write_byte(b'\n');
+    }
+
+    // The last line is followed by an empty row with the cursor
+    let last_row = ROWS - 2;
+    assert_eq!(character(&buffer, 0, last_row), b'a' + ROWS as u8 + 4);
+    assert_eq!(character(&buffer, 0, last_row - 1), b'a' + ROWS as u8 + 3);
+    assert_eq!(character(&buffer, 0, ROWS - 1), b' ');
+    assert_eq!(terminal.cursor_index(), (ROWS - 1) as usize * COLUMNS as usize);
+
+    assert_eq!(character(&buffer, 0, 0), b'D');
+    assert_eq!(attribute(&buffer, 0, 0), 0x21);
+}
+
+/// Description: Only changed rows are copied to the text buffer
+fn test_flush_rows() {
+    let mut buffer = buffer();
+    let mut backend = unsafe { VgaTextBackend::new(buffer.as_mut_ptr(), COLUMNS, ROWS) };
+    let row_bytes = COLUMNS as usize * size_of::<u16>();
+    assert_eq!(backend.flushed_bytes(), ROWS as usize * row_bytes);
+
+    assert_eq!(backend.draw_char((3, 5), 'x', color::WHITE, color::BLACK), 1);
+    assert_eq!(character(&buffer, 3, 5), 0);
+    backend.flush();
+    assert_eq!(character(&buffer, 3, 5), b'x');
+    assert_eq!(backend.cell((3, 5)), 0x0700 | b'x' as u16);
+    assert_eq!(backend.flushed_bytes(), (ROWS as usize + 1) * row_bytes);
+
+    backend.fill((0, 2), (COLUMNS, 3), color::BLUE);
+    backend.flush();
+    assert_eq!(attribute(&buffer, 10, 4), 0x11);
+    assert_eq!(backend.flushed_bytes(), (ROWS as usize + 4) * row_bytes);
+}
+
+/// Description: Screenshots render the cells with the glyphs of the LFB font in the VGA colors
+fn test_screenshot() {
+    let mut buffer = buffer();
+    let mut backend = unsafe { VgaTextBackend::new(buffer.as_mut_ptr(), COLUMNS, ROWS) };
+    backend.draw_char((1, 2), 'x', color::RED, color::BLUE);
+
+    let (width, height) = (COLUMNS as u32 * lfb::DEFAULT_CHAR_WIDTH, ROWS as u32 * lfb::DEFAULT_CHAR_HEIGHT);
+    let ppm = backend.screenshot();
+    let header = format!("P6\n{} {}\n255\n", width, height);
+    assert!(ppm.starts_with(header.as_bytes()));
+    assert_eq!(ppm.len(), header.len() + (width * height * 3) as usize);
+
+    let cell_pixels: Vec<&[u8]> = (0..lfb::DEFAULT_CHAR_HEIGHT)
+        .flat_map(|y| (0..lfb::DEFAULT_CHAR_WIDTH).map(move |x| (lfb::DEFAULT_CHAR_WIDTH + x, 2 * lfb::DEFAULT_CHAR_HEIGHT + y)))
+        .map(|(x, y)| {
+            let offset = header.len() + ((y * width + x) * 3) as usize;
+            &ppm[offset..offset + 3]
+        })
+        .collect();
+    assert!(cell_pixels.iter().any(|pixel| *pixel == [0xaa, 0x00, 0x00]));
+    assert!(cell_pixels.iter().any(|pixel| *pixel == [0x00, 0x00, 0xaa]));
+    assert!(cell_pixels.iter().all(|pixel| *pixel == [0xaa, 0x00, 0x00] || *pixel == [0x00, 0x00, 0xaa]));
+}
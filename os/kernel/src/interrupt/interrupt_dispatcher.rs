@@ -6,9 +6,9 @@ use core::ptr;
 use spin::Mutex;
 use x86_64::registers::control::Cr2;
 use x86_64::set_general_handler;
-use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::structures::idt::{InterruptStackFrame, PageFaultErrorCode};
 use crate::{apic, idt, interrupt_dispatcher, scheduler};
-use crate::memory::PAGE_SIZE;
+use crate::memory::{ksm, nvram_snapshot, swap, PAGE_SIZE};
 
 #[repr(u8)]
 #[derive(PartialEq, PartialOrd, Copy, Clone, Debug)]
@@ -208,6 +208,24 @@ fn handle_page_fault(frame: InterruptStackFrame, _index: u8, error: Option<u64>)
     let fault_addr = Cr2::read().expect("Invalid address in CR2 during page fault");
     let thread = scheduler().current_thread();
 
+    // Check if the kernel has written to a write-protected page of non-volatile memory (first write after a snapshot)
+    let error_code = PageFaultErrorCode::from_bits_truncate(error.unwrap_or(0));
+    let kernel_write = error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION | PageFaultErrorCode::CAUSED_BY_WRITE) && !error_code.contains(PageFaultErrorCode::USER_MODE);
+    if kernel_write && nvram_snapshot::handle_write_fault(fault_addr) {
+        return;
+    }
+
+    // Check if a merged user page has been written (copy-on-write)
+    let write_protected = error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION | PageFaultErrorCode::CAUSED_BY_WRITE);
+    if write_protected && ksm::handle_write_fault(fault_addr) {
+        return;
+    }
+
+    // Check if a swapped out user page has been accessed (also by the kernel, if it has been evicted during a copy)
+    if !error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION) && swap::handle_fault(fault_addr) {
+        return;
+    }
+
     // Check if page fault occurred right below the user stack
     if !thread.is_kernel_thread() && !thread.stacks_locked() && fault_addr > (thread.user_stack_start() - PAGE_SIZE as u64) && fault_addr < thread.user_stack_start() {
         thread.grow_user_stack(); // Grow stack by one page
@@ -0,0 +1,82 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: kernel_tests                                                    ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Run the tests of all kernel modules (their 'run_tests()'        ║
+   ║         functions) in a kernel thread, before the shell is started.     ║
+   ║         Only compiled with the kernel feature 'kernel-tests'. A failing ║
+   ║         test panics, so the kernel panic screen shows the failed        ║
+   ║         assertion.                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use log::info;
+use crate::{boot_state_tests, panic_screen_tests, stack_guard_tests};
+use crate::device::{compose_tests, key_repeat_tests, keyboard_layout_tests, lfb_terminal_tests, lfb_tests, line_editor_tests, pit_tests, utf8_tests, vga_text_tests};
+#[cfg(feature = "heap-tracking")]
+use crate::memory::heap_tracking_tests;
+use crate::memory::{alloc_tests, ksm_tests, nvram_alloc_tests, nvram_snapshot_tests, physical_tests, pressure_tests, regions_tests, swap_tests, user_tests, virtual_tests};
+use crate::naming::{file_lock_tests, name_service_tests, watch_tests};
+use crate::process::{auth_tests, credentials_tests, pipe_tests, process_tests, scheduler_tests};
+use crate::sync::mutex_tests;
+use crate::syscall::{sys_naming_tests, sys_terminal_tests, sys_time_tests, sys_vmem_tests, syscall_builder_tests};
+
+///
+/// Description:
+///    Run all kernel tests (called by the test thread created in `boot`). Tests without
+///    requirements run first, followed by the tests, that need the scheduler, the name
+///    service or a process context.
+///
+pub fn run() {
+    info!("Running kernel tests");
+
+    // Data structures and conversions
+    syscall_builder_tests::run_tests();
+    utf8_tests::run_tests();
+    line_editor_tests::run_tests();
+    compose_tests::run_tests();
+    keyboard_layout_tests::run_tests();
+    key_repeat_tests::run_tests();
+    pit_tests::run_tests();
+    lfb_tests::run_tests();
+    panic_screen_tests::run_tests();
+    boot_state_tests::run_tests();
+
+    // Memory management
+    regions_tests::run_tests();
+    alloc_tests::run_tests();
+    #[cfg(feature = "heap-tracking")]
+    heap_tracking_tests::run_tests();
+    physical_tests::run_tests();
+    virtual_tests::run_tests();
+    pressure_tests::run_tests();
+    user_tests::run_tests();
+    swap_tests::run_tests();
+    ksm_tests::run_tests();
+    nvram_alloc_tests::run_tests();
+    nvram_snapshot_tests::run_tests();
+
+    // Threads and processes
+    mutex_tests::run_tests();
+    scheduler_tests::run_tests();
+    process_tests::run_tests();
+    stack_guard_tests::run_tests();
+    pipe_tests::run_tests();
+
+    // Naming service
+    name_service_tests::run_tests();
+    file_lock_tests::run_tests();
+    watch_tests::run_tests();
+    credentials_tests::run_tests();
+    auth_tests::run_tests();
+
+    // System calls and terminals
+    sys_naming_tests::run_tests();
+    sys_vmem_tests::run_tests();
+    sys_time_tests::run_tests();
+    sys_terminal_tests::run_tests();
+    vga_text_tests::run_tests();
+    lfb_terminal_tests::run_tests();
+
+    info!("All kernel tests passed.");
+}
@@ -16,19 +16,25 @@
 #![feature(abi_x86_interrupt)]
 #![feature(trait_upcasting)]
 #![feature(ptr_metadata)]
+#![feature(btreemap_alloc)]
 #![allow(internal_features)]
 #![no_std]
 
+use alloc::boxed::Box;
 use alloc::sync::Arc;
+use crate::boot_state::BootReason;
+use crate::panic_screen::Registers;
 use crate::device::apic::Apic;
 use crate::device::lfb_terminal::{CursorThread, LFBTerminal};
+use crate::device::clock::MonotonicClock;
 use crate::device::pit::Timer;
 use crate::device::ps2::{Keyboard, PS2};
 use crate::device::serial;
 use crate::device::serial::{BaudRate, ComPort, SerialPort};
 use crate::device::speaker::Speaker;
-use crate::device::terminal::Terminal;
+use crate::device::terminal::{Terminal, TerminalBackend};
 use crate::memory::alloc::{AcpiHandler, KernelAllocator};
+use crate::memory::nvram_alloc::NvramAllocator;
 use crate::interrupt::interrupt_dispatcher::InterruptDispatcher;
 use crate::log::Logger;
 use crate::process::scheduler::Scheduler;
@@ -57,6 +63,8 @@ extern crate alloc;
 #[macro_use]
 pub mod device;
 pub mod boot;
+pub mod boot_state;
+pub mod boot_timing;
 pub mod interrupt;
 pub mod memory;
 pub mod log;
@@ -64,7 +72,18 @@ pub mod syscall;
 pub mod process;
 pub mod consts;
 pub mod naming;
+pub mod panic_screen;
 pub mod network;
+pub mod shutdown;
+pub mod stack_guard;
+pub mod sync;
+#[cfg(feature = "kernel-tests")]
+pub mod kernel_tests;
+pub mod boot_state_tests;
+pub mod panic_screen_tests;
+pub mod stack_guard_tests;
+
+pub use shutdown::shutdown;
 
 pub mod built_info {
     // The file has been placed there by the build script.
@@ -73,8 +92,11 @@ pub mod built_info {
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    boot_state::record(BootReason::Panic);
+
     if terminal_initialized() {
-        println!("Panic: {}", info);
+        let registers = Registers::capture();
+        panic_screen::show(info, &registers);
     } else {
         let args = [info.message().as_str().unwrap()];
         let record = Record::builder()
@@ -218,6 +240,27 @@ pub fn allocator() -> &'static KernelAllocator {
     &ALLOCATOR
 }
 
+/// Non-volatile memory allocator.
+/// Manages the first non-volatile memory region (if available, see 'nvmem::init()').
+/// Allocations and the root pointer survive reboots.
+static NVRAM_ALLOCATOR: NvramAllocator = NvramAllocator::new();
+
+pub fn nvram_allocator() -> &'static NvramAllocator {
+    &NVRAM_ALLOCATOR
+}
+
+/// Reason of the last restart.
+/// Read from NVRAM by 'boot_state::init()', which is called by 'boot.rs' after initializing non-volatile memory.
+static LAST_BOOT_REASON: Once<BootReason> = Once::new();
+
+pub fn init_last_boot_reason(reason: BootReason) {
+    LAST_BOOT_REASON.call_once(|| reason);
+}
+
+pub fn last_boot_reason() -> BootReason {
+    *LAST_BOOT_REASON.get().unwrap_or(&BootReason::Unknown)
+}
+
 /// Kernel logger.
 /// Used to log kernel messages. During the boot process, log messages are printed to the serial port.
 /// 'boot.rs' sets up the log-crate to use this logger, so that macros like 'error!' or 'info!' can be used.
@@ -289,6 +332,18 @@ pub fn timer() -> Arc<Timer> {
     Arc::clone(TIMER.get().unwrap())
 }
 
+/// Monotonic clock.
+/// Based on the TSC if it is invariant, otherwise on the PIT. The source is selected at initialization.
+static CLOCK: Once<MonotonicClock> = Once::new();
+
+pub fn init_clock() {
+    CLOCK.call_once(MonotonicClock::new);
+}
+
+pub fn clock() -> &'static MonotonicClock {
+    CLOCK.get().expect("Trying to access monotonic clock before initialization!")
+}
+
 /// PC Speaker.
 /// A very simple device that generate square waves at a certain frequency, thus creating beep sounds.
 static SPEAKER: Once<Arc<Speaker>> = Once::new();
@@ -332,9 +387,13 @@ pub fn serial_port() -> Option<Arc<SerialPort>> {
 /// reads keyboard input. Applications can use the 'read' system call to get keyboard input from the terminal.
 static TERMINAL: Once<Arc<dyn Terminal>> = Once::new();
 
-pub fn init_terminal(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8) {
-    let lfb_terminal = Arc::new(LFBTerminal::new(buffer, pitch, width, height, bpp));
+/// Description: Create the terminal drawing to `backend` (linear framebuffer or VGA text mode) and start the cursor thread
+pub fn init_terminal(backend: Box<dyn TerminalBackend>) {
+    let lfb_terminal = Arc::new(LFBTerminal::with_backend(backend));
     lfb_terminal.clear();
+    if let Some(serial) = serial_port() {
+        lfb_terminal.attach_serial(serial); // shell can also be used via the serial port
+    }
     TERMINAL.call_once(|| lfb_terminal);
 
     scheduler().ready(Thread::new_kernel_thread(|| {
@@ -1,15 +1,29 @@
 use acpi::PhysicalMapping;
 use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+use core::fmt;
+use core::fmt::Write;
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
 use linked_list_allocator::LockedHeap;
+use spin::Mutex;
+use stream::OutputStream;
+use x86_64::instructions::{hlt, interrupts};
 use x86_64::PhysAddr;
 use x86_64::structures::paging::frame::PhysFrameRange;
 use x86_64::structures::paging::PhysFrame;
 use crate::memory::{PAGE_SIZE, physical};
 use crate::memory::physical::phys_limit;
+use crate::serial_port;
+#[cfg(feature = "heap-tracking")]
+use crate::memory::heap_tracking;
+
+/// Called with the layout of a failed heap allocation (before `alloc()` returns a null pointer)
+pub type OomHandler = fn(Layout);
 
 pub struct KernelAllocator {
     heap: LockedHeap,
+    oom_handler: Mutex<OomHandler>,
+    handling_oom: AtomicBool, // an allocation failing in the handler must not call it again
 }
 
 #[derive(Default)]
@@ -20,7 +34,7 @@ pub struct AcpiHandler;
 
 impl KernelAllocator {
     pub const fn new() -> Self {
-        Self { heap: LockedHeap::empty() }
+        Self { heap: LockedHeap::empty(), oom_handler: Mutex::new(halt_on_oom), handling_oom: AtomicBool::new(false) }
     }
 
     pub unsafe fn init(&self, frames: &PhysFrameRange) {
@@ -35,10 +49,41 @@ impl KernelAllocator {
     pub fn is_locked(&self) -> bool {
         self.heap.is_locked()
     }
-}
 
-unsafe impl Allocator for KernelAllocator {
-    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+    /// Size of the kernel heap in bytes
+    pub fn size(&self) -> usize {
+        self.heap.lock().size()
+    }
+
+    /// Number of bytes currently allocated on the kernel heap
+    pub fn used(&self) -> usize {
+        self.heap.lock().used()
+    }
+
+    ///
+    /// Description:
+    ///    Set the handler called, when a heap allocation via the global allocator fails
+    ///    (default: `halt_on_oom()`). If the handler returns, the allocation fails as usual.
+    ///
+    /// Return: the previous handler
+    ///
+    pub fn set_oom_handler(&self, handler: OomHandler) -> OomHandler {
+        core::mem::replace(&mut *self.oom_handler.lock(), handler)
+    }
+
+    /// Description: Call the OOM handler for `layout` (unless it is already running)
+    fn out_of_memory(&self, layout: Layout) {
+        if self.handling_oom.swap(true, Ordering::Acquire) {
+            return;
+        }
+
+        let handler = *self.oom_handler.lock();
+        handler(layout);
+        self.handling_oom.store(false, Ordering::Release);
+    }
+
+    /// Allocate without recording the allocation (see 'heap_tracking.rs')
+    pub fn allocate_untracked(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         if layout.size() == 0 {
             return Ok(NonNull::slice_from_raw_parts(layout.dangling(), 0));
         }
@@ -49,7 +94,11 @@ unsafe impl Allocator for KernelAllocator {
         }
     }
 
-    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+    /// Free memory allocated with `allocate_untracked()`
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by `allocate_untracked()` with the same `layout` and must not be used anymore.
+    pub unsafe fn deallocate_untracked(&self, ptr: NonNull<u8>, layout: Layout) {
         if layout.size() != 0 {
             let mut heap = self.heap.lock();
             unsafe { heap.deallocate(ptr, layout); }
@@ -57,20 +106,81 @@ unsafe impl Allocator for KernelAllocator {
     }
 }
 
+unsafe impl Allocator for KernelAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let allocation = self.allocate_untracked(layout)?;
+        #[cfg(feature = "heap-tracking")]
+        if layout.size() != 0 {
+            heap_tracking::record(allocation.cast::<u8>().as_ptr(), layout.size());
+        }
+
+        Ok(allocation)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        #[cfg(feature = "heap-tracking")]
+        if layout.size() != 0 {
+            heap_tracking::forget(ptr.as_ptr());
+        }
+
+        unsafe { self.deallocate_untracked(ptr, layout); }
+    }
+}
+
 unsafe impl GlobalAlloc for KernelAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.heap.lock()
+        let ptr = self.heap.lock()
             .allocate_first_fit(layout)
             .ok()
-            .map_or(core::ptr::null_mut(), |allocation| allocation.as_ptr())
+            .map_or(core::ptr::null_mut(), |allocation| allocation.as_ptr());
+
+        if ptr.is_null() {
+            self.out_of_memory(layout);
+            return ptr;
+        }
+
+        #[cfg(feature = "heap-tracking")]
+        heap_tracking::record(ptr, layout.size());
+
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Forget the allocation first, because the memory may be allocated again right after freeing it
+        #[cfg(feature = "heap-tracking")]
+        heap_tracking::forget(ptr);
+
         let mut heap = self.heap.lock();
         unsafe { heap.deallocate(NonNull::new_unchecked(ptr), layout); }
     }
 }
 
+///
+/// Description:
+///    Default OOM handler: Print a diagnostic to the serial port and halt the system.
+///    Nothing is allocated (formatting a panic message could need the heap again).
+///
+pub fn halt_on_oom(layout: Layout) {
+    interrupts::disable();
+    if let Some(serial) = serial_port() {
+        let _ = writeln!(RawWriter(serial.as_ref()), "\nKernel heap exhausted: Failed to allocate [{}] bytes (alignment [{}]), halting", layout.size(), layout.align());
+    }
+
+    loop {
+        hlt();
+    }
+}
+
+/// Formats directly into an output stream (without building a string on the heap)
+struct RawWriter<'a>(&'a dyn OutputStream);
+
+impl Write for RawWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s);
+        Ok(())
+    }
+}
+
 unsafe impl Allocator for StackAllocator {
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         if PAGE_SIZE % layout.align() != 0 {
@@ -0,0 +1,83 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: alloc_tests                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the OOM handler of the kernel heap allocator. The default  ║
+   ║         handler (which halts the system) is temporarily replaced by a   ║
+   ║         handler counting its calls.                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ::log::info;
+
+use crate::allocator;
+
+static OOM_CALLS: AtomicUsize = AtomicUsize::new(0);
+static OOM_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Description:
+///    Run all kernel allocator tests
+///
+pub fn run_tests() {
+    info!("alloc: running tests");
+
+    test_oom_handler();
+    test_oom_in_handler();
+
+    info!("alloc: all tests passed.");
+}
+
+fn count_oom(layout: Layout) {
+    OOM_CALLS.fetch_add(1, Ordering::Relaxed);
+    OOM_SIZE.store(layout.size(), Ordering::Relaxed);
+}
+
+/// Description: Try to allocate more than the whole heap (fails, even if nothing else is allocated)
+fn exhaust_heap() -> (*mut u8, Layout) {
+    let layout = Layout::from_size_align(allocator().size() + 1, 8).unwrap();
+    (unsafe { allocator().alloc(layout) }, layout)
+}
+
+/// Description: A failed allocation calls the handler exactly once and returns a null pointer
+fn test_oom_handler() {
+    OOM_CALLS.store(0, Ordering::Relaxed);
+    let previous = allocator().set_oom_handler(count_oom);
+
+    let (ptr, layout) = exhaust_heap();
+    assert!(ptr.is_null());
+    assert_eq!(OOM_CALLS.load(Ordering::Relaxed), 1);
+    assert_eq!(OOM_SIZE.load(Ordering::Relaxed), layout.size());
+
+    // Successful allocations do not call the handler
+    let small = Layout::new::<u64>();
+    let ptr = unsafe { allocator().alloc(small) };
+    assert!(!ptr.is_null());
+    unsafe { allocator().dealloc(ptr, small); }
+    assert_eq!(OOM_CALLS.load(Ordering::Relaxed), 1);
+
+    allocator().set_oom_handler(previous);
+}
+
+/// Description: An allocation failing in the handler does not call it again
+fn test_oom_in_handler() {
+    fn failing_handler(layout: Layout) {
+        count_oom(layout);
+        let (ptr, _) = exhaust_heap();
+        assert!(ptr.is_null());
+    }
+
+    OOM_CALLS.store(0, Ordering::Relaxed);
+    let previous = allocator().set_oom_handler(failing_handler);
+
+    assert!(exhaust_heap().0.is_null());
+    assert_eq!(OOM_CALLS.load(Ordering::Relaxed), 1);
+
+    // The handler is called again for the next failure
+    assert!(exhaust_heap().0.is_null());
+    assert_eq!(OOM_CALLS.load(Ordering::Relaxed), 2);
+
+    allocator().set_oom_handler(previous);
+}
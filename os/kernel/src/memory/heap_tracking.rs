@@ -0,0 +1,172 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: heap_tracking                                                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Accounting of live kernel heap allocations for finding leaks.   ║
+   ║         Only enabled with the kernel feature 'heap-tracking', because   ║
+   ║         every allocation is recorded with its size and call site in a   ║
+   ║         map. The call site is a short backtrace (return addresses),     ║
+   ║         found by following the frame pointers (use 'addr2line' to get   ║
+   ║         the source lines). The map itself allocates from the heap with  ║
+   ║         'UntrackedAllocator', so its own allocations are not recorded.  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::{AllocError, Allocator, Layout};
+use core::arch::asm;
+use core::cmp::Reverse;
+use core::fmt::Write;
+use core::ptr::NonNull;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+use crate::allocator;
+use crate::consts::{KERNEL_STACK_PAGES, USER_SPACE_START};
+use crate::memory::PAGE_SIZE;
+
+/// Number of return addresses identifying a call site
+pub const BACKTRACE_DEPTH: usize = 4;
+/// Frames of the allocator (`record()`, `GlobalAlloc::alloc()` and the `__rust_alloc` shim), which are skipped
+const SKIP_FRAMES: usize = 2;
+
+/// Return addresses (innermost first, 0 if the backtrace is shorter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CallSite(pub [usize; BACKTRACE_DEPTH]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    pub size: usize,
+    pub call_site: CallSite,
+}
+
+/// Live allocations of one call site
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallSiteTotal {
+    pub call_site: CallSite,
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Allocates from the kernel heap without recording the allocations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UntrackedAllocator;
+
+/// Live allocations by address (created with the first allocation)
+static ALLOCATIONS: Mutex<Option<BTreeMap<usize, Allocation, UntrackedAllocator>>> = Mutex::new(None);
+
+unsafe impl Allocator for UntrackedAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        allocator().allocate_untracked(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { allocator().deallocate_untracked(ptr, layout); }
+    }
+}
+
+impl CallSite {
+    ///
+    /// Description:
+    ///    Collect the return addresses of the calling functions, skipping `skip` frames. Only
+    ///    frames on the current kernel stack are followed (stops at the first invalid frame pointer).
+    ///
+    #[inline(always)]
+    fn current(mut skip: usize) -> Self {
+        let mut frame: usize;
+        unsafe { asm!("mov {}, rbp", out(reg) frame, options(nomem, nostack, preserves_flags)); }
+
+        let mut addresses = [0; BACKTRACE_DEPTH];
+        let mut count = 0;
+        while count < BACKTRACE_DEPTH && frame != 0 && frame.is_multiple_of(8) {
+            // Frame layout: saved frame pointer of the caller, followed by the return address
+            let (next, return_address) = unsafe { (*(frame as *const usize), *((frame + 8) as *const usize)) };
+            match skip {
+                0 => {
+                    addresses[count] = return_address;
+                    count += 1;
+                }
+                _ => skip -= 1,
+            }
+
+            // Callers have higher frame addresses on the same stack (and never in user space)
+            if next <= frame || next - frame > KERNEL_STACK_PAGES * PAGE_SIZE || next >= USER_SPACE_START {
+                break;
+            }
+            frame = next;
+        }
+
+        CallSite(addresses)
+    }
+}
+
+///
+/// Description: Record the allocation of `size` bytes at `ptr` (called by the kernel allocator).
+///              Must not be inlined, so that the number of skipped frames is always the same.
+///
+#[inline(never)]
+pub fn record(ptr: *mut u8, size: usize) {
+    let call_site = CallSite::current(SKIP_FRAMES);
+    interrupts::without_interrupts(|| {
+        ALLOCATIONS.lock()
+            .get_or_insert_with(|| BTreeMap::new_in(UntrackedAllocator))
+            .insert(ptr as usize, Allocation { size, call_site });
+    });
+}
+
+/// Description: Forget the allocation at `ptr` (called by the kernel allocator, before the memory is freed)
+pub fn forget(ptr: *mut u8) {
+    interrupts::without_interrupts(|| {
+        if let Some(allocations) = ALLOCATIONS.lock().as_mut() {
+            allocations.remove(&(ptr as usize));
+        }
+    });
+}
+
+/// Description: Return the recorded allocation at `ptr`
+pub fn allocation(ptr: *const u8) -> Option<Allocation> {
+    interrupts::without_interrupts(|| ALLOCATIONS.lock().as_ref()?.get(&(ptr as usize)).copied())
+}
+
+/// Description: Live allocations grouped by call site, the call site with the most bytes first
+pub fn totals() -> Vec<CallSiteTotal> {
+    // Copy the allocations first, since allocating with the global allocator requires the lock
+    let snapshot = interrupts::without_interrupts(|| {
+        let mut snapshot = Vec::new_in(UntrackedAllocator);
+        if let Some(allocations) = ALLOCATIONS.lock().as_ref() {
+            snapshot.extend(allocations.values().copied());
+        }
+        snapshot
+    });
+
+    let mut totals = BTreeMap::<CallSite, CallSiteTotal>::new();
+    for allocation in snapshot.iter() {
+        let total = totals.entry(allocation.call_site).or_insert(CallSiteTotal { call_site: allocation.call_site, count: 0, bytes: 0 });
+        total.count += 1;
+        total.bytes += allocation.size;
+    }
+
+    let mut totals = totals.into_values().collect::<Vec<CallSiteTotal>>();
+    totals.sort_by_key(|total| Reverse(total.bytes));
+    totals
+}
+
+/// Description: Human readable report of `totals()` (one line per call site)
+pub fn dump() -> String {
+    let totals = totals();
+    let mut report = String::new();
+    writeln!(report, "Live kernel allocations: {} in {} bytes from {} call sites",
+             totals.iter().map(|total| total.count).sum::<usize>(),
+             totals.iter().map(|total| total.bytes).sum::<usize>(),
+             totals.len()).unwrap();
+    writeln!(report, "{:>8} {:>10}  call site (return addresses, innermost first)", "count", "bytes").unwrap();
+
+    for total in totals.iter() {
+        write!(report, "{:>8} {:>10} ", total.count, total.bytes).unwrap();
+        total.call_site.0.iter().take_while(|address| **address != 0).for_each(|address| write!(report, " 0x{:x}", address).unwrap());
+        report.push('\n');
+    }
+
+    report
+}
@@ -0,0 +1,75 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: heap_tracking_tests                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the accounting of heap allocations by leaking allocations  ║
+   ║         of a known size and finding them in the report. Only compiled   ║
+   ║         with the kernel feature 'heap-tracking'.                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use ::log::info;
+
+use crate::memory::heap_tracking;
+use crate::memory::heap_tracking::UntrackedAllocator;
+
+/// Unusual size, so the leaked allocation is easy to find
+const LEAK_SIZE: usize = 12345;
+
+///
+/// Description:
+///    Run all heap tracking tests
+///
+pub fn run_tests() {
+    info!("heap_tracking: running tests");
+
+    test_leak_found();
+    test_freed_forgotten();
+    test_grouped_by_call_site();
+    test_untracked();
+
+    info!("heap_tracking: all tests passed.");
+}
+
+/// Description: A leaked allocation is recorded with its size and listed with its call site in the report
+fn test_leak_found() {
+    let leak = Box::leak(vec![0u8; LEAK_SIZE].into_boxed_slice());
+
+    let allocation = heap_tracking::allocation(leak.as_ptr()).expect("Leaked allocation has not been recorded");
+    assert_eq!(allocation.size, LEAK_SIZE);
+    assert_ne!(allocation.call_site.0[0], 0);
+
+    let total = heap_tracking::totals().into_iter().find(|total| total.call_site == allocation.call_site).unwrap();
+    assert!(total.count >= 1 && total.bytes >= LEAK_SIZE);
+    assert!(heap_tracking::dump().contains(&format!(" 0x{:x}", allocation.call_site.0[0])));
+}
+
+/// Description: Freed memory is no longer listed
+fn test_freed_forgotten() {
+    let buffer = vec![0u8; LEAK_SIZE];
+    let ptr = buffer.as_ptr();
+    assert!(heap_tracking::allocation(ptr).is_some());
+
+    drop(buffer);
+    assert!(heap_tracking::allocation(ptr).is_none());
+}
+
+/// Description: Allocations from the same call site are counted together
+fn test_grouped_by_call_site() {
+    let boxes = (0..3).map(|i| Box::new([i as u8; 100])).collect::<Vec<Box<[u8; 100]>>>();
+    let allocations = boxes.iter().map(|b| heap_tracking::allocation(b.as_ptr()).unwrap()).collect::<Vec<_>>();
+    assert!(allocations.iter().all(|allocation| allocation.call_site == allocations[0].call_site));
+
+    let total = heap_tracking::totals().into_iter().find(|total| total.call_site == allocations[0].call_site).unwrap();
+    assert!(total.count >= 3 && total.bytes >= 300);
+}
+
+/// Description: Allocations of the tracking map itself (with `UntrackedAllocator`) are not recorded
+fn test_untracked() {
+    let untracked = Box::new_in(0x1234u64, UntrackedAllocator);
+    assert!(heap_tracking::allocation(&*untracked as *const u64 as *const u8).is_none());
+}
@@ -0,0 +1,256 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: ksm                                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Kernel same-page merging (opt-in with the kernel command line   ║
+   ║         flag 'ksm'). The scanner thread hashes the frames of user pages ║
+   ║         (code, heap and anonymous mappings) and keeps the first page    ║
+   ║         per hash as candidate. A page with the hash of a candidate is   ║
+   ║         write-protected and compared completely with it. If both are    ║
+   ║         identical, the page is remapped to the frame of the candidate   ║
+   ║         and its own frame is freed. Merged pages, that have been        ║
+   ║         writable, are marked `MERGED` and read-only: the first write    ║
+   ║         causes a page fault, which gives the page a private copy        ║
+   ║         (copy-on-write). At most `SCAN_PAGES` pages are scanned per     ║
+   ║         `SCAN_INTERVAL_MS`, the candidates are forgotten after each     ║
+   ║         complete pass.                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use core::slice;
+use spin::Mutex;
+use x86_64::instructions::tlb;
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::VirtAddr;
+use crate::consts::USER_SPACE_START;
+use crate::memory::{physical, PAGE_SIZE};
+use crate::memory::physical::AllocFlags;
+use crate::memory::r#virtual::{AddressSpace, VmaType};
+use crate::{process_manager, scheduler};
+
+/// Marks a read-only page, that is shared after merging and has been writable before (copied on the first write)
+pub const MERGED: PageTableFlags = PageTableFlags::BIT_10;
+
+/// Interval of the scanner thread
+pub const SCAN_INTERVAL_MS: usize = 200;
+/// Pages scanned per interval
+pub const SCAN_PAGES: usize = 256;
+/// Maximum number of mappings of a merged frame (e.g. of zeroed pages), the reference count of a frame is limited
+pub const MAX_SHARING: usize = 4096;
+
+/// Types of memory areas, whose pages are merged
+const SCANNED_TYPES: [VmaType; 3] = [VmaType::Code, VmaType::Heap, VmaType::Mapping];
+
+/// First page found with a hash
+struct Candidate {
+    address_space: Weak<AddressSpace>,
+    page: Page,
+    frame: PhysFrame,
+}
+
+struct ScanState {
+    position: usize,                       // index of the next page to scan (in the order of `scan()`)
+    candidates: BTreeMap<u64, Candidate>,  // by the hash of their content
+}
+
+static STATE: Mutex<ScanState> = Mutex::new(ScanState { position: 0, candidates: BTreeMap::new() });
+
+/// Serializes merging with copying merged pages (the reference count of a frame must not change in between)
+static COPY: Mutex<()> = Mutex::new(());
+
+fn frame_bytes(frame: PhysFrame) -> &'static [u8] {
+    unsafe { slice::from_raw_parts(frame.start_address().as_u64() as *const u8, PAGE_SIZE) }
+}
+
+/// Description: FNV-1a hash of the content of `frame`
+fn hash(frame: PhysFrame) -> u64 {
+    frame_bytes(frame).iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+///
+/// Description:
+///    Scan up to `max_pages` pages, continuing after the last scanned page. Pages are scanned
+///    process by process, in the order of their memory areas.
+///
+/// Return: the number of merged pages (each freeing a page frame)
+///
+pub fn scan(max_pages: usize) -> usize {
+    let mut state = STATE.lock();
+    let processes = process_manager().read().active_processes();
+    let mut index = 0;
+    let mut merged = 0;
+
+    for process in processes.iter() {
+        let address_space = process.address_space();
+        for vma in SCANNED_TYPES.iter().flat_map(|typ| process.find_vmas(*typ)) {
+            for page in vma.range() {
+                if index < state.position {
+                    index += 1;
+                    continue;
+                }
+                if index == state.position.saturating_add(max_pages) {
+                    state.position = index;
+                    return merged;
+                }
+
+                index += 1;
+                if merge(&mut state.candidates, &address_space, page) {
+                    merged += 1;
+                }
+            }
+        }
+    }
+
+    // Complete pass -> start again with the first page
+    state.position = 0;
+    state.candidates.clear();
+    merged
+}
+
+/// Description: Merge `page` with the candidate with the same content or make it the candidate for its hash (`true`, if it has been merged)
+fn merge(candidates: &mut BTreeMap<u64, Candidate>, address_space: &Arc<AddressSpace>, page: Page) -> bool {
+    let Some(frame) = mergeable_frame(address_space, page) else {
+        return false;
+    };
+
+    let hash = hash(frame);
+    if let Some(candidate) = candidates.get(&hash) {
+        if candidate.frame == frame {
+            return false;
+        }
+        if physical::ref_count(candidate.frame) < MAX_SHARING && candidate.address_space.upgrade().is_some_and(|candidate_space| protect(&candidate_space, candidate.page, candidate.frame)) {
+            return replace(address_space, page, frame, candidate.frame);
+        }
+    }
+
+    // No candidate yet (or it has been unmapped, written or shared too often meanwhile)
+    candidates.insert(hash, Candidate { address_space: Arc::downgrade(address_space), page, frame });
+    false
+}
+
+/// Description: Frame of `page`, if it is a present user page, which is not shared or only shared by merging
+fn mergeable_frame(address_space: &AddressSpace, page: Page) -> Option<PhysFrame> {
+    address_space.update_entry(page, |entry| {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE) {
+            return None;
+        }
+
+        let frame = PhysFrame::from_start_address(entry.addr()).ok()?;
+        match physical::ref_count(frame) {
+            1 => Some(frame),
+            count if count > 1 && flags.contains(MERGED) => Some(frame),
+            _ => None, // not counted (e.g. non-volatile memory) or shared otherwise
+        }
+    }).flatten()
+}
+
+/// Description: Write-protect `page`, if it still maps `frame` (`true`, if it is read-only now)
+fn protect(address_space: &AddressSpace, page: Page, frame: PhysFrame) -> bool {
+    address_space.update_entry(page, |entry| {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT) || entry.addr() != frame.start_address() {
+            return false;
+        }
+
+        if flags.contains(PageTableFlags::WRITABLE) {
+            if physical::ref_count(frame) != 1 {
+                return false;
+            }
+            entry.set_flags((flags - PageTableFlags::WRITABLE) | MERGED);
+            tlb::flush(page.start_address());
+        }
+        true
+    }).unwrap_or(false)
+}
+
+/// Description: Map `page` to `target`, if it still maps `frame` and both have the same content (`true`, if it has been remapped)
+fn replace(address_space: &AddressSpace, page: Page, frame: PhysFrame, target: PhysFrame) -> bool {
+    let _copy = COPY.lock();
+    address_space.update_entry(page, |entry| {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT) || entry.addr() != frame.start_address() {
+            return false;
+        }
+
+        // Write-protect the page before comparing, so it cannot change after the comparison
+        let protected_flags = match flags.contains(PageTableFlags::WRITABLE) {
+            true => (flags - PageTableFlags::WRITABLE) | MERGED,
+            false => flags,
+        };
+        entry.set_flags(protected_flags);
+        tlb::flush(page.start_address());
+
+        if frame_bytes(frame) != frame_bytes(target) {
+            entry.set_flags(flags);
+            return false;
+        }
+
+        physical::inc_ref(target);
+        entry.set_frame(target, protected_flags);
+        tlb::flush(page.start_address());
+
+        if physical::dec_ref(frame) == 0 {
+            unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+        }
+        true
+    }).unwrap_or(false)
+}
+
+///
+/// Description:
+///    Make the merged `page` of `address_space` writable again. A frame, which is still shared,
+///    is copied first (copy-on-write).
+///
+/// Return: `true`, if `page` is writable now, `false` if it is not a merged page (or there is no memory for the copy)
+///
+pub fn unshare(address_space: &AddressSpace, page: Page) -> bool {
+    let _copy = COPY.lock();
+    address_space.update_entry(page, |entry| {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT | MERGED) {
+            return false;
+        }
+
+        let frame = PhysFrame::from_start_address(entry.addr()).unwrap();
+        let writable_flags = (flags - MERGED) | PageTableFlags::WRITABLE;
+        if physical::ref_count(frame) > 1 {
+            let Some(copy) = physical::alloc_frames(1, AllocFlags::SKIP_ZERO).map(|frames| frames[0]) else {
+                return false;
+            };
+
+            unsafe { (copy.start_address().as_u64() as *mut u8).copy_from_nonoverlapping(frame_bytes(frame).as_ptr(), PAGE_SIZE); }
+            physical::inc_ref(copy);
+            entry.set_frame(copy, writable_flags);
+            if physical::dec_ref(frame) == 0 {
+                unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+            }
+        } else {
+            entry.set_flags(writable_flags);
+        }
+
+        tlb::flush(page.start_address());
+        true
+    }).unwrap_or(false)
+}
+
+/// Description: Handle a write to the read-only page at `addr` of the current process (`true`, if it was a merged page, which is writable now)
+pub fn handle_write_fault(addr: VirtAddr) -> bool {
+    if addr.as_u64() < USER_SPACE_START as u64 {
+        return false;
+    }
+
+    let address_space = process_manager().read().current_process().address_space();
+    unshare(&address_space, Page::containing_address(addr))
+}
+
+/// Description: Body of the scanner thread, merging identical pages at a bounded rate
+pub fn run_scanner() {
+    loop {
+        scheduler().sleep(SCAN_INTERVAL_MS);
+        scan(SCAN_PAGES);
+    }
+}
@@ -0,0 +1,77 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: ksm_tests                                                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test merging identical pages and copying merged pages on the    ║
+   ║         first write. The tests map memory into the address space of the ║
+   ║         calling process and run complete scans (which may also merge    ║
+   ║         pages of other processes).                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::memory::{MAP_READ, MAP_WRITE};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::PageTableFlags;
+
+use crate::consts::USER_SPACE_MAP_START;
+use crate::memory::{ksm, physical, user, PAGE_SIZE};
+use crate::process_manager;
+use crate::syscall::sys_vmem::{sys_map_memory, sys_unmap_memory};
+
+const PAGES: usize = 3;
+
+///
+/// Description: Run all same-page merging tests
+///
+pub fn run_tests() {
+    info!("ksm: running tests");
+
+    test_merge_and_copy();
+
+    info!("ksm: all tests passed.");
+}
+
+fn page_addr(index: usize) -> VirtAddr {
+    VirtAddr::new((USER_SPACE_MAP_START + index * PAGE_SIZE) as u64)
+}
+
+/// Description: Fill page `index` of the test mapping with `byte`
+fn fill(index: usize, byte: u8) {
+    assert_eq!(user::copy_to_user(page_addr(index).as_mut_ptr(), &[byte; PAGE_SIZE]), Ok(()));
+}
+
+/// Description: Two identical pages share a frame after a pass, the first write gives the written page its own copy again
+fn test_merge_and_copy() {
+    let addr = USER_SPACE_MAP_START;
+    assert_eq!(sys_map_memory(addr, PAGES * PAGE_SIZE, MAP_READ | MAP_WRITE), addr as isize);
+    fill(0, 0x5a);
+    fill(1, 0x5a);
+    fill(2, 0xa5);
+
+    let address_space = process_manager().read().current_process().address_space();
+    let free_before = physical::free_frames();
+
+    // A pass started by an earlier scan is completed first, the second pass starts with an empty candidate table
+    ksm::scan(usize::MAX);
+    ksm::scan(usize::MAX);
+
+    let frame = |index: usize| address_space.translate(page_addr(index)).unwrap();
+    assert_eq!(frame(0), frame(1));
+    assert_ne!(frame(0), frame(2));
+    assert!(physical::free_frames() > free_before);
+
+    let flags = address_space.page_flags(page_addr(1)).unwrap();
+    assert!(flags.contains(ksm::MERGED));
+    assert!(!flags.contains(PageTableFlags::WRITABLE));
+    assert!(address_space.page_flags(page_addr(2)).unwrap().contains(PageTableFlags::WRITABLE));
+
+    // Copy-on-write: the other page keeps the original content
+    assert_eq!(user::copy_to_user(page_addr(1).as_mut_ptr(), b"written"), Ok(()));
+    assert_ne!(frame(0), frame(1));
+    assert!(!address_space.page_flags(page_addr(1)).unwrap().contains(ksm::MERGED));
+    assert_eq!(user::bytes_from_user(page_addr(0).as_ptr(), 7).as_deref(), Ok(&[0x5a; 7][..]));
+    assert_eq!(user::bytes_from_user(page_addr(1).as_ptr(), 7).as_deref(), Ok(&b"written"[..]));
+
+    assert_eq!(sys_unmap_memory(addr, PAGES * PAGE_SIZE), 0);
+}
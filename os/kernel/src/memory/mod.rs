@@ -1,7 +1,28 @@
 pub mod alloc;
+#[cfg(feature = "heap-tracking")]
+pub mod heap_tracking;
+pub mod ksm;
 pub mod physical;
+pub mod pressure;
+pub mod regions;
+pub mod swap;
 pub mod r#virtual;
 pub mod nvmem;
+pub mod nvram_alloc;
+pub mod nvram_snapshot;
+pub mod user;
+pub mod alloc_tests;
+#[cfg(feature = "heap-tracking")]
+pub mod heap_tracking_tests;
+pub mod ksm_tests;
+pub mod physical_tests;
+pub mod pressure_tests;
+pub mod regions_tests;
+pub mod swap_tests;
+pub mod virtual_tests;
+pub mod nvram_alloc_tests;
+pub mod nvram_snapshot_tests;
+pub mod user_tests;
 
 #[derive(Clone, Copy)]
 pub enum MemorySpace {
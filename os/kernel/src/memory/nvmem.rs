@@ -1,17 +1,28 @@
 use alloc::vec::Vec;
+use core::alloc::{AllocError, Allocator, Layout};
+use core::arch::asm;
 use core::ptr;
+use core::ptr::NonNull;
 use core::cmp::PartialEq;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
 use acpi::AcpiTable;
 use acpi::sdt::{SdtHeader, Signature};
 use bitflags::bitflags;
 use log::info;
+use spin::Mutex;
+use syscall::return_vals::Errno;
 use uefi::table::boot::PAGE_SIZE;
 use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
 use x86_64::structures::paging::frame::PhysFrameRange;
 use x86_64::structures::paging::page::PageRange;
 use x86_64::{PhysAddr, VirtAddr};
-use crate::{acpi_tables, process_manager};
-use crate::memory::MemorySpace;
+use crate::{acpi_tables, nvram_allocator, process_manager};
+use crate::memory::{physical, MemorySpace};
+use crate::memory::nvram_alloc::{Compaction, NvramAllocator};
+use crate::memory::nvram_snapshot;
+use crate::memory::nvram_snapshot::SnapshotId;
+use crate::memory::r#virtual::{AddressSpace, VirtualMemoryArea};
 
 #[allow(dead_code)]
 #[repr(u16)]
@@ -128,9 +139,13 @@ pub struct NvdimmControlRegionStructure {
 pub struct FlushHintAddressStructure {
     header: NfitStructureHeader,
     device_handle: u32,
-    hint_count: u16
+    hint_count: u16,
+    reserved: [u8; 6],
 }
 
+/// Flush hint addresses of all NVDIMMs (a write to them drains the write queues of the memory controller)
+static FLUSH_HINTS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
 unsafe impl AcpiTable for Nfit {
     const SIGNATURE: Signature = Signature::NFIT;
 
@@ -171,6 +186,19 @@ impl Nfit {
 
         return ranges;
     }
+
+    pub fn get_flush_hint_addresses(&self) -> Vec<u64> {
+        let mut hints = Vec::<u64>::new();
+
+        self.get_structures().iter().for_each(|structure| {
+            let structure_type = unsafe { ptr::from_ref(structure).read_unaligned().typ };
+            if structure_type == NfitStructureType::FlushHintAddress {
+                hints.extend(structure.as_structure::<FlushHintAddressStructure>().get_flush_hint_addresses());
+            }
+        });
+
+        hints
+    }
 }
 
 impl NfitStructureHeader {
@@ -209,7 +237,7 @@ pub fn init() {
         info!("Found NFIT table");
 
         // Search NFIT table for non-volatile memory ranges
-        for spa in nfit.get_phys_addr_ranges() {
+        for (i, spa) in nfit.get_phys_addr_ranges().iter().enumerate() {
             // Copy values to avoid unaligned access of packed struct fields
             let address = spa.base;
             let length = spa.length;
@@ -220,6 +248,202 @@ pub fn init() {
             process_manager().read().kernel_process().expect("Failed to get kernel process")
                 .address_space()
                 .map(PageRange { start: start_page, end: start_page + (length / PAGE_SIZE as u64) }, MemorySpace::Kernel, PageTableFlags::PRESENT | PageTableFlags::WRITABLE);
+
+            // Use first range for persistent allocations
+            if i == 0 {
+                if unsafe { nvram_allocator().init(address as *mut u8, length as usize) } {
+                    info!("Restored NVRAM allocator state from previous boot");
+                } else {
+                    info!("Formatted non-volatile memory for NVRAM allocator");
+                }
+
+                nvram_snapshot::set_region(Some(PageRange { start: start_page, end: start_page + (length / PAGE_SIZE as u64) })).expect("Failed to enable NVRAM snapshots");
+            }
+        }
+
+        // Map flush hint addresses uncached, so that writes to them reach the memory controller
+        let hints = nfit.get_flush_hint_addresses();
+        for hint in hints.iter() {
+            let page = Page::containing_address(VirtAddr::new(*hint));
+            process_manager().read().kernel_process().expect("Failed to get kernel process")
+                .address_space()
+                .map(PageRange { start: page, end: page + 1 }, MemorySpace::Kernel, PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE);
         }
+
+        info!("Found [{}] flush hint addresses", hints.len());
+        *FLUSH_HINTS.lock() = hints;
+    }
+}
+///
+/// Description:
+///    Allocate `count` zeroed page frames of non-volatile memory (for mapping them into user space).
+///    The frames must be returned with `unmap()` (or `free_frames()`, if they are not mapped).
+///
+pub fn alloc_frames(count: usize) -> Option<PhysFrameRange> {
+    if !nvram_allocator().is_initialized() {
+        return None;
     }
-}
\ No newline at end of file
+
+    let layout = Layout::from_size_align(count.checked_mul(PAGE_SIZE)?, PAGE_SIZE).ok()?;
+    let memory = nvram_allocator().allocate(layout).ok()?;
+    let start = PhysFrame::from_start_address(PhysAddr::new(memory.as_ptr() as *mut u8 as u64)).unwrap();
+    let frames = PhysFrameRange { start, end: start + count as u64 };
+
+    physical::zero(frames);
+    Some(frames)
+}
+
+/// Description: Unmap `vma` (mapped with frames from `alloc_frames()`) and return the frames to the NVRAM allocator
+pub fn unmap(address_space: &AddressSpace, vma: &VirtualMemoryArea) {
+    let start = address_space.translate(vma.start()).expect("NVRAM mapping is not mapped");
+    let start = PhysFrame::from_start_address(start).unwrap();
+
+    address_space.unmap(vma.range(), false);
+    free_frames(PhysFrameRange { start, end: start + (vma.end() - vma.start()) / PAGE_SIZE as u64 });
+}
+
+/// Description: Return `frames` (allocated with `alloc_frames()` and not mapped anymore) to the NVRAM allocator
+pub fn free_frames(frames: PhysFrameRange) {
+    let size = (frames.end - frames.start) as usize * PAGE_SIZE;
+    unsafe { nvram_allocator().deallocate(NonNull::new(frames.start.start_address().as_u64() as *mut u8).unwrap(), Layout::from_size_align(size, PAGE_SIZE).unwrap()); }
+}
+
+///
+/// Description:
+///    Compact the global NVRAM allocator by moving the root allocation with `root_layout`
+///    to the lowest free blocks (see `NvramAllocator::compact()`).
+///
+/// Return: the result or `AllocError`, if there is no non-volatile memory or the root does not match `root_layout`
+///
+/// # Safety
+/// No references to the root allocation may exist (offline, e.g. while booting)
+///
+pub unsafe fn compact(root_layout: Layout) -> Result<Compaction, AllocError> {
+    unsafe { nvram_allocator().compact(root_layout) }
+}
+
+///
+/// Description:
+///    Take a snapshot of the non-volatile memory (see `nvram_snapshot`). Snapshots can be nested,
+///    each one must be removed with `rollback()` or `discard()`.
+///
+/// Return: the id of the snapshot or `Errno::ENOSYS`, if there is no non-volatile memory
+///
+pub fn snapshot() -> Result<SnapshotId, Errno> {
+    nvram_snapshot::snapshot()
+}
+
+///
+/// Description: Restore the non-volatile memory to snapshot `id` (removes `id` and all newer snapshots)
+///
+/// Return: `Ok(())` or `Errno::ENOENT`, if there is no snapshot `id`
+///
+pub fn rollback(id: SnapshotId) -> Result<(), Errno> {
+    nvram_snapshot::rollback(id)
+}
+
+///
+/// Description: Remove snapshot `id` and keep the current content of the non-volatile memory
+///
+/// Return: `Ok(())` or `Errno::ENOENT`, if there is no snapshot `id`
+///
+pub fn discard(id: SnapshotId) -> Result<(), Errno> {
+    nvram_snapshot::discard(id)
+}
+
+///
+/// Description:
+///    Write back all cached data, so that pending writes to non-volatile memory
+///    are persisted (e.g. before shutting down). Written back data may still be queued
+///    in the memory controller, so the queues are drained by writing to the flush hint
+///    addresses of all NVDIMMs afterwards (if the platform provides them).
+///
+pub fn flush() {
+    unsafe { asm!("wbinvd", "sfence", options(nostack, preserves_flags)); }
+
+    let hints = FLUSH_HINTS.lock();
+    if !hints.is_empty() {
+        hints.iter().for_each(|hint| unsafe { (*hint as *mut u64).write_volatile(0) });
+        unsafe { asm!("sfence", options(nostack, preserves_flags)); }
+    }
+}
+
+///
+/// Description:
+///    Owning pointer to a value of type `T` in non-volatile memory (like `Box<T>`, but allocated
+///    with an `NvramAllocator`). The value is dropped and its memory is returned to the allocator,
+///    when the box is dropped. Use `leak()` to keep the value (e.g. as root of the allocator).
+///
+pub struct NvmemBox<'a, T> {
+    ptr: NonNull<T>,
+    allocator: &'a NvramAllocator,
+}
+
+impl<T> NvmemBox<'static, T> {
+    /// Description: Move `value` into non-volatile memory allocated with the global NVRAM allocator
+    pub fn new(value: T) -> Result<Self, AllocError> {
+        NvmemBox::new_in(value, nvram_allocator())
+    }
+}
+
+impl<'a, T> NvmemBox<'a, T> {
+    ///
+    /// Description:
+    ///    Move `value` into non-volatile memory allocated with `allocator`.
+    ///
+    /// Return: the box or `AllocError`, if the allocator is not initialized or has no space left
+    ///
+    pub fn new_in(value: T, allocator: &'a NvramAllocator) -> Result<Self, AllocError> {
+        let ptr = allocator.allocate(Layout::new::<T>())?.cast::<T>();
+        unsafe { ptr.write(value) };
+
+        Ok(Self { ptr, allocator })
+    }
+
+    ///
+    /// Description:
+    ///    Consume the box without dropping the value or freeing its memory.
+    ///    The value stays allocated until it is passed to `from_raw_in()` or deallocated manually.
+    ///
+    pub fn leak(self) -> *mut T {
+        ManuallyDrop::new(self).ptr.as_ptr()
+    }
+
+    ///
+    /// Description:
+    ///    Take ownership of a value, which has been leaked with `leak()` (e.g. before a reboot).
+    ///
+    /// Parameters: \
+    ///    `ptr` pointer returned by `leak()` \
+    ///    `allocator` allocator, which has been used to create the box
+    ///
+    /// # Safety
+    /// `ptr` must not be used otherwise and must not be taken again (the box frees it, when it is dropped).
+    ///
+    pub unsafe fn from_raw_in(ptr: *mut T, allocator: &'a NvramAllocator) -> Self {
+        Self { ptr: NonNull::new(ptr).expect("NvmemBox: Null pointer"), allocator }
+    }
+}
+
+impl<T> Deref for NvmemBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for NvmemBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for NvmemBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            self.allocator.deallocate(self.ptr.cast(), Layout::new::<T>());
+        }
+    }
+}
@@ -0,0 +1,617 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: nvram_alloc                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Allocator for non-volatile memory, whose metadata is stored in  ║
+   ║         the non-volatile memory itself, so allocations survive reboots. ║
+   ║                                                                         ║
+   ║         Layout of the region:                                           ║
+   ║           +0          header (magic, checksum, block count, root,       ║
+   ║                       relocation journal, transaction checkpoint)       ║
+   ║           +80         allocation bitmap (one bit per block)             ║
+   ║           +80+bitmap  bitmap at the start of the open transaction       ║
+   ║           +data       blocks of NVRAM_BLOCK_SIZE bytes (block aligned)  ║
+   ║                                                                         ║
+   ║         On 'init()', a region with valid magic and checksum is reused,  ║
+   ║         otherwise it is formatted. The 'root' pointer allows finding    ║
+   ║         an allocation from a previous boot (stored as offset, so the    ║
+   ║         region may be mapped at a different address).                   ║
+   ║         Metadata is only written back by 'nvmem::flush()' (shutdown).   ║
+   ║         The 'Allocator' impl does not log, unless the kernel is built   ║
+   ║         with the feature 'nvram-trace'.                                 ║
+   ║                                                                         ║
+   ║         'compact()' moves the root allocation to the lowest free        ║
+   ║         blocks (other allocations are unknown to the allocator and      ║
+   ║         stay in place). The data is copied first, then the metadata     ║
+   ║         update is written to the journal in the header, applied and     ║
+   ║         the journal is cleared. If a crash interrupts the update,       ║
+   ║         'init()' applies the journal again, so the root is either at    ║
+   ║         the old or at the new position.                                 ║
+   ║                                                                         ║
+   ║         'begin_transaction()' copies the bitmap and the root into the   ║
+   ║         checkpoint (copy-on-checkpoint), 'rollback()' restores them and ║
+   ║         'commit()' discards the checkpoint. If a crash interrupts a     ║
+   ║         transaction, 'init()' rolls it back, so no allocation made in   ║
+   ║         the transaction survives. Only the allocator state is restored, ║
+   ║         not the content of the memory (see 'nvram_snapshot').           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::alloc::{AllocError, Allocator, Layout};
+use core::ptr;
+use core::ptr::NonNull;
+use core::slice;
+use spin::Mutex;
+use crate::memory::nvmem;
+
+/// "D3OSNVM3" (version 2 added the relocation journal, version 3 the transaction checkpoint, older regions are formatted)
+const NVRAM_MAGIC: u64 = 0x44334f534e564d33;
+pub const NVRAM_BLOCK_SIZE: usize = 64;
+
+#[repr(C)]
+struct NvramHeader {
+    magic: u64,
+    checksum: u64,
+    block_count: u64,
+    root: u64, // offset of root allocation from region start, 0 = none
+    journal: NvramJournal,
+    checkpoint: NvramCheckpoint,
+}
+
+/// Pending relocation of the root allocation (see 'compact()'), not covered by the header checksum
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NvramJournal {
+    old_root: u64, // offsets from region start
+    new_root: u64,
+    blocks: u64,
+    checksum: u64, // over the other fields, 0 = no pending relocation
+}
+
+/// Root at the start of the open transaction (see `begin_transaction()`), not covered by the header checksum
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NvramCheckpoint {
+    root: u64,
+    checksum: u64, // over the root and the checkpoint bitmap, 0 = no open transaction
+}
+
+/// Result of `NvramAllocator::compact()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compaction {
+    pub moved_bytes: usize,  // 0, if the root allocation has not been moved
+    pub largest_free: usize, // largest contiguous free space afterwards (in bytes)
+}
+
+/// Usage of the NVRAM region (all values in bytes, see `NvramAllocator::stats()`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvramStats {
+    pub total: usize, // all blocks (without header and bitmap)
+    pub used: usize,
+    pub free: usize,
+    pub largest_free_block: usize, // largest contiguous free space (the largest possible allocation)
+}
+
+struct NvramRegion {
+    start: *mut u8,
+    block_count: usize,
+    data_offset: usize,
+}
+
+pub struct NvramAllocator {
+    region: Mutex<Option<NvramRegion>>,
+}
+
+unsafe impl Send for NvramAllocator {}
+unsafe impl Sync for NvramAllocator {}
+
+impl Default for NvramAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NvramAllocator {
+    pub const fn new() -> Self {
+        Self { region: Mutex::new(None) }
+    }
+
+    ///
+    /// Description:
+    ///    Manage the non-volatile memory at `start` with `length` bytes. If the region contains
+    ///    valid metadata from a previous boot, it is reused, otherwise the region is formatted.
+    ///    A transaction, which has not been committed before the reboot, is rolled back.
+    ///
+    /// Return: `true`, if allocations from a previous boot have been restored
+    ///
+    /// # Safety
+    /// `start` must point to `length` bytes of mapped non-volatile memory, which is not used otherwise.
+    ///
+    pub unsafe fn init(&self, start: *mut u8, length: usize) -> bool {
+        // Compute number of blocks, such that header, bitmap and blocks fit into the region
+        let header_size = size_of::<NvramHeader>();
+        assert!(length > header_size + NVRAM_BLOCK_SIZE, "NVRAM region too small");
+        let mut block_count = (length - header_size) * 8 / (8 * NVRAM_BLOCK_SIZE + 2);
+        let data_offset = |block_count: usize| (header_size + 2 * block_count.div_ceil(8)).next_multiple_of(NVRAM_BLOCK_SIZE);
+        while data_offset(block_count) + block_count * NVRAM_BLOCK_SIZE > length {
+            block_count -= 1;
+        }
+
+        let mut region = NvramRegion { start, block_count, data_offset: data_offset(block_count) };
+        region.recover();
+        region.recover_transaction();
+        let restored = region.is_valid();
+        if !restored {
+            region.format();
+        }
+
+        *self.region.lock() = Some(region);
+        restored
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.region.lock().is_some()
+    }
+
+    /// Description: Set the root allocation, which can be retrieved after a reboot with 'root()'
+    pub fn set_root(&self, root: Option<NonNull<u8>>) {
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().expect("NVRAM allocator not initialized");
+
+        region.header_mut().root = root.map_or(0, |ptr| ptr.as_ptr() as u64 - region.start as u64);
+        region.update_checksum();
+    }
+
+    /// Description: Get the root allocation (see 'set_root()')
+    pub fn root(&self) -> Option<NonNull<u8>> {
+        let guard = self.region.lock();
+        let region = guard.as_ref()?;
+
+        match region.header().root {
+            0 => None,
+            offset => NonNull::new(unsafe { region.start.add(offset as usize) }),
+        }
+    }
+
+    /// Description: Number of free bytes
+    pub fn free(&self) -> usize {
+        self.region.lock().as_ref().map_or(0, |region| region.free_blocks() * NVRAM_BLOCK_SIZE)
+    }
+
+    /// Description: Size of the largest contiguous free space in bytes (the largest possible allocation)
+    pub fn largest_free(&self) -> usize {
+        self.region.lock().as_ref().map_or(0, |region| region.largest_free())
+    }
+
+    ///
+    /// Description:
+    ///    Usage of the region, taken under a single lock (a consistent snapshot). The allocator is
+    ///    not waited for, so calling this while holding its lock (e.g. from tracing within an
+    ///    allocation) does not deadlock.
+    ///
+    /// Return: the statistics or `None`, if the allocator is not initialized or currently locked
+    ///
+    pub fn stats(&self) -> Option<NvramStats> {
+        let guard = self.region.try_lock()?;
+        let region = guard.as_ref()?;
+
+        let free = region.free_blocks() * NVRAM_BLOCK_SIZE;
+        let total = region.block_count * NVRAM_BLOCK_SIZE;
+        Some(NvramStats { total, used: total - free, free, largest_free_block: region.largest_free() })
+    }
+
+    ///
+    /// Description:
+    ///    Move the root allocation to the lowest free blocks (at its alignment), so that the free
+    ///    space behind it is coalesced. The move is failure atomic (see module description).
+    ///    Free blocks are always coalesced in the bitmap, so allocations, which are not reachable
+    ///    from the root, stay where they are.
+    ///
+    /// Parameters: `root_layout` layout of the root allocation
+    ///
+    /// Return: the result or `AllocError`, if the allocator is not initialized or the root
+    ///         allocation does not match `root_layout` (some of its blocks are free)
+    ///
+    /// # Safety
+    /// No references to the root allocation may exist (e.g. call it while booting, before
+    /// `root()` is used) and it must not contain pointers to itself.
+    ///
+    pub unsafe fn compact(&self, root_layout: Layout) -> Result<Compaction, AllocError> {
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().ok_or(AllocError)?;
+
+        let moved_bytes = match region.journal_root_move(root_layout)? {
+            Some(journal) => {
+                region.apply(&journal);
+                nvmem::flush();
+                region.clear_journal();
+                journal.blocks as usize * NVRAM_BLOCK_SIZE
+            }
+            None => 0,
+        };
+
+        Ok(Compaction { moved_bytes, largest_free: region.largest_free() })
+    }
+
+    ///
+    /// Description:
+    ///    Begin a transaction: the current allocations and the root are recorded in the
+    ///    checkpoint, until the transaction is ended with `commit()` or `rollback()`.
+    ///
+    /// Return: `Ok(())` or `AllocError`, if the allocator is not initialized or a transaction is open
+    ///
+    pub fn begin_transaction(&self) -> Result<(), AllocError> {
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().ok_or(AllocError)?;
+        if region.in_transaction() {
+            return Err(AllocError);
+        }
+
+        let (bitmap, checkpoint_bitmap) = region.bitmaps_mut();
+        checkpoint_bitmap.copy_from_slice(bitmap);
+        region.header_mut().checkpoint.root = region.header().root;
+        nvmem::flush();
+
+        // Commit point: from now on, 'init()' rolls back to the checkpoint
+        region.header_mut().checkpoint.checksum = region.checkpoint_checksum();
+        nvmem::flush();
+        Ok(())
+    }
+
+    ///
+    /// Description: End the open transaction and keep all allocations made in it.
+    ///
+    /// Return: `Ok(())` or `AllocError`, if there is no open transaction
+    ///
+    pub fn commit(&self) -> Result<(), AllocError> {
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().ok_or(AllocError)?;
+        if !region.in_transaction() {
+            return Err(AllocError);
+        }
+
+        // The metadata must be persistent, before the checkpoint is discarded
+        nvmem::flush();
+        region.clear_checkpoint();
+        Ok(())
+    }
+
+    ///
+    /// Description:
+    ///    End the open transaction and restore the allocations and the root at its start
+    ///    (allocations made in the transaction are freed, deallocations are undone).
+    ///
+    /// Return: `Ok(())` or `AllocError`, if there is no open transaction
+    ///
+    /// # Safety
+    /// Memory allocated in the transaction must not be used anymore.
+    ///
+    pub unsafe fn rollback(&self) -> Result<(), AllocError> {
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().ok_or(AllocError)?;
+        if !region.in_transaction() {
+            return Err(AllocError);
+        }
+
+        region.restore_checkpoint();
+        Ok(())
+    }
+
+    /// Description: Check if a transaction is open (see `begin_transaction()`)
+    pub fn in_transaction(&self) -> bool {
+        self.region.lock().as_ref().is_some_and(|region| region.in_transaction())
+    }
+
+    ///
+    /// Description:
+    ///    Like `compact()`, but stop after the relocation has been written to the journal
+    ///    (simulates a crash during compaction for tests, the root still points to the old copy).
+    ///
+    /// Return: `true`, if a relocation has been journaled
+    ///
+    pub(crate) unsafe fn compact_interrupted(&self, root_layout: Layout) -> Result<bool, AllocError> {
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().ok_or(AllocError)?;
+
+        region.journal_root_move(root_layout).map(|journal| journal.is_some())
+    }
+}
+
+unsafe impl Allocator for NvramAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(NonNull::slice_from_raw_parts(layout.dangling(), 0));
+        }
+
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().ok_or(AllocError)?;
+        let blocks = layout.size().div_ceil(NVRAM_BLOCK_SIZE);
+
+        if let Some(first) = region.find_free(blocks, layout.align(), region.block_count) {
+            let address = region.block_address(first);
+            (first..first + blocks).for_each(|block| region.set_used(block, true));
+            region.update_checksum();
+
+            #[cfg(feature = "nvram-trace")]
+            log::info!("NVRAM: Allocated [{}] bytes at [{:?}] (blocks [{}..{}])", layout.size(), address, first, first + blocks);
+            return Ok(NonNull::slice_from_raw_parts(NonNull::new(address).unwrap(), layout.size()));
+        }
+
+        #[cfg(feature = "nvram-trace")]
+        log::info!("NVRAM: Failed to allocate [{}] bytes", layout.size());
+
+        Err(AllocError)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        let mut guard = self.region.lock();
+        let region = guard.as_mut().expect("NVRAM allocator not initialized");
+        let first = (ptr.as_ptr() as usize - region.block_address(0) as usize) / NVRAM_BLOCK_SIZE;
+
+        (first..first + layout.size().div_ceil(NVRAM_BLOCK_SIZE)).for_each(|block| region.set_used(block, false));
+        region.update_checksum();
+
+        #[cfg(feature = "nvram-trace")]
+        log::info!("NVRAM: Deallocated [{}] bytes at [{:?}]", layout.size(), ptr);
+    }
+}
+
+impl NvramRegion {
+    fn header(&self) -> &NvramHeader {
+        unsafe { &*(self.start as *const NvramHeader) }
+    }
+
+    fn header_mut(&mut self) -> &mut NvramHeader {
+        unsafe { &mut *(self.start as *mut NvramHeader) }
+    }
+
+    fn bitmap(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.start.add(size_of::<NvramHeader>()), self.block_count.div_ceil(8)) }
+    }
+
+    fn bitmap_mut(&mut self) -> &mut [u8] {
+        self.bitmaps_mut().0
+    }
+
+    /// Description: Allocation bitmap and checkpoint bitmap (stored behind each other after the header)
+    fn bitmaps_mut(&mut self) -> (&mut [u8], &mut [u8]) {
+        let length = self.block_count.div_ceil(8);
+        unsafe { slice::from_raw_parts_mut(self.start.add(size_of::<NvramHeader>()), 2 * length) }.split_at_mut(length)
+    }
+
+    fn checkpoint_bitmap(&self) -> &[u8] {
+        let length = self.block_count.div_ceil(8);
+        unsafe { slice::from_raw_parts(self.start.add(size_of::<NvramHeader>() + length), length) }
+    }
+
+    fn block_address(&self, block: usize) -> *mut u8 {
+        unsafe { self.start.add(self.data_offset + block * NVRAM_BLOCK_SIZE) }
+    }
+
+    /// Description: Block index of the allocation at `offset` (from region start), if it lies at a block start
+    fn block_index(&self, offset: u64) -> Option<usize> {
+        let offset = (offset as usize).checked_sub(self.data_offset)?;
+        match offset % NVRAM_BLOCK_SIZE == 0 && offset / NVRAM_BLOCK_SIZE < self.block_count {
+            true => Some(offset / NVRAM_BLOCK_SIZE),
+            false => None,
+        }
+    }
+
+    fn block_offset(&self, block: usize) -> u64 {
+        (self.data_offset + block * NVRAM_BLOCK_SIZE) as u64
+    }
+
+    /// Description: First fit search for `blocks` free blocks at alignment `align`, which end before block `limit`
+    fn find_free(&self, blocks: usize, align: usize, limit: usize) -> Option<usize> {
+        let mut first = 0;
+        while first + blocks <= limit {
+            if !(self.block_address(first) as usize).is_multiple_of(align) {
+                first += 1;
+                continue;
+            }
+
+            match (first..first + blocks).find(|block| self.is_used(*block)) {
+                Some(used) => first = used + 1,
+                None => return Some(first),
+            }
+        }
+
+        None
+    }
+
+    fn free_blocks(&self) -> usize {
+        (0..self.block_count).filter(|block| !self.is_used(*block)).count()
+    }
+
+    fn largest_free(&self) -> usize {
+        let (largest, _) = (0..self.block_count).fold((0, 0), |(largest, current), block| match self.is_used(block) {
+            true => (largest, 0),
+            false => (largest.max(current + 1), current + 1),
+        });
+
+        largest * NVRAM_BLOCK_SIZE
+    }
+
+    ///
+    /// Description:
+    ///    First half of a compaction: copy the root allocation to the lowest free blocks below it
+    ///    and write the relocation to the journal (the commit point, see `recover()`).
+    ///
+    /// Return: the journaled relocation or `None`, if there is no root or no free space below it
+    ///
+    fn journal_root_move(&mut self, root_layout: Layout) -> Result<Option<NvramJournal>, AllocError> {
+        let root = self.header().root;
+        if root == 0 || root_layout.size() == 0 {
+            return Ok(None);
+        }
+
+        let blocks = root_layout.size().div_ceil(NVRAM_BLOCK_SIZE);
+        let old_first = self.block_index(root).ok_or(AllocError)?;
+        if old_first + blocks > self.block_count || (old_first..old_first + blocks).any(|block| !self.is_used(block)) {
+            return Err(AllocError);
+        }
+
+        // The old blocks are used, so the new ones never overlap them
+        let new_first = match self.find_free(blocks, root_layout.align(), old_first) {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        unsafe { ptr::copy_nonoverlapping(self.block_address(old_first), self.block_address(new_first), blocks * NVRAM_BLOCK_SIZE); }
+        nvmem::flush();
+
+        let journal = NvramJournal::new(root, self.block_offset(new_first), blocks as u64);
+        self.header_mut().journal = journal;
+        nvmem::flush();
+
+        Ok(Some(journal))
+    }
+
+    /// Description: Move the root in the metadata as described by `journal` (may be repeated after a crash)
+    fn apply(&mut self, journal: &NvramJournal) {
+        let old_first = self.block_index(journal.old_root).unwrap();
+        let new_first = self.block_index(journal.new_root).unwrap();
+        let blocks = journal.blocks as usize;
+
+        (old_first..old_first + blocks).for_each(|block| self.set_used(block, false));
+        (new_first..new_first + blocks).for_each(|block| self.set_used(block, true));
+        self.header_mut().root = journal.new_root;
+        self.update_checksum();
+    }
+
+    fn clear_journal(&mut self) {
+        let journal = &mut self.header_mut().journal;
+        journal.checksum = 0; // invalidate first
+        nvmem::flush();
+        *journal = NvramJournal::EMPTY;
+        nvmem::flush();
+    }
+
+    /// Description: Complete a relocation, which has been interrupted after it has been journaled
+    fn recover(&mut self) {
+        let header = self.header();
+        let journal = header.journal;
+        if header.magic != NVRAM_MAGIC || header.block_count != self.block_count as u64 || !journal.is_valid() {
+            return;
+        }
+
+        let in_region = |offset: u64| self.block_index(offset).is_some_and(|first| first + journal.blocks as usize <= self.block_count);
+        if journal.blocks > 0 && in_region(journal.old_root) && in_region(journal.new_root) {
+            self.apply(&journal);
+            nvmem::flush();
+        }
+
+        self.clear_journal();
+    }
+
+    /// Description: Roll back a transaction, which has not been committed before a crash or reboot
+    fn recover_transaction(&mut self) {
+        let header = self.header();
+        if header.magic == NVRAM_MAGIC && header.block_count == self.block_count as u64 && self.in_transaction() {
+            self.restore_checkpoint();
+        }
+    }
+
+    fn in_transaction(&self) -> bool {
+        let checksum = self.header().checkpoint.checksum;
+        checksum != 0 && checksum == self.checkpoint_checksum()
+    }
+
+    /// Description: Checksum over the root and the bitmap of the checkpoint (never 0, which marks no open transaction)
+    fn checkpoint_checksum(&self) -> u64 {
+        checksum(self.header().checkpoint.root.to_le_bytes().iter()
+            .chain(self.checkpoint_bitmap().iter())) | 1
+    }
+
+    /// Description: Restore the bitmap and the root of the checkpoint and close the transaction (may be repeated after a crash)
+    fn restore_checkpoint(&mut self) {
+        let (bitmap, checkpoint_bitmap) = self.bitmaps_mut();
+        bitmap.copy_from_slice(checkpoint_bitmap);
+        self.header_mut().root = self.header().checkpoint.root;
+        self.update_checksum();
+        nvmem::flush();
+
+        self.clear_checkpoint();
+    }
+
+    fn clear_checkpoint(&mut self) {
+        self.header_mut().checkpoint = NvramCheckpoint::EMPTY;
+        nvmem::flush();
+    }
+
+    fn is_used(&self, block: usize) -> bool {
+        self.bitmap()[block / 8] & (1 << (block % 8)) != 0
+    }
+
+    fn set_used(&mut self, block: usize, used: bool) {
+        let bitmap = self.bitmap_mut();
+        if used {
+            bitmap[block / 8] |= 1 << (block % 8);
+        } else {
+            bitmap[block / 8] &= !(1 << (block % 8));
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        let header = self.header();
+        header.magic == NVRAM_MAGIC && header.block_count == self.block_count as u64 && header.checksum == self.checksum()
+    }
+
+    fn format(&mut self) {
+        self.bitmap_mut().fill(0);
+
+        let block_count = self.block_count as u64;
+        let header = self.header_mut();
+        header.magic = NVRAM_MAGIC;
+        header.block_count = block_count;
+        header.root = 0;
+        header.journal = NvramJournal::EMPTY;
+        header.checkpoint = NvramCheckpoint::EMPTY;
+        self.update_checksum();
+    }
+
+    fn update_checksum(&mut self) {
+        self.header_mut().checksum = self.checksum();
+    }
+
+    /// Description: Checksum over block count, root offset and the allocation bitmap
+    fn checksum(&self) -> u64 {
+        let header = self.header();
+        checksum(header.block_count.to_le_bytes().iter()
+            .chain(header.root.to_le_bytes().iter())
+            .chain(self.bitmap().iter()))
+    }
+}
+
+impl NvramCheckpoint {
+    const EMPTY: NvramCheckpoint = NvramCheckpoint { root: 0, checksum: 0 };
+}
+
+impl NvramJournal {
+    const EMPTY: NvramJournal = NvramJournal { old_root: 0, new_root: 0, blocks: 0, checksum: 0 };
+
+    fn new(old_root: u64, new_root: u64, blocks: u64) -> Self {
+        let mut journal = NvramJournal { old_root, new_root, blocks, checksum: 0 };
+        journal.checksum = journal.compute_checksum();
+        journal
+    }
+
+    fn is_valid(&self) -> bool {
+        self.checksum != 0 && self.checksum == self.compute_checksum()
+    }
+
+    /// Description: Checksum over all other fields (never 0, which marks an empty journal)
+    fn compute_checksum(&self) -> u64 {
+        checksum(self.old_root.to_le_bytes().iter()
+            .chain(self.new_root.to_le_bytes().iter())
+            .chain(self.blocks.to_le_bytes().iter())) | 1
+    }
+}
+
+/// Description: FNV-1a hash over `bytes`, used to detect incomplete writes to non-volatile memory
+pub fn checksum<'a>(bytes: impl Iterator<Item = &'a u8>) -> u64 {
+    bytes.fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
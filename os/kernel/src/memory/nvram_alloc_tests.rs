@@ -0,0 +1,411 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: nvram_alloc_tests                                               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test that allocations of the NVRAM allocator survive a reboot.  ║
+   ║         A reboot is simulated by initializing a new allocator over the  ║
+   ║         same memory, which is a buffer on the kernel heap (so the tests ║
+   ║         also run on machines without non-volatile memory). Also tests   ║
+   ║         compaction and its recovery after a simulated crash, the usage  ║
+   ║         statistics and transactions.                                    ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec;
+use core::alloc::{Allocator, Layout};
+use core::ptr::NonNull;
+use ::log::info;
+
+use crate::memory::nvmem::NvmemBox;
+use crate::memory::nvram_alloc::{Compaction, NvramAllocator, NvramStats, NVRAM_BLOCK_SIZE};
+
+const REGION_SIZE: usize = 16 * 1024;
+/// Offsets of the relocation journal, the transaction checkpoint and the allocation bitmap in the region (see 'nvram_alloc.rs')
+const JOURNAL_OFFSET: usize = 32;
+const CHECKPOINT_OFFSET: usize = 64;
+const BITMAP_OFFSET: usize = 80;
+
+///
+/// Description:
+///    Run all NVRAM allocator tests
+///
+pub fn run_tests() {
+    info!("nvram_alloc: running tests");
+
+    test_survives_reboot();
+    test_corrupted_metadata();
+    test_alignment();
+    test_round_trip();
+    test_stats();
+    test_box();
+    test_box_leak();
+    test_compact();
+    test_compact_interrupted();
+    test_compact_torn_journal();
+    test_compact_wrong_layout();
+    test_transaction_rollback();
+    test_transaction_commit();
+    test_transaction_crash();
+
+    info!("nvram_alloc: all tests passed.");
+}
+
+/// Description: A value allocated before a reboot can be read via the root pointer afterwards
+fn test_survives_reboot() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    assert!(!unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "empty region must be formatted");
+    assert!(allocator.root().is_none());
+
+    let value = allocator.allocate(Layout::new::<u64>()).unwrap().cast::<u64>();
+    unsafe { value.write(0xd3057e57) };
+    allocator.set_root(Some(value.cast()));
+    let free = allocator.free();
+
+    // "Reboot"
+    let allocator = NvramAllocator::new();
+    assert!(unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "valid region must be restored");
+    assert_eq!(allocator.free(), free);
+
+    let root = allocator.root().expect("root lost after reboot").cast::<u64>();
+    assert_eq!(root, value);
+    assert_eq!(unsafe { root.read() }, 0xd3057e57);
+
+    // The restored allocation is still in use
+    let other = allocator.allocate(Layout::new::<u64>()).unwrap().cast::<u64>();
+    assert_ne!(other, value);
+
+    unsafe { allocator.deallocate(other.cast(), Layout::new::<u64>()); }
+    unsafe { allocator.deallocate(value.cast(), Layout::new::<u64>()); }
+    assert_eq!(allocator.free(), free + NVRAM_BLOCK_SIZE);
+}
+
+/// Description: Metadata with a wrong checksum is discarded and the region is formatted
+fn test_corrupted_metadata() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let value = allocator.allocate(Layout::new::<u64>()).unwrap();
+    allocator.set_root(Some(value.cast()));
+
+    // Flip a bit in the allocation bitmap (directly behind the header)
+    region[BITMAP_OFFSET] ^= 0x80;
+
+    let allocator = NvramAllocator::new();
+    assert!(!unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "corrupted region must be formatted");
+    assert!(allocator.root().is_none());
+}
+
+/// Description: Allocations respect the requested alignment and fail, if the region is full
+fn test_alignment() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+
+    let layout = Layout::from_size_align(100, 256).unwrap();
+    let first = allocator.allocate(layout).unwrap();
+    let second = allocator.allocate(layout).unwrap();
+    assert_eq!(first.cast::<u8>().as_ptr() as usize % 256, 0);
+    assert_eq!(second.cast::<u8>().as_ptr() as usize % 256, 0);
+    assert_ne!(first.cast::<u8>(), second.cast::<u8>());
+
+    assert!(allocator.allocate(Layout::from_size_align(REGION_SIZE, 8).unwrap()).is_err());
+}
+
+/// Description: Allocate/deallocate round trip returns pointers inside the region and frees all blocks again
+fn test_round_trip() {
+    let mut region = vec![0u8; REGION_SIZE];
+    let range = region.as_ptr_range();
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let free = allocator.free();
+
+    for size in [1, NVRAM_BLOCK_SIZE, 3 * NVRAM_BLOCK_SIZE + 1] {
+        let layout = Layout::from_size_align(size, 8).unwrap();
+        let ptr = allocator.allocate(layout).unwrap();
+        let start = ptr.cast::<u8>().as_ptr() as *const u8;
+        assert!(range.contains(&start) && range.contains(&unsafe { start.add(size - 1) }), "allocation outside of region");
+
+        unsafe { allocator.deallocate(ptr.cast(), layout); }
+        assert_eq!(allocator.free(), free);
+    }
+
+    // Zero sized allocations do not use any blocks
+    let empty = allocator.allocate(Layout::new::<()>()).unwrap();
+    assert_eq!(empty.len(), 0);
+    unsafe { allocator.deallocate(empty.cast(), Layout::new::<()>()); }
+    assert_eq!(allocator.free(), free);
+}
+
+/// Description: Used and free bytes always add up to the total, freeing restores the free bytes
+fn test_stats() {
+    let mut region = vec![0u8; REGION_SIZE];
+    let allocator = NvramAllocator::new();
+    assert_eq!(allocator.stats(), None);
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+
+    let empty = allocator.stats().unwrap();
+    assert_eq!(empty.used, 0);
+    assert_eq!(empty.free, empty.total);
+    assert_eq!(empty.largest_free_block, empty.total);
+    assert!(empty.total > 0 && empty.total < REGION_SIZE);
+
+    let first_layout = Layout::from_size_align(2 * NVRAM_BLOCK_SIZE, 1).unwrap();
+    let second_layout = Layout::from_size_align(NVRAM_BLOCK_SIZE + 1, 1).unwrap();
+    let first = allocator.allocate(first_layout).unwrap();
+    let second = allocator.allocate(second_layout).unwrap();
+
+    let stats = allocator.stats().unwrap();
+    assert_eq!(stats, NvramStats { total: empty.total, used: 4 * NVRAM_BLOCK_SIZE, free: empty.total - 4 * NVRAM_BLOCK_SIZE, largest_free_block: empty.total - 4 * NVRAM_BLOCK_SIZE });
+    assert_eq!(stats.used + stats.free, stats.total);
+    assert_eq!(stats.free, allocator.free());
+
+    // A hole at the start is free, but not part of the largest free block
+    unsafe { allocator.deallocate(first.cast(), first_layout); }
+    let stats = allocator.stats().unwrap();
+    assert_eq!(stats.used + stats.free, stats.total);
+    assert_eq!(stats.free, empty.total - 2 * NVRAM_BLOCK_SIZE);
+    assert_eq!(stats.largest_free_block, empty.total - 4 * NVRAM_BLOCK_SIZE);
+
+    unsafe { allocator.deallocate(second.cast(), second_layout); }
+    assert_eq!(allocator.stats(), Some(empty));
+}
+
+#[derive(Debug, PartialEq)]
+struct Record {
+    id: u64,
+    values: [u32; 8],
+}
+
+/// Description: A value in an `NvmemBox` can be modified via `DerefMut` and its memory is freed on drop
+fn test_box() {
+    let mut region = vec![0u8; REGION_SIZE];
+    let range = region.as_ptr_range();
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let free = allocator.free();
+
+    let mut record = NvmemBox::new_in(Record { id: 1, values: [0; 8] }, &allocator).unwrap();
+    assert!(range.contains(&(&*record as *const Record as *const u8)), "box outside of region");
+    assert!(allocator.free() < free);
+
+    record.id = 42;
+    record.values[7] = 0xd3;
+    assert_eq!(*record, Record { id: 42, values: [0, 0, 0, 0, 0, 0, 0, 0xd3] });
+
+    drop(record);
+    assert_eq!(allocator.free(), free);
+
+    // Uninitialized allocator
+    assert!(NvmemBox::new_in(0u64, &NvramAllocator::new()).is_err());
+}
+
+/// Description: A leaked `NvmemBox` keeps its memory and can be restored with `from_raw_in()`
+fn test_box_leak() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let free = allocator.free();
+
+    let value = NvmemBox::new_in(0xd3057e57u64, &allocator).unwrap().leak();
+    let used = allocator.free();
+    assert!(used < free);
+    assert_eq!(unsafe { value.read() }, 0xd3057e57);
+
+    let value = unsafe { NvmemBox::from_raw_in(value, &allocator) };
+    assert_eq!(allocator.free(), used);
+    assert_eq!(*value, 0xd3057e57);
+
+    drop(value);
+    assert_eq!(allocator.free(), free);
+}
+
+/// Description: Allocate a gap, the root `Record` and another allocation behind it, then free the gap
+fn fragmented_root(allocator: &NvramAllocator) -> (NonNull<Record>, NonNull<u8>) {
+    let gap_layout = Layout::from_size_align(2 * NVRAM_BLOCK_SIZE, 8).unwrap();
+    let gap = allocator.allocate(gap_layout).unwrap().cast::<u8>();
+    let root = allocator.allocate(Layout::new::<Record>()).unwrap().cast::<Record>();
+    let _behind = allocator.allocate(Layout::new::<u64>()).unwrap();
+    unsafe { root.write(Record { id: 7, values: [1, 2, 3, 4, 5, 6, 7, 8] }) };
+    allocator.set_root(Some(root.cast()));
+    unsafe { allocator.deallocate(gap, gap_layout); }
+
+    (root, gap)
+}
+
+/// Description: Compaction moves the root to the lowest free blocks and keeps its data, also across a reboot
+fn test_compact() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let (old_root, gap) = fragmented_root(&allocator);
+    let free = allocator.free();
+
+    let compaction = unsafe { allocator.compact(Layout::new::<Record>()) }.unwrap();
+    assert_eq!(compaction, Compaction { moved_bytes: NVRAM_BLOCK_SIZE, largest_free: allocator.largest_free() });
+    assert_eq!(allocator.free(), free);
+
+    let root = allocator.root().unwrap().cast::<Record>();
+    assert_eq!(root.cast::<u8>(), gap);
+    assert_ne!(root, old_root);
+    assert_eq!(unsafe { root.read() }, Record { id: 7, values: [1, 2, 3, 4, 5, 6, 7, 8] });
+
+    // The old blocks are free again (coalesced with the rest of the gap), the root is not moved a second time
+    let merged = allocator.allocate(Layout::from_size_align(2 * NVRAM_BLOCK_SIZE, 8).unwrap()).unwrap().cast::<u8>();
+    assert_eq!(merged.as_ptr(), unsafe { gap.as_ptr().add(NVRAM_BLOCK_SIZE) });
+    assert_eq!(merged.as_ptr().wrapping_add(NVRAM_BLOCK_SIZE), old_root.as_ptr() as *mut u8);
+    assert_eq!(unsafe { allocator.compact(Layout::new::<Record>()) }.unwrap().moved_bytes, 0);
+
+    // "Reboot"
+    let allocator = NvramAllocator::new();
+    assert!(unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "compacted region must be restored");
+    assert_eq!(allocator.root().unwrap().cast::<Record>(), root);
+    assert_eq!(unsafe { root.read() }.id, 7);
+
+    // Nothing to do without a root
+    allocator.set_root(None);
+    assert_eq!(unsafe { allocator.compact(Layout::new::<Record>()) }.unwrap().moved_bytes, 0);
+}
+
+/// Description: A crash after the relocation has been journaled is completed on the next boot
+fn test_compact_interrupted() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let (old_root, gap) = fragmented_root(&allocator);
+    let free = allocator.free();
+
+    assert!(unsafe { allocator.compact_interrupted(Layout::new::<Record>()) }.unwrap());
+    assert_eq!(allocator.root().unwrap().cast::<Record>(), old_root);
+
+    // "Reboot" with the journal in place
+    let allocator = NvramAllocator::new();
+    assert!(unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "journaled region must be recovered");
+    assert_eq!(allocator.root().unwrap().cast::<u8>(), gap);
+    assert_eq!(unsafe { allocator.root().unwrap().cast::<Record>().read() }, Record { id: 7, values: [1, 2, 3, 4, 5, 6, 7, 8] });
+    assert_eq!(allocator.free(), free);
+    assert!(region[JOURNAL_OFFSET..CHECKPOINT_OFFSET].iter().all(|byte| *byte == 0), "journal not cleared");
+}
+
+/// Description: A crash while writing the journal leaves the root at its old position
+fn test_compact_torn_journal() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let (old_root, _) = fragmented_root(&allocator);
+    let free = allocator.free();
+
+    assert!(unsafe { allocator.compact_interrupted(Layout::new::<Record>()) }.unwrap());
+    region[JOURNAL_OFFSET + 8] ^= 0x40; // incomplete write of the new root offset
+
+    let allocator = NvramAllocator::new();
+    assert!(unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "region with torn journal must be restored");
+    assert_eq!(allocator.root().unwrap().cast::<Record>(), old_root);
+    assert_eq!(unsafe { old_root.read() }.values, [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(allocator.free(), free);
+}
+
+/// Description: A layout larger than the root allocation is rejected, the root stays in place
+fn test_compact_wrong_layout() {
+    let mut region = vec![0u8; REGION_SIZE];
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let root = allocator.allocate(Layout::new::<u64>()).unwrap().cast::<u8>();
+    allocator.set_root(Some(root));
+
+    assert!(unsafe { allocator.compact(Layout::from_size_align(4 * NVRAM_BLOCK_SIZE, 8).unwrap()) }.is_err());
+    assert_eq!(allocator.root(), Some(root));
+
+    // Uninitialized allocator
+    assert!(unsafe { NvramAllocator::new().compact(Layout::new::<u64>()) }.is_err());
+}
+
+/// Description: Allocations made in a rolled back transaction are reclaimed, deallocations are undone
+fn test_transaction_rollback() {
+    let mut region = vec![0u8; REGION_SIZE];
+    let layout = Layout::from_size_align(2 * NVRAM_BLOCK_SIZE, 8).unwrap();
+
+    let allocator = NvramAllocator::new();
+    assert!(allocator.begin_transaction().is_err());
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    assert!(unsafe { allocator.rollback() }.is_err());
+
+    let first = allocator.allocate(layout).unwrap().cast::<u8>();
+    let free = allocator.free();
+
+    allocator.begin_transaction().unwrap();
+    assert!(allocator.in_transaction());
+    assert!(allocator.begin_transaction().is_err(), "transactions are not nested");
+    let second = allocator.allocate(layout).unwrap().cast::<u8>();
+    allocator.set_root(Some(second));
+    unsafe { allocator.deallocate(first, layout); }
+
+    unsafe { allocator.rollback() }.unwrap();
+    assert!(!allocator.in_transaction());
+    assert_eq!(allocator.free(), free);
+    assert!(allocator.root().is_none());
+
+    // The blocks of the second allocation are free again, the first allocation is still in use
+    let third = allocator.allocate(layout).unwrap().cast::<u8>();
+    assert_eq!(third, second);
+    assert_ne!(third, first);
+}
+
+/// Description: Allocations made in a committed transaction are kept, also across a reboot
+fn test_transaction_commit() {
+    let mut region = vec![0u8; REGION_SIZE];
+    let layout = Layout::new::<u64>();
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    assert!(allocator.commit().is_err());
+    let free = allocator.free();
+
+    allocator.begin_transaction().unwrap();
+    let value = allocator.allocate(layout).unwrap().cast::<u8>();
+    allocator.set_root(Some(value));
+    allocator.commit().unwrap();
+    assert!(!allocator.in_transaction());
+    assert_eq!(allocator.free(), free - NVRAM_BLOCK_SIZE);
+
+    // "Reboot"
+    let allocator = NvramAllocator::new();
+    assert!(unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) });
+    assert_eq!(allocator.root(), Some(value));
+    assert_eq!(allocator.free(), free - NVRAM_BLOCK_SIZE);
+}
+
+/// Description: A transaction, which has not been committed before a reboot, is rolled back by `init()`
+fn test_transaction_crash() {
+    let mut region = vec![0u8; REGION_SIZE];
+    let layout = Layout::new::<u64>();
+
+    let allocator = NvramAllocator::new();
+    unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) };
+    let root = allocator.allocate(layout).unwrap().cast::<u8>();
+    allocator.set_root(Some(root));
+    let free = allocator.free();
+
+    allocator.begin_transaction().unwrap();
+    let lost = allocator.allocate(layout).unwrap().cast::<u8>();
+    allocator.set_root(Some(lost));
+    region[BITMAP_OFFSET + 8] = 0xff; // incomplete write of the bitmap (the header checksum does not match anymore)
+
+    // "Reboot" in the middle of the transaction
+    let allocator = NvramAllocator::new();
+    assert!(unsafe { allocator.init(region.as_mut_ptr(), REGION_SIZE) }, "region must be restored from the checkpoint");
+    assert!(!allocator.in_transaction());
+    assert_eq!(allocator.root(), Some(root));
+    assert_eq!(allocator.free(), free);
+    assert_eq!(allocator.allocate(layout).unwrap().cast::<u8>(), lost);
+}
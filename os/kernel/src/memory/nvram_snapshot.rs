@@ -0,0 +1,242 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: nvram_snapshot                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Snapshots of the non-volatile memory for checkpointing          ║
+   ║         persistent data. 'snapshot()' write-protects all NVRAM pages    ║
+   ║         in the identity mapping of every address space. The first       ║
+   ║         write to a protected page causes a page fault, the handler      ║
+   ║         copies the page into a frame of normal memory (owned by the     ║
+   ║         newest snapshot) and makes the page writable again.             ║
+   ║                                                                         ║
+   ║         Snapshots are nested (generations, oldest first). A page is     ║
+   ║         write-protected, if the newest snapshot has no copy of it. A    ║
+   ║         snapshot without a copy of a page shares its content with the   ║
+   ║         next newer snapshot, so 'rollback()' restores the oldest copy   ║
+   ║         of each page from the snapshot and all newer ones, and          ║
+   ║         'discard()' passes its copies on to the next older snapshot.    ║
+   ║                                                                         ║
+   ║         Copies are kept in volatile memory, so snapshots do not survive ║
+   ║         a reboot. NVRAM mapped into user space ('MAP_NVRAM') is not     ║
+   ║         protected.                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::BTreeMap;
+use alloc::collections::btree_map::Entry;
+use alloc::vec::Vec;
+use spin::Mutex;
+use syscall::return_vals::Errno;
+use x86_64::instructions::{interrupts, tlb};
+use x86_64::registers::control::{Cr0, Cr0Flags};
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::page::PageRange;
+use x86_64::VirtAddr;
+use crate::memory::{nvmem, physical, PAGE_SIZE};
+use crate::process_manager;
+
+pub type SnapshotId = u64;
+
+struct Snapshot {
+    id: SnapshotId,
+    copies: BTreeMap<Page, PhysFrame>, // content of pages at the time of the snapshot (if written since)
+}
+
+struct SnapshotState {
+    pages: PageRange,                  // identity mapped pages covered by snapshots
+    flags: PageTableFlags,             // flags of the writable mapping
+    snapshots: Vec<Snapshot>,          // oldest first
+    next_id: SnapshotId,
+}
+
+static STATE: Mutex<Option<SnapshotState>> = Mutex::new(None);
+
+///
+/// Description:
+///    Use the identity mapped `pages` for snapshots (the non-volatile memory, set by `nvmem::init()`)
+///    or disable snapshots (`None`).
+///
+/// Return: the previous pages or `Errno::EAGAIN`, if snapshots exist
+///
+pub fn set_region(pages: Option<PageRange>) -> Result<Option<PageRange>, Errno> {
+    interrupts::without_interrupts(|| {
+        let mut state = STATE.lock();
+        if state.as_ref().is_some_and(|state| !state.snapshots.is_empty()) {
+            return Err(Errno::EAGAIN);
+        }
+
+        let previous = state.take().map(|state| state.pages);
+        *state = pages.map(|pages| {
+            let kernel_space = process_manager().read().kernel_process().expect("Failed to get kernel process").address_space();
+            let flags = kernel_space.page_flags(pages.start.start_address()).expect("Snapshot region is not mapped");
+            SnapshotState { pages, flags: (flags - PageTableFlags::ACCESSED - PageTableFlags::DIRTY) | PageTableFlags::WRITABLE, snapshots: Vec::new(), next_id: 1 }
+        });
+
+        Ok(previous)
+    })
+}
+
+///
+/// Description: Take a snapshot of the non-volatile memory (nested in existing snapshots).
+///
+/// Return: the id of the snapshot or `Errno::ENOSYS`, if there is no non-volatile memory
+///
+pub fn snapshot() -> Result<SnapshotId, Errno> {
+    interrupts::without_interrupts(|| {
+        let mut state = STATE.lock();
+        let state = state.as_mut().ok_or(Errno::ENOSYS)?;
+
+        // Without WP, the kernel could write to write-protected pages
+        unsafe { Cr0::update(|flags| flags.insert(Cr0Flags::WRITE_PROTECT)); }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.snapshots.push(Snapshot { id, copies: BTreeMap::new() });
+        state.update_protection();
+
+        Ok(id)
+    })
+}
+
+///
+/// Description:
+///    Restore the non-volatile memory to the content it had, when snapshot `id` was taken.
+///    Snapshot `id` and all newer snapshots are removed.
+///
+/// Return: `Ok(())` or `Errno::ENOENT`, if there is no snapshot `id`
+///
+pub fn rollback(id: SnapshotId) -> Result<(), Errno> {
+    interrupts::without_interrupts(|| {
+        let mut state = STATE.lock();
+        let state = state.as_mut().ok_or(Errno::ENOENT)?;
+        let position = state.position(id).ok_or(Errno::ENOENT)?;
+
+        // The oldest copy of a page is its content at the time of snapshot `id`
+        let removed = state.snapshots.split_off(position);
+        let mut restore = BTreeMap::new();
+        for (page, frame) in removed.iter().flat_map(|snapshot| snapshot.copies.iter()) {
+            restore.entry(*page).or_insert(*frame);
+        }
+
+        // Pages must be writable, otherwise restoring them would cause page faults
+        set_flags(state.pages, state.flags);
+        for (page, frame) in restore.iter() {
+            unsafe { (page.start_address().as_u64() as *mut u8).copy_from_nonoverlapping(frame.start_address().as_u64() as *const u8, PAGE_SIZE); }
+        }
+        nvmem::flush();
+
+        removed.iter().flat_map(|snapshot| snapshot.copies.values()).for_each(|frame| free_copy(*frame));
+        state.update_protection();
+
+        Ok(())
+    })
+}
+
+///
+/// Description: Remove snapshot `id` without changing the non-volatile memory (frees its copies)
+///
+/// Return: `Ok(())` or `Errno::ENOENT`, if there is no snapshot `id`
+///
+pub fn discard(id: SnapshotId) -> Result<(), Errno> {
+    interrupts::without_interrupts(|| {
+        let mut state = STATE.lock();
+        let state = state.as_mut().ok_or(Errno::ENOENT)?;
+        let position = state.position(id).ok_or(Errno::ENOENT)?;
+        let snapshot = state.snapshots.remove(position);
+
+        // A copy still describes the next older snapshot, if that has no copy of the page
+        match position.checked_sub(1) {
+            Some(older) => {
+                let older = &mut state.snapshots[older].copies;
+                for (page, frame) in snapshot.copies {
+                    match older.contains_key(&page) {
+                        true => free_copy(frame),
+                        false => { older.insert(page, frame); }
+                    }
+                }
+            }
+            None => snapshot.copies.into_values().for_each(free_copy),
+        }
+
+        state.update_protection();
+        Ok(())
+    })
+}
+
+/// Description: Ids of all snapshots (oldest first)
+pub fn snapshots() -> Vec<SnapshotId> {
+    interrupts::without_interrupts(|| STATE.lock().as_ref().map_or(Vec::new(), |state| state.snapshots.iter().map(|snapshot| snapshot.id).collect()))
+}
+
+/// Description: Number of page copies kept for snapshot `id`
+pub fn copy_count(id: SnapshotId) -> Option<usize> {
+    interrupts::without_interrupts(|| {
+        let state = STATE.lock();
+        let state = state.as_ref()?;
+        state.snapshots.get(state.position(id)?).map(|snapshot| snapshot.copies.len())
+    })
+}
+
+///
+/// Description:
+///    Handle a write to a write-protected page (called by the page fault handler): copy the page
+///    for the newest snapshot and make it writable again.
+///
+/// Return: `true`, if the fault has been handled (the write can be repeated)
+///
+pub fn handle_write_fault(addr: VirtAddr) -> bool {
+    let mut state = STATE.lock();
+    let state = match state.as_mut() {
+        Some(state) => state,
+        None => return false,
+    };
+
+    let page = Page::containing_address(addr);
+    let newest = match state.snapshots.last_mut() {
+        Some(newest) if page >= state.pages.start && page < state.pages.end => newest,
+        _ => return false,
+    };
+
+    if let Entry::Vacant(entry) = newest.copies.entry(page) {
+        let frame = physical::alloc(1).start;
+        unsafe { (frame.start_address().as_u64() as *mut u8).copy_from_nonoverlapping(page.start_address().as_u64() as *const u8, PAGE_SIZE); }
+        entry.insert(frame);
+    }
+
+    set_flags(PageRange { start: page, end: page + 1 }, state.flags);
+    true
+}
+
+impl SnapshotState {
+    fn position(&self, id: SnapshotId) -> Option<usize> {
+        self.snapshots.iter().position(|snapshot| snapshot.id == id)
+    }
+
+    /// Description: Write-protect all pages, of which the newest snapshot has no copy (all pages are writable without snapshots)
+    fn update_protection(&self) {
+        match self.snapshots.last() {
+            Some(newest) => {
+                set_flags(self.pages, self.flags - PageTableFlags::WRITABLE);
+                newest.copies.keys().for_each(|page| set_flags(PageRange { start: *page, end: *page + 1 }, self.flags));
+            }
+            None => set_flags(self.pages, self.flags),
+        }
+    }
+}
+
+/// Description: Set `flags` for `pages` in all address spaces (they all contain the identity mapping)
+fn set_flags(pages: PageRange, flags: PageTableFlags) {
+    for address_space in process_manager().read().address_spaces() {
+        address_space.set_flags(pages, flags);
+    }
+
+    match pages.end - pages.start {
+        1 => tlb::flush(pages.start.start_address()),
+        _ => tlb::flush_all(),
+    }
+}
+
+fn free_copy(frame: PhysFrame) {
+    unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+}
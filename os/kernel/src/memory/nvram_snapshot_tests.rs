@@ -0,0 +1,165 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: nvram_snapshot_tests                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test snapshots, rollback and discard of non-volatile memory.    ║
+   ║         The snapshot region is temporarily replaced by page frames of   ║
+   ║         normal memory (so the tests also run on machines without        ║
+   ║         non-volatile memory). Writes to the region after a snapshot     ║
+   ║         cause real page faults.                                         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::ptr;
+use ::log::info;
+use syscall::return_vals::Errno;
+use x86_64::structures::paging::page::PageRange;
+use x86_64::structures::paging::Page;
+use x86_64::VirtAddr;
+
+use crate::memory::{nvram_snapshot, physical, PAGE_SIZE};
+
+const REGION_PAGES: usize = 4;
+
+///
+/// Description:
+///    Run all NVRAM snapshot tests
+///
+pub fn run_tests() {
+    info!("nvram_snapshot: running tests");
+
+    with_region(|region| {
+        test_rollback(region);
+        test_nested(region);
+        test_discard_merges(region);
+        test_discard_frees(region);
+        test_unknown_id();
+    });
+
+    info!("nvram_snapshot: all tests passed.");
+}
+
+/// Description: Run `test` with a snapshot region of `REGION_PAGES` frames (identity mapped) and restore the previous region afterwards
+fn with_region(test: impl FnOnce(PageRange)) {
+    let frames = physical::alloc(REGION_PAGES);
+    let start = Page::from_start_address(VirtAddr::new(frames.start.start_address().as_u64())).unwrap();
+    let region = PageRange { start, end: start + REGION_PAGES as u64 };
+
+    let previous = nvram_snapshot::set_region(Some(region)).expect("Snapshots of non-volatile memory exist");
+    for page in 0..REGION_PAGES {
+        fill(region, page, 0);
+    }
+
+    test(region);
+
+    nvram_snapshot::set_region(previous).unwrap();
+    unsafe { physical::free(frames); }
+}
+
+fn fill(region: PageRange, page: usize, value: u8) {
+    let addr = (region.start + page as u64).start_address().as_u64() as *mut u8;
+    for i in 0..PAGE_SIZE {
+        unsafe { ptr::write_volatile(addr.add(i), value); }
+    }
+}
+
+fn byte(region: PageRange, page: usize, offset: usize) -> u8 {
+    let addr = (region.start + page as u64).start_address().as_u64() as *const u8;
+    unsafe { ptr::read_volatile(addr.add(offset)) }
+}
+
+/// Description: The first write to a page after a snapshot copies it, rollback restores the copy
+fn test_rollback(region: PageRange) {
+    let id = nvram_snapshot::snapshot().unwrap();
+    assert_eq!(nvram_snapshot::copy_count(id), Some(0));
+
+    fill(region, 1, 0xaa);
+    fill(region, 1, 0xbb); // page is writable again -> no second copy
+    assert_eq!(nvram_snapshot::copy_count(id), Some(1));
+    assert_eq!(byte(region, 1, PAGE_SIZE - 1), 0xbb);
+
+    nvram_snapshot::rollback(id).unwrap();
+    assert!(nvram_snapshot::snapshots().is_empty());
+    assert_eq!(byte(region, 1, 0), 0);
+    assert_eq!(byte(region, 1, PAGE_SIZE - 1), 0);
+
+    // Without snapshots, writes do not cause copies
+    fill(region, 1, 0x11);
+    fill(region, 1, 0);
+}
+
+/// Description: Rollback to an older snapshot restores the content it had, even if only newer snapshots have copies
+fn test_nested(region: PageRange) {
+    fill(region, 0, 1);
+    let older = nvram_snapshot::snapshot().unwrap();
+    fill(region, 0, 2);
+
+    let newer = nvram_snapshot::snapshot().unwrap();
+    fill(region, 0, 3);
+    fill(region, 2, 3); // only copied by the newer snapshot
+    assert_eq!(nvram_snapshot::snapshots(), [older, newer]);
+    assert_eq!(nvram_snapshot::copy_count(older), Some(1));
+    assert_eq!(nvram_snapshot::copy_count(newer), Some(2));
+
+    nvram_snapshot::rollback(older).unwrap();
+    assert!(nvram_snapshot::snapshots().is_empty());
+    assert_eq!(byte(region, 0, 0), 1);
+    assert_eq!(byte(region, 2, 0), 0);
+
+    fill(region, 0, 0);
+}
+
+/// Description: Discarding the newer snapshot passes copies, which the older snapshot has not, on to it
+fn test_discard_merges(region: PageRange) {
+    let older = nvram_snapshot::snapshot().unwrap();
+    fill(region, 0, 1);
+
+    let newer = nvram_snapshot::snapshot().unwrap();
+    fill(region, 0, 2);
+    fill(region, 3, 2);
+    nvram_snapshot::discard(newer).unwrap();
+    assert_eq!(nvram_snapshot::snapshots(), [older]);
+    assert_eq!(nvram_snapshot::copy_count(older), Some(2));
+
+    // Page 3 is writable (the older snapshot has its copy now) -> no further copy
+    fill(region, 3, 4);
+    assert_eq!(nvram_snapshot::copy_count(older), Some(2));
+
+    nvram_snapshot::rollback(older).unwrap();
+    assert_eq!(byte(region, 0, 0), 0);
+    assert_eq!(byte(region, 3, 0), 0);
+}
+
+/// Description: Discard keeps the current content and frees all copies
+fn test_discard_frees(region: PageRange) {
+    let free_frames = physical::free_frames();
+
+    let older = nvram_snapshot::snapshot().unwrap();
+    fill(region, 0, 1);
+    fill(region, 1, 1);
+    let newer = nvram_snapshot::snapshot().unwrap();
+    fill(region, 0, 2);
+    assert_eq!(physical::free_frames(), free_frames - 3);
+
+    nvram_snapshot::discard(newer).unwrap();
+    assert_eq!(physical::free_frames(), free_frames - 2); // the older snapshot already had a copy of page 0
+    nvram_snapshot::discard(older).unwrap();
+    assert_eq!(physical::free_frames(), free_frames);
+    assert!(nvram_snapshot::snapshots().is_empty());
+
+    assert_eq!(byte(region, 0, 0), 2);
+    assert_eq!(byte(region, 1, 0), 1);
+    fill(region, 0, 0);
+    fill(region, 1, 0);
+}
+
+fn test_unknown_id() {
+    let id = nvram_snapshot::snapshot().unwrap();
+    assert_eq!(nvram_snapshot::rollback(id + 1), Err(Errno::ENOENT));
+    assert_eq!(nvram_snapshot::discard(id + 1), Err(Errno::ENOENT));
+
+    // Snapshots cannot be removed twice and the region cannot be changed while they exist
+    assert!(nvram_snapshot::set_region(None).is_err_and(|errno| errno == Errno::EAGAIN));
+    nvram_snapshot::discard(id).unwrap();
+    assert_eq!(nvram_snapshot::discard(id), Err(Errno::ENOENT));
+}
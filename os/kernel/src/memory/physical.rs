@@ -1,17 +1,35 @@
 use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::asm;
 use core::cell::Cell;
 use core::fmt::{Debug, Formatter};
 use core::ptr;
+use core::slice;
+use core::sync::atomic::{AtomicU16, AtomicUsize};
+use core::sync::atomic::Ordering::Relaxed;
+use bitflags::bitflags;
 use spin::Mutex;
 use spin::once::Once;
 use x86_64::PhysAddr;
 use x86_64::structures::paging::frame::PhysFrameRange;
 use x86_64::structures::paging::PhysFrame;
-use crate::memory::PAGE_SIZE;
+use crate::memory::{pressure, PAGE_SIZE};
 
 static PAGE_FRAME_ALLOCATOR: Mutex<PageFrameListAllocator> = Mutex::new(PageFrameListAllocator::new());
 static PHYS_LIMIT: Once<Mutex<Cell<PhysFrame>>> = Once::new();
+static TOTAL_FRAMES: AtomicUsize = AtomicUsize::new(0);
+static REF_COUNTS: Once<&'static [AtomicU16]> = Once::new();
+
+bitflags! {
+    /// Options for allocations, that are mapped into user space
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct AllocFlags: u8 {
+        /// Do not zero the frames. Only for internal use, if the caller overwrites the frames completely
+        /// (e.g. when loading a file), since user visible memory must never contain data of a previous owner.
+        const SKIP_ZERO = 0x01;
+    }
+}
 
 /// Check if the page frame allocator is currently locked.
 pub fn allocator_locked() -> bool {
@@ -37,12 +55,100 @@ pub unsafe fn insert(mut region: PhysFrameRange) {
         current_limit.swap(&Cell::new(region.end));
     }
 
+    TOTAL_FRAMES.fetch_add((region.end - region.start) as usize, Relaxed);
+
     unsafe { free(region); }
 }
 
-/// Allocate `frame_count` contiguous page frames.
+/// Allocate `frame_count` contiguous page frames (for kernel use, the frames are not zeroed).
 pub fn alloc(frame_count: usize) -> PhysFrameRange {
-    PAGE_FRAME_ALLOCATOR.lock().alloc_block(frame_count)
+    let mut allocator = PAGE_FRAME_ALLOCATOR.lock();
+    let frames = allocator.alloc_block(frame_count, 1);
+    let free = allocator.free_frames();
+    drop(allocator);
+
+    pressure::check(free, total_frames());
+    frames.expect("PageFrameAllocator: Out of memory!")
+}
+
+/// Allocate the given page frames (e.g. the kernel heap in a region chosen while booting).
+/// Panics, if any frame of `frames` is not free.
+pub fn alloc_at(frames: PhysFrameRange) -> PhysFrameRange {
+    let mut allocator = PAGE_FRAME_ALLOCATOR.lock();
+    let free_before = allocator.free_frames();
+    unsafe { allocator.reserve_block(frames); }
+
+    assert_eq!(free_before - allocator.free_frames(), (frames.end - frames.start) as usize,
+               "PageFrameAllocator: Block [0x{:x} - 0x{:x}] is not completely free!", frames.start.start_address().as_u64(), frames.end.start_address().as_u64());
+    frames
+}
+
+/// Allocate `frame_count` contiguous page frames, starting at a multiple of `align_frames` frames
+/// (e.g. for DMA buffers), or return `None`, if no free block is large enough (or `align_frames` is 0).
+/// The frames are zeroed, unless `flags` contains `AllocFlags::SKIP_ZERO`. Free them with `free_contiguous()`.
+pub fn alloc_contiguous(frame_count: usize, align_frames: usize, flags: AllocFlags) -> Option<PhysFrameRange> {
+    if align_frames == 0 {
+        return None;
+    }
+
+    let mut allocator = PAGE_FRAME_ALLOCATOR.lock();
+    let frames = allocator.alloc_block(frame_count, align_frames);
+    let free = allocator.free_frames();
+    drop(allocator);
+
+    pressure::check(free, total_frames());
+    let frames = frames?;
+    if !flags.contains(AllocFlags::SKIP_ZERO) {
+        zero(frames);
+    }
+
+    Some(frames)
+}
+
+/// Allocate `frame_count` page frames, which need not be contiguous, while locking the allocator only once.
+/// Either all frames are allocated or none (`None` is returned, if not enough memory is available).
+/// The frames are zeroed, unless `flags` contains `AllocFlags::SKIP_ZERO`.
+pub fn alloc_frames(frame_count: usize, flags: AllocFlags) -> Option<Vec<PhysFrame>> {
+    // Allocate result vector before locking, since the kernel heap must not be used while holding the lock
+    let mut frames = Vec::with_capacity(frame_count);
+    let mut allocator = PAGE_FRAME_ALLOCATOR.lock();
+
+    while frames.len() < frame_count {
+        // Take as many frames as possible from the first free block
+        let available = allocator.head.next.as_ref().map_or(0, |block| block.frame_count);
+        if available == 0 {
+            // Not enough memory -> Return already allocated frames
+            for frame in frames {
+                unsafe { allocator.free_block(PhysFrameRange { start: frame, end: frame + 1 }); }
+            }
+            let free = allocator.free_frames();
+            drop(allocator);
+
+            pressure::check(free, total_frames());
+            return None;
+        }
+
+        let block = allocator.alloc_block(available.min(frame_count - frames.len()), 1).unwrap();
+        frames.extend(block);
+    }
+
+    let free = allocator.free_frames();
+    drop(allocator); // Zero without holding the lock
+
+    pressure::check(free, total_frames());
+    if !flags.contains(AllocFlags::SKIP_ZERO) {
+        frames.iter().for_each(|frame| zero(PhysFrameRange { start: *frame, end: *frame + 1 }));
+    }
+
+    Some(frames)
+}
+
+/// Fill `frames` with zeros (physical memory is identity mapped in the kernel).
+pub fn zero(frames: PhysFrameRange) {
+    let start = frames.start.start_address().as_u64() as *mut u8;
+    let length = (frames.end - frames.start) as usize * PAGE_SIZE;
+
+    unsafe { asm!("rep stosb", inout("rcx") length => _, inout("rdi") start => _, in("al") 0u8, options(nostack, preserves_flags)); }
 }
 
 /// Free `frame_count` contiguous page frames.
@@ -51,9 +157,37 @@ pub unsafe fn free(frames: PhysFrameRange) {
     unsafe { PAGE_FRAME_ALLOCATOR.lock().free_block(frames); }
 }
 
+/// Free a block allocated with `alloc_contiguous()`.
+/// Panics, if any frame of `frames` is already free (double free).
+///
+/// # Safety
+/// The frames must not be used anymore.
+pub unsafe fn free_contiguous(frames: PhysFrameRange) {
+    let mut allocator = PAGE_FRAME_ALLOCATOR.lock();
+    assert!(!allocator.overlaps(frames), "PageFrameAllocator: Block [0x{:x} - 0x{:x}] is already (partially) free!",
+            frames.start.start_address().as_u64(), frames.end.start_address().as_u64());
+
+    unsafe { allocator.free_block(frames); }
+}
+
 /// Permanently reserve a block of free memory.
 pub unsafe fn reserve(frames: PhysFrameRange) {
-    unsafe { PAGE_FRAME_ALLOCATOR.lock().reserve_block(frames); }
+    let mut allocator = PAGE_FRAME_ALLOCATOR.lock();
+
+    // The reserved region may only partially overlap free blocks, so count what has actually been removed
+    let free_before = allocator.free_frames();
+    unsafe { allocator.reserve_block(frames); }
+    TOTAL_FRAMES.fetch_sub(free_before - allocator.free_frames(), Relaxed);
+}
+
+/// Get the number of page frames, managed by PAGE_FRAME_ALLOCATOR (excluding reserved ones).
+pub fn total_frames() -> usize {
+    TOTAL_FRAMES.load(Relaxed)
+}
+
+/// Get the number of currently available page frames.
+pub fn free_frames() -> usize {
+    PAGE_FRAME_ALLOCATOR.lock().free_frames()
 }
 
 /// Get the highest physical address, managed by PAGE_FRAME_ALLOCATOR.
@@ -61,6 +195,62 @@ pub fn phys_limit() -> PhysFrame {
     return PHYS_LIMIT.get().unwrap().lock().get();
 }
 
+/// Create the reference counters for all page frames (one per frame, indexed by frame number).
+/// Must be called once, after all memory regions have been inserted.
+pub fn init_ref_counts() {
+    REF_COUNTS.call_once(|| {
+        let count = phys_limit().start_address().as_u64() as usize / PAGE_SIZE;
+        let frames = alloc((count * size_of::<AtomicU16>()).div_ceil(PAGE_SIZE));
+
+        let counters = frames.start.start_address().as_u64() as *mut AtomicU16;
+        unsafe {
+            counters.write_bytes(0, count);
+            slice::from_raw_parts(counters, count)
+        }
+    });
+}
+
+/// Get the reference counter of `frame` or `None`, if the frame is not managed by PAGE_FRAME_ALLOCATOR.
+fn ref_counter(frame: PhysFrame) -> Option<&'static AtomicU16> {
+    let index = frame.start_address().as_u64() as usize / PAGE_SIZE;
+    REF_COUNTS.get()?.get(index)
+}
+
+/// Get the number of mappings of `frame` (0 for frames not counted, e.g. memory mapped I/O).
+pub fn ref_count(frame: PhysFrame) -> usize {
+    ref_counter(frame).map_or(0, |counter| counter.load(Relaxed) as usize)
+}
+
+/// Increment the number of mappings of `frame` and return the new count.
+/// Frames outside of the managed memory are not counted (returns 0).
+pub fn inc_ref(frame: PhysFrame) -> usize {
+    match ref_counter(frame) {
+        Some(counter) => {
+            let old = counter.fetch_add(1, Relaxed);
+            assert!(old < u16::MAX, "Too many mappings of page frame [0x{:x}]", frame.start_address().as_u64());
+            old as usize + 1
+        }
+        None => 0
+    }
+}
+
+/// Decrement the number of mappings of `frame` and return the new count.
+/// The frame must only be freed, if the count has dropped to zero.
+pub fn dec_ref(frame: PhysFrame) -> usize {
+    match ref_counter(frame) {
+        Some(counter) => {
+            let old = counter.fetch_update(Relaxed, Relaxed, |count| Some(count.saturating_sub(1))).unwrap();
+            old.saturating_sub(1) as usize
+        }
+        None => 0
+    }
+}
+
+/// Check if `frame` is currently in the free list.
+pub fn is_free(frame: PhysFrame) -> bool {
+    PAGE_FRAME_ALLOCATOR.lock().contains(frame)
+}
+
 /// Get a dump of the current free list.
 pub fn dump() -> String {
     format!("{:?}", PAGE_FRAME_ALLOCATOR.lock())
@@ -155,11 +345,50 @@ impl PageFrameListAllocator {
         }
     }
 
-    /// Search a free memory block.
-    fn find_free_block(&mut self, frame_count: usize) -> Option<&'static mut PageFrameNode> {
+    /// Check if `frame` lies within a block of the free list.
+    fn contains(&self, frame: PhysFrame) -> bool {
+        let mut current = &self.head;
+        while let Some(block) = &current.next {
+            if frame >= block.start() && frame < block.end() {
+                return true;
+            }
+            current = block;
+        }
+
+        false
+    }
+
+    /// Check if any frame of `frames` lies within a block of the free list.
+    fn overlaps(&self, frames: PhysFrameRange) -> bool {
+        let mut current = &self.head;
+        while let Some(block) = &current.next {
+            if frames.start < block.end() && frames.end > block.start() {
+                return true;
+            }
+            current = block;
+        }
+
+        false
+    }
+
+    /// Count the page frames of all blocks in the free list.
+    fn free_frames(&self) -> usize {
+        let mut available: usize = 0;
+
+        let mut current = &self.head;
+        while let Some(block) = &current.next {
+            available += block.frame_count;
+            current = block;
+        }
+
+        available
+    }
+
+    /// Search a free memory block, containing `frame_count` frames starting at a multiple of `align_frames`.
+    fn find_free_block(&mut self, frame_count: usize, align_frames: usize) -> Option<&'static mut PageFrameNode> {
         let mut current = &mut self.head;
         while let Some(ref mut block) = current.next {
-            if block.frame_count >= frame_count {
+            if aligned_frame(block.start(), align_frames).is_some_and(|start| start.saturating_add(frame_count as u64) <= frame_number(block.end())) {
                 let next = block.next.take();
                 let ret = Some(current.next.take().unwrap());
                 current.next = next;
@@ -174,19 +403,24 @@ impl PageFrameListAllocator {
         return None;
     }
 
-    /// Allocate `frame_count` page frames.
-    fn alloc_block(&mut self, frame_count: usize) -> PhysFrameRange {
-        match self.find_free_block(frame_count) {
-            Some(block) => {
-                let remaining = PhysFrameRange { start: block.start() + frame_count as u64, end: block.end() };
-                if (remaining.end - remaining.start) > 0 {
-                    unsafe { self.insert(remaining); }
-                }
-                
-                return PhysFrameRange { start: block.start(), end: remaining.start };
-            },
-            None => panic!("PageFrameAllocator: Out of memory!")
+    /// Allocate `frame_count` page frames, starting at a multiple of `align_frames` frames.
+    /// The parts of the block below and above the allocated frames remain in the free list.
+    fn alloc_block(&mut self, frame_count: usize, align_frames: usize) -> Option<PhysFrameRange> {
+        let block = self.find_free_block(frame_count, align_frames)?;
+        let (block_start, block_end) = (block.start(), block.end());
+
+        let start = block_start + (aligned_frame(block_start, align_frames).unwrap() - frame_number(block_start));
+        let end = start + frame_count as u64;
+        unsafe {
+            if start > block_start {
+                self.insert(PhysFrameRange { start: block_start, end: start });
+            }
+            if block_end > end {
+                self.insert(PhysFrameRange { start: end, end: block_end });
+            }
         }
+
+        return Some(PhysFrameRange { start, end });
     }
 
     /// Free a block of memory, consisting of at least one page frame.
@@ -281,4 +515,14 @@ impl PageFrameListAllocator {
             current = current.next.as_mut().unwrap();
         }
     }
-}
\ No newline at end of file
+}
+
+/// Get the number of `frame`, counted from physical address 0.
+fn frame_number(frame: PhysFrame) -> u64 {
+    frame.start_address().as_u64() / PAGE_SIZE as u64
+}
+
+/// Get the number of the first frame at or above `frame`, which is a multiple of `align_frames`.
+fn aligned_frame(frame: PhysFrame, align_frames: usize) -> Option<u64> {
+    frame_number(frame).checked_next_multiple_of(align_frames as u64)
+}
@@ -0,0 +1,183 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: physical_tests                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test and benchmark bulk allocation of the page frame allocator, ║
+   ║         test aligned contiguous allocation, reference counting and      ║
+   ║         zeroing of user page frames.                                    ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec::Vec;
+use ::log::info;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::page::PageRange;
+
+use crate::clock;
+use crate::consts::USER_SPACE_START;
+use crate::memory::{physical, MemorySpace, PAGE_SIZE};
+use crate::memory::physical::AllocFlags;
+use crate::memory::r#virtual::AddressSpace;
+
+/// Number of frames allocated in the benchmark
+const BENCHMARK_FRAMES: usize = 1024;
+
+///
+/// Description:
+///    Run all page frame allocator tests and the bulk allocation benchmark
+///
+pub fn run_tests() {
+    info!("physical: running tests");
+
+    test_alloc_frames();
+    test_alloc_frames_all_or_nothing();
+    test_alloc_contiguous();
+    test_alloc_contiguous_aligned();
+    test_shared_frame();
+    test_zeroed_mapping();
+    benchmark_alloc_frames();
+
+    info!("physical: all tests passed.");
+}
+
+fn free_frames(frames: Vec<PhysFrame>) {
+    for frame in frames {
+        unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+    }
+}
+
+/// Description: Bulk allocation returns the requested number of distinct frames
+fn test_alloc_frames() {
+    let free = physical::free_frames();
+
+    let mut frames = physical::alloc_frames(64, AllocFlags::empty()).expect("alloc_frames(64) -> None");
+    assert_eq!(frames.len(), 64);
+    assert_eq!(physical::free_frames(), free - 64);
+
+    frames.sort();
+    frames.dedup();
+    assert_eq!(frames.len(), 64, "alloc_frames() returned a frame twice");
+
+    free_frames(frames);
+    assert_eq!(physical::free_frames(), free);
+    assert_eq!(physical::alloc_frames(0, AllocFlags::empty()).map(|frames| frames.len()), Some(0));
+}
+
+/// Description: If not all frames can be allocated, none are allocated
+fn test_alloc_frames_all_or_nothing() {
+    let free = physical::free_frames();
+
+    assert!(physical::alloc_frames(free + 1, AllocFlags::empty()).is_none());
+    assert_eq!(physical::free_frames(), free, "frames leaked by failed alloc_frames()");
+}
+
+/// Description: Contiguous allocation fails without panicking, if no block is large enough
+fn test_alloc_contiguous() {
+    let free = physical::free_frames();
+
+    let frames = physical::alloc_contiguous(16, 1, AllocFlags::empty()).expect("alloc_contiguous(16) -> None");
+    assert_eq!(frames.end - frames.start, 16);
+    unsafe { physical::free_contiguous(frames); }
+    assert_eq!(physical::free_frames(), free);
+
+    // The freed run is fused with its neighbours again, so first fit returns the same run
+    let again = physical::alloc_contiguous(16, 1, AllocFlags::empty()).expect("alloc_contiguous(16) -> None");
+    assert_eq!(again, frames, "freed run could not be re-acquired");
+    unsafe { physical::free_contiguous(again); }
+
+    assert!(physical::alloc_contiguous(free + 1, 1, AllocFlags::empty()).is_none());
+    assert!(physical::alloc_contiguous(1, 0, AllocFlags::empty()).is_none());
+    assert_eq!(physical::free_frames(), free);
+}
+
+/// Description: Aligned runs start at a multiple of the alignment and the rest of the block stays free
+fn test_alloc_contiguous_aligned() {
+    let free = physical::free_frames();
+
+    for align in [2, 16, 64, 512] {
+        let frames = physical::alloc_contiguous(8, align, AllocFlags::SKIP_ZERO).expect("aligned alloc_contiguous(8) -> None");
+        let start = frames.start.start_address().as_u64() as usize / PAGE_SIZE;
+        assert_eq!(start % align, 0, "run at frame {} not aligned to {} frames", start, align);
+        assert_eq!(frames.end - frames.start, 8);
+        assert_eq!(physical::free_frames(), free - 8);
+
+        unsafe { physical::free_contiguous(frames); }
+        assert_eq!(physical::free_frames(), free);
+    }
+
+    // No block contains a frame at this alignment (beyond the physical limit)
+    let beyond_limit = 2 * physical::phys_limit().start_address().as_u64() as usize / PAGE_SIZE;
+    assert!(physical::alloc_contiguous(1, beyond_limit, AllocFlags::empty()).is_none());
+    assert_eq!(physical::free_frames(), free);
+}
+
+/// Description: A frame mapped into two address spaces is only freed after it has been unmapped from both
+fn test_shared_frame() {
+    let first = AddressSpace::new(4);
+    let second = AddressSpace::new(4);
+    let frames = physical::alloc(1);
+    let frame = frames.start;
+    let page = Page::from_start_address(VirtAddr::new(USER_SPACE_START as u64)).unwrap();
+    let pages = PageRange { start: page, end: page + 1 };
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+
+    first.map_physical(frames, pages, MemorySpace::User, flags);
+    second.map_physical(frames, pages, MemorySpace::User, flags);
+    assert_eq!(physical::ref_count(frame), 2);
+
+    first.unmap(pages, true);
+    assert_eq!(physical::ref_count(frame), 1);
+    assert!(!physical::is_free(frame), "shared frame freed, while still mapped");
+    assert_eq!(second.translate(page.start_address()), Some(frame.start_address()));
+
+    second.unmap(pages, true);
+    assert_eq!(physical::ref_count(frame), 0);
+    assert!(physical::is_free(frame), "frame not freed after last unmap");
+}
+
+/// Description: Data written to a user page must not be visible in a new mapping, after it has been freed
+fn test_zeroed_mapping() {
+    let address_space = AddressSpace::new(4);
+    let page = Page::from_start_address(VirtAddr::new(USER_SPACE_START as u64)).unwrap();
+    let pages = PageRange { start: page, end: page + 1 };
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+
+    // Write pattern via identity mapping of the physical frame
+    address_space.map(pages, MemorySpace::User, flags);
+    let frame = address_space.translate(page.start_address()).unwrap().as_u64() as *mut u8;
+    unsafe { frame.write_bytes(0xa5, PAGE_SIZE); }
+    address_space.unmap(pages, true);
+
+    address_space.map(pages, MemorySpace::User, flags);
+    let frame = address_space.translate(page.start_address()).unwrap().as_u64() as *const u8;
+    let content = unsafe { core::slice::from_raw_parts(frame, PAGE_SIZE) };
+    assert!(content.iter().all(|byte| *byte == 0), "new user mapping contains old data");
+    address_space.unmap(pages, true);
+
+    // Frames of contiguous allocations are zeroed as well
+    let frames = physical::alloc_contiguous(4, 1, AllocFlags::empty()).unwrap();
+    let content = unsafe { core::slice::from_raw_parts(frames.start.start_address().as_u64() as *const u8, 4 * PAGE_SIZE) };
+    assert!(content.iter().all(|byte| *byte == 0), "alloc_contiguous() returned non-zeroed frames");
+    unsafe { physical::free_contiguous(frames); }
+}
+
+/// Description: Compare allocating BENCHMARK_FRAMES frames one at a time with a single bulk allocation
+fn benchmark_alloc_frames() {
+    let mut frames = Vec::with_capacity(BENCHMARK_FRAMES);
+
+    let start = clock().now_ns();
+    for _ in 0..BENCHMARK_FRAMES {
+        frames.push(physical::alloc(1).start);
+    }
+    let single_ns = clock().now_ns() - start;
+    free_frames(frames);
+
+    let start = clock().now_ns();
+    let frames = physical::alloc_frames(BENCHMARK_FRAMES, AllocFlags::SKIP_ZERO).expect("alloc_frames() -> None");
+    let bulk_ns = clock().now_ns() - start;
+    free_frames(frames);
+
+    info!("physical: allocating {} frames took {} us one at a time, {} us in bulk", BENCHMARK_FRAMES, single_ns / 1000, bulk_ns / 1000);
+}
@@ -0,0 +1,151 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pressure                                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Memory pressure notifications. The page frame allocator checks  ║
+   ║         the number of free frames after each allocation and raises the  ║
+   ║         pressure level (`Low` or `Critical`), when it drops below a     ║
+   ║         threshold, so subscribed processes can free caches before       ║
+   ║         memory runs out. Each subscriber keeps only the latest unread   ║
+   ║         level (no queue). Notifications are rate-limited: a level is    ║
+   ║         only lowered, when the free frames exceed its threshold by      ║
+   ║         `HYSTERESIS_PERCENT` and `RECOVERY_DELAY_MS` have passed since  ║
+   ║         the last change, so oscillating around a threshold does not     ║
+   ║         flood the subscribers. Rising levels are reported immediately.  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec::Vec;
+use spin::{Mutex, MutexGuard};
+use syscall::memory::PressureLevel;
+use syscall::return_vals::Errno;
+use crate::timer;
+
+/// Free frames (in percent of all frames) below which the level becomes `Low`
+pub const LOW_PERCENT: usize = 10;
+/// Free frames (in percent of all frames) below which the level becomes `Critical`
+pub const CRITICAL_PERCENT: usize = 3;
+/// Additional free frames (in percent of all frames) needed to lower the level again
+pub const HYSTERESIS_PERCENT: usize = 2;
+/// Minimum time between the last change and lowering the level
+pub const RECOVERY_DELAY_MS: usize = 1000;
+
+struct PressureState {
+    level: PressureLevel,
+    last_change_ms: usize,
+}
+
+struct Subscriber {
+    owner: usize,
+    pending: Option<PressureLevel>, // latest level, not read yet
+}
+
+static STATE: Mutex<PressureState> = Mutex::new(PressureState { level: PressureLevel::Normal, last_change_ms: 0 });
+
+/// All subscribed processes
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+
+/// Description: Current pressure level
+pub fn level() -> PressureLevel {
+    STATE.lock().level
+}
+
+///
+/// Description:
+///    Level for `free` of `total` frames, starting from the level `current`. A lower level
+///    than `current` requires `HYSTERESIS_PERCENT` more free frames than its threshold.
+///
+pub fn level_for(free: usize, total: usize, current: PressureLevel) -> PressureLevel {
+    let percent = |percent: usize| total / 100 * percent + total % 100 * percent / 100;
+    let below = |threshold: usize, level: PressureLevel| match current >= level {
+        true => free < percent(threshold + HYSTERESIS_PERCENT),
+        false => free < percent(threshold),
+    };
+
+    if below(CRITICAL_PERCENT, PressureLevel::Critical) {
+        PressureLevel::Critical
+    } else if below(LOW_PERCENT, PressureLevel::Low) {
+        PressureLevel::Low
+    } else {
+        PressureLevel::Normal
+    }
+}
+
+///
+/// Description:
+///    Update the pressure level after an allocation left `free` of `total` frames and notify
+///    all subscribers, if it has changed (called by the page frame allocator, without holding
+///    its lock). Nothing is done, if the state is locked (e.g. when called recursively).
+///
+pub fn check(free: usize, total: usize) {
+    if let Some(state) = STATE.try_lock() {
+        update(state, free, total, || timer().systime_ms());
+    }
+}
+
+/// Description: Like `check()`, but at the time `now` (in ms) instead of the system time (for tests)
+pub fn check_at(free: usize, total: usize, now: usize) {
+    update(STATE.lock(), free, total, || now);
+}
+
+/// Description: Update `state` for `free` of `total` frames (the time is only read, if the level changes)
+fn update(mut state: MutexGuard<PressureState>, free: usize, total: usize, now: impl FnOnce() -> usize) {
+    let level = level_for(free, total, state.level);
+    if level == state.level {
+        return;
+    }
+
+    if let Some(level) = change(&mut state, level, now()) {
+        drop(state);
+        notify(level);
+    }
+}
+
+/// Description: Apply the rate limit to a change of `state` to `level` at time `now` (in ms). Returns the new level, if it has changed.
+fn change(state: &mut PressureState, level: PressureLevel, now: usize) -> Option<PressureLevel> {
+    if level < state.level && now.saturating_sub(state.last_change_ms) < RECOVERY_DELAY_MS {
+        return None;
+    }
+
+    state.level = level;
+    state.last_change_ms = now;
+    Some(level)
+}
+
+/// Description: Store `level` for all subscribers (skipped, if the subscribers are locked, e.g. while one is being added)
+fn notify(level: PressureLevel) {
+    if let Some(mut subscribers) = SUBSCRIBERS.try_lock() {
+        subscribers.iter_mut().for_each(|subscriber| subscriber.pending = Some(level));
+    }
+}
+
+///
+/// Description:
+///    Subscribe process `owner` to pressure notifications. If the level is not `Normal`, it
+///    is reported right away. Subscribing twice has no further effect.
+///
+pub fn subscribe(owner: usize) {
+    let level = level();
+    let mut subscribers = SUBSCRIBERS.lock();
+    if subscribers.iter().all(|subscriber| subscriber.owner != owner) {
+        let pending = if level != PressureLevel::Normal { Some(level) } else { None };
+        subscribers.push(Subscriber { owner, pending });
+    }
+}
+
+/// Description: Unsubscribe process `owner` (also called, when the process exits)
+pub fn unsubscribe(owner: usize) {
+    SUBSCRIBERS.lock().retain(|subscriber| subscriber.owner != owner);
+}
+
+///
+/// Description: Take the latest unread level of process `owner` (without blocking).
+///
+/// Return: the level, `Errno::EAGAIN`, if the level has not changed since the last read,
+///         or `Errno::EINVAL`, if `owner` is not subscribed
+///
+pub fn read(owner: usize) -> Result<PressureLevel, Errno> {
+    let mut subscribers = SUBSCRIBERS.lock();
+    let subscriber = subscribers.iter_mut().find(|subscriber| subscriber.owner == owner).ok_or(Errno::EINVAL)?;
+    subscriber.pending.take().ok_or(Errno::EAGAIN)
+}
@@ -0,0 +1,104 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pressure_tests                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the thresholds, notifications and rate limit of memory     ║
+   ║         pressure. Allocations are simulated with `pressure::check_at()` ║
+   ║         for a subscriber, that is not a real process.                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::memory::PressureLevel;
+use syscall::return_vals::Errno;
+
+use crate::memory::pressure;
+use crate::memory::pressure::{level_for, RECOVERY_DELAY_MS};
+use crate::timer;
+
+/// Owner of the test subscription (no real process has this id)
+const TEST_PROCESS: usize = usize::MAX - 1;
+const TOTAL: usize = 1000;
+
+///
+/// Description:
+///    Run all memory pressure tests (requires enough free memory, so that the level is `Normal`)
+///
+pub fn run_tests() {
+    info!("pressure: running tests");
+
+    test_thresholds();
+    test_hysteresis();
+    test_notifications();
+    test_oscillation();
+
+    info!("pressure: all tests passed.");
+}
+
+/// Description: `Low` below 10 %, `Critical` below 3 % of the frames
+fn test_thresholds() {
+    assert_eq!(level_for(TOTAL, TOTAL, PressureLevel::Normal), PressureLevel::Normal);
+    assert_eq!(level_for(100, TOTAL, PressureLevel::Normal), PressureLevel::Normal);
+    assert_eq!(level_for(99, TOTAL, PressureLevel::Normal), PressureLevel::Low);
+    assert_eq!(level_for(30, TOTAL, PressureLevel::Normal), PressureLevel::Low);
+    assert_eq!(level_for(29, TOTAL, PressureLevel::Normal), PressureLevel::Critical);
+    assert_eq!(level_for(0, TOTAL, PressureLevel::Normal), PressureLevel::Critical);
+
+    // Without any frames (before booting has inserted memory), there is no pressure
+    assert_eq!(level_for(0, 0, PressureLevel::Normal), PressureLevel::Normal);
+}
+
+/// Description: A level is only lowered, when its threshold is exceeded by 2 % of the frames
+fn test_hysteresis() {
+    assert_eq!(level_for(110, TOTAL, PressureLevel::Low), PressureLevel::Low);
+    assert_eq!(level_for(120, TOTAL, PressureLevel::Low), PressureLevel::Normal);
+    assert_eq!(level_for(40, TOTAL, PressureLevel::Critical), PressureLevel::Critical);
+    assert_eq!(level_for(50, TOTAL, PressureLevel::Critical), PressureLevel::Low);
+    assert_eq!(level_for(120, TOTAL, PressureLevel::Critical), PressureLevel::Normal);
+}
+
+/// Description: Subscribers get the latest level once, rising levels immediately, lower levels after the recovery delay
+fn test_notifications() {
+    assert_eq!(pressure::level(), PressureLevel::Normal);
+    assert_eq!(pressure::read(TEST_PROCESS), Err(Errno::EINVAL));
+    pressure::subscribe(TEST_PROCESS);
+    assert_eq!(pressure::read(TEST_PROCESS), Err(Errno::EAGAIN));
+
+    let start = timer().systime_ms();
+    pressure::check_at(50, TOTAL, start);
+    assert_eq!(pressure::read(TEST_PROCESS), Ok(PressureLevel::Low));
+    assert_eq!(pressure::read(TEST_PROCESS), Err(Errno::EAGAIN));
+
+    // Unread levels are replaced by the latest one
+    pressure::check_at(20, TOTAL, start + 1);
+    pressure::check_at(10, TOTAL, start + 2);
+    assert_eq!(pressure::read(TEST_PROCESS), Ok(PressureLevel::Critical));
+
+    // Recovery is delayed
+    pressure::check_at(TOTAL, TOTAL, start + 3);
+    assert_eq!(pressure::read(TEST_PROCESS), Err(Errno::EAGAIN));
+    pressure::check_at(TOTAL, TOTAL, start + 1 + RECOVERY_DELAY_MS);
+    assert_eq!(pressure::read(TEST_PROCESS), Ok(PressureLevel::Normal));
+    assert_eq!(pressure::level(), PressureLevel::Normal);
+
+    pressure::unsubscribe(TEST_PROCESS);
+    assert_eq!(pressure::read(TEST_PROCESS), Err(Errno::EINVAL));
+}
+
+/// Description: Free frames oscillating around a threshold cause a single notification
+fn test_oscillation() {
+    pressure::subscribe(TEST_PROCESS);
+    let start = timer().systime_ms();
+
+    for i in 0..100 {
+        let free = if i % 2 == 0 { 95 } else { 125 };
+        pressure::check_at(free, TOTAL, start + i);
+    }
+    assert_eq!(pressure::read(TEST_PROCESS), Ok(PressureLevel::Low));
+    assert_eq!(pressure::level(), PressureLevel::Low);
+
+    // Back to normal for the rest of the system
+    pressure::check_at(TOTAL, TOTAL, start + 100 + RECOVERY_DELAY_MS);
+    assert_eq!(pressure::level(), PressureLevel::Normal);
+    pressure::unsubscribe(TEST_PROCESS);
+}
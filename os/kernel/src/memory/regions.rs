@@ -0,0 +1,141 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: regions                                                         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Usable memory regions found in the memory map during booting.   ║
+   ║         All regions are inserted into the page frame allocator. The     ║
+   ║         initial kernel heap is taken from the largest region, the other ║
+   ║         regions remain available for frame allocation. The regions are  ║
+   ║         collected before the heap exists, so they are kept in an array  ║
+   ║         of fixed size.                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use x86_64::PhysAddr;
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::PhysFrame;
+use crate::memory::PAGE_SIZE;
+
+/// Maximum number of usable regions (further regions of a memory map are ignored)
+pub const MAX_USABLE_REGIONS: usize = 128;
+
+pub struct UsableRegions {
+    regions: [Option<PhysFrameRange>; MAX_USABLE_REGIONS],
+    count: usize,
+    ignored: usize, // regions not stored, because the array is full
+}
+
+impl Default for UsableRegions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsableRegions {
+    pub const fn new() -> Self {
+        Self { regions: [None; MAX_USABLE_REGIONS], count: 0, ignored: 0 }
+    }
+
+    ///
+    /// Description:
+    ///    Add the usable memory region [`start`, `end`) from a memory map. Only complete page
+    ///    frames are used, a region without any is ignored.
+    ///
+    /// Parameters: \
+    ///    `start` physical start address \
+    ///    `end` physical end address (exclusive)
+    ///
+    pub fn add(&mut self, start: u64, end: u64) {
+        let start = PhysAddr::new(start).align_up(PAGE_SIZE as u64);
+        let end = PhysAddr::new(end).align_down(PAGE_SIZE as u64);
+        if start >= end {
+            return;
+        }
+
+        if self.count == MAX_USABLE_REGIONS {
+            self.ignored += 1;
+            return;
+        }
+
+        self.regions[self.count] = Some(PhysFrameRange {
+            start: PhysFrame::from_start_address(start).unwrap(),
+            end: PhysFrame::from_start_address(end).unwrap(),
+        });
+        self.count += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Description: Number of regions, which have been ignored, because more than `MAX_USABLE_REGIONS` have been added
+    pub fn ignored(&self) -> usize {
+        self.ignored
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PhysFrameRange> + '_ {
+        self.regions[..self.count].iter().map(|region| region.unwrap())
+    }
+
+    /// Description: Largest region (the first one, if several regions have the same size)
+    pub fn largest(&self) -> Option<PhysFrameRange> {
+        self.iter().fold(None, |largest: Option<PhysFrameRange>, region| match largest {
+            Some(largest) if largest.end - largest.start >= region.end - region.start => Some(largest),
+            _ => Some(region),
+        })
+    }
+
+    ///
+    /// Description:
+    ///    Choose the frames for the initial kernel heap: the first `frame_count` frames of the
+    ///    largest region, which lie above `reserved` (if it overlaps the region) and above frame 0
+    ///    (which is never used, see `physical::insert()`).
+    ///
+    /// Parameters: \
+    ///    `frame_count` size of the heap in frames \
+    ///    `reserved` frames, which must not be used (the kernel image)
+    ///
+    /// Return: the frames or `None`, if the largest region is too small
+    ///
+    pub fn heap_region(&self, frame_count: usize, reserved: PhysFrameRange) -> Option<PhysFrameRange> {
+        let (start, _) = self.heap_bounds(frame_count, reserved)?;
+        Some(PhysFrameRange { start, end: start + frame_count as u64 })
+    }
+
+    ///
+    /// Description:
+    ///    Like `heap_region()`, but the heap starts at a random frame within the usable part of
+    ///    the largest region (so heap addresses differ across boots). The heap is always page
+    ///    aligned and lies completely within the region.
+    ///
+    /// Parameters: \
+    ///    `frame_count` size of the heap in frames \
+    ///    `reserved` frames, which must not be used (the kernel image) \
+    ///    `random` random number selecting the start frame
+    ///
+    /// Return: the frames or `None`, if the largest region is too small
+    ///
+    pub fn random_heap_region(&self, frame_count: usize, reserved: PhysFrameRange, random: u64) -> Option<PhysFrameRange> {
+        let (lowest, positions) = self.heap_bounds(frame_count, reserved)?;
+        let start = lowest + random % positions;
+        Some(PhysFrameRange { start, end: start + frame_count as u64 })
+    }
+
+    /// Description: Lowest possible start frame of the heap and the number of possible start frames
+    fn heap_bounds(&self, frame_count: usize, reserved: PhysFrameRange) -> Option<(PhysFrame, u64)> {
+        let region = self.largest()?;
+        let mut start = region.start.max(PhysFrame::containing_address(PhysAddr::new(PAGE_SIZE as u64)));
+        if reserved.start < region.end && reserved.end > start {
+            start = start.max(reserved.end);
+        }
+
+        match start + frame_count as u64 <= region.end {
+            true => Some((start, region.end - (start + frame_count as u64) + 1)),
+            false => None,
+        }
+    }
+}
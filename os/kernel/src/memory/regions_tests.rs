@@ -0,0 +1,133 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: regions_tests                                                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test collecting usable regions from a synthetic memory map and  ║
+   ║         choosing the region for the kernel heap.                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec::Vec;
+use ::log::info;
+use x86_64::PhysAddr;
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::PhysFrame;
+
+use crate::memory::PAGE_SIZE;
+use crate::memory::regions::{UsableRegions, MAX_USABLE_REGIONS};
+
+const MIB: u64 = 0x100000;
+const HEAP_FRAMES: usize = 0x400;
+
+///
+/// Description:
+///    Run all usable region tests
+///
+pub fn run_tests() {
+    info!("regions: running tests");
+
+    test_three_regions();
+    test_heap_above_reserved();
+    test_too_small();
+    test_random_heap();
+    test_full();
+
+    info!("regions: all tests passed.");
+}
+
+fn frames(start: u64, end: u64) -> PhysFrameRange {
+    PhysFrameRange {
+        start: PhysFrame::from_start_address(PhysAddr::new(start)).unwrap(),
+        end: PhysFrame::from_start_address(PhysAddr::new(end)).unwrap(),
+    }
+}
+
+/// Description: Memory map with three usable areas (unaligned borders, the largest one in the middle)
+fn synthetic_map() -> UsableRegions {
+    let mut regions = UsableRegions::new();
+    regions.add(0, 0x9fc00);              // low memory (end is not page aligned)
+    regions.add(MIB + 0x800, 8 * MIB);    // start is not page aligned
+    regions.add(16 * MIB, 20 * MIB);
+    regions.add(0x9fc00, 0x9fe00);        // less than a page -> ignored
+
+    regions
+}
+
+/// Description: All areas are kept (for the page frame allocator), the heap is taken from the largest one
+fn test_three_regions() {
+    let regions = synthetic_map();
+    assert_eq!(regions.len(), 3);
+    assert!(!regions.is_empty());
+    assert_eq!(regions.ignored(), 0);
+
+    let all = regions.iter().collect::<Vec<PhysFrameRange>>();
+    assert_eq!(all, [frames(0, 0x9f000), frames(MIB + PAGE_SIZE as u64, 8 * MIB), frames(16 * MIB, 20 * MIB)]);
+    assert_eq!(regions.largest(), Some(frames(MIB + PAGE_SIZE as u64, 8 * MIB)));
+
+    // Kernel image in the low region -> heap at the start of the largest region
+    let heap = regions.heap_region(HEAP_FRAMES, frames(0x10000, 0x20000)).unwrap();
+    assert_eq!(heap.start, all[1].start);
+    assert_eq!((heap.end - heap.start) as usize, HEAP_FRAMES);
+
+    // The other regions remain available
+    assert!(all.iter().filter(|region| region.start != heap.start).all(|region| region.end <= heap.start || region.start >= heap.end));
+}
+
+/// Description: The heap must not overlap the kernel image
+fn test_heap_above_reserved() {
+    let regions = synthetic_map();
+    let kernel = frames(2 * MIB, 3 * MIB);
+
+    let heap = regions.heap_region(HEAP_FRAMES, kernel).unwrap();
+    assert_eq!(heap.start, kernel.end);
+    assert_eq!((heap.end - heap.start) as usize, HEAP_FRAMES);
+}
+
+/// Description: No heap, if the largest region (above the kernel image) is too small
+fn test_too_small() {
+    let regions = synthetic_map();
+    let largest = (7 * MIB - PAGE_SIZE as u64) as usize / PAGE_SIZE;
+    assert!(regions.heap_region(largest, frames(0x10000, 0x20000)).is_some());
+    assert!(regions.heap_region(largest + 1, frames(0x10000, 0x20000)).is_none());
+    assert!(regions.heap_region(HEAP_FRAMES, frames(MIB, 8 * MIB)).is_none());
+
+    assert!(UsableRegions::new().is_empty());
+    assert!(UsableRegions::new().heap_region(1, frames(0, 0)).is_none());
+}
+
+/// Description: A random heap is page aligned and lies within the largest region, above the kernel image
+fn test_random_heap() {
+    let regions = synthetic_map();
+    let kernel = frames(2 * MIB, 3 * MIB);
+    let largest = regions.largest().unwrap();
+    let last_start = largest.end - HEAP_FRAMES as u64;
+
+    for random in [0, 1, 0x1234_5678, u64::MAX - 1, u64::MAX] {
+        let heap = regions.random_heap_region(HEAP_FRAMES, kernel, random).unwrap();
+        assert!(heap.start >= kernel.end && heap.end <= largest.end);
+        assert_eq!((heap.end - heap.start) as usize, HEAP_FRAMES);
+        assert!(heap.start.start_address().is_aligned(PAGE_SIZE as u64));
+    }
+
+    // Random 0 gives the fixed position, all possible start frames can be chosen
+    assert_eq!(regions.random_heap_region(HEAP_FRAMES, kernel, 0), regions.heap_region(HEAP_FRAMES, kernel));
+    let positions = last_start - kernel.end + 1;
+    assert_eq!(regions.random_heap_region(HEAP_FRAMES, kernel, positions - 1).unwrap().start, last_start);
+    assert_eq!(regions.random_heap_region(HEAP_FRAMES, kernel, positions).unwrap().start, kernel.end);
+
+    // Only one position, if the heap fills the usable part of the region
+    let size = (largest.end - kernel.end) as usize;
+    assert_eq!(regions.random_heap_region(size, kernel, 0x1234_5678), regions.heap_region(size, kernel));
+    assert!(regions.random_heap_region(size + 1, kernel, 0).is_none());
+}
+
+/// Description: Regions beyond `MAX_USABLE_REGIONS` are counted, but not stored
+fn test_full() {
+    let mut regions = UsableRegions::new();
+    for i in 0..MAX_USABLE_REGIONS as u64 + 2 {
+        regions.add(i * 2 * MIB, i * 2 * MIB + MIB);
+    }
+
+    assert_eq!(regions.len(), MAX_USABLE_REGIONS);
+    assert_eq!(regions.ignored(), 2);
+}
@@ -0,0 +1,352 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: swap                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Swapping of anonymous user pages (heap) to a swap device. Under ║
+   ║         memory pressure, the swapper thread evicts pages, which have    ║
+   ║         not been accessed since the last pass (second chance: the       ║
+   ║         ACCESSED flag is cleared for the others). An evicted page is    ║
+   ║         marked not present with `SWAPPED` and the slot number in the    ║
+   ║         address field of its page table entry, before it is written     ║
+   ║         out. An access causes a page fault, which swaps the page in     ║
+   ║         again. Evictions and swap-ins are serialized, so a page, which  ║
+   ║         is being written out, is swapped in only after the write has    ║
+   ║         completed (no data is lost). Swapping is disabled until a swap  ║
+   ║         device is configured, e.g. non-volatile memory with the kernel  ║
+   ║         command line flag 'nvswap' (returned at shutdown).              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
+use log::{info, warn};
+use spin::{Mutex, RwLock};
+use syscall::memory::PressureLevel;
+use syscall::return_vals::Errno;
+use x86_64::instructions::tlb;
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::page_table::PageTableEntry;
+use x86_64::{PhysAddr, VirtAddr};
+use crate::consts::USER_SPACE_START;
+use crate::memory::{nvmem, physical, pressure, PAGE_SIZE};
+use crate::memory::physical::AllocFlags;
+use crate::memory::r#virtual::{AddressSpace, VmaType};
+use crate::{process_manager, scheduler};
+
+/// Marks the page table entry of a swapped out page (not present, the address field holds the slot)
+pub const SWAPPED: PageTableFlags = PageTableFlags::BIT_9;
+
+/// Size of the swap area in non-volatile memory (with 'nvswap')
+pub const NVRAM_SWAP_PAGES: usize = 4096;
+/// Interval of the swapper thread
+pub const SWAP_INTERVAL_MS: usize = 100;
+/// Pages evicted per interval with `PressureLevel::Low` (`PressureLevel::Critical` evicts four times as many)
+pub const EVICT_BATCH: usize = 64;
+
+/// Backing store with page sized slots for swapped out pages
+pub trait SwapDevice: Send + Sync {
+    fn slot_count(&self) -> usize;
+
+    fn write_slot(&self, slot: usize, page: &[u8; PAGE_SIZE]);
+
+    fn read_slot(&self, slot: usize, page: &mut [u8; PAGE_SIZE]);
+}
+
+/// Swap device in memory, which is not managed by the page frame allocator (e.g. non-volatile memory)
+pub struct MemorySwap {
+    start: *mut u8,
+    slots: usize,
+    nvram: Option<PhysFrameRange>, // returned to the NVRAM allocator, when dropped
+}
+
+unsafe impl Send for MemorySwap {}
+unsafe impl Sync for MemorySwap {}
+
+struct SwapArea {
+    device: Box<dyn SwapDevice>,
+    used: Vec<AtomicBool>,
+}
+
+/// The configured swap area (`None` = swapping disabled)
+static AREA: RwLock<Option<SwapArea>> = RwLock::new(None);
+
+/// Serializes evictions and swap-ins (taken before the page tables are locked)
+static TRANSFER: Mutex<()> = Mutex::new(());
+
+impl MemorySwap {
+    ///
+    /// Description: Swap device with the page sized slots in the `length` bytes at `start` (identity mapped)
+    ///
+    /// # Safety
+    /// The memory must not be used otherwise, as long as the device exists
+    ///
+    pub unsafe fn new(start: *mut u8, length: usize) -> Self {
+        Self { start, slots: length / PAGE_SIZE, nvram: None }
+    }
+
+    /// Description: Swap device in `pages` pages of non-volatile memory (or `None`, if there is not enough)
+    pub fn nvram(pages: usize) -> Option<Self> {
+        let frames = nvmem::alloc_frames(pages)?;
+        Some(Self { start: frames.start.start_address().as_u64() as *mut u8, slots: pages, nvram: Some(frames) })
+    }
+
+    fn slot(&self, slot: usize) -> *mut u8 {
+        assert!(slot < self.slots, "Swap slot [{}] out of range", slot);
+        unsafe { self.start.add(slot * PAGE_SIZE) }
+    }
+}
+
+impl SwapDevice for MemorySwap {
+    fn slot_count(&self) -> usize {
+        self.slots
+    }
+
+    fn write_slot(&self, slot: usize, page: &[u8; PAGE_SIZE]) {
+        unsafe { ptr::copy_nonoverlapping(page.as_ptr(), self.slot(slot), PAGE_SIZE); }
+    }
+
+    fn read_slot(&self, slot: usize, page: &mut [u8; PAGE_SIZE]) {
+        unsafe { ptr::copy_nonoverlapping(self.slot(slot), page.as_mut_ptr(), PAGE_SIZE); }
+    }
+}
+
+impl Drop for MemorySwap {
+    fn drop(&mut self) {
+        if let Some(frames) = self.nvram {
+            nvmem::free_frames(frames);
+        }
+    }
+}
+
+impl SwapArea {
+    fn alloc_slot(&self) -> Option<usize> {
+        self.used.iter().position(|used| used.compare_exchange(false, true, Relaxed, Relaxed).is_ok())
+    }
+}
+
+///
+/// Description:
+///    Use `device` for swapping (`None` disables swapping). The swap area can only be changed,
+///    while no page is swapped out.
+///
+/// Return: `Ok(())` or `Errno::EAGAIN`, if pages are swapped out to the current area
+///
+pub fn configure(device: Option<Box<dyn SwapDevice>>) -> Result<(), Errno> {
+    let mut area = AREA.write();
+    if used_slots() > 0 {
+        return Err(Errno::EAGAIN);
+    }
+
+    *area = device.map(|device| {
+        let used = (0..device.slot_count()).map(|_| AtomicBool::new(false)).collect();
+        SwapArea { device, used }
+    });
+    Ok(())
+}
+
+/// Description: Use `NVRAM_SWAP_PAGES` pages of non-volatile memory for swapping (called while booting with 'nvswap')
+pub fn init_nvram() {
+    match MemorySwap::nvram(NVRAM_SWAP_PAGES) {
+        Some(device) => {
+            configure(Some(Box::new(device))).expect("Failed to configure swap area");
+            info!("Swapping to [{}] pages of non-volatile memory", NVRAM_SWAP_PAGES);
+        }
+        None => warn!("Not enough non-volatile memory for swapping"),
+    }
+}
+
+///
+/// Description:
+///    Remove the swap area, even if pages are swapped out (called at shutdown, when no user
+///    code runs anymore, so non-volatile memory used for swapping is returned).
+///
+pub fn release() {
+    *AREA.write() = None;
+}
+
+/// Description: Number of slots of the swap area (0, if swapping is disabled)
+pub fn slot_count() -> usize {
+    AREA.read().as_ref().map_or(0, |area| area.used.len())
+}
+
+/// Description: Number of swapped out pages
+pub fn used_slots() -> usize {
+    AREA.read().as_ref().map_or(0, |area| area.used.iter().filter(|used| used.load(Relaxed)).count())
+}
+
+/// Description: Free the slot of the swapped out page with `entry` (called, when the page is unmapped)
+pub fn free_slot(entry: &PageTableEntry) {
+    if let Some(used) = AREA.read().as_ref().and_then(|area| area.used.get(slot_of(entry))) {
+        used.store(false, Relaxed);
+    }
+}
+
+fn slot_of(entry: &PageTableEntry) -> usize {
+    entry.addr().as_u64() as usize / PAGE_SIZE
+}
+
+fn frame_bytes(frame: PhysFrame) -> &'static mut [u8; PAGE_SIZE] {
+    unsafe { (frame.start_address().as_u64() as *mut [u8; PAGE_SIZE]).as_mut().unwrap() }
+}
+
+///
+/// Description:
+///    Evict `page` of `address_space`, if it is present, has not been accessed since the last
+///    call and is not shared. Otherwise, the ACCESSED flag is cleared (second chance).
+///
+/// Return: `true`, if the page has been swapped out
+///
+pub fn evict(address_space: &AddressSpace, page: Page) -> bool {
+    let _transfer = TRANSFER.lock();
+    let area = AREA.read();
+    let Some(area) = area.as_ref() else {
+        return false;
+    };
+    let Some(slot) = area.alloc_slot() else {
+        return false;
+    };
+
+    let evicted = address_space.update_entry(page, |entry| {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE) {
+            return false;
+        }
+        if flags.contains(PageTableFlags::ACCESSED) {
+            entry.set_flags(flags - PageTableFlags::ACCESSED);
+            tlb::flush(page.start_address());
+            return false;
+        }
+
+        let frame = PhysFrame::from_start_address(entry.addr()).unwrap();
+        if physical::ref_count(frame) != 1 {
+            return false;
+        }
+
+        // Not present first, so every access during the write faults and waits for the write to complete
+        let swapped_flags = (flags - PageTableFlags::PRESENT - PageTableFlags::DIRTY) | SWAPPED;
+        entry.set_addr(PhysAddr::new((slot * PAGE_SIZE) as u64), swapped_flags);
+        tlb::flush_all();
+
+        area.device.write_slot(slot, frame_bytes(frame));
+        physical::dec_ref(frame);
+        unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+        true
+    }).unwrap_or(false);
+
+    if !evicted {
+        area.used[slot].store(false, Relaxed);
+    }
+
+    evicted
+}
+
+///
+/// Description:
+///    Swap in the page containing `addr` in `address_space`, if it is swapped out.
+///
+/// Return: `true`, if the page is present now (also, if another thread has swapped it in
+///         meanwhile), so the faulting access can be repeated
+///
+pub fn swap_in(address_space: &AddressSpace, addr: VirtAddr) -> bool {
+    let page = Page::containing_address(addr);
+    if address_space.update_entry(page, |entry| entry.flags().contains(SWAPPED)) != Some(true) {
+        return false;
+    }
+
+    let _transfer = TRANSFER.lock();
+    let area = AREA.read();
+    let Some(area) = area.as_ref() else {
+        return false;
+    };
+
+    // Allocated before the page tables are locked (the data is copied into the whole frame)
+    let Some(frame) = physical::alloc_frames(1, AllocFlags::SKIP_ZERO).map(|frames| frames[0]) else {
+        return false;
+    };
+
+    let mut used_frame = false;
+    let present = address_space.update_entry(page, |entry| {
+        let flags = entry.flags();
+        if flags.contains(PageTableFlags::PRESENT) {
+            return true;
+        }
+        if !flags.contains(SWAPPED) {
+            return false;
+        }
+
+        let slot = slot_of(entry);
+        area.device.read_slot(slot, frame_bytes(frame));
+        physical::inc_ref(frame);
+        entry.set_frame(frame, (flags - SWAPPED) | PageTableFlags::PRESENT);
+        area.used[slot].store(false, Relaxed);
+
+        used_frame = true;
+        true
+    }).unwrap_or(false);
+
+    if !used_frame {
+        unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+    }
+
+    present
+}
+
+/// Description: Handle a page fault on the not present page at `addr` of the current process (`true`, if it has been swapped in)
+pub fn handle_fault(addr: VirtAddr) -> bool {
+    if addr.as_u64() < USER_SPACE_START as u64 || AREA.read().is_none() {
+        return false;
+    }
+
+    let address_space = process_manager().read().current_process().address_space();
+    swap_in(&address_space, addr)
+}
+
+///
+/// Description:
+///    Evict up to `count` heap pages of all user processes. Pages accessed since the last pass
+///    get a second chance, so at most two passes are made.
+///
+/// Return: the number of evicted pages
+///
+pub fn evict_pages(count: usize) -> usize {
+    let processes = process_manager().read().active_processes();
+    let mut evicted = 0;
+
+    for _pass in 0..2 {
+        for process in processes.iter() {
+            let address_space = process.address_space();
+            for vma in process.find_vmas(VmaType::Heap) {
+                for page in vma.range() {
+                    if evicted == count || slot_count() == used_slots() {
+                        return evicted;
+                    }
+                    if evict(&address_space, page) {
+                        evicted += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    evicted
+}
+
+/// Description: Body of the swapper thread, evicting pages as long as there is memory pressure
+pub fn run_swapper() {
+    loop {
+        scheduler().sleep(SWAP_INTERVAL_MS);
+
+        let batch = match pressure::level() {
+            PressureLevel::Normal => continue,
+            PressureLevel::Low => EVICT_BATCH,
+            PressureLevel::Critical => 4 * EVICT_BATCH,
+        };
+        if AREA.read().is_some() {
+            evict_pages(batch);
+        }
+    }
+}
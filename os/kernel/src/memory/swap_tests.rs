@@ -0,0 +1,121 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: swap_tests                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test evicting and swapping in user pages with a swap device on  ║
+   ║         the kernel heap. The tests map memory into the address space of ║
+   ║         the calling process and require, that no page is swapped out.   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::boxed::Box;
+use alloc::vec;
+use ::log::info;
+use syscall::memory::{MAP_READ, MAP_WRITE};
+use syscall::return_vals::Errno;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::Page;
+
+use crate::consts::USER_SPACE_MAP_START;
+use crate::memory::{swap, user, PAGE_SIZE};
+use crate::memory::swap::MemorySwap;
+use crate::process_manager;
+use crate::syscall::sys_vmem::{sys_map_memory, sys_unmap_memory};
+
+const SLOTS: usize = 2;
+
+///
+/// Description: Run all swap tests
+///
+pub fn run_tests() {
+    info!("swap: running tests");
+
+    let mut buffer = vec![0u8; SLOTS * PAGE_SIZE];
+    let device = unsafe { MemorySwap::new(buffer.as_mut_ptr(), buffer.len()) };
+    assert_eq!(swap::configure(Some(Box::new(device))), Ok(()));
+    assert_eq!(swap::slot_count(), SLOTS);
+
+    test_second_chance();
+    test_unmap_frees_slot();
+    test_full();
+
+    assert_eq!(swap::configure(None), Ok(()));
+    assert_eq!(swap::slot_count(), 0);
+    drop(buffer);
+
+    info!("swap: all tests passed.");
+}
+
+/// Description: Map `pages` pages at `USER_SPACE_MAP_START` and write `data` to the start of each page
+fn map_with_data(pages: usize, data: &[u8]) {
+    let addr = USER_SPACE_MAP_START;
+    assert_eq!(sys_map_memory(addr, pages * PAGE_SIZE, MAP_READ | MAP_WRITE), addr as isize);
+    for page in 0..pages {
+        assert_eq!(user::copy_to_user((addr + page * PAGE_SIZE) as *mut u8, data), Ok(()));
+    }
+}
+
+fn page(index: usize) -> Page {
+    Page::from_start_address(VirtAddr::new((USER_SPACE_MAP_START + index * PAGE_SIZE) as u64)).unwrap()
+}
+
+/// Description: An accessed page is only evicted on the second try, its data is restored, when it is accessed again
+fn test_second_chance() {
+    map_with_data(1, b"hello");
+    let address_space = process_manager().read().current_process().address_space();
+
+    assert!(!swap::evict(&address_space, page(0)));
+    assert!(swap::evict(&address_space, page(0)));
+    assert_eq!(swap::used_slots(), 1);
+    assert!(address_space.page_flags(page(0).start_address()).is_none());
+    assert!(address_space.translate(page(0).start_address()).is_none());
+
+    // The slot cannot be given up, while it is in use
+    assert_eq!(swap::configure(None), Err(Errno::EAGAIN));
+
+    // Checking the access swaps the page in
+    assert_eq!(user::bytes_from_user(USER_SPACE_MAP_START as *const u8, 5).as_deref(), Ok(&b"hello"[..]));
+    assert_eq!(swap::used_slots(), 0);
+    assert!(address_space.translate(page(0).start_address()).is_some());
+    assert!(!swap::swap_in(&address_space, page(0).start_address()));
+
+    assert_eq!(sys_unmap_memory(USER_SPACE_MAP_START, PAGE_SIZE), 0);
+}
+
+/// Description: Unmapping a swapped out page frees its slot
+fn test_unmap_frees_slot() {
+    map_with_data(1, b"data");
+    let address_space = process_manager().read().current_process().address_space();
+
+    swap::evict(&address_space, page(0));
+    assert!(swap::evict(&address_space, page(0)));
+    assert_eq!(swap::used_slots(), 1);
+
+    assert_eq!(sys_unmap_memory(USER_SPACE_MAP_START, PAGE_SIZE), 0);
+    assert_eq!(swap::used_slots(), 0);
+}
+
+/// Description: No page is evicted, when all slots are in use (unmapped pages are never evicted)
+fn test_full() {
+    map_with_data(SLOTS + 1, b"full");
+    let address_space = process_manager().read().current_process().address_space();
+
+    for index in 0..=SLOTS {
+        swap::evict(&address_space, page(index));
+    }
+    for index in 0..SLOTS {
+        assert!(swap::evict(&address_space, page(index)));
+    }
+    assert_eq!(swap::used_slots(), SLOTS);
+    assert!(!swap::evict(&address_space, page(SLOTS)));
+    assert!(address_space.page_flags(page(SLOTS).start_address()).is_some());
+    assert!(!swap::evict(&address_space, page(SLOTS + 1)));
+
+    // Every page keeps its data
+    for index in 0..=SLOTS {
+        assert_eq!(user::bytes_from_user((USER_SPACE_MAP_START + index * PAGE_SIZE) as *const u8, 4).as_deref(), Ok(&b"full"[..]));
+    }
+    assert_eq!(swap::used_slots(), 0);
+
+    assert_eq!(sys_unmap_memory(USER_SPACE_MAP_START, (SLOTS + 1) * PAGE_SIZE), 0);
+}
@@ -0,0 +1,210 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: user                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Protection of user memory against the kernel (SMEP and SMAP)    ║
+   ║         and the only functions allowed to access user memory:           ║
+   ║           copy_from_user    copy bytes from user memory                 ║
+   ║           bytes_from_user   copy bytes from user memory into a vector   ║
+   ║           copy_to_user      copy bytes to user memory                   ║
+   ║           write_to_user     write a value to user memory                ║
+   ║           string_from_user  copy a UTF-8 string from user memory        ║
+   ║         With SMAP, every other access to user pages from the kernel     ║
+   ║         causes a page fault. The copy functions temporarily allow the   ║
+   ║         access with 'stac' and forbid it again with 'clac'. They first  ║
+   ║         check in the page tables of the current process that the whole  ║
+   ║         range is present and user accessible (writable for writes).     ║
+   ║         Swapped out pages are swapped in by the check, merged pages     ║
+   ║         (see 'ksm') are copied before writing. The check is repeated    ║
+   ║         while copying with locked page tables, so that no other thread  ║
+   ║         can unmap or swap out a page after the check (which would fault ║
+   ║         in the kernel).                                                 ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::mem::size_of;
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
+use log::{info, warn};
+use raw_cpuid::CpuId;
+use syscall::return_vals::Errno;
+use x86_64::registers::control::{Cr4, Cr4Flags};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use crate::consts::USER_SPACE_START;
+use crate::memory::{ksm, swap, PAGE_SIZE};
+use crate::process_manager;
+
+/// Set, if SMAP is enabled ('stac' and 'clac' are only available with SMAP support)
+static SMAP_ENABLED: AtomicBool = AtomicBool::new(false);
+
+///
+/// Description:
+///    Enable SMEP (the kernel must not execute user pages) and SMAP (the kernel
+///    must not access user pages, except in the copy functions of this module),
+///    if supported by the CPU.
+///
+pub fn enable_protection() {
+    let features = CpuId::new().get_extended_feature_info();
+    let smep = features.as_ref().is_some_and(|features| features.has_smep());
+    let smap = features.as_ref().is_some_and(|features| features.has_smap());
+
+    if smep {
+        unsafe { Cr4::update(|flags| flags.insert(Cr4Flags::SUPERVISOR_MODE_EXECUTION_PROTECTION)) };
+        info!("SMEP has been enabled");
+    } else {
+        warn!("CPU does not support SMEP");
+    }
+
+    if smap {
+        unsafe { Cr4::update(|flags| flags.insert(Cr4Flags::SUPERVISOR_MODE_ACCESS_PREVENTION)) };
+        SMAP_ENABLED.store(true, Relaxed);
+        info!("SMAP has been enabled");
+    } else {
+        warn!("CPU does not support SMAP");
+    }
+}
+
+/// Description: Check if `size` bytes at `addr` lie in user space and are mapped user accessible in the current process
+pub fn is_user_memory(addr: usize, size: usize) -> bool {
+    has_access(addr, size, PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE)
+}
+
+/// Description: Like `is_user_memory()`, but the memory must also be writable
+pub fn is_writable_user_memory(addr: usize, size: usize) -> bool {
+    has_access(addr, size, PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::WRITABLE)
+}
+
+/// Description: Check if all pages of `size` bytes at `addr` lie in user space and are mapped with `flags`
+fn has_access(addr: usize, size: usize, flags: PageTableFlags) -> bool {
+    let end = match addr.checked_add(size) {
+        Some(end) if addr >= USER_SPACE_START && size > 0 => end,
+        _ => return false,
+    };
+
+    let address_space = process_manager().read().current_process().address_space();
+    (addr..end).step_by(PAGE_SIZE).chain([end - 1]).all(|page| VirtAddr::try_new(page as u64).is_ok_and(|page| {
+        // Swapped out pages are swapped in and merged pages are copied for writing, so the copy does not fault
+        let mut page_flags = address_space.page_flags(page);
+        if page_flags.is_none() && swap::swap_in(&address_space, page) {
+            page_flags = address_space.page_flags(page);
+        }
+        if flags.contains(PageTableFlags::WRITABLE) && page_flags.is_some_and(|page_flags| page_flags.contains(ksm::MERGED))
+            && ksm::unshare(&address_space, Page::containing_address(page)) {
+            page_flags = address_space.page_flags(page);
+        }
+
+        page_flags.is_some_and(|page_flags| page_flags.contains(flags))
+    }))
+}
+
+///
+/// Description: Copy `dst.len()` bytes from user memory at `src` into `dst`.
+///
+/// Return: `Ok(())` or `Errno::EFAULT`, if `src` is not mapped user memory
+///
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // `src` is only accessed after it has been checked against the locked page tables
+pub fn copy_from_user(dst: &mut [u8], src: *const u8) -> Result<(), Errno> {
+    if dst.is_empty() {
+        return Ok(());
+    }
+
+    let len = dst.len();
+    locked_user_access(src as usize, len, PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE, || unsafe {
+        dst.as_mut_ptr().copy_from_nonoverlapping(src, len)
+    })
+}
+
+///
+/// Description: Copy `src` to user memory at `dst`.
+///
+/// Return: `Ok(())` or `Errno::EFAULT`, if `dst` is not mapped writable user memory
+///
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // `dst` is only accessed after it has been checked against the locked page tables
+pub fn copy_to_user(dst: *mut u8, src: &[u8]) -> Result<(), Errno> {
+    if src.is_empty() {
+        return Ok(());
+    }
+
+    locked_user_access(dst as usize, src.len(), PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::WRITABLE, || unsafe {
+        dst.copy_from_nonoverlapping(src.as_ptr(), src.len())
+    })
+}
+
+///
+/// Description: Write `value` to user memory at `dst` (which needs not be aligned).
+///
+/// Return: `Ok(())` or `Errno::EFAULT`, if `dst` is not mapped writable user memory
+///
+pub fn write_to_user<T: Copy>(dst: *mut T, value: T) -> Result<(), Errno> {
+    let bytes = unsafe { core::slice::from_raw_parts(ptr::from_ref(&value) as *const u8, size_of::<T>()) };
+    copy_to_user(dst as *mut u8, bytes)
+}
+
+///
+/// Description: Copy a UTF-8 string of `len` bytes from user memory at `src`.
+///
+/// Return: the string, `Errno::EFAULT` if `src` is not mapped user memory or `Errno::EINVAL` if it is not valid UTF-8
+///
+pub fn string_from_user(src: *const u8, len: usize) -> Result<String, Errno> {
+    String::from_utf8(bytes_from_user(src, len)?).map_err(|_| Errno::EINVAL)
+}
+
+///
+/// Description: Copy `len` bytes from user memory at `src` into a new vector.
+///
+/// Return: the bytes or `Errno::EFAULT`, if `src` is not mapped user memory
+///         (checked before allocating, so a bogus `len` cannot exhaust the kernel heap)
+///
+pub fn bytes_from_user(src: *const u8, len: usize) -> Result<Vec<u8>, Errno> {
+    if len > 0 && !is_user_memory(src as usize, len) {
+        return Err(Errno::EFAULT);
+    }
+
+    let mut bytes = vec![0; len];
+    copy_from_user(&mut bytes, src)?;
+    Ok(bytes)
+}
+
+///
+/// Description:
+///    Execute `access` with access to the user pages of `size` bytes at `addr` allowed, while the page tables
+///    of the current process are locked. The pages are prepared by `has_access()` (swapped in, unshared) and
+///    checked again under the lock, since another thread may have unmapped them or the swapper may have evicted
+///    them in between. In the latter case, the pages are prepared again.
+///
+/// Return: `Ok(())` or `Errno::EFAULT`, if the pages are not mapped with `flags`
+///
+fn locked_user_access(addr: usize, size: usize, flags: PageTableFlags, mut access: impl FnMut()) -> Result<(), Errno> {
+    while has_access(addr, size, flags) {
+        let address_space = process_manager().read().current_process().address_space();
+        if address_space.with_mapped(VirtAddr::new(addr as u64), size, flags, || with_user_access(&mut access)).is_some() {
+            return Ok(());
+        }
+    }
+
+    Err(Errno::EFAULT)
+}
+
+/// Description: Allow access to user pages while executing `f` (must only be used by the copy functions)
+fn with_user_access<R>(f: impl FnOnce() -> R) -> R {
+    // The AC flag is saved in RFLAGS by interrupts and thread switches, so it does not leak to other threads.
+    // No 'nomem' option: memory accesses must not be moved across 'stac' and 'clac'.
+    let smap = SMAP_ENABLED.load(Relaxed);
+    if smap {
+        unsafe { asm!("stac", options(nostack)) };
+    }
+
+    let result = f();
+
+    if smap {
+        unsafe { asm!("clac", options(nostack)) };
+    }
+
+    result
+}
@@ -0,0 +1,112 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: user_tests                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the access checks of the user memory copy functions with   ║
+   ║         unmapped, read-only and kernel-only pages. The tests map memory ║
+   ║         into the address space of the calling process.                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::memory::{MAP_READ, MAP_WRITE};
+use syscall::return_vals::Errno;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::structures::paging::page::PageRange;
+
+use crate::consts::USER_SPACE_MAP_START;
+use crate::memory::{user, MemorySpace, PAGE_SIZE};
+use crate::process_manager;
+use crate::syscall::sys_vmem::{sys_map_memory, sys_unmap_memory};
+
+///
+/// Description: Run all user memory access tests
+///
+pub fn run_tests() {
+    info!("user: running tests");
+
+    test_unmapped();
+    test_read_write();
+    test_read_only();
+    test_kernel_only();
+
+    info!("user: all tests passed.");
+}
+
+/// Description: Unmapped pages and kernel addresses are rejected without touching them
+fn test_unmapped() {
+    let addr = USER_SPACE_MAP_START;
+    let mut buffer = [0u8; 16];
+
+    assert_eq!(user::copy_from_user(&mut buffer, addr as *const u8), Err(Errno::EFAULT));
+    assert_eq!(user::copy_to_user(addr as *mut u8, &buffer), Err(Errno::EFAULT));
+    assert_eq!(user::write_to_user(addr as *mut u64, 0x1234), Err(Errno::EFAULT));
+    assert_eq!(user::string_from_user(addr as *const u8, 4), Err(Errno::EFAULT));
+
+    // A bogus length is rejected before memory is allocated for it
+    assert_eq!(user::bytes_from_user(addr as *const u8, usize::MAX / 2), Err(Errno::EFAULT));
+    assert_eq!(user::bytes_from_user(usize::MAX as *const u8, 2), Err(Errno::EFAULT));
+
+    // Kernel memory is never user memory
+    let kernel = buffer.as_ptr();
+    assert_eq!(user::bytes_from_user(kernel, buffer.len()), Err(Errno::EFAULT));
+}
+
+/// Description: Data written to writable user memory can be read back, ranges must be mapped completely
+fn test_read_write() {
+    let addr = USER_SPACE_MAP_START;
+    assert_eq!(sys_map_memory(addr, PAGE_SIZE, MAP_READ | MAP_WRITE), addr as isize);
+
+    assert_eq!(user::copy_to_user(addr as *mut u8, b"hello"), Ok(()));
+    assert_eq!(user::bytes_from_user(addr as *const u8, 5).as_deref(), Ok(&b"hello"[..]));
+    assert_eq!(user::bytes_from_user(addr as *const u8, 0).as_deref(), Ok(&b""[..]));
+
+    // The range reaches into the unmapped page behind the mapping
+    let last = addr + PAGE_SIZE - 2;
+    assert_eq!(user::copy_to_user(last as *mut u8, b"abc"), Err(Errno::EFAULT));
+    assert_eq!(user::bytes_from_user(last as *const u8, 3), Err(Errno::EFAULT));
+    assert_eq!(user::bytes_from_user(last as *const u8, 2).map(|bytes| bytes.len()), Ok(2));
+
+    assert_eq!(sys_unmap_memory(addr, PAGE_SIZE), 0);
+    assert_eq!(user::bytes_from_user(addr as *const u8, 5), Err(Errno::EFAULT));
+}
+
+/// Description: Read-only user memory can be read, but not written
+fn test_read_only() {
+    let addr = USER_SPACE_MAP_START;
+    assert_eq!(sys_map_memory(addr, PAGE_SIZE, MAP_READ), addr as isize);
+
+    assert!(user::is_user_memory(addr, PAGE_SIZE));
+    assert!(!user::is_writable_user_memory(addr, PAGE_SIZE));
+    assert_eq!(user::bytes_from_user(addr as *const u8, 8).as_deref(), Ok(&[0u8; 8][..]));
+    assert_eq!(user::copy_to_user(addr as *mut u8, b"hello"), Err(Errno::EFAULT));
+    assert_eq!(user::write_to_user(addr as *mut u64, 0x1234), Err(Errno::EFAULT));
+
+    assert_eq!(sys_unmap_memory(addr, PAGE_SIZE), 0);
+}
+
+/// Description: A present page in user space, which is not user accessible, is rejected
+fn test_kernel_only() {
+    // Map a user page first, so the page tables on the higher levels are user accessible
+    let addr = USER_SPACE_MAP_START;
+    assert_eq!(sys_map_memory(addr, PAGE_SIZE, MAP_READ | MAP_WRITE), addr as isize);
+
+    let page = Page::from_start_address(VirtAddr::new((addr + PAGE_SIZE) as u64)).unwrap();
+    let pages = PageRange { start: page, end: page + 1 };
+    let address_space = process_manager().read().current_process().address_space();
+    address_space.map(pages, MemorySpace::User, PageTableFlags::PRESENT | PageTableFlags::WRITABLE);
+
+    let flags = address_space.page_flags(page.start_address()).unwrap();
+    assert!(flags.contains(PageTableFlags::PRESENT | PageTableFlags::WRITABLE));
+    assert!(!flags.contains(PageTableFlags::USER_ACCESSIBLE));
+
+    let kernel_only = addr + PAGE_SIZE;
+    assert!(!user::is_user_memory(kernel_only, 1));
+    assert!(!user::is_user_memory(addr, PAGE_SIZE + 1));
+    assert_eq!(user::bytes_from_user(kernel_only as *const u8, 8), Err(Errno::EFAULT));
+    assert_eq!(user::copy_to_user(kernel_only as *mut u8, b"hello"), Err(Errno::EFAULT));
+
+    address_space.unmap(pages, true);
+    assert_eq!(sys_unmap_memory(addr, PAGE_SIZE), 0);
+}
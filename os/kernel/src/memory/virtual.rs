@@ -1,17 +1,28 @@
 use core::cmp::min;
 use core::ptr;
-use spin::RwLock;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
+use log::{info, warn};
+use raw_cpuid::CpuId;
+use spin::{Mutex, RwLock};
 use x86_64::structures::paging::{Page, PageTable, PageTableFlags, PageTableIndex, PhysFrame};
+use x86_64::structures::paging::page_table::PageTableEntry;
+use x86_64::instructions::{interrupts, tlb};
+use x86_64::instructions::tlb::Pcid;
 use x86_64::{PhysAddr, VirtAddr};
-use x86_64::registers::control::{Cr3, Cr3Flags};
+use x86_64::registers::control::{Cr3, Cr3Flags, Cr4, Cr4Flags};
+use x86_64::registers::model_specific::{Efer, EferFlags};
 use x86_64::structures::paging::frame::PhysFrameRange;
 use x86_64::structures::paging::page::PageRange;
-use crate::memory::{MemorySpace, PAGE_SIZE, physical};
+use crate::memory::{ksm, MemorySpace, PAGE_SIZE, physical, swap};
+use crate::memory::physical::AllocFlags;
 use crate::process_manager;
 
 pub struct AddressSpace {
     root_table: RwLock<*mut PageTable>,
-    depth: usize
+    depth: usize,
+    pcid: Option<u16>, // `None`, if all process-context identifiers are in use
+    stale: AtomicBool // TLB entries tagged with `pcid` must be flushed, when switching to this address space
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -22,12 +33,115 @@ pub struct VirtualMemoryArea {
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum VmaType {
-    Code, Heap, Stack, Environment
+    Code, Heap, Stack, Environment, Mapping, NvramMapping
 }
 
 unsafe impl Send for AddressSpace {}
 unsafe impl Sync for AddressSpace {}
 
+/// Size of a huge page, mapped by a single level 2 entry
+pub const HUGE_PAGE_SIZE: usize = 512 * PAGE_SIZE;
+
+/// Number of process-context identifiers (12 bits in CR3). PCID 0 is shared by all address
+/// spaces without an own identifier, so switching to it always flushes the TLB.
+const PCID_COUNT: usize = 4096;
+
+/// Bit 63 of CR3: Keep the TLB entries of the new PCID, when CR3 is written
+const CR3_NO_FLUSH: u64 = 1 << 63;
+
+/// Allocated process-context identifiers (one bit per PCID)
+static PCIDS: Mutex<[u64; PCID_COUNT / 64]> = Mutex::new(pcid_bitmap());
+
+/// Set, if the TLB is tagged with process-context identifiers (CR4.PCIDE)
+static PCID_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Set, if the CPU honors the NO_EXECUTE flag in page table entries (EFER.NXE)
+static NO_EXECUTE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+///
+/// Description:
+///    Enable the NO_EXECUTE page table flag (EFER.NXE), if supported by the CPU.
+///    Without EFER.NXE, bit 63 of a page table entry is reserved and using it causes
+///    a page fault. Must be called before the first address space is loaded.
+///
+/// Return: `true`, if NO_EXECUTE is honored by the hardware
+///
+pub fn enable_no_execute() -> bool {
+    let supported = CpuId::new().get_extended_processor_and_feature_identifiers()
+        .is_some_and(|features| features.has_execute_disable());
+
+    if !supported {
+        warn!("CPU does not support the NX bit; pages marked as NO_EXECUTE remain executable");
+    } else if Efer::read().contains(EferFlags::NO_EXECUTE_ENABLE) {
+        info!("NX bit has already been enabled");
+    } else {
+        unsafe { Efer::update(|flags| flags.insert(EferFlags::NO_EXECUTE_ENABLE)) };
+        info!("NX bit has been enabled");
+    }
+
+    NO_EXECUTE_ENABLED.store(supported, Relaxed);
+    supported
+}
+
+///
+/// Description:
+///    Tag TLB entries with the process-context identifier of their address space (CR4.PCIDE),
+///    if supported by the CPU, so that switching address spaces does not flush the TLB.
+///    Must be called, while an address space is loaded with CR3 bits 0-11 cleared (see `load()`).
+///
+/// Return: `true`, if PCIDs are used (otherwise every address space switch flushes the TLB)
+///
+pub fn enable_pcid() -> bool {
+    let supported = CpuId::new().get_feature_info().is_some_and(|features| features.has_pcid());
+
+    if supported {
+        unsafe { Cr4::update(|flags| flags.insert(Cr4Flags::PCID)) };
+        info!("Process-context identifiers have been enabled");
+    } else {
+        info!("CPU does not support process-context identifiers; switching address spaces flushes the TLB");
+    }
+
+    PCID_ENABLED.store(supported, Relaxed);
+    supported
+}
+
+/// Description: Check whether TLB entries are tagged with process-context identifiers
+pub fn pcid_enabled() -> bool {
+    PCID_ENABLED.load(Relaxed)
+}
+
+/// Description: Initial PCID bitmap (PCID 0 is reserved for address spaces without an own identifier)
+const fn pcid_bitmap() -> [u64; PCID_COUNT / 64] {
+    let mut bitmap = [0; PCID_COUNT / 64];
+    bitmap[0] = 1;
+    bitmap
+}
+
+/// Description: Allocate the lowest free process-context identifier
+fn alloc_pcid() -> Option<u16> {
+    let mut pcids = PCIDS.lock();
+    let (index, word) = pcids.iter_mut().enumerate().find(|(_, word)| **word != u64::MAX)?;
+    let bit = word.trailing_ones() as usize;
+    *word |= 1 << bit;
+
+    Some((index * 64 + bit) as u16)
+}
+
+/// Description: Free `pcid` (its TLB entries are flushed, when the next owner is loaded the first time)
+fn free_pcid(pcid: u16) {
+    PCIDS.lock()[pcid as usize / 64] &= !(1 << (pcid as usize % 64));
+}
+
+/// Description: Check whether NO_EXECUTE flags in page table entries are honored by the hardware
+pub fn no_execute_enabled() -> bool {
+    NO_EXECUTE_ENABLED.load(Relaxed)
+}
+
+/// Description: Remove NO_EXECUTE from `flags`, if it is not supported (it would be a reserved bit otherwise)
+fn supported_flags(flags: PageTableFlags) -> PageTableFlags {
+    if no_execute_enabled() { flags } else { flags - PageTableFlags::NO_EXECUTE }
+}
+
 fn page_table_index(virt_addr: VirtAddr, level: usize) -> PageTableIndex {
     PageTableIndex::new_truncate((virt_addr.as_u64() >> 12 >> ((level as u8 - 1) * 9)) as u16)
 }
@@ -39,6 +153,9 @@ impl Drop for AddressSpace {
         let root_table = unsafe { root_table_guard.as_mut().unwrap() };
 
         AddressSpace::drop_table(root_table, depth);
+        if let Some(pcid) = self.pcid {
+            free_pcid(pcid);
+        }
     }
 }
 
@@ -93,7 +210,8 @@ impl AddressSpace {
         let root_table = table_addr.start_address().as_u64() as *mut PageTable;
         unsafe { root_table.as_mut().unwrap().zero(); }
 
-        Self { root_table: RwLock::new(root_table), depth }
+        // A recycled PCID may still tag TLB entries of its previous owner
+        Self { root_table: RwLock::new(root_table), depth, pcid: alloc_pcid(), stale: AtomicBool::new(true) }
     }
 
     pub fn from_other(other: &AddressSpace) -> Self {
@@ -112,7 +230,46 @@ impl AddressSpace {
     }
 
     pub fn load(&self) {
-        unsafe { Cr3::write(PhysFrame::from_start_address(self.page_table_address()).unwrap(), Cr3Flags::empty()) };
+        let frame = PhysFrame::from_start_address(self.page_table_address()).unwrap();
+        match self.pcid {
+            Some(pcid) if pcid_enabled() => unsafe { Cr3::write_pcid(frame, Pcid::new(pcid).unwrap()) },
+            _ => unsafe { Cr3::write(frame, Cr3Flags::empty()) }
+        }
+        self.stale.store(false, Relaxed);
+    }
+
+    /// Description: Process-context identifier of this address space (`None`, if all are in use)
+    pub fn pcid(&self) -> Option<u16> {
+        self.pcid
+    }
+
+    ///
+    /// Description:
+    ///    Value to write into CR3 to switch to this address space (called by the scheduler).
+    ///    With PCIDs, the TLB entries of this address space are kept, unless its page tables
+    ///    have been changed, while another address space was loaded (see `invalidate()`).
+    ///
+    pub fn cr3(&self) -> u64 {
+        let address = self.page_table_address().as_u64();
+        match self.pcid {
+            Some(pcid) if pcid_enabled() => match self.stale.swap(false, Relaxed) {
+                true => address | pcid as u64,
+                false => address | pcid as u64 | CR3_NO_FLUSH
+            },
+            _ => address
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Called after mappings have been removed or restricted. If this address space is not
+    ///    loaded, `tlb::flush()` cannot reach its TLB entries, so they are flushed, when it is
+    ///    loaded the next time. Callers still flush the TLB for the loaded address space.
+    ///
+    fn invalidate(&self) {
+        if pcid_enabled() && Cr3::read().0.start_address() != self.page_table_address() {
+            self.stale.store(true, Relaxed);
+        }
     }
 
     pub fn page_table_address(&self) -> PhysAddr {
@@ -123,13 +280,15 @@ impl AddressSpace {
         PhysAddr::new(root_table as u64)
     }
 
+    /// Map `pages`. In user space, new page frames are allocated, which are always zeroed,
+    /// so that no data of a previous owner is visible. Kernel space is identity mapped.
     pub fn map(&self, pages: PageRange, space: MemorySpace, flags: PageTableFlags) {
         let depth = self.depth;
         let root_table_guard = self.root_table.write();
         let root_table = unsafe { root_table_guard.as_mut().unwrap() };
         let frames = PhysFrameRange { start: PhysFrame::from_start_address(PhysAddr::zero()).unwrap(), end: PhysFrame::from_start_address(PhysAddr::zero()).unwrap() };
 
-        AddressSpace::map_in_table(root_table, frames, pages, space, flags, depth);
+        AddressSpace::map_in_table(root_table, frames, pages, space, supported_flags(flags), depth);
     }
 
     pub fn map_physical(&self, frames: PhysFrameRange, pages: PageRange, space: MemorySpace, flags: PageTableFlags) {
@@ -138,7 +297,7 @@ impl AddressSpace {
         let root_table = unsafe { root_table_guard.as_mut().unwrap() };
 
         assert_eq!(frames.end - frames.start, pages.end - pages.start);
-        AddressSpace::map_in_table(root_table, frames, pages, space, flags, depth);
+        AddressSpace::map_in_table(root_table, frames, pages, space, supported_flags(flags), depth);
     }
 
     pub fn translate(&self, addr: VirtAddr) -> Option<PhysAddr> {
@@ -149,12 +308,52 @@ impl AddressSpace {
         AddressSpace::translate_in_table(root_table, addr, depth)
     }
 
-    pub fn unmap(&self, pages: PageRange, free_physical: bool) {
+    ///
+    /// Description:
+    ///    Effective flags of the page containing `addr`. `WRITABLE` and `USER_ACCESSIBLE` are only
+    ///    set, if they are set on all levels (as checked by the MMU).
+    ///
+    /// Return: the flags or `None`, if the page is not present
+    ///
+    pub fn page_flags(&self, addr: VirtAddr) -> Option<PageTableFlags> {
         let depth = self.depth;
         let root_table_guard = self.root_table.read();
         let root_table = unsafe { root_table_guard.as_mut().unwrap() };
 
+        AddressSpace::flags_in_table(root_table, addr, depth)
+    }
+
+    ///
+    /// Description:
+    ///    Call `access` while the page tables are locked, if all pages of `size` bytes at `addr`
+    ///    are mapped with `flags`. As long as the lock is held, no page can be unmapped, swapped
+    ///    out or merged, so `access` can touch these pages without faulting. Interrupts are
+    ///    disabled meanwhile, so no thread waits for the lock of a preempted thread.
+    ///
+    /// Return: the result of `access` or `None`, if a page is not mapped with `flags`
+    ///
+    pub fn with_mapped<R>(&self, addr: VirtAddr, size: usize, flags: PageTableFlags, access: impl FnOnce() -> R) -> Option<R> {
+        let depth = self.depth;
+        let end = addr + (size.max(1) - 1) as u64;
+        interrupts::without_interrupts(|| {
+            let root_table_guard = self.root_table.read();
+            let root_table = unsafe { root_table_guard.as_mut().unwrap() };
+
+            let mut pages = (addr.as_u64()..end.as_u64()).step_by(PAGE_SIZE).map(VirtAddr::new).chain([end]);
+            match pages.all(|page| AddressSpace::flags_in_table(root_table, page, depth).is_some_and(|page_flags| page_flags.contains(flags))) {
+                true => Some(access()),
+                false => None,
+            }
+        })
+    }
+
+    pub fn unmap(&self, pages: PageRange, free_physical: bool) {
+        let depth = self.depth;
+        let root_table_guard = self.root_table.write();
+        let root_table = unsafe { root_table_guard.as_mut().unwrap() };
+
         AddressSpace::unmap_in_table(root_table, pages, depth, free_physical);
+        self.invalidate();
     }
 
     pub fn set_flags(&self, pages: PageRange, flags: PageTableFlags) {
@@ -162,7 +361,54 @@ impl AddressSpace {
         let root_table_guard = self.root_table.write();
         let root_table = unsafe { root_table_guard.as_mut().unwrap() };
 
-        AddressSpace::set_flags_in_table(root_table, pages, flags, depth);
+        AddressSpace::set_flags_in_table(root_table, pages, supported_flags(flags), depth);
+        self.invalidate();
+    }
+
+    ///
+    /// Description:
+    ///    Call `update` with the level 1 entry of `page`, while the page tables are locked
+    ///    (e.g. to swap the page out or in, see `swap`). The TLB is not flushed.
+    ///
+    /// Return: the result of `update` or `None`, if there is no level 1 table for `page`
+    ///
+    pub fn update_entry<R>(&self, page: Page, update: impl FnOnce(&mut PageTableEntry) -> R) -> Option<R> {
+        let depth = self.depth;
+        let root_table_guard = self.root_table.write();
+        let root_table = unsafe { root_table_guard.as_mut().unwrap() };
+
+        let result = AddressSpace::entry_in_table(root_table, page.start_address(), depth).map(update);
+        self.invalidate();
+        result
+    }
+
+    ///
+    /// Description:
+    ///    Replace the 4 KiB mappings in `pages` with 2 MiB pages (to reduce TLB misses), where
+    ///    all 512 pages of an aligned 2 MiB area are present, map physically contiguous frames
+    ///    starting at a 2 MiB boundary and have the same flags. Huge pages are split again on
+    ///    demand, when a single page is mapped, unmapped or gets different flags.
+    ///
+    /// Return: the number of new huge pages
+    ///
+    pub fn promote(&self, pages: PageRange) -> usize {
+        let depth = self.depth;
+        let root_table_guard = self.root_table.write();
+        let root_table = unsafe { root_table_guard.as_mut().unwrap() };
+
+        let mut promoted: usize = 0;
+        let mut addr = pages.start.start_address().align_up(HUGE_PAGE_SIZE as u64);
+        while addr + HUGE_PAGE_SIZE as u64 <= pages.end.start_address() {
+            if AddressSpace::level_2_entry_in_table(root_table, addr, depth).is_some_and(AddressSpace::promote_entry) {
+                tlb::flush(addr);
+                promoted += 1;
+            }
+
+            addr += HUGE_PAGE_SIZE as u64;
+        }
+
+        self.invalidate();
+        promoted
     }
 
     fn copy_table(source: &PageTable, target: &mut PageTable, level: usize) {
@@ -173,6 +419,10 @@ impl AddressSpace {
                     target_entry.set_unused();
                     continue;
                 }
+                if source_entry.flags().contains(PageTableFlags::HUGE_PAGE) { // Huge pages are copied 1:1, like level 1 entries
+                    target_entry.set_addr(source_entry.addr(), source_entry.flags());
+                    continue;
+                }
 
                 let phys_frame = physical::alloc(1).start;
                 let flags = source[index].flags();
@@ -198,12 +448,16 @@ impl AddressSpace {
             for entry in table.iter_mut().skip(start_index) {
                 let next_level_table;
                 if entry.is_unused() { // Entry is empty -> Allocate new page frame
+                    // Access rights of a table entry apply to all pages below, so they are only restricted in level 1 entries
                     let phys_frame = physical::alloc(1).start;
-                    entry.set_frame(phys_frame, flags);
+                    entry.set_frame(phys_frame, (flags | PageTableFlags::WRITABLE) - PageTableFlags::NO_EXECUTE);
 
                     next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
                     next_level_table.zero();
                 } else {
+                    if entry.flags().contains(PageTableFlags::HUGE_PAGE) { // Remapping a part of a huge page
+                        AddressSpace::split_entry(entry, pages.start.start_address());
+                    }
                     next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
                 }
 
@@ -244,6 +498,9 @@ impl AddressSpace {
                 if entry.is_unused() {
                     continue;
                 }
+                if entry.flags().contains(PageTableFlags::HUGE_PAGE) { // The level 1 table is freed below, if all pages are unmapped
+                    AddressSpace::split_entry(entry, pages.start.start_address());
+                }
 
                 let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
                 let freed_pages = AddressSpace::unmap_in_table(next_level_table, pages, level - 1, free_physical);
@@ -269,9 +526,13 @@ impl AddressSpace {
                     break;
                 }
 
-                if !entry.is_unused() {
-                    if free_physical {
-                        let frame = PhysFrame::from_start_address(entry.addr()).unwrap();
+                if entry.flags().contains(swap::SWAPPED) {
+                    swap::free_slot(entry);
+                    entry.set_unused();
+                } else if !entry.is_unused() {
+                    // Frames, that are still mapped elsewhere, are not freed
+                    let frame = PhysFrame::from_start_address(entry.addr()).unwrap();
+                    if physical::dec_ref(frame) == 0 && free_physical {
                         unsafe { physical::free(PhysFrameRange { start: frame, end: frame + 1 }); }
                     }
 
@@ -288,7 +549,7 @@ impl AddressSpace {
     fn drop_table(table: &mut PageTable, level: usize) {
         if level > 1 { // Calculate next level page table until level == 1
             for entry in table.iter_mut() {
-                if entry.addr() == PhysAddr::zero() {
+                if entry.addr() == PhysAddr::zero() || entry.flags().contains(PageTableFlags::HUGE_PAGE) {
                     continue;
                 }
 
@@ -313,6 +574,22 @@ impl AddressSpace {
                 if entry.is_unused() { // Skip empty entries
                     continue;
                 }
+                if entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+                    let huge_page = pages.start.start_address().align_down(HUGE_PAGE_SIZE as u64);
+                    if pages.start.start_address() == huge_page && pages.end.start_address() >= huge_page + HUGE_PAGE_SIZE as u64 {
+                        // All pages get the new flags -> Keep the huge page
+                        entry.set_flags(flags | PageTableFlags::HUGE_PAGE);
+                        pages = PageRange { start: pages.start + 512, end: pages.end };
+                        total_edited_pages += 512;
+
+                        if pages.start >= pages.end {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    AddressSpace::split_entry(entry, huge_page);
+                }
 
                 let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
 
@@ -350,14 +627,118 @@ impl AddressSpace {
             return None;
         }
 
-        if level > 1 { // Calculate next level page table until level == 1
+        if level > 1 && entry.flags().contains(PageTableFlags::HUGE_PAGE) { // Huge page -> no level 1 table
+            Some(entry.addr() + (addr.as_u64() & (HUGE_PAGE_SIZE as u64 - 1)))
+        } else if level > 1 { // Calculate next level page table until level == 1
             let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
             AddressSpace::translate_in_table(next_level_table, addr, level - 1)
+        } else if entry.flags().contains(swap::SWAPPED) { // The address is a swap slot
+            None
         } else { // Reached level 1 page table
             Some(entry.addr() + (addr - aligned_addr))
         }
     }
 
+    fn entry_in_table(table: &mut PageTable, addr: VirtAddr, level: usize) -> Option<&mut PageTableEntry> {
+        let entry = &mut table[page_table_index(addr, level)];
+        if level == 1 {
+            return Some(entry);
+        }
+        if entry.is_unused() || entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+            return None;
+        }
+
+        let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
+        AddressSpace::entry_in_table(next_level_table, addr, level - 1)
+    }
+
+    fn level_2_entry_in_table(table: &mut PageTable, addr: VirtAddr, level: usize) -> Option<&mut PageTableEntry> {
+        let entry = &mut table[page_table_index(addr, level)];
+        if level == 2 {
+            return Some(entry);
+        }
+        if entry.is_unused() || entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+            return None;
+        }
+
+        let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
+        AddressSpace::level_2_entry_in_table(next_level_table, addr, level - 1)
+    }
+
+    ///
+    /// Description:
+    ///    Replace the level 1 table of the level 2 `entry` with a huge page, if all of its entries
+    ///    map contiguous frames with the same flags. The access rights of `entry` are kept.
+    ///    The TLB is not flushed.
+    ///
+    /// Return: `true`, if the level 1 table has been freed
+    ///
+    fn promote_entry(entry: &mut PageTableEntry) -> bool {
+        if entry.is_unused() || entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+            return false;
+        }
+
+        // The CPU sets ACCESSED and DIRTY for each page, so they are ignored (and set for the huge page again)
+        let table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
+        let flags = table[0].flags() - (PageTableFlags::ACCESSED | PageTableFlags::DIRTY);
+        let start = table[0].addr();
+        // Bit 7 is the PAT bit in level 1 entries; swapped out and merged pages are handled per page
+        if !flags.contains(PageTableFlags::PRESENT) || flags.intersects(PageTableFlags::HUGE_PAGE | swap::SWAPPED | ksm::MERGED) || !start.is_aligned(HUGE_PAGE_SIZE as u64) {
+            return false;
+        }
+        let contiguous = table.iter().enumerate()
+            .all(|(index, page_entry)| page_entry.flags() - (PageTableFlags::ACCESSED | PageTableFlags::DIRTY) == flags && page_entry.addr() == start + (index * PAGE_SIZE) as u64);
+        if !contiguous {
+            return false;
+        }
+
+        let restricting = PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+        let table_frame = PhysFrame::from_start_address(entry.addr()).unwrap();
+        entry.set_addr(start, (flags - (restricting - entry.flags())) | PageTableFlags::HUGE_PAGE);
+        unsafe { physical::free(PhysFrameRange { start: table_frame, end: table_frame + 1 }); }
+
+        true
+    }
+
+    ///
+    /// Description:
+    ///    Split the huge page of the level 2 `entry`, which maps `addr`, into a level 1 table
+    ///    with 512 entries for the same frames and flags (e.g. before changing a single page).
+    ///
+    fn split_entry(entry: &mut PageTableEntry, addr: VirtAddr) {
+        let flags = entry.flags() - PageTableFlags::HUGE_PAGE;
+        let start = entry.addr();
+
+        let table_frame = physical::alloc(1).start;
+        let table = unsafe { (table_frame.start_address().as_u64() as *mut PageTable).as_mut().unwrap() };
+        for (index, page_entry) in table.iter_mut().enumerate() {
+            page_entry.set_addr(start + (index * PAGE_SIZE) as u64, flags);
+        }
+
+        // Same as in `map_in_table()`: Access rights are only restricted in level 1 entries
+        entry.set_frame(table_frame, (flags | PageTableFlags::WRITABLE) - PageTableFlags::NO_EXECUTE);
+        // The translations are unchanged, but the TLB must not hold the huge page and 4 KiB pages at once
+        tlb::flush(addr.align_down(HUGE_PAGE_SIZE as u64));
+    }
+
+    fn flags_in_table(table: &mut PageTable, addr: VirtAddr, level: usize) -> Option<PageTableFlags> {
+        let index = usize::from(page_table_index(addr.align_down(PAGE_SIZE as u64), level));
+        let entry = &table[index];
+        if !entry.flags().contains(PageTableFlags::PRESENT) {
+            return None;
+        }
+
+        if level > 1 && entry.flags().contains(PageTableFlags::HUGE_PAGE) { // Huge page -> no level 1 table
+            Some(entry.flags() - PageTableFlags::HUGE_PAGE)
+        } else if level > 1 { // Access rights of a higher level restrict all pages below
+            let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
+            let restricting = PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+            AddressSpace::flags_in_table(next_level_table, addr, level - 1).map(|flags| flags - (restricting - entry.flags()))
+        } else {
+            Some(entry.flags())
+        }
+    }
+
     fn identity_map_kernel(table: &mut PageTable, pages: PageRange, flags: PageTableFlags) -> usize {
         let start_index = usize::from(page_table_index(pages.start.start_address(), 1));
         let alloc_count = min((pages.end - pages.start) as usize, 512 - start_index);
@@ -378,13 +759,10 @@ impl AddressSpace {
     fn map_user(table: &mut PageTable, pages: PageRange, flags: PageTableFlags) -> usize {
         let start_index = usize::from(page_table_index(pages.start.start_address(), 1));
         let alloc_count = min((pages.end - pages.start) as usize, 512 - start_index);
+        let frames = physical::alloc_frames(alloc_count, AllocFlags::empty()).expect("PageFrameAllocator: Out of memory!");
 
-        for (count, entry) in table.iter_mut().skip(start_index).enumerate() {
-            if count >= alloc_count {
-                break;
-            }
-
-            let phys_frame = physical::alloc(1).start;
+        for (entry, phys_frame) in table.iter_mut().skip(start_index).zip(frames) {
+            physical::inc_ref(phys_frame);
             entry.set_frame(phys_frame, flags);
         }
 
@@ -401,7 +779,9 @@ impl AddressSpace {
                 break;
             }
 
-            entry.set_frame(frame_iter.next().unwrap(), flags);
+            let phys_frame = frame_iter.next().unwrap();
+            physical::inc_ref(phys_frame);
+            entry.set_frame(phys_frame, flags);
         }
 
         alloc_count
@@ -416,4 +796,14 @@ impl AddressSpace {
 
         true
     }
-}
\ No newline at end of file
+}
+
+///
+/// Description:
+///    Translate `addr` using the page tables loaded in CR3 (4 levels). Does not lock
+///    any address space, so it can be used when locks may be held (e.g. on panic).
+///
+pub fn translate_current(addr: VirtAddr) -> Option<PhysAddr> {
+    let root_table = unsafe { (Cr3::read().0.start_address().as_u64() as *mut PageTable).as_mut().unwrap() };
+    AddressSpace::translate_in_table(root_table, addr, 4)
+}
@@ -0,0 +1,187 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: virtual_tests                                                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test promoting identity mapped kernel pages to 2 MiB pages and  ║
+   ║         splitting them again on demand, the process-context identifiers ║
+   ║         of address spaces and the access to mapped pages with locked    ║
+   ║         page tables. The tests use their own address spaces, which are  ║
+   ║         never loaded, so the mapped memory is not accessed.             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::structures::paging::page::PageRange;
+
+use crate::memory::{MemorySpace, PAGE_SIZE};
+use crate::memory::r#virtual::{pcid_enabled, AddressSpace, HUGE_PAGE_SIZE};
+
+/// Start of the test mapping (2 MiB aligned)
+const BASE: u64 = 0x4000_0000;
+const FLAGS: PageTableFlags = PageTableFlags::PRESENT.union(PageTableFlags::WRITABLE);
+
+///
+/// Description: Run all huge page and PCID tests
+///
+pub fn run_tests() {
+    info!("virtual: running tests");
+
+    test_promote();
+    test_split_on_set_flags();
+    test_split_on_unmap();
+    test_copy();
+    test_with_mapped();
+    test_pcid_recycling();
+    test_pcid_flush();
+
+    info!("virtual: all tests passed.");
+}
+
+fn page(addr: u64) -> Page {
+    Page::from_start_address(VirtAddr::new(addr)).unwrap()
+}
+
+fn pages(start: u64, end: u64) -> PageRange {
+    PageRange { start: page(start), end: page(end) }
+}
+
+/// Description: Address space with two complete 2 MiB areas and one additional page on each side identity mapped
+fn mapped_address_space() -> AddressSpace {
+    let address_space = AddressSpace::new(4);
+    address_space.map(pages(BASE - PAGE_SIZE as u64, BASE + 2 * HUGE_PAGE_SIZE as u64 + PAGE_SIZE as u64), MemorySpace::Kernel, FLAGS);
+    address_space
+}
+
+fn assert_identity(address_space: &AddressSpace, addr: u64) {
+    assert_eq!(address_space.translate(VirtAddr::new(addr)), Some(PhysAddr::new(addr)));
+}
+
+/// Description: The access is only done, if all pages of the range are mapped with the requested flags
+fn test_with_mapped() {
+    let address_space = mapped_address_space();
+    let start = VirtAddr::new(BASE - PAGE_SIZE as u64);
+    let size = 2 * HUGE_PAGE_SIZE + 2 * PAGE_SIZE;
+
+    assert_eq!(address_space.with_mapped(start, size, FLAGS, || 42), Some(42));
+    assert_eq!(address_space.with_mapped(start + 1u64, 1, FLAGS, || 42), Some(42));
+
+    // Not user accessible, the range ends on an unmapped page or crosses an unmapped page
+    let mut accessed = false;
+    assert_eq!(address_space.with_mapped(start, size, FLAGS | PageTableFlags::USER_ACCESSIBLE, || accessed = true), None);
+    assert_eq!(address_space.with_mapped(start, size + 1, FLAGS, || accessed = true), None);
+    address_space.unmap(pages(BASE, BASE + PAGE_SIZE as u64), false);
+    assert_eq!(address_space.with_mapped(start, 2 * PAGE_SIZE, FLAGS, || accessed = true), None);
+    assert!(!accessed);
+}
+
+/// Description: Only complete and aligned areas are promoted, the translations and flags are unchanged
+fn test_promote() {
+    let address_space = mapped_address_space();
+    assert_eq!(address_space.promote(pages(BASE - PAGE_SIZE as u64, BASE + 2 * HUGE_PAGE_SIZE as u64 + PAGE_SIZE as u64)), 2);
+    assert_eq!(address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64)), 0);
+
+    for addr in [BASE - PAGE_SIZE as u64, BASE, BASE + 0x1234, BASE + HUGE_PAGE_SIZE as u64 + 0x10_0008, BASE + 2 * HUGE_PAGE_SIZE as u64] {
+        assert_identity(&address_space, addr);
+        assert_eq!(address_space.page_flags(VirtAddr::new(addr)), Some(FLAGS));
+    }
+}
+
+/// Description: Changing the flags of a single page splits the huge page, changing all flags keeps it
+fn test_split_on_set_flags() {
+    let address_space = mapped_address_space();
+    address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64));
+
+    let read_only = BASE + 5 * PAGE_SIZE as u64;
+    address_space.set_flags(pages(read_only, read_only + PAGE_SIZE as u64), PageTableFlags::PRESENT);
+    assert_eq!(address_space.page_flags(VirtAddr::new(read_only)), Some(PageTableFlags::PRESENT));
+    assert_eq!(address_space.page_flags(VirtAddr::new(read_only - PAGE_SIZE as u64)), Some(FLAGS));
+    assert_eq!(address_space.page_flags(VirtAddr::new(read_only + PAGE_SIZE as u64)), Some(FLAGS));
+    for addr in [BASE, read_only, read_only + 8, BASE + HUGE_PAGE_SIZE as u64 - 1] {
+        assert_identity(&address_space, addr);
+    }
+
+    // Pages with different flags are not promoted
+    assert_eq!(address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64)), 0);
+    address_space.set_flags(pages(read_only, read_only + PAGE_SIZE as u64), FLAGS);
+    assert_eq!(address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64)), 1);
+
+    // Changing a complete huge page does not split it (it would be promoted again otherwise)
+    let second = BASE + HUGE_PAGE_SIZE as u64;
+    address_space.set_flags(pages(second, second + HUGE_PAGE_SIZE as u64), FLAGS | PageTableFlags::NO_CACHE);
+    assert_eq!(address_space.page_flags(VirtAddr::new(second + 0x3000)), Some(FLAGS | PageTableFlags::NO_CACHE));
+    assert_eq!(address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64)), 0);
+    assert_identity(&address_space, second + 0x3000);
+}
+
+/// Description: Unmapping a part of a huge page keeps the other pages mapped
+fn test_split_on_unmap() {
+    let address_space = mapped_address_space();
+    address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64));
+
+    let unmapped = BASE + HUGE_PAGE_SIZE as u64 - PAGE_SIZE as u64;
+    address_space.unmap(pages(unmapped, unmapped + 2 * PAGE_SIZE as u64), false);
+    assert_eq!(address_space.translate(VirtAddr::new(unmapped)), None);
+    assert_eq!(address_space.translate(VirtAddr::new(unmapped + PAGE_SIZE as u64)), None);
+    assert_eq!(address_space.page_flags(VirtAddr::new(unmapped)), None);
+    assert_identity(&address_space, unmapped - PAGE_SIZE as u64);
+    assert_identity(&address_space, unmapped + 2 * PAGE_SIZE as u64);
+
+    // Mapping the pages again splits nothing else
+    address_space.map(pages(unmapped, unmapped + 2 * PAGE_SIZE as u64), MemorySpace::Kernel, FLAGS);
+    assert_identity(&address_space, unmapped);
+    assert_eq!(address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64)), 2);
+}
+
+/// Description: Huge pages are copied into new address spaces (like the kernel mappings of a process)
+fn test_copy() {
+    let address_space = mapped_address_space();
+    address_space.promote(pages(BASE, BASE + 2 * HUGE_PAGE_SIZE as u64));
+
+    let copy = AddressSpace::from_other(&address_space);
+    assert_identity(&copy, BASE + 0x1234);
+    assert_eq!(copy.page_flags(VirtAddr::new(BASE + 0x1234)), Some(FLAGS));
+
+    // Splitting the copy does not change the original
+    copy.set_flags(pages(BASE, BASE + PAGE_SIZE as u64), PageTableFlags::PRESENT);
+    assert_eq!(copy.page_flags(VirtAddr::new(BASE)), Some(PageTableFlags::PRESENT));
+    assert_eq!(address_space.page_flags(VirtAddr::new(BASE)), Some(FLAGS));
+}
+
+/// Description: Each address space has an own PCID, which is reused after the address space has been dropped
+fn test_pcid_recycling() {
+    let first = AddressSpace::new(4);
+    let second = AddressSpace::new(4);
+    assert!(first.pcid().is_some_and(|pcid| pcid > 0));
+    assert_ne!(first.pcid(), second.pcid());
+
+    let pcid = first.pcid();
+    drop(first);
+    let third = AddressSpace::new(4);
+    assert_eq!(third.pcid(), pcid);
+}
+
+/// Description: TLB entries are kept, unless the page tables have been changed, while another address space was loaded
+fn test_pcid_flush() {
+    let address_space = mapped_address_space();
+    let address = address_space.page_table_address().as_u64();
+    if !pcid_enabled() {
+        assert_eq!(address_space.cr3(), address);
+        return;
+    }
+
+    // TLB entries of the previous owner of the PCID are flushed once
+    let pcid = address_space.pcid().unwrap() as u64;
+    assert_eq!(address_space.cr3(), address | pcid);
+    assert_eq!(address_space.cr3(), address | pcid | 1 << 63);
+
+    // Adding mappings keeps the TLB entries, restricting them does not
+    address_space.map(pages(BASE + 4 * HUGE_PAGE_SIZE as u64, BASE + 4 * HUGE_PAGE_SIZE as u64 + PAGE_SIZE as u64), MemorySpace::Kernel, FLAGS);
+    assert_eq!(address_space.cr3(), address | pcid | 1 << 63);
+    address_space.set_flags(pages(BASE, BASE + PAGE_SIZE as u64), PageTableFlags::PRESENT);
+    assert_eq!(address_space.cr3(), address | pcid);
+    address_space.unmap(pages(BASE, BASE + PAGE_SIZE as u64), false);
+    assert_eq!(address_space.cr3(), address | pcid);
+    assert_eq!(address_space.cr3(), address | pcid | 1 << 63);
+}
@@ -0,0 +1,133 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: file_lock                                                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Advisory locks (like 'flock') on containers of the name         ║
+   ║         service. A container may be locked shared by several processes  ║
+   ║         or exclusively by one process. The locks are advisory: reading  ║
+   ║         and writing are never blocked, only other lock requests are.    ║
+   ║         Locks belong to a process (there are no open files yet) and are ║
+   ║         identified by the node number of the container, so they apply   ║
+   ║         to all hard links. A new request of the holder replaces its     ║
+   ║         current lock (e.g. upgrade from shared to exclusive). All locks ║
+   ║         of a process are released, when it exits.                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec::Vec;
+use spin::Mutex;
+use syscall::return_vals::Errno;
+use crate::scheduler;
+
+/// Interval for retrying a blocking lock request, while the lock is held by others
+const LOCK_RETRY_MS: usize = 5;
+
+/// Locks of all containers, which are locked by at least one process
+static LOCKS: Mutex<Vec<FileLock>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LockType {
+    Shared = 0,
+    Exclusive = 1,
+    Unlock = 2,
+}
+
+impl TryFrom<usize> for LockType {
+    type Error = Errno;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(LockType::Shared),
+            1 => Ok(LockType::Exclusive),
+            2 => Ok(LockType::Unlock),
+            _ => Err(Errno::EINVAL),
+        }
+    }
+}
+
+/// Holders of the lock of one container
+struct FileLock {
+    node: usize,
+    shared: Vec<usize>,
+    exclusive: Option<usize>,
+}
+
+impl FileLock {
+    fn is_free(&self) -> bool {
+        self.shared.is_empty() && self.exclusive.is_none()
+    }
+
+    /// Description: Replace the lock of `holder` by `lock_type`, if no other holder conflicts
+    fn try_set(&mut self, holder: usize, lock_type: LockType) -> bool {
+        let others_exclusive = self.exclusive.is_some_and(|id| id != holder);
+        let others_shared = self.shared.iter().any(|&id| id != holder);
+
+        match lock_type {
+            LockType::Shared if others_exclusive => return false,
+            LockType::Exclusive if others_exclusive || others_shared => return false,
+            _ => {}
+        }
+
+        self.shared.retain(|&id| id != holder);
+        self.exclusive = self.exclusive.filter(|&id| id != holder);
+        match lock_type {
+            LockType::Shared => self.shared.push(holder),
+            LockType::Exclusive => self.exclusive = Some(holder),
+            LockType::Unlock => {}
+        }
+
+        true
+    }
+}
+
+///
+/// Description:
+///    Set or release the lock of process `holder` on the container with node number `node`.
+///
+/// Parameters: \
+///    `node` node number of the container (see `Stat::ino`) \
+///    `holder` id of the locking process \
+///    `lock_type` requested lock or `LockType::Unlock` \
+///    `blocking` wait until the lock is available (otherwise fail with `Errno::EAGAIN`)
+///
+pub fn lock(node: usize, holder: usize, lock_type: LockType, blocking: bool) -> Result<(), Errno> {
+    loop {
+        if try_lock(node, holder, lock_type) {
+            return Ok(());
+        }
+        if !blocking {
+            return Err(Errno::EAGAIN);
+        }
+
+        // The lock table must not be held while waiting
+        scheduler().sleep(LOCK_RETRY_MS);
+    }
+}
+
+/// Description: Release all locks of process `holder` (called, when the process exits)
+pub fn release_all(holder: usize) {
+    let mut locks = LOCKS.lock();
+    for lock in locks.iter_mut() {
+        lock.try_set(holder, LockType::Unlock);
+    }
+    locks.retain(|lock| !lock.is_free());
+}
+
+fn try_lock(node: usize, holder: usize, lock_type: LockType) -> bool {
+    let mut locks = LOCKS.lock();
+    let index = match locks.iter().position(|lock| lock.node == node) {
+        Some(index) => index,
+        None if lock_type == LockType::Unlock => return true,
+        None => {
+            locks.push(FileLock { node, shared: Vec::new(), exclusive: None });
+            locks.len() - 1
+        }
+    };
+
+    let locked = locks[index].try_set(holder, lock_type);
+    if locks[index].is_free() {
+        locks.swap_remove(index);
+    }
+
+    locked
+}
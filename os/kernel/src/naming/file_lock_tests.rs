@@ -0,0 +1,141 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: file_lock_tests                                                 ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test advisory locks. Processes are simulated by holder ids,     ║
+   ║         which are not used by real processes.                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::SeqCst;
+use ::log::info;
+use syscall::return_vals::Errno;
+
+use crate::naming::file_lock::{lock, release_all, LockType};
+use crate::naming::name_service::{del, link, mkentry, stat};
+use crate::process::thread::Thread;
+use crate::scheduler;
+
+const HOLDER_A: usize = usize::MAX - 1;
+const HOLDER_B: usize = usize::MAX - 2;
+const HOLDER_C: usize = usize::MAX - 3;
+
+/// Node number of the container used by `test_mutual_exclusion`
+static NODE: AtomicUsize = AtomicUsize::new(0);
+/// Number of holders currently inside the critical section of `test_mutual_exclusion`
+static IN_SECTION: AtomicUsize = AtomicUsize::new(0);
+const SECTION_ROUNDS: usize = 20;
+
+///
+/// Description: Run all file lock tests (requires the name service and the scheduler to be running)
+///
+pub fn run_tests() {
+    info!("file_lock: running tests");
+
+    let r = mkentry("/", "lock_test", vec![0; 16]);
+    assert!(r == Ok(0), "mkentry(\"/lock_test\") -> {:?}", r);
+    let node = stat("/lock_test").unwrap().ino;
+
+    test_shared(node);
+    test_upgrade(node);
+    test_release_all(node);
+    test_hard_link(node);
+    test_mutual_exclusion(node);
+
+    let r = del("/lock_test");
+    assert!(r == Ok(0), "del(\"/lock_test\") -> {:?}", r);
+
+    info!("file_lock: all tests passed.");
+}
+
+/// Description: Shared locks coexist, an exclusive lock is refused while any of them is held
+fn test_shared(node: usize) {
+    assert_eq!(lock(node, HOLDER_A, LockType::Shared, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_B, LockType::Shared, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_C, LockType::Exclusive, false), Err(Errno::EAGAIN));
+
+    assert_eq!(lock(node, HOLDER_A, LockType::Unlock, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_C, LockType::Exclusive, false), Err(Errno::EAGAIN));
+    assert_eq!(lock(node, HOLDER_B, LockType::Unlock, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_C, LockType::Exclusive, false), Ok(()));
+
+    // Exclusive lock -> neither shared nor exclusive locks of others
+    assert_eq!(lock(node, HOLDER_A, LockType::Shared, false), Err(Errno::EAGAIN));
+    assert_eq!(lock(node, HOLDER_B, LockType::Exclusive, false), Err(Errno::EAGAIN));
+    assert_eq!(lock(node, HOLDER_C, LockType::Unlock, false), Ok(()));
+
+    // Unlocking without holding a lock is no error
+    assert_eq!(lock(node, HOLDER_C, LockType::Unlock, false), Ok(()));
+}
+
+/// Description: The only holder of a shared lock can upgrade it to an exclusive lock (and downgrade it again)
+fn test_upgrade(node: usize) {
+    assert_eq!(lock(node, HOLDER_A, LockType::Shared, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_A, LockType::Exclusive, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_B, LockType::Shared, false), Err(Errno::EAGAIN));
+
+    assert_eq!(lock(node, HOLDER_A, LockType::Shared, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_B, LockType::Shared, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_A, LockType::Exclusive, false), Err(Errno::EAGAIN));
+
+    assert_eq!(lock(node, HOLDER_A, LockType::Unlock, false), Ok(()));
+    assert_eq!(lock(node, HOLDER_B, LockType::Unlock, false), Ok(()));
+}
+
+/// Description: All locks of an exiting process are released
+fn test_release_all(node: usize) {
+    assert_eq!(lock(node, HOLDER_A, LockType::Exclusive, false), Ok(()));
+    release_all(HOLDER_A);
+    assert_eq!(lock(node, HOLDER_B, LockType::Exclusive, false), Ok(()));
+    release_all(HOLDER_B);
+}
+
+/// Description: A lock applies to the container, not to the name (all hard links share it)
+fn test_hard_link(node: usize) {
+    let r = link("/lock_test", "/lock_test2");
+    assert!(r == Ok(0), "link(\"/lock_test\", \"/lock_test2\") -> {:?}", r);
+    let linked = stat("/lock_test2").unwrap().ino;
+    assert_eq!(linked, node);
+
+    assert_eq!(lock(node, HOLDER_A, LockType::Exclusive, false), Ok(()));
+    assert_eq!(lock(linked, HOLDER_B, LockType::Exclusive, false), Err(Errno::EAGAIN));
+    release_all(HOLDER_A);
+
+    let r = del("/lock_test2");
+    assert!(r == Ok(0), "del(\"/lock_test2\") -> {:?}", r);
+}
+
+/// Description: Two holders contending for an exclusive lock (blocking) are never inside the critical section at the same time
+fn test_mutual_exclusion(node: usize) {
+    NODE.store(node, SeqCst);
+    IN_SECTION.store(0, SeqCst);
+
+    let threads = [Thread::new_kernel_thread(|| contend(HOLDER_A)), Thread::new_kernel_thread(|| contend(HOLDER_B))];
+    let ids = threads.iter().map(|thread| thread.id()).collect::<Vec<usize>>();
+    threads.into_iter().for_each(|thread| scheduler().ready(thread));
+    ids.iter().for_each(|id| { scheduler().join(*id); });
+
+    assert_eq!(IN_SECTION.load(SeqCst), 0);
+    assert_eq!(lock(node, HOLDER_C, LockType::Exclusive, false), Ok(()), "lock not released after contention");
+    release_all(HOLDER_C);
+}
+
+/// Description: Enter the critical section `SECTION_ROUNDS` times, protected by an exclusive lock of `holder`
+fn contend(holder: usize) {
+    let node = NODE.load(SeqCst);
+    for _ in 0..SECTION_ROUNDS {
+        lock(node, holder, LockType::Exclusive, true).unwrap();
+
+        let inside = IN_SECTION.fetch_add(1, SeqCst);
+        assert!(inside == 0, "holder {} entered critical section, while {} other holder(s) inside", holder, inside);
+        scheduler().sleep(2); // let the other holder try to enter
+        IN_SECTION.fetch_sub(1, SeqCst);
+
+        lock(node, holder, LockType::Unlock, false).unwrap();
+        scheduler().yield_thread();
+    }
+    scheduler().exit(0);
+}
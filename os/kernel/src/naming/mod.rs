@@ -1,5 +1,10 @@
 pub mod stat;
+pub mod path;
 pub mod name_service;
+pub mod file_lock;
+pub mod file_lock_tests;
+pub mod watch;
+pub mod watch_tests;
 mod name_service_internal;
 pub mod name_service_tests;
 
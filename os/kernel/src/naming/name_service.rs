@@ -6,17 +6,27 @@
    ║ Author: Michael Schoettner, 30.8.2024, HHU                              ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use syscall::return_vals::{SyscallResult,Errno};
 use ::log::info;
 
+use crate::naming::file_lock;
+use crate::naming::file_lock::LockType;
 use crate::naming::name_service_internal;
 use crate::naming::name_service_internal::get_root_dir;
-use crate::naming::stat::Stat;
+use crate::naming::path::{canonicalize, MAX_PATH_LENGTH};
+use crate::naming::stat::{Stat, DEFAULT_CONT_PERMISSIONS, DEFAULT_DIR_PERMISSIONS, PERMISSION_MASK};
+use crate::naming::watch;
+use crate::process::credentials::Credentials;
+use crate::process_manager;
 
 pub type Result<T> = ::core::result::Result<T, Errno>;
 
+/// Maximum number of symbolic links followed while resolving a path
+pub const MAX_LINK_HOPS: usize = 40;
+
 
 
 // Wrapper function to convert Result<(), Errno> to SyscallResult
@@ -29,7 +39,8 @@ fn convert_result(result: core::result::Result<(), Errno>) -> SyscallResult {
 
 ///
 /// Description:
-///    Add an entry (with or without data)
+///    Add an entry (with or without data). The permissions are `DEFAULT_CONT_PERMISSIONS`
+///    without the bits of the creation mask of the current process.
 ///
 /// Parameters: \
 ///   `path` path (must exist) \
@@ -37,19 +48,78 @@ fn convert_result(result: core::result::Result<(), Errno>) -> SyscallResult {
 ///   `content` data bytes
 ///
 pub fn mkentry(path: &str, name: &str, content: Vec<u8>) -> SyscallResult {
-    convert_result(get_root_dir().mkentry(path, name, content))
+    let path = resolve(path, true)?;
+    let permissions = process_manager().read().current_process().creation_permissions(DEFAULT_CONT_PERMISSIONS);
+    get_root_dir().mkentry(&path, name, content, permissions)?;
+
+    watch::created(&child_path(&path, name));
+    Ok(0)
 }
 
+/// Type of the entries created by `create()` (duplicated in 'library/naming/src/lib.rs')
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File = 0,
+    Directory = 1,
+}
+
+///
+/// Description:
+///    Create the empty container or the directory `path`. The parent directory of a container
+///    must exist, missing parents of a directory are created (see `mkdir()`).
+///
+/// Parameters: \
+///   `path` absolute path or path relative to the current working directory \
+///   `kind` type of the new entry
+///
+/// Return: `Errno::EINVAL`, if `path` is empty or contains a NUL character, or `Errno::EEXIST`,
+///         if the entry exists already
+///
+pub fn create(path: &str, kind: EntryKind) -> SyscallResult {
+    if path.is_empty() || path.contains('\0') {
+        return Err(Errno::EINVAL);
+    }
+
+    match kind {
+        EntryKind::Directory => mkdir(path),
+        EntryKind::File => {
+            let path = absolute_path(path)?;
+            let (parent, name) = path.rsplit_once('/').unwrap();
+            if name.is_empty() {
+                return Err(Errno::EEXIST); // root directory
+            }
+
+            mkentry(if parent.is_empty() { "/" } else { parent }, name, Vec::new())
+        }
+    }
+}
 
 ///
 /// Description:
-///    Add a directory. Creates all sub directories for the given path (if they do not exist already)
+///    Add a directory. Creates all sub directories for the given path (if they do not exist already).
+///    The permissions are `DEFAULT_DIR_PERMISSIONS` without the bits of the creation mask of the
+///    current process.
 ///
 /// Parameters: \
 ///   `path` path to be created
 ///
 pub fn mkdir(path: &str) -> SyscallResult {
-    convert_result(get_root_dir().mkdir(path))
+    let path = resolve(path, false)?;
+
+    // Remember the missing directories, which are created below
+    let mut missing = Vec::new();
+    let mut prefix = String::new();
+    for part in path.split('/').filter(|s| !s.is_empty()) {
+        prefix = prefix + "/" + part;
+        if get_root_dir().stat(&prefix).is_err() {
+            missing.push(prefix.clone());
+        }
+    }
+
+    let permissions = process_manager().read().current_process().creation_permissions(DEFAULT_DIR_PERMISSIONS);
+    get_root_dir().mkdir(&path, permissions)?;
+    missing.iter().for_each(|dir| watch::created(dir));
+    Ok(0)
 }
 
 ///
@@ -60,7 +130,18 @@ pub fn mkdir(path: &str) -> SyscallResult {
 ///   `path` path to entry
 ///
 pub fn stat(path: &str) -> Result<Stat> {
-    get_root_dir().stat(path)
+    get_root_dir().stat(&resolve(path, true)?)
+}
+
+///
+/// Description:
+///    Get `stat` info for the given entry, without following a link as last component
+///
+/// Parameters: \
+///   `path` path to entry
+///
+pub fn lstat(path: &str) -> Result<Stat> {
+    get_root_dir().stat(&resolve(path, false)?)
 }
 
 ///
@@ -71,7 +152,7 @@ pub fn stat(path: &str) -> Result<Stat> {
 ///   `path` path to entry
 ///
 pub fn cont(path: &str) -> Result<Vec<u8>> {
-    get_root_dir().cont(path)
+    get_root_dir().cont(&resolve(path, true)?)
 }
 
 ///
@@ -82,7 +163,7 @@ pub fn cont(path: &str) -> Result<Vec<u8>> {
 ///   `path` path to directory
 ///
 pub fn dir(path: &str) -> Result<Vec<Stat>> {
-    get_root_dir().dir(path)
+    get_root_dir().dir(&resolve(path, true)?)
 }
 
 ///
@@ -94,7 +175,11 @@ pub fn dir(path: &str) -> Result<Vec<Stat>> {
 ///   `new_name` new name
 ///
 pub fn rename(path: &str, new_name: &str) -> SyscallResult {
-    convert_result(get_root_dir().rename(path, new_name))
+    let path = resolve(path, false)?;
+    get_root_dir().rename(&path, new_name)?;
+
+    watch::renamed(&path, new_name);
+    Ok(0)
 }
 
 ///
@@ -105,7 +190,304 @@ pub fn rename(path: &str, new_name: &str) -> SyscallResult {
 ///   `path`  path&entry name
 ///
 pub fn del(path: &str) -> SyscallResult {
-    convert_result(get_root_dir().del(path))
+    let path = resolve(path, false)?;
+    get_root_dir().del(&path)?;
+
+    watch::deleted(&path);
+    Ok(0)
+}
+
+///
+/// Description:
+///    Change the working directory of the current process.
+///
+/// Parameters: \
+///   `path` absolute path or path relative to the current working directory
+///
+pub fn chdir(path: &str) -> SyscallResult {
+    let path = resolve(path, true)?;
+    if path != "/" && !get_root_dir().stat(&path)?.mode.is_directory() {
+        return Err(Errno::ENOTDIR);
+    }
+
+    process_manager().read().current_process().set_cwd(path);
+    Ok(0)
+}
+
+///
+/// Description:
+///    Set the creation mask of the current process and return the previous one. Permission
+///    bits set in the mask are cleared in the permissions of new entries (see `Process::umask()`).
+///
+/// Parameters: \
+///   `mask` new creation mask (bits outside of `PERMISSION_MASK` are ignored)
+///
+pub fn set_umask(mask: u32) -> u32 {
+    process_manager().read().current_process().set_umask(mask)
+}
+
+///
+/// Description:
+///    Set the permission bits of an entry (returned by `stat()`). A symbolic link is followed.
+///    Only the owner of the entry and root may change the permissions.
+///
+/// Parameters: \
+///   `path` path to entry \
+///   `permissions` new permission bits
+///
+/// Return: `Errno::EINVAL`, if bits outside of `PERMISSION_MASK` are set or `Errno::EACCES`,
+///         if the current process is not allowed to change the permissions
+///
+pub fn chmod(path: &str, permissions: u32) -> SyscallResult {
+    chmod_as(process_manager().read().current_process().credentials(), path, permissions)
+}
+
+/// Description: Like `chmod()`, but with the permission check for the given `credentials`
+pub fn chmod_as(credentials: Credentials, path: &str, permissions: u32) -> SyscallResult {
+    if permissions & !PERMISSION_MASK != 0 {
+        return Err(Errno::EINVAL);
+    }
+
+    let path = resolve(path, true)?;
+    credentials.may_chmod(get_root_dir().stat(&path)?.uid)?;
+
+    get_root_dir().chmod(&path, permissions)?;
+    Ok(0)
+}
+
+///
+/// Description:
+///    Set the owning user and group of an entry (returned by `stat()`). A symbolic link is followed.
+///    Only root may change the owner.
+///
+/// Parameters: \
+///   `path` path to entry \
+///   `uid` new user id \
+///   `gid` new group id
+///
+/// Return: `Errno::EACCES`, if the current process is not allowed to change the owner
+///
+pub fn chown(path: &str, uid: u32, gid: u32) -> SyscallResult {
+    chown_as(process_manager().read().current_process().credentials(), path, uid, gid)
+}
+
+/// Description: Like `chown()`, but with the permission check for the given `credentials`
+pub fn chown_as(credentials: Credentials, path: &str, uid: u32, gid: u32) -> SyscallResult {
+    let path = resolve(path, true)?;
+    get_root_dir().stat(&path)?; // missing entries are reported before missing permissions
+    credentials.may_chown()?;
+
+    get_root_dir().chown(&path, uid, gid)?;
+    Ok(0)
+}
+
+///
+/// Description:
+///    Create the symbolic link `link_path` pointing to `target`. The target is stored as given
+///    (it need not exist), a relative target is resolved relative to the directory of the link.
+///
+/// Parameters: \
+///   `target` path the link points to \
+///   `link_path` path of the new link (parent directory must exist)
+///
+pub fn symlink(target: &str, link_path: &str) -> SyscallResult {
+    if target.is_empty() || target.contains('\0') {
+        return Err(Errno::EINVAL);
+    }
+    if target.len() > MAX_PATH_LENGTH {
+        return Err(Errno::ENAMETOOLONG);
+    }
+
+    let link_path = absolute_path(link_path)?;
+    let (parent, name) = link_path.rsplit_once('/').unwrap();
+    if name.is_empty() {
+        return Err(Errno::EEXIST); // root directory
+    }
+
+    let parent = resolve_links(String::from(parent), true)?;
+    get_root_dir().symlink(&parent, name, target)?;
+
+    watch::created(&child_path(&parent, name));
+    Ok(0)
+}
+
+///
+/// Description:
+///    Create the hard link `new_path` for the container `existing`. Both names refer to the
+///    same content, which is only freed, after all links have been deleted. A symbolic link
+///    as `existing` is followed.
+///
+/// Parameters: \
+///   `existing` path to an existing container \
+///   `new_path` path of the new link (parent directory must exist)
+///
+/// Return: `Errno::EISDIR`, if `existing` is a directory
+///
+pub fn link(existing: &str, new_path: &str) -> SyscallResult {
+    let existing = resolve(existing, true)?;
+
+    let new_path = absolute_path(new_path)?;
+    let (parent, name) = new_path.rsplit_once('/').unwrap();
+    if name.is_empty() {
+        return Err(Errno::EEXIST); // root directory
+    }
+
+    let parent = resolve_links(String::from(parent), true)?;
+    get_root_dir().link(&existing, &parent, name)?;
+
+    watch::created(&child_path(&parent, name));
+    Ok(0)
+}
+
+///
+/// Description:
+///    Get the target of a symbolic link (the link itself is not followed)
+///
+/// Parameters: \
+///   `path` path to link
+///
+/// Return: target of the link or `Errno::EINVAL`, if the entry is not a link
+///
+pub fn readlink(path: &str) -> Result<String> {
+    get_root_dir().readlink(&resolve(path, false)?)?.ok_or(Errno::EINVAL)
+}
+
+///
+/// Description:
+///    Set or release an advisory lock of the current process on a container (see `file_lock`).
+///    A symbolic link is followed.
+///
+/// Parameters: \
+///   `path` path to container \
+///   `lock_type` shared or exclusive lock or unlock \
+///   `blocking` wait until the lock is available (otherwise fail with `Errno::EAGAIN`)
+///
+/// Return: `Errno::EISDIR`, if `path` is a directory
+///
+pub fn flock(path: &str, lock_type: LockType, blocking: bool) -> SyscallResult {
+    let stat = stat(path)?;
+    if !stat.mode.is_container() {
+        return Err(Errno::EISDIR);
+    }
+
+    let holder = process_manager().read().current_process().id();
+    convert_result(file_lock::lock(stat.ino, holder, lock_type, blocking))
+}
+
+///
+/// Description:
+///    Watch the entry `path` for events (see `watch`). A symbolic link is followed. A directory
+///    reports events of its children, any other entry events of itself.
+///
+/// Parameters: \
+///   `path` path to entry \
+///   `mask` events to be reported (see `watch::ALL_EVENTS`)
+///
+/// Return: id of the watch for `watch_read()`
+///
+pub fn watch_path(path: &str, mask: u32) -> SyscallResult {
+    let path = resolve(path, true)?;
+    let directory = path == "/" || get_root_dir().stat(&path)?.mode.is_directory();
+
+    let owner = process_manager().read().current_process().id();
+    watch::add(&path, directory, owner, mask)
+}
+
+///
+/// Description:
+///    Take the queued events of the watch `id` of the current process (without blocking)
+///
+/// Parameters: \
+///   `id` id returned by `watch_path()` \
+///   `max_size` maximum serialized size of the returned events (see `WatchEvent::serialize()`)
+///
+/// Return: the events or `Errno::EAGAIN`, if there are none
+///
+pub fn watch_read(id: usize, max_size: usize) -> Result<Vec<watch::WatchEvent>> {
+    let owner = process_manager().read().current_process().id();
+    watch::read(id, owner, max_size)
+}
+
+/// Description: Remove the watch `id` of the current process
+pub fn watch_remove(id: usize) -> SyscallResult {
+    let owner = process_manager().read().current_process().id();
+    convert_result(watch::remove(id, owner))
+}
+
+/// Description: Path of the entry `name` in the canonical directory `dir`
+fn child_path(dir: &str, name: &str) -> String {
+    if dir == "/" { String::from("/") + name } else { String::from(dir) + "/" + name }
+}
+
+/// Description: Canonicalize `path` and follow all links in it (the last component only if `follow_last` is set)
+fn resolve(path: &str, follow_last: bool) -> Result<String> {
+    resolve_links(absolute_path(path)?, follow_last)
+}
+
+///
+/// Description:
+///    Replace symbolic links in the canonical `path` by their targets, component by component.
+///    Missing components are kept, so that the following lookup fails with `Errno::ENOENT`
+///    (e.g. for dangling links).
+///
+/// Parameters: \
+///   `path` canonical path \
+///   `follow_last` follow a link as last component
+///
+/// Return: the resolved path or `Errno::ELOOP`, if more than `MAX_LINK_HOPS` links are followed
+///
+fn resolve_links(mut path: String, follow_last: bool) -> Result<String> {
+    let mut hops = 0;
+
+    'restart: loop {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        for i in 0..parts.len() {
+            let last = i == parts.len() - 1;
+            let prefix = parts[..=i].iter().fold(String::new(), |prefix, part| prefix + "/" + *part);
+
+            match get_root_dir().readlink(&prefix) {
+                Ok(Some(target)) if !last || follow_last => {
+                    hops += 1;
+                    if hops > MAX_LINK_HOPS {
+                        return Err(Errno::ELOOP);
+                    }
+
+                    // Replace the link by its target and start again (the target may contain links itself)
+                    let parent = parts[..i].iter().fold(String::new(), |parent, part| parent + "/" + *part);
+                    let rest = parts[i + 1..].join("/");
+                    let target = if rest.is_empty() { target } else { target + "/" + &rest };
+
+                    path = canonicalize(&target, if parent.is_empty() { "/" } else { &parent })?;
+                    continue 'restart;
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        return Ok(path);
+    }
+}
+
+/// Description: Canonicalize `path` against the working directory of the current process
+fn absolute_path(path: &str) -> Result<String> {
+    if path.starts_with('/') {
+        canonicalize(path, "/")
+    } else {
+        canonicalize(path, &process_manager().read().current_process().cwd())
+    }
+}
+
+///
+/// Description:
+///    Check that the name space is consistent before shutting down, after all other processes
+///    have exited or have been killed. The name space is kept in memory only, so no data needs
+///    to be written back, but a killed thread may have left an operation unfinished.
+///
+/// Return: `false`, if a directory is still locked by an unfinished operation
+///
+pub fn sync() -> bool {
+    get_root_dir().sync()
 }
 
 ///
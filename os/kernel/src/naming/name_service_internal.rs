@@ -13,11 +13,13 @@ use crate::naming::stat::Stat;
 use crate::naming::name_service::Result;
 
 use alloc::boxed::Box;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::fmt::Debug;
 use core::mem;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
 
 use spin::{Once, RwLock};
 
@@ -54,6 +56,8 @@ struct DirectoryInner {
     entries: Vec<DirEntry>,
 }
 
+/// Directory entry: the name of an entry in its directory. Containers are separate nodes,
+/// which may be referenced by several entries (hard links), their link count is kept in the node.
 #[derive(Debug, Clone)]
 pub struct DirEntry {
     entry_type: EntryType,
@@ -62,15 +66,32 @@ pub struct DirEntry {
 
 #[derive(Debug, Clone)]
 enum EntryType {
-    Container(Container),
+    Container(Arc<Container>),
     Directory(Box<Directory>),
+    Link(String), // target path (as given, relative targets are relative to the directory of the link)
 }
 
-#[derive(Debug, Clone)]
+/// Container node, shared by all its hard links. The content is freed with the last
+/// reference, i.e. after the last link has been deleted and nobody is reading it anymore.
+#[derive(Debug)]
 struct Container {
+    ino: usize, // unique node number (never reused)
     content: Vec<u8>,
+    nlink: AtomicUsize, // number of directory entries referencing this container
+    meta: RwLock<Metadata>, // shared by all links (overrides the fields in the `stat` of the entries)
 }
 
+/// Permissions and owner of an entry (changed by `chmod()` and `chown()`)
+#[derive(Debug, Clone, Copy)]
+struct Metadata {
+    mode: Mode,
+    uid: u32,
+    gid: u32,
+}
+
+/// Node number of the next container (0 is used for entries without a node)
+static NEXT_INO: AtomicUsize = AtomicUsize::new(1);
+
 impl Directory {
     fn new() -> Self {
         Directory(RwLock::new(DirectoryInner {
@@ -81,13 +102,13 @@ impl Directory {
     ///
     /// Create directory (with all sub directories)
     ///  
-    pub(super) fn mkdir(&self, path: &str) -> Result<()> {
+    pub(super) fn mkdir(&self, path: &str, permissions: u32) -> Result<()> {
         let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-        self.mkdir_in_dir(&parts)
+        self.mkdir_in_dir(&parts, permissions)
     }
 
     // Helper function to recursively add sub directories as needed
-    fn mkdir_in_dir(&self, parts: &[&str]) -> Result<()> {
+    fn mkdir_in_dir(&self, parts: &[&str], permissions: u32) -> Result<()> {
         let mut dir = self.0.write();
 
         // If no more path parts, we did not find the file
@@ -105,7 +126,7 @@ impl Directory {
                     if remaining_parts.is_empty() {
                         return Err(Errno::EEXIST); // we are done, dir already exists
                     } else {
-                        return dir.mkdir_in_dir(remaining_parts); // continue recursively
+                        return dir.mkdir_in_dir(remaining_parts, permissions); // continue recursively
                     }
                 } else {
                     // found file with same name, abort
@@ -118,14 +139,14 @@ impl Directory {
 
         let new_entry = DirEntry::new_directory(Stat::new(
             current_part.to_string(),
-            Mode::new(stat::MODE_DIR),
+            Mode::with_permissions(stat::MODE_DIR, permissions),
             0,
         ));
         dir.entries.push(new_entry);
 
         // Retrieve the newly created directory
         if let EntryType::Directory(ref mut new_dir) = dir.entries.last_mut().unwrap().entry_type {
-            return new_dir.mkdir_in_dir(remaining_parts);
+            return new_dir.mkdir_in_dir(remaining_parts, permissions);
         } else {
             return Err(Errno::EEXIST); // This should not happen
         }
@@ -134,11 +155,11 @@ impl Directory {
     ///
     /// Register a new entry in the given `path`
     ///  
-    pub(super) fn mkentry(&self, path: &str, name: &str, content: Vec<u8>) -> Result<()> {
+    pub(super) fn mkentry(&self, path: &str, name: &str, content: Vec<u8>, permissions: u32) -> Result<()> {
         let element_size = mem::size_of::<u8>();
         let total_size = element_size * content.len();
 
-        let stat = Stat::new(name.to_string(), Mode::new(stat::MODE_CONT), total_size);
+        let stat = Stat::new(name.to_string(), Mode::with_permissions(stat::MODE_CONT, permissions), total_size);
 
         let new_entry = DirEntry::new_file(stat, content);
         let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
@@ -175,13 +196,110 @@ impl Directory {
         return Err(Errno::ENOENT);
     }
 
+    ///
+    /// Register a new symbolic link `name` pointing to `target` in the given `path`
+    ///
+    pub(super) fn symlink(&self, path: &str, name: &str, target: &str) -> Result<()> {
+        let stat = Stat::new(name.to_string(), Mode::with_permissions(stat::MODE_LINK, stat::LINK_PERMISSIONS), target.len());
+
+        let new_entry = DirEntry::new_link(stat, target.to_string());
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        self.mkentry_in_dir(&parts, new_entry)
+    }
+
+    ///
+    /// Add the hard link `name` in the given `path` to the container `existing`
+    ///
+    pub(super) fn link(&self, existing: &str, path: &str, name: &str) -> Result<()> {
+        let parts: Vec<&str> = existing.split('/').filter(|s| !s.is_empty()).collect();
+        let dentry = self.get_dentry(&parts)?;
+        let container = match dentry.entry_type {
+            EntryType::Container(container) => container,
+            EntryType::Directory(_) => return Err(Errno::EISDIR), // directories cannot have several links
+            EntryType::Link(_) => return Err(Errno::EINVAL), // links are followed by the name service
+        };
+
+        let stat = Stat::new(name.to_string(), dentry.stat.mode, dentry.stat.size);
+        let new_entry = DirEntry { entry_type: EntryType::Container(Arc::clone(&container)), stat };
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        self.mkentry_in_dir(&parts, new_entry)?;
+
+        container.nlink.fetch_add(1, Relaxed);
+        Ok(())
+    }
+
+    ///
+    /// Retrieve the target of a symbolic link (`None`, if the entry is not a link)
+    ///
+    pub(super) fn readlink(&self, path: &str) -> Result<Option<String>> {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        match self.get_dentry(&parts)?.entry_type {
+            EntryType::Link(target) => Ok(Some(target)),
+            _ => Ok(None),
+        }
+    }
+
+    ///
+    /// Set the permission bits of the given entry (the type of the entry is kept)
+    ///
+    pub(super) fn chmod(&self, path: &str, permissions: u32) -> Result<()> {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        self.update_metadata(&parts, &|meta: &mut Metadata| meta.mode = Mode::with_permissions(meta.mode.entry_type(), permissions))
+    }
+
+    ///
+    /// Set the owning user and group of the given entry
+    ///
+    pub(super) fn chown(&self, path: &str, uid: u32, gid: u32) -> Result<()> {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        self.update_metadata(&parts, &|meta: &mut Metadata| {
+            meta.uid = uid;
+            meta.gid = gid;
+        })
+    }
+
+    /// Recursive helper function for looking up an entry and changing its metadata, if found
+    fn update_metadata(&self, parts: &[&str], update: &dyn Fn(&mut Metadata)) -> Result<()> {
+        let mut dir = self.0.write();
+
+        // If no more path parts, we did not find the entry
+        if parts.is_empty() {
+            return Err(Errno::ENOENT);
+        }
+
+        // Recusively navigate to the right sub directory
+        let current_part = parts[0];
+        let remaining_parts = &parts[1..];
+        for entry in &mut dir.entries {
+            if entry.stat.name == current_part {
+                if remaining_parts.is_empty() {
+                    match entry.entry_type {
+                        // Containers keep their metadata in the node (shared by all links)
+                        EntryType::Container(ref container) => update(&mut container.meta.write()),
+                        _ => {
+                            let mut meta = Metadata { mode: entry.stat.mode, uid: entry.stat.uid, gid: entry.stat.gid };
+                            update(&mut meta);
+                            (entry.stat.mode, entry.stat.uid, entry.stat.gid) = (meta.mode, meta.uid, meta.gid);
+                        }
+                    }
+                    return Ok(());
+                } else if let EntryType::Directory(ref directory) = entry.entry_type {
+                    return directory.update_metadata(remaining_parts, update);
+                } else {
+                    return Err(Errno::ENOENT);
+                }
+            }
+        }
+        return Err(Errno::ENOENT);
+    }
+
     ///
     /// Retrieve `stat` info for a given entry
     ///
     pub(super) fn stat(&self, path: &str) -> Result<Stat> {
         let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
         match self.get_dentry(&parts) {
-            Ok(dentry) => Ok(dentry.stat),
+            Ok(dentry) => Ok(dentry.stat()),
             Err(e) => Err(e),
         }
     }
@@ -218,7 +336,7 @@ impl Directory {
                     // yes, return the content
                     let mut ret: Vec<Stat> = Vec::new();
                     for entry in &dentry.0.read().entries {
-                        ret.push(entry.stat.clone());
+                        ret.push(entry.stat());
                     }
                     return Ok(ret);
                 } else {
@@ -329,9 +447,12 @@ impl Directory {
         let remaining_parts = &parts[1..];
         let mut dir = self.0.write();
 
-        // Last part -> remove entry and return
+        // Last part -> remove entry and return (the content of a container is freed with its last link)
         if remaining_parts.is_empty() {
-            dir.entries.retain(|entry| entry.stat.name != current_part);
+            if let Some(index) = dir.entries.iter().position(|entry| entry.stat.name == current_part)
+                && let EntryType::Container(ref container) = dir.entries.remove(index).entry_type {
+                container.nlink.fetch_sub(1, Relaxed);
+            }
             return Ok(());
         }
         // Recusively navigate to the right sub directory
@@ -353,18 +474,35 @@ impl Directory {
     ///
     /// Debug function dumping full naming service content
     ///  
+    ///
+    /// Check that no operation on this directory and its sub directories is in progress
+    /// (an operation holds the lock of each directory it changes). Does not wait for the locks.
+    ///
+    pub(super) fn sync(&self) -> bool {
+        match self.0.try_write() {
+            Some(dir) => dir.entries.iter().all(|entry| match &entry.entry_type {
+                EntryType::Directory(sub_dir) => sub_dir.sync(),
+                _ => true,
+            }),
+            None => false,
+        }
+    }
+
     pub(super) fn dump(&self, depth: usize) {
         let indent = "   ".repeat(depth);
         for entry in &self.0.read().entries {
             match &entry.entry_type {
                 EntryType::Container(_file) => {
-                    println!("{}[F] {}, {:?}", indent, entry.stat.name, entry.stat);
+                    println!("{}[F] {}, {:?}", indent, entry.stat.name, entry.stat());
                     //println!("{}  [F]: {}, size: {}", indent, file.name, entry.stat.size)
                 }
                 EntryType::Directory(dir) => {
                     println!("{}[D] {}", indent, entry.stat.name);
                     dir.dump(depth + 1);
                 }
+                EntryType::Link(target) => {
+                    println!("{}[L] {} -> {}", indent, entry.stat.name, target);
+                }
             }
         }
     }
@@ -381,9 +519,33 @@ impl Clone for Directory {
 
 impl DirEntry {
     fn new_file(stat: Stat, content: Vec<u8>) -> Self {
-        let c = content.clone();
         DirEntry {
-            entry_type: EntryType::Container(Container { content: c }),
+            entry_type: EntryType::Container(Arc::new(Container {
+                ino: NEXT_INO.fetch_add(1, Relaxed),
+                content,
+                nlink: AtomicUsize::new(1),
+                meta: RwLock::new(Metadata { mode: stat.mode, uid: stat.uid, gid: stat.gid }),
+            })),
+            stat,
+        }
+    }
+
+    /// `stat` info of this entry, including the current link count and metadata of a container
+    fn stat(&self) -> Stat {
+        let mut stat = self.stat.clone();
+        if let EntryType::Container(ref container) = self.entry_type {
+            let meta = *container.meta.read();
+            stat.nlink = container.nlink.load(Relaxed);
+            stat.ino = container.ino;
+            (stat.mode, stat.uid, stat.gid) = (meta.mode, meta.uid, meta.gid);
+        }
+
+        stat
+    }
+
+    fn new_link(stat: Stat, target: String) -> Self {
+        DirEntry {
+            entry_type: EntryType::Link(target),
             stat,
         }
     }
@@ -9,7 +9,10 @@
 use alloc::vec;
 use ::log::info;
 
-use crate::naming::name_service::{cont, del, dir, mkdir, mkentry, rename, stat};
+use crate::naming::name_service::{chdir, chmod, chown, cont, del, dir, link, lstat, mkdir, mkentry, readlink, rename, set_umask, stat, symlink};
+use crate::naming::stat::{DEFAULT_UMASK, MODE_CONT, ROOT_ID};
+use crate::process_manager;
+use crate::naming::path::{canonicalize, MAX_PATH_LENGTH};
 use syscall::return_vals::Errno;
 
 ///
@@ -24,8 +27,14 @@ pub fn run_tests() {
     test_stat();
     test_cont();
     test_dir();
+    test_canonicalize();
+    test_relative();
+    test_symlink();
+    test_link();
     test_del();
     test_rename();
+    test_umask();
+    test_chmod_chown();
 
     info!("name_service: all tests passed.");
 }
@@ -193,6 +202,199 @@ fn test_dir() {
     info!("   test 'dir':     passed");
 }
 
+///
+/// Description:
+///    Testing `canonicalize` (`//`, `.`, `..`, trailing slashes and invalid paths)
+///
+fn test_canonicalize() {
+    let cases = [
+        ("home", "/", "/home"),
+        ("schoettner/./brief.txt", "/home", "/home/schoettner/brief.txt"),
+        ("../ruhland", "/home/schoettner", "/home/ruhland"),
+        ("/tmp//x/", "/home/schoettner", "/tmp/x"),
+        ("/a/./b", "/", "/a/b"),
+        ("../../..", "/home", "/"),
+        ("..", "/", "/"),
+        (".", "/home", "/home"),
+        ("/../home/./ruhland/..", "/home", "/home"),
+    ];
+
+    for (path, cwd, expected) in cases {
+        let r = canonicalize(path, cwd);
+        assert!(r.as_deref() == Ok(expected), "canonicalize(\"{}\", \"{}\") -> {:?}", path, cwd, r);
+    }
+
+    // Invalid paths -> should fail
+    let r = canonicalize("", "/home");
+    assert!(r == Err(Errno::ENOENT), "canonicalize(\"\") -> {:?}", r);
+    let r = canonicalize("/home/a\0b", "/");
+    assert!(r == Err(Errno::EINVAL), "canonicalize(\"/home/a\\0b\") -> {:?}", r);
+    let long = "x".repeat(MAX_PATH_LENGTH + 1);
+    let r = canonicalize(&long, "/");
+    assert!(r == Err(Errno::ENAMETOOLONG), "canonicalize(<{} bytes>) -> {:?}", long.len(), r);
+
+    // Resolution against the working directory may exceed the maximum length
+    let long = "x".repeat(MAX_PATH_LENGTH);
+    let r = canonicalize(&long, "/home");
+    assert!(r == Err(Errno::ENAMETOOLONG), "canonicalize(<{} bytes>, \"/home\") -> {:?}", long.len(), r);
+
+    // Different spellings of the same path name the same entry
+    let r = stat("/home/./schoettner//brief.txt/");
+    assert!(r.is_ok(), "stat(\"/home/./schoettner//brief.txt/\") failed -> {:?}", r);
+
+    info!("   test 'canonicalize': passed");
+}
+
+///
+/// Description:
+///    Testing relative paths with `chdir` (restores `/` as working directory)
+///
+fn test_relative() {
+    // Change into existing directory -> should work
+    let r = chdir("/home/schoettner");
+    assert!(r == Ok(0), "chdir(\"/home/schoettner\") -> {:?}", r);
+
+    // Get container relative to working directory -> should work
+    let pathname = "brief.txt";
+    let r = cont(pathname);
+    assert!(r.is_ok(), "cont(\"{}\") failed -> {:?}", pathname, r);
+
+    let pathname = "../ruhland/klausur.txt";
+    let r = stat(pathname);
+    assert!(r.is_ok(), "stat(\"{}\") failed -> {:?}", pathname, r);
+
+    // Change into container or non-existing directory -> should fail
+    let r = chdir("brief.txt");
+    assert!(r == Err(Errno::ENOTDIR), "chdir(\"brief.txt\") -> {:?}", r);
+    let r = chdir("../krakowski");
+    assert!(r == Err(Errno::ENOENT), "chdir(\"../krakowski\") -> {:?}", r);
+
+    // Change to parent directory relative to working directory -> should work
+    let r = chdir("..");
+    assert!(r == Ok(0), "chdir(\"..\") -> {:?}", r);
+    let pathname = "ruhland/klausur.txt";
+    let r = cont(pathname);
+    assert!(r.is_ok(), "cont(\"{}\") failed -> {:?}", pathname, r);
+
+    let r = chdir("/");
+    assert!(r == Ok(0), "chdir(\"/\") -> {:?}", r);
+
+    info!("   test 'relative': passed");
+}
+
+///
+/// Description:
+///    Testing symbolic links (removes all links and `/links` again)
+///
+fn test_symlink() {
+    let r = mkdir("/links");
+    assert!(r == Ok(0), "mkdir(\"/links\") -> {:?}", r);
+
+    // Link to existing directory -> should be followed by stat & cont
+    let r = symlink("/home/schoettner", "/links/s");
+    assert!(r == Ok(0), "symlink(\"/links/s\") -> {:?}", r);
+    let r = stat("/links/s");
+    assert!(r.as_ref().is_ok_and(|s| s.mode.is_directory()), "stat(\"/links/s\") -> {:?}", r);
+    let r = lstat("/links/s");
+    assert!(r.as_ref().is_ok_and(|s| s.mode.is_link()), "lstat(\"/links/s\") -> {:?}", r);
+    let pathname = "/links/s/brief.txt";
+    let r = cont(pathname);
+    assert!(r.is_ok(), "cont(\"{}\") failed -> {:?}", pathname, r);
+    let r = readlink("/links/s");
+    assert!(r.as_deref() == Ok("/home/schoettner"), "readlink(\"/links/s\") -> {:?}", r);
+
+    // Relative target is resolved relative to the directory of the link
+    let r = symlink("../home/ruhland/klausur.txt", "/links/k");
+    assert!(r == Ok(0), "symlink(\"/links/k\") -> {:?}", r);
+    let r = cont("/links/k");
+    assert!(r.is_ok(), "cont(\"/links/k\") failed -> {:?}", r);
+
+    // Dangling link -> lookup fails, the link itself exists
+    let r = symlink("/nowhere", "/links/d");
+    assert!(r == Ok(0), "symlink(\"/links/d\") -> {:?}", r);
+    let r = stat("/links/d");
+    assert!(matches!(r, Err(Errno::ENOENT)), "stat(\"/links/d\") -> {:?}", r);
+    let r = lstat("/links/d");
+    assert!(r.is_ok(), "lstat(\"/links/d\") failed -> {:?}", r);
+
+    // Links pointing to each other -> should fail with ELOOP
+    let r = symlink("/links/b", "/links/a");
+    assert!(r == Ok(0), "symlink(\"/links/a\") -> {:?}", r);
+    let r = symlink("a", "/links/b");
+    assert!(r == Ok(0), "symlink(\"/links/b\") -> {:?}", r);
+    let r = stat("/links/a");
+    assert!(matches!(r, Err(Errno::ELOOP)), "stat(\"/links/a\") -> {:?}", r);
+
+    // Invalid target, existing link & readlink of a directory -> should fail
+    let r = symlink("", "/links/e");
+    assert!(r == Err(Errno::EINVAL), "symlink(\"\") -> {:?}", r);
+    let r = symlink("/home", "/links/s");
+    assert!(r == Err(Errno::EEXIST), "symlink(\"/links/s\") -> {:?}", r);
+    let r = readlink("/links");
+    assert!(r == Err(Errno::EINVAL), "readlink(\"/links\") -> {:?}", r);
+
+    // Working directory through a link -> should work
+    let r = chdir("/links/s");
+    assert!(r == Ok(0), "chdir(\"/links/s\") -> {:?}", r);
+    let r = cont("brief.txt");
+    assert!(r.is_ok(), "cont(\"brief.txt\") failed -> {:?}", r);
+    let r = chdir("/");
+    assert!(r == Ok(0), "chdir(\"/\") -> {:?}", r);
+
+    // Delete links -> should remove the links only
+    for pathname in ["/links/s", "/links/k", "/links/d", "/links/a", "/links/b", "/links"] {
+        let r = del(pathname);
+        assert!(r == Ok(0), "del(\"{}\") -> {:?}", pathname, r);
+    }
+    let pathname = "/home/schoettner/brief.txt";
+    let r = stat(pathname);
+    assert!(r.is_ok(), "stat(\"{}\") failed -> {:?}", pathname, r);
+
+    info!("   test 'symlink': passed");
+}
+
+///
+/// Description:
+///    Testing hard links (restores `/home/ruhland/klausur.txt` as only link)
+///
+fn test_link() {
+    let original = "/home/ruhland/klausur.txt";
+    let second = "/home/schoettner/klausur.txt";
+    let content = cont(original).unwrap();
+
+    // Link existing container -> both names refer to the same content
+    let r = link(original, second);
+    assert!(r == Ok(0), "link(\"{}\", \"{}\") -> {:?}", original, second, r);
+    for pathname in [original, second] {
+        let r = stat(pathname);
+        assert!(r.as_ref().is_ok_and(|s| s.nlink == 2), "stat(\"{}\") -> {:?}", pathname, r);
+    }
+
+    // Link directory, existing name or non-existing container -> should fail
+    let r = link("/home/ruhland", "/home/ruhland2");
+    assert!(r == Err(Errno::EISDIR), "link(\"/home/ruhland\") -> {:?}", r);
+    let r = link(original, second);
+    assert!(r == Err(Errno::EEXIST), "link(\"{}\", \"{}\") -> {:?}", original, second, r);
+    let r = link("/home/ruhland/klausur2.txt", "/home/klausur2.txt");
+    assert!(r == Err(Errno::ENOENT), "link(\"/home/ruhland/klausur2.txt\") -> {:?}", r);
+
+    // Delete one of two links -> content must still be available via the other one
+    let r = del(original);
+    assert!(r == Ok(0), "del(\"{}\") -> {:?}", original, r);
+    let r = cont(second);
+    assert!(r.as_ref() == Ok(&content), "cont(\"{}\") -> {:?}", second, r);
+    let r = stat(second);
+    assert!(r.as_ref().is_ok_and(|s| s.nlink == 1), "stat(\"{}\") -> {:?}", second, r);
+
+    // Restore original name
+    let r = link(second, original);
+    assert!(r == Ok(0), "link(\"{}\", \"{}\") -> {:?}", second, original, r);
+    let r = del(second);
+    assert!(r == Ok(0), "del(\"{}\") -> {:?}", second, r);
+
+    info!("   test 'link':    passed");
+}
+
 ///
 /// Description:
 ///    Testing `del`
@@ -273,3 +475,89 @@ fn test_rename() {
 
     info!("   test 'rename':  passed");
 }
+
+///
+/// Description:
+///    New entries get the default permissions without the bits of the creation mask
+///
+fn test_umask() {
+    let previous = set_umask(DEFAULT_UMASK);
+    assert_eq!(set_umask(DEFAULT_UMASK), DEFAULT_UMASK);
+
+    // Default mask -> 'rw-r--r--' for containers, 'rwxr-xr-x' for directories
+    assert_eq!(mkdir("/umask/default"), Ok(0));
+    assert_eq!(mkentry("/umask/default", "file", vec![1]), Ok(0));
+    assert_eq!(stat("/umask").unwrap().mode.permissions(), 0o755);
+    assert_eq!(stat("/umask/default").unwrap().mode.permissions(), 0o755);
+    assert_eq!(stat("/umask/default/file").unwrap().mode.permissions(), 0o644);
+
+    // Private mask (bits outside of 0o777 are ignored) -> links are not affected
+    assert_eq!(set_umask(0o7077), DEFAULT_UMASK);
+    assert_eq!(mkdir("/umask/private"), Ok(0));
+    assert_eq!(mkentry("/umask/private", "file", vec![1]), Ok(0));
+    assert_eq!(symlink("file", "/umask/private/link"), Ok(0));
+    assert_eq!(stat("/umask/private").unwrap().mode.permissions(), 0o700);
+    assert_eq!(stat("/umask/private/file").unwrap().mode.permissions(), 0o600);
+    assert_eq!(lstat("/umask/private/link").unwrap().mode.permissions(), 0o777);
+
+    // Explicitly requested permissions are masked as well
+    assert_eq!(set_umask(0o027), 0o077);
+    let process = process_manager().read().current_process();
+    assert_eq!(process.creation_permissions(0o666), 0o640);
+    assert_eq!(process.creation_permissions(0o750), 0o750);
+
+    set_umask(previous);
+    for path in ["/umask/private/link", "/umask/private/file", "/umask/private", "/umask/default/file", "/umask/default", "/umask"] {
+        let r = del(path);
+        assert!(r == Ok(0), "del(\"{}\") -> {:?}", path, r);
+    }
+
+    info!("   test 'umask':  passed");
+}
+
+///
+/// Description:
+///    Permissions and owner changed by `chmod` and `chown` are returned by `stat` (for all
+///    hard links of a container and for the target of a symbolic link)
+///
+fn test_chmod_chown() {
+    assert_eq!(mkdir("/meta"), Ok(0));
+    assert_eq!(mkentry("/meta", "file", vec![1]), Ok(0));
+    assert_eq!(link("/meta/file", "/meta/hard"), Ok(0));
+    assert_eq!(symlink("file", "/meta/link"), Ok(0));
+
+    let s = stat("/meta/file").unwrap();
+    assert_eq!((s.uid, s.gid), (ROOT_ID, ROOT_ID));
+
+    assert_eq!(chmod("/meta/file", 0o640), Ok(0));
+    assert_eq!(chown("/meta/link", 1000, 100), Ok(0));
+    for path in ["/meta/file", "/meta/hard", "/meta/link"] {
+        let s = stat(path).unwrap();
+        assert_eq!(s.mode.permissions(), 0o640, "permissions of \"{}\"", path);
+        assert_eq!(s.mode.entry_type(), MODE_CONT);
+        assert_eq!((s.uid, s.gid), (1000, 100), "owner of \"{}\"", path);
+    }
+
+    // The link itself is not changed
+    let s = lstat("/meta/link").unwrap();
+    assert_eq!((s.mode.permissions(), s.uid), (0o777, ROOT_ID));
+
+    // Directories
+    assert_eq!(chmod("/meta", 0o700), Ok(0));
+    assert_eq!(chown("/meta", 1, 2), Ok(0));
+    let s = stat("/meta").unwrap();
+    assert!(s.mode.is_directory());
+    assert_eq!((s.mode.permissions(), s.uid, s.gid), (0o700, 1, 2));
+
+    // Errors
+    assert_eq!(chmod("/meta/missing", 0o644), Err(Errno::ENOENT));
+    assert_eq!(chown("/missing/file", 0, 0), Err(Errno::ENOENT));
+    assert_eq!(chmod("/meta/file", 0o1644), Err(Errno::EINVAL));
+
+    for path in ["/meta/link", "/meta/hard", "/meta/file", "/meta"] {
+        let r = del(path);
+        assert!(r == Ok(0), "del(\"{}\") -> {:?}", path, r);
+    }
+
+    info!("   test 'chmod/chown':  passed");
+}
@@ -0,0 +1,70 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: path                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Canonicalization of paths, used by all functions of the name    ║
+   ║         service, so that every spelling of a path names the same entry. ║
+   ║         The canonical form is absolute, without '.' and '..' and        ║
+   ║         without empty components (e.g. '/a/b' for 'a//./b/' in '/').    ║
+   ║         Rules:                                                          ║
+   ║           - trailing slashes are ignored ('/a/b/' is '/a/b')            ║
+   ║           - '..' at the root stays at the root                          ║
+   ║           - empty paths, NUL bytes and paths longer than                ║
+   ║             MAX_PATH_LENGTH (before and after resolution) are rejected  ║
+   ║         Canonicalization is purely lexical: '..' removes the previous   ║
+   ║         component. Links are not followed here, they are resolved by    ║
+   ║         the name service afterwards ('name_service::resolve_links()').  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::String;
+use alloc::vec::Vec;
+use syscall::return_vals::Errno;
+
+/// Maximum length of a path in bytes
+pub const MAX_PATH_LENGTH: usize = 4096;
+
+///
+/// Description: Canonicalize `path` (absolute or relative to `cwd`).
+///
+/// Parameters: \
+///   `path` absolute or relative path \
+///   `cwd` canonical working directory, used if `path` is relative
+///
+/// Return: the canonical path, `Errno::ENOENT` for an empty path, `Errno::EINVAL` for
+///         a path containing NUL or `Errno::ENAMETOOLONG`, if the path is too long
+///
+pub fn canonicalize(path: &str, cwd: &str) -> Result<String, Errno> {
+    if path.is_empty() {
+        return Err(Errno::ENOENT);
+    }
+    if path.contains('\0') {
+        return Err(Errno::EINVAL);
+    }
+    if path.len() > MAX_PATH_LENGTH {
+        return Err(Errno::ENAMETOOLONG);
+    }
+
+    let base = if path.starts_with('/') { "" } else { cwd };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in base.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => { parts.pop(); }
+            part => parts.push(part),
+        }
+    }
+
+    let mut canonical = String::new();
+    for part in parts {
+        canonical.push('/');
+        canonical.push_str(part);
+    }
+
+    match canonical.len() {
+        0 => Ok(String::from("/")),
+        len if len > MAX_PATH_LENGTH => Err(Errno::ENAMETOOLONG),
+        _ => Ok(canonical),
+    }
+}
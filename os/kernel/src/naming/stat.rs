@@ -14,6 +14,20 @@ pub const MODE_CONT: u32 = 0b001u32;
 pub const MODE_DIR: u32  = 0b010u32;
 pub const MODE_LINK: u32 = 0b011u32;
 pub const MODE_DEV: u32  = 0b100u32;
+const MODE_TYPE_MASK: u32 = 0b111u32;
+
+/// Permission bits (like 'rwxrwxrwx' in Unix) are stored above the entry type
+const MODE_PERMISSION_SHIFT: u32 = 3;
+pub const PERMISSION_MASK: u32 = 0o777;
+/// Permissions of new containers and directories, before the creation mask of the process is applied
+pub const DEFAULT_CONT_PERMISSIONS: u32 = 0o666;
+pub const DEFAULT_DIR_PERMISSIONS: u32 = 0o777;
+/// Permissions of symbolic links (the creation mask is not applied)
+pub const LINK_PERMISSIONS: u32 = 0o777;
+/// Owner (user and group) of new entries
+pub const ROOT_ID: u32 = 0;
+/// Creation mask of the first process (inherited by all other processes)
+pub const DEFAULT_UMASK: u32 = 0o022;
 
 
 #[derive(Debug, Clone)]
@@ -21,8 +35,12 @@ pub struct Stat {
     pub name: String,
     pub mode: Mode,
     pub size: usize,
+    pub nlink: usize, // number of hard links (directory entries) of a container
+    pub ino: usize, // node number of a container (0 for other entries)
     pub ctime: u64, // creation time
     pub dev_id: u64, // for device files
+    pub uid: u32, // owning user (only stored, permissions are not enforced yet)
+    pub gid: u32, // owning group
 }
 
 impl Stat {
@@ -32,7 +50,11 @@ impl Stat {
             mode,
             dev_id: 0,
             size,
+            nlink: 1,
+            ino: 0,
             ctime: 0,
+            uid: ROOT_ID,
+            gid: ROOT_ID,
         }
     }
     pub fn zeroed() -> Stat {
@@ -41,7 +63,11 @@ impl Stat {
             mode: Mode::new(MODE_CONT),
             dev_id: 0,
             size: 0,
+            nlink: 0,
+            ino: 0,
             ctime: 0,
+            uid: ROOT_ID,
+            gid: ROOT_ID,
         }
     }
 }
@@ -55,20 +81,34 @@ impl Mode {
         Mode(value)
     }
 
+    /// Description: Mode of an entry of type `entry_type` (one of the `MODE_*` constants) with the given permission bits
+    pub fn with_permissions(entry_type: u32, permissions: u32) -> Mode {
+        Mode((entry_type & MODE_TYPE_MASK) | ((permissions & PERMISSION_MASK) << MODE_PERMISSION_SHIFT))
+    }
+
+    /// Description: Permission bits (see `PERMISSION_MASK`)
+    pub fn permissions(self) -> u32 {
+        (self.0 >> MODE_PERMISSION_SHIFT) & PERMISSION_MASK
+    }
+
+    /// Description: Type of the entry (one of the `MODE_*` constants, which are values and not flags)
+    pub fn entry_type(self) -> u32 {
+        self.0 & MODE_TYPE_MASK
+    }
+
     pub fn is_directory(self) -> bool {
-        (self.0 & MODE_DIR) == MODE_DIR
+        self.entry_type() == MODE_DIR
     }
 
     pub fn is_container(self) -> bool {
-        (self.0 & MODE_CONT) == MODE_CONT
+        self.entry_type() == MODE_CONT
     }
 
     pub fn is_link(self) -> bool {
-        (self.0 & MODE_LINK) == MODE_LINK
+        self.entry_type() == MODE_LINK
     }
 
     pub fn is_dev_entry(self) -> bool {
-     (self.0 & MODE_DEV) == MODE_DEV
- }
-
+        self.entry_type() == MODE_DEV
+    }
 }
\ No newline at end of file
@@ -0,0 +1,243 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: watch                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Watches on entries of the name service (like 'inotify'). A      ║
+   ║         watch on a directory reports events for its children (not       ║
+   ║         recursively), a watch on any other entry reports events for the ║
+   ║         entry itself. Both report the deletion of the watched entry.    ║
+   ║         Events are queued per watch and fetched without blocking (there ║
+   ║         are no file descriptors yet, a watch is identified by its id).  ║
+   ║         The queue is bounded, events are dropped, when it is full, and  ║
+   ║         a single `OVERFLOW` event is reported instead.                  ║
+   ║         Watches are registered by path. Renaming a watched entry (or    ║
+   ║         one of its parent directories) moves the watch along, deleting  ║
+   ║         it leaves the watch behind (it does not report events anymore). ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use spin::Mutex;
+use syscall::return_vals::Errno;
+
+// Event types (also used as mask bits for selecting events)
+pub const CREATED: u32 = 1 << 0;
+/// Reserved: the name service has no operation changing a container yet
+pub const MODIFIED: u32 = 1 << 1;
+pub const DELETED: u32 = 1 << 2;
+pub const RENAMED: u32 = 1 << 3;
+pub const ALL_EVENTS: u32 = CREATED | MODIFIED | DELETED | RENAMED;
+/// Events have been dropped (reported regardless of the mask)
+pub const OVERFLOW: u32 = 1 << 31;
+
+/// Maximum number of queued events per watch
+pub const MAX_QUEUED_EVENTS: usize = 64;
+
+/// Size of the header of a serialized event (see `WatchEvent::serialize()`)
+pub const EVENT_HEADER_SIZE: usize = 3 * size_of::<u32>();
+
+static NEXT_WATCH_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// All active watches
+static WATCHES: Mutex<Vec<Watch>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub kind: u32,
+    pub name: String,     // name of the child (empty for the watched entry itself)
+    pub new_name: String, // new name (only for `RENAMED`)
+}
+
+impl WatchEvent {
+    fn new(kind: u32, name: &str, new_name: &str) -> Self {
+        Self { kind, name: name.to_string(), new_name: new_name.to_string() }
+    }
+
+    /// Description: Size of the serialized event (in bytes)
+    pub fn size(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name.len() + self.new_name.len()
+    }
+
+    ///
+    /// Description:
+    ///    Append the event to `buf` as `kind`, length of `name` and length of `new_name`
+    ///    (each as native endian `u32`), followed by the bytes of both names.
+    ///
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.kind.to_ne_bytes());
+        buf.extend_from_slice(&(self.name.len() as u32).to_ne_bytes());
+        buf.extend_from_slice(&(self.new_name.len() as u32).to_ne_bytes());
+        buf.extend_from_slice(self.name.as_bytes());
+        buf.extend_from_slice(self.new_name.as_bytes());
+    }
+}
+
+struct Watch {
+    id: usize,
+    owner: usize,
+    path: String,    // canonical path without links
+    directory: bool, // report events of the children
+    mask: u32,
+    events: VecDeque<WatchEvent>,
+    overflow: bool,
+}
+
+impl Watch {
+    fn push(&mut self, event: WatchEvent) {
+        if self.mask & event.kind == 0 {
+            return;
+        }
+
+        if self.events.len() < MAX_QUEUED_EVENTS {
+            self.events.push_back(event);
+        } else {
+            self.overflow = true;
+        }
+    }
+}
+
+///
+/// Description:
+///    Add a watch for process `owner`.
+///
+/// Parameters: \
+///    `path` canonical path (without links) of an existing entry \
+///    `directory` `path` is a directory (report events of its children) \
+///    `owner` id of the watching process \
+///    `mask` events to be reported (see `ALL_EVENTS`)
+///
+/// Return: id of the new watch or `Errno::EINVAL`, if `mask` selects no (known) event
+///
+pub fn add(path: &str, directory: bool, owner: usize, mask: u32) -> Result<usize, Errno> {
+    if mask == 0 || mask & !ALL_EVENTS != 0 {
+        return Err(Errno::EINVAL);
+    }
+
+    let id = NEXT_WATCH_ID.fetch_add(1, Relaxed);
+    WATCHES.lock().push(Watch {
+        id,
+        owner,
+        path: path.to_string(),
+        directory,
+        mask,
+        events: VecDeque::new(),
+        overflow: false,
+    });
+
+    Ok(id)
+}
+
+/// Description: Remove the watch `id` of process `owner`
+pub fn remove(id: usize, owner: usize) -> Result<(), Errno> {
+    let mut watches = WATCHES.lock();
+    match watches.iter().position(|watch| watch.id == id && watch.owner == owner) {
+        Some(index) => {
+            watches.swap_remove(index);
+            Ok(())
+        }
+        None => Err(Errno::EINVAL),
+    }
+}
+
+/// Description: Remove all watches of process `owner` (called, when the process exits)
+pub fn remove_all(owner: usize) {
+    WATCHES.lock().retain(|watch| watch.owner != owner);
+}
+
+///
+/// Description:
+///    Take queued events of the watch `id` of process `owner`, as long as their serialized size
+///    fits into `max_size` bytes. A pending overflow is reported as last event.
+///
+/// Return: the events (at least one) or `Errno::EAGAIN`, if there are none, \
+///         `Errno::EINVAL`, if `max_size` is too small for the next event or `id` is unknown
+///
+pub fn read(id: usize, owner: usize, max_size: usize) -> Result<Vec<WatchEvent>, Errno> {
+    let mut watches = WATCHES.lock();
+    let watch = watches.iter_mut().find(|watch| watch.id == id && watch.owner == owner).ok_or(Errno::EINVAL)?;
+
+    let mut events = Vec::new();
+    let mut size = 0;
+    while let Some(event) = watch.events.front() {
+        if size + event.size() > max_size {
+            break;
+        }
+        size += event.size();
+        events.push(watch.events.pop_front().unwrap());
+    }
+
+    // The overflow is reported after all events queued before it
+    if watch.overflow && watch.events.is_empty() && size + EVENT_HEADER_SIZE <= max_size {
+        watch.overflow = false;
+        events.push(WatchEvent::new(OVERFLOW, "", ""));
+    }
+
+    if !events.is_empty() {
+        Ok(events)
+    } else if watch.events.is_empty() && !watch.overflow {
+        Err(Errno::EAGAIN)
+    } else {
+        Err(Errno::EINVAL)
+    }
+}
+
+/// Description: Report the creation of the entry with canonical `path` (called by the name service)
+pub fn created(path: &str) {
+    emit(path, WatchEvent::new(CREATED, name(path), ""), false);
+}
+
+/// Description: Report the deletion of the entry with canonical `path` (called by the name service)
+pub fn deleted(path: &str) {
+    emit(path, WatchEvent::new(DELETED, name(path), ""), true);
+}
+
+/// Description: Report renaming the entry with canonical `path` to `new_name` (called by the name service)
+pub fn renamed(path: &str, new_name: &str) {
+    emit(path, WatchEvent::new(RENAMED, name(path), new_name), true);
+
+    // Watches on the entry or below it follow the new name
+    let new_path = String::from(parent(path)) + "/" + new_name;
+    for watch in WATCHES.lock().iter_mut() {
+        if watch.path == path {
+            watch.path = new_path.clone();
+        } else if let Some(rest) = watch.path.strip_prefix(path).filter(|rest| rest.starts_with('/')) {
+            watch.path = new_path.clone() + rest;
+        }
+    }
+}
+
+///
+/// Description:
+///    Queue `event` for all watches on the parent directory of `path` and (if `self_event` is set)
+///    for all watches on `path` itself (with empty names, unless renamed).
+///
+fn emit(path: &str, event: WatchEvent, self_event: bool) {
+    let parent = parent(path);
+
+    for watch in WATCHES.lock().iter_mut() {
+        if watch.directory && watch.path == parent {
+            watch.push(event.clone());
+        } else if self_event && watch.path == path {
+            let name = if event.kind == RENAMED { event.name.as_str() } else { "" };
+            watch.push(WatchEvent::new(event.kind, name, &event.new_name));
+        }
+    }
+}
+
+/// Description: Parent directory of the canonical `path` ("/" for entries in the root directory)
+fn parent(path: &str) -> &str {
+    match path.rsplit_once('/') {
+        Some(("", _)) | None => "/",
+        Some((parent, _)) => parent,
+    }
+}
+
+/// Description: Last component of the canonical `path`
+fn name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
@@ -0,0 +1,186 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: watch_tests                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test watches on entries of the name service. Processes are      ║
+   ║         simulated by owner ids, which are not used by real processes.   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use ::log::info;
+use syscall::return_vals::Errno;
+
+use crate::naming::name_service::{del, link, mkdir, mkentry, rename, symlink};
+use crate::naming::watch;
+use crate::naming::watch::{WatchEvent, ALL_EVENTS, CREATED, DELETED, EVENT_HEADER_SIZE, MAX_QUEUED_EVENTS, OVERFLOW, RENAMED};
+
+const OWNER: usize = usize::MAX - 1;
+const OTHER_OWNER: usize = usize::MAX - 2;
+
+///
+/// Description: Run all watch tests (requires the name service to be running)
+///
+pub fn run_tests() {
+    info!("watch: running tests");
+
+    let r = mkdir("/watch_test");
+    assert!(r == Ok(0), "mkdir(\"/watch_test\") -> {:?}", r);
+
+    test_directory();
+    test_container();
+    test_mask();
+    test_overflow();
+    test_read_size();
+    test_follow_rename();
+    test_owner();
+
+    let r = del("/watch_test");
+    assert!(r == Ok(0), "del(\"/watch_test\") -> {:?}", r);
+
+    info!("watch: all tests passed.");
+}
+
+/// Description: Take all queued events of the watch `id` of `OWNER`
+fn read_all(id: usize) -> Vec<WatchEvent> {
+    watch::read(id, OWNER, usize::MAX).unwrap_or_default()
+}
+
+fn event(kind: u32, name: &str, new_name: &str) -> WatchEvent {
+    WatchEvent { kind, name: String::from(name), new_name: String::from(new_name) }
+}
+
+/// Description: A watch on a directory reports creating, renaming and deleting its children
+fn test_directory() {
+    let id = watch::add("/watch_test", true, OWNER, ALL_EVENTS).unwrap();
+    assert_eq!(watch::read(id, OWNER, usize::MAX), Err(Errno::EAGAIN));
+
+    mkentry("/watch_test", "a", vec![0; 4]).unwrap();
+    symlink("a", "/watch_test/b").unwrap();
+    link("/watch_test/a", "/watch_test/c").unwrap();
+    mkdir("/watch_test/d/e").unwrap(); // "e" is no child
+    rename("/watch_test/a", "f").unwrap();
+    del("/watch_test/f").unwrap();
+    del("/watch_test/b").unwrap();
+    del("/watch_test/c").unwrap();
+    del("/watch_test/d/e").unwrap();
+    del("/watch_test/d").unwrap();
+
+    assert_eq!(read_all(id), vec![
+        event(CREATED, "a", ""),
+        event(CREATED, "b", ""),
+        event(CREATED, "c", ""),
+        event(CREATED, "d", ""),
+        event(RENAMED, "a", "f"),
+        event(DELETED, "f", ""),
+        event(DELETED, "b", ""),
+        event(DELETED, "c", ""),
+        event(DELETED, "d", ""),
+    ]);
+    assert_eq!(watch::read(id, OWNER, usize::MAX), Err(Errno::EAGAIN));
+
+    // Failed operations report nothing
+    assert!(del("/watch_test/missing").is_err());
+    assert_eq!(watch::read(id, OWNER, usize::MAX), Err(Errno::EAGAIN));
+
+    watch::remove(id, OWNER).unwrap();
+    assert_eq!(watch::read(id, OWNER, usize::MAX), Err(Errno::EINVAL));
+}
+
+/// Description: A watch on a container reports only events of the container itself
+fn test_container() {
+    mkentry("/watch_test", "file", vec![0; 4]).unwrap();
+    let id = watch::add("/watch_test/file", false, OWNER, ALL_EVENTS).unwrap();
+
+    mkentry("/watch_test", "other", vec![0; 4]).unwrap();
+    del("/watch_test/other").unwrap();
+    rename("/watch_test/file", "renamed").unwrap();
+    del("/watch_test/renamed").unwrap();
+
+    assert_eq!(read_all(id), vec![event(RENAMED, "file", "renamed"), event(DELETED, "", "")]);
+    watch::remove(id, OWNER).unwrap();
+}
+
+/// Description: Only events selected by the mask are queued; an empty or unknown mask is rejected
+fn test_mask() {
+    assert_eq!(watch::add("/watch_test", true, OWNER, 0), Err(Errno::EINVAL));
+    assert_eq!(watch::add("/watch_test", true, OWNER, OVERFLOW), Err(Errno::EINVAL));
+
+    let id = watch::add("/watch_test", true, OWNER, DELETED).unwrap();
+    mkentry("/watch_test", "a", vec![0; 4]).unwrap();
+    del("/watch_test/a").unwrap();
+
+    assert_eq!(read_all(id), vec![event(DELETED, "a", "")]);
+    watch::remove(id, OWNER).unwrap();
+}
+
+/// Description: Events beyond `MAX_QUEUED_EVENTS` are dropped and reported by a single overflow event
+fn test_overflow() {
+    let id = watch::add("/watch_test", true, OWNER, CREATED).unwrap();
+    let count = MAX_QUEUED_EVENTS + 10;
+    for i in 0..count {
+        mkentry("/watch_test", &format!("{}", i), vec![0; 1]).unwrap();
+    }
+
+    let events = read_all(id);
+    assert_eq!(events.len(), MAX_QUEUED_EVENTS + 1);
+    assert_eq!(events[0], event(CREATED, "0", ""));
+    assert_eq!(events[MAX_QUEUED_EVENTS - 1].name, format!("{}", MAX_QUEUED_EVENTS - 1));
+    assert_eq!(events[MAX_QUEUED_EVENTS].kind, OVERFLOW);
+    assert_eq!(watch::read(id, OWNER, usize::MAX), Err(Errno::EAGAIN));
+
+    for i in 0..count {
+        del(&format!("/watch_test/{}", i)).unwrap();
+    }
+    watch::remove(id, OWNER).unwrap();
+}
+
+/// Description: Reading returns only as many events, as fit into the given size
+fn test_read_size() {
+    let id = watch::add("/watch_test", true, OWNER, CREATED).unwrap();
+    mkentry("/watch_test", "ab", vec![0; 1]).unwrap();
+    mkentry("/watch_test", "cd", vec![0; 1]).unwrap();
+
+    let size = EVENT_HEADER_SIZE + 2;
+    assert_eq!(watch::read(id, OWNER, size - 1), Err(Errno::EINVAL));
+    assert_eq!(watch::read(id, OWNER, size + 1), Ok(vec![event(CREATED, "ab", "")]));
+    assert_eq!(watch::read(id, OWNER, size), Ok(vec![event(CREATED, "cd", "")]));
+
+    let mut bytes = Vec::new();
+    event(RENAMED, "ab", "xyz").serialize(&mut bytes);
+    assert_eq!(bytes.len(), event(RENAMED, "ab", "xyz").size());
+    assert_eq!(&bytes[EVENT_HEADER_SIZE..], b"abxyz");
+
+    del("/watch_test/ab").unwrap();
+    del("/watch_test/cd").unwrap();
+    watch::remove(id, OWNER).unwrap();
+}
+
+/// Description: Renaming a watched directory (or its parent) moves the watch along
+fn test_follow_rename() {
+    mkdir("/watch_test/p/q").unwrap();
+    let id = watch::add("/watch_test/p/q", true, OWNER, CREATED).unwrap();
+
+    rename("/watch_test/p", "r").unwrap();
+    rename("/watch_test/r/q", "s").unwrap();
+    mkentry("/watch_test/r/s", "a", vec![0; 1]).unwrap();
+    assert_eq!(read_all(id), vec![event(CREATED, "a", "")]);
+
+    del("/watch_test/r/s/a").unwrap();
+    del("/watch_test/r/s").unwrap();
+    del("/watch_test/r").unwrap();
+    watch::remove(id, OWNER).unwrap();
+}
+
+/// Description: Watches belong to their owner and are removed, when it exits
+fn test_owner() {
+    let id = watch::add("/watch_test", true, OWNER, CREATED).unwrap();
+    assert_eq!(watch::read(id, OTHER_OWNER, usize::MAX), Err(Errno::EINVAL));
+    assert_eq!(watch::remove(id, OTHER_OWNER), Err(Errno::EINVAL));
+
+    watch::remove_all(OWNER);
+    assert_eq!(watch::read(id, OWNER, usize::MAX), Err(Errno::EINVAL));
+}
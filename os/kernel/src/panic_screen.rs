@@ -0,0 +1,109 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: panic_screen                                                    ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Diagnostic output of the panic handler: the panic message, a    ║
+   ║         register snapshot and a frame pointer backtrace (the kernel is  ║
+   ║         built with frame pointers, see 'd3os_kernel.json').             ║
+   ║         Nothing here allocates memory (a panic may be caused by OOM).   ║
+   ║         The backtrace is bounded and only follows frames, which are     ║
+   ║         mapped and lie above the previous one, so a corrupt stack does  ║
+   ║         not cause a loop or a page fault.                               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::arch::asm;
+use core::panic::PanicInfo;
+use x86_64::registers::control::{Cr2, Cr3};
+use x86_64::VirtAddr;
+use crate::memory::r#virtual::translate_current;
+
+/// Maximum number of frames printed in a backtrace
+pub const MAX_FRAMES: usize = 32;
+
+/// White on red, clear screen
+const PANIC_COLORS: &str = "\x1b[97;41m\x1b[2J";
+
+#[derive(Clone, Copy)]
+pub struct Registers {
+    pub rip: u64,
+    pub rsp: u64,
+    pub rbp: u64,
+    pub cr2: u64,
+    pub cr3: u64,
+}
+
+impl Registers {
+    /// Description: Capture the registers of the caller (inlined, so `rip`, `rsp` and `rbp` belong to the caller)
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let (rip, rsp, rbp): (u64, u64, u64);
+        unsafe { asm!("lea {}, [rip]", "mov {}, rsp", "mov {}, rbp", out(reg) rip, out(reg) rsp, out(reg) rbp, options(nomem, nostack, preserves_flags)) };
+
+        Self { rip, rsp, rbp, cr2: Cr2::read_raw(), cr3: Cr3::read().0.start_address().as_u64() }
+    }
+}
+
+///
+/// Description:
+///    Walk the frame pointer chain starting at `rbp` and call `f` with each return address.
+///    Stops after `MAX_FRAMES` frames or at the first frame, that is not plausible.
+///
+/// Parameters: \
+///    `rbp` frame pointer of the first frame \
+///    `f` called for each return address
+///
+pub fn backtrace(mut rbp: u64, mut f: impl FnMut(u64)) {
+    for _ in 0..MAX_FRAMES {
+        // A frame consists of the saved frame pointer, followed by the return address
+        if rbp == 0 || !rbp.is_multiple_of(8) || !is_mapped(rbp) || !is_mapped(rbp + 15) {
+            return;
+        }
+
+        let frame = rbp as *const u64;
+        let (next_rbp, return_address) = unsafe { (frame.read(), frame.add(1).read()) };
+        if return_address == 0 {
+            return;
+        }
+        f(return_address);
+
+        // The stack grows downwards, so the next frame must be above the current one
+        if next_rbp <= rbp {
+            return;
+        }
+        rbp = next_rbp;
+    }
+}
+
+///
+/// Description: Clear the terminal to a red background and print `info`, `registers` and a backtrace.
+///
+pub fn show(info: &PanicInfo, registers: &Registers) {
+    print!("{}", PANIC_COLORS);
+    println!("*** KERNEL PANIC ***");
+    println!("");
+    match info.location() {
+        Some(location) => println!("Panic at {}:{}:{}", location.file(), location.line(), location.column()),
+        None => println!("Panic at unknown location"),
+    }
+    println!("{}", info.message());
+    println!("");
+
+    println!("RIP: 0x{:016x}  RSP: 0x{:016x}  RBP: 0x{:016x}", registers.rip, registers.rsp, registers.rbp);
+    println!("CR2: 0x{:016x}  CR3: 0x{:016x}", registers.cr2, registers.cr3);
+    println!("");
+
+    println!("Backtrace:");
+    let mut depth = 0;
+    backtrace(registers.rbp, |address| {
+        println!("  #{:<2} 0x{:016x}", depth, address);
+        depth += 1;
+    });
+    if depth == MAX_FRAMES {
+        println!("  ... (truncated after {} frames)", MAX_FRAMES);
+    }
+}
+
+fn is_mapped(addr: u64) -> bool {
+    VirtAddr::try_new(addr).is_ok_and(|addr| translate_current(addr).is_some())
+}
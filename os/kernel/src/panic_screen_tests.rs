@@ -0,0 +1,78 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: panic_screen_tests                                              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the frame pointer walk on fabricated stack frames.         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use crate::panic_screen::{backtrace, MAX_FRAMES};
+
+/// Number of fabricated frames (more than `MAX_FRAMES`)
+const FRAME_COUNT: usize = MAX_FRAMES + 8;
+
+///
+/// Description: Run all backtrace tests
+///
+pub fn run_tests() {
+    info!("panic_screen: running tests");
+
+    test_chain();
+    test_depth_bounded();
+    test_loop();
+
+    info!("panic_screen: all tests passed.");
+}
+
+/// Description: Link `count` frames in `stack` (saved rbp, return address), return the address of the first one
+fn build_frames(stack: &mut [u64; 2 * FRAME_COUNT], count: usize) -> u64 {
+    let base = stack.as_ptr() as u64;
+    for i in 0..count {
+        let next = if i + 1 < count { base + 16 * (i as u64 + 1) } else { 0 };
+        stack[2 * i] = next;
+        stack[2 * i + 1] = 0x1000 + i as u64;
+    }
+
+    base
+}
+
+/// Description: All return addresses of a short chain are reported in order
+fn test_chain() {
+    let mut stack = [0u64; 2 * FRAME_COUNT];
+    let rbp = build_frames(&mut stack, 3);
+
+    let mut addresses = [0u64; 3];
+    let mut count = 0;
+    backtrace(rbp, |address| {
+        addresses[count] = address;
+        count += 1;
+    });
+
+    assert_eq!(count, 3);
+    assert_eq!(addresses, [0x1000, 0x1001, 0x1002]);
+}
+
+/// Description: A long chain is cut off after `MAX_FRAMES` frames
+fn test_depth_bounded() {
+    let mut stack = [0u64; 2 * FRAME_COUNT];
+    let rbp = build_frames(&mut stack, FRAME_COUNT);
+
+    let mut count = 0;
+    backtrace(rbp, |_| count += 1);
+    assert_eq!(count, MAX_FRAMES);
+}
+
+/// Description: A frame pointing to itself (corrupt stack) ends the walk
+fn test_loop() {
+    let mut stack = [0u64; 2 * FRAME_COUNT];
+    let rbp = build_frames(&mut stack, 2);
+
+    // The walk reads the stack through `rbp`, so it must be written through a pointer (not the local array)
+    let stack_ptr = stack.as_mut_ptr();
+    unsafe { stack_ptr.write(rbp) };
+
+    let mut count = 0;
+    backtrace(rbp, |_| count += 1);
+    assert_eq!(count, 1);
+}
@@ -0,0 +1,219 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: auth                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Credential store and authentication of users. The store is the  ║
+   ║         container '/etc/shadow' (only accessible by root) with one line ║
+   ║         'name:uid:gid:salt:hash' per user. Salt and hash are hex        ║
+   ║         encoded, the hash is PBKDF2-HMAC-SHA256 over the password. The  ║
+   ║         store is created during booting with the user 'root' (without   ║
+   ║         password), if it does not exist.                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::arch::x86_64::_rdtsc;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering::Relaxed;
+use spin::Mutex;
+use syscall::return_vals::Errno;
+
+use crate::naming::name_service;
+use crate::process::credentials::Credentials;
+use crate::process::sha256::{pbkdf2_sha256, Sha256, DIGEST_SIZE};
+use crate::timer;
+
+pub const SHADOW_DIRECTORY: &str = "/etc";
+pub const SHADOW_NAME: &str = "shadow";
+pub const SHADOW_PATH: &str = "/etc/shadow";
+pub const SHADOW_PERMISSIONS: u32 = 0o600;
+
+pub const SALT_SIZE: usize = 16;
+/// Iterations of PBKDF2 (makes guessing passwords expensive)
+pub const HASH_ROUNDS: usize = 1000;
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// Serializes all accesses to the store (it is replaced as a whole, when it is changed)
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+/// Mixed into the salt, so that salts generated at the same time differ
+static SALT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowEntry {
+    pub name: String,
+    pub credentials: Credentials,
+    salt: [u8; SALT_SIZE],
+    hash: [u8; DIGEST_SIZE],
+}
+
+impl ShadowEntry {
+    /// Description: Entry for the user `name` with a new random salt
+    pub fn new(name: &str, password: &str, credentials: Credentials) -> Self {
+        ShadowEntry::with_salt(name, password, credentials, new_salt())
+    }
+
+    pub fn with_salt(name: &str, password: &str, credentials: Credentials, salt: [u8; SALT_SIZE]) -> Self {
+        Self { name: name.to_string(), credentials, salt, hash: pbkdf2_sha256(password.as_bytes(), &salt, HASH_ROUNDS) }
+    }
+
+    ///
+    /// Description:
+    ///    Check `password` against the stored hash. The hash is always computed and compared
+    ///    completely, so the time does not depend on where a wrong password differs.
+    ///
+    pub fn verify(&self, password: &str) -> bool {
+        let hash = pbkdf2_sha256(password.as_bytes(), &self.salt, HASH_ROUNDS);
+        hash.iter().zip(self.hash.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    /// Description: Parse a line of the store (`None`, if it is malformed)
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let uid = fields.next()?.parse::<u32>().ok()?;
+        let gid = fields.next()?.parse::<u32>().ok()?;
+        let salt = decode_hex::<SALT_SIZE>(fields.next()?)?;
+        let hash = decode_hex::<DIGEST_SIZE>(fields.next()?)?;
+        if fields.next().is_some() || !valid_name(name) {
+            return None;
+        }
+
+        Some(Self { name: name.to_string(), credentials: Credentials::new(uid, gid), salt, hash })
+    }
+
+    /// Description: Line of the store (without newline)
+    pub fn to_line(&self) -> String {
+        format!("{}:{}:{}:{}:{}", self.name, self.credentials.uid, self.credentials.gid, encode_hex(&self.salt), encode_hex(&self.hash))
+    }
+}
+
+///
+/// Description:
+///    Create the store with the user 'root' (empty password), if it does not exist
+///    (called once during booting, after the name service has been initialized).
+///
+pub fn init() {
+    let _lock = STORE_LOCK.lock();
+    if name_service::stat(SHADOW_PATH).is_ok() {
+        return;
+    }
+
+    let root = ShadowEntry::new("root", "", Credentials::ROOT);
+    write_store(&[root]).expect("Failed to create credential store");
+}
+
+///
+/// Description:
+///    Check the password of a user. Unknown users and wrong passwords are handled the same
+///    way (including computing a hash), so the result does not reveal which users exist.
+///
+/// Return: the credentials of the user or `Errno::EACCES`
+///
+pub fn authenticate(name: &str, password: &str) -> Result<Credentials, Errno> {
+    let entries = {
+        let _lock = STORE_LOCK.lock();
+        read_store()?
+    };
+
+    let (entry, known) = match entries.into_iter().find(|entry| entry.name == name) {
+        Some(entry) => (entry, true),
+        None => (ShadowEntry { name: String::new(), credentials: Credentials::ROOT, salt: [0; SALT_SIZE], hash: [0; DIGEST_SIZE] }, false),
+    };
+
+    match entry.verify(password) && known {
+        true => Ok(entry.credentials),
+        false => Err(Errno::EACCES),
+    }
+}
+
+///
+/// Description: Add a user to the store
+///
+/// Return: `Errno::EEXIST`, if the user exists or `Errno::EINVAL`, if the name is invalid
+///         (empty, longer than `MAX_NAME_LENGTH` or containing other characters than
+///         letters, digits, '_', '-' and '.')
+///
+pub fn add_user(name: &str, password: &str, credentials: Credentials) -> Result<(), Errno> {
+    if !valid_name(name) {
+        return Err(Errno::EINVAL);
+    }
+
+    let _lock = STORE_LOCK.lock();
+    let mut entries = read_store()?;
+    if entries.iter().any(|entry| entry.name == name) {
+        return Err(Errno::EEXIST);
+    }
+
+    entries.push(ShadowEntry::new(name, password, credentials));
+    write_store(&entries)
+}
+
+/// Description: Remove a user from the store (`Errno::ENOENT`, if there is no such user)
+pub fn remove_user(name: &str) -> Result<(), Errno> {
+    let _lock = STORE_LOCK.lock();
+    let mut entries = read_store()?;
+    let count = entries.len();
+    entries.retain(|entry| entry.name != name);
+    if entries.len() == count {
+        return Err(Errno::ENOENT);
+    }
+
+    write_store(&entries)
+}
+
+/// Description: Read all entries of the store (malformed lines are skipped)
+fn read_store() -> Result<Vec<ShadowEntry>, Errno> {
+    let content = name_service::cont(SHADOW_PATH)?;
+    let content = core::str::from_utf8(&content).map_err(|_| Errno::EIO)?;
+
+    Ok(content.lines().filter_map(ShadowEntry::parse).collect())
+}
+
+/// Description: Replace the store by `entries` (owned by root, only accessible by root)
+fn write_store(entries: &[ShadowEntry]) -> Result<(), Errno> {
+    let content = entries.iter().map(|entry| entry.to_line() + "\n").collect::<String>();
+
+    name_service::mkdir(SHADOW_DIRECTORY).or_else(|errno| if errno == Errno::EEXIST { Ok(0) } else { Err(errno) })?;
+    match name_service::del(SHADOW_PATH) {
+        Ok(_) | Err(Errno::ENOENT) => {}
+        Err(errno) => return Err(errno),
+    }
+
+    name_service::mkentry(SHADOW_DIRECTORY, SHADOW_NAME, content.into_bytes())?;
+    name_service::chown_as(Credentials::ROOT, SHADOW_PATH, Credentials::ROOT.uid, Credentials::ROOT.gid)?;
+    name_service::chmod_as(Credentials::ROOT, SHADOW_PATH, SHADOW_PERMISSIONS)?;
+    Ok(())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_NAME_LENGTH && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Description: Salt from the time stamp counter, the system time and a counter (unique, not secret)
+fn new_salt() -> [u8; SALT_SIZE] {
+    let mut sha = Sha256::new();
+    sha.update(&unsafe { _rdtsc() }.to_le_bytes());
+    sha.update(&(timer().systime_ns() as u64).to_le_bytes());
+    sha.update(&SALT_COUNTER.fetch_add(1, Relaxed).to_le_bytes());
+
+    sha.finish()[..SALT_SIZE].try_into().unwrap()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != 2 * N || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+
+    Some(bytes)
+}
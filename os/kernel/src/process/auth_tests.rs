@@ -0,0 +1,103 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: auth_tests                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the hash functions (with published test vectors), entries  ║
+   ║         of the credential store and authentication of users.            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::return_vals::Errno;
+
+use crate::naming::name_service::stat;
+use crate::process::auth::{add_user, authenticate, remove_user, ShadowEntry, SALT_SIZE, SHADOW_PATH, SHADOW_PERMISSIONS};
+use crate::process::credentials::Credentials;
+use crate::process::sha256::{hmac_sha256, pbkdf2_sha256, sha256, DIGEST_SIZE};
+
+const USER: Credentials = Credentials::new(1000, 100);
+
+///
+/// Description:
+///    Run all auth tests (requires the name service and the credential store)
+///
+pub fn run_tests() {
+    info!("auth: running tests");
+
+    test_sha256();
+    test_hmac_pbkdf2();
+    test_entry();
+    test_authenticate();
+    test_store_permissions();
+
+    info!("auth: all tests passed.");
+}
+
+fn hex(s: &str) -> [u8; DIGEST_SIZE] {
+    let mut bytes = [0u8; DIGEST_SIZE];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+    }
+
+    bytes
+}
+
+/// Description: FIPS 180-2 test vectors (including a message spanning two blocks)
+fn test_sha256() {
+    assert_eq!(sha256(b"abc"), hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+    assert_eq!(sha256(b""), hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+    assert_eq!(sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+               hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"));
+}
+
+/// Description: RFC 4231 (HMAC) and RFC 7914 style (PBKDF2-HMAC-SHA256) test vectors
+fn test_hmac_pbkdf2() {
+    assert_eq!(hmac_sha256(&[0x0b; 20], b"Hi There"), hex("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"));
+    assert_eq!(pbkdf2_sha256(b"password", b"salt", 1), hex("120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"));
+    assert_eq!(pbkdf2_sha256(b"password", b"salt", 2), hex("ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"));
+}
+
+/// Description: Entries are written and parsed without loss, malformed lines are rejected
+fn test_entry() {
+    let entry = ShadowEntry::with_salt("alice", "secret", USER, [7; SALT_SIZE]);
+    assert!(entry.verify("secret"));
+    assert!(!entry.verify("Secret"));
+    assert!(!entry.verify(""));
+
+    let line = entry.to_line();
+    assert!(line.starts_with("alice:1000:100:0707"));
+    assert_eq!(ShadowEntry::parse(&line), Some(entry.clone()));
+
+    // Different salts give different hashes for the same password
+    assert_ne!(ShadowEntry::with_salt("alice", "secret", USER, [8; SALT_SIZE]).to_line(), line);
+
+    assert_eq!(ShadowEntry::parse("alice:1000:100"), None);
+    assert_eq!(ShadowEntry::parse(&(line.clone() + ":extra")), None);
+    assert_eq!(ShadowEntry::parse(&line.replace("1000", "x")), None);
+    assert_eq!(ShadowEntry::parse(&line[..line.len() - 2]), None);
+}
+
+/// Description: Unknown users and wrong passwords give the same error
+fn test_authenticate() {
+    assert_eq!(add_user("auth_test", "pass word", USER), Ok(()));
+    assert_eq!(add_user("auth_test", "other", USER), Err(Errno::EEXIST));
+    assert_eq!(add_user("", "pass", USER), Err(Errno::EINVAL));
+    assert_eq!(add_user("bad:name", "pass", USER), Err(Errno::EINVAL));
+
+    assert_eq!(authenticate("auth_test", "pass word"), Ok(USER));
+    assert_eq!(authenticate("auth_test", "pass"), Err(Errno::EACCES));
+    assert_eq!(authenticate("auth_unknown", "pass word"), Err(Errno::EACCES));
+    assert_eq!(authenticate("root", ""), Ok(Credentials::ROOT));
+
+    assert_eq!(remove_user("auth_test"), Ok(()));
+    assert_eq!(remove_user("auth_test"), Err(Errno::ENOENT));
+    assert_eq!(authenticate("auth_test", "pass word"), Err(Errno::EACCES));
+}
+
+/// Description: The store is only accessible by root
+fn test_store_permissions() {
+    let stat = stat(SHADOW_PATH).unwrap();
+    assert_eq!(stat.uid, Credentials::ROOT.uid);
+    assert_eq!(stat.gid, Credentials::ROOT.gid);
+    assert_eq!(stat.mode.permissions(), SHADOW_PERMISSIONS);
+}
@@ -0,0 +1,86 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: credentials                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: User and group of a process. The first process runs as root     ║
+   ║         (uid 0), all other processes inherit the credentials of the     ║
+   ║         creating process. There is no login yet, so the user can only   ║
+   ║         be changed by `set_uid`, which is restricted to root.           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use syscall::return_vals::Errno;
+use crate::naming::stat::ROOT_ID;
+use crate::process::scheduler::DEFAULT_PRIORITY;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Credentials {
+    /// Credentials of the first process
+    pub const ROOT: Credentials = Credentials { uid: ROOT_ID, gid: ROOT_ID };
+
+    pub const fn new(uid: u32, gid: u32) -> Self {
+        Self { uid, gid }
+    }
+
+    pub fn is_root(self) -> bool {
+        self.uid == ROOT_ID
+    }
+
+    ///
+    /// Description:
+    ///    Change the user id. Only root may switch to an arbitrary user, other users may only
+    ///    "change" to their own user id. The group id is kept.
+    ///
+    /// Return: `Errno::EACCES`, if the change is not allowed
+    ///
+    pub fn set_uid(&mut self, uid: u32) -> Result<(), Errno> {
+        if !self.is_root() && uid != self.uid {
+            return Err(Errno::EACCES);
+        }
+
+        self.uid = uid;
+        Ok(())
+    }
+
+    /// Description: Check if the permissions of an entry owned by `owner` may be changed (by its owner or root)
+    pub fn may_chmod(self, owner: u32) -> Result<(), Errno> {
+        match self.is_root() || self.uid == owner {
+            true => Ok(()),
+            false => Err(Errno::EACCES),
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Check if a thread may get the scheduling priority `priority`. Only root may raise a thread
+    ///    above `DEFAULT_PRIORITY`: a busy thread with a higher priority would starve the shell and
+    ///    all kernel threads (lower priorities never preempt higher ones).
+    ///
+    pub fn may_set_priority(self, priority: u8) -> Result<(), Errno> {
+        match self.is_root() || priority <= DEFAULT_PRIORITY {
+            true => Ok(()),
+            false => Err(Errno::EACCES),
+        }
+    }
+
+    /// Description: Check if the system may be powered off or rebooted (only by root)
+    pub fn may_shut_down(self) -> Result<(), Errno> {
+        match self.is_root() {
+            true => Ok(()),
+            false => Err(Errno::EACCES),
+        }
+    }
+
+    /// Description: Check if the owner of an entry may be changed (only by root)
+    pub fn may_chown(self) -> Result<(), Errno> {
+        match self.is_root() {
+            true => Ok(()),
+            false => Err(Errno::EACCES),
+        }
+    }
+}
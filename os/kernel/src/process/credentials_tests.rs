@@ -0,0 +1,101 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: credentials_tests                                               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test changing the user id and the permission checks of the      ║
+   ║         name service for root and other users. The checks are done      ║
+   ║         with explicit credentials, so the current process stays root.   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec;
+use ::log::info;
+use syscall::return_vals::Errno;
+
+use crate::naming::name_service::{chmod_as, chown_as, del, mkdir, mkentry, stat};
+use crate::process::credentials::Credentials;
+use crate::process::scheduler::{DEFAULT_PRIORITY, MAX_PRIORITY};
+use crate::process_manager;
+
+const USER: Credentials = Credentials::new(1000, 100);
+
+///
+/// Description:
+///    Run all credentials tests (requires the name service to be running)
+///
+pub fn run_tests() {
+    info!("credentials: running tests");
+
+    test_first_process();
+    test_set_uid();
+    test_privileged_operations();
+    test_priority();
+    test_shut_down();
+
+    info!("credentials: all tests passed.");
+}
+
+/// Description: The kernel process runs as root
+fn test_first_process() {
+    let kernel_process = process_manager().read().kernel_process().unwrap();
+    assert_eq!(kernel_process.credentials(), Credentials::ROOT);
+    assert!(kernel_process.credentials().is_root());
+}
+
+/// Description: Root may switch to any user, other users only to themselves
+fn test_set_uid() {
+    let mut credentials = USER;
+    assert_eq!(credentials.set_uid(USER.uid), Ok(()));
+    assert_eq!(credentials.set_uid(0), Err(Errno::EACCES));
+    assert_eq!(credentials.set_uid(2000), Err(Errno::EACCES));
+    assert_eq!(credentials, USER);
+
+    let mut credentials = Credentials::ROOT;
+    assert_eq!(credentials.set_uid(USER.uid), Ok(()));
+    assert_eq!(credentials, Credentials::new(USER.uid, 0));
+    assert!(!credentials.is_root());
+
+    // No way back to root
+    assert_eq!(credentials.set_uid(0), Err(Errno::EACCES));
+}
+
+/// Description: Other users may lower the priority of their threads and restore the default, only root may raise it above
+fn test_priority() {
+    assert_eq!(USER.may_set_priority(0), Ok(()));
+    assert_eq!(USER.may_set_priority(DEFAULT_PRIORITY), Ok(()));
+    assert_eq!(USER.may_set_priority(DEFAULT_PRIORITY + 1), Err(Errno::EACCES));
+    assert_eq!(USER.may_set_priority(MAX_PRIORITY), Err(Errno::EACCES));
+    assert_eq!(Credentials::ROOT.may_set_priority(MAX_PRIORITY), Ok(()));
+}
+
+/// Description: Only root may power off or reboot the system
+fn test_shut_down() {
+    assert_eq!(USER.may_shut_down(), Err(Errno::EACCES));
+    assert_eq!(Credentials::ROOT.may_shut_down(), Ok(()));
+}
+
+/// Description: Changing the owner is denied for other users but allowed for root; permissions may also be changed by the owner
+fn test_privileged_operations() {
+    assert_eq!(mkdir("/credentials_test"), Ok(0));
+    assert_eq!(mkentry("/credentials_test", "file", vec![1]), Ok(0));
+    let path = "/credentials_test/file";
+
+    assert_eq!(chown_as(USER, path, USER.uid, USER.gid), Err(Errno::EACCES));
+    assert_eq!(chmod_as(USER, path, 0o600), Err(Errno::EACCES));
+    assert_eq!(stat(path).unwrap().uid, 0);
+
+    assert_eq!(chown_as(Credentials::ROOT, path, USER.uid, USER.gid), Ok(0));
+    let s = stat(path).unwrap();
+    assert_eq!((s.uid, s.gid), (USER.uid, USER.gid));
+
+    // The owner may change the permissions, but not give the entry away
+    assert_eq!(chmod_as(USER, path, 0o600), Ok(0));
+    assert_eq!(stat(path).unwrap().mode.permissions(), 0o600);
+    assert_eq!(chown_as(USER, path, 0, 0), Err(Errno::EACCES));
+
+    // Missing entries are reported as such
+    assert_eq!(chown_as(USER, "/credentials_test/missing", 0, 0), Err(Errno::ENOENT));
+
+    assert_eq!(del(path), Ok(0));
+    assert_eq!(del("/credentials_test"), Ok(0));
+}
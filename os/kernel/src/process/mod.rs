@@ -1,3 +1,12 @@
+pub mod auth;
+pub mod credentials;
 pub mod scheduler;
+pub mod pipe;
+pub mod sha256;
 pub mod thread;
 pub mod process;
+pub mod auth_tests;
+pub mod credentials_tests;
+pub mod scheduler_tests;
+pub mod pipe_tests;
+pub mod process_tests;
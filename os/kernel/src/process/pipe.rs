@@ -0,0 +1,224 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pipe                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Pipes for passing bytes between threads and processes. A pipe   ║
+   ║         is a bounded ring buffer on the kernel heap with a read end and ║
+   ║         a write end, each identified by a file descriptor of its        ║
+   ║         process (the standard streams 0 - 2 belong to the terminal).    ║
+   ║         Both ends share the pipe (reference counted), so its buffer is  ║
+   ║         freed, when both ends have been closed.                         ║
+   ║                                                                         ║
+   ║         Reading from an empty pipe blocks until a byte is written or    ║
+   ║         all write ends are closed (end of file, 0 bytes are read).      ║
+   ║         Writing to a full pipe blocks until all bytes have been         ║
+   ║         written. Non-blocking ends fail with 'EAGAIN' instead (unless   ║
+   ║         some bytes have been transferred). Writing to a pipe without    ║
+   ║         read ends fails with 'EPIPE'. File descriptors are not          ║
+   ║         inherited by new processes, they are closed on process exit.    ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use spin::Mutex;
+use syscall::io::STDERR;
+use syscall::return_vals::Errno;
+use crate::scheduler;
+
+/// Size of the ring buffer of a pipe (in bytes)
+pub const PIPE_CAPACITY: usize = 4096;
+
+/// Flag for `create()`: reading and writing fail with `Errno::EAGAIN` instead of blocking
+pub const NON_BLOCKING: usize = 1 << 0;
+
+/// File descriptors of all processes, identified by process id and descriptor
+static FILE_DESCRIPTORS: Mutex<BTreeMap<(usize, usize), PipeEnd>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    Read,
+    Write,
+}
+
+struct PipeEnd {
+    pipe: Arc<Mutex<Pipe>>,
+    direction: Direction,
+    non_blocking: bool,
+}
+
+struct Pipe {
+    buffer: Vec<u8>,
+    start: usize,   // position of the oldest byte
+    len: usize,     // number of buffered bytes
+    readers: usize, // number of open read ends
+    writers: usize, // number of open write ends
+}
+
+impl Pipe {
+    fn new() -> Self {
+        Self { buffer: vec![0; PIPE_CAPACITY], start: 0, len: 0, readers: 1, writers: 1 }
+    }
+
+    /// Description: Append as many bytes of `data` as fit into the buffer. Returns the number of bytes appended.
+    fn push(&mut self, data: &[u8]) -> usize {
+        let count = data.len().min(PIPE_CAPACITY - self.len);
+        for (i, byte) in data[..count].iter().enumerate() {
+            self.buffer[(self.start + self.len + i) % PIPE_CAPACITY] = *byte;
+        }
+
+        self.len += count;
+        count
+    }
+
+    /// Description: Remove up to `data.len()` of the oldest bytes into `data`. Returns the number of bytes removed.
+    fn pop(&mut self, data: &mut [u8]) -> usize {
+        let count = data.len().min(self.len);
+        for (i, byte) in data[..count].iter_mut().enumerate() {
+            *byte = self.buffer[(self.start + i) % PIPE_CAPACITY];
+        }
+
+        self.start = (self.start + count) % PIPE_CAPACITY;
+        self.len -= count;
+        count
+    }
+}
+
+///
+/// Description: Create a pipe for process `process_id`.
+///
+/// Parameters: \
+///    `process_id` owner of both file descriptors \
+///    `flags` `NON_BLOCKING` or 0
+///
+/// Return: file descriptors of the read end and the write end or `Errno::EINVAL` for unknown flags
+///
+pub fn create(process_id: usize, flags: usize) -> Result<(usize, usize), Errno> {
+    if flags & !NON_BLOCKING != 0 {
+        return Err(Errno::EINVAL);
+    }
+
+    let pipe = Arc::new(Mutex::new(Pipe::new()));
+    let non_blocking = flags & NON_BLOCKING != 0;
+
+    let mut descriptors = FILE_DESCRIPTORS.lock();
+    let read_fd = free_descriptor(&descriptors, process_id, STDERR + 1);
+    descriptors.insert((process_id, read_fd), PipeEnd { pipe: Arc::clone(&pipe), direction: Direction::Read, non_blocking });
+    let write_fd = free_descriptor(&descriptors, process_id, read_fd + 1);
+    descriptors.insert((process_id, write_fd), PipeEnd { pipe, direction: Direction::Write, non_blocking });
+
+    Ok((read_fd, write_fd))
+}
+
+/// Description: Check if `fd` is a pipe end of process `process_id`
+pub fn is_pipe(process_id: usize, fd: usize) -> bool {
+    FILE_DESCRIPTORS.lock().contains_key(&(process_id, fd))
+}
+
+///
+/// Description: Read up to `buf.len()` bytes from the read end `fd` of process `process_id`.
+///
+/// Return: number of bytes read (0 = all write ends are closed), `Errno::EBADF`, if `fd` is no read end
+///         or `Errno::EAGAIN`, if a non-blocking pipe is empty
+///
+pub fn read(process_id: usize, fd: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    let (pipe, non_blocking) = pipe_end(process_id, fd, Direction::Read)?;
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    loop {
+        {
+            let mut pipe = pipe.lock();
+            if pipe.len > 0 {
+                return Ok(pipe.pop(buf));
+            }
+            if pipe.writers == 0 {
+                return Ok(0);
+            }
+        }
+
+        if non_blocking {
+            return Err(Errno::EAGAIN);
+        }
+
+        scheduler().yield_thread();
+    }
+}
+
+///
+/// Description: Write `buf` to the write end `fd` of process `process_id`.
+///
+/// Return: number of bytes written (all of them, if the pipe is blocking), `Errno::EBADF`, if `fd` is no
+///         write end, `Errno::EPIPE`, if all read ends are closed, or `Errno::EAGAIN`, if a non-blocking pipe is full
+///
+pub fn write(process_id: usize, fd: usize, buf: &[u8]) -> Result<usize, Errno> {
+    let (pipe, non_blocking) = pipe_end(process_id, fd, Direction::Write)?;
+
+    let mut written = 0;
+    while written < buf.len() {
+        {
+            let mut pipe = pipe.lock();
+            if pipe.readers == 0 {
+                return if written > 0 { Ok(written) } else { Err(Errno::EPIPE) };
+            }
+
+            written += pipe.push(&buf[written..]);
+        }
+
+        if written < buf.len() {
+            if non_blocking {
+                return if written > 0 { Ok(written) } else { Err(Errno::EAGAIN) };
+            }
+
+            scheduler().yield_thread();
+        }
+    }
+
+    Ok(written)
+}
+
+///
+/// Description: Close the pipe end `fd` of process `process_id` (the pipe is freed with its last end).
+///
+/// Return: `Ok(())` or `Errno::EBADF`, if `fd` is no pipe end
+///
+pub fn close(process_id: usize, fd: usize) -> Result<(), Errno> {
+    let end = FILE_DESCRIPTORS.lock().remove(&(process_id, fd)).ok_or(Errno::EBADF)?;
+    close_end(end);
+
+    Ok(())
+}
+
+/// Description: Close all pipe ends of process `process_id` (called when the process exits)
+pub fn close_all(process_id: usize) {
+    let mut descriptors = FILE_DESCRIPTORS.lock();
+    let fds = descriptors.keys().filter(|(owner, _)| *owner == process_id).copied().collect::<Vec<(usize, usize)>>();
+    let ends = fds.iter().filter_map(|key| descriptors.remove(key)).collect::<Vec<PipeEnd>>();
+    drop(descriptors);
+
+    ends.into_iter().for_each(close_end);
+}
+
+fn close_end(end: PipeEnd) {
+    let mut pipe = end.pipe.lock();
+    match end.direction {
+        Direction::Read => pipe.readers -= 1,
+        Direction::Write => pipe.writers -= 1,
+    }
+}
+
+/// Description: Lowest descriptor of process `process_id`, which is at least `lowest` and not in use
+fn free_descriptor(descriptors: &BTreeMap<(usize, usize), PipeEnd>, process_id: usize, lowest: usize) -> usize {
+    (lowest..).find(|fd| !descriptors.contains_key(&(process_id, *fd))).unwrap()
+}
+
+/// Description: Pipe and blocking mode of the end `fd` of process `process_id`, if it is an end for `direction`
+fn pipe_end(process_id: usize, fd: usize, direction: Direction) -> Result<(Arc<Mutex<Pipe>>, bool), Errno> {
+    match FILE_DESCRIPTORS.lock().get(&(process_id, fd)) {
+        Some(end) if end.direction == direction => Ok((Arc::clone(&end.pipe), end.non_blocking)),
+        _ => Err(Errno::EBADF),
+    }
+}
@@ -0,0 +1,148 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pipe_tests                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test pipes: passing data from a producer thread to a consumer   ║
+   ║         through a smaller buffer, end of file, non-blocking ends and    ║
+   ║         closing. The file descriptors belong to a process id, which is  ║
+   ║         not used by any process.                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::SeqCst;
+use ::log::info;
+use syscall::io::STDERR;
+use syscall::return_vals::Errno;
+
+use crate::process::pipe;
+use crate::process::pipe::{NON_BLOCKING, PIPE_CAPACITY};
+use crate::process::thread::Thread;
+use crate::scheduler;
+
+const TEST_PROCESS: usize = usize::MAX - 1;
+const TRANSFER_SIZE: usize = 10 * 1024;
+const CHUNK_SIZE: usize = 1000;
+
+/// Write end used by the producer thread of `test_producer_consumer()`
+static PRODUCER_FD: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Description:
+///    Run all pipe tests
+///
+pub fn run_tests() {
+    info!("pipe: running tests");
+
+    test_producer_consumer();
+    test_end_of_file();
+    test_non_blocking();
+    test_broken_pipe();
+    test_descriptors();
+
+    info!("pipe: all tests passed.");
+}
+
+fn pattern(index: usize) -> u8 {
+    (index % 251) as u8
+}
+
+/// Description: Write `TRANSFER_SIZE` bytes in chunks to `PRODUCER_FD` and close it
+fn produce() {
+    let fd = PRODUCER_FD.load(SeqCst);
+    let data = (0..TRANSFER_SIZE).map(pattern).collect::<Vec<u8>>();
+    for chunk in data.chunks(CHUNK_SIZE) {
+        assert_eq!(pipe::write(TEST_PROCESS, fd, chunk), Ok(chunk.len()));
+    }
+
+    pipe::close(TEST_PROCESS, fd).unwrap();
+    scheduler().exit(0);
+}
+
+/// Description: 10 KiB pass through a 4 KiB pipe (the producer blocks while it is full), then the consumer reads end of file
+fn test_producer_consumer() {
+    const { assert!(TRANSFER_SIZE > 2 * PIPE_CAPACITY) };
+    let (read_fd, write_fd) = pipe::create(TEST_PROCESS, 0).unwrap();
+    PRODUCER_FD.store(write_fd, SeqCst);
+
+    let producer = Thread::new_kernel_thread(produce);
+    let producer_id = producer.id();
+    scheduler().ready(producer);
+
+    let mut received = Vec::new();
+    let mut buffer = vec![0u8; 768];
+    loop {
+        match pipe::read(TEST_PROCESS, read_fd, &mut buffer).unwrap() {
+            0 => break,
+            count => received.extend_from_slice(&buffer[..count]),
+        }
+    }
+
+    scheduler().join(producer_id);
+    assert_eq!(received.len(), TRANSFER_SIZE);
+    assert!(received.iter().enumerate().all(|(i, byte)| *byte == pattern(i)), "data corrupted");
+
+    pipe::close(TEST_PROCESS, read_fd).unwrap();
+}
+
+/// Description: Buffered bytes can still be read after the write end has been closed, then 0 is returned
+fn test_end_of_file() {
+    let (read_fd, write_fd) = pipe::create(TEST_PROCESS, 0).unwrap();
+    assert_eq!(pipe::write(TEST_PROCESS, write_fd, b"abc"), Ok(3));
+    pipe::close(TEST_PROCESS, write_fd).unwrap();
+
+    let mut buffer = [0u8; 8];
+    assert_eq!(pipe::read(TEST_PROCESS, read_fd, &mut buffer), Ok(3));
+    assert_eq!(&buffer[..3], b"abc");
+    assert_eq!(pipe::read(TEST_PROCESS, read_fd, &mut buffer), Ok(0));
+
+    pipe::close(TEST_PROCESS, read_fd).unwrap();
+}
+
+/// Description: Non-blocking ends fail with `EAGAIN` instead of blocking, partial writes are returned
+fn test_non_blocking() {
+    let (read_fd, write_fd) = pipe::create(TEST_PROCESS, NON_BLOCKING).unwrap();
+    let mut buffer = vec![0u8; PIPE_CAPACITY + 16];
+    assert_eq!(pipe::read(TEST_PROCESS, read_fd, &mut buffer), Err(Errno::EAGAIN));
+
+    assert_eq!(pipe::write(TEST_PROCESS, write_fd, &buffer), Ok(PIPE_CAPACITY));
+    assert_eq!(pipe::write(TEST_PROCESS, write_fd, b"x"), Err(Errno::EAGAIN));
+
+    // The ring buffer wraps around
+    assert_eq!(pipe::read(TEST_PROCESS, read_fd, &mut buffer[..10]), Ok(10));
+    assert_eq!(pipe::write(TEST_PROCESS, write_fd, b"0123456789"), Ok(10));
+    assert_eq!(pipe::read(TEST_PROCESS, read_fd, &mut buffer), Ok(PIPE_CAPACITY));
+    assert_eq!(&buffer[PIPE_CAPACITY - 10..PIPE_CAPACITY], b"0123456789");
+
+    assert_eq!(pipe::create(TEST_PROCESS, 1 << 7), Err(Errno::EINVAL));
+    pipe::close(TEST_PROCESS, read_fd).unwrap();
+    pipe::close(TEST_PROCESS, write_fd).unwrap();
+}
+
+/// Description: Writing without a read end fails with `EPIPE`
+fn test_broken_pipe() {
+    let (read_fd, write_fd) = pipe::create(TEST_PROCESS, 0).unwrap();
+    pipe::close(TEST_PROCESS, read_fd).unwrap();
+    assert_eq!(pipe::write(TEST_PROCESS, write_fd, b"lost"), Err(Errno::EPIPE));
+    pipe::close(TEST_PROCESS, write_fd).unwrap();
+}
+
+/// Description: Descriptors start after the standard streams, are reused after closing and are bound to their direction
+fn test_descriptors() {
+    let (read_fd, write_fd) = pipe::create(TEST_PROCESS, 0).unwrap();
+    assert_eq!((read_fd, write_fd), (STDERR + 1, STDERR + 2));
+    assert!(pipe::is_pipe(TEST_PROCESS, read_fd) && !pipe::is_pipe(TEST_PROCESS + 1, read_fd));
+
+    let mut buffer = [0u8; 4];
+    assert_eq!(pipe::read(TEST_PROCESS, write_fd, &mut buffer), Err(Errno::EBADF));
+    assert_eq!(pipe::write(TEST_PROCESS, read_fd, &buffer), Err(Errno::EBADF));
+
+    pipe::close(TEST_PROCESS, read_fd).unwrap();
+    assert_eq!(pipe::close(TEST_PROCESS, read_fd), Err(Errno::EBADF));
+    assert_eq!(pipe::create(TEST_PROCESS, 0).map(|(read_fd, _)| read_fd), Ok(STDERR + 1));
+
+    pipe::close_all(TEST_PROCESS);
+    assert!(!pipe::is_pipe(TEST_PROCESS, write_fd));
+}
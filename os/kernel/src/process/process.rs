@@ -1,16 +1,24 @@
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicIsize, AtomicU32, AtomicUsize};
 use core::sync::atomic::Ordering::Relaxed;
 use spin::RwLock;
+use syscall::process::{ProcessInfo, ProcessState};
+use syscall::return_vals::Errno;
 use x86_64::structures::paging::{Page, PageTableFlags};
 use x86_64::structures::paging::page::PageRange;
 use x86_64::VirtAddr;
 use crate::{ process_manager, scheduler};
-use crate::memory::MemorySpace;
+use crate::memory::{nvmem, pressure, MemorySpace};
 use crate::memory::physical::phys_limit;
+use crate::naming::{file_lock, watch};
+use crate::process::pipe;
+use crate::naming::stat::{DEFAULT_UMASK, PERMISSION_MASK};
 use crate::memory::r#virtual::{AddressSpace, VirtualMemoryArea, VmaType};
+use crate::process::credentials::Credentials;
+use crate::process::scheduler::KILLED_EXIT_CODE;
 
 static PROCESS_ID_COUNTER: AtomicUsize = AtomicUsize::new(1);
 
@@ -28,7 +36,8 @@ impl ProcessManager {
         Self { active_processes: Vec::new(), exited_processes: Vec::new() }
     }
 
-    pub fn create_process(&mut self) -> Arc<Process> {
+    /// Description: Create a process named `name` (the first process is the kernel process)
+    pub fn create_process(&mut self, name: &str) -> Arc<Process> {
         let address_space = match self.kernel_process() {
             Some(kernel_process) => { // Create user address space
                 Arc::new(AddressSpace::from_other(&kernel_process.address_space()))
@@ -43,8 +52,18 @@ impl ProcessManager {
             }
         };
 
-        let process = Arc::new(Process::new(address_space));
+        // The working directory, the creation mask and the credentials are inherited from the creating process
+        let (parent_id, cwd, umask, credentials) = match self.active_processes.is_empty() {
+            true => (0, String::from("/"), DEFAULT_UMASK, Credentials::ROOT),
+            false => {
+                let parent = self.current_process();
+                (parent.id(), parent.cwd(), parent.umask(), parent.credentials())
+            }
+        };
+
+        let process = Arc::new(Process::new(String::from(name), parent_id, address_space, cwd, umask, credentials));
         self.active_processes.push(Arc::clone(&process));
+        scheduler().add_process(process.id(), parent_id);
 
         process
     }
@@ -53,6 +72,24 @@ impl ProcessManager {
         self.active_processes.iter().map(|process| process.id()).collect()
     }
 
+    /// Description: All active processes (e.g. to swap out their pages)
+    pub fn active_processes(&self) -> Vec<Arc<Process>> {
+        self.active_processes.clone()
+    }
+
+    /// Description: Infos of all active processes and of exited processes, which have not been cleaned up yet
+    pub fn process_infos(&self) -> Vec<ProcessInfo> {
+        let active = self.active_processes.iter().map(|process| process.info(ProcessState::Active));
+        let exited = self.exited_processes.iter().map(|process| process.info(ProcessState::Exited));
+
+        active.chain(exited).collect()
+    }
+
+    /// Description: Address spaces of all active processes (e.g. to change the flags of kernel pages in all of them)
+    pub fn address_spaces(&self) -> Vec<Arc<AddressSpace>> {
+        self.active_processes.iter().map(|process| process.address_space()).collect()
+    }
+
     pub fn kernel_process(&self) -> Option<Arc<Process>> {
         match self.active_processes.get(0) {
             Some(kernel_process) => Some(Arc::clone(kernel_process)),
@@ -76,6 +113,14 @@ impl ProcessManager {
         let process = Arc::clone(&self.active_processes[index]);
         process.kill_all_threads_but_current();
 
+        file_lock::release_all(process_id);
+        watch::remove_all(process_id);
+        pipe::close_all(process_id);
+        pressure::unsubscribe(process_id);
+
+        // Threads waiting for the process get the 32-bit pattern of the exit code (must not look like an Errno)
+        scheduler().process_exited(process_id, process.exit_code() as i32 as u32 as usize);
+
         self.active_processes.swap_remove(index);
         self.exited_processes.push(process);
     }
@@ -89,6 +134,11 @@ impl ProcessManager {
         for thread_id in process.thread_ids() {
             scheduler().kill(thread_id);
         }
+        file_lock::release_all(process_id);
+        watch::remove_all(process_id);
+        pipe::close_all(process_id);
+        pressure::unsubscribe(process_id);
+        scheduler().process_exited(process_id, KILLED_EXIT_CODE);
 
         self.active_processes.swap_remove(index);
         self.exited_processes.push(process);
@@ -101,36 +151,144 @@ impl ProcessManager {
 
 pub struct Process {
     id: usize,
+    name: String, // name of the application (truncated in `ProcessInfo`)
+    parent_id: usize, // id of the creating process (0 for the kernel process)
     address_space: Arc<AddressSpace>,
-    memory_areas: RwLock<Vec<VirtualMemoryArea>>
+    memory_areas: RwLock<Vec<VirtualMemoryArea>>,
+    exit_code: AtomicIsize, // set when the process exits
+    cwd: RwLock<String>, // canonical path (see 'path::canonicalize()')
+    umask: AtomicU32, // creation mask for permissions of new entries (see 'umask()')
+    credentials: RwLock<Credentials>, // user and group the process runs as
+    run_time_ns: AtomicUsize, // time all threads of the process have been running (see 'Scheduler::account_switch()')
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
         for vma in self.memory_areas.read().iter() {
-            self.address_space.unmap(vma.range(), true);
+            match vma.typ() {
+                VmaType::NvramMapping => nvmem::unmap(&self.address_space, vma),
+                _ => self.address_space.unmap(vma.range(), true),
+            }
         }
     }
 }
 
 impl Process {
-    fn new(address_space: Arc<AddressSpace>) -> Self {
-        Self { id: next_process_id(), address_space, memory_areas: RwLock::new(Vec::new()) }
+    fn new(name: String, parent_id: usize, address_space: Arc<AddressSpace>, cwd: String, umask: u32, credentials: Credentials) -> Self {
+        Self {
+            id: next_process_id(),
+            name,
+            parent_id,
+            address_space,
+            memory_areas: RwLock::new(Vec::new()),
+            exit_code: AtomicIsize::new(0),
+            cwd: RwLock::new(cwd),
+            umask: AtomicU32::new(umask),
+            credentials: RwLock::new(credentials),
+            run_time_ns: AtomicUsize::new(0),
+        }
     }
 
     pub fn id(&self) -> usize {
         self.id
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent_id(&self) -> usize {
+        self.parent_id
+    }
+
+    pub fn info(&self, state: ProcessState) -> ProcessInfo {
+        let mut info = ProcessInfo::new(self.id, self.parent_id, state, &self.name);
+        info.run_time_ms = (self.run_time_ns() / 1_000_000) as u64;
+        info
+    }
+
+    /// Description: Time all threads of the process have been running so far
+    pub fn run_time_ns(&self) -> usize {
+        self.run_time_ns.load(Relaxed)
+    }
+
+    /// Description: Add `ns` to the run time of the process (called by the scheduler, when a thread of the process is switched out)
+    pub fn add_run_time(&self, ns: usize) {
+        self.run_time_ns.fetch_add(ns, Relaxed);
+    }
+
     pub fn address_space(&self) -> Arc<AddressSpace> {
         Arc::clone(&self.address_space)
     }
 
+    /// Description: Current working directory (absolute path)
+    pub fn cwd(&self) -> String {
+        self.cwd.read().clone()
+    }
+
+    /// Description: Set the current working directory (`cwd` must be canonical)
+    pub fn set_cwd(&self, cwd: String) {
+        *self.cwd.write() = cwd;
+    }
+
+    ///
+    /// Description:
+    ///    Creation mask: permission bits set in the mask are cleared in the permissions of new
+    ///    entries (like 'umask' in Unix, default `DEFAULT_UMASK`)
+    ///
+    pub fn umask(&self) -> u32 {
+        self.umask.load(Relaxed)
+    }
+
+    /// Description: Set the creation mask (only bits in `PERMISSION_MASK`) and return the previous one
+    pub fn set_umask(&self, mask: u32) -> u32 {
+        self.umask.swap(mask & PERMISSION_MASK, Relaxed)
+    }
+
+    ///
+    /// Description:
+    ///    Permissions of a new entry. The creation mask only removes bits: explicitly requested
+    ///    permissions (e.g. a mode argument) are masked the same way as default permissions.
+    ///
+    /// Parameters: `requested` requested (or default) permission bits
+    ///
+    pub fn creation_permissions(&self, requested: u32) -> u32 {
+        requested & PERMISSION_MASK & !self.umask()
+    }
+
+    pub fn credentials(&self) -> Credentials {
+        *self.credentials.read()
+    }
+
+    /// Description: Change the user id of the process (see `Credentials::set_uid()`)
+    pub fn set_uid(&self, uid: u32) -> Result<(), Errno> {
+        self.credentials.write().set_uid(uid)
+    }
+
     pub fn add_vma(&self, new_area: VirtualMemoryArea) {
+        if !self.try_add_vma(new_area) {
+            panic!("Process: Trying to add a VMA, which overlaps with an existing one!");
+        }
+    }
+
+    /// Description: Add `new_area`, if it does not overlap with an existing VMA. Return `false` otherwise.
+    pub fn try_add_vma(&self, new_area: VirtualMemoryArea) -> bool {
         let mut areas = self.memory_areas.write();
         match areas.iter().find(|area| area.overlaps_with(&new_area)) {
-            Some(_) => panic!("Process: Trying to add a VMA, which overlaps with an existing one!"),
-            None => areas.push(new_area)
+            Some(_) => false,
+            None => {
+                areas.push(new_area);
+                true
+            }
+        }
+    }
+
+    /// Description: Remove `vma` (the pages are not unmapped)
+    pub fn remove_vma(&self, vma: VirtualMemoryArea) {
+        let mut areas = self.memory_areas.write();
+        match areas.iter().position(|area| *area == vma) {
+            Some(index) => { areas.remove(index); }
+            None => panic!("Trying to remove a non-existent VMA!")
         }
     }
 
@@ -164,10 +322,15 @@ impl Process {
         }
     }
 
-    pub fn exit(&self) {
+    pub fn exit(&self, exit_code: isize) {
+        self.exit_code.store(exit_code, Relaxed);
         process_manager().write().exit(self.id);
     }
 
+    pub fn exit_code(&self) -> isize {
+        self.exit_code.load(Relaxed)
+    }
+
     pub fn thread_ids(&self) -> Vec<usize> {
         scheduler().active_thread_ids().iter()
             .filter(|&&thread_id| {
@@ -0,0 +1,69 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: process_tests                                                   ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the process list (infos for 'ps') with two processes,      ║
+   ║         which are created (without threads) and exit again.             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::String;
+use ::log::info;
+use syscall::process::{ProcessInfo, ProcessState, PROCESS_NAME_LENGTH};
+
+use crate::{process_manager, scheduler};
+
+///
+/// Description:
+///    Run all process tests (requires the kernel process to be initialized)
+///
+pub fn run_tests() {
+    info!("process: running tests");
+
+    test_info_name();
+    test_process_list();
+
+    info!("process: all tests passed.");
+}
+
+/// Description: Names are truncated at a character boundary
+fn test_info_name() {
+    let info = ProcessInfo::new(1, 0, ProcessState::Active, "shell");
+    assert_eq!(info.name(), "shell");
+    assert_eq!(info.name_len, 5);
+
+    let long = "x".repeat(PROCESS_NAME_LENGTH + 10);
+    assert_eq!(ProcessInfo::new(1, 0, ProcessState::Active, &long).name(), &long[..PROCESS_NAME_LENGTH]);
+
+    let umlauts = "ä".repeat(PROCESS_NAME_LENGTH); // 2 bytes per character
+    let info = ProcessInfo::new(1, 0, ProcessState::Active, &(String::from("a") + &umlauts));
+    assert_eq!(info.name_len as usize, PROCESS_NAME_LENGTH - 1);
+    assert_eq!(info.name(), String::from("a") + &umlauts[..PROCESS_NAME_LENGTH - 2]);
+}
+
+/// Description: Two created processes are listed with their parent, exited processes until they are cleaned up
+fn test_process_list() {
+    let current = process_manager().read().current_process();
+    let first = process_manager().write().create_process("ps_test_first");
+    let second = process_manager().write().create_process("ps_test_second");
+
+    let infos = process_manager().read().process_infos();
+    let kernel = infos.iter().find(|info| info.parent_pid == 0).expect("Kernel process is not listed");
+    assert_eq!(kernel.name(), "kernel");
+    for process in [&first, &second] {
+        let info = infos.iter().find(|info| info.pid == process.id()).expect("Created process is not listed");
+        assert_eq!(info.name(), process.name());
+        assert_eq!(info.parent_pid, current.id());
+        assert_eq!(info.state, ProcessState::Active);
+    }
+
+    process_manager().write().exit(first.id());
+    let infos = process_manager().read().process_infos();
+    assert_eq!(infos.iter().find(|info| info.pid == first.id()).map(|info| info.state), Some(ProcessState::Exited));
+    assert_eq!(infos.iter().find(|info| info.pid == second.id()).map(|info| info.state), Some(ProcessState::Active));
+
+    process_manager().write().exit(second.id());
+    for process in [&first, &second] {
+        let _ = scheduler().wait_process(current.id(), process.id()); // reap the exit codes
+    }
+}
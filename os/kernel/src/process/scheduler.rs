@@ -1,7 +1,10 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: scheduler                                                       ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: Implementation of the scheduler.                                ║
+   ║ Descr.: Implementation of the scheduler. Besides the ready and sleep    ║
+   ║         queues, threads may be blocked waiting for a thread or process  ║
+   ║         to exit, or on a futex (a user address, see `futex_wait()` and  ║
+   ║         `futex_wake()`).                                                ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, HHU                                             ║
    ╚═════════════════════════════════════════════════════════════════════════╝
@@ -16,6 +19,13 @@ use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::Relaxed;
 use smallmap::Map;
 use spin::{Mutex, MutexGuard};
+use syscall::return_vals::Errno;
+
+/// Parent id of each running process and the threads waiting for it to exit (indexed by process id)
+type WaitMap = Map<usize, (usize, Vec<Rc<Thread>>)>;
+
+/// Threads waiting on a futex (indexed by process id and user address of the futex)
+type FutexMap = Map<(usize, usize), Vec<Rc<Thread>>>;
 
 // thread IDs
 static THREAD_ID_COUNTER: AtomicUsize = AtomicUsize::new(1);
@@ -24,11 +34,109 @@ pub fn next_thread_id() -> usize {
     THREAD_ID_COUNTER.fetch_add(1, Relaxed)
 }
 
+// thread priorities (higher value = higher priority)
+pub const MAX_PRIORITY: u8 = 7;
+pub const DEFAULT_PRIORITY: u8 = 4;
+const NUM_PRIORITIES: usize = MAX_PRIORITY as usize + 1;
+
+/// Exit code, passed to threads waiting for a killed thread (128 + SIGKILL, as in Unix shells)
+pub const KILLED_EXIT_CODE: usize = 137;
+
+/// Time slice of a thread: the timer interrupt preempts the running thread after this time (boot option 'timeslice=<ms>')
+pub const DEFAULT_TIME_SLICE_MS: usize = 10;
+/// Longest time slice accepted by the boot option (the interval must fit into the APIC timer register)
+pub const MAX_TIME_SLICE_MS: usize = 1000;
+
+/// Ready threads, one queue per priority level (round-robin within a level)
+pub(crate) struct ReadyQueue {
+    queues: [VecDeque<Rc<Thread>>; NUM_PRIORITIES],
+}
+
+impl ReadyQueue {
+    pub fn new() -> Self {
+        Self { queues: core::array::from_fn(|_| VecDeque::new()) }
+    }
+
+    /// Description: Enqueue `thread` at the end of its priority level
+    pub fn push(&mut self, thread: Rc<Thread>) {
+        self.queues[thread.priority() as usize].push_front(thread);
+    }
+
+    /// Description: Dequeue the first thread of the highest non-empty priority level
+    pub fn pop(&mut self) -> Option<Rc<Thread>> {
+        self.queues.iter_mut().rev().find_map(|queue| queue.pop_back())
+    }
+
+    /// Description: Return the priority of the thread, `pop()` would return
+    pub fn highest_priority(&self) -> Option<u8> {
+        self.queues.iter().rposition(|queue| !queue.is_empty()).map(|priority| priority as u8)
+    }
+
+    /// Description: Remove and return the thread with the given `thread_id`
+    pub fn remove(&mut self, thread_id: usize) -> Option<Rc<Thread>> {
+        for queue in self.queues.iter_mut() {
+            if let Some(index) = queue.iter().position(|thread| thread.id() == thread_id) {
+                return queue.remove(index);
+            }
+        }
+
+        None
+    }
+
+    pub fn retain(&mut self, f: impl Fn(&Rc<Thread>) -> bool) {
+        for queue in self.queues.iter_mut() {
+            queue.retain(&f);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<Thread>> {
+        self.queues.iter().rev().flat_map(|queue| queue.iter().rev())
+    }
+}
+
+/// Sleeping threads, sorted by their wake-up time (in timer ticks, see `Timer::systime_ticks()`)
+pub(crate) struct SleepQueue {
+    entries: VecDeque<(Rc<Thread>, u64)>,
+}
+
+impl SleepQueue {
+    pub fn new() -> Self {
+        Self { entries: VecDeque::new() }
+    }
+
+    /// Description: Insert `thread` behind all threads with the same or an earlier wake-up time
+    pub fn push(&mut self, thread: Rc<Thread>, wakeup_tick: u64) {
+        let index = self.entries.partition_point(|entry| entry.1 <= wakeup_tick);
+        self.entries.insert(index, (thread, wakeup_tick));
+    }
+
+    /// Description: Remove and return the first thread, whose wake-up time is not after `now`
+    pub fn pop_expired(&mut self, now: u64) -> Option<Rc<Thread>> {
+        match self.entries.front() {
+            Some(entry) if entry.1 <= now => self.entries.pop_front().map(|entry| entry.0),
+            _ => None,
+        }
+    }
+
+    /// Description: Wake-up time of the first sleeping thread
+    pub fn next_wakeup(&self) -> Option<u64> {
+        self.entries.front().map(|entry| entry.1)
+    }
+
+    pub fn retain(&mut self, f: impl Fn(&Rc<Thread>) -> bool) {
+        self.entries.retain(|entry| f(&entry.0));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<Thread>> {
+        self.entries.iter().map(|entry| &entry.0)
+    }
+}
+
 /// Everything related to the ready state in the scheduler
 struct ReadyState {
     initialized: bool,
     current_thread: Option<Rc<Thread>>,
-    ready_queue: VecDeque<Rc<Thread>>,
+    ready_queue: ReadyQueue,
 }
 
 impl ReadyState {
@@ -36,7 +144,7 @@ impl ReadyState {
         Self {
             initialized: false,
             current_thread: None,
-            ready_queue: VecDeque::new(),
+            ready_queue: ReadyQueue::new(),
         }
     }
 }
@@ -44,8 +152,12 @@ impl ReadyState {
 /// Main struct of the scheduler
 pub struct Scheduler {
     ready_state: Mutex<ReadyState>,
-    sleep_list: Mutex<Vec<(Rc<Thread>, usize)>>,
+    sleep_queue: Mutex<SleepQueue>,
     join_map: Mutex<Map<usize, Vec<Rc<Thread>>>>, // manage which threads are waiting for a thread-id to terminate
+    exit_codes: Mutex<Map<usize, usize>>,         // exit codes of terminated threads, nobody has waited for yet
+    wait_map: Mutex<WaitMap>, // parent id of each running process and the threads waiting for it to exit
+    process_exit_codes: Mutex<Map<usize, (usize, usize)>>, // parent id and exit code of exited processes, their parent has not waited for yet
+    futex_map: Mutex<FutexMap>, // threads waiting on a futex (process id, user address), locked before 'ready_state'
 }
 
 unsafe impl Send for Scheduler {}
@@ -63,8 +175,12 @@ impl Scheduler {
     pub fn new() -> Self {
         Self {
             ready_state: Mutex::new(ReadyState::new()),
-            sleep_list: Mutex::new(Vec::new()),
+            sleep_queue: Mutex::new(SleepQueue::new()),
             join_map: Mutex::new(Map::new()),
+            exit_codes: Mutex::new(Map::new()),
+            wait_map: Mutex::new(Map::new()),
+            process_exit_codes: Mutex::new(Map::new()),
+            futex_map: Mutex::new(Map::new()),
         }
     }
 
@@ -75,13 +191,13 @@ impl Scheduler {
 
     pub fn active_thread_ids(&self) -> Vec<usize> {
         let state = self.get_ready_state();
-        let sleep_list = self.sleep_list.lock();
+        let sleep_queue = self.sleep_queue.lock();
 
         state.ready_queue.iter()
             .map(|thread| thread.id())
             .collect::<Vec<usize>>()
             .into_iter()
-            .chain(sleep_list.iter().map(|entry| entry.0.id()))
+            .chain(sleep_queue.iter().map(|thread| thread.id()))
             .collect()
     }
 
@@ -102,7 +218,8 @@ impl Scheduler {
     /// Description: Start the scheduler, called only once from `boot.rs` 
     pub fn start(&self) {
         let mut state = self.get_ready_state();
-        state.current_thread = state.ready_queue.pop_back();
+        state.current_thread = state.ready_queue.pop();
+        state.current_thread.as_ref().expect("Failed to dequeue first thread!").start_running(timer().systime_ns());
 
         unsafe { Thread::start_first(state.current_thread.as_ref().expect("Failed to dequeue first thread!").as_ref()); }
     }
@@ -135,25 +252,95 @@ impl Scheduler {
             }
         }
 
-        state.ready_queue.push_front(thread);
+        state.ready_queue.push(thread);
         join_map.insert(id, Vec::new());
     }
 
-    /// Description: Put calling thread to sleep for `ms` milliseconds
+    ///
+    /// Description: Change the priority of a thread (current, ready, sleeping or joining)
+    ///
+    /// Parameters: \
+    ///    `process_id` process the thread must belong to \
+    ///    `thread_id` thread to be changed \
+    ///    `priority` new priority (0 ..= `MAX_PRIORITY`)
+    ///
+    /// Return: `false` if the thread does not exist in the given process
+    ///
+    pub fn set_priority(&self, process_id: usize, thread_id: usize, priority: u8) -> bool {
+        assert!(priority <= MAX_PRIORITY, "Invalid thread priority [{}]!", priority);
+
+        let (mut state, join_map) = self.get_ready_state_and_join_map();
+        let current = Scheduler::current(&state);
+        let matches = |thread: &Rc<Thread>| thread.id() == thread_id && thread.process().id() == process_id;
+
+        if matches(&current) {
+            current.set_priority(priority);
+            return true;
+        }
+
+        // Ready threads must be moved to the queue of their new priority level
+        if state.ready_queue.iter().any(matches) {
+            let thread = state.ready_queue.remove(thread_id).unwrap();
+            thread.set_priority(priority);
+            state.ready_queue.push(thread);
+            return true;
+        }
+
+        let sleep_queue = self.sleep_queue.lock();
+        let blocked = sleep_queue.iter()
+            .chain(join_map.iter().flat_map(|entry| entry.1.iter()))
+            .find(|thread| matches(thread));
+
+        match blocked {
+            Some(thread) => {
+                thread.set_priority(priority);
+                true
+            }
+            None => false
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Put calling thread to sleep for at least `ms` milliseconds. The thread is removed from
+    ///    the ready queue and woken up by the timer interrupt (see `wake_up_sleeping()`).
+    ///    Sleeping for 0 ms is the same as `yield_thread()`.
+    ///
     pub fn sleep(&self, ms: usize) {
+        if ms == 0 {
+            self.yield_thread();
+            return;
+        }
+
         let mut state = self.get_ready_state();
         let thread = Scheduler::current(&state);
-        let wakeup_time = timer().systime_ms() + ms;
+        let wakeup_tick = timer().systime_ticks().saturating_add(timer().sleep_ticks(ms));
 
         {
             // Execute in own block, so that the lock is released automatically (block() does not return)
-            let mut sleep_list = self.sleep_list.lock();
-            sleep_list.push((thread, wakeup_time));
+            let mut sleep_queue = self.sleep_queue.lock();
+            sleep_queue.push(thread, wakeup_tick);
         }
 
         self.block(&mut state);
     }
 
+    ///
+    /// Description:
+    ///    Move all sleeping threads, whose wake-up time has passed, to the ready queue (called by
+    ///    the timer interrupt). If the scheduler or the kernel heap is locked by the interrupted
+    ///    thread, nothing is done and the threads are woken up by the next interrupt.
+    ///
+    pub fn wake_up_sleeping(&self) {
+        if allocator().is_locked() {
+            return;
+        }
+
+        if let (Some(mut state), Some(mut sleep_queue)) = (self.ready_state.try_lock(), self.sleep_queue.try_lock()) {
+            Scheduler::check_sleep_queue(&mut state, &mut sleep_queue);
+        }
+    }
+
     /// 
     /// Description: Switch from current to next thread (from ready queue)
     /// 
@@ -161,39 +348,56 @@ impl Scheduler {
     ///                         false = no EOI needed
     /// 
     fn switch_thread(&self, interrupt: bool) {
-        if let Some(mut state) = self.ready_state.try_lock() {
-            if !state.initialized {
-                return;
-            }
+        if let Some(state) = self.ready_state.try_lock() {
+            self.switch_to_next(state, interrupt);
+        }
+    }
 
-            if let Some(mut sleep_list) = self.sleep_list.try_lock() {
-                Scheduler::check_sleep_list(&mut state, &mut sleep_list);
-            }
+    ///
+    /// Description:
+    ///    Give up the CPU voluntarily: the calling thread is put at the end of its priority
+    ///    level and the next thread with at least the same priority is started. Returns
+    ///    immediately, if there is no such thread. Unlike `switch_thread_no_interrupt()`,
+    ///    this waits for the ready queue lock instead of skipping the switch.
+    ///
+    pub fn yield_thread(&self) {
+        let state = self.get_ready_state();
+        self.switch_to_next(state, false);
+    }
 
-            let current = Scheduler::current(&state);
-            let next = match state.ready_queue.pop_back() {
-                Some(thread) => thread,
-                None => return,
-            };
+    /// Description: Switch to the next thread, if its priority is not lower than the one of the current thread
+    fn switch_to_next(&self, mut state: MutexGuard<ReadyState>, interrupt: bool) {
+        if !state.initialized {
+            return;
+        }
 
-            // Current thread is initializing itself and may not be interrupted
-            if current.stacks_locked() || tss().is_locked() {
-                return;
-            }
+        let current = Scheduler::current(&state);
 
-            let current_ptr = ptr::from_ref(current.as_ref());
-            let next_ptr = ptr::from_ref(next.as_ref());
+        // Do not preempt the current thread in favor of a thread with lower priority
+        match state.ready_queue.highest_priority() {
+            Some(priority) if priority >= current.priority() => {}
+            _ => return,
+        }
 
-            state.current_thread = Some(next);
-            state.ready_queue.push_front(current);
+        // Current thread is initializing itself and may not be interrupted
+        if current.stacks_locked() || tss().is_locked() {
+            return;
+        }
 
-            if interrupt {
-                apic().end_of_interrupt();
-            }
+        let next = state.ready_queue.pop().unwrap();
+        let current_ptr = ptr::from_ref(current.as_ref());
+        let next_ptr = ptr::from_ref(next.as_ref());
 
-            unsafe {
-                Thread::switch(current_ptr, next_ptr);
-            }
+        Scheduler::account_switch(&current, &next);
+        state.current_thread = Some(next);
+        state.ready_queue.push(current);
+
+        if interrupt {
+            apic().end_of_interrupt();
+        }
+
+        unsafe {
+            Thread::switch(current_ptr, next_ptr);
         }
     }
 
@@ -212,27 +416,38 @@ impl Scheduler {
     /// 
     /// Parameters: `thread_id` thread to wait for
     /// 
-    pub fn join(&self, thread_id: usize) {
+    /// Return: exit code of the thread or `None` if the thread does not exist
+    ///         (or has already terminated and its exit code has been retrieved before)
+    ///
+    pub fn join(&self, thread_id: usize) -> Option<usize> {
         let mut state = self.get_ready_state();
         let thread = Scheduler::current(&state);
 
         {
-            // Execute in own block, so that the lock is released automatically (block() does not return)
+            // Execute in own block, so that the lock is released before blocking
             let mut join_map = self.join_map.lock();
             let join_list = join_map.get_mut(&thread_id);
             if join_list.is_some() {
-                join_list.unwrap().push(thread);
+                join_list.unwrap().push(Rc::clone(&thread));
             } else {
-                // Joining on a non-existent thread has no effect (i.e. the thread has already finished running)
-                return;
+                // The thread has already finished running (or never existed)
+                return self.exit_codes.lock().remove(&thread_id);
             }
         }
 
         self.block(&mut state);
+
+        // The exiting thread has passed its exit code to all waiting threads
+        Some(thread.join_result())
     }
 
+    ///
     /// Description: Exit calling thread.
-    pub fn exit(&self) {
+    ///
+    /// Parameters: `exit_code` passed to all threads waiting in 'join()'. If no thread is waiting, the
+    ///             exit code is kept until the next 'join()' on this thread.
+    ///
+    pub fn exit(&self, exit_code: usize) {
         let mut ready_state;
         let current;
 
@@ -245,8 +460,13 @@ impl Scheduler {
             current = Scheduler::current(&ready_state);
             let join_list = join_map.get_mut(&current.id()).expect("Missing join_map entry!");
 
+            if join_list.is_empty() {
+                self.exit_codes.lock().insert(current.id(), exit_code);
+            }
+
             for thread in join_list {
-                ready_state.ready_queue.push_front(Rc::clone(thread));
+                thread.set_join_result(exit_code);
+                ready_state.ready_queue.push(Rc::clone(thread));
             }
 
             join_map.remove(&current.id());
@@ -256,8 +476,150 @@ impl Scheduler {
         self.block(&mut ready_state);
     }
 
+    /// Description: Register a new process created by `parent_id`, so that threads of the parent can wait for it to exit with `wait_process()`
+    pub fn add_process(&self, process_id: usize, parent_id: usize) {
+        self.wait_map.lock().insert(process_id, (parent_id, Vec::new()));
+    }
+
+    ///
+    /// Description: Calling thread wants to wait for a child process to exit
+    ///
+    /// Parameters: \
+    ///    `parent_id` process of the calling thread \
+    ///    `process_id` process to wait for (must have been created by `parent_id`)
+    ///
+    /// Return: exit code of the process, `Errno::ENOENT` if the process does not exist
+    ///         (or has already exited and its exit code has been retrieved before)
+    ///         or `Errno::ECHILD` if the process is not a child of `parent_id`
+    ///
+    pub fn wait_process(&self, parent_id: usize, process_id: usize) -> Result<usize, Errno> {
+        let mut ready_state;
+        let thread;
+
+        {
+            // Execute in own block, so that wait_map is released before blocking
+            let state = self.get_ready_state_and_wait_map();
+            ready_state = state.0;
+            let mut wait_map = state.1;
+
+            thread = Scheduler::current(&ready_state);
+            match wait_map.get_mut(&process_id) {
+                Some((parent, _)) if *parent != parent_id => return Err(Errno::ECHILD),
+                Some((_, wait_list)) => wait_list.push(Rc::clone(&thread)),
+                // The process has already exited (or never existed)
+                None => {
+                    let mut exit_codes = self.process_exit_codes.lock();
+                    return match exit_codes.get(&process_id) {
+                        Some((parent, _)) if *parent != parent_id => Err(Errno::ECHILD),
+                        Some(_) => Ok(exit_codes.remove(&process_id).unwrap().1),
+                        None => Err(Errno::ENOENT),
+                    };
+                }
+            }
+        }
+
+        self.block(&mut ready_state);
+
+        // The exiting process has passed its exit code to all waiting threads
+        Ok(thread.join_result())
+    }
+
+    ///
+    /// Description: Wake up all threads waiting for a process to exit (called by the process manager)
+    ///
+    /// Parameters: \
+    ///    `process_id` process, which has exited \
+    ///    `exit_code` passed to all threads waiting in 'wait_process()'. If no thread is waiting,
+    ///                the exit code is kept until the parent waits for the process or exits itself.
+    ///
+    pub fn process_exited(&self, process_id: usize, exit_code: usize) {
+        {
+            // Forget the futexes of the process (its threads do not run anymore)
+            let mut futex_map = self.futex_map.lock();
+            let keys = futex_map.keys().filter(|key| key.0 == process_id).copied().collect::<Vec<_>>();
+            keys.iter().for_each(|key| { futex_map.remove(key); });
+        }
+
+        let (mut ready_state, mut wait_map) = self.get_ready_state_and_wait_map();
+        let (parent_id, wait_list) = match wait_map.remove(&process_id) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        {
+            // Nobody can wait for the exited children of the process anymore -> drop their exit codes
+            let mut exit_codes = self.process_exit_codes.lock();
+            let orphans = exit_codes.iter().filter(|(_, (parent, _))| *parent == process_id).map(|(id, _)| *id).collect::<Vec<_>>();
+            orphans.iter().for_each(|id| { exit_codes.remove(id); });
+
+            // Keep the exit code for the parent, unless it is already waiting or has exited itself
+            if wait_list.is_empty() && wait_map.contains_key(&parent_id) {
+                exit_codes.insert(process_id, (parent_id, exit_code));
+            }
+        }
+
+        for thread in wait_list {
+            thread.set_join_result(exit_code);
+            ready_state.ready_queue.push(thread);
+        }
+    }
+
+    ///
+    /// Description:
+    ///    Block the calling thread on the futex `key` (process id and user address), until it is
+    ///    woken up by `futex_wake()`. `check` is called before blocking, while the futex map is
+    ///    locked (e.g. to compare the value at the user address with the expected value), so a
+    ///    wake-up between checking and blocking is not lost.
+    ///
+    /// Return: `Ok(())` after being woken up, or the error of `check` (the thread has not been blocked)
+    ///
+    pub fn futex_wait(&self, key: (usize, usize), check: impl FnOnce() -> Result<(), Errno>) -> Result<(), Errno> {
+        let mut ready_state;
+
+        {
+            // Execute in own block, so that futex_map is released before blocking
+            let mut futex_map = self.futex_map.lock();
+            check()?;
+
+            ready_state = self.get_ready_state();
+            let thread = Scheduler::current(&ready_state);
+            match futex_map.get_mut(&key) {
+                Some(wait_list) => wait_list.push(thread),
+                None => { futex_map.insert(key, Vec::from([thread])); }
+            }
+        }
+
+        self.block(&mut ready_state);
+        Ok(())
+    }
+
+    ///
+    /// Description: Wake up at most `count` threads waiting on the futex `key` (in the order they started waiting)
+    ///
+    /// Return: number of threads woken up
+    ///
+    pub fn futex_wake(&self, key: (usize, usize), count: usize) -> usize {
+        let mut futex_map = self.futex_map.lock();
+        let mut ready_state = self.get_ready_state();
+        let wait_list = match futex_map.get_mut(&key) {
+            Some(wait_list) => wait_list,
+            None => return 0,
+        };
+
+        let woken = count.min(wait_list.len());
+        wait_list.drain(..woken).for_each(|thread| ready_state.ready_queue.push(thread));
+        if wait_list.is_empty() {
+            futex_map.remove(&key);
+        }
+
+        woken
+    }
+
     /// 
-    /// Description: Kill the thread with the  given id
+    /// Description:
+    ///    Kill the thread with the given id. The thread is removed from the ready queue and from
+    ///    every list it may be blocked in (sleep queue, join lists, wait lists of processes and
+    ///    futexes), so it is never woken up again. Threads joining it get `KILLED_EXIT_CODE`.
     /// 
     /// Parameters: `thread_id` thread to be killed
     /// 
@@ -272,17 +634,31 @@ impl Scheduler {
             }
         }
 
+        // Remove the thread from the lists only woken up by other threads first (locked before 'ready_state')
+        {
+            let mut futex_map = self.futex_map.lock();
+            futex_map.values_mut().for_each(|wait_list| wait_list.retain(|thread| thread.id() != thread_id));
+            let empty = futex_map.iter().filter(|(_, wait_list)| wait_list.is_empty()).map(|(key, _)| *key).collect::<Vec<_>>();
+            empty.iter().for_each(|key| { futex_map.remove(key); });
+        }
+        {
+            let (_ready_state, mut wait_map) = self.get_ready_state_and_wait_map();
+            wait_map.values_mut().for_each(|(_, wait_list)| wait_list.retain(|thread| thread.id() != thread_id));
+        }
+
         let state = self.get_ready_state_and_join_map();
         let mut ready_state = state.0;
         let mut join_map = state.1;
 
-        let join_list = join_map.get_mut(&thread_id).expect("Missing join map entry!");
+        let join_list = join_map.remove(&thread_id).expect("Missing join map entry!");
 
         for thread in join_list {
-            ready_state.ready_queue.push_front(Rc::clone(thread));
+            thread.set_join_result(KILLED_EXIT_CODE);
+            ready_state.ready_queue.push(thread);
         }
 
-        join_map.remove(&thread_id);
+        join_map.values_mut().for_each(|join_list| join_list.retain(|thread| thread.id() != thread_id));
+        self.sleep_queue.lock().retain(|thread| thread.id() != thread_id);
         ready_state.ready_queue.retain(|thread| thread.id() != thread_id);
     }
 
@@ -293,14 +669,14 @@ impl Scheduler {
     /// MS -> why this param?
     /// 
     fn block(&self, state: &mut ReadyState) {
-        let mut next_thread = state.ready_queue.pop_back();
+        let mut next_thread = state.ready_queue.pop();
 
         {
             // Execute in own block, so that the lock is released automatically (block() does not return)
-            let mut sleep_list = self.sleep_list.lock();
+            let mut sleep_queue = self.sleep_queue.lock();
             while next_thread.is_none() {
-                Scheduler::check_sleep_list(state, &mut sleep_list);
-                next_thread = state.ready_queue.pop_back();
+                Scheduler::check_sleep_queue(state, &mut sleep_queue);
+                next_thread = state.ready_queue.pop();
             }
         }
 
@@ -315,6 +691,7 @@ impl Scheduler {
         let current_ptr = ptr::from_ref(current.as_ref());
         let next_ptr = ptr::from_ref(next.as_ref());
 
+        Scheduler::account_switch(&current, &next);
         state.current_thread = Some(next);
         drop(current); // Decrease Rc manually, because Thread::switch does not return
 
@@ -323,22 +700,26 @@ impl Scheduler {
         }
     }
 
+    /// Description: Add the time slice of `current` to its run time (and the one of its process) and start measuring for `next`
+    fn account_switch(current: &Thread, next: &Thread) {
+        let now_ns = timer().systime_ns();
+        let ran_ns = current.stop_running(now_ns);
+        current.process().add_run_time(ran_ns);
+        next.start_running(now_ns);
+    }
+
     /// Description: Return current running thread
     fn current(state: &ReadyState) -> Rc<Thread> {
         Rc::clone(state.current_thread.as_ref().expect("Trying to access current thread before initialization!"))
     }
 
-    fn check_sleep_list(state: &mut ReadyState, sleep_list: &mut Vec<(Rc<Thread>, usize)>) {
-        let time = timer().systime_ms();
+    /// Description: Move all threads, whose wake-up time has passed, from `sleep_queue` to the ready queue
+    fn check_sleep_queue(state: &mut ReadyState, sleep_queue: &mut SleepQueue) {
+        let now = timer().systime_ticks();
 
-        sleep_list.retain(|entry| {
-            if time >= entry.1 {
-                state.ready_queue.push_front(Rc::clone(&entry.0));
-                return false;
-            }
-
-            return true;
-        });
+        while let Some(thread) = sleep_queue.pop_expired(now) {
+            state.ready_queue.push(thread);
+        }
     }
 
     /// Description: Helper function returning `ReadyState` of scheduler in a MutexGuard
@@ -374,4 +755,17 @@ impl Scheduler {
             }
         }
     }
+
+    /// Description: Helper function returning `ReadyState` and the wait map of scheduler, each in a MutexGuard
+    fn get_ready_state_and_wait_map(&self) -> (MutexGuard<'_, ReadyState>, MutexGuard<'_, WaitMap>) {
+        loop {
+            let ready_state = self.get_ready_state();
+
+            if let Some(wait_map) = self.wait_map.try_lock() {
+                return (ready_state, wait_map);
+            } else {
+                self.switch_thread_no_interrupt();
+            }
+        }
+    }
 }
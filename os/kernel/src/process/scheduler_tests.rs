@@ -0,0 +1,496 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: scheduler_tests                                                 ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the priority-aware ready queue of the scheduler, the sleep ║
+   ║         queue, joining threads and waiting for processes with exit      ║
+   ║         codes, waiting on and waking up futexes, as well as killing     ║
+   ║         blocked threads.                                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicU32, AtomicUsize};
+use core::sync::atomic::Ordering::SeqCst;
+use ::log::info;
+use spin::Mutex;
+use syscall::return_vals::Errno;
+
+use crate::process::scheduler::{ReadyQueue, SleepQueue, DEFAULT_PRIORITY, MAX_PRIORITY};
+use crate::process::thread::Thread;
+use crate::{process_manager, scheduler, timer};
+
+///
+/// Description:
+///    Run all scheduler tests (requires the kernel process to be initialized)
+///
+pub fn run_tests() {
+    info!("scheduler: running tests");
+
+    test_default_priority();
+    test_priority_order();
+    test_round_robin();
+    test_requeue();
+    test_yield_keeps_priority();
+    test_yield_alternates();
+    test_sleep_queue_order();
+    test_sleep_wake_order();
+    test_sleep_zero();
+    test_join_exit_code();
+    test_join_exited_thread();
+    test_join_nonexistent_thread();
+    test_preemption();
+    test_run_statistics();
+    test_wait_process_exit_code();
+    test_wait_exited_process();
+    test_wait_not_child();
+    test_reap_orphans();
+    test_futex_wait_wake();
+    test_futex_value_changed();
+    test_futex_wake_count();
+    test_kill_sleeping();
+    test_kill_futex_waiter();
+
+    info!("scheduler: all tests passed.");
+}
+
+fn create_threads(priorities: &[u8]) -> Vec<Rc<Thread>> {
+    priorities.iter().map(|&priority| {
+        let thread = Thread::new_kernel_thread(|| {});
+        thread.set_priority(priority);
+        thread
+    }).collect()
+}
+
+fn pop_ids(queue: &mut ReadyQueue) -> Vec<usize> {
+    let mut ids = Vec::new();
+    while let Some(thread) = queue.pop() {
+        ids.push(thread.id());
+    }
+
+    ids
+}
+
+/// Description: New kernel threads get the middle priority
+fn test_default_priority() {
+    let thread = Thread::new_kernel_thread(|| {});
+    assert!(thread.priority() == DEFAULT_PRIORITY, "new_kernel_thread() -> priority {}", thread.priority());
+}
+
+/// Description: Three threads with different priorities must be dequeued highest first
+fn test_priority_order() {
+    let threads = create_threads(&[0, MAX_PRIORITY, DEFAULT_PRIORITY]);
+    let mut queue = ReadyQueue::new();
+    threads.iter().for_each(|thread| queue.push(Rc::clone(thread)));
+
+    let expected = [threads[1].id(), threads[2].id(), threads[0].id()];
+    let ids = pop_ids(&mut queue);
+    assert!(ids == expected, "pop() order -> {:?}, expected {:?}", ids, expected);
+}
+
+/// Description: Three threads on the same level must be dequeued in FIFO order (as done by ThreadSwitch)
+fn test_round_robin() {
+    let threads = create_threads(&[DEFAULT_PRIORITY, DEFAULT_PRIORITY, DEFAULT_PRIORITY]);
+    let mut queue = ReadyQueue::new();
+    threads.iter().for_each(|thread| queue.push(Rc::clone(thread)));
+
+    // Simulate two thread switches: the dequeued thread is enqueued again
+    for _ in 0..2 {
+        let thread = queue.pop().unwrap();
+        queue.push(thread);
+    }
+
+    let expected = [threads[2].id(), threads[0].id(), threads[1].id()];
+    let ids = pop_ids(&mut queue);
+    assert!(ids == expected, "pop() order -> {:?}, expected {:?}", ids, expected);
+}
+
+/// Description: Changing the priority of a queued thread moves it to its new level
+fn test_requeue() {
+    let threads = create_threads(&[DEFAULT_PRIORITY, DEFAULT_PRIORITY, 0]);
+    let mut queue = ReadyQueue::new();
+    threads.iter().for_each(|thread| queue.push(Rc::clone(thread)));
+
+    let thread = queue.remove(threads[2].id()).unwrap();
+    thread.set_priority(MAX_PRIORITY);
+    queue.push(thread);
+
+    assert!(queue.highest_priority() == Some(MAX_PRIORITY), "highest_priority() -> {:?}", queue.highest_priority());
+
+    let expected = [threads[2].id(), threads[0].id(), threads[1].id()];
+    let ids = pop_ids(&mut queue);
+    assert!(ids == expected, "pop() order -> {:?}, expected {:?}", ids, expected);
+}
+
+/// Description: Yielding never selects a thread with a lower priority (the yielding thread keeps running)
+fn test_yield_keeps_priority() {
+    let threads = create_threads(&[DEFAULT_PRIORITY, 0]);
+    let mut queue = ReadyQueue::new();
+    queue.push(Rc::clone(&threads[1]));
+
+    // Only a lower priority thread is ready -> no switch
+    assert!(queue.highest_priority() < Some(threads[0].priority()), "highest_priority() -> {:?}", queue.highest_priority());
+
+    // Simulate a yield: the current thread is enqueued and the next thread of the highest level is dequeued
+    queue.push(Rc::clone(&threads[0]));
+    let next = queue.pop().unwrap();
+    assert!(next.id() == threads[0].id(), "yield selected thread {}, expected {}", next.id(), threads[0].id());
+}
+
+/// Shared counter of `test_yield_alternates`
+static YIELD_COUNTER: AtomicUsize = AtomicUsize::new(0);
+const YIELD_ROUNDS: usize = 100;
+
+/// Description: Two threads, which yield while waiting for their turn, increment a shared counter alternately
+fn test_yield_alternates() {
+    YIELD_COUNTER.store(0, SeqCst);
+
+    let threads = [Thread::new_kernel_thread(|| increment_alternating(0)), Thread::new_kernel_thread(|| increment_alternating(1))];
+    let ids = threads.iter().map(|thread| thread.id()).collect::<Vec<usize>>();
+    threads.into_iter().for_each(|thread| scheduler().ready(thread));
+    ids.iter().for_each(|id| { scheduler().join(*id); });
+
+    let count = YIELD_COUNTER.load(SeqCst);
+    assert!(count == 2 * YIELD_ROUNDS, "counter -> {}, expected {}", count, 2 * YIELD_ROUNDS);
+}
+
+/// Description: Increment `YIELD_COUNTER`, whenever its parity matches `parity` (yield otherwise)
+fn increment_alternating(parity: usize) {
+    for _ in 0..YIELD_ROUNDS {
+        while YIELD_COUNTER.load(SeqCst) % 2 != parity {
+            scheduler().yield_thread();
+        }
+
+        let previous = YIELD_COUNTER.fetch_add(1, SeqCst);
+        assert!(previous % 2 == parity, "thread with parity {} incremented counter {}", parity, previous);
+    }
+    scheduler().exit(0);
+}
+
+/// Description: The sleep queue returns threads by wake-up time (in insertion order for equal times)
+fn test_sleep_queue_order() {
+    let threads = create_threads(&[DEFAULT_PRIORITY; 4]);
+    let mut queue = SleepQueue::new();
+    queue.push(Rc::clone(&threads[0]), 300);
+    queue.push(Rc::clone(&threads[1]), 100);
+    queue.push(Rc::clone(&threads[2]), 200);
+    queue.push(Rc::clone(&threads[3]), 100);
+    assert_eq!(queue.next_wakeup(), Some(100));
+
+    assert!(queue.pop_expired(99).is_none(), "thread woken up too early");
+
+    let mut ids = Vec::new();
+    while let Some(thread) = queue.pop_expired(250) {
+        ids.push(thread.id());
+    }
+    assert!(ids == [threads[1].id(), threads[3].id(), threads[2].id()], "woken up {:?}", ids);
+    assert_eq!(queue.next_wakeup(), Some(300));
+    assert_eq!(queue.iter().count(), 1);
+}
+
+/// Ids of the threads of `test_sleep_wake_order` in the order they woke up
+static WAKE_ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Description: A thread sleeping for a shorter time wakes up first (although it has been started later)
+fn test_sleep_wake_order() {
+    WAKE_ORDER.lock().clear();
+
+    let long = Thread::new_kernel_thread(|| sleep_and_record(60));
+    let short = Thread::new_kernel_thread(|| sleep_and_record(20));
+    let ids = [long.id(), short.id()];
+    scheduler().ready(long);
+    scheduler().ready(short);
+    ids.iter().for_each(|id| { scheduler().join(*id); });
+
+    let order = WAKE_ORDER.lock().clone();
+    assert!(order == [ids[1], ids[0]], "wake order {:?}, expected {:?}", order, [ids[1], ids[0]]);
+}
+
+/// Description: Sleep for `ms` milliseconds, check that the time has passed and append the thread id to `WAKE_ORDER`
+fn sleep_and_record(ms: usize) {
+    let start = timer().systime_ms();
+    scheduler().sleep(ms);
+    let slept = timer().systime_ms() - start;
+    assert!(slept >= ms, "slept {} ms, expected at least {} ms", slept, ms);
+
+    WAKE_ORDER.lock().push(scheduler().current_thread().id());
+    scheduler().exit(0);
+}
+
+/// Set by the thread of `test_sleep_zero`
+static SLEEP_ZERO_RAN: AtomicUsize = AtomicUsize::new(0);
+
+/// Description: Sleeping for 0 ms does not block, but lets another ready thread run (like yielding)
+fn test_sleep_zero() {
+    SLEEP_ZERO_RAN.store(0, SeqCst);
+
+    let thread = Thread::new_kernel_thread(|| {
+        SLEEP_ZERO_RAN.store(1, SeqCst);
+        scheduler().exit(0);
+    });
+    let id = thread.id();
+    scheduler().ready(thread);
+
+    scheduler().sleep(0);
+    assert!(SLEEP_ZERO_RAN.load(SeqCst) == 1, "ready thread did not run during sleep(0)");
+    scheduler().join(id);
+}
+
+/// Description: Joining a running thread blocks until it exits and returns its exit code
+fn test_join_exit_code() {
+    let thread = Thread::new_kernel_thread(|| {
+        scheduler().sleep(10);
+        scheduler().exit(42);
+    });
+    let id = thread.id();
+    scheduler().ready(thread);
+
+    let exit_code = scheduler().join(id);
+    assert!(exit_code == Some(42), "join() -> {:?}, expected Some(42)", exit_code);
+}
+
+/// Description: Joining an already exited thread returns immediately (only once)
+fn test_join_exited_thread() {
+    let thread = Thread::new_kernel_thread(|| scheduler().exit(7));
+    let id = thread.id();
+    scheduler().ready(thread);
+    scheduler().sleep(50); // let the thread run and exit
+
+    let exit_code = scheduler().join(id);
+    assert!(exit_code == Some(7), "join() on exited thread -> {:?}, expected Some(7)", exit_code);
+
+    let exit_code = scheduler().join(id);
+    assert!(exit_code.is_none(), "second join() on exited thread -> {:?}, expected None", exit_code);
+}
+
+/// Description: Joining a thread, that never existed, returns None
+fn test_join_nonexistent_thread() {
+    let exit_code = scheduler().join(usize::MAX);
+    assert!(exit_code.is_none(), "join() on nonexistent thread -> {:?}, expected None", exit_code);
+}
+
+static PREEMPTION_SPINNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Description: Two threads of the same priority, that never give up the CPU, are both preempted by the timer interrupt
+fn test_preemption() {
+    let ids = [0, 1].map(|_| {
+        let thread = Thread::new_kernel_thread(|| {
+            // Wait until both threads are spinning (needs a time slice of the other thread)
+            PREEMPTION_SPINNING.fetch_add(1, SeqCst);
+            let deadline = timer().systime_ms() + 1000;
+            while PREEMPTION_SPINNING.load(SeqCst) < 2 && timer().systime_ms() < deadline {
+                spin_loop();
+            }
+            scheduler().exit(PREEMPTION_SPINNING.load(SeqCst));
+        });
+        let id = thread.id();
+        scheduler().ready(thread);
+        id
+    });
+
+    for id in ids {
+        let exit_code = scheduler().join(id);
+        assert!(exit_code == Some(2), "spinning thread has not been preempted ({:?} threads spinning)", exit_code);
+    }
+}
+
+/// Description: The run time of a thread and of its process grows, while the thread is running
+fn test_run_statistics() {
+    let process = scheduler().current_thread().process();
+    let process_run_time = process.run_time_ns();
+    let thread = Thread::new_kernel_thread(|| {
+        let start = timer().systime_ms();
+        while timer().systime_ms() < start + 20 {
+            spin_loop();
+        }
+        scheduler().exit(0);
+    });
+    let id = thread.id();
+    scheduler().ready(Rc::clone(&thread));
+    scheduler().join(id);
+
+    assert!(thread.dispatches() >= 1, "thread has never been dispatched");
+    assert!(thread.run_time_ns() > 0, "run time of a busy thread is 0");
+    assert!(process.run_time_ns() >= process_run_time + thread.run_time_ns(), "run time of the thread is missing in its process");
+}
+
+/// Process ids used by the wait tests (registered directly in the scheduler, without creating a process)
+const WAIT_PROCESS_ID: usize = usize::MAX - 1;
+const EXITED_PROCESS_ID: usize = usize::MAX - 2;
+const PARENT_PROCESS_ID: usize = usize::MAX - 4;
+const CHILD_PROCESS_ID: usize = usize::MAX - 5;
+
+/// Description: Id of the process running the tests (parent of the processes registered by the wait tests)
+fn current_process_id() -> usize {
+    process_manager().read().current_process().id()
+}
+
+/// Description: Waiting for a running process blocks until it exits and returns its exit code
+fn test_wait_process_exit_code() {
+    scheduler().add_process(WAIT_PROCESS_ID, current_process_id());
+    scheduler().ready(Thread::new_kernel_thread(|| {
+        scheduler().sleep(10);
+        scheduler().process_exited(WAIT_PROCESS_ID, 42);
+        scheduler().exit(0);
+    }));
+
+    let exit_code = scheduler().wait_process(current_process_id(), WAIT_PROCESS_ID);
+    assert!(exit_code == Ok(42), "wait_process() -> {:?}, expected Ok(42)", exit_code);
+}
+
+/// Description: An already exited process can be reaped exactly once, a nonexistent process not at all
+fn test_wait_exited_process() {
+    scheduler().add_process(EXITED_PROCESS_ID, current_process_id());
+    scheduler().process_exited(EXITED_PROCESS_ID, 7);
+
+    let exit_code = scheduler().wait_process(current_process_id(), EXITED_PROCESS_ID);
+    assert!(exit_code == Ok(7), "wait_process() on exited process -> {:?}, expected Ok(7)", exit_code);
+
+    let exit_code = scheduler().wait_process(current_process_id(), EXITED_PROCESS_ID);
+    assert!(exit_code == Err(Errno::ENOENT), "second wait_process() on exited process -> {:?}, expected ENOENT", exit_code);
+
+    let exit_code = scheduler().wait_process(current_process_id(), usize::MAX);
+    assert!(exit_code == Err(Errno::ENOENT), "wait_process() on nonexistent process -> {:?}, expected ENOENT", exit_code);
+}
+
+/// Description: Only the parent may wait for a process, before and after the process has exited
+fn test_wait_not_child() {
+    scheduler().add_process(PARENT_PROCESS_ID, current_process_id());
+    scheduler().add_process(CHILD_PROCESS_ID, PARENT_PROCESS_ID);
+
+    let exit_code = scheduler().wait_process(current_process_id(), CHILD_PROCESS_ID);
+    assert!(exit_code == Err(Errno::ECHILD), "wait_process() on running grandchild -> {:?}, expected ECHILD", exit_code);
+
+    scheduler().process_exited(CHILD_PROCESS_ID, 3);
+    let exit_code = scheduler().wait_process(current_process_id(), CHILD_PROCESS_ID);
+    assert!(exit_code == Err(Errno::ECHILD), "wait_process() on exited grandchild -> {:?}, expected ECHILD", exit_code);
+
+    let exit_code = scheduler().wait_process(PARENT_PROCESS_ID, CHILD_PROCESS_ID);
+    assert!(exit_code == Ok(3), "wait_process() by parent -> {:?}, expected Ok(3)", exit_code);
+
+    scheduler().process_exited(PARENT_PROCESS_ID, 0);
+    scheduler().wait_process(current_process_id(), PARENT_PROCESS_ID).expect("wait_process() on exited child failed");
+}
+
+/// Description: Exit codes are dropped, when the parent exits without waiting (before or after its children)
+fn test_reap_orphans() {
+    scheduler().add_process(PARENT_PROCESS_ID, current_process_id());
+    scheduler().add_process(CHILD_PROCESS_ID, PARENT_PROCESS_ID);
+    scheduler().add_process(WAIT_PROCESS_ID, PARENT_PROCESS_ID);
+
+    scheduler().process_exited(CHILD_PROCESS_ID, 1);
+    scheduler().process_exited(PARENT_PROCESS_ID, 0);
+    scheduler().process_exited(WAIT_PROCESS_ID, 2);
+
+    for id in [CHILD_PROCESS_ID, WAIT_PROCESS_ID] {
+        let exit_code = scheduler().wait_process(PARENT_PROCESS_ID, id);
+        assert!(exit_code == Err(Errno::ENOENT), "wait_process() on orphan {} -> {:?}, expected ENOENT", id, exit_code);
+    }
+
+    let exit_code = scheduler().wait_process(current_process_id(), PARENT_PROCESS_ID);
+    assert!(exit_code == Ok(0), "wait_process() on exited parent -> {:?}, expected Ok(0)", exit_code);
+}
+
+/// Futex keys used by the futex tests (process id of a nonexistent process, so user threads cannot interfere)
+const FUTEX_PROCESS_ID: usize = usize::MAX - 3;
+static FUTEX: AtomicU32 = AtomicU32::new(0);
+static FUTEX_WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+fn futex_key() -> (usize, usize) {
+    (FUTEX_PROCESS_ID, FUTEX.as_ptr() as usize)
+}
+
+/// Description: Block on the test futex, if it contains `expected`
+fn futex_wait(expected: u32) -> Result<(), Errno> {
+    scheduler().futex_wait(futex_key(), || match FUTEX.load(SeqCst) == expected {
+        true => Ok(()),
+        false => Err(Errno::EAGAIN),
+    })
+}
+
+/// Description: A thread waiting on a futex is blocked until another thread changes the value and wakes it up
+fn test_futex_wait_wake() {
+    FUTEX.store(0, SeqCst);
+    let thread = Thread::new_kernel_thread(|| {
+        scheduler().sleep(10);
+        FUTEX.store(1, SeqCst);
+        FUTEX_WOKEN.store(scheduler().futex_wake(futex_key(), usize::MAX), SeqCst);
+        scheduler().exit(0);
+    });
+    let id = thread.id();
+    scheduler().ready(thread);
+
+    let result = futex_wait(0);
+    assert!(result.is_ok(), "futex_wait() -> {:?}, expected Ok(())", result);
+    assert_eq!(FUTEX.load(SeqCst), 1);
+
+    scheduler().join(id);
+    assert_eq!(FUTEX_WOKEN.load(SeqCst), 1);
+}
+
+/// Description: Waiting fails with EAGAIN, if the futex does not contain the expected value, waking up nobody returns 0
+fn test_futex_value_changed() {
+    FUTEX.store(1, SeqCst);
+
+    let result = futex_wait(0);
+    assert!(result == Err(Errno::EAGAIN), "futex_wait() on changed value -> {:?}, expected Err(EAGAIN)", result);
+    assert_eq!(scheduler().futex_wake(futex_key(), usize::MAX), 0);
+}
+
+/// Description: Only `count` waiting threads are woken up
+fn test_futex_wake_count() {
+    FUTEX.store(0, SeqCst);
+    let waiters = [Thread::new_kernel_thread(|| {
+        let _ = futex_wait(0);
+        scheduler().exit(0);
+    }), Thread::new_kernel_thread(|| {
+        let _ = futex_wait(0);
+        scheduler().exit(0);
+    })];
+    let ids = waiters.iter().map(|thread| thread.id()).collect::<Vec<usize>>();
+    waiters.into_iter().for_each(|thread| scheduler().ready(thread));
+    scheduler().sleep(50); // let both threads block on the futex
+
+    assert_eq!(scheduler().futex_wake(futex_key(), 1), 1);
+    assert_eq!(scheduler().futex_wake(futex_key(), usize::MAX), 1);
+    assert_eq!(scheduler().futex_wake(futex_key(), usize::MAX), 0);
+    ids.iter().for_each(|id| { scheduler().join(*id); });
+}
+
+/// Set by the thread of `test_kill_sleeping`, if it runs again after sleeping
+static KILLED_RAN: AtomicUsize = AtomicUsize::new(0);
+
+/// Description: A killed thread is removed from the sleep queue and never runs again, joining it afterwards finds nothing
+fn test_kill_sleeping() {
+    KILLED_RAN.store(0, SeqCst);
+    let thread = Thread::new_kernel_thread(|| {
+        scheduler().sleep(50);
+        KILLED_RAN.store(1, SeqCst);
+        scheduler().exit(0);
+    });
+    let id = thread.id();
+    scheduler().ready(thread);
+    scheduler().sleep(10); // let the thread go to sleep
+
+    scheduler().kill(id);
+    scheduler().sleep(100);
+    assert_eq!(KILLED_RAN.load(SeqCst), 0, "killed thread has been woken up");
+    assert!(scheduler().join(id).is_none());
+}
+
+/// Description: A killed thread is removed from the wait list of a futex, so waking up the futex finds nobody
+fn test_kill_futex_waiter() {
+    FUTEX.store(0, SeqCst);
+    let thread = Thread::new_kernel_thread(|| {
+        let _ = futex_wait(0);
+        scheduler().exit(0);
+    });
+    let id = thread.id();
+    scheduler().ready(thread);
+    scheduler().sleep(10); // let the thread block on the futex
+
+    scheduler().kill(id);
+    assert_eq!(scheduler().futex_wake(futex_key(), usize::MAX), 0);
+}
@@ -22,16 +22,20 @@
 
 use crate::memory::alloc::StackAllocator;
 use crate::memory::r#virtual::{VirtualMemoryArea, VmaType};
+use crate::memory::physical::AllocFlags;
 use crate::memory::{MemorySpace, PAGE_SIZE};
 use crate::process::process::Process;
 use crate::process::scheduler;
+use crate::process::scheduler::DEFAULT_PRIORITY;
 use crate::syscall::syscall_dispatcher::CORE_LOCAL_STORAGE_TSS_RSP0_PTR_INDEX;
 use crate::{memory, process_manager, scheduler, tss};
 use alloc::rc::Rc;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::arch::asm;
-use core::{mem, ptr};
+use core::{iter, mem, ptr};
+use core::sync::atomic::{AtomicU8, AtomicUsize};
+use core::sync::atomic::Ordering::Relaxed;
 use goblin::elf::Elf;
 use goblin::elf64;
 use spin::Mutex;
@@ -58,6 +62,11 @@ pub struct Thread {
     process: Arc<Process>, // reference to my process
     entry: fn(),           // user thread: =0;                 kernel thread: address of entry function
     user_rip: VirtAddr,    // user thread: elf-entry function; kernel thread: =0
+    priority: AtomicU8,    // scheduling priority (0 = lowest, 'MAX_PRIORITY' = highest)
+    join_result: AtomicUsize, // exit code of the thread, this thread has been waiting for in 'Scheduler::join()'
+    run_time_ns: AtomicUsize, // time the thread has been running (updated, when the thread is switched out)
+    dispatches: AtomicUsize,  // number of times the thread has been switched to
+    started_ns: AtomicUsize,  // system time, when the thread has been switched to the last time
 }
 
 impl Stacks {
@@ -87,6 +96,11 @@ impl Thread {
             process: process_manager().read() .kernel_process() .expect("Trying to create a kernel thread before process initialization!"),
             entry,
             user_rip: VirtAddr::zero(),
+            priority: AtomicU8::new(DEFAULT_PRIORITY),
+            join_result: AtomicUsize::new(0),
+            run_time_ns: AtomicUsize::new(0),
+            dispatches: AtomicUsize::new(0),
+            started_ns: AtomicUsize::new(0),
         };
 
         thread.prepare_kernel_stack();
@@ -97,10 +111,14 @@ impl Thread {
     ///
     /// Description: Parses elf_buffer for entry function and returns a reference to a prepared user thread
     ///
-    /// Parameters: `elf_buffer` elf code image
+    /// Parameters: \
+    ///   `elf_buffer` elf code image \
+    ///   `name` program name (passed as first argument) \
+    ///   `args` further arguments \
+    ///   `vars` environment variables ("NAME=value")
     ///
-    pub fn load_application(elf_buffer: &[u8], name: &str, args: &Vec<&str>) -> Rc<Thread> {
-        let process = process_manager().write().create_process();
+    pub fn load_application(elf_buffer: &[u8], name: &str, args: &Vec<&str>, vars: &Vec<&str>) -> Rc<Thread> {
+        let process = process_manager().write().create_process(name);
         let address_space = process.address_space();
 
         // Parse elf file headers and map code vma if successful
@@ -113,7 +131,8 @@ impl Thread {
                 } else {
                     (header.p_memsz as usize / PAGE_SIZE) + 1
                 };
-                let frames = memory::physical::alloc(page_count);
+                // Not zeroed here, since the frames are overwritten completely below
+                let frames = memory::physical::alloc_contiguous(page_count, 1, AllocFlags::SKIP_ZERO).expect("ELF: Not enough memory for program section");
                 let virt_start = Page::from_start_address(VirtAddr::new(header.p_vaddr)).expect("ELF: Program section not page aligned");
                 let pages = PageRange { start: virt_start, end: virt_start + page_count as u64 };
 
@@ -121,7 +140,7 @@ impl Thread {
                     let code = elf_buffer.as_ptr().offset(header.p_offset as isize);
                     let target = frames.start.start_address().as_u64() as *mut u8;
                     target.copy_from(code, header.p_filesz as usize);
-                    target.offset(header.p_filesz as isize).write_bytes(0, (header.p_memsz - header.p_filesz) as usize);
+                    target.offset(header.p_filesz as isize).write_bytes(0, page_count * PAGE_SIZE - header.p_filesz as usize); // bss and rest of last page
                 }
 
                 process.address_space().map_physical(frames, pages, MemorySpace::User, PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE);
@@ -141,44 +160,44 @@ impl Thread {
         process.add_vma(VirtualMemoryArea::new(user_stack_pages, VmaType::Stack));
 
         // create environment for the application
-        let args_size = args.iter().map(|arg| arg.len()).sum::<usize>();
+        // layout: argc, argv[0..argc], envc, envp[0..envc], followed by the NUL-terminated strings
+        // (name first, then the arguments and the environment variables)
+        let argc = args.len() + 1;
+        let strings = iter::once(&name).chain(args.iter()).chain(vars.iter());
+        let strings_size = strings.clone().map(|string| string.len() + 1).sum::<usize>();
+        let table_size = (argc + vars.len() + 2) * size_of::<usize>();
         let env_virt_start = Page::from_start_address(VirtAddr::new(USER_SPACE_ENV_START as u64)).unwrap();
-        let env_size = args_size;
+        let env_size = table_size + strings_size;
         let env_page_count = if env_size > 0 && env_size % PAGE_SIZE == 0 { env_size / PAGE_SIZE } else { (env_size / PAGE_SIZE) + 1 };
-        let env_frames = memory::physical::alloc(env_page_count);
+        let env_frames = memory::physical::alloc_contiguous(env_page_count, 1, AllocFlags::empty()).expect("Not enough memory for environment");
         let env_pages = PageRange { start: env_virt_start, end: env_virt_start + env_page_count as u64 };
 
         // map and add vma for environment of the application
         address_space.map_physical(env_frames, env_pages, MemorySpace::User, PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE);
         process.add_vma(VirtualMemoryArea::new(env_pages, VmaType::Environment));
 
-        // create argc and argv in the user space environment
+        // create argc, argv, envc and envp in the user space environment
         let env_addr = VirtAddr::new(env_frames.start.start_address().as_u64()); // Start address of user space environment
-        let argc = env_addr.as_mut_ptr::<usize>(); // First entry in environment is argc (number of arguments)
-        let argv = (env_addr + size_of::<usize>() as u64).as_mut_ptr::<*const u8>(); // Second entry in environment is argv (array of pointers to arguments)
+        let table = env_addr.as_mut_ptr::<usize>(); // argc, argv, envc and envp are all machine words
 
-        // copy arguments directly behind argv array and store pointers to them in argv
+        // copy strings directly behind the table and store pointers to them in argv and envp
         unsafe {
-            argc.write(args.len() + 1);
-
-            let args_begin = argv.offset((args.len() + 1) as isize) as *mut u8; // Physical start address of arguments (we use this address to copy them)
-            let args_begin_virt = env_virt_start.start_address() + size_of::<usize>() as u64 + ((args.len() + 1) * size_of::<usize>()) as u64; // Virtual start address of arguments (they will be visible here in user space)
-
-            // copy program name as first argument
-            args_begin.copy_from(name.as_bytes().as_ptr(), name.len());
-            args_begin.add(name.len()).write(0); // null-terminate the string for C compatibility
-            argv.write(args_begin_virt.as_ptr());
-
-            let mut offset = name.len() + 1;
-
-            // copy remaining arguments
-            for (i, arg) in args.iter().enumerate() {
-                let target = args_begin.add(offset);
-                target.copy_from(arg.as_bytes().as_ptr(), arg.len());
-                target.add(arg.len()).write(0); // null-terminate the string for C compatibility
-
-                argv.add(i + 1).write((args_begin_virt + offset as u64).as_ptr());
-                offset += arg.len() + 1;
+            table.write(argc);
+            table.add(argc + 1).write(vars.len());
+
+            let strings_begin = table.add(argc + vars.len() + 2) as *mut u8; // Physical start address of strings (we use this address to copy them)
+            let strings_begin_virt = env_virt_start.start_address() + table_size as u64; // Virtual start address of strings (they will be visible here in user space)
+
+            let mut offset = 0;
+            for (i, string) in strings.enumerate() {
+                let target = strings_begin.add(offset);
+                target.copy_from(string.as_bytes().as_ptr(), string.len());
+                target.add(string.len()).write(0); // null-terminate the string for C compatibility
+
+                // argv[i] follows argc, envp[i - argc] follows envc
+                let slot = if i < argc { i + 1 } else { i + 2 };
+                table.add(slot).write((strings_begin_virt + offset as u64).as_u64() as usize);
+                offset += string.len() + 1;
             }
         }
 
@@ -189,6 +208,11 @@ impl Thread {
             process,
             entry: unsafe { mem::transmute(ptr::null::<fn()>()) },
             user_rip: VirtAddr::new(elf.entry),
+            priority: AtomicU8::new(DEFAULT_PRIORITY),
+            join_result: AtomicUsize::new(0),
+            run_time_ns: AtomicUsize::new(0),
+            dispatches: AtomicUsize::new(0),
+            started_ns: AtomicUsize::new(0),
         };
 
         thread.prepare_kernel_stack();
@@ -229,6 +253,11 @@ impl Thread {
             process: parent,
             entry,
             user_rip: kickoff_addr,
+            priority: AtomicU8::new(DEFAULT_PRIORITY),
+            join_result: AtomicUsize::new(0),
+            run_time_ns: AtomicUsize::new(0),
+            dispatches: AtomicUsize::new(0),
+            started_ns: AtomicUsize::new(0),
         };
 
         thread.prepare_kernel_stack();
@@ -246,7 +275,7 @@ impl Thread {
         if thread.is_kernel_thread() {
             (thread.entry)();  // Directly call the entry function of kernel thread
             drop(thread);      // Manually decrease reference count, because exit() will never return
-            scheduler.exit();
+            scheduler.exit(0);
         } else {
             let thread_ptr = ptr::from_ref(thread.as_ref());
             drop(thread); // Manually decrease reference count, because switch_to_user_mode() will never return
@@ -274,13 +303,56 @@ impl Thread {
         let current_rsp0 = ptr::from_ref(&current.stacks.lock().old_rsp0) as *mut u64;
         let next_rsp0 = next.stacks.lock().old_rsp0.as_u64();
         let next_rsp0_end = next.kernel_stack_addr().as_u64();
-        let next_address_space = next.process.address_space().page_table_address().as_u64();
+        let next_address_space = next.process.address_space().cr3();
 
         unsafe {
             thread_switch(current_rsp0, next_rsp0, next_rsp0_end, next_address_space);
         }
     }
 
+    /// Description: Return the scheduling priority of this thread
+    pub fn priority(&self) -> u8 {
+        self.priority.load(Relaxed)
+    }
+
+    /// Description: Set the scheduling priority. Only call via `Scheduler::set_priority()`, which requeues the thread.
+    pub(super) fn set_priority(&self, priority: u8) {
+        self.priority.store(priority, Relaxed);
+    }
+
+    /// Description: Time the thread has been running so far (not including its current time slice)
+    pub fn run_time_ns(&self) -> usize {
+        self.run_time_ns.load(Relaxed)
+    }
+
+    /// Description: Number of times the thread has been switched to by the scheduler
+    pub fn dispatches(&self) -> usize {
+        self.dispatches.load(Relaxed)
+    }
+
+    /// Description: The scheduler switches to this thread at system time `now_ns`
+    pub(super) fn start_running(&self, now_ns: usize) {
+        self.started_ns.store(now_ns, Relaxed);
+        self.dispatches.fetch_add(1, Relaxed);
+    }
+
+    /// Description: The scheduler switches away from this thread at system time `now_ns`. Return the time it has been running.
+    pub(super) fn stop_running(&self, now_ns: usize) -> usize {
+        let ran_ns = now_ns.saturating_sub(self.started_ns.load(Relaxed));
+        self.run_time_ns.fetch_add(ran_ns, Relaxed);
+        ran_ns
+    }
+
+    /// Description: Exit code of the thread joined last (see 'Scheduler::join()')
+    pub(super) fn join_result(&self) -> usize {
+        self.join_result.load(Relaxed)
+    }
+
+    /// Description: Pass the exit code of a joined thread to this (waiting) thread
+    pub(super) fn set_join_result(&self, exit_code: usize) {
+        self.join_result.store(exit_code, Relaxed);
+    }
+
     /// Description: Check if stacks are locked
     pub fn stacks_locked(&self) -> bool {
         self.stacks.is_locked()
@@ -388,10 +460,9 @@ impl Thread {
             let user_stack_addr = stacks.user_stack.as_ptr() as u64;
             let capacity = stacks.kernel_stack.capacity();
 
-            // init stack with 0s
-            for _ in 0..stacks.user_stack.capacity() {
-                stacks.user_stack.push(0);
-            }
+            // The stack pages have been zeroed when they were mapped (the kernel must not write to them with SMAP enabled)
+            let capacity_user = stacks.user_stack.capacity();
+            unsafe { stacks.user_stack.set_len(capacity_user); }
 
             stacks.kernel_stack[capacity - 6] = self.user_rip.as_u64(); // Address of entry point for user thread
 
@@ -0,0 +1,133 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: shutdown                                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Orderly shutdown of the system before power off or reboot.      ║
+   ║         The steps are executed in the following order:                  ║
+   ║           1. Refuse creation of new threads and processes and notify    ║
+   ║              user processes (they see 'shutdown_pending()')             ║
+   ║           2. Wait for user processes to exit (bounded by a timeout),    ║
+   ║              remaining processes are killed afterwards                  ║
+   ║           3. Sync the name space (check for unfinished operations)      ║
+   ║           4. Release the swap area                                      ║
+   ║           5. Persist non-volatile memory (incl. the memory controller)  ║
+   ║           6. Disable interrupts                                         ║
+   ║         Only processes running as root may shut down the system.        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Relaxed, SeqCst};
+use log::{info, warn};
+use uefi::Status;
+use uefi::table::runtime::ResetType;
+use x86_64::instructions::{hlt, interrupts};
+use x86_64::instructions::port::PortWriteOnly;
+use crate::boot_state::{self, BootReason};
+use crate::memory::{nvmem, swap};
+use crate::naming::name_service;
+use crate::{efi_system_table, process_manager, scheduler, timer};
+
+/// Time given to user processes to exit on their own, before they are killed
+pub const SHUTDOWN_TIMEOUT_MS: usize = 1000;
+
+static SHUTDOWN_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Description: Check whether the system is shutting down (no new threads may be started)
+pub fn in_progress() -> bool {
+    SHUTDOWN_IN_PROGRESS.load(Relaxed)
+}
+
+///
+/// Description:
+///    Shut down all subsystems in a defined order. Must be called before the
+///    machine is powered off or reset. Interrupts are disabled on return.
+///    Calling this function more than once has no further effect.
+///
+pub fn shutdown() {
+    if SHUTDOWN_IN_PROGRESS.swap(true, SeqCst) {
+        interrupts::disable();
+        return;
+    }
+
+    // From now on, 'in_progress()' notifies user processes (system call 'ShutdownPending'), that they should exit
+    info!("Shutting down, asking user processes to exit");
+
+    // Give user processes the chance to exit; the calling process is not waited for
+    let caller = process_manager().read().current_process().id();
+    let deadline = timer().systime_ms() + SHUTDOWN_TIMEOUT_MS;
+    while remaining_processes(caller) > 0 && timer().systime_ms() < deadline {
+        scheduler().sleep(10);
+    }
+
+    // Force exit of all processes, that did not terminate in time
+    let kernel = process_manager().read().kernel_process().map(|process| process.id());
+    let ids = process_manager().read().active_process_ids();
+    for id in ids.into_iter().filter(|id| Some(*id) != kernel && *id != caller) {
+        warn!("Killing process [{}], which did not exit in time", id);
+        process_manager().write().kill(id);
+    }
+
+    // A killed process may have been interrupted during an operation on the name space
+    if !name_service::sync() {
+        warn!("Name space is still locked by a killed process");
+    }
+
+    // Swapped out pages are not needed anymore (returns non-volatile memory used for swapping)
+    swap::release();
+
+    // Write back cached data to non-volatile memory and drain the write queues of the NVDIMMs
+    nvmem::flush();
+
+    interrupts::disable();
+}
+
+///
+/// Description: Shut down the system and power off the machine. Does not return.
+///
+pub fn poweroff() -> ! {
+    boot_state::record(BootReason::PowerOff);
+    shutdown();
+
+    if let Some(efi_system_table) = efi_system_table() {
+        let system_table = efi_system_table.read();
+        unsafe { system_table.runtime_services() }.reset(ResetType::SHUTDOWN, Status::SUCCESS, None);
+    }
+
+    // Fallback: ACPI power off for QEMU (PM1a control register)
+    unsafe { PortWriteOnly::<u16>::new(0x604).write(0x2000); }
+    halt()
+}
+
+///
+/// Description: Shut down the system and reset the machine. Does not return.
+///
+pub fn reboot() -> ! {
+    boot_state::record(BootReason::Reboot);
+    shutdown();
+
+    if let Some(efi_system_table) = efi_system_table() {
+        let system_table = efi_system_table.read();
+        unsafe { system_table.runtime_services() }.reset(ResetType::COLD, Status::SUCCESS, None);
+    }
+
+    // Fallback: Pulse reset line via the PS/2 controller
+    unsafe { PortWriteOnly::<u8>::new(0x64).write(0xfe); }
+    halt()
+}
+
+/// Description: Number of user processes still running, except `caller`
+fn remaining_processes(caller: usize) -> usize {
+    let process_manager = process_manager().read();
+    let kernel = process_manager.kernel_process().map(|process| process.id());
+
+    process_manager.active_process_ids().iter()
+        .filter(|id| Some(**id) != kernel && **id != caller)
+        .count()
+}
+
+fn halt() -> ! {
+    loop {
+        hlt();
+    }
+}
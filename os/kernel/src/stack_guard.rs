@@ -0,0 +1,85 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: stack_guard                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Runtime support for the stack protector of the compiler (the    ║
+   ║         kernel is built with '-Z stack-protector=strong'). Functions    ║
+   ║         with buffers on the stack store the guard value (canary) below  ║
+   ║         their return address and check it before returning. If it has   ║
+   ║         been overwritten, '__stack_chk_fail' is called, which panics.   ║
+   ║         LLVM reads the guard from '__stack_chk_guard' or, for Linux     ║
+   ║         targets (like ours), from 'fs:0x28'. Thus, the FS base points   ║
+   ║         0x28 bytes below '__stack_chk_guard' (set in 'boot.asm').       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::ptr;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use x86_64::registers::model_specific::FsBase;
+use x86_64::VirtAddr;
+use crate::scheduler;
+
+/// Offset of the guard value relative to the FS base for Linux targets
+const FS_GUARD_OFFSET: u64 = 0x28;
+
+/// Exit code of a thread, which is terminated instead of panicking (see `terminate_on_failure()`)
+pub const STACK_SMASHED_EXIT_CODE: usize = 0xdead;
+
+const NO_THREAD: usize = usize::MAX;
+
+/// Guard value (canary), set once during booting
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static mut __stack_chk_guard: u64 = 0x595e9fbd94fda700;
+
+/// Number of detected stack overflows (only counted for threads, which are terminated instead of panicking)
+static DETECTED: AtomicUsize = AtomicUsize::new(0);
+/// Thread, which is terminated instead of panicking (for testing the stack protector)
+static TERMINATE_THREAD: AtomicUsize = AtomicUsize::new(NO_THREAD);
+
+///
+/// Description:
+///    Set the guard value. Must be called at the very beginning of booting, because protected
+///    functions, which have been entered before and return afterwards, detect a mismatch.
+///    This function has no buffers on the stack, so it is not protected itself.
+///    The lowest byte of the guard is zero, so string operations stop before reading beyond it.
+///
+/// Parameters: `random` new guard value (should be random, e.g. from `rng::random_u64()`)
+///
+pub fn init(random: u64) {
+    unsafe { ptr::addr_of_mut!(__stack_chk_guard).write_volatile(random & !0xff); }
+}
+
+/// Description: Check that the FS base points to the guard value (as set in 'boot.asm')
+pub fn is_fs_base_valid() -> bool {
+    FsBase::read() == VirtAddr::new(ptr::addr_of!(__stack_chk_guard) as u64 - FS_GUARD_OFFSET)
+}
+
+///
+/// Description:
+///    Terminate thread `thread_id` with `STACK_SMASHED_EXIT_CODE`, if the stack protector
+///    detects an overflow, instead of panicking (used by the tests).
+///
+pub fn terminate_on_failure(thread_id: usize) {
+    TERMINATE_THREAD.store(thread_id, Relaxed);
+}
+
+/// Description: Number of overflows, detected in threads registered with `terminate_on_failure()`
+pub fn detected() -> usize {
+    DETECTED.load(Relaxed)
+}
+
+/// Description: Called by protected functions, if the guard value on the stack has been overwritten
+#[unsafe(no_mangle)]
+pub extern "C" fn __stack_chk_fail() -> ! {
+    let terminate_thread = TERMINATE_THREAD.load(Relaxed);
+    if terminate_thread != NO_THREAD && scheduler().current_thread().id() == terminate_thread {
+        TERMINATE_THREAD.store(NO_THREAD, Relaxed);
+        DETECTED.fetch_add(1, Relaxed);
+        scheduler().exit(STACK_SMASHED_EXIT_CODE);
+        unreachable!("Thread has not exited!");
+    }
+
+    panic!("Stack smashing detected: the guard value of a function on the current stack has been overwritten!");
+}
@@ -0,0 +1,80 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: stack_guard_tests                                               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the stack protector: a function overflowing a buffer on    ║
+   ║         its stack must call '__stack_chk_fail' instead of returning to  ║
+   ║         a corrupted address. The overflow is done in a separate thread, ║
+   ║         which is terminated by the failure handler (instead of a panic).║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::hint::black_box;
+use core::ptr;
+use ::log::info;
+
+use crate::process::thread::Thread;
+use crate::scheduler;
+use crate::stack_guard::{self, __stack_chk_guard, STACK_SMASHED_EXIT_CODE};
+
+const BUFFER_SIZE: usize = 16;
+
+///
+/// Description:
+///    Run all stack guard tests (requires the scheduler)
+///
+pub fn run_tests() {
+    info!("stack_guard: running tests");
+
+    test_guard();
+    test_no_overflow();
+    test_overflow_detected();
+
+    info!("stack_guard: all tests passed.");
+}
+
+///
+/// Description: Write `count` bytes into a buffer of `BUFFER_SIZE` bytes on the stack
+///              (more than `BUFFER_SIZE` bytes overwrite the guard value behind it)
+///
+#[inline(never)]
+fn fill_buffer(count: usize) -> u8 {
+    let mut buffer = [0u8; BUFFER_SIZE];
+    let start = black_box(buffer.as_mut_ptr());
+    for i in 0..count {
+        unsafe { start.add(i).write_volatile(0x41); }
+    }
+
+    unsafe { start.read_volatile() }
+}
+
+/// Description: The guard is set from the RNG (lowest byte zero) and the FS base points to it
+fn test_guard() {
+    let guard = unsafe { ptr::addr_of!(__stack_chk_guard).read_volatile() };
+    assert_eq!(guard & 0xff, 0);
+    assert_ne!(guard, 0);
+    assert!(stack_guard::is_fs_base_valid());
+}
+
+/// Description: Functions, which stay within their buffers, return normally
+fn test_no_overflow() {
+    let detected = stack_guard::detected();
+    assert_eq!(fill_buffer(BUFFER_SIZE), 0x41);
+    assert_eq!(stack_guard::detected(), detected);
+}
+
+/// Description: Overwriting the guard value calls the failure handler (which terminates the thread)
+fn test_overflow_detected() {
+    let detected = stack_guard::detected();
+    let thread = Thread::new_kernel_thread(|| {
+        fill_buffer(BUFFER_SIZE + 32);
+        scheduler().exit(0); // not reached, if the overflow is detected
+    });
+    let id = thread.id();
+    stack_guard::terminate_on_failure(id);
+    scheduler().ready(thread);
+
+    let exit_code = scheduler().join(id);
+    assert!(exit_code == Some(STACK_SMASHED_EXIT_CODE), "join() -> {:?}, expected Some({})", exit_code, STACK_SMASHED_EXIT_CODE);
+    assert_eq!(stack_guard::detected(), detected + 1);
+}
@@ -0,0 +1,4 @@
+pub mod mutex;
+pub mod mutex_tests;
+
+pub use mutex::{Mutex, MutexGuard};
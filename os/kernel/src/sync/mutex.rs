@@ -0,0 +1,147 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: mutex                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Spinlock based mutex, which remembers where it has been locked. ║
+   ║         Besides the unbounded 'lock()', it offers 'try_lock_for()',     ║
+   ║         which gives up after a timeout (measured with the monotonic     ║
+   ║         clock) and logs the current holder. This turns would-be         ║
+   ║         deadlocks into loud diagnostics.                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::panic::Location;
+use core::ptr;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering::Relaxed;
+use core::time::Duration;
+use log::warn;
+use x86_64::instructions::interrupts;
+use crate::device::clock::ClockSource;
+use crate::{clock, timer};
+
+pub struct Mutex<T: ?Sized> {
+    holder: AtomicPtr<Location<'static>>,
+    inner: spin::Mutex<T>,
+}
+
+pub struct MutexGuard<'a, T: ?Sized + 'a> {
+    holder: &'a AtomicPtr<Location<'static>>,
+    inner: spin::MutexGuard<'a, T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self { holder: AtomicPtr::new(ptr::null_mut()), inner: spin::Mutex::new(value) }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Description: Acquire the lock, spinning as long as necessary (without a time limit).
+    #[track_caller]
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        let guard = self.inner.lock();
+        self.guard(guard, Location::caller())
+    }
+
+    /// Description: Try to acquire the lock once, without waiting.
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let location = Location::caller();
+        self.inner.try_lock().map(|guard| self.guard(guard, location))
+    }
+
+    ///
+    /// Description:
+    ///    Try to acquire the lock until `timeout` has elapsed (measured with the
+    ///    monotonic clock, see `init_clock()`). On timeout, the location of the
+    ///    current holder is logged and `None` is returned.
+    ///
+    /// Parameters: `timeout` maximum time to wait for the lock
+    ///
+    #[track_caller]
+    pub fn try_lock_for(&self, timeout: Duration) -> Option<MutexGuard<'_, T>> {
+        let location = Location::caller();
+        let timeout_ns = timeout.as_nanos() as u64;
+
+        // The PIT based clock is only advanced by the timer interrupt, otherwise we count the waited milliseconds ourselves
+        let use_clock = matches!(clock().source(), ClockSource::Tsc { .. }) || interrupts::are_enabled();
+        let start_ns = if use_clock { clock().now_ns() } else { 0 };
+        let mut waited_ns = 0;
+
+        loop {
+            if let Some(guard) = self.inner.try_lock() {
+                return Some(self.guard(guard, location));
+            }
+
+            if waited_ns >= timeout_ns {
+                break;
+            }
+
+            if use_clock {
+                spin_loop();
+                waited_ns = clock().now_ns() - start_ns;
+            } else {
+                timer().wait(1);
+                waited_ns += 1000000;
+            }
+        }
+
+        match self.holder() {
+            Some(holder) => warn!("Mutex: Timeout after [{} ms] at [{}] (locked at [{}])", timeout.as_millis(), location, holder),
+            None => warn!("Mutex: Timeout after [{} ms] at [{}] (holder unknown)", timeout.as_millis(), location),
+        }
+
+        None
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Description: Return the source location, where the lock has been acquired (if locked)
+    pub fn holder(&self) -> Option<&'static Location<'static>> {
+        unsafe { self.holder.load(Relaxed).as_ref() }
+    }
+
+    ///
+    /// Description: Force unlock this mutex.
+    ///
+    /// # Safety
+    /// Only call this, if the lock is not held by anyone else.
+    ///
+    pub unsafe fn force_unlock(&self) {
+        self.holder.store(ptr::null_mut(), Relaxed);
+        unsafe { self.inner.force_unlock(); }
+    }
+
+    fn guard<'a>(&'a self, inner: spin::MutexGuard<'a, T>, location: &'static Location<'static>) -> MutexGuard<'a, T> {
+        self.holder.store(ptr::from_ref(location).cast_mut(), Relaxed);
+        MutexGuard { holder: &self.holder, inner }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.holder.store(ptr::null_mut(), Relaxed);
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner.deref()
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.inner.deref_mut()
+    }
+}
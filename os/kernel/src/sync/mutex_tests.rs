@@ -0,0 +1,60 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: mutex_tests                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the timeout-bounded lock acquisition of the kernel mutex.  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::time::Duration;
+use ::log::info;
+
+use crate::clock;
+use crate::sync::Mutex;
+
+///
+/// Description:
+///    Run all mutex tests (requires the monotonic clock)
+///
+pub fn run_tests() {
+    info!("mutex: running tests");
+
+    test_try_lock_for_free();
+    test_try_lock_for_timeout();
+    test_holder();
+
+    info!("mutex: all tests passed.");
+}
+
+/// Description: Acquiring a free mutex with a timeout should succeed immediately
+fn test_try_lock_for_free() {
+    let mutex = Mutex::new(0);
+    let r = mutex.try_lock_for(Duration::from_millis(10));
+    assert!(r.is_some(), "try_lock_for() on free mutex -> None");
+}
+
+/// Description: Acquiring a held mutex should fail, but only after the timeout has elapsed
+fn test_try_lock_for_timeout() {
+    let mutex = Mutex::new(0);
+    let _guard = mutex.lock();
+
+    let start = clock().now_ns();
+    let r = mutex.try_lock_for(Duration::from_millis(20));
+    let elapsed_ms = (clock().now_ns() - start) / 1000000;
+
+    assert!(r.is_none(), "try_lock_for() on held mutex -> Some");
+    assert!(elapsed_ms >= 20, "try_lock_for() returned after {} ms, expected >= 20 ms", elapsed_ms);
+    assert!(mutex.holder().is_some(), "holder() of mutex after timeout -> None");
+}
+
+/// Description: The holder location must be tracked while locked and cleared after unlock
+fn test_holder() {
+    let mutex = Mutex::new(0);
+    {
+        let _guard = mutex.lock();
+        assert!(mutex.holder().is_some(), "holder() of locked mutex -> None");
+    }
+
+    assert!(mutex.holder().is_none(), "holder() of unlocked mutex -> Some");
+    assert!(mutex.try_lock_for(Duration::from_millis(10)).is_some(), "try_lock_for() after unlock -> None");
+}
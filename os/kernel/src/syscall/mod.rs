@@ -7,10 +7,18 @@
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 
+pub mod sys_log;
 pub mod sys_naming;
+pub mod sys_power;
+pub mod sys_stats;
 pub mod sys_terminal;
 pub mod sys_concurrent;
 pub mod sys_time;
 pub mod sys_vmem;
 
 pub mod syscall_dispatcher;
+pub mod sys_naming_tests;
+pub mod sys_terminal_tests;
+pub mod sys_time_tests;
+pub mod sys_vmem_tests;
+pub mod syscall_builder_tests;
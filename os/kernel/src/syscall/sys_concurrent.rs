@@ -1,18 +1,24 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: sys_concurrent                                                  ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: All system calls related to processes and threads.              ║
+   ║ Descr.: All system calls related to processes and threads, including    ║
+   ║         the futex system calls for synchronizing user threads.          ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, 30.8.2024, HHU                                  ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 use alloc::vec::Vec;
 use alloc::rc::Rc;
-use core::ptr::slice_from_raw_parts;
-use core::str::from_utf8;
+use alloc::string::String;
+use core::mem::{size_of, ManuallyDrop, MaybeUninit};
+use core::slice;
 use x86_64::VirtAddr;
+use syscall::process::ProcessInfo;
 use syscall::return_vals::Errno;
-use crate::{initrd, process_manager, scheduler};
+use crate::{initrd, process_manager, scheduler, shutdown};
+use crate::memory::user;
+use crate::process::auth;
+use crate::process::scheduler::MAX_PRIORITY;
 use crate::process::thread::Thread;
 
 
@@ -20,13 +26,71 @@ pub fn sys_process_id() -> isize {
     process_manager().read().current_process().id() as isize
 }
 
-pub fn sys_process_exit() -> isize {
-    scheduler().current_thread().process().exit();
-    scheduler().exit();
+pub fn sys_process_exit(exit_code: isize) -> isize {
+    scheduler().current_thread().process().exit(exit_code);
+    // Exit code of the thread is the 32-bit pattern of the process exit code (must not look like an Errno)
+    scheduler().exit(exit_code as i32 as u32 as usize);
     0
 }
 
+/// Return the user id of the current process
+pub fn sys_get_uid() -> isize {
+    process_manager().read().current_process().credentials().uid as isize
+}
+
+/// Change the user id of the current process (only root may switch to another user)
+pub fn sys_set_uid(uid: u32) -> isize {
+    match process_manager().read().current_process().set_uid(uid) {
+        Ok(()) => 0,
+        Err(errno) => errno.into(),
+    }
+}
+
+///
+/// Description: Check the password of the user `user` (does not change the user id of the current process).
+///
+/// Return: the user id or `Errno::EACCES` (for unknown users and wrong passwords)
+///
+pub fn sys_authenticate(user_buff: *const u8, user_buff_len: usize, password_buff: *const u8, password_buff_len: usize) -> isize {
+    let user = match user::string_from_user(user_buff, user_buff_len) {
+        Ok(user) => user,
+        Err(errno) => return errno.into(),
+    };
+    let password = match user::string_from_user(password_buff, password_buff_len) {
+        Ok(password) => password,
+        Err(errno) => return errno.into(),
+    };
+
+    match auth::authenticate(&user, &password) {
+        Ok(credentials) => credentials.uid as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+///
+/// Description: Wait for the child process `id` to exit. The exit code of a process can only be retrieved once.
+///
+/// Return: exit code of the process (32-bit pattern), `Errno::ENOENT` if there is no such process
+///         (or its exit code has already been retrieved), `Errno::ECHILD` if it has not been created
+///         by the calling process or `Errno::EINVAL` if the calling process waits for itself
+///
+pub fn sys_process_wait(id: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    if id == process_id {
+        return Errno::EINVAL.into();
+    }
+
+    match scheduler().wait_process(process_id, id) {
+        Ok(exit_code) => exit_code as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
 pub fn sys_thread_create(kickoff_addr: u64, entry: fn()) -> isize {
+    if shutdown::in_progress() {
+        return Errno::EACCES.into();
+    }
+
     let thread = Thread::new_user_thread(process_manager().read().current_process(), VirtAddr::new(kickoff_addr), entry);
     let id = thread.id();
 
@@ -34,6 +98,29 @@ pub fn sys_thread_create(kickoff_addr: u64, entry: fn()) -> isize {
     id as isize
 }
 
+///
+/// Description: Set the scheduling priority of thread `id` (must belong to the calling process).
+///
+/// Return: 0, `Errno::EINVAL` if `priority` exceeds `MAX_PRIORITY`, `Errno::EACCES` if a process
+///         not running as root raises a thread above `DEFAULT_PRIORITY` or `Errno::ENOENT` if
+///         there is no such thread in the calling process
+///
+pub fn sys_thread_set_priority(id: usize, priority: usize) -> isize {
+    if priority > MAX_PRIORITY as usize {
+        return Errno::EINVAL.into();
+    }
+
+    let process = process_manager().read().current_process();
+    if let Err(errno) = process.credentials().may_set_priority(priority as u8) {
+        return errno.into();
+    }
+
+    match scheduler().set_priority(process.id(), id, priority as u8) {
+        true => 0,
+        false => Errno::ENOENT.into(),
+    }
+}
+
 pub fn sys_thread_id() -> isize {
     scheduler().current_thread().id() as isize
 }
@@ -43,29 +130,171 @@ pub fn sys_thread_switch() -> isize {
     0
 }
 
+pub fn sys_thread_yield() -> isize {
+    scheduler().yield_thread();
+    0
+}
+
 pub fn sys_thread_sleep(ms: usize) -> isize {
     scheduler().sleep(ms);
     0
 }
 
+///
+/// Description:
+///    Block the calling thread on the futex at `addr` (an aligned `u32` in user memory), if it
+///    still contains `expected`, until another thread of the process calls `sys_futex_wake()`
+///    on the same address. The value is compared, while wake-ups are excluded.
+///
+/// Return: 0 after being woken up, `Errno::EAGAIN` if the value is not `expected`,
+///         `Errno::EINVAL` if `addr` is not aligned or `Errno::EFAULT` if it is no user memory
+///
+pub fn sys_futex_wait(addr: *const u32, expected: u32) -> isize {
+    if !addr.is_aligned() {
+        return Errno::EINVAL.into();
+    }
+
+    let process_id = process_manager().read().current_process().id();
+    let check = || {
+        let mut value = [0; size_of::<u32>()];
+        user::copy_from_user(&mut value, addr as *const u8)?;
+        match u32::from_ne_bytes(value) == expected {
+            true => Ok(()),
+            false => Err(Errno::EAGAIN),
+        }
+    };
+
+    match scheduler().futex_wait((process_id, addr as usize), check) {
+        Ok(()) => 0,
+        Err(errno) => errno.into(),
+    }
+}
+
+///
+/// Description: Wake up at most `count` threads of the calling process waiting on the futex at `addr`.
+///
+/// Return: number of threads woken up, `Errno::EINVAL` if `addr` is not aligned
+///
+pub fn sys_futex_wake(addr: *const u32, count: usize) -> isize {
+    if !addr.is_aligned() {
+        return Errno::EINVAL.into();
+    }
+
+    let process_id = process_manager().read().current_process().id();
+    scheduler().futex_wake((process_id, addr as usize), count) as isize
+}
+
+///
+/// Description: Wait for thread `id` to terminate.
+///
+/// Return: exit code of the thread, `Errno::ENOENT` if there is no such thread
+///         or `Errno::EINVAL` if the calling thread tries to join itself
+///
 pub fn sys_thread_join(id: usize) -> isize {
-    scheduler().join(id);
-    0
+    if id == scheduler().current_thread().id() {
+        return Errno::EINVAL.into();
+    }
+
+    match scheduler().join(id) {
+        Some(exit_code) => exit_code as isize,
+        None => Errno::ENOENT.into(),
+    }
 }
 
-pub fn sys_thread_exit() -> isize {
-    scheduler().exit();
+/// Description: Terminate the calling thread. `exit_code` must not exceed `isize::MAX` (would look like an Errno).
+pub fn sys_thread_exit(exit_code: usize) -> isize {
+    if exit_code > isize::MAX as usize {
+        return Errno::EINVAL.into();
+    }
+
+    scheduler().exit(exit_code);
     0
 }
 
-pub fn sys_process_execute_binary(name_buffer: *const u8, name_length: usize, args: *const Vec<&str>) -> isize {
-    let app_name = from_utf8(unsafe { slice_from_raw_parts(name_buffer, name_length).as_ref().unwrap() }).unwrap();
+///
+/// Description:
+///    Copy the infos of all processes to the user buffer `buffer` with space for `capacity` entries.
+///    If `buffer` is null, nothing is copied (to find out the required capacity).
+///
+/// Return: number of processes or `Errno::ENOMEM`, if `capacity` is too small (nothing is copied)
+///
+pub fn sys_process_list(buffer: *mut ProcessInfo, capacity: usize) -> isize {
+    let infos = process_manager().read().process_infos();
+    if buffer.is_null() {
+        return infos.len() as isize;
+    }
+    if capacity < infos.len() {
+        return Errno::ENOMEM.into();
+    }
+
+    for (i, info) in infos.iter().enumerate() {
+        if let Err(errno) = user::write_to_user(buffer.wrapping_add(i), *info) {
+            return errno.into();
+        }
+    }
+
+    infos.len() as isize
+}
+
+///
+/// Description: Start the application `name` from the ramdisk in a new process.
+///
+/// Parameters: \
+///    `name_buffer`, `name_length` name of the application \
+///    `args` arguments (without the program name) \
+///    `vars` environment variables ("NAME=value") of the new process
+///
+/// Return: id of the new process, `Errno::ENOENT` if there is no such application,
+///         `Errno::EFAULT` for invalid pointers or `Errno::EACCES` during a shutdown
+///
+pub fn sys_process_execute_binary(name_buffer: *const u8, name_length: usize, args: *const Vec<&str>, vars: *const Vec<&str>) -> isize {
+    if shutdown::in_progress() {
+        return Errno::EACCES.into();
+    }
+
+    let app_name = match user::string_from_user(name_buffer, name_length) {
+        Ok(app_name) => app_name,
+        Err(errno) => return errno.into(),
+    };
+    let args = match args_from_user(args) {
+        Ok(args) => args,
+        Err(errno) => return errno.into(),
+    };
+    let vars = match args_from_user(vars) {
+        Ok(vars) => vars,
+        Err(errno) => return errno.into(),
+    };
+    let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+    let vars = vars.iter().map(String::as_str).collect::<Vec<&str>>();
+
     match initrd().entries().find(|entry| entry.filename().as_str().unwrap() == app_name) {
         Some(app) => {
-            let thread = Thread::load_application(app.data(), app_name, unsafe { args.as_ref().unwrap() });
+            let thread = Thread::load_application(app.data(), &app_name, &args, &vars);
             scheduler().ready(Rc::clone(&thread));
-            thread.id() as isize
+            thread.process().id() as isize
         }
         None => Errno::ENOENT.into(),
     }
 }
+
+/// Description: Copy the argument (or environment) vector `args` of the calling process (including all strings) into kernel memory
+fn args_from_user(args: *const Vec<&str>) -> Result<Vec<String>, Errno> {
+    // Copy the vector itself, which only contains the pointer to the argument slices
+    let mut vector = MaybeUninit::<Vec<&str>>::uninit();
+    user::copy_from_user(unsafe { slice::from_raw_parts_mut(vector.as_mut_ptr() as *mut u8, size_of::<Vec<&str>>()) }, args as *const u8)?;
+    let vector = ManuallyDrop::new(unsafe { vector.assume_init() }); // owned by the user process, must not be dropped
+
+    // Copy the argument slices
+    let bytes = vector.len().checked_mul(size_of::<&str>()).ok_or(Errno::EFAULT)?;
+    if bytes > 0 && !user::is_user_memory(vector.as_ptr() as usize, bytes) {
+        return Err(Errno::EFAULT); // check before allocating memory for a bogus length
+    }
+    let mut slices: Vec<&str> = Vec::with_capacity(vector.len());
+    user::copy_from_user(unsafe { slice::from_raw_parts_mut(slices.as_mut_ptr() as *mut u8, bytes) }, vector.as_ptr() as *const u8)?;
+    unsafe { slices.set_len(vector.len()); }
+
+    // Copy the strings, the slices still point to user memory
+    slices.iter()
+        .map(|arg| user::string_from_user(arg.as_ptr(), arg.len()))
+        .collect()
+}
@@ -0,0 +1,41 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_log                                                         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: System call for writing messages of applications to the kernel  ║
+   ║         log (e.g. panic messages).                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use log::{log, Level};
+use syscall::return_vals::Errno;
+use crate::memory::user;
+use crate::scheduler;
+
+///
+/// Description: Write a message of the calling thread to the kernel log.
+///
+/// Parameters: \
+///    `level` 1 = error, 2 = warn, 3 = info, 4 = debug, 5 = trace \
+///    `buffer` UTF-8 message \
+///    `length` length of message in bytes
+///
+pub fn sys_log(level: usize, buffer: *const u8, length: usize) -> isize {
+    let level = match level {
+        1 => Level::Error,
+        2 => Level::Warn,
+        3 => Level::Info,
+        4 => Level::Debug,
+        5 => Level::Trace,
+        _ => return Errno::EINVAL.into(),
+    };
+
+    let message = match user::string_from_user(buffer, length) {
+        Ok(message) => message,
+        Err(errno) => return errno.into(),
+    };
+
+    let thread = scheduler().current_thread();
+    log!(level, "[Process {}][Thread {}] {}", thread.process().id(), thread.id(), message);
+    0
+}
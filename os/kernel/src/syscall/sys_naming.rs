@@ -6,34 +6,163 @@
    ║ Author: Michael Schoettner, 30.8.2024, HHU                              ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
-use alloc::vec;
-use core::ptr::slice_from_raw_parts;
-use core::str::from_utf8;
-use syscall::return_vals::{convert_syscall_result_to_ret_code};
+use alloc::vec::Vec;
+use syscall::return_vals::{convert_syscall_result_to_ret_code, Errno};
 
+use crate::memory::user;
+use crate::naming::file_lock::LockType;
 use crate::naming::name_service;
+use crate::naming::name_service::EntryKind;
+use crate::process_manager;
 
 
 
-pub fn sys_mkentry(
-    path_buff: *const u8,
-    path_buff_len: usize,
-    name_buff: *const u8,
-    name_buff_len: usize
-) -> isize {
-    let path = from_utf8(unsafe {
-        slice_from_raw_parts(path_buff, path_buff_len)
-            .as_ref()
-            .unwrap()
-    })
-    .unwrap();
-    let name = from_utf8(unsafe {
-        slice_from_raw_parts(name_buff, name_buff_len)
-            .as_ref()
-            .unwrap()
-    })
-    .unwrap();
-
-    let r = name_service::mkentry(path, name, vec![1]);
-    convert_syscall_result_to_ret_code(r)
+/// Create the empty container or directory `path` (`kind` 0 = container, 1 = directory, see `name_service::EntryKind`)
+pub fn sys_mkentry(path_buff: *const u8, path_buff_len: usize, kind: usize) -> isize {
+    let kind = match kind {
+        0 => EntryKind::File,
+        1 => EntryKind::Directory,
+        _ => return Errno::EINVAL.into(),
+    };
+
+    match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => convert_syscall_result_to_ret_code(name_service::create(&path, kind)),
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Change the working directory of the current process to `path` (absolute or relative)
+pub fn sys_chdir(path_buff: *const u8, path_buff_len: usize) -> isize {
+    let path = match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => path,
+        Err(errno) => return errno.into(),
+    };
+
+    convert_syscall_result_to_ret_code(name_service::chdir(&path))
+}
+
+/// Copy the working directory of the current process into `buffer`. Returns the length of the path.
+/// Fails with `EINVAL`, if the path is longer than `length`.
+pub fn sys_getcwd(buffer: *mut u8, length: usize) -> isize {
+    let cwd = process_manager().read().current_process().cwd();
+    if cwd.len() > length {
+        return Errno::EINVAL.into();
+    }
+
+    match user::copy_to_user(buffer, cwd.as_bytes()) {
+        Ok(()) => cwd.len() as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Set the creation mask of the current process to `mask`. Returns the previous mask.
+pub fn sys_set_umask(mask: u32) -> isize {
+    name_service::set_umask(mask) as isize
+}
+
+/// Set the permission bits of the entry `path` to `permissions`
+pub fn sys_chmod(path_buff: *const u8, path_buff_len: usize, permissions: u32) -> isize {
+    match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => convert_syscall_result_to_ret_code(name_service::chmod(&path, permissions)),
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Set the owning user and group of the entry `path`
+pub fn sys_chown(path_buff: *const u8, path_buff_len: usize, uid: u32, gid: u32) -> isize {
+    match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => convert_syscall_result_to_ret_code(name_service::chown(&path, uid, gid)),
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Create the symbolic link `link` pointing to `target`
+pub fn sys_symlink(target_buff: *const u8, target_buff_len: usize, link_buff: *const u8, link_buff_len: usize) -> isize {
+    let target = user::string_from_user(target_buff, target_buff_len);
+    let link = user::string_from_user(link_buff, link_buff_len);
+
+    let (target, link) = match (target, link) {
+        (Ok(target), Ok(link)) => (target, link),
+        (Err(errno), _) | (_, Err(errno)) => return errno.into(),
+    };
+
+    convert_syscall_result_to_ret_code(name_service::symlink(&target, &link))
+}
+
+/// Create the hard link `new_path` for the container `existing`
+pub fn sys_link(existing_buff: *const u8, existing_buff_len: usize, new_buff: *const u8, new_buff_len: usize) -> isize {
+    let existing = user::string_from_user(existing_buff, existing_buff_len);
+    let new_path = user::string_from_user(new_buff, new_buff_len);
+
+    let (existing, new_path) = match (existing, new_path) {
+        (Ok(existing), Ok(new_path)) => (existing, new_path),
+        (Err(errno), _) | (_, Err(errno)) => return errno.into(),
+    };
+
+    convert_syscall_result_to_ret_code(name_service::link(&existing, &new_path))
+}
+
+/// Set or release an advisory lock on the container `path` (`lock_type` see `file_lock::LockType`)
+pub fn sys_file_lock(path_buff: *const u8, path_buff_len: usize, lock_type: usize, blocking: bool) -> isize {
+    let path = match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => path,
+        Err(errno) => return errno.into(),
+    };
+    let lock_type = match LockType::try_from(lock_type) {
+        Ok(lock_type) => lock_type,
+        Err(errno) => return errno.into(),
+    };
+
+    convert_syscall_result_to_ret_code(name_service::flock(&path, lock_type, blocking))
+}
+
+/// Copy the target of the symbolic link `path` into `buffer`. Returns the length of the target.
+/// Fails with `EINVAL`, if `path` is not a link or the target is longer than `length`.
+pub fn sys_readlink(path_buff: *const u8, path_buff_len: usize, buffer: *mut u8, length: usize) -> isize {
+    let path = match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => path,
+        Err(errno) => return errno.into(),
+    };
+
+    let target = match name_service::readlink(&path) {
+        Ok(target) if target.len() <= length => target,
+        Ok(_) => return Errno::EINVAL.into(),
+        Err(errno) => return errno.into(),
+    };
+
+    match user::copy_to_user(buffer, target.as_bytes()) {
+        Ok(()) => target.len() as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Watch the entry `path` for the events in `mask` (see `watch`). Returns the id of the watch.
+pub fn sys_watch_path(path_buff: *const u8, path_buff_len: usize, mask: u32) -> isize {
+    let path = match user::string_from_user(path_buff, path_buff_len) {
+        Ok(path) => path,
+        Err(errno) => return errno.into(),
+    };
+
+    convert_syscall_result_to_ret_code(name_service::watch_path(&path, mask))
+}
+
+/// Copy the queued events of the watch `id` into `buffer` (see `WatchEvent::serialize()`).
+/// Returns the number of bytes written. Fails with `EAGAIN`, if there are no events.
+pub fn sys_watch_read(id: usize, buffer: *mut u8, length: usize) -> isize {
+    let events = match name_service::watch_read(id, length) {
+        Ok(events) => events,
+        Err(errno) => return errno.into(),
+    };
+
+    let mut bytes = Vec::new();
+    events.iter().for_each(|event| event.serialize(&mut bytes));
+    match user::copy_to_user(buffer, &bytes) {
+        Ok(()) => bytes.len() as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Remove the watch `id` of the current process
+pub fn sys_watch_remove(id: usize) -> isize {
+    convert_syscall_result_to_ret_code(name_service::watch_remove(id))
 }
@@ -0,0 +1,76 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_naming_tests                                                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the 'Mkentry' system call. The paths are copied into       ║
+   ║         memory, which is mapped into the address space of the calling   ║
+   ║         process.                                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::memory::{MAP_READ, MAP_WRITE};
+use syscall::return_vals::Errno;
+
+use crate::consts::USER_SPACE_MAP_START;
+use crate::memory::{user, PAGE_SIZE};
+use crate::naming::name_service::{del, stat};
+use crate::naming::stat::{MODE_CONT, MODE_DIR};
+use crate::syscall::sys_naming::sys_mkentry;
+use crate::syscall::sys_vmem::{sys_map_memory, sys_unmap_memory};
+
+const FILE: usize = 0;
+const DIRECTORY: usize = 1;
+
+///
+/// Description: Run all naming system call tests
+///
+pub fn run_tests() {
+    info!("sys_naming: running tests");
+
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_START, PAGE_SIZE, MAP_READ | MAP_WRITE), USER_SPACE_MAP_START as isize);
+    test_mkentry();
+    test_invalid_arguments();
+    assert_eq!(sys_unmap_memory(USER_SPACE_MAP_START, PAGE_SIZE), 0);
+
+    info!("sys_naming: all tests passed.");
+}
+
+/// Description: Call 'Mkentry' with `path` copied into user memory
+fn mkentry(path: &[u8], kind: usize) -> isize {
+    assert_eq!(user::copy_to_user(USER_SPACE_MAP_START as *mut u8, path), Ok(()));
+    sys_mkentry(USER_SPACE_MAP_START as *const u8, path.len(), kind)
+}
+
+/// Description: A directory and a container in it are created, creating them again fails
+fn test_mkentry() {
+    assert_eq!(mkentry(b"/sys_naming_test", DIRECTORY), 0);
+    assert_eq!(mkentry(b"/sys_naming_test/file", FILE), 0);
+    assert!(stat("/sys_naming_test").is_ok_and(|stat| stat.mode.entry_type() == MODE_DIR));
+    assert!(stat("/sys_naming_test/file").is_ok_and(|stat| stat.mode.entry_type() == MODE_CONT && stat.size == 0));
+
+    let eexist = isize::from(Errno::EEXIST);
+    assert_eq!(mkentry(b"/sys_naming_test/file", FILE), eexist);
+    assert_eq!(mkentry(b"/sys_naming_test", DIRECTORY), eexist);
+    assert_eq!(mkentry(b"/sys_naming_test", FILE), eexist);
+    assert_eq!(mkentry(b"/", FILE), eexist);
+
+    // The parent directory of a container must exist
+    assert_eq!(mkentry(b"/sys_naming_test/missing/file", FILE), isize::from(Errno::ENOENT));
+
+    assert_eq!(del("/sys_naming_test/file"), Ok(0));
+    assert_eq!(del("/sys_naming_test"), Ok(0));
+}
+
+/// Description: Malformed paths, unknown kinds and paths outside of user memory are rejected
+fn test_invalid_arguments() {
+    let einval = isize::from(Errno::EINVAL);
+    assert_eq!(mkentry(b"", FILE), einval);
+    assert_eq!(mkentry(b"/sys_naming_\xff", FILE), einval);
+    assert_eq!(mkentry(b"/sys_naming_\0", DIRECTORY), einval);
+    assert_eq!(mkentry(b"/sys_naming_test", 2), einval);
+    assert!(stat("/sys_naming_test").is_err());
+
+    let path = "/sys_naming_test";
+    assert_eq!(sys_mkentry(path.as_ptr(), path.len(), FILE), isize::from(Errno::EFAULT));
+}
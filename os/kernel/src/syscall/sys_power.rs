@@ -0,0 +1,46 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_power                                                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: All system calls related to power off and reboot.               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+
+use crate::{process_manager, shutdown};
+
+
+///
+/// Description: Shut down the system and power off the machine (only returns on failure).
+///
+/// Return: `Errno::EACCES`, if the calling process does not run as root
+///
+pub fn sys_poweroff() -> isize {
+    if let Err(errno) = process_manager().read().current_process().credentials().may_shut_down() {
+        return errno.into();
+    }
+
+    shutdown::poweroff();
+}
+
+///
+/// Description: Shut down the system and reset the machine (only returns on failure).
+///
+/// Return: `Errno::EACCES`, if the calling process does not run as root
+///
+pub fn sys_reboot() -> isize {
+    if let Err(errno) = process_manager().read().current_process().credentials().may_shut_down() {
+        return errno.into();
+    }
+
+    shutdown::reboot();
+}
+
+///
+/// Description: Check if the system is shutting down (processes should exit, before they are killed).
+///
+/// Return: 1, if a shutdown is in progress, 0 otherwise
+///
+pub fn sys_shutdown_pending() -> isize {
+    shutdown::in_progress() as isize
+}
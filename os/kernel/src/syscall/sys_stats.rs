@@ -0,0 +1,93 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_stats                                                       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Recording of per system call latency statistics. The dispatcher ║
+   ║         only calls 'syscall_stats_record' if the kernel is built with   ║
+   ║         the feature 'syscall-stats', otherwise the hot path is unchanged║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering::Relaxed;
+use syscall::NUM_SYSCALLS;
+use syscall::return_vals::Errno;
+use syscall::stats::{latency_bucket, SyscallStats, NUM_LATENCY_BUCKETS};
+use crate::memory::user;
+
+struct StatsEntry {
+    count: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+    total: AtomicU64,
+    buckets: [AtomicU64; NUM_LATENCY_BUCKETS],
+}
+
+impl StatsEntry {
+    const fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            buckets: [const { AtomicU64::new(0) }; NUM_LATENCY_BUCKETS],
+        }
+    }
+
+    fn snapshot(&self) -> SyscallStats {
+        let count = self.count.load(Relaxed);
+        SyscallStats {
+            count,
+            min: if count == 0 { 0 } else { self.min.load(Relaxed) },
+            max: self.max.load(Relaxed),
+            total: self.total.load(Relaxed),
+            buckets: core::array::from_fn(|i| self.buckets[i].load(Relaxed)),
+        }
+    }
+}
+
+static STATS: [StatsEntry; NUM_SYSCALLS] = [const { StatsEntry::new() }; NUM_SYSCALLS];
+
+///
+/// Description: Called by the dispatcher after system call `id` has returned.
+///
+/// Parameters: \
+///    `id` number of the system call \
+///    `start` TSC value read before the system call has been called
+///
+#[allow(dead_code)]
+#[unsafe(no_mangle)]
+pub extern "C" fn syscall_stats_record(id: usize, start: u64) {
+    let latency = unsafe { core::arch::x86_64::_rdtsc() }.saturating_sub(start);
+    let entry = &STATS[id];
+
+    entry.count.fetch_add(1, Relaxed);
+    entry.total.fetch_add(latency, Relaxed);
+    entry.min.fetch_min(latency, Relaxed);
+    entry.max.fetch_max(latency, Relaxed);
+    entry.buckets[latency_bucket(latency)].fetch_add(1, Relaxed);
+}
+
+///
+/// Description: Copy the statistics of all system calls to the user buffer `stats`.
+///
+/// Return: number of copied entries or `Errno::ENOSYS` if statistics are not recorded
+///
+pub fn sys_get_syscall_stats(stats: *mut SyscallStats, count: usize) -> isize {
+    if !cfg!(feature = "syscall-stats") {
+        return Errno::ENOSYS.into();
+    }
+    if stats.is_null() {
+        return Errno::EINVAL.into();
+    }
+
+    let count = count.min(NUM_SYSCALLS);
+    for (i, entry) in STATS.iter().take(count).enumerate() {
+        if let Err(errno) = user::write_to_user(stats.wrapping_add(i), entry.snapshot()) {
+            return errno.into();
+        }
+    }
+
+    count as isize
+}
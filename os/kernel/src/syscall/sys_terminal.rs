@@ -1,26 +1,275 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: sys_terminal                                                    ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: All system calls for terminal.                                  ║
+   ║ Descr.: All system calls for terminal and pipes (including vectored     ║
+   ║         reads and writes).                                              ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, 30.8.2024, HHU                                  ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
-use core::ptr::slice_from_raw_parts;
-use core::str::from_utf8;
-use crate::terminal;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str;
+use syscall::io::{IoVec, MAX_IOVECS, STDERR, STDIN, STDOUT};
+use syscall::keyboard::KeyboardLayout;
+use syscall::return_vals::{convert_syscall_result_to_ret_code, Errno};
+use crate::memory::user;
+use crate::process::pipe;
+use crate::{process_manager, terminal};
 
-pub fn sys_terminal_read() -> isize {
-    let terminal = terminal();
-    match terminal.read_byte() {
-        -1 => panic!("Input stream closed!"),
-        c => c as isize
+/// Read up to `length` bytes from `fd` (`STDIN` or the read end of a pipe) into `buffer`. Returns the number of bytes read.
+/// Blocks until the first byte is available. A short read from the terminal ends after a newline.
+pub fn sys_terminal_read(fd: usize, buffer: *mut u8, length: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    if pipe::is_pipe(process_id, fd) {
+        return sys_pipe_read(process_id, fd, buffer, length);
+    }
+    if fd != STDIN {
+        return Errno::EBADF.into();
+    }
+    if length == 0 {
+        return 0;
+    }
+    if !user::is_writable_user_memory(buffer as usize, length) {
+        return Errno::EINVAL.into();
+    }
+
+    let bytes = read_terminal(length);
+    match user::copy_to_user(buffer, &bytes) {
+        Ok(()) => bytes.len() as isize,
+        Err(errno) => errno.into(),
     }
 }
 
-pub fn sys_terminal_write(buffer: *const u8, length: usize) -> isize {
-    let string = from_utf8(unsafe { slice_from_raw_parts(buffer, length).as_ref().unwrap() }).unwrap();
-    let terminal = terminal();
-    terminal.write_str(string);
+/// Write `length` bytes from `buffer` to `fd` (`STDOUT` or `STDERR` as UTF-8 or the write end of a pipe). Returns the number of bytes written.
+pub fn sys_terminal_write(fd: usize, buffer: *const u8, length: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    if pipe::is_pipe(process_id, fd) {
+        return match user::bytes_from_user(buffer, length) {
+            Ok(bytes) => convert_syscall_result_to_ret_code(pipe::write(process_id, fd, &bytes)),
+            Err(errno) => errno.into(),
+        };
+    }
+    if fd != STDOUT && fd != STDERR {
+        return Errno::EBADF.into();
+    }
+    if length == 0 {
+        return 0;
+    }
+    if !user::is_user_memory(buffer as usize, length) {
+        return Errno::EINVAL.into();
+    }
+
+    match user::bytes_from_user(buffer, length) {
+        Ok(bytes) => convert_syscall_result_to_ret_code(write_terminal(&bytes)),
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Read from `fd` (like `sys_terminal_read()`) into the `count` segments of `iovecs` (filled in order). Returns the number of bytes read.
+/// Only the segments before the first one, which is not writable user memory, are used.
+pub fn sys_read_vectored(fd: usize, iovecs: *const IoVec, count: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    let is_pipe = pipe::is_pipe(process_id, fd);
+    if !is_pipe && fd != STDIN {
+        return Errno::EBADF.into();
+    }
+
+    let segments = match iovecs_from_user(iovecs, count) {
+        Ok(segments) => segments,
+        Err(errno) => return errno.into(),
+    };
+    let usable = segments.iter().take_while(|segment| segment.len == 0 || user::is_writable_user_memory(segment.base as usize, segment.len)).count();
+    let length = segments[..usable].iter().map(|segment| segment.len).sum::<usize>();
+    if length == 0 {
+        return if usable < segments.len() { Errno::EFAULT.into() } else { 0 };
+    }
+
+    // A single read of the total length, scattered over the segments afterwards
+    let bytes = match is_pipe {
+        true => {
+            let mut bytes = vec![0; length];
+            match pipe::read(process_id, fd, &mut bytes) {
+                Ok(count) => bytes.truncate(count),
+                Err(errno) => return errno.into(),
+            }
+            bytes
+        }
+        false => read_terminal(length),
+    };
+
+    let mut remaining = bytes.as_slice();
+    for segment in &segments[..usable] {
+        let (part, rest) = remaining.split_at(segment.len.min(remaining.len()));
+        if let Err(errno) = user::copy_to_user(segment.base as *mut u8, part) {
+            return errno.into();
+        }
+        remaining = rest;
+    }
+
+    bytes.len() as isize
+}
+
+/// Write the `count` segments of `iovecs` (in order) to `fd` (like `sys_terminal_write()`). Returns the number of bytes written.
+/// Only the segments before the first one, which is not user memory, are written.
+pub fn sys_write_vectored(fd: usize, iovecs: *const IoVec, count: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    let is_pipe = pipe::is_pipe(process_id, fd);
+    if !is_pipe && fd != STDOUT && fd != STDERR {
+        return Errno::EBADF.into();
+    }
+
+    let segments = match iovecs_from_user(iovecs, count) {
+        Ok(segments) => segments,
+        Err(errno) => return errno.into(),
+    };
+
+    // Gather all segments, so that they are written at once
+    let mut bytes = Vec::new();
+    for segment in segments.iter() {
+        match user::bytes_from_user(segment.base, segment.len) {
+            Ok(segment_bytes) => bytes.extend_from_slice(&segment_bytes),
+            Err(errno) if bytes.is_empty() => return errno.into(),
+            Err(_) => break,
+        }
+    }
+    if bytes.is_empty() {
+        return 0;
+    }
+
+    let written = match is_pipe {
+        true => pipe::write(process_id, fd, &bytes),
+        false => write_terminal(&bytes),
+    };
+    convert_syscall_result_to_ret_code(written)
+}
+
+/// Read up to `length` bytes at `offset` from `fd` into `buffer`, without changing the position of `fd`.
+/// The terminal and pipes are not seekable (`Errno::ESPIPE`) and there are no other file descriptors yet.
+pub fn sys_pread(fd: usize, _buffer: *mut u8, _length: usize, _offset: usize) -> isize {
+    seek_error(fd).into()
+}
+
+/// Write `length` bytes from `buffer` at `offset` to `fd`, without changing the position of `fd`.
+/// The terminal and pipes are not seekable (`Errno::ESPIPE`) and there are no other file descriptors yet.
+pub fn sys_pwrite(fd: usize, _buffer: *const u8, _length: usize, _offset: usize) -> isize {
+    seek_error(fd).into()
+}
+
+/// Description: Error for positional I/O on `fd`: `Errno::ESPIPE` for the terminal and pipes, `Errno::EBADF` for an unknown `fd`
+fn seek_error(fd: usize) -> Errno {
+    let process_id = process_manager().read().current_process().id();
+    match fd {
+        STDIN | STDOUT | STDERR => Errno::ESPIPE,
+        _ if pipe::is_pipe(process_id, fd) => Errno::ESPIPE,
+        _ => Errno::EBADF,
+    }
+}
+
+/// Read a line (with editing and history) into `buffer`. Returns the number of bytes written.
+/// A line longer than `length` is truncated at a character boundary.
+pub fn sys_terminal_read_line(buffer: *mut u8, length: usize) -> isize {
+    if buffer.is_null() {
+        return Errno::EINVAL.into();
+    }
+
+    let line = terminal().read_line();
+    let mut count = line.len().min(length);
+    while !line.is_char_boundary(count) {
+        count -= 1;
+    }
+
+    match user::copy_to_user(buffer, &line.as_bytes()[..count]) {
+        Ok(()) => count as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Translate the scancodes of the keyboard with the layout `layout` (see `KeyboardLayout`). Returns the previous layout.
+pub fn sys_set_keyboard_layout(layout: usize) -> isize {
+    match KeyboardLayout::try_from(layout) {
+        Ok(layout) => usize::from(terminal().set_keyboard_layout(layout)) as isize,
+        Err(_) => Errno::EINVAL.into(),
+    }
+}
+
+/// Repeat a held key after `delay_ms` every `interval_ms` (0 = keys are not repeated).
+pub fn sys_set_key_repeat(delay_ms: usize, interval_ms: usize) -> isize {
+    terminal().set_key_repeat(delay_ms, interval_ms);
     0
 }
+
+/// Create a pipe and write the file descriptors of its read end and write end to `fds` (two `usize`). `flags` see `pipe::create()`.
+pub fn sys_pipe_create(fds: *mut usize, flags: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    let (read_fd, write_fd) = match pipe::create(process_id, flags) {
+        Ok(fds) => fds,
+        Err(errno) => return errno.into(),
+    };
+
+    match user::write_to_user(fds as *mut [usize; 2], [read_fd, write_fd]) {
+        Ok(()) => 0,
+        Err(errno) => {
+            let _ = pipe::close(process_id, read_fd);
+            let _ = pipe::close(process_id, write_fd);
+            errno.into()
+        }
+    }
+}
+
+/// Close the file descriptor `fd` (only pipe ends can be closed)
+pub fn sys_close(fd: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    convert_syscall_result_to_ret_code(pipe::close(process_id, fd).map(|_| 0))
+}
+
+fn sys_pipe_read(process_id: usize, fd: usize, buffer: *mut u8, length: usize) -> isize {
+    if length > 0 && !user::is_writable_user_memory(buffer as usize, length) {
+        return Errno::EINVAL.into();
+    }
+
+    let mut bytes = vec![0; length];
+    let count = match pipe::read(process_id, fd, &mut bytes) {
+        Ok(count) => count,
+        Err(errno) => return errno.into(),
+    };
+
+    match user::copy_to_user(buffer, &bytes[..count]) {
+        Ok(()) => count as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+/// Description: Read up to `length` bytes from the terminal (blocks until the first byte is available, ends after a newline)
+fn read_terminal(length: usize) -> Vec<u8> {
+    let terminal = terminal();
+    let mut bytes = Vec::with_capacity(length);
+    while bytes.len() < length {
+        match terminal.read_byte() {
+            -1 => panic!("Input stream closed!"),
+            c => bytes.push(c as u8),
+        }
+        if bytes.last() == Some(&b'\n') {
+            break;
+        }
+    }
+
+    bytes
+}
+
+/// Description: Write `bytes` to the terminal. Fails with `Errno::EINVAL`, if they are not valid UTF-8.
+fn write_terminal(bytes: &[u8]) -> Result<usize, Errno> {
+    let string = str::from_utf8(bytes).map_err(|_| Errno::EINVAL)?;
+    terminal().write_str(string);
+    Ok(bytes.len())
+}
+
+/// Description: Copy the array of `count` segments at `iovecs` from user memory (at most `MAX_IOVECS`, otherwise `Errno::EINVAL`)
+fn iovecs_from_user(iovecs: *const IoVec, count: usize) -> Result<Vec<IoVec>, Errno> {
+    if count > MAX_IOVECS {
+        return Err(Errno::EINVAL);
+    }
+
+    let bytes = user::bytes_from_user(iovecs as *const u8, count * size_of::<IoVec>())?;
+    Ok(bytes.chunks_exact(size_of::<IoVec>()).map(|chunk| unsafe { (chunk.as_ptr() as *const IoVec).read_unaligned() }).collect())
+}
@@ -0,0 +1,85 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_terminal_tests                                              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test argument checks of the 'TerminalRead', 'TerminalWrite',    ║
+   ║         'ReadVectored', 'WriteVectored', 'PRead' and 'PWrite' system    ║
+   ║         calls (only rejected calls, nothing is read or written).        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::io::{IoVec, MAX_IOVECS, STDERR, STDIN, STDOUT};
+use syscall::return_vals::Errno;
+
+use crate::syscall::sys_terminal::{sys_pread, sys_pwrite, sys_read_vectored, sys_terminal_read, sys_terminal_write, sys_write_vectored};
+
+///
+/// Description: Run all terminal tests
+///
+pub fn run_tests() {
+    info!("sys_terminal: running tests");
+
+    test_bad_fd();
+    test_zero_length();
+    test_kernel_buffer();
+    test_vectored_bad_fd();
+    test_vectored_empty();
+    test_vectored_kernel_iovecs();
+    test_positional_not_seekable();
+
+    info!("sys_terminal: all tests passed.");
+}
+
+/// Description: Reading from an output stream or writing to an input stream fails
+fn test_bad_fd() {
+    let mut buffer = [0u8; 4];
+    assert_eq!(sys_terminal_read(STDOUT, buffer.as_mut_ptr(), buffer.len()), isize::from(Errno::EBADF));
+    assert_eq!(sys_terminal_write(STDIN, buffer.as_ptr(), buffer.len()), isize::from(Errno::EBADF));
+}
+
+/// Description: Empty buffers are accepted without touching memory (even a null pointer)
+fn test_zero_length() {
+    assert_eq!(sys_terminal_read(STDIN, core::ptr::null_mut(), 0), 0);
+    assert_eq!(sys_terminal_write(STDOUT, core::ptr::null(), 0), 0);
+}
+
+/// Description: Buffers outside of user memory are rejected
+fn test_kernel_buffer() {
+    let mut buffer = [0u8; 4];
+    assert_eq!(sys_terminal_read(STDIN, buffer.as_mut_ptr(), buffer.len()), isize::from(Errno::EINVAL));
+    assert_eq!(sys_terminal_write(STDOUT, buffer.as_ptr(), buffer.len()), isize::from(Errno::EINVAL));
+}
+
+/// Description: Vectored calls check the file descriptor like the simple ones
+fn test_vectored_bad_fd() {
+    assert_eq!(sys_read_vectored(STDOUT, core::ptr::null(), 0), isize::from(Errno::EBADF));
+    assert_eq!(sys_write_vectored(STDIN, core::ptr::null(), 0), isize::from(Errno::EBADF));
+}
+
+/// Description: An empty segment list transfers nothing, too many segments are rejected
+fn test_vectored_empty() {
+    assert_eq!(sys_read_vectored(STDIN, core::ptr::null(), 0), 0);
+    assert_eq!(sys_write_vectored(STDOUT, core::ptr::null(), 0), 0);
+    assert_eq!(sys_write_vectored(STDOUT, core::ptr::null(), MAX_IOVECS + 1), isize::from(Errno::EINVAL));
+}
+
+/// Description: A segment array outside of user memory is rejected
+fn test_vectored_kernel_iovecs() {
+    let buffer = [0u8; 4];
+    let iovecs = [IoVec::new(&buffer)];
+    assert_eq!(sys_read_vectored(STDIN, iovecs.as_ptr(), iovecs.len()), isize::from(Errno::EFAULT));
+    assert_eq!(sys_write_vectored(STDOUT, iovecs.as_ptr(), iovecs.len()), isize::from(Errno::EFAULT));
+}
+
+/// Description: The terminal is not seekable, unknown file descriptors are rejected
+fn test_positional_not_seekable() {
+    let mut buffer = [0u8; 4];
+    for fd in [STDIN, STDOUT, STDERR] {
+        assert_eq!(sys_pread(fd, buffer.as_mut_ptr(), buffer.len(), 0), isize::from(Errno::ESPIPE));
+        assert_eq!(sys_pwrite(fd, buffer.as_ptr(), buffer.len(), 0), isize::from(Errno::ESPIPE));
+    }
+
+    assert_eq!(sys_pread(usize::MAX, buffer.as_mut_ptr(), buffer.len(), 0), isize::from(Errno::EBADF));
+    assert_eq!(sys_pwrite(usize::MAX, buffer.as_ptr(), buffer.len(), 0), isize::from(Errno::EBADF));
+}
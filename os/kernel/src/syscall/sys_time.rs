@@ -11,14 +11,21 @@ use alloc::format;
 use alloc::string::ToString;
 use chrono::{DateTime, Datelike, TimeDelta, Timelike};
 use core::ptr;
+use syscall::return_vals::Errno;
 use uefi::table::runtime::{Time, TimeParams};
 use crate::{efi_system_table, timer};
 
 
+///
+/// Description:
+///    Nanoseconds since boot. The system time advances with every timer interrupt (every
+///    millisecond), so its resolution is 1 ms, but no precision is lost by the conversion.
+///
 pub fn sys_get_system_time() -> isize {
-    timer().systime_ms() as isize
+    timer().systime_ns() as isize
 }
 
+/// Description: Read the real time clock and return milliseconds since the Unix epoch
 pub fn sys_get_date() -> isize {
     if let Some(efi_system_table) = efi_system_table() {
         let system_table = efi_system_table.read();
@@ -43,24 +50,36 @@ pub fn sys_get_date() -> isize {
                         .expect("Failed to parse date from EFI runtime services")
                         .timestamp_millis() as isize
                 } else {
-                    0
+                    Errno::EIO.into()
                 }
             }
-            Err(_) => 0
+            Err(_) => Errno::EIO.into()
         }
     }
 
-    0
+    Errno::ENOSYS.into()
 }
 
+///
+/// Description: Set the real time clock.
+///
+/// Parameters: `date_ms` milliseconds since the Unix epoch (dates before the epoch are rejected with EINVAL)
+///
 pub fn sys_set_date(date_ms: usize) -> isize {
+    if (date_ms as i64) < 0 {
+        return Errno::EINVAL.into();
+    }
+
     if let Some(efi_system_table) = efi_system_table() {
         let system_table = efi_system_table.write();
         let runtime_services_read = unsafe { system_table.runtime_services() };
         let runtime_services = unsafe { ptr::from_ref(runtime_services_read).cast_mut().as_mut().unwrap() };
 
-        let date = DateTime::from_timestamp_millis(date_ms as i64).expect("Failed to parse date from milliseconds");
-        let uefi_date = Time::new(TimeParams {
+        let date = match DateTime::from_timestamp_millis(date_ms as i64) {
+            Some(date) => date,
+            None => return Errno::EINVAL.into(),
+        };
+        let uefi_date = match Time::new(TimeParams {
             year: date.year() as u16,
             month: date.month() as u8,
             day: date.day() as u8,
@@ -70,13 +89,16 @@ pub fn sys_set_date(date_ms: usize) -> isize {
             nanosecond: date.nanosecond(),
             time_zone: None,
             daylight: Default::default(),
-        }).expect("Failed to create EFI date");
+        }) {
+            Ok(time) => time,
+            Err(_) => return Errno::EINVAL.into(),
+        };
 
         return match unsafe { runtime_services.set_time(&uefi_date) } {
-            Ok(_) => true as isize,
-            Err(_) => false as isize,
+            Ok(_) => 0,
+            Err(_) => Errno::EIO.into(),
         };
     }
 
-    false as isize
+    Errno::ENOSYS.into()
 }
@@ -0,0 +1,74 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_time_tests                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the 'GetSystemTime', 'GetDate' and 'SetDate' system calls. ║
+   ║         The date round trip requires EFI runtime services and is        ║
+   ║         skipped without them.                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use chrono::NaiveDate;
+use syscall::return_vals::Errno;
+
+use crate::syscall::sys_time::{sys_get_date, sys_get_system_time, sys_set_date};
+use crate::timer;
+
+/// Maximum difference between a set date and the date read back (the clock keeps running)
+const TOLERANCE_MS: isize = 2000;
+
+///
+/// Description: Run all time and date tests
+///
+pub fn run_tests() {
+    info!("sys_time: running tests");
+
+    test_system_time();
+    test_before_epoch();
+    test_round_trip();
+
+    info!("sys_time: all tests passed.");
+}
+
+/// Description: The system time is returned in nanoseconds and never decreases
+fn test_system_time() {
+    let first = sys_get_system_time();
+    let second = sys_get_system_time();
+    assert!(first >= 0);
+    assert!(second >= first);
+
+    // Same clock as the kernel's system time in milliseconds
+    let ms = timer().systime_ms() as isize;
+    assert!(second / 1_000_000 <= ms && ms - second / 1_000_000 < TOLERANCE_MS);
+
+    timer().wait(2);
+    assert!(sys_get_system_time() >= second);
+}
+
+/// Description: Dates before the Unix epoch are rejected
+fn test_before_epoch() {
+    let date_ms = -1000i64;
+    assert_eq!(sys_set_date(date_ms as usize), isize::from(Errno::EINVAL));
+}
+
+/// Description: A date written to the real time clock is read back (the previous date is restored afterwards)
+fn test_round_trip() {
+    let previous = sys_get_date();
+    if previous == isize::from(Errno::ENOSYS) {
+        info!("sys_time: no EFI runtime services, skipping round trip");
+        return;
+    }
+    assert!(previous >= 0);
+
+    let date = NaiveDate::from_ymd_opt(2024, 9, 1).unwrap()
+        .and_hms_opt(12, 34, 56).unwrap()
+        .and_utc();
+    let date_ms = date.timestamp_millis() as isize;
+
+    assert_eq!(sys_set_date(date_ms as usize), 0);
+    let read = sys_get_date();
+    assert!(read >= date_ms && read - date_ms < TOLERANCE_MS);
+
+    assert_eq!(sys_set_date(previous as usize), 0);
+}
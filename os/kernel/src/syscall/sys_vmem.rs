@@ -1,23 +1,54 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: sys_vmem                                                        ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: All system calls related to virtual memory management.          ║
+   ║ Descr.: All system calls related to memory management.                  ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland & Michael Schoettner, 30.8.2024, HHU             ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 
+use crate::consts::{USER_SPACE_ENV_START, USER_SPACE_MAP_END, USER_SPACE_MAP_START};
 use crate::memory::r#virtual::{VirtualMemoryArea, VmaType};
-use crate::memory::{MemorySpace, PAGE_SIZE};
-use crate::process_manager;
-use x86_64::structures::paging::PageTableFlags;
+use crate::memory::{nvmem, physical, pressure, user, MemorySpace, PAGE_SIZE};
+use crate::{allocator, process_manager};
+#[cfg(feature = "heap-tracking")]
+use crate::memory::heap_tracking;
+use syscall::memory::{MemoryStats, MAP_EXEC, MAP_NVRAM, MAP_READ, MAP_WRITE};
+use syscall::return_vals::Errno;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::structures::paging::page::PageRange;
+use x86_64::VirtAddr;
 
 
+///
+/// Description:
+///    Map `size` bytes (rounded up to pages) of heap memory. The first call
+///    maps the heap right behind the code area, each further call maps the
+///    next chunk directly behind the previous one, so the heap grows contiguously.
+///
+/// Return: start address of the mapped chunk or `Errno::ENOMEM`
+///
 pub fn sys_map_user_heap(size: usize) -> isize {
+    if size == 0 {
+        return Errno::EINVAL.into();
+    }
+
     let process = process_manager().read().current_process();
-    let code_areas = process.find_vmas(VmaType::Code);
-    let code_area = code_areas.get(0).expect("Process does not have code area!");
-    let heap_start = code_area.end().align_up(PAGE_SIZE as u64);
+    let heap_start = match process.find_vmas(VmaType::Heap).iter().map(|area| area.end()).max() {
+        Some(heap_end) => heap_end,
+        None => {
+            let code_areas = process.find_vmas(VmaType::Code);
+            let code_area = code_areas.get(0).expect("Process does not have code area!");
+            code_area.end().align_up(PAGE_SIZE as u64)
+        }
+    };
+
+    // The heap must not grow into the environment
+    let size = size.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    if heap_start.as_u64().checked_add(size as u64).is_none_or(|end| end > USER_SPACE_ENV_START as u64) {
+        return Errno::ENOMEM.into();
+    }
+
     let heap_area = VirtualMemoryArea::from_address(heap_start, size, VmaType::Heap);
 
     process.address_space().map(heap_area.range(), MemorySpace::User, PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE);
@@ -26,3 +57,192 @@ pub fn sys_map_user_heap(size: usize) -> isize {
     heap_start.as_u64() as isize
 }
 
+
+///
+/// Description: Fill `stats` with the usage of the kernel heap and physical memory.
+///
+/// Return: 0 or `Errno::EINVAL`, if `stats` does not point to mapped user memory
+///
+pub fn sys_memory_stats(stats: *mut MemoryStats) -> isize {
+    if !user::is_writable_user_memory(stats as usize, size_of::<MemoryStats>()) || !stats.is_aligned() {
+        return Errno::EINVAL.into();
+    }
+
+    let memory_stats = MemoryStats {
+        heap_total: allocator().size(),
+        heap_used: allocator().used(),
+        phys_total: physical::total_frames() * PAGE_SIZE,
+        phys_free: physical::free_frames() * PAGE_SIZE,
+    };
+
+    match user::write_to_user(stats, memory_stats) {
+        Ok(()) => 0,
+        Err(errno) => errno.into(),
+    }
+}
+
+///
+/// Description:
+///    Copy the report of live kernel heap allocations (grouped by call site, see 'heap_tracking.rs')
+///    as text to the user buffer `buffer` with `length` bytes. If `buffer` is null, nothing is
+///    copied (to find out the required length).
+///
+/// Return: length of the report, `Errno::ENOMEM` if `length` is too small (nothing is copied)
+///         or `Errno::ENOSYS` if the kernel has been built without the feature 'heap-tracking'
+///
+#[cfg(feature = "heap-tracking")]
+pub fn sys_heap_allocations(buffer: *mut u8, length: usize) -> isize {
+    let report = heap_tracking::dump();
+    if buffer.is_null() {
+        return report.len() as isize;
+    }
+    if length < report.len() {
+        return Errno::ENOMEM.into();
+    }
+
+    match user::copy_to_user(buffer, report.as_bytes()) {
+        Ok(()) => report.len() as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+#[cfg(not(feature = "heap-tracking"))]
+pub fn sys_heap_allocations(_buffer: *mut u8, _length: usize) -> isize {
+    Errno::ENOSYS.into()
+}
+
+///
+/// Description: Subscribe (`subscribe` = 1) or unsubscribe (`subscribe` = 0) the current process to memory pressure notifications.
+///
+/// Return: 0 or `Errno::EINVAL` for any other value of `subscribe`
+///
+pub fn sys_memory_pressure_subscribe(subscribe: usize) -> isize {
+    let process_id = process_manager().read().current_process().id();
+    match subscribe {
+        0 => pressure::unsubscribe(process_id),
+        1 => pressure::subscribe(process_id),
+        _ => return Errno::EINVAL.into(),
+    }
+
+    0
+}
+
+///
+/// Description: Take the latest unread memory pressure level of the current process (without blocking).
+///
+/// Return: the level (see `PressureLevel`), `Errno::EAGAIN` if it has not changed since the last call
+///         or `Errno::EINVAL` if the process is not subscribed
+///
+pub fn sys_memory_pressure_read() -> isize {
+    let process_id = process_manager().read().current_process().id();
+    match pressure::read(process_id) {
+        Ok(level) => usize::from(level) as isize,
+        Err(errno) => errno.into(),
+    }
+}
+
+///
+/// Description:
+///    Map `length` bytes (rounded up to pages) at `virt_addr` into the calling process.
+///    Normal memory is taken from the page frame allocator, NVRAM (`MAP_NVRAM`) from the
+///    NVRAM allocator. Both are zeroed before they are mapped.
+///
+/// Parameters: \
+///    `virt_addr` page aligned start address inside the mapping area (USER_SPACE_MAP_START..USER_SPACE_MAP_END) \
+///    `length` number of bytes to map \
+///    `flags` access rights (`MAP_READ` is required), optionally `MAP_NVRAM`
+///
+/// Return: `virt_addr`, `Errno::EINVAL` for invalid arguments or overlapping mappings, `Errno::ENOMEM`
+///
+pub fn sys_map_memory(virt_addr: usize, length: usize, flags: usize) -> isize {
+    let pages = match mapping_pages(virt_addr, length) {
+        Some(pages) => pages,
+        None => return Errno::EINVAL.into(),
+    };
+    if flags & !(MAP_READ | MAP_WRITE | MAP_EXEC | MAP_NVRAM) != 0 || flags & MAP_READ == 0 {
+        return Errno::EINVAL.into();
+    }
+
+    let mut page_flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+    if flags & MAP_WRITE != 0 {
+        page_flags |= PageTableFlags::WRITABLE;
+    }
+    if flags & MAP_EXEC == 0 {
+        page_flags |= PageTableFlags::NO_EXECUTE;
+    }
+
+    // Reserve the area first, so that concurrent mappings cannot overlap
+    let process = process_manager().read().current_process();
+    let typ = if flags & MAP_NVRAM != 0 { VmaType::NvramMapping } else { VmaType::Mapping };
+    let area = VirtualMemoryArea::new(pages, typ);
+    if !process.try_add_vma(area) {
+        return Errno::EINVAL.into();
+    }
+
+    match typ {
+        VmaType::NvramMapping => match nvmem::alloc_frames((pages.end - pages.start) as usize) {
+            Some(frames) => process.address_space().map_physical(frames, pages, MemorySpace::User, page_flags),
+            None => {
+                process.remove_vma(area);
+                return Errno::ENOMEM.into();
+            }
+        },
+        _ => {
+            if physical::free_frames() < (pages.end - pages.start) as usize {
+                process.remove_vma(area);
+                return Errno::ENOMEM.into();
+            }
+            process.address_space().map(pages, MemorySpace::User, page_flags);
+        }
+    }
+
+    virt_addr as isize
+}
+
+///
+/// Description: Unmap a mapping created with `sys_map_memory` and free its frames.
+///
+/// Parameters: \
+///    `virt_addr` start address of the mapping \
+///    `length` length of the mapping (partial unmapping is not supported)
+///
+/// Return: 0 or `Errno::EINVAL`, if there is no such mapping
+///
+pub fn sys_unmap_memory(virt_addr: usize, length: usize) -> isize {
+    let pages = match mapping_pages(virt_addr, length) {
+        Some(pages) => pages,
+        None => return Errno::EINVAL.into(),
+    };
+
+    let process = process_manager().read().current_process();
+    let area = [VmaType::Mapping, VmaType::NvramMapping].iter()
+        .flat_map(|typ| process.find_vmas(*typ))
+        .find(|area| area.range() == pages);
+
+    match area {
+        Some(area) => {
+            process.remove_vma(area);
+            match area.typ() {
+                VmaType::NvramMapping => nvmem::unmap(&process.address_space(), &area),
+                _ => process.address_space().unmap(pages, true),
+            }
+            0
+        }
+        None => Errno::EINVAL.into(),
+    }
+}
+
+/// Description: Pages of `length` bytes at `virt_addr`, if the range is page aligned and inside the mapping area
+fn mapping_pages(virt_addr: usize, length: usize) -> Option<PageRange> {
+    if !virt_addr.is_multiple_of(PAGE_SIZE) || length == 0 {
+        return None;
+    }
+
+    let end = virt_addr.checked_add(length)?.checked_next_multiple_of(PAGE_SIZE)?;
+    if virt_addr < USER_SPACE_MAP_START || end > USER_SPACE_MAP_END {
+        return None;
+    }
+
+    let start = Page::from_start_address(VirtAddr::new(virt_addr as u64)).ok()?;
+    Some(PageRange { start, end: start + ((end - virt_addr) / PAGE_SIZE) as u64 })
+}
@@ -0,0 +1,78 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_vmem_tests                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the 'MapMemory' and 'UnmapMemory' system calls. The tests  ║
+   ║         map memory into the address space of the calling process.       ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use syscall::memory::{MAP_EXEC, MAP_READ, MAP_WRITE};
+use syscall::return_vals::Errno;
+use x86_64::VirtAddr;
+
+use crate::consts::{USER_SPACE_MAP_END, USER_SPACE_MAP_START};
+use crate::memory::PAGE_SIZE;
+use crate::process_manager;
+use crate::syscall::sys_vmem::{sys_map_memory, sys_unmap_memory};
+
+///
+/// Description: Run all mapping tests
+///
+pub fn run_tests() {
+    info!("sys_vmem: running tests");
+
+    test_map_unmap();
+    test_invalid_arguments();
+    test_overlapping();
+
+    info!("sys_vmem: all tests passed.");
+}
+
+fn is_mapped(addr: usize) -> bool {
+    process_manager().read().current_process().address_space().translate(VirtAddr::new(addr as u64)).is_some()
+}
+
+/// Description: A mapping is rounded up to pages, installed in the page tables and removed again
+fn test_map_unmap() {
+    let addr = USER_SPACE_MAP_START;
+
+    assert_eq!(sys_map_memory(addr, PAGE_SIZE + 1, MAP_READ | MAP_WRITE), addr as isize);
+    assert!(is_mapped(addr) && is_mapped(addr + PAGE_SIZE));
+    assert!(!is_mapped(addr + 2 * PAGE_SIZE));
+
+    // Only complete mappings can be unmapped
+    assert_eq!(sys_unmap_memory(addr, PAGE_SIZE), isize::from(Errno::EINVAL));
+    assert_eq!(sys_unmap_memory(addr, 2 * PAGE_SIZE), 0);
+    assert!(!is_mapped(addr) && !is_mapped(addr + PAGE_SIZE));
+    assert_eq!(sys_unmap_memory(addr, 2 * PAGE_SIZE), isize::from(Errno::EINVAL));
+}
+
+/// Description: Unaligned addresses, addresses outside the mapping area and unknown flags are rejected
+fn test_invalid_arguments() {
+    let einval = isize::from(Errno::EINVAL);
+
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_START + 1, PAGE_SIZE, MAP_READ), einval);
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_START, 0, MAP_READ), einval);
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_START - PAGE_SIZE, PAGE_SIZE, MAP_READ), einval);
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_END - PAGE_SIZE, 2 * PAGE_SIZE, MAP_READ), einval);
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_START, PAGE_SIZE, MAP_WRITE | MAP_EXEC), einval);
+    assert_eq!(sys_map_memory(USER_SPACE_MAP_START, PAGE_SIZE, MAP_READ | 1 << 10), einval);
+    assert!(!is_mapped(USER_SPACE_MAP_START));
+}
+
+/// Description: A mapping must not overlap an existing one
+fn test_overlapping() {
+    let addr = USER_SPACE_MAP_START + 16 * PAGE_SIZE;
+
+    assert_eq!(sys_map_memory(addr, 4 * PAGE_SIZE, MAP_READ), addr as isize);
+    assert_eq!(sys_map_memory(addr + 3 * PAGE_SIZE, 2 * PAGE_SIZE, MAP_READ), isize::from(Errno::EINVAL));
+    assert_eq!(sys_map_memory(addr - PAGE_SIZE, 2 * PAGE_SIZE, MAP_READ), isize::from(Errno::EINVAL));
+
+    // Directly adjacent mappings are fine
+    assert_eq!(sys_map_memory(addr + 4 * PAGE_SIZE, PAGE_SIZE, MAP_READ), (addr + 4 * PAGE_SIZE) as isize);
+
+    assert_eq!(sys_unmap_memory(addr + 4 * PAGE_SIZE, PAGE_SIZE), 0);
+    assert_eq!(sys_unmap_memory(addr, 4 * PAGE_SIZE), 0);
+}
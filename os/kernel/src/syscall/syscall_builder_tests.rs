@@ -0,0 +1,50 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: syscall_builder_tests                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Test the argument marshalling of 'SyscallBuilder' (the system   ║
+   ║         calls are not fired, only the register values are checked).     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use ::log::info;
+use core::ptr;
+use syscall::SystemCall;
+use syscall::builder::{SyscallBuilder, MAX_ARGS};
+
+///
+/// Description: Run all builder tests
+///
+pub fn run_tests() {
+    info!("syscall_builder: running tests");
+
+    test_no_args();
+    test_arg_order();
+    test_typed_args();
+
+    info!("syscall_builder: all tests passed.");
+}
+
+/// Description: A builder without arguments passes no registers
+fn test_no_args() {
+    let builder = SyscallBuilder::new(SystemCall::ThreadId);
+    assert!(builder.args().is_empty());
+}
+
+/// Description: Arguments are passed in the order they are pushed
+fn test_arg_order() {
+    let builder = (1..=MAX_ARGS).fold(SyscallBuilder::new(SystemCall::Log), |builder, arg| builder.arg(arg));
+    assert_eq!(builder.args(), &[1, 2, 3, 4, 5, 6]);
+}
+
+/// Description: Typed arguments are converted to register values
+fn test_typed_args() {
+    let value = 42u32;
+    let builder = SyscallBuilder::new(SystemCall::TerminalWrite)
+        .arg(ptr::from_ref(&value))
+        .arg(true)
+        .arg(-1isize)
+        .arg(7u8);
+
+    assert_eq!(builder.args(), &[ptr::from_ref(&value) as usize, 1, usize::MAX, 7]);
+}
@@ -12,15 +12,20 @@ use core::ops::Deref;
 use core::ptr;
 use syscall::NUM_SYSCALLS;
 use x86_64::registers::control::{Efer, EferFlags};
-use x86_64::registers::model_specific::{KernelGsBase, LStar, Star};
+use x86_64::registers::model_specific::{KernelGsBase, LStar, SFMask, Star};
+use x86_64::registers::rflags::RFlags;
 use x86_64::structures::gdt::SegmentSelector;
 use x86_64::{PrivilegeLevel, VirtAddr};
-use crate::syscall::sys_vmem::sys_map_user_heap;
+use crate::syscall::sys_vmem::{sys_heap_allocations, sys_map_memory, sys_map_user_heap, sys_memory_pressure_read, sys_memory_pressure_subscribe, sys_memory_stats, sys_unmap_memory};
 use crate::syscall::sys_time::{sys_get_date, sys_get_system_time, sys_set_date, };
-use crate::syscall::sys_concurrent::{sys_process_execute_binary, sys_process_exit, sys_process_id, sys_thread_create, sys_thread_exit,
-    sys_thread_id, sys_thread_join, sys_thread_sleep, sys_thread_switch,};
-use crate::syscall::sys_terminal::{sys_terminal_read, sys_terminal_write};
-use crate::syscall::sys_naming::sys_mkentry;
+use crate::syscall::sys_concurrent::{sys_authenticate, sys_futex_wait, sys_futex_wake, sys_get_uid, sys_process_execute_binary, sys_process_exit, sys_process_id, sys_process_list, sys_process_wait, sys_thread_create, sys_thread_exit,
+    sys_set_uid, sys_thread_id, sys_thread_join, sys_thread_set_priority, sys_thread_sleep, sys_thread_switch, sys_thread_yield};
+use crate::syscall::sys_terminal::{sys_close, sys_pipe_create, sys_pread, sys_pwrite, sys_read_vectored, sys_set_key_repeat, sys_set_keyboard_layout, sys_terminal_read, sys_terminal_read_line, sys_terminal_write, sys_write_vectored};
+use crate::syscall::sys_log::sys_log;
+use crate::syscall::sys_naming::{sys_chdir, sys_chmod, sys_chown, sys_file_lock, sys_getcwd, sys_link, sys_mkentry, sys_readlink, sys_set_umask, sys_symlink, sys_watch_path,
+    sys_watch_read, sys_watch_remove};
+use crate::syscall::sys_power::{sys_poweroff, sys_reboot, sys_shutdown_pending};
+use crate::syscall::sys_stats::sys_get_syscall_stats;
 
 use crate::{core_local_storage, tss};
 
@@ -62,6 +67,9 @@ pub fn init() {
     // Set rip for syscall
     LStar::write(VirtAddr::new(syscall_handler as u64));
 
+    // Clear the AC flag on entry, otherwise user mode could disable SMAP for system calls by setting it
+    SFMask::write(RFlags::ALIGNMENT_CHECK);
+
     // Initialize core local storage (accessible via 'swapgs')
     let mut core_local_storage = core_local_storage().lock();
     core_local_storage.tss_rsp0_ptr =
@@ -99,6 +107,47 @@ impl SyscallTable {
                 sys_get_date as *const _,
                 sys_set_date as *const _,
                 sys_mkentry as *const _,
+                sys_poweroff as *const _,
+                sys_reboot as *const _,
+                sys_get_syscall_stats as *const _,
+                sys_thread_set_priority as *const _,
+                sys_log as *const _,
+                sys_terminal_read_line as *const _,
+                sys_memory_stats as *const _,
+                sys_map_memory as *const _,
+                sys_unmap_memory as *const _,
+                sys_chdir as *const _,
+                sys_getcwd as *const _,
+                sys_thread_yield as *const _,
+                sys_symlink as *const _,
+                sys_readlink as *const _,
+                sys_process_wait as *const _,
+                sys_link as *const _,
+                sys_file_lock as *const _,
+                sys_watch_path as *const _,
+                sys_watch_read as *const _,
+                sys_watch_remove as *const _,
+                sys_set_umask as *const _,
+                sys_chmod as *const _,
+                sys_chown as *const _,
+                sys_get_uid as *const _,
+                sys_set_uid as *const _,
+                sys_authenticate as *const _,
+                sys_process_list as *const _,
+                sys_heap_allocations as *const _,
+                sys_pipe_create as *const _,
+                sys_close as *const _,
+                sys_read_vectored as *const _,
+                sys_write_vectored as *const _,
+                sys_pread as *const _,
+                sys_pwrite as *const _,
+                sys_memory_pressure_subscribe as *const _,
+                sys_memory_pressure_read as *const _,
+                sys_set_keyboard_layout as *const _,
+                sys_set_key_repeat as *const _,
+                sys_futex_wait as *const _,
+                sys_futex_wake as *const _,
+                sys_shutdown_pending as *const _,
             ],
         }
     }
@@ -118,7 +167,7 @@ unsafe impl Sync for SyscallTable {}
 ///    See AMD64 ABI. 
 ///
 /// Return: \
-///    Two values in `rax`, `rdx` to reconstruct `Result`in user mode
+///    Return value in `rax` (negative for errors), all other registers except `rcx` and `r11` are preserved
 unsafe extern "C" fn syscall_handler() {
     asm!(
     // We are now in ring 0, but still on the user stack
@@ -190,14 +239,47 @@ unsafe extern "C" fn syscall_handler() {
     );
 }
 
+#[cfg(not(feature = "syscall-stats"))]
+#[naked]
+#[unsafe(no_mangle)]
+#[allow(unsafe_op_in_unsafe_fn)]
+unsafe extern "C" fn syscall_disp() {
+    asm!(
+    "call [{SYSCALL_TABLE} + 8 * rax]",
+    "ret",
+    SYSCALL_TABLE = sym SYSCALL_TABLE,
+    options(noreturn)
+    );
+}
+
+#[cfg(feature = "syscall-stats")]
 #[naked]
 #[unsafe(no_mangle)]
 #[allow(unsafe_op_in_unsafe_fn)]
+/// Description: Same as above, but measures the latency of each system call (see `sys_stats.rs`)
 unsafe extern "C" fn syscall_disp() {
     asm!(
+    "push rax", // Save system call ID
+    "push rdx", // rdtsc overwrites rdx (3rd parameter)
+    "rdtsc",
+    "shl rdx, 32",
+    "or rax, rdx",
+    "mov r11, rax", // r11 = start timestamp
+    "pop rdx",
+    "mov rax, [rsp]", // Restore system call ID
+    "push r11", // Save start timestamp
+
     "call [{SYSCALL_TABLE} + 8 * rax]",
+
+    "mov rdi, [rsp + 8]", // 1st parameter: system call ID
+    "mov rsi, [rsp]", // 2nd parameter: start timestamp
+    "mov [rsp], rax", // Save return value
+    "call {RECORD}",
+    "pop rax", // Restore return value
+    "add rsp, 8",
     "ret",
     SYSCALL_TABLE = sym SYSCALL_TABLE,
+    RECORD = sym crate::syscall::sys_stats::syscall_stats_record,
     options(noreturn)
     );
 }
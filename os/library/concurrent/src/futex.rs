@@ -0,0 +1,36 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: futex                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Syscalls for futexes: a thread blocks on an `AtomicU32` with    ║
+   ║         `wait()`, as long as it contains an expected value, until       ║
+   ║         another thread of the process calls `wake()` on it. Mutexes and ║
+   ║         condition variables can be built on this without spinning (the  ║
+   ║         fast path only uses atomic operations on the value).            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::sync::atomic::AtomicU32;
+use syscall::{syscall, SystemCall};
+use syscall::return_vals::Errno;
+
+///
+/// Description:
+///    Block the calling thread, if `futex` contains `expected`, until `wake()` is called on `futex`.
+///    The comparison and blocking are atomic with respect to `wake()`, so a wake-up is never lost.
+///    Callers have to check their condition again after returning.
+///
+/// Return: `Ok(())` after being woken up or `Errno::EAGAIN`, if `futex` did not contain `expected`
+///
+pub fn wait(futex: &AtomicU32, expected: u32) -> Result<(), Errno> {
+    syscall(SystemCall::FutexWait, &[futex.as_ptr() as usize, expected as usize]).map(|_| ())
+}
+
+///
+/// Description: Wake up at most `count` threads blocked in `wait()` on `futex` (`usize::MAX` for all of them).
+///
+/// Return: number of threads woken up
+///
+pub fn wake(futex: &AtomicU32, count: usize) -> usize {
+    syscall(SystemCall::FutexWake, &[futex.as_ptr() as usize, count]).unwrap_or(0)
+}
@@ -2,5 +2,6 @@
 
 extern crate alloc;
 
+pub mod futex;
 pub mod process;
 pub mod thread;
\ No newline at end of file
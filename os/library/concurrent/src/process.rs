@@ -6,7 +6,15 @@
    ║ Author: Fabian Ruhland, Michael Schoettner, 31.8.2024, HHU              ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use syscall::{syscall, SystemCall};
+use syscall::builder::SyscallBuilder;
+use syscall::env;
+use syscall::process::{process_count, process_list, ProcessInfo, ProcessState};
+use syscall::return_vals::Errno;
 
 pub struct Process {
     id: usize,
@@ -20,16 +28,152 @@ impl Process {
     pub fn id(&self) -> usize {
         self.id
     }
+
+    /// Description: Wait for the process to exit and return its exit code
+    pub fn wait(&self) -> Result<i32, Errno> {
+        wait(self.id)
+    }
 }
 
 pub fn current() -> Option<Process> {
-    let res = syscall(SystemCall::ProcessId, &[]);
-    match res {
-        Ok(id) => Some(Process::new(id)),
-        Err(_) => None,
-    }    
+    Some(Process::new(sys_process_id()))
+}
+
+/// Cached id of the calling process (the same for all its threads, 'usize::MAX' = not cached yet)
+static PROCESS_ID: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Description: Id of the calling process (only the first call uses the system call)
+pub fn sys_process_id() -> usize {
+    match PROCESS_ID.load(Ordering::Relaxed) {
+        usize::MAX => {
+            let id = syscall(SystemCall::ProcessId, &[]).expect("System call 'ProcessId' failed");
+            PROCESS_ID.store(id, Ordering::Relaxed);
+            id
+        }
+        id => id,
+    }
+}
+
+/// Description: User id of the calling process (0 = root)
+pub fn uid() -> u32 {
+    syscall(SystemCall::GetUid, &[]).unwrap_or(0) as u32
+}
+
+///
+/// Description: Change the user id of the calling process (inherited by new processes).
+///
+/// Return: `Errno::EACCES`, if the calling process is not root and `uid` is not its own user id
+///
+pub fn set_uid(uid: u32) -> Result<(), Errno> {
+    syscall(SystemCall::SetUid, &[uid as usize]).map(|_| ())
+}
+
+///
+/// Description: Check the password of the user `user` (the user id of the calling process is not changed).
+///
+/// Return: the user id or `Errno::EACCES`, if the user is unknown or the password is wrong
+///
+pub fn authenticate(user: &str, password: &str) -> Result<u32, Errno> {
+    syscall(SystemCall::Authenticate, &[user.as_bytes().as_ptr() as usize,
+    user.len(),
+    password.as_bytes().as_ptr() as usize,
+    password.len(),]).map(|uid| uid as u32)
+}
+
+///
+/// Description: Start the application `name` from the initial ramdisk in a new process,
+///              which inherits the environment variables of the calling process.
+///
+/// Return: the new process or `Errno::ENOENT`, if there is no such application
+///
+pub fn execute(name: &str, args: Vec<&str>) -> Result<Process, Errno> {
+    execute_with_env(name, args, (0..env::envc()).filter_map(env::env).collect())
+}
+
+///
+/// Description: Start the application `name` from the initial ramdisk in a new process with the environment variables `vars` ("NAME=value").
+///
+/// Return: the new process or `Errno::ENOENT`, if there is no such application
+///
+pub fn execute_with_env(name: &str, args: Vec<&str>, vars: Vec<&str>) -> Result<Process, Errno> {
+    syscall(SystemCall::ProcessExecuteBinary, &[name.as_bytes().as_ptr() as usize,
+    name.len(),
+    ptr::from_ref(&args) as usize,
+    ptr::from_ref(&vars) as usize,]).map(Process::new)
+}
+
+///
+/// Description: Wait for the child process `id` to exit. The exit code of a process can only be retrieved once.
+///
+/// Return: exit code of the process, `Errno::ENOENT` if there is no such process
+///         (or its exit code has already been retrieved), `Errno::ECHILD` if it has not
+///         been started by the calling process or `Errno::EINVAL` when waiting for itself
+///
+pub fn wait(id: usize) -> Result<i32, Errno> {
+    SyscallBuilder::new(SystemCall::ProcessWait).arg(id).invoke().map(|exit_code| exit_code as u32 as i32)
+}
+
+///
+/// Description: Terminate the calling process (all its threads).
+///
+/// Parameters: `exit_code` 0 = success, anything else = failure
+///
+pub fn exit(exit_code: i32) -> ! {
+    let _ = syscall(SystemCall::ProcessExit, &[exit_code as isize as usize]);
+    panic!("System call 'ProcessExit' has returned!")
+}
+
+///
+/// Description: Infos of all processes (including exited processes, which have not been cleaned up yet)
+///
+/// Return: the infos, sorted by process id
+///
+pub fn list() -> Result<Vec<ProcessInfo>, Errno> {
+    loop {
+        // Some room for processes created between both system calls
+        let capacity = process_count()? + 4;
+        let mut infos = vec![ProcessInfo::new(0, 0, ProcessState::Active, ""); capacity];
+        match process_list(&mut infos) {
+            Ok(count) => {
+                infos.truncate(count);
+                infos.sort_by_key(|info| info.pid);
+                return Ok(infos);
+            }
+            Err(Errno::ENOMEM) => continue,
+            Err(errno) => return Err(errno),
+        }
+    }
+}
+
+///
+/// Description: Shut down the system and power off the machine (only returns on failure).
+///
+/// Return: `Errno::EACCES`, if the calling process does not run as root
+///
+pub fn poweroff() -> Errno {
+    match syscall(SystemCall::Poweroff, &[]) {
+        Ok(_) => panic!("System call 'Poweroff' has returned!"),
+        Err(errno) => errno,
+    }
+}
+
+///
+/// Description: Shut down the system and reset the machine (only returns on failure).
+///
+/// Return: `Errno::EACCES`, if the calling process does not run as root
+///
+pub fn reboot() -> Errno {
+    match syscall(SystemCall::Reboot, &[]) {
+        Ok(_) => panic!("System call 'Reboot' has returned!"),
+        Err(errno) => errno,
+    }
 }
 
-pub fn exit() {
-    syscall(SystemCall::ProcessExit, &[]).expect("Failed to exit process");
+///
+/// Description:
+///    Check if the system is shutting down. Long running processes should check this
+///    regularly and exit, since remaining processes are killed after one second.
+///
+pub fn shutdown_pending() -> bool {
+    syscall(SystemCall::ShutdownPending, &[]).is_ok_and(|pending| pending == 1)
 }
@@ -1,14 +1,36 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: thread                                                          ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: Syscalls for thread functions.                                  ║
+   ║ Descr.: Syscalls for thread functions. The id of the calling thread is  ║
+   ║         cached per thread. There is no thread local storage, so the     ║
+   ║         cache is an array of atomics, keyed by the user stack slot of   ║
+   ║         the thread (each thread has its own slot of                     ║
+   ║         'MAX_USER_STACK_SIZE' bytes above 'MAIN_USER_STACK_START', see  ║
+   ║         'kernel/src/process/thread.rs'). A new thread resets the entry  ║
+   ║         of its slot, which may have been used by an exited thread.      ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, Michael Schoettner, 31.8.2024, HHU              ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
-use alloc::vec::Vec;
 use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use syscall::{syscall, SystemCall};
+use syscall::builder::SyscallBuilder;
+use syscall::return_vals::Errno;
+
+// Duplicated from 'kernel/src/process/scheduler.rs'
+pub const MAX_PRIORITY: u8 = 7;
+pub const DEFAULT_PRIORITY: u8 = 4;
+
+// Duplicated from 'kernel/src/consts.rs'
+const MAIN_USER_STACK_START: usize = 0x10080000000;
+const MAX_USER_STACK_SIZE: usize = 0x40000000;
+
+/// Number of stack slots with a cached thread id (threads in higher slots always use the system call)
+const CACHED_STACK_SLOTS: usize = 64;
+const NO_ID: usize = usize::MAX;
+
+static THREAD_IDS: [AtomicUsize; CACHED_STACK_SLOTS] = [const { AtomicUsize::new(NO_ID) }; CACHED_STACK_SLOTS];
 
 pub struct Thread {
     id: usize,
@@ -23,14 +45,24 @@ impl Thread {
         self.id
     }
 
-    pub fn join(&self) {
-        let _ = syscall(SystemCall::ThreadJoin, &[self.id]);
+    /// Description: Wait for the thread to terminate and return its exit code
+    pub fn join(&self) -> Result<usize, Errno> {
+        join(self.id)
+    }
+
+    pub fn set_priority(&self, priority: u8) -> Result<(), Errno> {
+        sys_thread_set_priority(self.id, priority)
     }
 }
 
 fn kickoff_user_thread(entry: fn()) {
+    // The stack slot may have been used by an exited thread before
+    if let Some(cached_id) = cached_id() {
+        cached_id.store(NO_ID, Ordering::Relaxed);
+    }
+
     entry();
-    exit();
+    exit(0);
 }
 
 pub fn create(entry: fn()) -> Option<Thread> {
@@ -43,11 +75,34 @@ pub fn create(entry: fn()) -> Option<Thread> {
 }
 
 pub fn current() -> Option<Thread> {
-    let res = syscall(SystemCall::ThreadId, &[]);
-    match res {
-        Ok(id) => Some(Thread::new(id as usize)),
-        Err(_) => None,
-    }    
+    Some(Thread::new(sys_thread_id()))
+}
+
+///
+/// Description:
+///    Id of the calling thread. Only the first call of a thread uses the system call,
+///    later calls return the cached id (see module description).
+///
+pub fn sys_thread_id() -> usize {
+    let cached_id = cached_id();
+    if let Some(id) = cached_id.map(|cached_id| cached_id.load(Ordering::Relaxed)).filter(|id| *id != NO_ID) {
+        return id;
+    }
+
+    let id = syscall(SystemCall::ThreadId, &[]).expect("System call 'ThreadId' failed");
+    if let Some(cached_id) = cached_id {
+        cached_id.store(id, Ordering::Relaxed);
+    }
+
+    id
+}
+
+/// Description: Cache entry of the calling thread (determined by the address of a local variable on its stack)
+fn cached_id() -> Option<&'static AtomicUsize> {
+    let stack_marker = 0u8;
+    let slot = (ptr::addr_of!(stack_marker) as usize).checked_sub(MAIN_USER_STACK_START)? / MAX_USER_STACK_SIZE;
+
+    THREAD_IDS.get(slot)
 }
 
 #[allow(dead_code)]
@@ -55,22 +110,41 @@ pub fn switch() {
     let _ = syscall(SystemCall::ThreadSwitch, &[]);
 }
 
+/// Put the calling thread at the end of the ready queue and run the next thread (if any) without blocking
+#[allow(dead_code)]
+pub fn yield_now() {
+    let _ = SyscallBuilder::new(SystemCall::ThreadYield).invoke();
+}
+
 #[allow(dead_code)]
 pub fn sleep(ms: usize) {
     let _ = syscall(SystemCall::ThreadSleep, &[ms]);
 }
 
-pub fn exit() -> ! {
-    let _ = syscall(SystemCall::ThreadExit, &[]);
-    panic!("System call 'ThreadExit' has returned!")
+///
+/// Description: Set scheduling priority of thread `id` (must belong to the calling process).
+///
+/// Parameters: `priority` 0 (lowest) ..= `MAX_PRIORITY` (highest), above `DEFAULT_PRIORITY` only for root
+///
+/// Return: `Errno::EACCES`, if a process not running as root raises a thread above `DEFAULT_PRIORITY`
+///
+pub fn sys_thread_set_priority(id: usize, priority: u8) -> Result<(), Errno> {
+    syscall(SystemCall::ThreadSetPriority, &[id, priority as usize]).map(|_| ())
 }
 
-pub fn start_application(name: &str, args: Vec<&str>) -> Option<Thread> {
-    let res = syscall(SystemCall::ProcessExecuteBinary, &[name.as_bytes().as_ptr() as usize,
-    name.len(),
-    ptr::from_ref(&args) as usize,]);
-    match res {
-        Ok(id) => Some(Thread::new(id as usize)),
-        Err(_) => None,
-    }    
+///
+/// Description: Wait for thread `id` to terminate.
+///
+/// Return: exit code of the thread, `Errno::ENOENT` if there is no such thread
+///         (or its exit code has already been retrieved) or `Errno::EINVAL` when joining itself
+///
+pub fn join(id: usize) -> Result<usize, Errno> {
+    syscall(SystemCall::ThreadJoin, &[id])
 }
+
+/// Description: Terminate the calling thread. `exit_code` (at most `isize::MAX`) is passed to `join()`.
+pub fn exit(exit_code: usize) -> ! {
+    let _ = syscall(SystemCall::ThreadExit, &[exit_code]);
+    panic!("System call 'ThreadExit' has returned!")
+}
+
@@ -1,10 +1,63 @@
-use crate::lfb::LFB;
+use crate::lfb::{bytes_per_pixel, LFB};
 use alloc::vec::Vec;
 
+/// Dirty rectangles kept before all of them are merged into their bounding box
+const MAX_DIRTY_RECTS: usize = 16;
+
+/// Double buffered LFB: All drawing goes to a back buffer on the heap (see `lfb()`),
+/// which is copied to the real framebuffer by `flush()` or `present()`.
+/// Reading from MMIO memory is slow and writing single pixels to it causes tearing,
+/// so the framebuffer should only be written in large blocks.
 pub struct BufferedLFB {
     buffer: Vec<u8>,
     lfb: LFB,
     target_lfb: LFB,
+    dirty: Vec<Rect>, // regions, that have not been presented yet (never overlapping or adjacent)
+    stats: DamageStats,
+}
+
+/// Rectangle on the screen (in pixels)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Statistics of the damage tracking (see `BufferedLFB::damage_stats()`)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DamageStats {
+    pub presents: usize,      // calls of `present()`, that copied at least one region
+    pub rects: usize,         // regions copied by `present()`
+    pub coalesced: usize,     // dirty rectangles merged with an overlapping or adjacent one
+    pub flushed_bytes: usize, // bytes copied to the framebuffer (by `present()` and `flush()`)
+}
+
+impl Rect {
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    const fn end_x(&self) -> u32 {
+        self.x + self.width
+    }
+
+    const fn end_y(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// Description: `other` overlaps this rectangle or adjoins it (sharing an edge or a corner)
+    pub const fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.end_x() && other.x <= self.end_x() && self.y <= other.end_y() && other.y <= self.end_y()
+    }
+
+    /// Description: Smallest rectangle containing this rectangle and `other`
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.end_x().max(other.end_x()) - x, self.end_y().max(other.end_y()) - y)
+    }
 }
 
 impl BufferedLFB {
@@ -12,7 +65,8 @@ impl BufferedLFB {
         let buffer = Vec::with_capacity((lfb.height() * lfb.pitch()) as usize);
         let raw_buffer = buffer.as_ptr() as *mut u8;
 
-        Self { buffer, lfb: LFB::new(raw_buffer, lfb.pitch(), lfb.width(), lfb.height(), lfb.bpp()), target_lfb: lfb }
+        Self { buffer, lfb: LFB::new(raw_buffer, lfb.pitch(), lfb.width(), lfb.height(), lfb.bpp(), lfb.pixel_format()), target_lfb: lfb,
+            dirty: Vec::with_capacity(MAX_DIRTY_RECTS + 1), stats: DamageStats::default() }
     }
 
     pub fn lfb(&mut self) -> &mut LFB {
@@ -23,15 +77,103 @@ impl BufferedLFB {
         &mut self.target_lfb
     }
 
+    /// Description: Number of bytes copied to the framebuffer so far
+    pub fn flushed_bytes(&self) -> usize {
+        self.stats.flushed_bytes
+    }
+
+    /// Description: Statistics of the damage tracking (e.g. to tune the size of dirty regions)
+    pub fn damage_stats(&self) -> DamageStats {
+        self.stats
+    }
+
+    /// Description: Regions, that have been drawn to since the last `present()`
+    pub fn dirty_rects(&self) -> &[Rect] {
+        &self.dirty
+    }
+
+    ///
+    /// Description: Remember lines of the back buffer, that have been drawn to (copied by `present()`).
+    ///
+    /// Parameters: \
+    ///    `start` first line (in pixels) \
+    ///    `count` number of lines
+    ///
+    pub fn mark_dirty(&mut self, start: u32, count: u32) {
+        self.mark_dirty_rect(Rect::new(0, start, self.lfb.width(), count));
+    }
+
+    ///
+    /// Description:
+    ///    Remember a region of the back buffer, that has been drawn to (copied by `present()`).
+    ///    The region is clipped to the screen and merged with dirty regions it overlaps or
+    ///    adjoins. If there are too many regions, they are merged into their bounding box,
+    ///    so a region may be copied needlessly, but never missed.
+    ///
+    pub fn mark_dirty_rect(&mut self, rect: Rect) {
+        let x = rect.x.min(self.lfb.width());
+        let y = rect.y.min(self.lfb.height());
+        let mut rect = Rect::new(x, y, rect.end_x().min(self.lfb.width()).saturating_sub(x), rect.end_y().min(self.lfb.height()).saturating_sub(y));
+        if rect.width == 0 || rect.height == 0 {
+            return;
+        }
+
+        // Merging may make the rectangle touch other rectangles, so repeat until no rectangle touches it
+        while let Some(index) = self.dirty.iter().position(|dirty| dirty.touches(&rect)) {
+            rect = rect.union(&self.dirty.swap_remove(index));
+            self.stats.coalesced += 1;
+        }
+
+        if self.dirty.len() == MAX_DIRTY_RECTS {
+            rect = self.dirty.drain(..).fold(rect, |bounds, dirty| bounds.union(&dirty));
+            self.stats.coalesced += MAX_DIRTY_RECTS;
+        }
+
+        self.dirty.push(rect);
+    }
+
     pub fn flush_lines(&mut self, start: u32, count: u32) {
         let offset = (self.lfb.pitch() * start) as isize;
         let bytes = (self.lfb().pitch() * count) as usize;
 
         unsafe { self.target_lfb.buffer().offset(offset).copy_from(self.buffer.as_ptr().offset(offset), bytes); }
+        self.stats.flushed_bytes += bytes;
+    }
+
+    /// Description: Copy `rect` from the back buffer to the framebuffer (whole lines are copied with a single copy)
+    fn flush_rect(&mut self, rect: Rect) {
+        if rect.x == 0 && rect.width == self.lfb.width() {
+            self.flush_lines(rect.y, rect.height);
+            return;
+        }
+
+        let bytes = (rect.width * bytes_per_pixel(self.lfb.bpp())) as usize;
+        for y in rect.y..rect.end_y() {
+            let offset = self.lfb.pixel_offset(rect.x, y);
+            unsafe { self.target_lfb.buffer().add(offset).copy_from(self.buffer.as_ptr().add(offset), bytes); }
+        }
+
+        self.stats.flushed_bytes += bytes * rect.height as usize;
+    }
+
+    /// Description: Copy all regions marked by `mark_dirty()` and `mark_dirty_rect()` to the framebuffer
+    pub fn present(&mut self) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        let mut dirty = core::mem::take(&mut self.dirty);
+        for rect in dirty.drain(..) {
+            self.flush_rect(rect);
+            self.stats.rects += 1;
+        }
+
+        self.dirty = dirty; // keep the capacity
+        self.stats.presents += 1;
     }
 
     pub fn flush(&mut self) {
+        self.dirty.clear();
         self.flush_lines(0, self.lfb.height());
-        // unsafe { self.target_lfb.buffer().copy_from(self.buffer.as_ptr(), (self.lfb.height() * self.lfb.pitch()) as usize); }
     }
 }
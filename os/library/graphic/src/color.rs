@@ -20,7 +20,7 @@ use core::marker::Copy;
  *  Red  Green Blue
  * XXXXX XXXXX XXXXX
  */
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
@@ -47,6 +47,99 @@ pub const WHITE: Color = Color { red: 170, green: 170, blue: 170, alpha: 255, };
 pub const HHU_BLUE: Color = Color { red: 0, green: 106, blue: 179, alpha: 255 };
 pub const HHU_GREEN: Color = Color { red: 140, green: 177, blue: 16, alpha: 255 };
 
+/// Position and size (in bits) of a color channel in a pixel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChannelMask {
+    pub position: u8,
+    pub size: u8,
+}
+
+impl ChannelMask {
+    pub const fn new(position: u8, size: u8) -> Self {
+        Self { position, size }
+    }
+
+    /// Description: Put the 8-bit channel `value` (reduced to `size` bits) at its position
+    const fn pack(&self, value: u8) -> u32 {
+        match self.size {
+            0 => 0,
+            size if size >= 8 => (value as u32) << self.position,
+            size => ((value as u32) >> (8 - size)) << self.position,
+        }
+    }
+
+    /// Description: Extract the channel from `pixel` and scale it to 8 bits
+    const fn unpack(&self, pixel: u32) -> u8 {
+        match self.size {
+            0 => 0,
+            size if size >= 8 => (pixel >> self.position) as u8,
+            size => (((pixel >> self.position) & ((1 << size) - 1)) << (8 - size)) as u8,
+        }
+    }
+}
+
+///
+/// Byte order of the color channels in the framebuffer.
+/// `Rgb` is the layout described above (red in the highest bits), `Bgr` swaps red and blue
+/// and `Bitmask` uses arbitrary channel positions (e.g. as reported by the bootloader).
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgb,
+    Bgr,
+    Bitmask { red: ChannelMask, green: ChannelMask, blue: ChannelMask },
+}
+
+impl PixelFormat {
+    ///
+    /// Description: Pixel format for the channel masks of a framebuffer with `bpp` bits per pixel.
+    ///
+    /// Return: `Rgb` or `Bgr`, if the masks match one of their layouts, `Bitmask` otherwise
+    ///
+    pub fn from_masks(bpp: u8, red: ChannelMask, green: ChannelMask, blue: ChannelMask) -> Self {
+        let (high, middle, low) = match bpp {
+            32 | 24 => (ChannelMask::new(16, 8), ChannelMask::new(8, 8), ChannelMask::new(0, 8)),
+            16 => (ChannelMask::new(11, 5), ChannelMask::new(5, 6), ChannelMask::new(0, 5)),
+            15 => (ChannelMask::new(10, 5), ChannelMask::new(5, 5), ChannelMask::new(0, 5)),
+            _ => return PixelFormat::Bitmask { red, green, blue },
+        };
+
+        if green != middle {
+            PixelFormat::Bitmask { red, green, blue }
+        } else if red == high && blue == low {
+            PixelFormat::Rgb
+        } else if red == low && blue == high {
+            PixelFormat::Bgr
+        } else {
+            PixelFormat::Bitmask { red, green, blue }
+        }
+    }
+
+    /// Description: Pixel value of `color` in a framebuffer with `bpp` bits per pixel (alpha is only kept with 32 bpp)
+    pub const fn pack(&self, color: Color, bpp: u8) -> u32 {
+        match self {
+            PixelFormat::Rgb => color.rgb(bpp),
+            PixelFormat::Bgr => color.swap_red_blue().rgb(bpp),
+            PixelFormat::Bitmask { red, green, blue } => {
+                let alpha = if bpp == 32 { (color.alpha as u32) << 24 } else { 0 };
+                red.pack(color.red) | green.pack(color.green) | blue.pack(color.blue) | alpha
+            }
+        }
+    }
+
+    /// Description: Color of the pixel value `pixel` in a framebuffer with `bpp` bits per pixel
+    pub const fn unpack(&self, pixel: u32, bpp: u8) -> Color {
+        match self {
+            PixelFormat::Rgb => Color::from_rgb(pixel, bpp),
+            PixelFormat::Bgr => Color::from_rgb(pixel, bpp).swap_red_blue(),
+            PixelFormat::Bitmask { red, green, blue } => {
+                let alpha = if bpp == 32 { (pixel >> 24) as u8 } else { 0 };
+                Color { red: red.unpack(pixel), green: green.unpack(pixel), blue: blue.unpack(pixel), alpha }
+            }
+        }
+    }
+}
+
 impl Color {
     pub const fn from_rgb(rgb: u32, bpp: u8) -> Color {
         match bpp {
@@ -91,6 +184,21 @@ impl Color {
         Self { red, green, blue, alpha: 0 }
     }
 
+    /// Description: Pixel value with `bpp` bits per pixel in the layout described above
+    pub const fn rgb(&self, bpp: u8) -> u32 {
+        match bpp {
+            32 => self.rgb_32(),
+            24 => self.rgb_24(),
+            16 => self.rgb_16() as u32,
+            15 => self.rgb_15() as u32,
+            _ => panic!("Color: Invalid bpp!"),
+        }
+    }
+
+    pub const fn swap_red_blue(&self) -> Color {
+        Self { red: self.blue, green: self.green, blue: self.red, alpha: self.alpha }
+    }
+
     pub const fn rgb_32(&self) -> u32 {
         ((self.alpha as u32) << 24) | ((self.red as u32) << 16) | ((self.green as u32) << 8) | ((self.blue) as u32)
     }
@@ -1,5 +1,8 @@
+use alloc::format;
+use alloc::vec::Vec;
 use unifont::get_glyph;
-use crate::color::Color;
+use crate::buffered_lfb::BufferedLFB;
+use crate::color::{Color, PixelFormat};
 
 pub struct LFB {
     buffer: *mut u8,
@@ -7,6 +10,7 @@ pub struct LFB {
     width: u32,
     height: u32,
     bpp: u8,
+    format: PixelFormat,
 
     pixel_drawer: PixelDrawer,
 }
@@ -16,9 +20,26 @@ unsafe impl Sync for LFB {}
 
 pub const DEFAULT_CHAR_WIDTH: u32 = 8;
 pub const DEFAULT_CHAR_HEIGHT: u32 = 16;
+/// Largest supported font scale (see `draw_char_scaled()`)
+pub const MAX_FONT_SCALE: u32 = 4;
+/// Drawn for characters without glyph in the font (a question mark in a diamond)
+pub const REPLACEMENT_CHAR: char = '\u{fffd}';
+
+/// Description: Bytes used by a pixel with `bpp` bits (15 bit pixels are stored in 16 bits, the highest bit is unused)
+pub const fn bytes_per_pixel(bpp: u8) -> u32 {
+    match bpp {
+        15 => 2,
+        bpp => (bpp as u32).div_ceil(8),
+    }
+}
+
+/// Description: The font (Unifont) has a glyph for `c`. It covers the BMP, including box drawing (U+2500-U+257F) and block elements (U+2580-U+259F).
+pub fn has_glyph(c: char) -> bool {
+    get_glyph(c).is_some()
+}
 
 impl LFB {
-    pub const fn new(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8) -> Self {
+    pub const fn new(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8, format: PixelFormat) -> Self {
         let pixel_drawer: PixelDrawer = match bpp {
             15 => draw_pixel_15_bit,
             16 => draw_pixel_16_bit,
@@ -27,7 +48,15 @@ impl LFB {
             _ => draw_pixel_stub,
         };
 
-        Self { buffer, pitch, width, height, bpp, pixel_drawer }
+        Self { buffer, pitch, width, height, bpp, format, pixel_drawer }
+    }
+
+    /// Description:
+    ///    Create an LFB with a back buffer on the heap. Drawing operations go to the back buffer,
+    ///    which is copied to `buffer` with `BufferedLFB::flush()`. Requires the heap, so
+    ///    `new()` must be used before the heap has been initialized.
+    pub fn new_double_buffered(buffer: *mut u8, pitch: u32, width: u32, height: u32, bpp: u8, format: PixelFormat) -> BufferedLFB {
+        BufferedLFB::new(LFB::new(buffer, pitch, width, height, bpp, format))
     }
 
     pub const fn buffer(&self) -> *mut u8 {
@@ -50,6 +79,19 @@ impl LFB {
         self.bpp
     }
 
+    pub const fn pixel_format(&self) -> PixelFormat {
+        self.format
+    }
+
+    ///
+    /// Description:
+    ///    Offset of pixel (`x`, `y`) in bytes. Rows start every `pitch` bytes, which may be more
+    ///    than `width` pixels (firmware may pad scanlines to any number of bytes).
+    ///
+    pub const fn pixel_offset(&self, x: u32, y: u32) -> usize {
+        pixel_offset(self.pitch, self.bpp, x, y)
+    }
+
     pub fn draw_pixel(&self, x: u32, y: u32, color: Color) {
         // Check if pixel is outside the framebuffer
         if x >= self.width || y >= self.height {
@@ -62,11 +104,8 @@ impl LFB {
         }
 
         // Blend if necessary and draw pixel
-        if color.alpha < 255 {
-            unsafe { (self.pixel_drawer)(self.buffer, self.pitch, x, y, self.read_pixel(x, y).blend(color)) };
-        } else {
-            unsafe { (self.pixel_drawer)(self.buffer, self.pitch, x, y, color) };
-        }
+        let color = if color.alpha < 255 { self.read_pixel(x, y).blend(color) } else { color };
+        unsafe { (self.pixel_drawer)(self.buffer.add(self.pixel_offset(x, y)), self.format.pack(color, self.bpp)) };
     }
 
     pub fn read_pixel(&self, x: u32, y: u32) -> Color {
@@ -74,12 +113,10 @@ impl LFB {
             panic!("LinearFrameBuffer: Trying to read a pixel out of bounds!");
         }
 
-        let bpp = if self.bpp == 15 { 16 } else { self.bpp() };
-
-        unsafe {
-            let ptr = self.buffer.offset(((x * (bpp / 8) as u32) + y * self.pitch) as isize) as *const u32;
-            Color::from_rgb(ptr.read(), self.bpp)
-        }
+        // Only the bytes of this pixel are read (reading a u32 could exceed the framebuffer at its end)
+        let mut bytes = [0u8; 4];
+        unsafe { bytes.as_mut_ptr().copy_from_nonoverlapping(self.buffer.add(self.pixel_offset(x, y)), bytes_per_pixel(self.bpp) as usize); }
+        self.format.unpack(u32::from_le_bytes(bytes), self.bpp)
     }
 
     pub fn fill_rect(&self, x: u32, y: u32, width: u32, height: u32, color: Color) {
@@ -97,8 +134,13 @@ impl LFB {
         self.draw_char_scaled(x, y, 1, 1, fg_color, bg_color, c)
     }
 
+    ///
+    /// Description:
+    ///    Draw `c` with each glyph pixel replicated to `x_scale` x `y_scale` pixels. Characters without
+    ///    glyph are drawn as `REPLACEMENT_CHAR`. Returns the unscaled glyph width.
+    ///
     pub fn draw_char_scaled(&self, x: u32, y: u32, x_scale: u32, y_scale: u32, fg_color: Color, bg_color: Color, c: char) -> u32 {
-        return match get_glyph(c) {
+        return match get_glyph(c).or_else(|| get_glyph(REPLACEMENT_CHAR)) {
             Some(glyph) => {
                 let mut x_offset = 0;
                 let mut y_offset = 0;
@@ -139,13 +181,35 @@ impl LFB {
         }
     }
 
+    /// Description: Encode the framebuffer as binary PPM image (P6 with 8 bits per channel, alpha is dropped)
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let header = format!("P6\n{} {}\n255\n", self.width, self.height);
+        let mut ppm = Vec::with_capacity(header.len() + (self.width * self.height * 3) as usize);
+        ppm.extend_from_slice(header.as_bytes());
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = self.read_pixel(x, y);
+                ppm.extend_from_slice(&[color.red, color.green, color.blue]);
+            }
+        }
+
+        ppm
+    }
+
     pub fn clear(&self) {
         unsafe {
             self.buffer.write_bytes(0, (self.pitch * self.height) as usize);
         }
     }
 
+    /// Description: Move the content `lines` pixel rows up and clear the rows below (the pixel size does not matter, since whole rows are moved)
     pub fn scroll_up(&self, lines: u32) {
+        if lines >= self.height {
+            self.clear();
+            return;
+        }
+
         unsafe {
             // Move screen buffer upwards by the given amount of lines
             self.buffer.copy_from(self.buffer.offset((self.pitch * lines) as isize), (self.pitch * (self.height - lines)) as usize);
@@ -156,40 +220,36 @@ impl LFB {
     }
 }
 
-type PixelDrawer = unsafe fn(addr: *mut u8, pitch: u32, x: u32, y: u32, color: Color);
-
-fn draw_pixel_stub(_addr: *mut u8, _pitch: u32, _x: u32, _y: u32, _color: Color) {
-    panic!("Using empty LFB!");
+/// Description: Offset of pixel (`x`, `y`) in bytes for a framebuffer with `pitch` bytes per row and `bpp` bits per pixel
+const fn pixel_offset(pitch: u32, bpp: u8, x: u32, y: u32) -> usize {
+    y as usize * pitch as usize + x as usize * bytes_per_pixel(bpp) as usize
 }
 
-unsafe fn draw_pixel_15_bit(addr: *mut u8, pitch: u32, x: u32, y: u32, color: Color) {
-    let index = (x + y * (pitch / 2)) as isize;
-    let rgb = color.rgb_15();
+/// Writes a pixel value (packed with the pixel format of the LFB) to the address of the pixel.
+/// The address need not be aligned, since the pitch may be any number of bytes.
+type PixelDrawer = unsafe fn(addr: *mut u8, pixel: u32);
 
-    unsafe { (addr as *mut u16).offset(index).write(rgb); }
+fn draw_pixel_stub(_addr: *mut u8, _pixel: u32) {
+    panic!("Using empty LFB!");
 }
 
-unsafe fn draw_pixel_16_bit(addr: *mut u8, pitch: u32, x: u32, y: u32, color: Color) {
-    let index = (x + y * (pitch / 2)) as isize;
-    let rgb = color.rgb_16();
-
-    unsafe { (addr as *mut u16).offset(index).write(rgb); }
+/// Same as 16 bit, but the highest bit is unused
+unsafe fn draw_pixel_15_bit(addr: *mut u8, pixel: u32) {
+    unsafe { (addr as *mut u16).write_unaligned((pixel & 0x7fff) as u16); }
 }
 
-unsafe fn draw_pixel_24_bit(addr: *mut u8, pitch: u32, x: u32, y: u32, color: Color) {
-    let index = (x * 3 + y * pitch) as isize;
-    let rgb = color.rgb_24();
+unsafe fn draw_pixel_16_bit(addr: *mut u8, pixel: u32) {
+    unsafe { (addr as *mut u16).write_unaligned(pixel as u16); }
+}
 
+unsafe fn draw_pixel_24_bit(addr: *mut u8, pixel: u32) {
     unsafe {
-        addr.offset(index).write((rgb & 0xff) as u8);
-        addr.offset(index + 1).write(((rgb >> 8) & 0xff) as u8);
-        addr.offset(index + 2).write(((rgb >> 16) & 0xff) as u8);
+        addr.write((pixel & 0xff) as u8);
+        addr.add(1).write(((pixel >> 8) & 0xff) as u8);
+        addr.add(2).write(((pixel >> 16) & 0xff) as u8);
     }
 }
 
-unsafe fn draw_pixel_32_bit(addr: *mut u8, pitch: u32, x: u32, y: u32, color: Color) {
-    let index = (x + y * (pitch / 4)) as isize;
-    let rgb = color.rgb_32();
-
-    unsafe { (addr as *mut u32).offset(index).write(rgb); }
+unsafe fn draw_pixel_32_bit(addr: *mut u8, pixel: u32) {
+    unsafe { (addr as *mut u32).write_unaligned(pixel); }
 }
@@ -6,3 +6,4 @@ pub mod ansi;
 pub mod buffered_lfb;
 pub mod color;
 pub mod lfb;
+pub mod offscreen_lfb;
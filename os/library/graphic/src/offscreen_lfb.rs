@@ -0,0 +1,33 @@
+use crate::color::PixelFormat;
+use crate::lfb::{bytes_per_pixel, LFB};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// LFB drawing to a buffer on the heap instead of a memory mapped framebuffer.
+/// Used to render text and graphics without a display (e.g. in tests), which can be
+/// checked pixel by pixel (see `LFB::read_pixel()`) or dumped with `LFB::to_ppm()`.
+pub struct OffscreenLFB {
+    buffer: Vec<u8>,
+    lfb: LFB,
+}
+
+impl OffscreenLFB {
+    /// Description: Create a cleared off-screen framebuffer without padding (the pitch is `width` pixels)
+    pub fn new(width: u32, height: u32, bpp: u8, format: PixelFormat) -> Self {
+        let pitch = width * bytes_per_pixel(bpp);
+        let mut buffer = vec![0u8; (pitch * height) as usize];
+        let lfb = LFB::new(buffer.as_mut_ptr(), pitch, width, height, bpp, format);
+
+        Self { buffer, lfb }
+    }
+
+    /// Description: The LFB drawing to the heap buffer (its `buffer()` can be passed to code expecting a framebuffer)
+    pub fn lfb(&self) -> &LFB {
+        &self.lfb
+    }
+
+    /// Description: Raw pixel data (`height` rows of `pitch` bytes)
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+}
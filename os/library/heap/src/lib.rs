@@ -0,0 +1,97 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: lib                                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Global allocator for applications. Memory is requested from the ║
+   ║         kernel with 'MapUserHeap' in chunks of 'GROW_SIZE' (or more)    ║
+   ║         and managed by a linked list allocator (free list). The kernel  ║
+   ║         maps each chunk directly behind the previous one, so the heap   ║
+   ║         simply grows upwards. If the kernel cannot map more memory,     ║
+   ║         'alloc' returns null.                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+#![no_std]
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::ptr::NonNull;
+use linked_list_allocator::{Heap, LockedHeap};
+use syscall::{syscall, SystemCall};
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// Minimum size by which the heap grows (must be a multiple of `PAGE_SIZE`)
+pub const GROW_SIZE: usize = 0x100000;
+
+#[global_allocator]
+static ALLOCATOR: UserHeap = UserHeap::empty();
+
+pub struct UserHeap {
+    heap: LockedHeap,
+}
+
+impl UserHeap {
+    pub const fn empty() -> Self {
+        Self { heap: LockedHeap::empty() }
+    }
+
+    ///
+    /// Description: Map at least `min_size` bytes of additional memory and add it to `heap`.
+    ///
+    /// Return: `false` if the kernel could not map the memory
+    ///
+    fn grow(heap: &mut Heap, min_size: usize) -> bool {
+        let size = min_size.max(GROW_SIZE).div_ceil(PAGE_SIZE) * PAGE_SIZE;
+        let start = match syscall(SystemCall::MapUserHeap, &[size]) {
+            Ok(start) => start as *mut u8,
+            Err(_) => return false,
+        };
+
+        unsafe {
+            if heap.size() == 0 {
+                heap.init(start, size);
+            } else if start == heap.top() {
+                heap.extend(size);
+            } else {
+                // Chunks are always mapped behind each other, anything else is a kernel bug
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+unsafe impl GlobalAlloc for UserHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.heap.lock();
+        if let Ok(ptr) = heap.allocate_first_fit(layout) {
+            return ptr.as_ptr();
+        }
+
+        // The new chunk must hold the allocation, including alignment and free list overhead
+        if !UserHeap::grow(&mut heap, layout.size() + layout.align() + 2 * size_of::<usize>()) {
+            return ptr::null_mut();
+        }
+
+        match heap.allocate_first_fit(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(ptr) = NonNull::new(ptr) {
+            unsafe { self.heap.lock().deallocate(ptr, layout); }
+        }
+    }
+}
+
+///
+/// Description: Return the current heap size and the number of free bytes.
+///
+pub fn stats() -> (usize, usize) {
+    let heap = ALLOCATOR.heap.lock();
+    (heap.size(), heap.free())
+}
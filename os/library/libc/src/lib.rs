@@ -8,13 +8,44 @@
 */
 #![no_std]
 
+use core::slice;
+use runtime::Args;
 use stream::strlen;
-use syscall::{syscall, SystemCall};
+use syscall::env::{ARGC_PTR, ARGV_PTR};
+use syscall::io::{self, STDOUT};
+use syscall::return_vals::Errno;
+
+unsafe extern "C" {
+    /// 'main' of the C application (renamed in runtime.h)
+    fn c_main(argc: i32, argv: *const *const u8) -> i32;
+}
+
+/// Called by the runtime, passes the raw argument vector to the C application
+#[unsafe(no_mangle)]
+pub fn main(_args: Args) -> i32 {
+    unsafe { c_main(*ARGC_PTR as i32, ARGV_PTR) }
+}
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn terminal_write(buffer: *const u8) {
-    let res = syscall(SystemCall::TerminalWrite, &[buffer as usize, strlen(buffer)]);
+    let res = io::write(STDOUT, unsafe { slice::from_raw_parts(buffer, strlen(buffer)) });
     if res.is_err() {
         panic!("Error while writing to the terminal!");
     }
-}
\ No newline at end of file
+}
+
+///
+/// Description:
+///    Message for the (positive) error number `errnum`, see `Errno::as_str()`.
+///    Unknown numbers give the message of `Errno::EUNKN`. The messages are static
+///    (unlike in C, they are not overwritten by the next call).
+///
+#[unsafe(no_mangle)]
+pub extern "C" fn strerror(errnum: i32) -> *const u8 {
+    let message = match errnum {
+        0 => c"Success",
+        _ => Errno::from(-(errnum as isize)).as_c_str(),
+    };
+
+    message.as_ptr() as *const u8
+}
@@ -1,6 +1,12 @@
 #ifndef TERMINAL_H
 #define TERMINAL_H
 
+// The runtime calls 'main(args)' of the Rust side (see lib.rs), which calls 'c_main(argc, argv)'
+#define main c_main
+
 void terminal_write(const char *str);
 
-#endif
\ No newline at end of file
+// Message for the error number 'errnum' (the buffer is overwritten by the next call)
+const char *strerror(int errnum);
+
+#endif
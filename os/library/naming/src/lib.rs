@@ -10,21 +10,238 @@
 
 use syscall::{return_vals::Errno, syscall, SystemCall};
 
-pub fn mkentry(path: &str, name: &str, data: usize) -> Result<usize, Errno> {
-    // Check if params are valid
-    if path.is_empty() || name.is_empty() {
-        Err(Errno::EINVAL) // Abort, if not
-    } else {
-        // params OK, do the syscall
-        syscall(
-            SystemCall::Mkentry,
-            &[
-                path.as_bytes().as_ptr() as usize,
-                path.len(),
-                name.as_bytes().as_ptr() as usize,
-                name.len(),
-                data, // place holder, to be replaced by pointer to container
-            ],
-        )
+// Duplicated from 'kernel/src/naming/file_lock.rs'
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LockType {
+    Shared = 0,
+    Exclusive = 1,
+    Unlock = 2,
+}
+
+// Duplicated from 'kernel/src/naming/name_service.rs'
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File = 0,
+    Directory = 1,
+}
+
+// Duplicated from 'kernel/src/naming/watch.rs'
+pub const WATCH_CREATED: u32 = 1 << 0;
+pub const WATCH_MODIFIED: u32 = 1 << 1;
+pub const WATCH_DELETED: u32 = 1 << 2;
+pub const WATCH_RENAMED: u32 = 1 << 3;
+pub const WATCH_ALL_EVENTS: u32 = WATCH_CREATED | WATCH_MODIFIED | WATCH_DELETED | WATCH_RENAMED;
+pub const WATCH_OVERFLOW: u32 = 1 << 31;
+const WATCH_EVENT_HEADER_SIZE: usize = 12;
+
+/// Event of a watch (`name` is empty for events of the watched entry itself)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WatchEvent<'a> {
+    pub kind: u32,
+    pub name: &'a str,
+    pub new_name: &'a str,
+}
+
+/// Iterator over the events, which have been copied into a buffer by `watch_read()`
+pub struct WatchEvents<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> WatchEvents<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for WatchEvents<'a> {
+    type Item = WatchEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buf = self.buf;
+        if buf.len() < WATCH_EVENT_HEADER_SIZE {
+            return None;
+        }
+
+        let field = |index: usize| u32::from_ne_bytes(buf[index * 4..index * 4 + 4].try_into().unwrap());
+        let (kind, name_len, new_name_len) = (field(0), field(1) as usize, field(2) as usize);
+        let names = &buf[WATCH_EVENT_HEADER_SIZE..];
+        if names.len() < name_len + new_name_len {
+            return None;
+        }
+
+        let event = WatchEvent {
+            kind,
+            name: core::str::from_utf8(&names[..name_len]).unwrap_or(""),
+            new_name: core::str::from_utf8(&names[name_len..name_len + new_name_len]).unwrap_or(""),
+        };
+        self.buf = &names[name_len + new_name_len..];
+        Some(event)
     }
 }
+
+///
+/// Description: Create the empty container (`EntryKind::File`) or the directory `path`.
+///              The parent directory of a container must exist, missing parents of a directory are created.
+///
+/// Return: `Errno::EINVAL`, if `path` is empty or malformed, or `Errno::EEXIST`, if the entry exists already
+///
+pub fn sys_mkentry(path: &str, kind: EntryKind) -> Result<(), Errno> {
+    if path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(SystemCall::Mkentry, &[path.as_bytes().as_ptr() as usize, path.len(), kind as usize]).map(|_| ())
+}
+
+///
+/// Description: Change the working directory of the current process.
+///
+/// Parameters: `path` absolute path or path relative to the current working directory
+///
+pub fn chdir(path: &str) -> Result<(), Errno> {
+    if path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(SystemCall::Chdir, &[path.as_bytes().as_ptr() as usize, path.len()]).map(|_| ())
+}
+
+///
+/// Description: Copy the working directory of the current process into `buf`.
+///
+/// Return: length of the path or `Errno::EINVAL`, if `buf` is too small
+///
+pub fn getcwd(buf: &mut [u8]) -> Result<usize, Errno> {
+    syscall(SystemCall::GetCwd, &[buf.as_mut_ptr() as usize, buf.len()])
+}
+
+///
+/// Description: Set the creation mask of the current process (inherited by new processes).
+///              Permission bits (0o777) set in `mask` are cleared for new containers (default 0o666)
+///              and directories (default 0o777). The initial mask is 0o022.
+///
+/// Return: previous creation mask
+///
+pub fn umask(mask: u32) -> u32 {
+    syscall(SystemCall::SetUmask, &[mask as usize]).unwrap_or(0) as u32
+}
+
+///
+/// Description: Set the permission bits (0o777) of the entry `path`. A symbolic link is followed.
+///
+pub fn chmod(path: &str, permissions: u32) -> Result<(), Errno> {
+    if path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(SystemCall::Chmod, &[path.as_bytes().as_ptr() as usize, path.len(), permissions as usize]).map(|_| ())
+}
+
+///
+/// Description: Set the owning user and group of the entry `path`. A symbolic link is followed.
+///
+pub fn chown(path: &str, uid: u32, gid: u32) -> Result<(), Errno> {
+    if path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(SystemCall::Chown, &[path.as_bytes().as_ptr() as usize, path.len(), uid as usize, gid as usize]).map(|_| ())
+}
+
+///
+/// Description: Create the symbolic link `link_path` pointing to `target` (which need not exist).
+///
+pub fn symlink(target: &str, link_path: &str) -> Result<(), Errno> {
+    if target.is_empty() || link_path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(
+        SystemCall::Symlink,
+        &[
+            target.as_bytes().as_ptr() as usize,
+            target.len(),
+            link_path.as_bytes().as_ptr() as usize,
+            link_path.len(),
+        ],
+    ).map(|_| ())
+}
+
+///
+/// Description: Create the hard link `new_path` for the container `existing` (both refer to the same content).
+///
+pub fn link(existing: &str, new_path: &str) -> Result<(), Errno> {
+    if existing.is_empty() || new_path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(
+        SystemCall::Link,
+        &[
+            existing.as_bytes().as_ptr() as usize,
+            existing.len(),
+            new_path.as_bytes().as_ptr() as usize,
+            new_path.len(),
+        ],
+    ).map(|_| ())
+}
+
+///
+/// Description: Set or release an advisory lock on the container `path`. Locks only block other lock
+///              requests (not reading or writing) and are released, when the process exits.
+///
+/// Parameters: `blocking` wait until the lock is available (otherwise fail with `Errno::EAGAIN`)
+///
+pub fn flock(path: &str, lock_type: LockType, blocking: bool) -> Result<(), Errno> {
+    if path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(
+        SystemCall::FileLock,
+        &[path.as_bytes().as_ptr() as usize, path.len(), lock_type as usize, blocking as usize],
+    ).map(|_| ())
+}
+
+///
+/// Description: Copy the target of the symbolic link `path` into `buf` (the link is not followed).
+///
+/// Return: length of the target or `Errno::EINVAL`, if `path` is not a link or `buf` is too small
+///
+pub fn readlink(path: &str, buf: &mut [u8]) -> Result<usize, Errno> {
+    syscall(
+        SystemCall::ReadLink,
+        &[path.as_bytes().as_ptr() as usize, path.len(), buf.as_mut_ptr() as usize, buf.len()],
+    )
+}
+
+///
+/// Description: Watch the entry `path` for the events in `mask` (`WATCH_*`). A directory reports
+///              events of its children, any other entry events of itself.
+///
+/// Return: id of the watch for `watch_read()`
+///
+pub fn watch_path(path: &str, mask: u32) -> Result<usize, Errno> {
+    if path.is_empty() {
+        return Err(Errno::EINVAL);
+    }
+
+    syscall(SystemCall::WatchPath, &[path.as_bytes().as_ptr() as usize, path.len(), mask as usize])
+}
+
+///
+/// Description: Copy the queued events of the watch `id` into `buf` (without blocking).
+///              Use `WatchEvents` to iterate over them.
+///
+/// Return: number of bytes written or `Errno::EAGAIN`, if there are no events
+///
+pub fn watch_read(id: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    syscall(SystemCall::WatchRead, &[id, buf.as_mut_ptr() as usize, buf.len()])
+}
+
+///
+/// Description: Remove the watch `id` (all watches are removed, when the process exits).
+///
+pub fn watch_remove(id: usize) -> Result<(), Errno> {
+    syscall(SystemCall::WatchRemove, &[id]).map(|_| ())
+}
@@ -1,7 +1,12 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: lib                                                             ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: Entry function for an application.                              ║
+   ║ Descr.: Entry function for an application. '_start' is called by the    ║
+   ║         kernel. It calls 'main(args) -> i32' of the application and     ║
+   ║         terminates the process with the returned exit code. The heap    ║
+   ║         is set up lazily by the global allocator (crate 'heap').        ║
+   ║         A panic is reported on the terminal and in the kernel log and   ║
+   ║         terminates the process with 'PANIC_EXIT_CODE'.                  ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, 31.8.2024, HHU                                  ║
    ╚═════════════════════════════════════════════════════════════════════════╝
@@ -10,44 +15,48 @@
 extern crate alloc;
 
 pub mod env;
+pub mod log;
 
 use concurrent::{process, thread};
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use log::{LogLevel, StackWriter};
 use terminal::{print, println};
-use linked_list_allocator::LockedHeap;
-use syscall::{syscall, SystemCall};
 
-unsafe extern "C" {
-    fn main(argc: isize, argv: *const *const u8) -> isize;
-}
+// Global allocator (the heap is mapped on demand)
+extern crate heap;
 
-const HEAP_SIZE: usize = 0x100000;
+pub use env::Args;
 
-#[global_allocator]
-static ALLOCATOR: LockedHeap = LockedHeap::empty();
+/// Exit code of a process, which has panicked
+pub const PANIC_EXIT_CODE: i32 = 101;
 
-#[panic_handler]
-fn panic(info: &PanicInfo) -> ! {
-    println!("Panic: {}!", info);
-    thread::exit();
+unsafe extern "Rust" {
+    /// Implemented by each application (C applications get it from 'libc')
+    fn main(args: Args) -> i32;
 }
 
-#[unsafe(no_mangle)]
-extern "C" fn entry() {
-    let heap_start: *mut u8;
-
-    let res = syscall(SystemCall::MapUserHeap, &[HEAP_SIZE]);
-    match res {
-        Ok(hs) => heap_start = hs as *mut u8,
-        Err(_) => panic!("Could not create user heap."),
+/// Report panic (without using the heap) and exit the process.
+/// A backtrace is not included, since the kernel does not offer one.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let mut message = StackWriter::<512>::new();
+    let _ = write!(message, "Panic in '{}'", env::arg(0).unwrap_or("unknown"));
+    if let Some(thread) = thread::current() {
+        let _ = write!(message, " (thread {})", thread.id());
     }
-
-    unsafe {
-        ALLOCATOR.lock().init(heap_start, HEAP_SIZE);
+    let _ = write!(message, ": {}", info.message());
+    if let Some(location) = info.location() {
+        let _ = write!(message, " at {}:{}:{}", location.file(), location.line(), location.column());
     }
 
-    unsafe {
-        main(*env::ARGC_PTR as isize, env::ARGV_PTR);
-    }
-    process::exit();
+    let _ = log::log(LogLevel::Error, message.as_str());
+    println!("{}", message.as_str());
+    process::exit(PANIC_EXIT_CODE);
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn _start() -> ! {
+    let exit_code = unsafe { main(env::args()) };
+    process::exit(exit_code);
 }
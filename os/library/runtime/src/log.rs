@@ -0,0 +1,55 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: log                                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Write messages to the kernel log (visible on the serial port).  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::fmt;
+use syscall::{syscall, SystemCall};
+use syscall::return_vals::Errno;
+
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+pub fn log(level: LogLevel, message: &str) -> Result<(), Errno> {
+    syscall(SystemCall::Log, &[level as usize, message.as_ptr() as usize, message.len()]).map(|_| ())
+}
+
+/// Formats into a fixed buffer on the stack (usable without heap, e.g. in the panic handler).
+/// Output exceeding the buffer is cut off.
+pub(crate) struct StackWriter<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> StackWriter<N> {
+    pub const fn new() -> Self {
+        Self { buffer: [0; N], len: 0 }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Cutting off may split a multibyte character -> only return the valid part
+        match core::str::from_utf8(&self.buffer[..self.len]) {
+            Ok(string) => string,
+            Err(err) => unsafe { core::str::from_utf8_unchecked(&self.buffer[..err.valid_up_to()]) },
+        }
+    }
+}
+
+impl<const N: usize> fmt::Write for StackWriter<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let count = s.len().min(N - self.len);
+        self.buffer[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
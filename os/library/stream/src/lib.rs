@@ -1,5 +1,7 @@
 #![no_std]
 
+pub mod utf8;
+
 use core::fmt;
 use core::fmt::Write;
 use core::ops::Deref;
@@ -0,0 +1,108 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: utf8                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Streaming UTF-8 decoder for byte-oriented input (terminal,      ║
+   ║         serial port). Bytes are fed one at a time, a character is only  ║
+   ║         returned, once all its bytes have been received. Invalid bytes  ║
+   ║         and interrupted sequences are replaced by U+FFFD.               ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::char::REPLACEMENT_CHARACTER;
+use core::str;
+
+/// Maximum length of a UTF-8 sequence
+const MAX_SEQUENCE_LENGTH: usize = 4;
+
+pub struct Utf8Decoder {
+    sequence: [u8; MAX_SEQUENCE_LENGTH], // bytes of the incomplete character
+    len: usize,
+}
+
+///
+/// Characters decoded from one input byte. These are at most two: if the byte interrupts an
+/// incomplete sequence, U+FFFD is returned for the sequence, followed by the character started
+/// by the byte (if it is complete, e.g. ASCII).
+///
+pub struct Decoded {
+    chars: [Option<char>; 2],
+    index: usize,
+}
+
+impl Default for Utf8Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Utf8Decoder {
+    pub const fn new() -> Self {
+        Self { sequence: [0; MAX_SEQUENCE_LENGTH], len: 0 }
+    }
+
+    /// Description: Process the next input `byte` and return the characters completed by it
+    pub fn feed(&mut self, byte: u8) -> Decoded {
+        let mut decoded = Decoded { chars: [None; 2], index: 0 };
+        self.decode(byte, &mut decoded);
+
+        decoded
+    }
+
+    /// Description: Check if the bytes of an incomplete character have been received
+    pub fn is_pending(&self) -> bool {
+        self.len > 0
+    }
+
+    /// Description: End of input: return U+FFFD, if an incomplete character is pending
+    pub fn finish(&mut self) -> Option<char> {
+        match self.is_pending() {
+            true => {
+                self.len = 0;
+                Some(REPLACEMENT_CHARACTER)
+            }
+            false => None,
+        }
+    }
+
+    fn decode(&mut self, byte: u8, decoded: &mut Decoded) {
+        self.sequence[self.len] = byte;
+        self.len += 1;
+
+        match str::from_utf8(&self.sequence[..self.len]) {
+            Ok(string) => {
+                self.len = 0;
+                decoded.push(string.chars().next().unwrap());
+            }
+            Err(err) if err.error_len().is_none() => {} // valid, but incomplete
+            Err(_) => {
+                // The pending bytes were valid, so `byte` is invalid: either it interrupts a sequence
+                // (and may start a new character itself) or it cannot start a character at all
+                let interrupted = self.len > 1;
+                self.len = 0;
+                decoded.push(REPLACEMENT_CHARACTER);
+
+                if interrupted {
+                    self.decode(byte, decoded);
+                }
+            }
+        }
+    }
+}
+
+impl Decoded {
+    fn push(&mut self, c: char) {
+        let slot = self.chars.iter_mut().find(|slot| slot.is_none()).expect("UTF-8: More than two characters decoded from one byte");
+        *slot = Some(c);
+    }
+}
+
+impl Iterator for Decoded {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = *self.chars.get(self.index)?;
+        self.index += 1;
+        c
+    }
+}
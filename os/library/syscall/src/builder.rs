@@ -0,0 +1,173 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: builder                                                         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Typed construction of system calls. Arguments are pushed one    ║
+   ║         by one and converted to registers by 'SyscallArg'. A call with  ║
+   ║         too many arguments panics, when the argument is pushed and not  ║
+   ║         when the call is fired. 'invoke' uses the 'syscallN' function   ║
+   ║         matching the number of arguments, so only the needed registers  ║
+   ║         are loaded. All knowledge about the register ABI is kept here.  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::arch::asm;
+use crate::SystemCall;
+use crate::return_vals::{SyscallResult, convert_ret_code_to_syscall_result};
+
+/// Maximum number of arguments of a system call (rdi, rsi, rdx, r10, r8, r9)
+pub const MAX_ARGS: usize = 6;
+
+/// Conversion of a typed argument to the value of a register
+pub trait SyscallArg {
+    fn into_arg(self) -> usize;
+}
+
+macro_rules! impl_syscall_arg {
+    ($($t:ty),*) => {
+        $(impl SyscallArg for $t {
+            fn into_arg(self) -> usize {
+                self as usize
+            }
+        })*
+    };
+}
+
+impl_syscall_arg!(usize, u8, u16, u32, u64, isize, i8, i16, i32, i64, bool);
+
+impl<T> SyscallArg for *const T {
+    fn into_arg(self) -> usize {
+        self as usize
+    }
+}
+
+impl<T> SyscallArg for *mut T {
+    fn into_arg(self) -> usize {
+        self as usize
+    }
+}
+
+pub struct SyscallBuilder {
+    call: SystemCall,
+    args: [usize; MAX_ARGS],
+    count: usize,
+}
+
+impl SyscallBuilder {
+    pub const fn new(call: SystemCall) -> Self {
+        Self { call, args: [0; MAX_ARGS], count: 0 }
+    }
+
+    ///
+    /// Description: Append the next argument. Panics, if the call already has `MAX_ARGS` arguments.
+    ///
+    /// Parameters: `value` argument, converted by `SyscallArg`
+    ///
+    pub fn arg<T: SyscallArg>(mut self, value: T) -> Self {
+        if self.count == MAX_ARGS {
+            panic!("System calls with more than {} params are not supported.", MAX_ARGS);
+        }
+
+        self.args[self.count] = value.into_arg();
+        self.count += 1;
+        self
+    }
+
+    /// Description: Return the arguments pushed so far, in register order
+    pub fn args(&self) -> &[usize] {
+        &self.args[..self.count]
+    }
+
+    ///
+    /// Description:
+    ///    Fire the system call. The kernel returns a single value in `rax`
+    ///    (all argument registers are preserved).
+    ///
+    /// Return: Result \
+    ///    success >= 0 \
+    ///    error, codes defined in return_vals.rs
+    ///
+    pub fn invoke(self) -> SyscallResult {
+        let call = self.call as usize;
+        let [a0, a1, a2, a3, a4, a5] = self.args;
+
+        let ret_code = unsafe {
+            match self.count {
+                0 => syscall0(call),
+                1 => syscall1(call, a0),
+                2 => syscall2(call, a0, a1),
+                3 => syscall3(call, a0, a1, a2),
+                4 => syscall4(call, a0, a1, a2, a3),
+                5 => syscall5(call, a0, a1, a2, a3, a4),
+                _ => syscall6(call, a0, a1, a2, a3, a4, a5),
+            }
+        };
+
+        convert_ret_code_to_syscall_result(ret_code)
+    }
+}
+
+// 'rcx' and 'r11' are overwritten by 'syscall' (return address and rflags)
+
+unsafe fn syscall0(call: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
+
+unsafe fn syscall1(call: usize, a0: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code, in("rdi") a0,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
+
+unsafe fn syscall2(call: usize, a0: usize, a1: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code, in("rdi") a0, in("rsi") a1,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
+
+unsafe fn syscall3(call: usize, a0: usize, a1: usize, a2: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code, in("rdi") a0, in("rsi") a1, in("rdx") a2,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
+
+unsafe fn syscall4(call: usize, a0: usize, a1: usize, a2: usize, a3: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code, in("rdi") a0, in("rsi") a1, in("rdx") a2, in("r10") a3,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
+
+unsafe fn syscall5(call: usize, a0: usize, a1: usize, a2: usize, a3: usize, a4: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code, in("rdi") a0, in("rsi") a1, in("rdx") a2, in("r10") a3, in("r8") a4,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
+
+unsafe fn syscall6(call: usize, a0: usize, a1: usize, a2: usize, a3: usize, a4: usize, a5: usize) -> isize {
+    let ret_code: isize;
+    unsafe {
+        asm!("syscall", inlateout("rax") call => ret_code, in("rdi") a0, in("rsi") a1, in("rdx") a2, in("r10") a3, in("r8") a4, in("r9") a5,
+            lateout("rcx") _, lateout("r11") _, clobber_abi("system"));
+    }
+    ret_code
+}
@@ -0,0 +1,164 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: env                                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Access to the program arguments and environment variables in    ║
+   ║         user mode.                                                      ║
+   ║                                                                         ║
+   ║         The kernel loader ('Thread::load_application') places them in   ║
+   ║         the environment area at USER_SPACE_ENV_START:                   ║
+   ║                                                                         ║
+   ║           +0                 argc (usize, always >= 1)                  ║
+   ║           +8                 argv[0] .. argv[argc - 1] (*const u8)      ║
+   ║           +8 + argc * 8      envc (usize)                               ║
+   ║           +16 + argc * 8     envp[0] .. envp[envc - 1] (*const u8)      ║
+   ║           behind envp        NUL-terminated strings                     ║
+   ║                                                                         ║
+   ║         argv[0] is the program name, each envp entry has the form       ║
+   ║         "NAME=value". No syscall is needed to read them.                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::slice;
+use core::str;
+
+// Duplicated from 'kernel/src/consts.rs'
+const USER_SPACE_START: usize = 0x10000000000;
+const USER_SPACE_CODE_START: usize = USER_SPACE_START;
+const USER_SPACE_ENV_START: usize = USER_SPACE_CODE_START + 0x40000000;
+const USER_SPACE_ENV_END: usize = USER_SPACE_ENV_START + 0x40000000;
+const USER_SPACE_ARG_START: usize = USER_SPACE_ENV_START;
+
+pub const ARGC_PTR: *const usize = USER_SPACE_ARG_START as *const usize;
+pub const ARGV_PTR: *const *const u8 = (USER_SPACE_ARG_START + size_of::<usize>()) as *const *const u8;
+
+/// Upper bound for argc + envc, ensures argc, argv, envc and envp fit into the environment area
+const MAX_ARGC: usize = (USER_SPACE_ENV_END - USER_SPACE_ARG_START) / size_of::<usize>() - 2;
+
+///
+/// Description: Return the number of arguments (including the program name).
+///
+pub fn argc() -> usize {
+    let argc = unsafe { ARGC_PTR.read() };
+    if argc > MAX_ARGC { 0 } else { argc }
+}
+
+///
+/// Description:
+///    Return argument `index` or `None` if `index` is out of range or the
+///    argument is not a valid, NUL-terminated UTF-8 string inside the
+///    environment area.
+///
+pub fn arg(index: usize) -> Option<&'static str> {
+    if index >= argc() {
+        return None;
+    }
+
+    string_at(unsafe { ARGV_PTR.add(index).read() } as usize)
+}
+
+///
+/// Description: Return the number of environment variables.
+///
+pub fn envc() -> usize {
+    let envc = unsafe { envc_ptr().read() };
+    if envc > MAX_ARGC - argc() { 0 } else { envc }
+}
+
+///
+/// Description:
+///    Return environment variable `index` in the form "NAME=value" or `None`
+///    if `index` is out of range or the entry is malformed (see `arg`).
+///
+pub fn env(index: usize) -> Option<&'static str> {
+    if index >= envc() {
+        return None;
+    }
+
+    string_at(unsafe { envp_ptr().add(index).read() } as usize)
+}
+
+///
+/// Description: Return the value of the environment variable `name` or `None` if it is not set.
+///
+pub fn var(name: &str) -> Option<&'static str> {
+    vars().find(|(var_name, _)| *var_name == name).map(|(_, value)| value)
+}
+
+///
+/// Description: Return an iterator over all environment variables as (name, value) pairs (entries without '=' are skipped).
+///
+pub fn vars() -> impl Iterator<Item = (&'static str, &'static str)> {
+    (0..envc()).filter_map(env).filter_map(|var| var.split_once('='))
+}
+
+/// Description: Address of envc (directly behind argv)
+fn envc_ptr() -> *const usize {
+    ARGV_PTR.wrapping_add(argc()) as *const usize
+}
+
+/// Description: Address of envp[0] (directly behind envc)
+fn envp_ptr() -> *const *const u8 {
+    envc_ptr().wrapping_add(1) as *const *const u8
+}
+
+///
+/// Description:
+///    Return the NUL-terminated UTF-8 string at `start` or `None`, if it does
+///    not lie between the end of envp and the end of the environment area.
+///
+fn string_at(start: usize) -> Option<&'static str> {
+    let strings_start = envp_ptr() as usize + envc() * size_of::<usize>();
+    if start < strings_start || start >= USER_SPACE_ENV_END {
+        return None;
+    }
+
+    // search the terminating NUL, but never leave the environment area
+    let mut len = 0;
+    loop {
+        if start + len >= USER_SPACE_ENV_END {
+            return None;
+        }
+        if unsafe { *((start + len) as *const u8) } == 0 {
+            break;
+        }
+        len += 1;
+    }
+
+    let bytes = unsafe { slice::from_raw_parts(start as *const u8, len) };
+    str::from_utf8(bytes).ok()
+}
+
+///
+/// Description: Return an iterator over all arguments.
+///
+pub fn args() -> ArgsIterator {
+    ArgsIterator { index: 0, argc: argc() }
+}
+
+pub struct ArgsIterator {
+    index: usize,
+    argc: usize,
+}
+
+impl Iterator for ArgsIterator {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.argc {
+            let index = self.index;
+            self.index += 1;
+
+            // skip arguments that are malformed
+            if let Some(arg) = arg(index) {
+                return Some(arg);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.argc - self.index))
+    }
+}
@@ -0,0 +1,174 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: io                                                              ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Reading and writing bytes via file descriptors: the standard    ║
+   ║         streams of the terminal and the ends of pipes. Both calls       ║
+   ║         return the number of bytes transferred, which may be less than  ║
+   ║         the buffer length (a read from the terminal ends after a        ║
+   ║         newline, a read from a pipe returns the buffered bytes).        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use crate::SystemCall;
+use crate::builder::SyscallBuilder;
+use crate::return_vals::Errno;
+
+/// Standard file descriptors
+pub const STDIN: usize = 0;
+pub const STDOUT: usize = 1;
+pub const STDERR: usize = 2;
+
+/// Flag for `pipe()`: reading from an empty or writing to a full pipe fails with `Errno::EAGAIN` instead of blocking
+// Duplicated from 'kernel/src/process/pipe.rs'
+pub const PIPE_NON_BLOCKING: usize = 1 << 0;
+
+/// Maximum number of segments for `readv()` and `writev()`
+pub const MAX_IOVECS: usize = 1024;
+
+/// Segment of a vectored read or write (layout shared with the kernel)
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct IoVec {
+    pub base: *const u8,
+    pub len: usize,
+}
+
+impl IoVec {
+    /// Description: Segment for writing `buf` (with `writev()`)
+    pub fn new(buf: &[u8]) -> Self {
+        Self { base: buf.as_ptr(), len: buf.len() }
+    }
+
+    /// Description: Segment for reading into `buf` (with `readv()`)
+    pub fn new_mut(buf: &mut [u8]) -> Self {
+        Self { base: buf.as_mut_ptr(), len: buf.len() }
+    }
+}
+
+///
+/// Description: Read up to `buf.len()` bytes from `fd`. Blocks until at least one byte is available.
+///
+/// Return: number of bytes read (0 = end of a pipe, all its write ends are closed), `Errno::EBADF` for an unknown `fd`
+///         or `Errno::EINVAL`, if `buf` is not mapped user memory
+///
+pub fn read(fd: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    SyscallBuilder::new(SystemCall::TerminalRead)
+        .arg(fd)
+        .arg(buf.as_mut_ptr())
+        .arg(buf.len())
+        .invoke()
+}
+
+///
+/// Description: Write `buf` (UTF-8) to `fd`.
+///
+/// Return: number of bytes written, `Errno::EBADF` for an unknown `fd` or `Errno::EINVAL`, if `buf` is not mapped user memory
+///
+pub fn write(fd: usize, buf: &[u8]) -> Result<usize, Errno> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    SyscallBuilder::new(SystemCall::TerminalWrite)
+        .arg(fd)
+        .arg(buf.as_ptr())
+        .arg(buf.len())
+        .invoke()
+}
+
+///
+/// Description:
+///    Read from `fd` into all segments of `iovecs` (in order) with a single system call
+///    (a single read of their total length, see `read()`). The buffers of the segments
+///    must stay valid during the call.
+///
+/// Return: number of bytes read, `Errno::EBADF` for an unknown `fd`, `Errno::EINVAL` for more than
+///         `MAX_IOVECS` segments or `Errno::EFAULT`, if the first non-empty segment is not writable user memory
+///
+pub fn readv(fd: usize, iovecs: &[IoVec]) -> Result<usize, Errno> {
+    SyscallBuilder::new(SystemCall::ReadVectored)
+        .arg(fd)
+        .arg(iovecs.as_ptr())
+        .arg(iovecs.len())
+        .invoke()
+}
+
+///
+/// Description:
+///    Write all segments of `iovecs` (in order) to `fd` with a single system call (e.g. a header
+///    and a body without copying them into one buffer). The buffers must stay valid during the call.
+///
+/// Return: number of bytes written (less than the total length, if a segment is not mapped user memory),
+///         `Errno::EBADF` for an unknown `fd` or `Errno::EINVAL` for more than `MAX_IOVECS` segments
+///
+pub fn writev(fd: usize, iovecs: &[IoVec]) -> Result<usize, Errno> {
+    SyscallBuilder::new(SystemCall::WriteVectored)
+        .arg(fd)
+        .arg(iovecs.as_ptr())
+        .arg(iovecs.len())
+        .invoke()
+}
+
+///
+/// Description:
+///    Read up to `buf.len()` bytes at `offset` from `fd` without changing its position (threads
+///    sharing `fd` do not race on a seek followed by a read). Only seekable file descriptors
+///    are supported, the terminal and pipes are not seekable.
+///
+/// Return: number of bytes read, `Errno::EBADF` for an unknown `fd` or `Errno::ESPIPE`, if `fd` is not seekable
+///
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> Result<usize, Errno> {
+    SyscallBuilder::new(SystemCall::PRead)
+        .arg(fd)
+        .arg(buf.as_mut_ptr())
+        .arg(buf.len())
+        .arg(offset)
+        .invoke()
+}
+
+///
+/// Description:
+///    Write `buf` at `offset` to `fd` without changing its position (see `pread()`).
+///
+/// Return: number of bytes written, `Errno::EBADF` for an unknown `fd` or `Errno::ESPIPE`, if `fd` is not seekable
+///
+pub fn pwrite(fd: usize, buf: &[u8], offset: usize) -> Result<usize, Errno> {
+    SyscallBuilder::new(SystemCall::PWrite)
+        .arg(fd)
+        .arg(buf.as_ptr())
+        .arg(buf.len())
+        .arg(offset)
+        .invoke()
+}
+
+///
+/// Description:
+///    Create a pipe with a buffer of 4 KiB. A write blocks until all bytes have been
+///    written, reading returns 0, after all write ends have been closed.
+///
+/// Parameters: `flags` `PIPE_NON_BLOCKING` or 0
+///
+/// Return: file descriptors of the read end and the write end or `Errno::EINVAL` for unknown flags
+///
+pub fn pipe(flags: usize) -> Result<(usize, usize), Errno> {
+    let mut fds = [0usize; 2];
+    SyscallBuilder::new(SystemCall::PipeCreate)
+        .arg(fds.as_mut_ptr())
+        .arg(flags)
+        .invoke()
+        .map(|_| (fds[0], fds[1]))
+}
+
+///
+/// Description: Close the file descriptor `fd` (a pipe end).
+///
+/// Return: `Errno::EBADF`, if `fd` is not open (the standard streams cannot be closed)
+///
+pub fn close(fd: usize) -> Result<(), Errno> {
+    SyscallBuilder::new(SystemCall::Close).arg(fd).invoke().map(|_| ())
+}
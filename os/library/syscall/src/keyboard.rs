@@ -0,0 +1,56 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: keyboard                                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Keyboard layouts for translating the scancodes of the PS/2      ║
+   ║         keyboard into characters. The layout of the terminal can be     ║
+   ║         changed at run time with `set_keyboard_layout()` (default:      ║
+   ║         German QWERTZ). The timing of repeating held keys can be set    ║
+   ║         with `set_key_repeat()`.                                        ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use crate::{syscall, SystemCall};
+use crate::return_vals::Errno;
+
+/// Keyboard layouts supported by the terminal
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+pub enum KeyboardLayout {
+    /// US QWERTY (104 keys)
+    Us = 0,
+    /// German QWERTZ (105 keys, with umlauts)
+    De = 1,
+}
+
+/// Names of all layouts (e.g. for selecting a layout in the shell)
+pub const LAYOUT_NAMES: [(&str, KeyboardLayout); 2] = [("us", KeyboardLayout::Us), ("de", KeyboardLayout::De)];
+
+impl KeyboardLayout {
+    pub fn name(&self) -> &'static str {
+        LAYOUT_NAMES.iter().find(|(_, layout)| layout == self).map(|(name, _)| *name).unwrap()
+    }
+
+    /// Description: Layout with the name `name` (see `LAYOUT_NAMES`) or `None`, if there is no such layout
+    pub fn from_name(name: &str) -> Option<Self> {
+        LAYOUT_NAMES.iter().find(|(layout_name, _)| *layout_name == name).map(|(_, layout)| *layout)
+    }
+}
+
+///
+/// Description: Translate the scancodes of the keyboard with `layout` from now on.
+///
+/// Return: the previous layout
+///
+pub fn set_keyboard_layout(layout: KeyboardLayout) -> Result<KeyboardLayout, Errno> {
+    syscall(SystemCall::SetKeyboardLayout, &[layout.into()]).map(|previous| KeyboardLayout::try_from(previous).unwrap_or(layout))
+}
+
+///
+/// Description: Repeat a held key after `delay_ms` every `interval_ms` (0 = keys are not repeated).
+///    Only the most recently pressed key repeats.
+///
+pub fn set_key_repeat(delay_ms: usize, interval_ms: usize) -> Result<(), Errno> {
+    syscall(SystemCall::SetKeyRepeat, &[delay_ms, interval_ms]).map(|_| ())
+}
@@ -0,0 +1,114 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: memory                                                          ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Memory usage of the system (kernel heap and physical frames),   ║
+   ║         shared by kernel and user mode, mapping of memory into the      ║
+   ║         address space of the calling process and memory pressure        ║
+   ║         notifications.                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use crate::{syscall, SystemCall};
+use core::ptr;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use crate::return_vals::{Errno, SyscallResult};
+
+/// Flags for `map_memory()`. Mapped memory is always readable.
+pub const MAP_READ: usize = 1 << 0;
+pub const MAP_WRITE: usize = 1 << 1;
+pub const MAP_EXEC: usize = 1 << 2;
+/// Use non-volatile memory instead of normal page frames
+pub const MAP_NVRAM: usize = 1 << 3;
+
+/// Memory pressure (shortage of free page frames), ordered by severity
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, IntoPrimitive, TryFromPrimitive)]
+pub enum PressureLevel {
+    Normal = 0,
+    /// Free caches, if possible
+    Low = 1,
+    /// Memory is about to run out
+    Critical = 2,
+}
+
+/// All values in bytes
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryStats {
+    pub heap_total: usize,
+    pub heap_used: usize,
+    pub phys_total: usize,
+    pub phys_free: usize,
+}
+
+///
+/// Description: Query memory usage of the kernel heap and physical memory.
+///
+pub fn memory_stats() -> Result<MemoryStats, Errno> {
+    let mut stats = MemoryStats::default();
+    syscall(SystemCall::MemoryStats, &[&mut stats as *mut MemoryStats as usize]).map(|_| stats)
+}
+
+///
+/// Description:
+///    Map `length` bytes (rounded up to pages) at `addr` with the access rights
+///    given by `flags` (`MAP_READ` must always be set). Normal memory is zeroed.
+///
+/// Return: the mapped base address or `Errno::EINVAL`, if `addr` is not page aligned,
+///         lies outside the mapping area or overlaps an existing mapping
+///
+pub fn map_memory(addr: usize, length: usize, flags: usize) -> Result<*mut u8, Errno> {
+    syscall(SystemCall::MapMemory, &[addr, length, flags]).map(|addr| addr as *mut u8)
+}
+
+///
+/// Description: Unmap memory mapped by `map_memory()` and free it (the whole mapping must be unmapped).
+///
+pub fn unmap_memory(addr: usize, length: usize) -> Result<(), Errno> {
+    syscall(SystemCall::UnmapMemory, &[addr, length]).map(|_| ())
+}
+
+///
+/// Description:
+///    Read the report of live kernel heap allocations (text, grouped by call site) into `buffer`
+///    (nothing is written, if it is too small).
+///
+/// Return: the length of the report, `Errno::ENOMEM` if `buffer` is too small
+///         or `Errno::ENOSYS` if the kernel does not track heap allocations
+///
+pub fn heap_allocations(buffer: &mut [u8]) -> SyscallResult {
+    syscall(SystemCall::HeapAllocations, &[buffer.as_mut_ptr() as usize, buffer.len()])
+}
+
+/// Description: Length of the heap allocation report (to size the buffer for `heap_allocations()`)
+pub fn heap_allocations_len() -> SyscallResult {
+    syscall(SystemCall::HeapAllocations, &[ptr::null_mut::<u8>() as usize, 0])
+}
+
+///
+/// Description:
+///    Subscribe the calling process to memory pressure notifications (see `memory_pressure()`).
+///    If the level is not `PressureLevel::Normal`, it can be read right away.
+///
+pub fn subscribe_memory_pressure() -> Result<(), Errno> {
+    syscall(SystemCall::MemoryPressureSubscribe, &[1]).map(|_| ())
+}
+
+/// Description: Stop memory pressure notifications of the calling process
+pub fn unsubscribe_memory_pressure() -> Result<(), Errno> {
+    syscall(SystemCall::MemoryPressureSubscribe, &[0]).map(|_| ())
+}
+
+///
+/// Description:
+///    Poll for a change of the memory pressure (without blocking). Only the latest level is kept,
+///    so a program polling rarely still sees the current state. Lower levels are reported with
+///    a delay, so a shortage near a threshold does not cause a flood of notifications.
+///
+/// Return: the new level, `Errno::EAGAIN` if it has not changed since the last call
+///         or `Errno::EINVAL` if the process is not subscribed
+///
+pub fn memory_pressure() -> Result<PressureLevel, Errno> {
+    syscall(SystemCall::MemoryPressureRead, &[]).map(|level| PressureLevel::try_from(level).unwrap_or(PressureLevel::Critical))
+}
@@ -0,0 +1,68 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: process                                                         ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Information about processes (e.g. for 'ps'), shared by kernel   ║
+   ║         and user mode.                                                  ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::ptr;
+use crate::{syscall, SystemCall};
+use crate::return_vals::SyscallResult;
+
+/// Maximum length of a process name in bytes (longer names are truncated)
+pub const PROCESS_NAME_LENGTH: usize = 32;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Active = 0,
+    /// The process has exited, but has not been cleaned up yet
+    Exited = 1,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessInfo {
+    pub pid: usize,
+    pub parent_pid: usize, // 0 for the kernel process
+    pub state: ProcessState,
+    pub name_len: u32,
+    pub name: [u8; PROCESS_NAME_LENGTH],
+    pub run_time_ms: u64, // time all threads of the process have been running
+}
+
+impl ProcessInfo {
+    /// Description: Create the info (`name` is truncated to `PROCESS_NAME_LENGTH` bytes at a character boundary)
+    pub fn new(pid: usize, parent_pid: usize, state: ProcessState, name: &str) -> Self {
+        let mut len = name.len().min(PROCESS_NAME_LENGTH);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut info = Self { pid, parent_pid, state, name_len: len as u32, name: [0; PROCESS_NAME_LENGTH], run_time_ms: 0 };
+        info.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        info
+    }
+
+    pub fn name(&self) -> &str {
+        let len = (self.name_len as usize).min(PROCESS_NAME_LENGTH);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+///
+/// Description:
+///    Read the infos of all processes into `buffer` (nothing is written, if it is too small).
+///
+/// Return: the number of processes or `Errno::ENOMEM`, if `buffer` is too small
+///
+pub fn process_list(buffer: &mut [ProcessInfo]) -> SyscallResult {
+    syscall(SystemCall::ProcessList, &[buffer.as_mut_ptr() as usize, buffer.len()])
+}
+
+/// Description: Number of processes (to size the buffer for `process_list()`)
+pub fn process_count() -> SyscallResult {
+    syscall(SystemCall::ProcessList, &[ptr::null_mut::<ProcessInfo>() as usize, 0])
+}
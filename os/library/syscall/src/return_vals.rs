@@ -7,6 +7,8 @@
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 
+use core::ffi::CStr;
+use core::fmt;
 use num_enum::{FromPrimitive, IntoPrimitive};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, IntoPrimitive, FromPrimitive)]
@@ -15,15 +17,94 @@ pub enum Errno {
     #[num_enum(default)]
     EUNKN     = -1,     // Unknown error
     ENOENT    = -2,     // No such file or directory
+    EINTR     = -4,     // Interrupted system call
+    EIO       = -5,     // I/O error
+    EBADF     = -9,     // Bad file descriptor
+    ECHILD    = -10,    // No child processes (or not a child of the calling process)
+    EAGAIN    = -11,    // Resource temporarily unavailable (would block)
+    ENOMEM    = -12,    // Out of memory
     EACCES    = -13,    // Permission denied
+    EFAULT    = -14,    // Bad address
     EEXIST    = -17,    // File/directory exists
     ENOTDIR   = -20,    // Not a directory
+    EISDIR    = -21,    // Is a directory
     EINVAL    = -22,    // Invalid argument
+    ESPIPE    = -29,    // Illegal seek (file descriptor is not seekable)
+    EPIPE     = -32,    // Broken pipe (no reader)
+    ENAMETOOLONG = -36, // File name too long
+    ENOSYS    = -38,    // Function not implemented
+    ELOOP     = -40,    // Too many levels of symbolic links
     ENOTEMPTY = -90,    // Directory not empty
 }
 
+impl Errno {
+    ///
+    /// Description:
+    ///    Short message describing the error (like 'strerror()' in C). The messages are stable,
+    ///    so programs may compare or parse them.
+    ///
+    pub fn as_str(&self) -> &'static str {
+        self.as_c_str().to_str().unwrap() // all messages are ASCII
+    }
+
+    /// Description: Message of `as_str()` as NUL-terminated string (for C applications)
+    pub fn as_c_str(&self) -> &'static CStr {
+        match self {
+            Errno::EUNKN => c"Unknown error",
+            Errno::ENOENT => c"No such file or directory",
+            Errno::EINTR => c"Interrupted system call",
+            Errno::EIO => c"I/O error",
+            Errno::EBADF => c"Bad file descriptor",
+            Errno::ECHILD => c"No child processes",
+            Errno::EAGAIN => c"Resource temporarily unavailable",
+            Errno::ENOMEM => c"Out of memory",
+            Errno::EACCES => c"Permission denied",
+            Errno::EFAULT => c"Bad address",
+            Errno::EEXIST => c"File exists",
+            Errno::ENOTDIR => c"Not a directory",
+            Errno::EISDIR => c"Is a directory",
+            Errno::EINVAL => c"Invalid argument",
+            Errno::ESPIPE => c"Illegal seek",
+            Errno::EPIPE => c"Broken pipe",
+            Errno::ENAMETOOLONG => c"File name too long",
+            Errno::ENOSYS => c"Function not implemented",
+            Errno::ELOOP => c"Too many levels of symbolic links",
+            Errno::ENOTEMPTY => c"Directory not empty",
+        }
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?})", self.as_str(), self)
+    }
+}
+
 pub type SyscallResult = Result<usize, Errno>;
 
+///
+/// Description:
+///    Call `f` again, as long as it fails with `Errno::EINTR` (the interrupted system call
+///    has not done anything and can be restarted). Other errors and results are returned.
+///
+///    Restartable are the blocking system calls: reading from the terminal or a file
+///    descriptor (`TerminalRead`, `TerminalReadLine`, `io::read()`), waiting
+///    (`ProcessWait`, `ThreadJoin`, `ThreadSleep`, `WatchRead`, `FutexWait`) and blocking file locks
+///    (`FileLock`). Currently, the kernel does not interrupt system calls (there are no
+///    signals yet), so `f` is called only once.
+///
+/// Example: `let count = retry_on_intr(|| io::read(STDIN, &mut buffer))?;`
+///
+#[inline]
+pub fn retry_on_intr<T, F: FnMut() -> Result<T, Errno>>(mut f: F) -> Result<T, Errno> {
+    loop {
+        match f() {
+            Err(Errno::EINTR) => continue,
+            result => return result,
+        }
+    }
+}
+
 pub fn convert_ret_code_to_syscall_result(ret_code: isize) -> SyscallResult {
     if ret_code < 0 {
         Err(Errno::from(ret_code))
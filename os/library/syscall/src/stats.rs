@@ -0,0 +1,49 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: stats                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Per system call latency statistics, shared by kernel and user   ║
+   ║         mode. Latencies are measured in TSC cycles. Recording must be   ║
+   ║         enabled with the kernel feature 'syscall-stats'.                ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use crate::{syscall, SystemCall, NUM_SYSCALLS};
+use crate::return_vals::SyscallResult;
+
+/// Upper bounds (exclusive, in cycles) of the latency buckets; the last bucket takes everything above
+pub const LATENCY_BUCKET_BOUNDS: [u64; 5] = [1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+pub const NUM_LATENCY_BUCKETS: usize = LATENCY_BUCKET_BOUNDS.len() + 1;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyscallStats {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub total: u64,
+    pub buckets: [u64; NUM_LATENCY_BUCKETS],
+}
+
+impl SyscallStats {
+    pub fn avg(&self) -> u64 {
+        self.total.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+/// Description: Return index of the bucket, `latency` falls into
+pub fn latency_bucket(latency: u64) -> usize {
+    LATENCY_BUCKET_BOUNDS.iter()
+        .position(|bound| latency < *bound)
+        .unwrap_or(LATENCY_BUCKET_BOUNDS.len())
+}
+
+///
+/// Description:
+///    Read latency statistics of all system calls (indexed by `SystemCall`).
+///
+/// Return: `Ok(NUM_SYSCALLS)` or `Err(Errno::ENOSYS)`, if the kernel does not record statistics
+///
+pub fn syscall_stats(stats: &mut [SyscallStats; NUM_SYSCALLS]) -> SyscallResult {
+    syscall(SystemCall::GetSyscallStats, &[stats.as_mut_ptr() as usize, stats.len()])
+}
@@ -0,0 +1,52 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: time                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: Sleep functions with explicit units over the 'ThreadSleep'      ║
+   ║         system call, which expects milliseconds, and the system time    ║
+   ║         since boot (for simple benchmarks).                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Author: simonMkraemer                                                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::time::Duration;
+use crate::{syscall, SystemCall};
+
+/// Resolution of the system time (interval of the timer interrupt, see 'kernel/src/device/pit.rs')
+pub const SYSTEM_TIME_RESOLUTION: Duration = Duration::from_millis(1);
+
+/// Longest possible sleep in milliseconds. The kernel adds the sleep time to the
+/// current system time, so only half of the value range is used to avoid an overflow.
+pub const MAX_SLEEP_MS: usize = isize::MAX as usize;
+
+///
+/// Description:
+///    Put the calling thread to sleep for at least `duration`. The kernel
+///    sleeps in whole milliseconds, so fractions are rounded up. Durations
+///    longer than `MAX_SLEEP_MS` are clamped to `MAX_SLEEP_MS`.
+///
+pub fn sleep(duration: Duration) {
+    let ms = duration.as_nanos().div_ceil(1_000_000);
+    let ms = if ms > MAX_SLEEP_MS as u128 { MAX_SLEEP_MS } else { ms as usize };
+
+    let _ = syscall(SystemCall::ThreadSleep, &[ms]);
+}
+
+/// Description: Put the calling thread to sleep for `ms` milliseconds
+pub fn sleep_ms(ms: u64) {
+    sleep(Duration::from_millis(ms));
+}
+
+/// Description: Put the calling thread to sleep for at least `us` microseconds (rounded up to milliseconds)
+pub fn sleep_us(us: u64) {
+    sleep(Duration::from_micros(us));
+}
+
+///
+/// Description:
+///    Time since boot. The kernel returns nanoseconds, so the conversion is lossless, but the
+///    time only advances in steps of `SYSTEM_TIME_RESOLUTION`. The time never decreases.
+///
+pub fn sys_system_time() -> Duration {
+    let nanos = syscall(SystemCall::GetSystemTime, &[]).expect("System call 'GetSystemTime' failed");
+    Duration::from_nanos(nanos as u64)
+}
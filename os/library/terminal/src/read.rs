@@ -1,17 +1,61 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: read                                                            ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: Read a input char from terminal.                                ║
+   ║ Descr.: Read a input char or an edited line from terminal.              ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, 31.8.2024, HHU                                  ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::string::String;
+use alloc::vec;
+use spin::Mutex;
+use stream::utf8::Utf8Decoder;
+use syscall::io::{self, STDIN};
 use syscall::{syscall, SystemCall};
 
+/// Maximum length of a line in bytes, longer lines are truncated
+pub const MAX_LINE_LENGTH: usize = 1024;
+
+/// Decoder for `read()` and a character decoded together with the previous one (not returned yet)
+static INPUT: Mutex<(Utf8Decoder, Option<char>)> = Mutex::new((Utf8Decoder::new(), None));
+
+///
+/// Description: Read a character from the terminal. Multibyte UTF-8 characters are decoded,
+///              invalid input is returned as U+FFFD.
+///
+/// Return: the character or `None` at the end of input
+///
 pub fn read() -> Option<char> {
-    let res = syscall(SystemCall::TerminalRead, &[]);
+    let mut input = INPUT.lock();
+    if let Some(c) = input.1.take() {
+        return Some(c);
+    }
+
+    let mut byte = [0u8; 1];
+    loop {
+        match io::read(STDIN, &mut byte) {
+            Ok(1) => {
+                let mut decoded = input.0.feed(byte[0]);
+                if let Some(c) = decoded.next() {
+                    input.1 = decoded.next();
+                    return Some(c);
+                }
+            }
+            _ => return input.0.finish(),
+        }
+    }
+}
+
+/// Read a line, supporting cursor keys, backspace and history (up/down keys).
+/// The returned line does not contain the terminating newline.
+pub fn read_line() -> Option<String> {
+    let mut buffer = vec![0u8; MAX_LINE_LENGTH];
+    let res = syscall(SystemCall::TerminalReadLine, &[buffer.as_mut_ptr() as usize, buffer.len()]);
     match res {
-        Ok(ch) => Some(char::from_u32(ch as u32).unwrap()),
+        Ok(count) => {
+            buffer.truncate(count);
+            String::from_utf8(buffer).ok()
+        }
         Err(_) => None,
-    }    
-}
\ No newline at end of file
+    }
+}
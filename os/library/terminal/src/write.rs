@@ -9,7 +9,7 @@
 use core::fmt;
 use core::fmt::Write;
 use spin::Mutex;
-use syscall::{syscall, SystemCall};
+use syscall::io::{self, STDOUT};
 
 #[macro_export]
 macro_rules! print {
@@ -40,11 +40,7 @@ impl Writer {
 
 impl Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let res = syscall(
-            SystemCall::TerminalWrite,
-            &[s.as_bytes().as_ptr() as usize, s.len()],
-        );
-        match res {
+        match io::write(STDOUT, s.as_bytes()) {
             Ok(_) => Ok(()),
             Err(_) => Err(fmt::Error),
         }
@@ -8,32 +8,39 @@
 */
 #![no_std]
 
-use chrono::{DateTime, TimeDelta, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
 use syscall::{syscall, SystemCall};
+use syscall::return_vals::Errno;
+use syscall::time::sys_system_time;
 
+/// Description: Time since boot (see `syscall::time::sys_system_time()` for the resolution)
 pub fn systime() -> TimeDelta {
-    let res = syscall(SystemCall::GetSystemTime, &[]);
-    match res {
-        Ok(systime) => TimeDelta::try_milliseconds(systime as i64).expect("Failed to create TimeDelta struct from systime"),
-        Err(_) => panic!("Syscall: GetSystemTime failed."),
-    }    
+    TimeDelta::from_std(sys_system_time()).expect("Failed to create TimeDelta struct from systime")
 }
 
-pub fn date() -> DateTime<Utc> {
-    let res = syscall(SystemCall::GetDate, &[]);
-    match res {
-        Ok(date_ms) => DateTime::from_timestamp_millis(date_ms as i64).expect("Failed to parse date from milliseconds returned by system call"),
-        Err(_) => panic!("Syscall: GetDate failed."),
-    }    
+pub fn date() -> Result<DateTime<Utc>, Errno> {
+    let date_ms = syscall(SystemCall::GetDate, &[])?;
+    DateTime::from_timestamp_millis(date_ms as i64).ok_or(Errno::EINVAL)
 }
 
-pub fn set_date(date: DateTime<Utc>) -> bool {
+///
+/// Description: Set the real time clock. Dates before the Unix epoch are rejected with `EINVAL`.
+///
+pub fn set_date(date: DateTime<Utc>) -> Result<(), Errno> {
     let date_ms = date.timestamp_millis();
+    if date_ms < 0 {
+        return Err(Errno::EINVAL);
+    }
 
-    let res = syscall(SystemCall::SetDate, &[date_ms as usize, ]);
-    match res {
-        Ok(_) => true,
-        Err(_) => false,
-    }    
+    syscall(SystemCall::SetDate, &[date_ms as usize, ]).map(|_| ())
+}
+
+/// Description: Return the current date (UTC) without time zone
+pub fn naive_date() -> Result<NaiveDateTime, Errno> {
+    date().map(|date| date.naive_utc())
+}
 
+/// Description: Set the real time clock to `date`, which is interpreted as UTC
+pub fn set_naive_date(date: NaiveDateTime) -> Result<(), Errno> {
+    set_date(date.and_utc())
 }
\ No newline at end of file